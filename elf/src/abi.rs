@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+use std::fmt;
+use crate::consts::*;
+use crate::dwarf::*;
+use crate::elf::Elf;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbiSymbol {
+  pub name: String,
+  pub version: Option<String>,
+  //name@@VERSION or unversioned, the definition new links bind to
+  pub default: bool,
+  pub symbol_type: u8,
+  pub binding: u8,
+  pub size: u64,
+  //from DWARF, "int (const char *, size_t)" for functions, the type for variables
+  pub signature: Option<String>,
+  //the signature with typedefs looked through and aggregates carrying their size, what
+  //compare() looks at: renaming a typedef is not a change, growing a struct is
+  pub signature_key: Option<String>,
+}
+
+impl AbiSymbol {
+  fn versioned_name(&self) -> String {
+    match &self.version {
+      Some(version) => format!("{}{}{}", self.name, if self.default { "@@" } else { "@" }, version),
+      None => self.name.clone(),
+    }
+  }
+}
+
+//The interface a shared object offers: its soname and exported dynamic symbols, sorted by
+//name and version.
+#[derive(Clone, Debug, Default)]
+pub struct AbiSnapshot {
+  pub soname: Option<String>,
+  pub symbols: Vec<AbiSymbol>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compatibility {
+  //binaries built against the old file keep working with the new one
+  Compatible,
+  Breaking,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiChangeKind {
+  SonameChanged { old: Option<String>, new: Option<String> },
+  Added,
+  Removed,
+  //an unversioned symbol got a default version, old references still bind to it
+  VersionAdded,
+  //name@@VERSION became name@VERSION or the reverse
+  DefaultChanged,
+  TypeChanged { old: u8, new: u8 },
+  BindingChanged { old: u8, new: u8 },
+  //only reported for data, copy relocations and TLS blocks are sized at link time
+  SizeChanged { old: u64, new: u64 },
+  SignatureChanged { old: String, new: String },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AbiChange {
+  //name@VERSION, None for file-wide changes
+  pub symbol: Option<String>,
+  pub kind: AbiChangeKind,
+  pub compatibility: Compatibility,
+}
+
+impl fmt::Display for AbiChange {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let compatibility = match self.compatibility {
+      Compatibility::Compatible => "compatible",
+      Compatibility::Breaking => "breaking",
+    };
+    let symbol = self.symbol.as_deref().unwrap_or("");
+    match &self.kind {
+      AbiChangeKind::SonameChanged { old, new } => write!(f, "{}: soname {} -> {}", compatibility, old.as_deref().unwrap_or("(none)"), new.as_deref().unwrap_or("(none)")),
+      AbiChangeKind::Added => write!(f, "{}: {} added", compatibility, symbol),
+      AbiChangeKind::Removed => write!(f, "{}: {} removed", compatibility, symbol),
+      AbiChangeKind::VersionAdded => write!(f, "{}: {} got a version", compatibility, symbol),
+      AbiChangeKind::DefaultChanged => write!(f, "{}: {} default version changed", compatibility, symbol),
+      AbiChangeKind::TypeChanged { old, new } => write!(f, "{}: {} type {} -> {}", compatibility, symbol, old, new),
+      AbiChangeKind::BindingChanged { old, new } => write!(f, "{}: {} binding {} -> {}", compatibility, symbol, old, new),
+      AbiChangeKind::SizeChanged { old, new } => write!(f, "{}: {} size {} -> {}", compatibility, symbol, old, new),
+      AbiChangeKind::SignatureChanged { old, new } => write!(f, "{}: {} signature {} -> {}", compatibility, symbol, old, new),
+    }
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AbiDiff {
+  pub changes: Vec<AbiChange>,
+}
+
+impl AbiDiff {
+  pub fn is_empty(&self) -> bool {
+    self.changes.is_empty()
+  }
+
+  pub fn is_compatible(&self) -> bool {
+    self.changes.iter().all(|change| change.compatibility == Compatibility::Compatible)
+  }
+
+  pub fn breaking(&self) -> impl Iterator<Item = &AbiChange> {
+    self.changes.iter().filter(|change| change.compatibility == Compatibility::Breaking)
+  }
+}
+
+fn is_data(symbol_type: u8) -> bool {
+  symbol_type == STT_OBJECT || symbol_type == STT_TLS || symbol_type == STT_COMMON
+}
+
+fn is_code(symbol_type: u8) -> bool {
+  symbol_type == STT_FUNC || symbol_type == STT_GNU_IFUNC
+}
+
+impl AbiSnapshot {
+  //Changes from `self` (the old file) to `newer`.
+  pub fn compare(&self, newer: &AbiSnapshot) -> AbiDiff {
+    let mut changes = Vec::new();
+    if self.soname != newer.soname {
+      changes.push(AbiChange {
+        symbol: None,
+        kind: AbiChangeKind::SonameChanged { old: self.soname.clone(), new: newer.soname.clone() },
+        compatibility: Compatibility::Breaking,
+      });
+    }
+    let key = |symbol: &AbiSymbol| (symbol.name.clone(), symbol.version.clone());
+    let old: HashMap<_, _> = self.symbols.iter().map(|symbol| (key(symbol), symbol)).collect();
+    let new: HashMap<_, _> = newer.symbols.iter().map(|symbol| (key(symbol), symbol)).collect();
+    for symbol in &self.symbols {
+      let change = |kind, compatibility| AbiChange { symbol: Some(symbol.versioned_name()), kind, compatibility };
+      let current = match new.get(&key(symbol)) {
+        Some(current) => current,
+        None => {
+          //an unversioned reference binds to the default version of the same name
+          let versioned = symbol.version.is_none() && newer.symbols.iter().any(|other| other.name == symbol.name && other.default);
+          changes.push(match versioned {
+            true => change(AbiChangeKind::VersionAdded, Compatibility::Compatible),
+            false => change(AbiChangeKind::Removed, Compatibility::Breaking),
+          });
+          continue;
+        },
+      };
+      if symbol.default != current.default {
+        changes.push(change(AbiChangeKind::DefaultChanged, Compatibility::Compatible));
+      }
+      if symbol.symbol_type != current.symbol_type {
+        let compatibility = match is_code(symbol.symbol_type) && is_code(current.symbol_type) {
+          true => Compatibility::Compatible,
+          false => Compatibility::Breaking,
+        };
+        changes.push(change(AbiChangeKind::TypeChanged { old: symbol.symbol_type, new: current.symbol_type }, compatibility));
+      }
+      if symbol.binding != current.binding {
+        changes.push(change(AbiChangeKind::BindingChanged { old: symbol.binding, new: current.binding }, Compatibility::Compatible));
+      }
+      if symbol.size != current.size && is_data(symbol.symbol_type) && is_data(current.symbol_type) {
+        changes.push(change(AbiChangeKind::SizeChanged { old: symbol.size, new: current.size }, Compatibility::Breaking));
+      }
+      if let (Some(old_key), Some(new_key)) = (&symbol.signature_key, &current.signature_key) {
+        if old_key != new_key {
+          //a struct that only grew reads the same, its key tells the two apart
+          let (old, new) = match (&symbol.signature, &current.signature) {
+            (Some(old), Some(new)) if old != new => (old.clone(), new.clone()),
+            _ => (old_key.clone(), new_key.clone()),
+          };
+          changes.push(change(AbiChangeKind::SignatureChanged { old, new }, Compatibility::Breaking));
+        }
+      }
+    }
+    for symbol in &newer.symbols {
+      if !old.contains_key(&key(symbol)) {
+        let versioned = symbol.version.is_some() && old.contains_key(&(symbol.name.clone(), None));
+        if !versioned {
+          changes.push(AbiChange { symbol: Some(symbol.versioned_name()), kind: AbiChangeKind::Added, compatibility: Compatibility::Compatible });
+        }
+      }
+    }
+    AbiDiff { changes }
+  }
+}
+
+//qualifiers go after a pointer, "char *const", and before anything else, "const char"
+fn qualified(inner: String, qualifier: &str) -> String {
+  if inner.ends_with('*') {
+    format!("{} {}", inner, qualifier)
+  } else {
+    format!("{} {}", qualifier, inner)
+  }
+}
+
+//C-like spelling of the type DIE at `offset`, void when there is none. References into
+//other units are not followed and read "?".
+pub(crate) fn type_name(unit: &Unit<'_>, offset: Option<u64>, canonical: bool, depth: usize) -> String {
+  let offset = match offset {
+    Some(offset) => offset,
+    None => return String::from("void"),
+  };
+  let index = match unit.index_of(offset) {
+    Some(index) if depth < 16 => index,
+    Some(_) => return String::from("..."),
+    None => return String::from("?"),
+  };
+  let die = &unit.dies[index];
+  let inner = || type_name(unit, die.reference(DW_AT_TYPE), canonical, depth + 1);
+  match die.tag {
+    DW_TAG_BASE_TYPE => die.string(DW_AT_NAME).map_or_else(|| String::from("?"), |name| name.into_owned()),
+    DW_TAG_STRUCTURE_TYPE | DW_TAG_CLASS_TYPE | DW_TAG_UNION_TYPE | DW_TAG_ENUMERATION_TYPE => {
+      let keyword = match die.tag {
+        DW_TAG_STRUCTURE_TYPE => "struct ",
+        DW_TAG_UNION_TYPE => "union ",
+        DW_TAG_ENUMERATION_TYPE => "enum ",
+        _ => "",
+      };
+      let name = unit.qualified_name(index).unwrap_or_else(|| String::from("<anonymous>"));
+      match die.unsigned(DW_AT_BYTE_SIZE) {
+        Some(size) if canonical => format!("{}{}/{}", keyword, name, size),
+        _ => format!("{}{}", keyword, name),
+      }
+    },
+    DW_TAG_TYPEDEF if canonical => inner(),
+    DW_TAG_TYPEDEF => unit.qualified_name(index).unwrap_or_else(|| String::from("?")),
+    DW_TAG_POINTER_TYPE => {
+      let target = die.reference(DW_AT_TYPE).and_then(|target| unit.index_of(target));
+      match target {
+        Some(target) if unit.dies[target].tag == DW_TAG_SUBROUTINE_TYPE => function_type(unit, target, "(*)", canonical, depth + 1),
+        _ => format!("{} *", inner()),
+      }
+    },
+    DW_TAG_REFERENCE_TYPE => format!("{} &", inner()),
+    DW_TAG_RVALUE_REFERENCE_TYPE => format!("{} &&", inner()),
+    DW_TAG_CONST_TYPE => qualified(inner(), "const"),
+    DW_TAG_VOLATILE_TYPE => qualified(inner(), "volatile"),
+    DW_TAG_RESTRICT_TYPE => qualified(inner(), "restrict"),
+    DW_TAG_ATOMIC_TYPE => format!("_Atomic {}", inner()),
+    DW_TAG_ARRAY_TYPE => {
+      let mut name = inner();
+      for child in unit.children(index).filter(|&child| unit.dies[child].tag == DW_TAG_SUBRANGE_TYPE) {
+        let subrange = &unit.dies[child];
+        match subrange.unsigned(DW_AT_COUNT).or_else(|| subrange.unsigned(DW_AT_UPPER_BOUND).map(|bound| bound + 1)) {
+          Some(count) => name.push_str(&format!("[{}]", count)),
+          None => name.push_str("[]"),
+        };
+      }
+      name
+    },
+    DW_TAG_SUBROUTINE_TYPE => function_type(unit, index, "", canonical, depth + 1),
+    _ => die.string(DW_AT_NAME).map_or_else(|| String::from("?"), |name| name.into_owned()),
+  }
+}
+
+//"ret (params)" for a subprogram or subroutine type, `declarator` goes between the two
+fn function_type(unit: &Unit<'_>, index: usize, declarator: &str, canonical: bool, depth: usize) -> String {
+  let reference = |index: usize, attribute: u64| match unit.inherited_attribute(index, attribute) {
+    Some(AttributeValue::Reference(offset)) => Some(offset),
+    _ => None,
+  };
+  //an out of line instance may drop parameters, the abstract instance lists them all
+  let source = unit.dies[index].reference(DW_AT_ABSTRACT_ORIGIN).and_then(|origin| unit.index_of(origin)).unwrap_or(index);
+  let mut parameters = Vec::new();
+  for child in unit.children(source) {
+    match unit.dies[child].tag {
+      DW_TAG_FORMAL_PARAMETER => parameters.push(type_name(unit, reference(child, DW_AT_TYPE), canonical, depth)),
+      DW_TAG_UNSPECIFIED_PARAMETERS => parameters.push(String::from("...")),
+      _ => {},
+    };
+  }
+  if parameters.is_empty() && matches!(unit.inherited_attribute(index, DW_AT_PROTOTYPED), Some(AttributeValue::Flag(true))) {
+    parameters.push(String::from("void"));
+  }
+  let result = type_name(unit, reference(index, DW_AT_TYPE), canonical, depth);
+  if declarator.is_empty() {
+    format!("{} ({})", result, parameters.join(", "))
+  } else {
+    format!("{} {}({})", result, declarator, parameters.join(", "))
+  }
+}
+
+impl Elf {
+  //Signatures of the external functions and variables DWARF describes, by linkage name
+  //(the plain name for C), as (signature, signature_key).
+  fn dwarf_signatures(&self) -> HashMap<String, (String, String)> {
+    let mut signatures = HashMap::new();
+    let dwarf = self.dwarf();
+    if dwarf.is_empty() {
+      return signatures;
+    }
+    for unit in dwarf.units() {
+      for (index, die) in unit.dies.iter().enumerate() {
+        let defined = match die.tag {
+          DW_TAG_SUBPROGRAM => die.attribute(DW_AT_LOW_PC).is_some() || die.attribute(DW_AT_RANGES).is_some(),
+          DW_TAG_VARIABLE => die.attribute(DW_AT_LOCATION).is_some(),
+          _ => false,
+        };
+        if !defined || die.flag(DW_AT_DECLARATION) || !matches!(unit.inherited_attribute(index, DW_AT_EXTERNAL), Some(AttributeValue::Flag(true))) {
+          continue;
+        }
+        let name = match unit.inherited_string(index, DW_AT_LINKAGE_NAME)
+          .or_else(|| unit.inherited_string(index, DW_AT_MIPS_LINKAGE_NAME))
+          .or_else(|| unit.inherited_string(index, DW_AT_NAME)) {
+          Some(name) => name.into_owned(),
+          None => continue,
+        };
+        let signature = |canonical| match die.tag {
+          DW_TAG_SUBPROGRAM => function_type(&unit, index, "", canonical, 0),
+          _ => match unit.inherited_attribute(index, DW_AT_TYPE) {
+            Some(AttributeValue::Reference(offset)) => type_name(&unit, Some(offset), canonical, 0),
+            _ => String::from("?"),
+          },
+        };
+        signatures.entry(name).or_insert_with(|| (signature(false), signature(true)));
+      }
+    }
+    signatures
+  }
+
+  //Exported dynamic symbols with their versions, and signatures when the file carries DWARF.
+  pub fn abi_snapshot(&self) -> AbiSnapshot {
+    let signatures = self.dwarf_signatures();
+    let mut symbols: Vec<AbiSymbol> = self.dynamic_symbols().into_iter()
+      .zip(self.symbol_versions())
+      .filter(|(symbol, _)| symbol.is_exported() && !symbol.name.is_empty())
+      //the absolute symbol a version script defines for every version
+      .filter(|(symbol, version)| !(symbol.section_index == SHN_ABS && version.as_ref().is_some_and(|version| *symbol.name == *version.name)))
+      .map(|(symbol, version)| {
+        let signature = signatures.get(&*symbol.name);
+        AbiSymbol {
+          name: symbol.name.to_string(),
+          default: version.as_ref().is_none_or(|version| !version.hidden),
+          version: version.map(|version| version.name),
+          symbol_type: symbol.symbol_type,
+          binding: symbol.binding,
+          size: symbol.size,
+          signature: signature.map(|(signature, _)| signature.clone()),
+          signature_key: signature.map(|(_, key)| key.clone()),
+        }
+      })
+      .collect();
+    symbols.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    AbiSnapshot { soname: self.soname(), symbols }
+  }
+}
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::io::ErrorKind;
+use std::path::Path;
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::parse_options::ParseOptions;
+
+pub const ARCHIVE_MAGIC: &[u8; 8] = b"!<arch>\n";
+const MEMBER_HEADER_SIZE: usize = 60;
+
+#[derive(Clone, Debug)]
+pub struct ArchiveMember {
+  pub name: String,
+  pub data: Vec<u8>,
+}
+
+//A static library in the GNU ar format. It is written deterministically, like `ar D`:
+//timestamps, owners and modes are zero and 644.
+#[derive(Clone, Debug, Default)]
+pub struct Archive {
+  pub members: Vec<ArchiveMember>,
+  //always write the 64-bit symbol index ("/SYM64/"), otherwise it is only used when a
+  //member starts past 4 GiB
+  pub wide_index: bool,
+}
+
+//An entry of the archive symbol index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArchiveSymbol {
+  pub name: String,
+  //index into Archive::members
+  pub member_index: usize,
+}
+
+//A member as it is laid out in the file, the symbol index and long name table included.
+struct RawMember<'a> {
+  //the header's name field without padding, "name/", "/123" or a special member's name
+  name: &'a str,
+  header_offset: usize,
+  data: &'a [u8],
+}
+
+fn header_field(header: &[u8], start: usize, end: usize) -> &str {
+  std::str::from_utf8(&header[start..end]).unwrap_or("").trim_end()
+}
+
+fn raw_members(data: &[u8]) -> io::Result<Vec<RawMember<'_>>> {
+  if data.starts_with(b"!<thin>\n") {
+    return Err(io::Error::new(ErrorKind::Unsupported, "thin archives are not supported"));
+  }
+  if !data.starts_with(ARCHIVE_MAGIC) {
+    return Err(io::Error::new(ErrorKind::InvalidData, "not an ar archive"));
+  }
+  let mut members = Vec::new();
+  let mut position = ARCHIVE_MAGIC.len();
+  while position < data.len() {
+    //members are aligned to 2, some writers leave a final pad byte
+    if data.len() - position == 1 && data[position] == b'\n' {
+      break;
+    }
+    let header = data.get(position..position + MEMBER_HEADER_SIZE)
+      .ok_or_else(|| io::Error::new(ErrorKind::UnexpectedEof, "truncated archive member header"))?;
+    if &header[58..60] != b"`\n" {
+      return Err(io::Error::new(ErrorKind::InvalidData, format!("bad archive member header at {}", position)));
+    }
+    let size: usize = header_field(header, 48, 58).parse()
+      .map_err(|_| io::Error::new(ErrorKind::InvalidData, format!("bad archive member size at {}", position)))?;
+    let start = position + MEMBER_HEADER_SIZE;
+    let body = data.get(start..start.saturating_add(size))
+      .ok_or_else(|| io::Error::new(ErrorKind::UnexpectedEof, "truncated archive member"))?;
+    members.push(RawMember { name: header_field(header, 0, 16), header_offset: position, data: body });
+    position = start + size + size % 2;
+  }
+  Ok(members)
+}
+
+fn member_header(out: &mut Vec<u8>, name: &str, mode: &str, size: usize) {
+  let header = format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", name, 0, 0, 0, mode, size);
+  out.extend_from_slice(header.as_bytes());
+}
+
+fn is_special_member(name: &str) -> bool {
+  name == "/" || name == "//" || name == "/SYM64/"
+}
+
+fn pad(out: &mut Vec<u8>) {
+  if out.len() % 2 == 1 {
+    out.push(b'\n');
+  }
+}
+
+//Symbols the index lists for a member: the defined global symbols of an ELF object,
+//commons included, which is what makes ld pull the member in.
+fn indexed_symbols(data: &[u8]) -> Vec<String> {
+  let elf = match Elf::parse(data.to_vec().into_boxed_slice(), &ParseOptions::default()) {
+    Ok((elf, _)) => elf,
+    Err(_) => return Vec::new(),
+  };
+  elf.symbol_table().iter()
+    .filter(|symbol| symbol.is_global() && !symbol.is_undefined() && !symbol.name.is_empty())
+    .filter(|symbol| symbol.symbol_type != STT_SECTION && symbol.symbol_type != STT_FILE)
+    .map(|symbol| symbol.name.to_string())
+    .collect()
+}
+
+impl Archive {
+  pub fn new() -> Archive {
+    Default::default()
+  }
+
+  pub fn add_member(&mut self, name: &str, data: Vec<u8>) {
+    self.members.push(ArchiveMember { name: name.to_string(), data });
+  }
+
+  //Adds a file under its file name.
+  pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let name = path.file_name().and_then(|name| name.to_str())
+      .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, format!("{} has no file name", path.display())))?;
+    let data = fs::read(path)?;
+    self.add_member(name, data);
+    Ok(())
+  }
+
+  //The archive with a symbol index ("/") first, as ranlib writes it, so that ld can use it
+  //without running ranlib, then the long name table ("//") when a name does not fit the
+  //16 byte header field. The index is left out when no member defines a symbol.
+  pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+    let mut long_names = Vec::new();
+    let mut header_names = Vec::new();
+    for member in &self.members {
+      if member.name.is_empty() || member.name.contains('/') {
+        return Err(io::Error::new(ErrorKind::InvalidInput, format!("invalid member name {:?}", member.name)));
+      }
+      if member.name.len() < 16 {
+        header_names.push(format!("{}/", member.name));
+      } else {
+        header_names.push(format!("/{}", long_names.len()));
+        long_names.extend_from_slice(member.name.as_bytes());
+        long_names.extend_from_slice(b"/\n");
+      }
+    }
+    //GNU ar counts the padding as part of the table
+    if long_names.len() % 2 == 1 {
+      long_names.push(b'\n');
+    }
+    let symbols: Vec<Vec<String>> = self.members.iter().map(|member| indexed_symbols(&member.data)).collect();
+    let symbol_count: usize = symbols.iter().map(Vec::len).sum();
+    let string_size: usize = symbols.iter().flatten().map(|name| name.len() + 1).sum();
+
+    //where the members start: after the index and the long name table, each padded to even.
+    //The index entries grow to 8 bytes when a member starts past what 4 can hold.
+    let layout = |word: usize| {
+      //like the long name table, the padding is counted in the index
+      let index_size = if symbol_count == 0 { 0 } else { (word + word * symbol_count + string_size).next_multiple_of(2) };
+      let mut position = ARCHIVE_MAGIC.len();
+      if index_size > 0 {
+        position += MEMBER_HEADER_SIZE + index_size;
+      }
+      if !long_names.is_empty() {
+        position += MEMBER_HEADER_SIZE + long_names.len();
+      }
+      let mut offsets = Vec::new();
+      for member in &self.members {
+        offsets.push(position as u64);
+        position += MEMBER_HEADER_SIZE + member.data.len() + member.data.len() % 2;
+      }
+      (index_size, offsets, position)
+    };
+    let mut word = if self.wide_index { 8 } else { 4 };
+    let (mut index_size, mut offsets, mut end) = layout(word);
+    if word == 4 && symbol_count > 0 && offsets.last().is_some_and(|&offset| offset > u32::MAX as u64) {
+      word = 8;
+      (index_size, offsets, end) = layout(word);
+    }
+
+    let mut out = Vec::with_capacity(end);
+    out.extend_from_slice(ARCHIVE_MAGIC);
+    if index_size > 0 {
+      let write_word = |out: &mut Vec<u8>, value: u64| match word {
+        8 => out.extend_from_slice(&value.to_be_bytes()),
+        _ => out.extend_from_slice(&(value as u32).to_be_bytes()),
+      };
+      member_header(&mut out, if word == 8 { "/SYM64/" } else { "/" }, "0", index_size);
+      write_word(&mut out, symbol_count as u64);
+      for (member, names) in symbols.iter().enumerate() {
+        for _ in names {
+          write_word(&mut out, offsets[member]);
+        }
+      }
+      for name in symbols.iter().flatten() {
+        out.extend_from_slice(name.as_bytes());
+        out.push(0);
+      }
+      if out.len() % 2 == 1 {
+        out.push(0);
+      }
+    }
+    if !long_names.is_empty() {
+      out.extend_from_slice(format!("{:<48}{:<10}`\n", "//", long_names.len()).as_bytes());
+      out.extend_from_slice(&long_names);
+    }
+    for (member, name) in self.members.iter().zip(&header_names) {
+      member_header(&mut out, name, "644", member.data.len());
+      out.extend_from_slice(&member.data);
+      pad(&mut out);
+    }
+    Ok(out)
+  }
+
+  //Reads a GNU (or SysV) archive. The symbol index is not kept, to_bytes() generates it
+  //again; read_symbol_index() reads it.
+  pub fn from_bytes(data: &[u8]) -> io::Result<Archive> {
+    let raw = raw_members(data)?;
+    let long_names = raw.iter().find(|member| member.name == "//").map_or(&[][..], |member| member.data);
+    let mut archive = Archive::new();
+    for member in raw.iter().filter(|member| !is_special_member(member.name)) {
+      let name = match member.name.strip_prefix('/') {
+        Some(offset) => {
+          let offset: usize = offset.parse().map_err(|_| io::Error::new(ErrorKind::InvalidData, format!("bad member name {:?}", member.name)))?;
+          let rest = long_names.get(offset..).ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "long member name past the name table"))?;
+          let end = rest.windows(2).position(|pair| pair == b"/\n").unwrap_or(rest.len());
+          String::from_utf8_lossy(&rest[..end]).into_owned()
+        },
+        None => member.name.strip_suffix('/').unwrap_or(member.name).to_string(),
+      };
+      archive.members.push(ArchiveMember { name, data: member.data.to_vec() });
+    }
+    Ok(archive)
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Archive> {
+    Archive::from_bytes(&fs::read(path)?)
+  }
+
+  //The symbol index of an archive, 32-bit ("/") or 64-bit ("/SYM64/"), in file order.
+  //Empty when there is none, ranlib has not been run.
+  pub fn read_symbol_index(data: &[u8]) -> io::Result<Vec<ArchiveSymbol>> {
+    let raw = raw_members(data)?;
+    let (index, word) = match raw.iter().find(|member| member.name == "/" || member.name == "/SYM64/") {
+      Some(member) if member.name == "/" => (member.data, 4),
+      Some(member) => (member.data, 8),
+      None => return Ok(Vec::new()),
+    };
+    let truncated = || io::Error::new(ErrorKind::UnexpectedEof, "truncated archive symbol index");
+    let read_word = |at: usize| -> io::Result<u64> {
+      let bytes = index.get(at..at + word).ok_or_else(truncated)?;
+      Ok(bytes.iter().fold(0, |value, &byte| value << 8 | byte as u64))
+    };
+    let count = read_word(0)? as usize;
+    let names_start = count.checked_add(1).and_then(|words| words.checked_mul(word)).filter(|&start| start <= index.len()).ok_or_else(truncated)?;
+    let mut names = index[names_start..].split(|&byte| byte == 0);
+    let members: HashMap<u64, usize> = raw.iter().filter(|member| !is_special_member(member.name)).enumerate()
+      .map(|(member_index, member)| (member.header_offset as u64, member_index))
+      .collect();
+    let mut symbols = Vec::with_capacity(count);
+    for entry in 0..count {
+      let offset = read_word(word + entry * word)?;
+      let member_index = *members.get(&offset)
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, format!("archive symbol index points at {}, not a member", offset)))?;
+      let name = names.next().ok_or_else(truncated)?;
+      symbols.push(ArchiveSymbol { name: String::from_utf8_lossy(name).into_owned(), member_index });
+    }
+    Ok(symbols)
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    fs::write(path, self.to_bytes()?)
+  }
+}
@@ -0,0 +1,157 @@
+use std::io;
+use std::io::ErrorKind;
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::loader::PAGE_SIZE;
+
+//the smallest gap Linux leaves between the stack and the mmap base
+const MMAP_GAP: u64 = 0x800_0000;
+//draws of a layout that lands below mmap_min_addr or past the address limit before giving up
+const ATTEMPTS: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct AslrOptions {
+  //where the randomized range starts: ELF_ET_DYN_BASE for executables with PT_INTERP, which
+  //move up from it, the mmap base for other ET_DYN objects (libraries and the dynamic
+  //loader), which move down from it. The machine's when None.
+  pub base: Option<u64>,
+  //place the object the way the dynamic loader maps a library, below the mmap base, even
+  //when it has PT_INTERP (libc.so.6 does)
+  pub library: bool,
+  //bits of page granular randomness, vm.mmap_rnd_bits (vm.mmap_rnd_compat_bits for 32-bit
+  //processes). The machine's default when None.
+  pub random_bits: Option<u32>,
+  //vm.mmap_min_addr
+  pub mmap_min_addr: u64,
+  //end of the user address space, TASK_SIZE. The machine's when None.
+  pub address_limit: Option<u64>,
+  pub page_size: u64,
+}
+
+impl Default for AslrOptions {
+  fn default() -> AslrOptions {
+    AslrOptions { base: None, library: false, random_bits: None, mmap_min_addr: 0x10000, address_limit: None, page_size: PAGE_SIZE }
+  }
+}
+
+//Where one simulated execution put the object.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RandomizedLayout {
+  //added to every link time address
+  pub bias: u64,
+  //lowest and end address of the mapped pages
+  pub start: u64,
+  pub end: u64,
+  pub entry: u64,
+  //page aligned PT_LOAD mappings: address, size and PF_ flags
+  pub segments: Vec<(u64, u64, u32)>,
+}
+
+impl RandomizedLayout {
+  pub fn address(&self, link_address: u64) -> u64 {
+    link_address.wrapping_add(self.bias)
+  }
+
+  //the link time address of a runtime one inside the object
+  pub fn link_address(&self, address: u64) -> Option<u64> {
+    if address >= self.start && address < self.end { Some(address.wrapping_sub(self.bias)) } else { None }
+  }
+}
+
+//SplitMix64, so that a seed gives the same layouts everywhere
+struct Random(u64);
+
+impl Random {
+  fn next(&mut self) -> u64 {
+    self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut value = self.0;
+    value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    value ^ (value >> 31)
+  }
+}
+
+//(ELF_ET_DYN_BASE, default mmap_rnd_bits, TASK_SIZE) as Linux has them for 4 KiB pages
+fn machine_defaults(machine: u16, class: u8) -> (u64, u32, u64) {
+  match (machine, class) {
+    (EM_X86_64, ELFCLASS64) => (0x5555_5555_4000, 28, 0x7fff_ffff_f000),
+    (EM_AARCH64, ELFCLASS64) => (0xaaaa_aaaa_a000, 18, 0x1_0000_0000_0000),
+    //39-bit Sv39, the smallest RISC-V user address space
+    (EM_RISCV, ELFCLASS64) => (0x2a_aaaa_a000, 18, 0x40_0000_0000),
+    (_, ELFCLASS64) => (0x5555_5555_4000, 18, 0x7fff_ffff_f000),
+    //32-bit processes on a 64-bit kernel
+    (EM_386, _) | (EM_X86_64, _) => (0x5655_5000, 8, 0xffff_e000),
+    _ => (0x5655_5000, 8, 0xffff_0000),
+  }
+}
+
+impl Elf {
+  //Simulates `count` executions under ASLR the way Linux places an ET_DYN object: the load
+  //bias is a random number of pages (random_bits worth) above ELF_ET_DYN_BASE for PIE
+  //executables, or below the mmap base for libraries and the dynamic loader, rounded down
+  //to the largest PT_LOAD alignment, as kernels since 5.10 honour it. Layouts that would map
+  //below mmap_min_addr or past the address limit are drawn again. The same seed and options
+  //give the same layouts.
+  pub fn randomized_layouts(&self, count: usize, seed: u64, options: &AslrOptions) -> io::Result<Vec<RandomizedLayout>> {
+    if self.header.description.obj_type != ET_DYN {
+      return Err(io::Error::new(ErrorKind::InvalidInput, "only ET_DYN objects are randomized"));
+    }
+    let page_size = options.page_size.max(1);
+    if !page_size.is_power_of_two() {
+      return Err(io::Error::new(ErrorKind::InvalidInput, format!("page size {:#x} is not a power of two", page_size)));
+    }
+    let loads: Vec<_> = self.program_headers.iter().filter(|ph| ph.entry_type == PT_LOAD).collect();
+    let start = loads.iter().map(|ph| ph.virtual_address & !(page_size - 1)).min()
+      .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "no PT_LOAD segments"))?;
+    //page aligned end of each segment
+    let ends = loads.iter().map(|ph| ph.virtual_address.checked_add(ph.memory_size)?.div_ceil(page_size).checked_mul(page_size))
+      .collect::<Option<Vec<u64>>>()
+      .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "a PT_LOAD segment ends past the address space"))?;
+    let end = ends.iter().copied().max().unwrap_or(start);
+    let size = end - start;
+    let alignment = loads.iter().map(|ph| ph.align).filter(|align| align.is_power_of_two()).fold(page_size, u64::max);
+
+    let (dyn_base, default_bits, default_limit) = machine_defaults(self.header.description.machine, self.header.identification.class);
+    let limit = options.address_limit.unwrap_or(default_limit);
+    let bits = options.random_bits.unwrap_or(default_bits).min(63);
+    let executable = !options.library && self.program_headers.iter().any(|ph| ph.entry_type == PT_INTERP);
+    let base = options.base.unwrap_or(if executable { dyn_base } else { limit.saturating_sub(MMAP_GAP) });
+
+    let mut random = Random(seed);
+    let mut layouts = Vec::with_capacity(count);
+    while layouts.len() < count {
+      let mut placed = None;
+      for _ in 0..ATTEMPTS {
+        let offset = (random.next() & ((1u64 << bits) - 1)).saturating_mul(page_size);
+        let candidate = if executable {
+          base.checked_add(offset)
+        } else {
+          base.checked_sub(offset).and_then(|top| top.checked_sub(size))
+        };
+        let candidate = match candidate {
+          Some(candidate) => candidate & !(alignment - 1),
+          None => continue,
+        };
+        if candidate >= options.mmap_min_addr && candidate.checked_add(size).is_some_and(|end| end <= limit) {
+          placed = Some(candidate);
+          break;
+        }
+      }
+      let placed = placed.ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, format!(
+        "{:#x} bytes do not fit between mmap_min_addr {:#x} and {:#x} from base {:#x}", size, options.mmap_min_addr, limit, base)))?;
+      let bias = placed.wrapping_sub(start);
+      let segments = loads.iter().zip(&ends).map(|(ph, &segment_end)| {
+        let segment_start = ph.virtual_address & !(page_size - 1);
+        (segment_start.wrapping_add(bias), segment_end - segment_start, ph.flags)
+      }).collect();
+      layouts.push(RandomizedLayout {
+        bias,
+        start: placed,
+        end: placed + size,
+        entry: self.header.description.entry.wrapping_add(bias),
+        segments,
+      });
+    }
+    Ok(layouts)
+  }
+}
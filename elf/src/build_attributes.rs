@@ -0,0 +1,187 @@
+use std::io;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::Elf;
+
+pub const STACK_PROTECTOR_NONE: u64 = 0;
+pub const STACK_PROTECTOR_BASIC: u64 = 1;
+pub const STACK_PROTECTOR_ALL: u64 = 2;
+pub const STACK_PROTECTOR_STRONG: u64 = 3;
+pub const STACK_PROTECTOR_EXPLICIT: u64 = 4;
+//recorded by annobin when the level could not be determined, e.g. under LTO
+pub const FORTIFY_UNKNOWN: u64 = 0xff;
+
+#[derive(Clone, PartialEq)]
+pub enum BuildAttributeValue {
+  Bool(bool),
+  Numeric(u64),
+  String(String),
+}
+
+pub struct BuildAttribute {
+  pub name: String,
+  pub value: BuildAttributeValue,
+  //address range the attribute applies to
+  pub start: u64,
+  pub end: u64,
+  //NT_GNU_BUILD_ATTRIBUTE_FUNC rather than _OPEN
+  pub function: bool,
+}
+
+pub struct GnuWarning {
+  pub symbol: String,
+  pub message: String,
+}
+
+//Weakest value found over every covered region, None when no note records the attribute.
+#[derive(Default)]
+pub struct HardeningFlags {
+  pub fortify_source: Option<u64>,
+  pub stack_protector: Option<u64>,
+  pub stack_clash: Option<bool>,
+  pub cf_protection: Option<u64>,
+  pub pic: Option<u64>,
+  pub glibcxx_assertions: Option<bool>,
+}
+
+//stack_prot values are not ordered by strength: none < explicit < basic < strong < all, and
+//values annobin does not define count as none
+fn stack_protector_rank(value: u64) -> u8 {
+  match value {
+    STACK_PROTECTOR_EXPLICIT => 1,
+    STACK_PROTECTOR_BASIC => 2,
+    STACK_PROTECTOR_STRONG => 3,
+    STACK_PROTECTOR_ALL => 4,
+    _ => 0,
+  }
+}
+
+fn attribute_id_name(id: u8) -> Option<&'static str> {
+  match id {
+    1 => Some("version"),
+    2 => Some("stack_prot"),
+    3 => Some("relro"),
+    4 => Some("stack_size"),
+    5 => Some("tool"),
+    6 => Some("ABI"),
+    7 => Some("PIC"),
+    8 => Some("short_enum"),
+    _ => None,
+  }
+}
+
+fn parse_attribute_name(name: &[u8]) -> Option<(String, BuildAttributeValue)> {
+  let name = name.strip_suffix(&[0]).unwrap_or(name);
+  if name.len() < 4 || &name[..2] != b"GA" {
+    return None;
+  }
+  let value_type = name[2];
+  let rest = &name[3..];
+  let (attribute, payload) = match attribute_id_name(rest[0]) {
+    Some(attribute) => (attribute.to_string(), &rest[1..]),
+    None => {
+      let end = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+      let payload = rest.get(end + 1..).unwrap_or(&[]);
+      (String::from_utf8_lossy(&rest[..end]).into_owned(), payload)
+    },
+  };
+  let value = match value_type {
+    b'+' => BuildAttributeValue::Bool(true),
+    b'!' => BuildAttributeValue::Bool(false),
+    b'*' => {
+      let mut value = 0u64;
+      for (position, &byte) in payload.iter().take(8).enumerate() {
+        value |= (byte as u64) << (position * 8);
+      }
+      BuildAttributeValue::Numeric(value)
+    },
+    b'$' => BuildAttributeValue::String(String::from_utf8_lossy(payload).into_owned()),
+    _ => return None,
+  };
+  Some((attribute, value))
+}
+
+impl Elf {
+  pub fn gnu_warnings(&self) -> Vec<GnuWarning> {
+    self.section_headers.iter()
+      .filter_map(|section| {
+        let symbol = self.section_name(section)?.strip_prefix(".gnu.warning.")?;
+        let data = self.data.get(section.offset as usize..section.offset.saturating_add(section.size) as usize)?;
+        let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+        Some(GnuWarning {
+          symbol: symbol.to_string(),
+          message: String::from_utf8_lossy(&data[..end]).into_owned(),
+        })
+      })
+      .collect()
+  }
+
+  //An error for an unknown byte order, the addresses in the descriptors cannot be read.
+  pub fn build_attributes(&self) -> io::Result<Vec<BuildAttribute>> {
+    let big_endian = match self.header.identification.endianness {
+      1 => false,
+      2 => true,
+      endianness => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown endianness {}", endianness))),
+    };
+    let mut attributes = Vec::new();
+    let (mut start, mut end) = (0, 0);
+    for note in self.notes() {
+      if note.note_type != NT_GNU_BUILD_ATTRIBUTE_OPEN && note.note_type != NT_GNU_BUILD_ATTRIBUTE_FUNC {
+        continue;
+      }
+      let (name, value) = match parse_attribute_name(&note.name) {
+        Some(attribute) => attribute,
+        None => continue,
+      };
+      let word = match self.header.identification.class {
+        1 => 4,
+        _ => 8,
+      };
+      //an empty descriptor applies to the same region as the previous note
+      if note.desc.len() >= word * 2 {
+        start = Elf::read_note_word(big_endian, &note.desc[..word]);
+        end = Elf::read_note_word(big_endian, &note.desc[word..word * 2]);
+      } else if note.desc.len() == word {
+        start = Elf::read_note_word(big_endian, &note.desc[..word]);
+        end = start;
+      }
+      attributes.push(BuildAttribute {
+        name,
+        value,
+        start,
+        end,
+        function: note.note_type == NT_GNU_BUILD_ATTRIBUTE_FUNC,
+      });
+    }
+    Ok(attributes)
+  }
+
+  pub fn hardening_flags(&self) -> io::Result<HardeningFlags> {
+    let mut flags: HardeningFlags = Default::default();
+    let weakest = |current: Option<u64>, value: u64| Some(current.map_or(value, |current| current.min(value)));
+    let all = |current: Option<bool>, value: bool| Some(current.unwrap_or(true) && value);
+    for attribute in self.build_attributes()? {
+      match (attribute.name.as_str(), &attribute.value) {
+        ("FORTIFY", &BuildAttributeValue::Numeric(value)) if value != FORTIFY_UNKNOWN => flags.fortify_source = weakest(flags.fortify_source, value),
+        ("stack_prot", &BuildAttributeValue::Numeric(value)) => {
+          flags.stack_protector = Some(flags.stack_protector.map_or(value, |current| if stack_protector_rank(value) < stack_protector_rank(current) { value } else { current }));
+        },
+        ("stack_clash", &BuildAttributeValue::Bool(value)) => flags.stack_clash = all(flags.stack_clash, value),
+        ("cf_protection", &BuildAttributeValue::Numeric(value)) => flags.cf_protection = weakest(flags.cf_protection, value),
+        ("PIC", &BuildAttributeValue::Numeric(value)) => flags.pic = weakest(flags.pic, value),
+        ("GLIBCXX_ASSERTIONS", &BuildAttributeValue::Bool(value)) => flags.glibcxx_assertions = all(flags.glibcxx_assertions, value),
+        _ => {},
+      };
+    }
+    Ok(flags)
+  }
+
+  fn read_note_word(big_endian: bool, bytes: &[u8]) -> u64 {
+    match (big_endian, bytes.len()) {
+      (false, 4) => LittleEndian::read_u32(bytes) as u64,
+      (false, _) => LittleEndian::read_u64(bytes),
+      (true, 4) => BigEndian::read_u32(bytes) as u64,
+      (true, _) => BigEndian::read_u64(bytes),
+    }
+  }
+}
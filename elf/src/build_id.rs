@@ -0,0 +1,82 @@
+use std::io;
+use std::ops::Range;
+use crate::consts::*;
+use crate::digest::{sha1, xxhash64};
+use crate::elf::Elf;
+use crate::note::Note;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BuildIdAlgorithm {
+  //20 bytes, ld's --build-id=sha1 and its default
+  Sha1,
+  //8 bytes, the size of lld's default --build-id=fast
+  Xxhash64,
+}
+
+impl Elf {
+  fn build_id_note(&self) -> Option<Note> {
+    self.notes().into_iter().find(|note| note.note_type == NT_GNU_BUILD_ID && note.name_str() == "GNU")
+  }
+
+  //Every PT_LOAD with its type, flags, address and sizes and its file bytes, or the SHF_ALLOC
+  //sections of files without segments, with the `masked` file ranges zeroed. Only what is
+  //loaded counts, so stripping or adding debug information leaves it unchanged.
+  pub(crate) fn loaded_content(&self, masked: &[Range<u64>]) -> Vec<u8> {
+    let mut input = Vec::new();
+    let mut push = |offset: u64, size: u64, fields: &[u64]| {
+      for field in fields {
+        input.extend_from_slice(&field.to_le_bytes());
+      }
+      let start = input.len();
+      let bytes = self.data.get(offset as usize..offset.saturating_add(size) as usize).unwrap_or(&[]);
+      input.extend_from_slice(bytes);
+      for range in masked {
+        for position in range.start.max(offset)..range.end.min(offset + bytes.len() as u64) {
+          input[start + (position - offset) as usize] = 0;
+        }
+      }
+    };
+    let loads: Vec<_> = self.program_headers.iter().filter(|ph| ph.entry_type == PT_LOAD).collect();
+    if loads.is_empty() {
+      for section in self.section_headers.iter().filter(|section| section.flags & SHF_ALLOC != 0) {
+        let size = if section.section_type == SHT_NOBITS { 0 } else { section.size };
+        push(section.offset, size, &[section.section_type as u64, section.flags, section.address, section.size]);
+      }
+    } else {
+      for ph in loads {
+        push(ph.offset, ph.file_size, &[ph.entry_type as u64, ph.flags as u64, ph.virtual_address, ph.file_size, ph.memory_size]);
+      }
+    }
+    input
+  }
+
+  //What the id is computed over: the loaded content with the build-id descriptor zeroed. Not
+  //what the linker computed over the whole file.
+  fn build_id_input(&self) -> Vec<u8> {
+    let desc: Vec<_> = self.build_id_note().map(|note| note.desc_offset..note.desc_offset + note.desc.len() as u64).into_iter().collect();
+    self.loaded_content(&desc)
+  }
+
+  //A fresh id for the current contents, at the algorithm's full length.
+  pub fn compute_build_id(&self, algorithm: BuildIdAlgorithm) -> Vec<u8> {
+    let input = self.build_id_input();
+    match algorithm {
+      BuildIdAlgorithm::Sha1 => sha1(&input).to_vec(),
+      BuildIdAlgorithm::Xxhash64 => xxhash64(&input, 0).to_be_bytes().to_vec(),
+    }
+  }
+
+  //Rewrites the NT_GNU_BUILD_ID descriptor in place after edits and returns the new id. The
+  //note keeps its size: a longer digest is cut to it, a shorter one is an error.
+  pub fn restamp_build_id(&mut self, algorithm: BuildIdAlgorithm) -> io::Result<Vec<u8>> {
+    let note = self.build_id_note().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no NT_GNU_BUILD_ID note"))?;
+    let mut id = self.compute_build_id(algorithm);
+    if id.len() < note.desc.len() {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("the build-id note holds {} bytes, {:?} gives {}", note.desc.len(), algorithm, id.len())));
+    }
+    id.truncate(note.desc.len());
+    let start = note.desc_offset as usize;
+    self.data[start..start + id.len()].copy_from_slice(&id);
+    Ok(id)
+  }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::parse_options::ParseOptions;
+
+const INDEX_MAGIC: &[u8; 8] = b"WBIDIDX\0";
+const INDEX_VERSION: u32 = 1;
+
+//What a scanner records about one file with a build-id.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuildIdEntry {
+  pub build_id: Vec<u8>,
+  pub path: PathBuf,
+  pub size: u64,
+  pub modified: Option<SystemTime>,
+  pub machine: u16,
+  pub obj_type: u16,
+  pub soname: Option<String>,
+  pub has_symbol_table: bool,
+  pub has_debug_info: bool,
+  //a separate debug file, objcopy --only-keep-debug: the code sections are SHT_NOBITS
+  pub debug_only: bool,
+}
+
+impl BuildIdEntry {
+  pub fn build_id_hex(&self) -> String {
+    self.build_id.iter().map(|byte| format!("{:02x}", byte)).collect()
+  }
+
+  fn from_elf(path: PathBuf, elf: &Elf, build_id: Vec<u8>, metadata: &fs::Metadata) -> BuildIdEntry {
+    let has_debug_info = elf.section_headers.iter().any(|section| elf.section_name(section) == Some(".debug_info"));
+    let code = elf.section_headers.iter().filter(|section| section.flags & SHF_EXECINSTR != 0).collect::<Vec<_>>();
+    BuildIdEntry {
+      build_id,
+      path,
+      size: metadata.len(),
+      modified: metadata.modified().ok(),
+      machine: elf.header.description.machine,
+      obj_type: elf.header.description.obj_type,
+      soname: elf.soname(),
+      has_symbol_table: elf.section_headers.iter().any(|section| section.section_type == SHT_SYMTAB),
+      has_debug_info,
+      debug_only: !code.is_empty() && code.iter().all(|section| section.section_type == SHT_NOBITS),
+    }
+  }
+}
+
+fn parse_hex(text: &str) -> Option<Vec<u8>> {
+  if !text.len().is_multiple_of(2) {
+    return None;
+  }
+  (0..text.len()).step_by(2).map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok()).collect()
+}
+
+//Build-ids of the files of scanned trees, e.g. to find the binary or the debug file a crash
+//report names. A build-id can map to several files: copies, the stripped binary and its debug
+//file, or one binary under several names.
+#[derive(Default)]
+pub struct BuildIdIndex {
+  entries: Vec<BuildIdEntry>,
+  by_id: HashMap<Vec<u8>, Vec<usize>>,
+  by_path: HashMap<PathBuf, usize>,
+}
+
+impl BuildIdIndex {
+  pub fn new() -> BuildIdIndex {
+    Default::default()
+  }
+
+  pub fn len(&self) -> usize {
+    self.entries.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+
+  pub fn entries(&self) -> &[BuildIdEntry] {
+    &self.entries
+  }
+
+  //Replaces the entry of the same path.
+  pub fn insert(&mut self, entry: BuildIdEntry) {
+    match self.by_path.get(&entry.path) {
+      Some(&index) => {
+        if self.entries[index].build_id != entry.build_id {
+          self.unlink_id(index);
+          self.by_id.entry(entry.build_id.clone()).or_default().push(index);
+        }
+        self.entries[index] = entry;
+      },
+      None => {
+        let index = self.entries.len();
+        self.by_id.entry(entry.build_id.clone()).or_default().push(index);
+        self.by_path.insert(entry.path.clone(), index);
+        self.entries.push(entry);
+      },
+    };
+  }
+
+  //The last entry takes the place of the removed one, entries() changes order.
+  pub fn remove_path<P: AsRef<Path>>(&mut self, path: P) -> Option<BuildIdEntry> {
+    let index = self.by_path.remove(path.as_ref())?;
+    self.unlink_id(index);
+    let entry = self.entries.swap_remove(index);
+    if let Some(moved) = self.entries.get(index) {
+      let last = self.entries.len();
+      self.by_path.insert(moved.path.clone(), index);
+      for slot in self.by_id.get_mut(&moved.build_id).into_iter().flatten().filter(|slot| **slot == last) {
+        *slot = index;
+      }
+    }
+    Some(entry)
+  }
+
+  //takes entry `index` out of the list of its build-id
+  fn unlink_id(&mut self, index: usize) {
+    let build_id = &self.entries[index].build_id;
+    if let Some(indices) = self.by_id.get_mut(build_id) {
+      indices.retain(|&other| other != index);
+      if indices.is_empty() {
+        self.by_id.remove(build_id);
+      }
+    }
+  }
+
+  fn rebuild(&mut self) {
+    self.by_id.clear();
+    self.by_path.clear();
+    for (index, entry) in self.entries.iter().enumerate() {
+      self.by_id.entry(entry.build_id.clone()).or_default().push(index);
+      self.by_path.insert(entry.path.clone(), index);
+    }
+  }
+
+  pub fn lookup(&self, build_id: &[u8]) -> Vec<&BuildIdEntry> {
+    self.by_id.get(build_id).map_or_else(Vec::new, |indices| indices.iter().map(|&index| &self.entries[index]).collect())
+  }
+
+  //the id as hex, upper or lower case, as in .build-id paths and debuginfod URLs
+  pub fn lookup_hex(&self, build_id: &str) -> Vec<&BuildIdEntry> {
+    parse_hex(build_id).map_or_else(Vec::new, |id| self.lookup(&id))
+  }
+
+  //the file to run or load, what debuginfod serves as /executable
+  pub fn executable(&self, build_id: &[u8]) -> Option<&BuildIdEntry> {
+    self.lookup(build_id).into_iter().find(|entry| !entry.debug_only)
+  }
+
+  //the file with the DWARF, a separate debug file or an unstripped binary, what debuginfod
+  //serves as /debuginfo
+  pub fn debug_info(&self, build_id: &[u8]) -> Option<&BuildIdEntry> {
+    let entries = self.lookup(build_id);
+    entries.iter().find(|entry| entry.debug_only && entry.has_debug_info)
+      .or_else(|| entries.iter().find(|entry| entry.has_debug_info))
+      .or_else(|| entries.iter().find(|entry| entry.debug_only))
+      .copied()
+  }
+
+  //Indexes one file, files that are not ELF or have no build-id give None.
+  pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<Option<&BuildIdEntry>> {
+    let path = path.as_ref();
+    let metadata = fs::metadata(path)?;
+    let mut magic = [0u8; 4];
+    if !metadata.is_file() || File::open(path)?.read_exact(&mut magic).is_err() || magic != [0x7f, b'E', b'L', b'F'] {
+      return Ok(None);
+    }
+    let elf = match Elf::parse(fs::read(path)?.into_boxed_slice(), &ParseOptions::default()) {
+      Ok((elf, _)) => elf,
+      Err(_) => return Ok(None),
+    };
+    let build_id = match elf.build_id() {
+      Some(build_id) if !build_id.is_empty() => build_id,
+      _ => return Ok(None),
+    };
+    self.insert(BuildIdEntry::from_elf(path.to_path_buf(), &elf, build_id, &metadata));
+    let index = self.by_path[path];
+    Ok(Some(&self.entries[index]))
+  }
+
+  //Indexes every ELF file with a build-id under `root` and returns how many were added or
+  //updated. Symbolic links are not followed, a .build-id tree would give every file twice.
+  //Files indexed before with the same size and modification time are not read again, and
+  //unreadable files and directories are skipped.
+  pub fn scan<P: AsRef<Path>>(&mut self, root: P) -> io::Result<usize> {
+    let mut pending = vec![root.as_ref().to_path_buf()];
+    let mut indexed = 0;
+    let mut first = true;
+    while let Some(directory) = pending.pop() {
+      let entries = match fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(err) if first => return Err(err),
+        Err(_) => continue,
+      };
+      first = false;
+      for entry in entries.flatten() {
+        let path = entry.path();
+        let metadata = match fs::symlink_metadata(&path) {
+          Ok(metadata) => metadata,
+          Err(_) => continue,
+        };
+        if metadata.is_dir() {
+          pending.push(path);
+          continue;
+        }
+        if !metadata.is_file() || metadata.len() < 52 {
+          continue;
+        }
+        let unchanged = self.by_path.get(&path).map(|&index| &self.entries[index])
+          .is_some_and(|known| known.size == metadata.len() && known.modified.is_some() && known.modified == metadata.modified().ok());
+        if !unchanged && self.add_file(&path).ok().flatten().is_some() {
+          indexed += 1;
+        }
+      }
+    }
+    Ok(indexed)
+  }
+
+  //Drops the entries of files that no longer exist or no longer carry the recorded id, and
+  //returns how many.
+  pub fn prune(&mut self) -> usize {
+    let before = self.entries.len();
+    self.entries.retain(|entry| {
+      match fs::metadata(&entry.path) {
+        Ok(metadata) if metadata.len() == entry.size && entry.modified.is_some() && metadata.modified().ok() == entry.modified => true,
+        Ok(_) => Elf::open_with_options(&entry.path, &ParseOptions::default()).ok().and_then(|(elf, _)| elf.build_id()).as_ref() == Some(&entry.build_id),
+        Err(_) => false,
+      }
+    });
+    self.rebuild();
+    before - self.entries.len()
+  }
+
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut out = INDEX_MAGIC.to_vec();
+    out.extend_from_slice(&INDEX_VERSION.to_le_bytes());
+    out.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+    let bytes = |out: &mut Vec<u8>, bytes: &[u8]| {
+      out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+      out.extend_from_slice(bytes);
+    };
+    for entry in &self.entries {
+      bytes(&mut out, &entry.build_id);
+      bytes(&mut out, &path_bytes(&entry.path));
+      out.extend_from_slice(&entry.size.to_le_bytes());
+      let modified = entry.modified.and_then(|time| time.duration_since(UNIX_EPOCH).ok());
+      out.push(modified.is_some() as u8);
+      out.extend_from_slice(&modified.map_or(0, |time| time.as_secs()).to_le_bytes());
+      out.extend_from_slice(&modified.map_or(0, |time| time.subsec_nanos()).to_le_bytes());
+      out.extend_from_slice(&entry.machine.to_le_bytes());
+      out.extend_from_slice(&entry.obj_type.to_le_bytes());
+      out.push(entry.soname.is_some() as u8);
+      bytes(&mut out, entry.soname.as_deref().unwrap_or("").as_bytes());
+      out.push(entry.has_symbol_table as u8 | (entry.has_debug_info as u8) << 1 | (entry.debug_only as u8) << 2);
+    }
+    out
+  }
+
+  pub fn from_bytes(data: &[u8]) -> io::Result<BuildIdIndex> {
+    if !data.starts_with(INDEX_MAGIC) {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "not a build-id index"));
+    }
+    let mut reader = IndexReader { data, position: INDEX_MAGIC.len() };
+    let version = reader.u32()?;
+    if version != INDEX_VERSION {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("build-id index version {} is not supported", version)));
+    }
+    let count = reader.u64()?;
+    let mut index = BuildIdIndex::new();
+    for _ in 0..count {
+      let build_id = reader.bytes()?.to_vec();
+      let path = path_from_bytes(reader.bytes()?);
+      let size = reader.u64()?;
+      let has_modified = reader.take(1)?[0] != 0;
+      let (seconds, nanos) = (reader.u64()?, reader.u32()?);
+      if nanos >= 1_000_000_000 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("build-id index time with {} nanoseconds", nanos)));
+      }
+      let modified = if has_modified { UNIX_EPOCH.checked_add(Duration::new(seconds, nanos)) } else { None };
+      let machine = reader.u16()?;
+      let obj_type = reader.u16()?;
+      let has_soname = reader.take(1)?[0] != 0;
+      let soname = String::from_utf8_lossy(reader.bytes()?).into_owned();
+      let flags = reader.take(1)?[0];
+      index.insert(BuildIdEntry {
+        build_id,
+        path,
+        size,
+        modified,
+        machine,
+        obj_type,
+        soname: if has_soname { Some(soname) } else { None },
+        has_symbol_table: flags & 1 != 0,
+        has_debug_info: flags & 2 != 0,
+        debug_only: flags & 4 != 0,
+      });
+    }
+    Ok(index)
+  }
+
+  //Writes to a temporary file next to `path` and renames it over, a reader never sees half
+  //an index.
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    fs::write(&temporary, self.to_bytes())?;
+    fs::rename(&temporary, path)
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<BuildIdIndex> {
+    BuildIdIndex::from_bytes(&fs::read(path)?)
+  }
+}
+
+struct IndexReader<'a> {
+  data: &'a [u8],
+  position: usize,
+}
+
+impl<'a> IndexReader<'a> {
+  fn take(&mut self, size: usize) -> io::Result<&'a [u8]> {
+    let bytes = self.position.checked_add(size).and_then(|end| self.data.get(self.position..end))
+      .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "truncated build-id index"))?;
+    self.position += size;
+    Ok(bytes)
+  }
+
+  fn u16(&mut self) -> io::Result<u16> {
+    let bytes = self.take(2)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+  }
+
+  fn u32(&mut self) -> io::Result<u32> {
+    let bytes = self.take(4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+  }
+
+  fn u64(&mut self) -> io::Result<u64> {
+    let bytes = self.take(8)?;
+    Ok(u64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]]))
+  }
+
+  fn bytes(&mut self) -> io::Result<&'a [u8]> {
+    let size = self.u32()? as usize;
+    self.take(size)
+  }
+}
+
+//paths are stored as the bytes the system uses, not every Unix path is UTF-8
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+  use std::os::unix::ffi::OsStrExt;
+  path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+  use std::os::unix::ffi::OsStrExt;
+  PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Vec<u8> {
+  path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: &[u8]) -> PathBuf {
+  PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+}
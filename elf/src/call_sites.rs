@@ -0,0 +1,238 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::symbol::Symbol;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CallKind {
+  //call rel32, bl
+  Call,
+  //jmp rel32, b: tail calls
+  Jump,
+  //call *slot(%rip), -fno-plt code calls through the GOT
+  IndirectCall,
+  IndirectJump,
+  //a relocation against the function there that is not a call, its address is taken
+  Reference,
+}
+
+#[derive(Clone, Debug)]
+pub struct CallSite {
+  //the instruction, section relative in relocatable objects
+  pub address: u64,
+  //the function, PLT stub or GOT slot reached
+  pub target: u64,
+  pub kind: CallKind,
+  pub section_index: usize,
+}
+
+//One stub of .plt, .plt.sec or .plt.got.
+#[derive(Clone, Debug)]
+pub struct PltEntry {
+  pub address: u64,
+  pub got_slot: u64,
+  pub name: Arc<str>,
+}
+
+fn is_plt_section(name: Option<&str>) -> bool {
+  name.is_some_and(|name| name == ".plt" || name.starts_with(".plt.") || name == ".iplt")
+}
+
+//A64 instructions are little-endian in big-endian images too
+fn read_a64(bytes: &[u8]) -> u32 {
+  u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+impl Elf {
+  //the symbol a relocation of the relocation section `section_index` refers to
+  pub(crate) fn relocation_symbol(&self, section_index: usize, symbol_index: u32) -> Option<&Symbol> {
+    let linked = self.section_headers.get(self.section_headers.get(section_index)?.link as usize)?;
+    let table = if linked.section_type == SHT_DYNSYM { self.dynamic_symbol_table() } else { self.symbol_table() };
+    table.get(symbol_index as usize)
+  }
+
+  fn relocation_symbol_name(&self, section_index: usize, symbol_index: u32) -> Option<Arc<str>> {
+    self.relocation_symbol(section_index, symbol_index).map(|symbol| symbol.name.clone()).filter(|name| !name.is_empty())
+  }
+
+  //GOT slots filled with a symbol's address: JUMP_SLOT and GLOB_DAT relocations, and in
+  //static executables IRELATIVE ones, named after the IFUNC symbol at the resolver
+  pub(crate) fn got_slot_symbols(&self) -> HashMap<u64, Arc<str>> {
+    let (jump_slot, glob_dat) = match self.header.description.machine {
+      EM_X86_64 | EM_386 => (7, 6),
+      EM_AARCH64 => (1026, 1025),
+      EM_ARM => (22, 21),
+      EM_RISCV => (5, 0),
+      _ => return HashMap::new(),
+    };
+    let irelative = self.irelative_relocation_type();
+    let resolvers: HashMap<u64, Arc<str>> = self.symbol_table().iter()
+      .filter(|symbol| symbol.symbol_type == STT_GNU_IFUNC && !symbol.is_undefined())
+      .map(|symbol| (symbol.value, symbol.name.clone()))
+      .collect();
+    self.relocations().into_iter().filter_map(|relocation| {
+      let name = if relocation.relocation_type == jump_slot || (glob_dat != 0 && relocation.relocation_type == glob_dat) {
+        self.relocation_symbol_name(relocation.section_index, relocation.symbol_index)?
+      } else if Some(relocation.relocation_type) == irelative {
+        resolvers.get(&(relocation.addend? as u64))?.clone()
+      } else {
+        return None;
+      };
+      Some((relocation.offset, name))
+    }).collect()
+  }
+
+  //The PLT stubs and the symbols they jump to, decoded from the stubs' GOT loads on x86
+  //(jmp *slot) and AArch64 (adrp x16 and ldr x17). Other machines give no entries.
+  pub fn plt_entries(&self) -> Vec<PltEntry> {
+    let slots = self.got_slot_symbols();
+    let mut entries = Vec::new();
+    let got_base = self.dynamic_value(DT_PLTGOT).unwrap_or(0);
+    for section in self.section_headers.iter().filter(|section| is_plt_section(self.section_name(section))) {
+      let code = self.section_data(section);
+      let entry_size = if section.entry_size >= 8 { section.entry_size } else { 16 };
+      let mut seen = HashSet::new();
+      let mut push = |offset: u64, slot: u64| {
+        let address = section.address + offset - offset % entry_size;
+        if let Some(name) = slots.get(&slot) {
+          if seen.insert(address) {
+            entries.push(PltEntry { address, got_slot: slot, name: name.clone() });
+          }
+        }
+      };
+      match self.header.description.machine {
+        EM_X86_64 | EM_386 => {
+          for (offset, window) in code.windows(6).enumerate() {
+            let displacement = i32::from_le_bytes([window[2], window[3], window[4], window[5]]) as i64 as u64;
+            let slot = match (self.header.description.machine, window[0], window[1]) {
+              //jmp *disp(%rip)
+              (EM_X86_64, 0xff, 0x25) => section.address.wrapping_add(offset as u64 + 6).wrapping_add(displacement),
+              //jmp *abs32, and jmp *disp(%ebx) with %ebx at the GOT
+              (EM_386, 0xff, 0x25) => displacement & 0xffff_ffff,
+              (EM_386, 0xff, 0xa3) => got_base.wrapping_add(displacement) & 0xffff_ffff,
+              _ => continue,
+            };
+            push(offset as u64, slot);
+          }
+        },
+        EM_AARCH64 => {
+          let words: Vec<u32> = code.chunks_exact(4).map(read_a64).collect();
+          for (index, pair) in words.windows(2).enumerate() {
+            let (adrp, ldr) = (pair[0], pair[1]);
+            if adrp & 0x9f00_0000 != 0x9000_0000 || ldr & 0xffc0_0000 != 0xf940_0000 {
+              continue;
+            }
+            let pc = section.address + index as u64 * 4;
+            let immediate = ((adrp >> 3) & 0x1f_fffc) | ((adrp >> 29) & 3);
+            let page = (pc & !0xfff).wrapping_add(((((immediate << 11) as i32) >> 11) as i64 as u64) << 12);
+            push(index as u64 * 4, page.wrapping_add(((ldr >> 10) & 0xfff) as u64 * 8));
+          }
+        },
+        _ => {},
+      };
+    }
+    entries
+  }
+
+  //Calls and jumps to `targets`, and on x86-64 calls and jumps through the GOT `slots`, in
+  //the executable sections of a linked file. The PLT itself is skipped. Instructions are
+  //found by their encoding, not by disassembly, so data in code can give a false site.
+  pub fn call_sites(&self, targets: &[u64], slots: &[u64]) -> Vec<CallSite> {
+    let targets: HashSet<u64> = targets.iter().copied().collect();
+    let slots: HashSet<u64> = slots.iter().copied().collect();
+    let mut sites = Vec::new();
+    for (section_index, section) in self.section_headers.iter().enumerate() {
+      if section.flags & SHF_EXECINSTR == 0 || is_plt_section(self.section_name(section)) {
+        continue;
+      }
+      let code = self.section_data(section);
+      let mut push = |address: u64, target: u64, kind: CallKind| sites.push(CallSite { address, target, kind, section_index });
+      match self.header.description.machine {
+        EM_X86_64 | EM_386 => {
+          for (offset, window) in code.windows(5).enumerate() {
+            let address = section.address + offset as u64;
+            let displacement = i32::from_le_bytes([window[1], window[2], window[3], window[4]]) as i64 as u64;
+            let target = address.wrapping_add(5).wrapping_add(displacement);
+            match window[0] {
+              0xe8 if targets.contains(&target) => push(address, target, CallKind::Call),
+              0xe9 if targets.contains(&target) => push(address, target, CallKind::Jump),
+              _ => {},
+            };
+          }
+          if self.header.description.machine == EM_X86_64 && !slots.is_empty() {
+            for (offset, window) in code.windows(6).enumerate() {
+              let address = section.address + offset as u64;
+              let displacement = i32::from_le_bytes([window[2], window[3], window[4], window[5]]) as i64 as u64;
+              let slot = address.wrapping_add(6).wrapping_add(displacement);
+              match (window[0], window[1]) {
+                (0xff, 0x15) if slots.contains(&slot) => push(address, slot, CallKind::IndirectCall),
+                (0xff, 0x25) if slots.contains(&slot) => push(address, slot, CallKind::IndirectJump),
+                _ => {},
+              };
+            }
+          }
+        },
+        EM_AARCH64 => {
+          for (index, word) in code.chunks_exact(4).enumerate() {
+            let word = read_a64(word);
+            if word & 0x7c00_0000 != 0x1400_0000 {
+              continue;
+            }
+            let address = section.address + index as u64 * 4;
+            let target = address.wrapping_add((((word & 0x03ff_ffff) << 6) as i32 >> 4) as i64 as u64);
+            if targets.contains(&target) {
+              push(address, target, if word & 0x8000_0000 != 0 { CallKind::Call } else { CallKind::Jump });
+            }
+          }
+        },
+        _ => {},
+      };
+    }
+    sites
+  }
+
+  //Relocations against named symbols in the code of a relocatable object, where calls to
+  //other files are still unresolved: calls are told apart by the relocation type and the
+  //opcode before the field, anything else is a Reference. The target is 0.
+  pub fn relocated_call_sites(&self) -> Vec<(Arc<str>, CallSite)> {
+    let machine = self.header.description.machine;
+    let mut sites = Vec::new();
+    for relocation in self.relocations() {
+      let target_index = match self.section_headers.get(relocation.section_index) {
+        Some(section) => section.info as usize,
+        None => continue,
+      };
+      let target = match self.section_headers.get(target_index) {
+        Some(section) if section.flags & SHF_EXECINSTR != 0 => section,
+        _ => continue,
+      };
+      let name = match self.relocation_symbol_name(relocation.section_index, relocation.symbol_index) {
+        Some(name) => name,
+        None => continue,
+      };
+      let code = self.section_data(target);
+      let before = |count: u64| relocation.offset.checked_sub(count).and_then(|start| code.get(start as usize..relocation.offset as usize));
+      let (address, kind) = match (machine, relocation.relocation_type) {
+        //R_*_PC32 and R_*_PLT32 after call or jmp
+        (EM_X86_64 | EM_386, 2 | 4) => match before(1) {
+          Some([0xe8]) => (relocation.offset - 1, CallKind::Call),
+          Some([0xe9]) => (relocation.offset - 1, CallKind::Jump),
+          _ => (relocation.offset, CallKind::Reference),
+        },
+        //R_X86_64_GOTPCREL and GOTPCRELX after call * or jmp *
+        (EM_X86_64, 9 | 41) => match before(2) {
+          Some([0xff, 0x15]) => (relocation.offset - 2, CallKind::IndirectCall),
+          Some([0xff, 0x25]) => (relocation.offset - 2, CallKind::IndirectJump),
+          _ => (relocation.offset, CallKind::Reference),
+        },
+        //R_AARCH64_CALL26 and JUMP26
+        (EM_AARCH64, 283) => (relocation.offset, CallKind::Call),
+        (EM_AARCH64, 282) => (relocation.offset, CallKind::Jump),
+        _ => (relocation.offset, CallKind::Reference),
+      };
+      sites.push((name, CallSite { address: target.address + address, target: 0, kind, section_index: target_index }));
+    }
+    sites
+  }
+}
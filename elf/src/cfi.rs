@@ -0,0 +1,450 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::ops::Range;
+use crate::dwarf::Reader;
+use crate::elf::Elf;
+
+pub const DW_EH_PE_ABSPTR: u8 = 0x00;
+pub const DW_EH_PE_ULEB128: u8 = 0x01;
+pub const DW_EH_PE_UDATA2: u8 = 0x02;
+pub const DW_EH_PE_UDATA4: u8 = 0x03;
+pub const DW_EH_PE_UDATA8: u8 = 0x04;
+pub const DW_EH_PE_SLEB128: u8 = 0x09;
+pub const DW_EH_PE_SDATA2: u8 = 0x0a;
+pub const DW_EH_PE_SDATA4: u8 = 0x0b;
+pub const DW_EH_PE_SDATA8: u8 = 0x0c;
+pub const DW_EH_PE_PCREL: u8 = 0x10;
+pub const DW_EH_PE_TEXTREL: u8 = 0x20;
+pub const DW_EH_PE_DATAREL: u8 = 0x30;
+pub const DW_EH_PE_FUNCREL: u8 = 0x40;
+pub const DW_EH_PE_ALIGNED: u8 = 0x50;
+pub const DW_EH_PE_INDIRECT: u8 = 0x80;
+pub const DW_EH_PE_OMIT: u8 = 0xff;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum CfaRule {
+  RegisterOffset(u16, i64),
+  Expression(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegisterRule {
+  Undefined,
+  SameValue,
+  //saved at CFA + offset
+  Offset(i64),
+  //the value is CFA + offset
+  ValOffset(i64),
+  Register(u16),
+  Expression(Vec<u8>),
+  ValExpression(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnwindRow {
+  pub address: u64,
+  pub cfa: CfaRule,
+  //sorted by register number, registers without a rule are absent
+  pub registers: Vec<(u16, RegisterRule)>,
+}
+
+impl UnwindRow {
+  pub fn register(&self, register: u16) -> Option<&RegisterRule> {
+    self.registers.iter().find(|(number, _)| *number == register).map(|(_, rule)| rule)
+  }
+
+  pub(crate) fn set_register(&mut self, register: u16, rule: Option<RegisterRule>) {
+    let position = self.registers.partition_point(|(number, _)| *number < register);
+    let present = self.registers.get(position).is_some_and(|(number, _)| *number == register);
+    match (rule, present) {
+      (Some(rule), true) => self.registers[position].1 = rule,
+      (Some(rule), false) => self.registers.insert(position, (register, rule)),
+      (None, true) => { self.registers.remove(position); },
+      (None, false) => {},
+    };
+  }
+}
+
+//Unwind information for one function. Every row applies from its address up to the next
+//row, the last one up to address + size.
+pub struct FrameDescription {
+  pub address: u64,
+  pub size: u64,
+  pub return_address_register: u16,
+  pub signal_frame: bool,
+  pub personality: Option<u64>,
+  pub lsda: Option<u64>,
+  pub rows: Vec<UnwindRow>,
+}
+
+//Anything that can describe how to recover the caller's frame: .eh_frame and .debug_frame
+//here, SFrame and other formats implement it as well.
+pub trait UnwindSource {
+  fn frame_descriptions(&self) -> Vec<FrameDescription>;
+}
+
+pub struct UnwindTable {
+  //sorted by address, the first source wins where several describe the same function
+  pub frames: Vec<FrameDescription>,
+}
+
+impl UnwindTable {
+  pub fn new(sources: &[&dyn UnwindSource]) -> UnwindTable {
+    let mut frames: Vec<FrameDescription> = Vec::new();
+    let mut addresses = HashSet::new();
+    for source in sources {
+      for frame in source.frame_descriptions() {
+        if addresses.insert(frame.address) {
+          frames.push(frame);
+        }
+      }
+    }
+    frames.sort_by_key(|frame| frame.address);
+    UnwindTable { frames }
+  }
+
+  pub fn frame_at(&self, address: u64) -> Option<&FrameDescription> {
+    let position = self.frames.partition_point(|frame| frame.address <= address);
+    let frame = &self.frames[position.checked_sub(1)?];
+    if address - frame.address < frame.size { Some(frame) } else { None }
+  }
+
+  pub fn row_at(&self, address: u64) -> Option<&UnwindRow> {
+    let frame = self.frame_at(address)?;
+    let position = frame.rows.partition_point(|row| row.address <= address);
+    frame.rows.get(position.checked_sub(1)?)
+  }
+}
+
+//Addresses encoded pointers can be relative to.
+#[derive(Default, Clone, Copy)]
+pub struct PointerBases {
+  pub text: u64,
+  pub data: u64,
+  pub function: u64,
+}
+
+//Reads a DW_EH_PE encoded pointer, `address` is the virtual address of reader.data[0].
+pub(crate) fn read_encoded_pointer(reader: &mut Reader, encoding: u8, address: u64, address_size: u8, bases: &PointerBases) -> Option<u64> {
+  if encoding == DW_EH_PE_OMIT {
+    return None;
+  }
+  let field_address = address.wrapping_add(reader.position as u64);
+  if encoding & 0x70 == DW_EH_PE_ALIGNED {
+    let size = address_size as usize;
+    reader.position = reader.position.div_ceil(size) * size;
+    return reader.unsigned(size);
+  }
+  let value = match encoding & 0x0f {
+    DW_EH_PE_ABSPTR => reader.unsigned(address_size as usize)?,
+    DW_EH_PE_ULEB128 => reader.uleb()?,
+    DW_EH_PE_UDATA2 => reader.unsigned(2)?,
+    DW_EH_PE_UDATA4 => reader.unsigned(4)?,
+    DW_EH_PE_UDATA8 => reader.unsigned(8)?,
+    DW_EH_PE_SLEB128 => reader.sleb()? as u64,
+    DW_EH_PE_SDATA2 => reader.unsigned(2)? as u16 as i16 as i64 as u64,
+    DW_EH_PE_SDATA4 => reader.unsigned(4)? as u32 as i32 as i64 as u64,
+    DW_EH_PE_SDATA8 => reader.unsigned(8)?,
+    _ => return None,
+  };
+  let base = match encoding & 0x70 {
+    DW_EH_PE_PCREL => field_address,
+    DW_EH_PE_TEXTREL => bases.text,
+    DW_EH_PE_DATAREL => bases.data,
+    DW_EH_PE_FUNCREL => bases.function,
+    _ => 0,
+  };
+  //indirect pointers point at the real value, which needs the loaded image
+  Some(base.wrapping_add(value))
+}
+
+struct CommonInformation {
+  code_alignment: u64,
+  data_alignment: i64,
+  return_address_register: u16,
+  address_size: u8,
+  fde_encoding: u8,
+  lsda_encoding: u8,
+  personality: Option<u64>,
+  signal_frame: bool,
+  augmented: bool,
+  //offsets of the initial instructions in the section
+  instructions: Range<usize>,
+}
+
+//.eh_frame or .debug_frame contents.
+pub struct CallFrameInformation<'a> {
+  pub data: &'a [u8],
+  //virtual address of the section
+  pub address: u64,
+  pub is_eh_frame: bool,
+  pub big_endian: bool,
+  pub address_size: u8,
+  pub bases: PointerBases,
+}
+
+impl<'a> CallFrameInformation<'a> {
+  fn reader(&self, position: usize) -> Reader<'a> {
+    Reader { data: self.data, position, big_endian: self.big_endian }
+  }
+
+  fn common_information(&self, offset: usize) -> Option<CommonInformation> {
+    let mut reader = self.reader(offset);
+    let (offset_size, end) = reader.initial_length()?;
+    let id = reader.unsigned(offset_size as usize)?;
+    let cie_id = if self.is_eh_frame { 0 } else if offset_size == 8 { u64::MAX } else { 0xffff_ffff };
+    if id != cie_id {
+      return None;
+    }
+    let version = reader.unsigned(1)?;
+    let augmentation = reader.c_string()?;
+    if augmentation.windows(2).any(|pair| pair == b"eh") {
+      reader.unsigned(self.address_size as usize)?;
+    }
+    let mut address_size = self.address_size;
+    if version >= 4 {
+      address_size = reader.unsigned(1)? as u8;
+      reader.unsigned(1)?;
+    }
+    let code_alignment = reader.uleb()?;
+    let data_alignment = reader.sleb()?;
+    let return_address_register = if version == 1 { reader.unsigned(1)? } else { reader.uleb()? } as u16;
+    let mut information = CommonInformation {
+      code_alignment,
+      data_alignment,
+      return_address_register,
+      address_size,
+      fde_encoding: DW_EH_PE_ABSPTR,
+      lsda_encoding: DW_EH_PE_OMIT,
+      personality: None,
+      signal_frame: false,
+      augmented: augmentation.first() == Some(&b'z'),
+      instructions: 0..0,
+    };
+    if information.augmented {
+      let length = usize::try_from(reader.uleb()?).ok()?;
+      let augmentation_end = reader.position.checked_add(length)?;
+      for &character in &augmentation[1..] {
+        match character {
+          b'L' => information.lsda_encoding = reader.unsigned(1)? as u8,
+          b'R' => information.fde_encoding = reader.unsigned(1)? as u8,
+          b'P' => {
+            let encoding = reader.unsigned(1)? as u8;
+            information.personality = read_encoded_pointer(&mut reader, encoding, self.address, address_size, &self.bases);
+          },
+          b'S' => information.signal_frame = true,
+          _ => {},
+        };
+      }
+      reader.position = augmentation_end;
+    }
+    self.data.get(reader.position..end)?;
+    information.instructions = reader.position..end;
+    Some(information)
+  }
+
+  fn frame_description(&self, cie_offset: usize, mut reader: Reader<'a>, end: usize) -> Option<FrameDescription> {
+    let cie = self.common_information(cie_offset)?;
+    let bases = self.bases;
+    let address = read_encoded_pointer(&mut reader, cie.fde_encoding, self.address, cie.address_size, &bases)?;
+    let size = read_encoded_pointer(&mut reader, cie.fde_encoding & 0x0f, self.address, cie.address_size, &bases)?;
+    let mut lsda = None;
+    if cie.augmented {
+      let length = usize::try_from(reader.uleb()?).ok()?;
+      let augmentation_end = reader.position.checked_add(length)?;
+      if length > 0 {
+        lsda = read_encoded_pointer(&mut reader, cie.lsda_encoding, self.address, cie.address_size, &bases);
+      }
+      reader.position = augmentation_end;
+    }
+    self.data.get(reader.position..end)?;
+    let instructions = reader.position..end;
+
+    let mut state = RowState { row: UnwindRow { address, cfa: CfaRule::RegisterOffset(0, 0), registers: Vec::new() }, stack: Vec::new(), rows: Vec::new() };
+    self.execute(&cie, cie.instructions.clone(), &mut state, None, address)?;
+    let initial = state.row.clone();
+    self.execute(&cie, instructions, &mut state, Some(&initial), address)?;
+    state.push();
+    Some(FrameDescription {
+      address,
+      size,
+      return_address_register: cie.return_address_register,
+      signal_frame: cie.signal_frame,
+      personality: cie.personality,
+      lsda,
+      rows: state.rows,
+    })
+  }
+
+  //Runs the instructions at `instructions` in the section, so DW_CFA_set_loc sees the real pc and data bases.
+  fn execute(&self, cie: &CommonInformation, instructions: Range<usize>, state: &mut RowState, initial: Option<&UnwindRow>, function: u64) -> Option<()> {
+    let mut reader = Reader { data: self.data.get(..instructions.end)?, position: instructions.start, big_endian: self.big_endian };
+    let bases = PointerBases { function, ..self.bases };
+    let factored = |value: i64| value.checked_mul(cie.data_alignment);
+    let unsigned_factored = |value: u64| i64::try_from(value).ok().and_then(factored);
+    let advance = |delta: u64| delta.checked_mul(cie.code_alignment);
+    let restore = |register: u16| initial.and_then(|initial| initial.register(register).cloned());
+    while reader.position < instructions.end {
+      let opcode = reader.unsigned(1)? as u8;
+      match opcode >> 6 {
+        1 => { state.advance(advance((opcode & 0x3f) as u64)?); continue; },
+        2 => { let offset = unsigned_factored(reader.uleb()?)?; state.row.set_register((opcode & 0x3f) as u16, Some(RegisterRule::Offset(offset))); continue; },
+        3 => { let register = (opcode & 0x3f) as u16; state.row.set_register(register, restore(register)); continue; },
+        _ => {},
+      };
+      match opcode {
+        0x00 => {},
+        0x01 => {
+          let address = read_encoded_pointer(&mut reader, cie.fde_encoding, self.address, cie.address_size, &bases)?;
+          state.push();
+          state.row.address = address;
+        },
+        0x02 => { let delta = reader.unsigned(1)?; state.advance(advance(delta)?); },
+        0x03 => { let delta = reader.unsigned(2)?; state.advance(advance(delta)?); },
+        0x04 => { let delta = reader.unsigned(4)?; state.advance(advance(delta)?); },
+        0x05 => { let register = reader.uleb()? as u16; let offset = unsigned_factored(reader.uleb()?)?; state.row.set_register(register, Some(RegisterRule::Offset(offset))); },
+        0x06 => { let register = reader.uleb()? as u16; state.row.set_register(register, restore(register)); },
+        0x07 => { let register = reader.uleb()? as u16; state.row.set_register(register, Some(RegisterRule::Undefined)); },
+        0x08 => { let register = reader.uleb()? as u16; state.row.set_register(register, Some(RegisterRule::SameValue)); },
+        0x09 => { let register = reader.uleb()? as u16; let other = reader.uleb()? as u16; state.row.set_register(register, Some(RegisterRule::Register(other))); },
+        0x0a => state.stack.push((state.row.cfa.clone(), state.row.registers.clone())),
+        0x0b => {
+          if let Some((cfa, registers)) = state.stack.pop() {
+            state.row.cfa = cfa;
+            state.row.registers = registers;
+          }
+        },
+        0x0c => { let register = reader.uleb()? as u16; let offset = i64::try_from(reader.uleb()?).ok()?; state.row.cfa = CfaRule::RegisterOffset(register, offset); },
+        0x0d => {
+          let register = reader.uleb()? as u16;
+          let offset = match state.row.cfa { CfaRule::RegisterOffset(_, offset) => offset, _ => 0 };
+          state.row.cfa = CfaRule::RegisterOffset(register, offset);
+        },
+        0x0e => {
+          let offset = i64::try_from(reader.uleb()?).ok()?;
+          if let CfaRule::RegisterOffset(register, _) = state.row.cfa {
+            state.row.cfa = CfaRule::RegisterOffset(register, offset);
+          }
+        },
+        0x0f => { let size = reader.uleb()? as usize; state.row.cfa = CfaRule::Expression(reader.bytes(size)?.to_vec()); },
+        0x10 => { let register = reader.uleb()? as u16; let size = reader.uleb()? as usize; state.row.set_register(register, Some(RegisterRule::Expression(reader.bytes(size)?.to_vec()))); },
+        0x11 => { let register = reader.uleb()? as u16; let offset = factored(reader.sleb()?)?; state.row.set_register(register, Some(RegisterRule::Offset(offset))); },
+        0x12 => { let register = reader.uleb()? as u16; let offset = factored(reader.sleb()?)?; state.row.cfa = CfaRule::RegisterOffset(register, offset); },
+        0x13 => {
+          let offset = factored(reader.sleb()?)?;
+          if let CfaRule::RegisterOffset(register, _) = state.row.cfa {
+            state.row.cfa = CfaRule::RegisterOffset(register, offset);
+          }
+        },
+        0x14 => { let register = reader.uleb()? as u16; let offset = unsigned_factored(reader.uleb()?)?; state.row.set_register(register, Some(RegisterRule::ValOffset(offset))); },
+        0x15 => { let register = reader.uleb()? as u16; let offset = factored(reader.sleb()?)?; state.row.set_register(register, Some(RegisterRule::ValOffset(offset))); },
+        0x16 => { let register = reader.uleb()? as u16; let size = reader.uleb()? as usize; state.row.set_register(register, Some(RegisterRule::ValExpression(reader.bytes(size)?.to_vec()))); },
+        //DW_CFA_GNU_window_save / DW_CFA_AARCH64_negate_ra_state carry no operands
+        0x2d => {},
+        0x2e => { reader.uleb()?; },
+        0x2f => { let register = reader.uleb()? as u16; let offset = unsigned_factored(reader.uleb()?)?.checked_neg()?; state.row.set_register(register, Some(RegisterRule::Offset(offset))); },
+        _ => return None,
+      };
+    }
+    Some(())
+  }
+}
+
+struct RowState {
+  row: UnwindRow,
+  stack: Vec<(CfaRule, Vec<(u16, RegisterRule)>)>,
+  rows: Vec<UnwindRow>,
+}
+
+impl RowState {
+  fn push(&mut self) {
+    match self.rows.last_mut() {
+      Some(last) if last.address == self.row.address => *last = self.row.clone(),
+      _ => self.rows.push(self.row.clone()),
+    };
+  }
+
+  fn advance(&mut self, delta: u64) {
+    self.push();
+    self.row.address = self.row.address.wrapping_add(delta);
+  }
+}
+
+impl<'a> UnwindSource for CallFrameInformation<'a> {
+  fn frame_descriptions(&self) -> Vec<FrameDescription> {
+    let mut frames = Vec::new();
+    let mut offset = 0;
+    while offset + 4 <= self.data.len() {
+      let mut reader = self.reader(offset);
+      let (offset_size, end) = match reader.initial_length() {
+        Some(length) => length,
+        None => break,
+      };
+      //a zero length terminates .eh_frame
+      if end == reader.position && offset_size == 4 || end > self.data.len() {
+        break;
+      }
+      let id_position = reader.position;
+      let id = match reader.unsigned(offset_size as usize) {
+        Some(id) => id,
+        None => break,
+      };
+      let is_cie = if self.is_eh_frame { id == 0 } else { id == 0xffff_ffff || id == u64::MAX };
+      if !is_cie {
+        let cie_offset = if self.is_eh_frame { id_position.checked_sub(id as usize) } else { Some(id as usize) };
+        if let Some(frame) = cie_offset.and_then(|cie_offset| self.frame_description(cie_offset, reader, end)) {
+          //discarded functions keep an FDE at address 0
+          if frame.size > 0 && frame.address != 0 {
+            frames.push(frame);
+          }
+        }
+      }
+      offset = end;
+    }
+    frames
+  }
+}
+
+impl Elf {
+  fn call_frame_information(&self, name: &str, is_eh_frame: bool) -> Option<CallFrameInformation<'_>> {
+    let section = self.section_by_name(name)?;
+    let text = self.section_by_name(".text").map_or(0, |text| text.address);
+    let data = self.section_by_name(".got").map_or(0, |got| got.address);
+    //.debug_frame of an ET_REL object points at its CIEs and functions through relocations
+    let contents = if is_eh_frame { self.section_data(section) } else { self.debug_section(name) };
+    Some(CallFrameInformation {
+      data: contents,
+      address: section.address,
+      is_eh_frame,
+      big_endian: self.header.identification.endianness == 2,
+      address_size: if self.header.identification.class == 1 { 4 } else { 8 },
+      bases: PointerBases { text, data, function: 0 },
+    })
+  }
+
+  pub fn eh_frame(&self) -> Option<CallFrameInformation<'_>> {
+    self.call_frame_information(".eh_frame", true)
+  }
+
+  pub fn debug_frame(&self) -> Option<CallFrameInformation<'_>> {
+    self.call_frame_information(".debug_frame", false)
+  }
+
+  //.eh_frame first, .debug_frame and then .sframe fill in functions it does not describe.
+  pub fn unwind_table(&self) -> UnwindTable {
+    let eh_frame = self.eh_frame();
+    let debug_frame = self.debug_frame();
+    let sframe = self.sframe();
+    let mut sources: Vec<&dyn UnwindSource> = Vec::new();
+    if let Some(eh_frame) = &eh_frame {
+      sources.push(eh_frame);
+    }
+    if let Some(debug_frame) = &debug_frame {
+      sources.push(debug_frame);
+    }
+    if let Some(sframe) = &sframe {
+      sources.push(sframe);
+    }
+    UnwindTable::new(&sources)
+  }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use crate::consts::*;
+use crate::workspace::Workspace;
+
+//defined by the linker in every shared object, a clash between them is expected
+pub(crate) const LINKER_DEFINED: &[&str] = &["_init", "_fini", "_edata", "_end", "__bss_start", "_DYNAMIC", "_GLOBAL_OFFSET_TABLE_"];
+
+#[derive(Clone, Debug)]
+pub struct ConflictingDefinition {
+  pub object_index: usize,
+  //biased, the TLS block offset for TLS symbols
+  pub address: u64,
+  pub version: Option<String>,
+  pub weak: bool,
+}
+
+//One name exported by more than one object of a lookup scope.
+#[derive(Clone, Debug)]
+pub struct SymbolConflict {
+  pub name: Arc<str>,
+  //the object whose lookup scope this is, None for the global scope
+  pub scope_root: Option<usize>,
+  //in lookup order. The first wins, even over a strong definition further on: the dynamic
+  //loader does not prefer strong over weak (LD_DYNAMIC_WEAK aside).
+  pub definitions: Vec<ConflictingDefinition>,
+  //objects of the scope whose references bind to the winner
+  pub bound_from: Vec<usize>,
+}
+
+impl SymbolConflict {
+  pub fn winner(&self) -> &ConflictingDefinition {
+    &self.definitions[0]
+  }
+
+  //the definitions the winner hides
+  pub fn shadowed(&self) -> &[ConflictingDefinition] {
+    &self.definitions[1..]
+  }
+}
+
+impl Workspace {
+  //Every exported name defined more than once in the global scope, and in the scope of each
+  //RTLD_LOCAL object, where a plugin's definitions lose to the global ones. Conflicts of the
+  //global scope are not repeated for the local scopes. Call resolve() first for bound_from.
+  pub fn symbol_conflicts(&self) -> Vec<SymbolConflict> {
+    let global: Vec<usize> = (0..self.objects.len()).filter(|&index| self.objects[index].global).collect();
+    let mut scopes = vec![(None, global.clone())];
+    for (index, object) in self.objects.iter().enumerate() {
+      if !object.global && object.local_scope.first() == Some(&index) {
+        scopes.push((Some(index), self.lookup_scope(index, &global)));
+      }
+    }
+    let mut conflicts = Vec::new();
+    for (scope_root, mut scope) in scopes {
+      let mut seen = vec![false; self.objects.len()];
+      scope.retain(|&index| !std::mem::replace(&mut seen[index], true));
+      let mut names: Vec<Arc<str>> = Vec::new();
+      let mut definitions: HashMap<Arc<str>, Vec<ConflictingDefinition>> = HashMap::new();
+      for &index in &scope {
+        let object = &self.objects[index];
+        let versions = object.elf.symbol_versions();
+        for (symbol_index, symbol) in object.elf.dynamic_symbol_table().iter().enumerate() {
+          if !symbol.is_exported() || symbol.name.is_empty() || LINKER_DEFINED.contains(&&*symbol.name) {
+            continue;
+          }
+          let version = versions.get(symbol_index).cloned().flatten();
+          //the absolute symbol naming a version, and name@VERSION, which only versioned
+          //references reach
+          if version.as_ref().is_some_and(|version| version.hidden || (symbol.section_index == SHN_ABS && *version.name == *symbol.name)) {
+            continue;
+          }
+          let entries = definitions.entry(symbol.name.clone()).or_insert_with(|| {
+            names.push(symbol.name.clone());
+            Vec::new()
+          });
+          //one object can define a name once per version, only its first definition is looked up
+          if entries.iter().all(|definition| definition.object_index != index) {
+            entries.push(ConflictingDefinition {
+              object_index: index,
+              address: object.symbol_address(symbol),
+              version: version.map(|version| version.name),
+              weak: symbol.binding == STB_WEAK,
+            });
+          }
+        }
+      }
+      for name in names {
+        let definitions = definitions.remove(&name).unwrap_or_default();
+        if definitions.len() < 2 || (scope_root.is_some() && definitions.iter().all(|definition| self.objects[definition.object_index].global)) {
+          continue;
+        }
+        let winner = definitions[0].object_index;
+        let mut bound_from: Vec<usize> = self.bindings.iter()
+          .filter(|binding| binding.name == name && binding.definition.is_some_and(|(object, _)| object == winner) && scope.contains(&binding.object_index))
+          .map(|binding| binding.object_index)
+          .collect();
+        bound_from.dedup();
+        conflicts.push(SymbolConflict { name, scope_root, definitions, bound_from });
+      }
+    }
+    conflicts
+  }
+}
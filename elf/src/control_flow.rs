@@ -0,0 +1,528 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::{TryFrom, TryInto};
+use std::io;
+use std::io::ErrorKind;
+use crate::consts::*;
+use crate::cpu_features::{decode_x86, X86Instruction};
+use crate::dwarf::read_unsigned;
+use crate::elf::Elf;
+
+//instructions before an indirect branch searched for the table load and the bounds check
+const LOOKBEHIND: usize = 16;
+//entries read from a table without a bounds check, or with an implausible one
+const MAX_CASES: usize = 4096;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JumpTableEntries {
+  //word sized addresses: jmp *table(,%rax,8) in non-PIC x86 code
+  Absolute,
+  //target = base + (entry << shift): x86-64 PIC tables of 32-bit offsets from the table,
+  //AArch64 tables of byte, halfword or word offsets from an adr label or the table
+  Relative { base: u64, shift: u8, signed: bool },
+}
+
+//A compiler-emitted switch table behind an indirect branch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JumpTable {
+  //the indirect jmp or br
+  pub branch: u64,
+  pub table: u64,
+  pub entry_size: u8,
+  pub entries: JumpTableEntries,
+  //the case count comes from the bounds check before the branch; without one entries are
+  //read while they land in the function
+  pub bounded: bool,
+  //one per entry, in table order, duplicates kept
+  pub targets: Vec<u64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockExit {
+  //runs into the next block, which a branch target split off
+  Fallthrough,
+  Jump,
+  Conditional,
+  //an indirect branch through the jump table with this index in jump_tables
+  Switch(usize),
+  //an indirect branch that is not a recognised switch, a tail call through a pointer
+  Indirect,
+  Return,
+  //ud2, hlt, int3, brk, undecodable bytes or the function end
+  Trap,
+  //a jump out of the function
+  TailCall(u64),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BasicBlock {
+  pub start: u64,
+  pub end: u64,
+  pub exit: BlockExit,
+  //blocks of the function: the taken branch before the fallthrough, switch cases in table
+  //order without duplicates
+  pub successors: Vec<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ControlFlowGraph {
+  pub function: u64,
+  pub size: u64,
+  //by address, the entry block first
+  pub blocks: Vec<BasicBlock>,
+  pub jump_tables: Vec<JumpTable>,
+}
+
+impl ControlFlowGraph {
+  pub fn block_at(&self, address: u64) -> Option<&BasicBlock> {
+    let index = self.blocks.partition_point(|block| block.end <= address);
+    self.blocks.get(index).filter(|block| block.start <= address)
+  }
+
+  pub fn predecessors(&self, block: u64) -> Vec<u64> {
+    self.blocks.iter().filter(|candidate| candidate.successors.contains(&block)).map(|candidate| candidate.start).collect()
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Flow {
+  Next,
+  Jump(u64),
+  Conditional(u64),
+  IndirectJump,
+  Return,
+  Trap,
+}
+
+#[derive(Clone, Copy)]
+struct Instruction {
+  address: u64,
+  length: u64,
+  flow: Flow,
+}
+
+//what the function's code looks like to the jump table matchers
+struct Code<'a> {
+  elf: &'a Elf,
+  data: &'a [u8],
+  address: u64,
+  big_endian: bool,
+  machine: u16,
+  is_64: bool,
+}
+
+impl<'a> Code<'a> {
+  fn bytes(&self, address: u64) -> Option<&'a [u8]> {
+    self.data.get(address.checked_sub(self.address)? as usize..)
+  }
+
+  fn word(&self, address: u64) -> Option<u32> {
+    Some(read_unsigned(self.bytes(address)?.get(..4)?, self.big_endian) as u32)
+  }
+
+  fn decode(&self, address: u64) -> Option<Instruction> {
+    match self.machine {
+      EM_X86_64 | EM_386 => {
+        let code = self.bytes(address)?;
+        let instruction = decode_x86(code, self.is_64)?;
+        Some(Instruction { address, length: instruction.length as u64, flow: x86_flow(code, address, &instruction) })
+      },
+      _ => Some(Instruction { address, length: 4, flow: aarch64_flow(self.word(address)?, address) }),
+    }
+  }
+
+  //reads a table entry from the file, wherever the table is
+  fn entry(&self, address: u64, size: u8, signed: bool) -> Option<i64> {
+    let offset = usize::try_from(self.elf.address_to_offset(address)?).ok()?;
+    let value = read_unsigned(self.elf.data.get(offset..offset.checked_add(size as usize)?)?, self.big_endian);
+    Some(if signed {
+      let shift = 64 - size as u32 * 8;
+      ((value << shift) as i64) >> shift
+    } else {
+      value as i64
+    })
+  }
+}
+
+fn relative(address: u64, length: usize, displacement: i64) -> u64 {
+  address.wrapping_add(length as u64).wrapping_add(displacement as u64)
+}
+
+fn x86_flow(code: &[u8], address: u64, instruction: &X86Instruction) -> Flow {
+  let length = instruction.length;
+  let rel8 = || code[length - 1] as i8 as i64;
+  let rel32 = || i32::from_le_bytes([code[length - 4], code[length - 3], code[length - 2], code[length - 1]]) as i64;
+  let reg = instruction.modrm.map(|modrm| (modrm >> 3) & 7);
+  //rel16 forms, with an operand size prefix, are not used by compilers
+  if instruction.prefix == 0x66 && matches!((instruction.map, instruction.opcode), (0, 0xe9) | (1, 0x80..=0x8f)) {
+    return Flow::Trap;
+  }
+  match (instruction.map, instruction.opcode) {
+    (0, 0x70..=0x7f) | (0, 0xe0..=0xe3) => Flow::Conditional(relative(address, length, rel8())),
+    (1, 0x80..=0x8f) => Flow::Conditional(relative(address, length, rel32())),
+    (0, 0xeb) => Flow::Jump(relative(address, length, rel8())),
+    (0, 0xe9) => Flow::Jump(relative(address, length, rel32())),
+    (0, 0xc2) | (0, 0xc3) | (0, 0xca) | (0, 0xcb) | (0, 0xcf) => Flow::Return,
+    (0, 0xcc) | (0, 0xf4) | (1, 0x0b) => Flow::Trap,
+    (0, 0xff) if reg == Some(4) || reg == Some(5) => Flow::IndirectJump,
+    _ => Flow::Next,
+  }
+}
+
+fn sign_extend(value: u32, bits: u32) -> i64 {
+  ((value as i64) << (64 - bits)) >> (64 - bits)
+}
+
+fn aarch64_flow(word: u32, address: u64) -> Flow {
+  if word & 0xfc00_0000 == 0x1400_0000 {
+    Flow::Jump(address.wrapping_add((sign_extend(word & 0x3ff_ffff, 26) << 2) as u64))
+  } else if word & 0xff00_0010 == 0x5400_0000 || word & 0x7e00_0000 == 0x3400_0000 {
+    //b.cond, cbz and cbnz
+    Flow::Conditional(address.wrapping_add((sign_extend((word >> 5) & 0x7_ffff, 19) << 2) as u64))
+  } else if word & 0x7e00_0000 == 0x3600_0000 {
+    //tbz and tbnz
+    Flow::Conditional(address.wrapping_add((sign_extend((word >> 5) & 0x3fff, 14) << 2) as u64))
+  } else if word & 0xffff_fc1f == 0xd61f_0000 {
+    Flow::IndirectJump
+  } else if word & 0xffff_fc1f == 0xd65f_0000 || word == 0xd65f_0bff || word == 0xd65f_0fff {
+    //ret, retaa, retab
+    Flow::Return
+  } else if word & 0xffe0_001f == 0xd420_0000 || word >> 16 == 0 {
+    //brk and udf
+    Flow::Trap
+  } else {
+    Flow::Next
+  }
+}
+
+//ja/jbe after cmp $n: n + 1 cases, jae/jb: n cases
+fn case_count(limit: u64, inclusive: bool) -> Option<usize> {
+  let count = if inclusive { limit.checked_add(1)? } else { limit };
+  (count > 0 && count <= MAX_CASES as u64).then_some(count as usize)
+}
+
+//The bounds check on the x86 path to the branch: the last jcc of the run and the cmp with an
+//immediate right before it.
+fn x86_bound(code: &Code, history: &[Instruction]) -> Option<usize> {
+  let position = history.iter().rposition(|instruction| matches!(instruction.flow, Flow::Conditional(_)))?;
+  let branch = decode_x86(code.bytes(history[position].address)?, code.is_64)?;
+  let condition = if branch.map == 0 { branch.opcode - 0x70 } else { branch.opcode - 0x80 };
+  let compare = history.get(position.checked_sub(1)?)?;
+  let bytes = code.bytes(compare.address)?;
+  let instruction = decode_x86(bytes, code.is_64)?;
+  let reg = instruction.modrm.map(|modrm| (modrm >> 3) & 7);
+  let length = instruction.length;
+  let limit = match (instruction.map, instruction.opcode) {
+    (0, 0x83) if reg == Some(7) => bytes[length - 1] as i8 as i64,
+    (0, 0x81) if reg == Some(7) => i32::from_le_bytes([bytes[length - 4], bytes[length - 3], bytes[length - 2], bytes[length - 1]]) as i64,
+    (0, 0x3d) => i32::from_le_bytes([bytes[length - 4], bytes[length - 3], bytes[length - 2], bytes[length - 1]]) as i64,
+    _ => return None,
+  };
+  match condition {
+    //ja, jbe
+    0x7 | 0x6 => case_count(limit as u64, true),
+    //jae, jb
+    0x3 | 0x2 => case_count(limit as u64, false),
+    _ => None,
+  }
+}
+
+fn x86_jump_table(code: &Code, history: &[Instruction]) -> Option<(u64, u8, JumpTableEntries)> {
+  let (branch, rest) = history.split_last()?;
+  let bytes = code.bytes(branch.address)?;
+  let jump = decode_x86(bytes, code.is_64)?;
+  let modrm = jump.modrm?;
+  let (mode, rm) = (modrm >> 6, modrm & 7);
+  let word = if code.is_64 { 8 } else { 4 };
+  if mode == 0 && rm == 4 {
+    //jmp *table(,%index,word): no base register, a 32-bit displacement
+    let sib = *bytes.get(jump.modrm_offset + 1)?;
+    if sib & 7 != 5 || 1 << (sib >> 6) != word {
+      return None;
+    }
+    let at = jump.modrm_offset + 2;
+    let table = i32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?) as i64 as u64;
+    let table = if code.is_64 { table } else { table & 0xffff_ffff };
+    return Some((table, word, JumpTableEntries::Absolute));
+  }
+  if mode != 3 || !code.is_64 {
+    return None;
+  }
+  //lea table(%rip), %base ... movslq (%base,%index,4), %target; add %base, %target; jmp *%target
+  let mut loaded_from = None;
+  for instruction in rest.iter().rev().take(LOOKBEHIND) {
+    let bytes = code.bytes(instruction.address)?;
+    let decoded = decode_x86(bytes, true)?;
+    let modrm = match decoded.modrm {
+      Some(modrm) if decoded.map == 0 => modrm,
+      _ => continue,
+    };
+    let (mode, reg, rm) = (modrm >> 6, ((modrm >> 3) & 7) | (decoded.rex & 4) << 1, modrm & 7);
+    match decoded.opcode {
+      0x63 if decoded.rex_w && mode == 0 && rm == 4 && loaded_from.is_none() => {
+        let sib = *bytes.get(decoded.modrm_offset + 1)?;
+        if sib >> 6 == 2 && sib & 7 != 5 {
+          loaded_from = Some((sib & 7) | (decoded.rex & 1) << 3);
+        }
+      },
+      0x8d if mode == 0 && rm == 5 && loaded_from == Some(reg) => {
+        let at = decoded.modrm_offset + 1;
+        let displacement = i32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?) as i64;
+        let table = relative(instruction.address, decoded.length, displacement);
+        return Some((table, 4, JumpTableEntries::Relative { base: table, shift: 0, signed: true }));
+      },
+      _ => {},
+    }
+  }
+  None
+}
+
+fn aarch64_register(word: u32, shift: u32) -> u32 {
+  (word >> shift) & 31
+}
+
+fn aarch64_bound(code: &Code, history: &[Instruction]) -> Option<usize> {
+  let position = history.iter().rposition(|instruction| matches!(instruction.flow, Flow::Conditional(_)))?;
+  let branch = code.word(history[position].address)?;
+  if branch & 0xff00_0010 != 0x5400_0000 {
+    return None;
+  }
+  //cmp wN, #imm is subs wzr, wN, #imm
+  let compare = code.word(history.get(position.checked_sub(1)?)?.address)?;
+  if compare & 0x7f80_001f != 0x7100_001f {
+    return None;
+  }
+  let limit = (((compare >> 10) & 0xfff) as u64) << if compare & (1 << 22) != 0 { 12 } else { 0 };
+  match branch & 0xf {
+    //hi, ls
+    8 | 9 => case_count(limit, true),
+    //hs, lo
+    2 | 3 => case_count(limit, false),
+    _ => None,
+  }
+}
+
+//adrp x, table; add x, x, :lo12:table; ldr{b,h,sw} i, [x, index]; (adr base;) add target,
+//base, i, {uxtb, sxtb, lsl} #shift; br target
+fn aarch64_jump_table(code: &Code, history: &[Instruction]) -> Option<(u64, u8, JumpTableEntries)> {
+  let (branch, rest) = history.split_last()?;
+  let target = aarch64_register(code.word(branch.address)?, 5);
+  let mut previous = rest.iter().rev().take(LOOKBEHIND).map(|instruction| (instruction.address, code.word(instruction.address)));
+  let mut find = |matches: &dyn Fn(u32) -> bool| -> Option<(u64, u32)> {
+    previous.by_ref().find_map(|(address, word)| word.filter(|&word| matches(word)).map(|word| (address, word)))
+  };
+
+  let add = find(&|word| word & 0x7f20_0000 == 0x0b20_0000 || word & 0x7f20_0000 == 0x0b00_0000)?.1;
+  if aarch64_register(add, 0) != target {
+    return None;
+  }
+  let (base_register, index_register) = (aarch64_register(add, 5), aarch64_register(add, 16));
+  let (shift, extend_signed) = if add & (1 << 21) != 0 {
+    //extended register: option 4 to 7 sign extend
+    ((add >> 10) & 7, Some((add >> 13) & 4 != 0))
+  } else {
+    if (add >> 22) & 3 != 0 {
+      return None;
+    }
+    ((add >> 10) & 0x3f, None)
+  };
+  let (load_address, base) = {
+    //the adr of the base label can come before or after the load
+    let mut adr = None;
+    let mut load = None;
+    while adr.is_none() || load.is_none() {
+      let (address, word) = find(&|word| {
+        (word & 0x9f00_0000 == 0x1000_0000 && aarch64_register(word, 0) == base_register)
+          || (word & 0x3f20_0c00 == 0x3820_0800 && aarch64_register(word, 0) == index_register)
+      })?;
+      if word & 0x9f00_0000 == 0x1000_0000 {
+        let offset = sign_extend(((word >> 5) & 0x7_ffff) << 2 | (word >> 29) & 3, 21);
+        adr = Some(address.wrapping_add(offset as u64));
+      } else {
+        load = Some(word);
+        if base_register == aarch64_register(word, 5) {
+          break;
+        }
+      }
+    }
+    (load?, adr)
+  };
+  let size = 1u8 << (load_address >> 30);
+  let load_signed = (load_address >> 22) & 3 >= 2;
+  let table_register = aarch64_register(load_address, 5);
+  let (_, lo12) = find(&|word| word & 0xff80_0000 == 0x9100_0000 && aarch64_register(word, 0) == table_register)?;
+  let source = aarch64_register(lo12, 5);
+  let (page_address, adrp) = find(&|word| word & 0x9f00_0000 == 0x9000_0000 && aarch64_register(word, 0) == source)?;
+  let page = sign_extend(((adrp >> 5) & 0x7_ffff) << 2 | (adrp >> 29) & 3, 21) << 12;
+  let table = (page_address & !0xfff).wrapping_add(page as u64).wrapping_add(((lo12 >> 10) & 0xfff) as u64);
+  let base = match base {
+    Some(base) => base,
+    None if base_register == table_register => table,
+    None => return None,
+  };
+  let signed = extend_signed.unwrap_or(load_signed);
+  Some((table, size, JumpTableEntries::Relative { base, shift: shift as u8, signed }))
+}
+
+impl Elf {
+  //Follows the function's branches from its start, splitting it into basic blocks. Indirect
+  //branches that load their target from a switch table get its cases as successors; the
+  //table is found from the instructions leading to the branch (x86 jmp *table(,%rax,8),
+  //x86-64 PIC lea/movslq/add, AArch64 adrp/add/ldr/adr/add), the case count from the bounds
+  //check before it. x86, x86-64 and AArch64.
+  pub fn control_flow_graph(&self, function: u64, size: u64) -> io::Result<ControlFlowGraph> {
+    let machine = self.header.description.machine;
+    if !matches!(machine, EM_X86_64 | EM_386 | EM_AARCH64) {
+      return Err(io::Error::new(ErrorKind::Unsupported, format!("no control flow decoding for machine {}", machine)));
+    }
+    let section = self.section_headers.iter()
+      .find(|section| section.flags & SHF_EXECINSTR != 0 && section.section_type == SHT_PROGBITS && section.address <= function && function - section.address < section.size)
+      .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, format!("{:#x} is not in an executable section", function)))?;
+    let code = Code {
+      elf: self,
+      data: self.section_data(section),
+      address: section.address,
+      big_endian: self.header.identification.endianness == 2,
+      machine,
+      is_64: self.header.identification.class == ELFCLASS64,
+    };
+    let end = function.saturating_add(size.max(1)).min(section.address.saturating_add(section.size));
+    let inside = |address: u64| address >= function && address < end;
+
+    let mut instructions: BTreeMap<u64, Instruction> = BTreeMap::new();
+    let mut leaders: BTreeSet<u64> = BTreeSet::new();
+    let mut jump_tables: Vec<JumpTable> = Vec::new();
+    let mut worklist = vec![function];
+    leaders.insert(function);
+    while let Some(start) = worklist.pop() {
+      if instructions.contains_key(&start) {
+        continue;
+      }
+      let mut history: Vec<Instruction> = Vec::new();
+      let mut address = start;
+      while inside(address) && !instructions.contains_key(&address) {
+        let instruction = match code.decode(address) {
+          Some(instruction) => instruction,
+          None => break,
+        };
+        instructions.insert(address, instruction);
+        history.push(instruction);
+        let next = address.saturating_add(instruction.length);
+        match instruction.flow {
+          Flow::Next => {
+            address = next;
+            continue;
+          },
+          Flow::Jump(target) if inside(target) => {
+            leaders.insert(target);
+            worklist.push(target);
+          },
+          Flow::Conditional(target) => {
+            if inside(target) {
+              leaders.insert(target);
+              worklist.push(target);
+            }
+            leaders.insert(next);
+            address = next;
+            continue;
+          },
+          Flow::IndirectJump => {
+            let found = match machine {
+              EM_AARCH64 => aarch64_jump_table(&code, &history).map(|table| (table, aarch64_bound(&code, &history))),
+              _ => x86_jump_table(&code, &history).map(|table| (table, x86_bound(&code, &history))),
+            };
+            if let Some(((table, entry_size, entries), bound)) = found {
+              let target_of = |index: usize| -> Option<u64> {
+                let at = table.checked_add(index as u64 * entry_size as u64)?;
+                match entries {
+                  JumpTableEntries::Absolute => code.entry(at, entry_size, false).map(|value| value as u64),
+                  JumpTableEntries::Relative { base, shift, signed } => code.entry(at, entry_size, signed).map(|value| base.wrapping_add((value << shift) as u64)),
+                }
+              };
+              let targets: Vec<u64> = match bound {
+                Some(count) => (0..count).map_while(target_of).collect(),
+                None => (0..MAX_CASES).map_while(|index| target_of(index).filter(|&target| inside(target))).collect(),
+              };
+              if !targets.is_empty() {
+                for &target in targets.iter().filter(|&&target| inside(target)) {
+                  leaders.insert(target);
+                  worklist.push(target);
+                }
+                jump_tables.push(JumpTable { branch: address, table, entry_size, entries, bounded: bound.is_some(), targets });
+              }
+            }
+          },
+          _ => {},
+        }
+        break;
+      }
+    }
+
+    let mut blocks: Vec<BasicBlock> = Vec::new();
+    let mut current: Option<(u64, u64)> = None;
+    for (&address, instruction) in &instructions {
+      let start = match current {
+        Some((start, block_end)) if block_end == address && !leaders.contains(&address) => start,
+        Some((start, block_end)) => {
+          let successors = if block_end == address { vec![address] } else { Vec::new() };
+          let exit = if successors.is_empty() { BlockExit::Trap } else { BlockExit::Fallthrough };
+          blocks.push(BasicBlock { start, end: block_end, exit, successors });
+          address
+        },
+        None => address,
+      };
+      let next = address.saturating_add(instruction.length);
+      let (exit, successors) = match instruction.flow {
+        Flow::Next => {
+          current = Some((start, next));
+          continue;
+        },
+        Flow::Jump(target) if inside(target) => (BlockExit::Jump, vec![target]),
+        Flow::Jump(target) => (BlockExit::TailCall(target), Vec::new()),
+        Flow::Conditional(target) => {
+          let mut successors: Vec<u64> = Some(target).filter(|&target| inside(target)).into_iter().collect();
+          if instructions.contains_key(&next) && target != next {
+            successors.push(next);
+          }
+          (BlockExit::Conditional, successors)
+        },
+        Flow::IndirectJump => match jump_tables.iter().position(|table| table.branch == address) {
+          Some(index) => {
+            let mut successors = Vec::new();
+            for &target in jump_tables[index].targets.iter().filter(|&&target| inside(target)) {
+              if !successors.contains(&target) {
+                successors.push(target);
+              }
+            }
+            (BlockExit::Switch(index), successors)
+          },
+          None => (BlockExit::Indirect, Vec::new()),
+        },
+        Flow::Return => (BlockExit::Return, Vec::new()),
+        Flow::Trap => (BlockExit::Trap, Vec::new()),
+      };
+      blocks.push(BasicBlock { start, end: next, exit, successors });
+      current = None;
+    }
+    if let Some((start, block_end)) = current {
+      blocks.push(BasicBlock { start, end: block_end, exit: BlockExit::Trap, successors: Vec::new() });
+    }
+    Ok(ControlFlowGraph { function, size, blocks, jump_tables })
+  }
+
+  //The jump tables of every sized function symbol, by branch address.
+  pub fn jump_tables(&self) -> Vec<JumpTable> {
+    let mut functions: Vec<(u64, u64)> = self.symbol_table().iter().chain(self.dynamic_symbol_table())
+      .filter(|symbol| symbol.symbol_type == STT_FUNC && !symbol.is_undefined() && symbol.size > 0)
+      .map(|symbol| (symbol.value, symbol.size))
+      .collect();
+    functions.sort_unstable();
+    functions.dedup_by_key(|function| function.0);
+    let mut tables: Vec<JumpTable> = functions.iter()
+      .filter_map(|&(function, size)| self.control_flow_graph(function, size).ok())
+      .flat_map(|graph| graph.jump_tables)
+      .collect();
+    tables.sort_by_key(|table| table.branch);
+    tables.dedup_by_key(|table| table.branch);
+    tables
+  }
+}
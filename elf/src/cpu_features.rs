@@ -0,0 +1,459 @@
+use byteorder::{ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::Elf;
+
+//ISA extensions seen in code, ordered roughly by when CPUs gained them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CpuFeature {
+  Sse3,
+  Ssse3,
+  Sse41,
+  Sse42,
+  Popcnt,
+  Cmpxchg16b,
+  Avx,
+  Avx2,
+  Fma,
+  F16c,
+  Bmi1,
+  Bmi2,
+  Lzcnt,
+  Movbe,
+  Avx512,
+  Crc32,
+  Aes,
+  Sha,
+  Lse,
+  Sve,
+  Sme,
+}
+
+impl CpuFeature {
+  //the name -march/-mcpu use for the extension
+  pub fn name(&self) -> &'static str {
+    match self {
+      CpuFeature::Sse3 => "sse3",
+      CpuFeature::Ssse3 => "ssse3",
+      CpuFeature::Sse41 => "sse4.1",
+      CpuFeature::Sse42 => "sse4.2",
+      CpuFeature::Popcnt => "popcnt",
+      CpuFeature::Cmpxchg16b => "cx16",
+      CpuFeature::Avx => "avx",
+      CpuFeature::Avx2 => "avx2",
+      CpuFeature::Fma => "fma",
+      CpuFeature::F16c => "f16c",
+      CpuFeature::Bmi1 => "bmi",
+      CpuFeature::Bmi2 => "bmi2",
+      CpuFeature::Lzcnt => "lzcnt",
+      CpuFeature::Movbe => "movbe",
+      CpuFeature::Avx512 => "avx512f",
+      CpuFeature::Crc32 => "crc",
+      CpuFeature::Aes => "aes",
+      CpuFeature::Sha => "sha2",
+      CpuFeature::Lse => "lse",
+      CpuFeature::Sve => "sve",
+      CpuFeature::Sme => "sme",
+    }
+  }
+
+  //x86-64 psABI microarchitecture level that includes the extension
+  pub fn x86_64_level(&self) -> Option<u8> {
+    match self {
+      CpuFeature::Sse3 | CpuFeature::Ssse3 | CpuFeature::Sse41 | CpuFeature::Sse42 | CpuFeature::Popcnt | CpuFeature::Cmpxchg16b => Some(2),
+      CpuFeature::Avx | CpuFeature::Avx2 | CpuFeature::Fma | CpuFeature::F16c | CpuFeature::Bmi1 | CpuFeature::Bmi2 | CpuFeature::Lzcnt | CpuFeature::Movbe => Some(3),
+      CpuFeature::Avx512 => Some(4),
+      _ => None,
+    }
+  }
+}
+
+pub struct CpuRequirements {
+  pub machine: u16,
+  pub class: u8,
+  //every extension found and the first address using it
+  pub used: Vec<(CpuFeature, u64)>,
+  //GNU_PROPERTY_X86_ISA_1_NEEDED and _USED bitmasks
+  pub isa_needed: Option<u32>,
+  pub isa_used: Option<u32>,
+  //GNU_PROPERTY_X86_FEATURE_1_AND or GNU_PROPERTY_AARCH64_FEATURE_1_AND
+  pub feature_and: Option<u32>,
+}
+
+impl CpuRequirements {
+  pub fn uses(&self, feature: CpuFeature) -> bool {
+    self.used.iter().any(|&(used, _)| used == feature)
+  }
+
+  //The lowest x86-64 level that runs the binary: the highest of what the code uses and
+  //what the ISA_1_NEEDED property asks for.
+  pub fn x86_64_level(&self) -> u8 {
+    let from_code = self.used.iter().filter_map(|(feature, _)| feature.x86_64_level()).max().unwrap_or(1);
+    let from_note = match self.isa_needed {
+      Some(bits) if bits & GNU_PROPERTY_X86_ISA_1_V4 != 0 => 4,
+      Some(bits) if bits & GNU_PROPERTY_X86_ISA_1_V3 != 0 => 3,
+      Some(bits) if bits & GNU_PROPERTY_X86_ISA_1_V2 != 0 => 2,
+      _ => 1,
+    };
+    from_code.max(from_note)
+  }
+
+  //-march style name of the minimum CPU, x86-64-v3 or armv8.1-a+sve. Extensions only used
+  //behind an ifunc or a cpuid check still count, the report is an upper bound for libraries
+  //that dispatch at runtime.
+  pub fn minimum_cpu(&self) -> Option<String> {
+    match (self.machine, self.class) {
+      (EM_X86_64, 2) => Some(match self.x86_64_level() {
+        1 => "x86-64".to_string(),
+        level => format!("x86-64-v{}", level),
+      }),
+      (EM_386, _) => {
+        let mut name = "i686".to_string();
+        for (feature, _) in &self.used {
+          name.push('+');
+          name.push_str(feature.name());
+        }
+        Some(name)
+      },
+      (EM_AARCH64, _) => {
+        let mut name = if self.uses(CpuFeature::Lse) { "armv8.1-a" } else { "armv8-a" }.to_string();
+        for (feature, _) in &self.used {
+          if *feature != CpuFeature::Lse {
+            name.push('+');
+            name.push_str(feature.name());
+          }
+        }
+        Some(name)
+      },
+      _ => None,
+    }
+  }
+}
+
+impl Elf {
+  //Sweeps every function of the executable sections, x86 code with a length decoder and
+  //fixed width code one word at a time.
+  pub fn cpu_requirements(&self) -> CpuRequirements {
+    let machine = self.header.description.machine;
+    let class = self.header.identification.class;
+    let mut used: Vec<(CpuFeature, u64)> = Vec::new();
+    let mut record = |feature: CpuFeature, address: u64| {
+      match used.iter_mut().find(|(used, _)| *used == feature) {
+        Some(entry) => entry.1 = entry.1.min(address),
+        None => used.push((feature, address)),
+      }
+    };
+
+    let mut starts: Vec<u64> = self.symbol_table().iter().chain(self.dynamic_symbol_table())
+      .filter(|symbol| symbol.symbol_type == STT_FUNC && !symbol.is_undefined())
+      .map(|symbol| symbol.value & !1)
+      .collect();
+    starts.sort_unstable();
+    starts.dedup();
+    for section in &self.section_headers {
+      if section.section_type != SHT_PROGBITS || section.flags & SHF_EXECINSTR == 0 {
+        continue;
+      }
+      let data = self.section_data(section);
+      let end = match section.address.checked_add(data.len() as u64) {
+        Some(end) => end,
+        None => continue,
+      };
+      //restart the sweep at every function so padding and data between functions can not
+      //desynchronize the decoder for long
+      let mut bounds: Vec<u64> = vec![section.address];
+      bounds.extend(starts.iter().copied().filter(|&start| start > section.address && start < end));
+      bounds.push(end);
+      for range in bounds.windows(2) {
+        let code = &data[(range[0] - section.address) as usize..(range[1] - section.address) as usize];
+        match machine {
+          EM_X86_64 | EM_386 => scan_x86(code, range[0], machine == EM_X86_64, &mut record),
+          //A64 instructions are little-endian in big-endian images too
+          EM_AARCH64 => scan_aarch64(code, range[0], &mut record),
+          _ => {},
+        }
+      }
+    }
+    used.sort();
+
+    let (isa_needed, isa_used, feature_and) = match machine {
+      EM_X86_64 | EM_386 => (
+        self.gnu_property(GNU_PROPERTY_X86_ISA_1_NEEDED),
+        self.gnu_property(GNU_PROPERTY_X86_ISA_1_USED),
+        self.gnu_property(GNU_PROPERTY_X86_FEATURE_1_AND),
+      ),
+      EM_AARCH64 => (None, None, self.gnu_property(GNU_PROPERTY_AARCH64_FEATURE_1_AND)),
+      _ => (None, None, None),
+    };
+    CpuRequirements { machine, class, used, isa_needed, isa_used, feature_and }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum X86Encoding {
+  Legacy,
+  Vex,
+  Evex,
+}
+
+pub(crate) struct X86Instruction {
+  pub(crate) length: usize,
+  pub(crate) encoding: X86Encoding,
+  //0 one byte opcodes, 1 0F, 2 0F38, 3 0F3A, EVEX maps 5 and 6 as encoded
+  pub(crate) map: u8,
+  pub(crate) opcode: u8,
+  //66, F3 or F2 selecting the instruction, VEX/EVEX pp mapped back to the prefix byte
+  pub(crate) prefix: u8,
+  //the REX prefix, 0 without one
+  pub(crate) rex: u8,
+  pub(crate) rex_w: bool,
+  //VEX.L, 256-bit operands
+  pub(crate) long: bool,
+  pub(crate) modrm: Option<u8>,
+  //where the ModRM byte is, the SIB byte and displacement follow it
+  pub(crate) modrm_offset: usize,
+}
+
+//displacement and SIB bytes after a ModRM byte, the ModRM byte included
+fn modrm_length(code: &[u8], position: usize, address_16: bool) -> Option<usize> {
+  let modrm = *code.get(position)?;
+  let (mode, rm) = (modrm >> 6, modrm & 7);
+  if address_16 {
+    return Some(1 + match (mode, rm) {
+      (0, 6) | (2, _) => 2,
+      (1, _) => 1,
+      _ => 0,
+    });
+  }
+  let mut length = 1;
+  if mode != 3 && rm == 4 {
+    let sib = *code.get(position + 1)?;
+    length += 1;
+    if mode == 0 && sib & 7 == 5 {
+      length += 4;
+    }
+  }
+  length += match (mode, rm) {
+    (0, 5) | (2, _) => 4,
+    (1, _) => 1,
+    _ => 0,
+  };
+  Some(length)
+}
+
+pub(crate) fn decode_x86(code: &[u8], is_64: bool) -> Option<X86Instruction> {
+  let mut position = 0;
+  let mut operand_16 = false;
+  let mut address_override = false;
+  let mut prefix = 0;
+  loop {
+    match *code.get(position)? {
+      0x66 => {
+        operand_16 = true;
+        if prefix == 0 {
+          prefix = 0x66;
+        }
+      },
+      byte @ (0xf2 | 0xf3) => prefix = byte,
+      0x67 => address_override = true,
+      0xf0 | 0x26 | 0x2e | 0x36 | 0x3e | 0x64 | 0x65 => {},
+      _ => break,
+    }
+    position += 1;
+    if position >= 15 {
+      return None;
+    }
+  }
+  let mut rex_w = false;
+  let mut rex = 0;
+  if is_64 && (0x40..=0x4f).contains(code.get(position)?) {
+    rex = code[position];
+    rex_w = code[position] & 8 != 0;
+    position += 1;
+  }
+  let address_16 = !is_64 && address_override;
+  let immediate_z = if operand_16 { 2 } else { 4 };
+  let byte = *code.get(position)?;
+
+  //outside 64-bit mode C4, C5 and 62 are LES, LDS and BOUND unless a register form follows
+  if matches!(byte, 0xc4 | 0xc5 | 0x62) && (is_64 || *code.get(position + 1)? >> 6 == 3) {
+    let (encoding, map, last, length) = match byte {
+      0xc5 => (X86Encoding::Vex, 1, *code.get(position + 1)?, 2),
+      0xc4 => (X86Encoding::Vex, *code.get(position + 1)? & 0x1f, *code.get(position + 2)?, 3),
+      _ => (X86Encoding::Evex, *code.get(position + 1)? & 7, *code.get(position + 2)?, 4),
+    };
+    if byte != 0xc5 {
+      rex_w = last & 0x80 != 0;
+    }
+    let long = encoding == X86Encoding::Vex && last & 4 != 0;
+    position += length;
+    let opcode = *code.get(position)?;
+    position += 1;
+    let has_modrm = !(encoding == X86Encoding::Vex && map == 1 && opcode == 0x77);
+    let modrm = if has_modrm { Some(*code.get(position)?) } else { None };
+    let modrm_offset = position;
+    if has_modrm {
+      position += modrm_length(code, position, address_16)?;
+    }
+    if map == 3 || (map == 1 && matches!(opcode, 0x70..=0x73 | 0xc2 | 0xc4..=0xc6)) {
+      position += 1;
+    }
+    let prefix = [0, 0x66, 0xf3, 0xf2][(last & 3) as usize];
+    return (position <= code.len()).then_some(X86Instruction { length: position, encoding, map, opcode, prefix, rex: 0, rex_w, long, modrm, modrm_offset });
+  }
+
+  position += 1;
+  let (map, opcode, has_modrm, immediate) = if byte == 0x0f {
+    let second = *code.get(position)?;
+    position += 1;
+    match second {
+      0x38 => {
+        let opcode = *code.get(position)?;
+        position += 1;
+        (2, opcode, true, 0)
+      },
+      0x3a => {
+        let opcode = *code.get(position)?;
+        position += 1;
+        (3, opcode, true, 1)
+      },
+      0x0f => (1, second, true, 1),
+      0x70..=0x73 | 0xa4 | 0xac | 0xba | 0xc2 | 0xc4..=0xc6 => (1, second, true, 1),
+      0x80..=0x8f => (1, second, false, immediate_z),
+      0x05..=0x0b | 0x0e | 0x30..=0x37 | 0x77 | 0xa0..=0xa2 | 0xa8..=0xaa | 0xc8..=0xcf => (1, second, false, 0),
+      _ => (1, second, true, 0),
+    }
+  } else {
+    let (has_modrm, immediate) = match byte {
+      //ALU operations, the segment prefixes never get here
+      0x00..=0x3f => match byte & 7 {
+        0..=3 => (true, 0),
+        4 => (false, 1),
+        5 => (false, immediate_z),
+        _ if is_64 => return None,
+        _ => (false, 0),
+      },
+      0x40..=0x61 => (false, 0),
+      0x62 | 0x63 | 0xc4 | 0xc5 => (true, 0),
+      0x68 => (false, immediate_z),
+      0x69 => (true, immediate_z),
+      0x6a => (false, 1),
+      0x6b => (true, 1),
+      0x6c..=0x6f => (false, 0),
+      0x70..=0x7f => (false, 1),
+      0x80 | 0x82 | 0x83 => (true, 1),
+      0x81 => (true, immediate_z),
+      0x84..=0x8f => (true, 0),
+      0x9a if !is_64 => (false, immediate_z + 2),
+      0x90..=0x9f => (false, 0),
+      0xa0..=0xa3 => (false, match (is_64, address_override) {
+        (true, false) => 8,
+        (true, true) | (false, false) => 4,
+        (false, true) => 2,
+      }),
+      0xa8 => (false, 1),
+      0xa9 => (false, immediate_z),
+      0xa4..=0xaf => (false, 0),
+      0xb0..=0xb7 => (false, 1),
+      0xb8..=0xbf => (false, if rex_w { 8 } else { immediate_z }),
+      0xc0 | 0xc1 | 0xc6 => (true, 1),
+      0xc7 => (true, immediate_z),
+      0xc2 | 0xca => (false, 2),
+      0xc8 => (false, 3),
+      0xcd | 0xd4 | 0xd5 => (false, 1),
+      0xc3 | 0xc9 | 0xcb | 0xcc | 0xce | 0xcf | 0xd6 | 0xd7 => (false, 0),
+      0xd0..=0xd3 | 0xd8..=0xdf => (true, 0),
+      0xe0..=0xe7 | 0xeb => (false, 1),
+      0xe8 | 0xe9 => (false, immediate_z),
+      0xea if !is_64 => (false, immediate_z + 2),
+      0xf6 => (true, if code.get(position)? & 0x38 <= 0x08 { 1 } else { 0 }),
+      0xf7 => (true, if code.get(position)? & 0x38 <= 0x08 { immediate_z } else { 0 }),
+      0xfe | 0xff => (true, 0),
+      0xec..=0xef | 0xf1 | 0xf4 | 0xf5 | 0xf8..=0xfd => (false, 0),
+      _ => return None,
+    };
+    (0, byte, has_modrm, immediate)
+  };
+  let modrm = if has_modrm { Some(*code.get(position)?) } else { None };
+  let modrm_offset = position;
+  if has_modrm {
+    position += modrm_length(code, position, address_16)?;
+  }
+  position += immediate;
+  (position <= code.len()).then_some(X86Instruction { length: position, encoding: X86Encoding::Legacy, map, opcode, prefix, rex, rex_w, long: false, modrm, modrm_offset })
+}
+
+fn x86_feature(instruction: &X86Instruction) -> Option<CpuFeature> {
+  let reg = instruction.modrm.map(|modrm| (modrm >> 3) & 7);
+  let (map, opcode, prefix) = (instruction.map, instruction.opcode, instruction.prefix);
+  match instruction.encoding {
+    X86Encoding::Evex => Some(CpuFeature::Avx512),
+    X86Encoding::Vex => Some(match (map, opcode, prefix) {
+      //VEX encoded general purpose register instructions are not AVX
+      (2, 0xf2, 0) | (2, 0xf3, 0) | (2, 0xf7, 0) => CpuFeature::Bmi1,
+      (2, 0xf5, _) | (2, 0xf6, 0xf2) | (2, 0xf7, _) | (3, 0xf0, 0xf2) => CpuFeature::Bmi2,
+      (2, 0x96..=0x9f, 0x66) | (2, 0xa6..=0xaf, 0x66) | (2, 0xb6..=0xbf, 0x66) => CpuFeature::Fma,
+      (2, 0x13, 0x66) | (3, 0x1d, 0x66) => CpuFeature::F16c,
+      (2, 0x16 | 0x36 | 0x45..=0x47 | 0x58..=0x5a | 0x78 | 0x79 | 0x8c | 0x8e | 0x90..=0x93, 0x66) => CpuFeature::Avx2,
+      (3, 0x00..=0x02 | 0x38 | 0x39 | 0x46, 0x66) => CpuFeature::Avx2,
+      //256-bit integer operations
+      (1, 0x60..=0x6d | 0x71..=0x76 | 0xd1..=0xfe, 0x66) if instruction.long => CpuFeature::Avx2,
+      _ => CpuFeature::Avx,
+    }),
+    X86Encoding::Legacy => match (map, opcode, prefix) {
+      (1, 0xb8, 0xf3) => Some(CpuFeature::Popcnt),
+      (1, 0xbd, 0xf3) => Some(CpuFeature::Lzcnt),
+      (1, 0xbc, 0xf3) => Some(CpuFeature::Bmi1),
+      (1, 0xc7, _) if reg == Some(1) && instruction.rex_w => Some(CpuFeature::Cmpxchg16b),
+      (1, 0x7c | 0x7d, 0x66 | 0xf2) | (1, 0xd0, 0x66 | 0xf2) | (1, 0xf0, 0xf2) | (1, 0x12, 0xf2 | 0xf3) | (1, 0x16, 0xf3) => Some(CpuFeature::Sse3),
+      (2, 0xf0 | 0xf1, 0xf2) => Some(CpuFeature::Sse42),
+      (2, 0xf0 | 0xf1, 0 | 0x66) => Some(CpuFeature::Movbe),
+      (2, 0x00..=0x0b | 0x1c..=0x1e, 0 | 0x66) | (3, 0x0f, 0 | 0x66) => Some(CpuFeature::Ssse3),
+      (2, 0x37, 0x66) | (3, 0x60..=0x63, 0x66) => Some(CpuFeature::Sse42),
+      (2, 0x10 | 0x14 | 0x15 | 0x17 | 0x20..=0x25 | 0x28..=0x2b | 0x30..=0x35 | 0x38..=0x41, 0x66) => Some(CpuFeature::Sse41),
+      (3, 0x08..=0x0e | 0x14..=0x17 | 0x20..=0x22 | 0x40..=0x42, 0x66) => Some(CpuFeature::Sse41),
+      (2, 0xc8..=0xcd, 0) | (3, 0xcc, 0) => Some(CpuFeature::Sha),
+      (2, 0xdb..=0xdf, 0x66) | (3, 0xdf, 0x66) => Some(CpuFeature::Aes),
+      _ => None,
+    },
+  }
+}
+
+fn scan_x86<F: FnMut(CpuFeature, u64)>(code: &[u8], address: u64, is_64: bool, record: &mut F) {
+  let mut position = 0;
+  while position < code.len() {
+    match decode_x86(&code[position..], is_64) {
+      Some(instruction) => {
+        if let Some(feature) = x86_feature(&instruction) {
+          record(feature, address + position as u64);
+        }
+        position += instruction.length;
+      },
+      None => position += 1,
+    }
+  }
+}
+
+fn scan_aarch64<F: FnMut(CpuFeature, u64)>(code: &[u8], address: u64, record: &mut F) {
+  for (index, word) in code.chunks_exact(4).map(LittleEndian::read_u32).enumerate() {
+    //top level A64 classes: op1 0010 is SVE, op0 1 with op1 0000 is SME
+    let feature = if (word >> 25) & 0xf == 0b0010 {
+      Some(CpuFeature::Sve)
+    } else if word >> 31 == 1 && (word >> 25) & 0xf == 0 {
+      Some(CpuFeature::Sme)
+    } else if word & 0x3f20_0c00 == 0x3820_0000 || word & 0x3fa0_7c00 == 0x08a0_7c00 {
+      //LDADD/SWP family and CAS
+      Some(CpuFeature::Lse)
+    } else if word & 0x7fe0_f000 == 0x1ac0_4000 {
+      Some(CpuFeature::Crc32)
+    } else if word & 0xffff_cc00 == 0x4e28_4800 {
+      Some(CpuFeature::Aes)
+    } else if word & 0xffe0_8c00 == 0x5e00_0000 || word & 0xffff_cc00 == 0x5e28_0800 {
+      Some(CpuFeature::Sha)
+    } else {
+      None
+    };
+    if let Some(feature) = feature {
+      record(feature, address + index as u64 * 4);
+    }
+  }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use crate::conflicts::LINKER_DEFINED;
+use crate::consts::*;
+use crate::workspace::Workspace;
+
+//looked up by name rather than bound through a relocation
+const LOOKED_UP_BY_NAME: &[&str] = &["_IO_stdin_used"];
+
+//An exported symbol no other loaded object binds to.
+#[derive(Clone, Debug)]
+pub struct DeadExport {
+  pub object_index: usize,
+  pub name: Arc<str>,
+  pub version: Option<String>,
+  pub symbol_type: u8,
+  pub size: u64,
+}
+
+impl Workspace {
+  //Exports of each object that no other object of the workspace references, candidates for
+  //hidden visibility. References from the object itself do not count: they bind locally
+  //once the symbol is hidden. Call resolve() first. Symbols only reached through dlsym, or
+  //from objects that are not loaded, look dead too. name@VERSION compatibility symbols
+  //are left out, they are there for binaries linked against an older version.
+  pub fn dead_exports(&self) -> Vec<DeadExport> {
+    let bound: HashSet<(usize, &str)> = self.bindings.iter()
+      .filter_map(|binding| binding.definition.filter(|&(object, _)| object != binding.object_index).map(|(object, _)| (object, &*binding.name)))
+      .collect();
+    let mut dead = Vec::new();
+    for (object_index, object) in self.objects.iter().enumerate() {
+      let versions = object.elf.symbol_versions();
+      for (symbol_index, symbol) in object.elf.dynamic_symbol_table().iter().enumerate() {
+        if !symbol.is_exported() || symbol.name.is_empty() || LINKER_DEFINED.contains(&&*symbol.name) || LOOKED_UP_BY_NAME.contains(&&*symbol.name) {
+          continue;
+        }
+        let version = versions.get(symbol_index).cloned().flatten();
+        if version.as_ref().is_some_and(|version| version.hidden || (symbol.section_index == SHN_ABS && *version.name == *symbol.name)) {
+          continue;
+        }
+        if !bound.contains(&(object_index, &*symbol.name)) {
+          dead.push(DeadExport {
+            object_index,
+            name: symbol.name.clone(),
+            version: version.map(|version| version.name),
+            symbol_type: symbol.symbol_type,
+            size: symbol.size,
+          });
+        }
+      }
+    }
+    dead
+  }
+
+  //A version script for relinking the object `object_index` with its dead exports hidden.
+  //Unversioned objects get an anonymous node keeping everything else global. Versioned ones
+  //get their version nodes back listing the exports still used, which replaces the script
+  //they were linked with; name@VERSION compatibility symbols still need their .symver.
+  pub fn hiding_version_script(&self, object_index: usize) -> String {
+    let dead: HashSet<Arc<str>> = self.dead_exports().into_iter()
+      .filter(|export| export.object_index == object_index)
+      .map(|export| export.name)
+      .collect();
+    let mut dead_names: Vec<&str> = dead.iter().map(|name| &**name).collect();
+    dead_names.sort_unstable();
+    let elf = &self.objects[object_index].elf;
+    let definitions: Vec<_> = elf.version_definitions().into_iter().filter(|definition| !definition.is_base()).collect();
+    let mut script = String::new();
+    if definitions.is_empty() {
+      script.push_str("{\n  global:\n    *;\n  local:\n");
+      for name in dead_names {
+        script.push_str(&format!("    {};\n", name));
+      }
+      script.push_str("};\n");
+      return script;
+    }
+    let versions = elf.symbol_versions();
+    for (position, definition) in definitions.iter().enumerate() {
+      let mut live: Vec<&str> = elf.dynamic_symbol_table().iter().enumerate()
+        .filter(|(symbol_index, symbol)| {
+          let version = versions.get(*symbol_index).cloned().flatten();
+          let in_node = match &version {
+            Some(version) => !version.hidden && version.file.is_none() && version.name == definition.name(),
+            //unversioned exports go in the first node
+            None => position == 0,
+          };
+          in_node && symbol.is_exported() && !symbol.name.is_empty() && &*symbol.name != definition.name() && !dead.contains(&symbol.name)
+        })
+        .map(|(_, symbol)| &*symbol.name)
+        .collect();
+      live.sort_unstable();
+      live.dedup();
+      script.push_str(&format!("{} {{\n", definition.name()));
+      if !live.is_empty() {
+        script.push_str("  global:\n");
+        for name in live {
+          script.push_str(&format!("    {};\n", name));
+        }
+      }
+      if position == 0 {
+        script.push_str("  local:\n    *;\n");
+      }
+      match definition.names.get(1..) {
+        Some(parents) if !parents.is_empty() => script.push_str(&format!("}} {};\n", parents.join(" "))),
+        _ => script.push_str("};\n"),
+      };
+    }
+    script
+  }
+}
@@ -0,0 +1,473 @@
+use std::collections::HashMap;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::elf::{read_str, Elf};
+use crate::leb128::read_uleb128;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum IndexedSymbolKind {
+  None,
+  Type,
+  Variable,
+  Function,
+  Other,
+}
+
+pub struct IndexedUnit {
+  pub offset: u64,
+  pub length: u64,
+}
+
+pub struct IndexedTypeUnit {
+  pub offset: u64,
+  pub type_offset: u64,
+  pub signature: u64,
+}
+
+pub struct IndexedRange {
+  pub low: u64,
+  pub high: u64,
+  pub unit_index: u32,
+}
+
+#[derive(Clone, Copy)]
+pub struct GdbIndexEntry {
+  //index into compilation_units followed by type_units
+  pub unit_index: u32,
+  pub kind: IndexedSymbolKind,
+  pub is_static: bool,
+}
+
+//Always little-endian, whatever the byte order of the object.
+pub struct GdbIndex<'a> {
+  pub version: u32,
+  pub compilation_units: Vec<IndexedUnit>,
+  pub type_units: Vec<IndexedTypeUnit>,
+  pub address_ranges: Vec<IndexedRange>,
+  //version 9 shortcut table: DW_LANG of the main function and its name
+  pub main_language: Option<u32>,
+  pub main_name: Option<&'a str>,
+  symbol_table: &'a [u8],
+  constant_pool: &'a [u8],
+}
+
+//version 4 hashes names as they are, later versions hash them lowercased
+fn gdb_index_hash(version: u32, name: &str) -> u32 {
+  name.bytes().fold(0u32, |hash, byte| {
+    let byte = if version >= 5 { byte.to_ascii_lowercase() } else { byte };
+    hash.wrapping_mul(67).wrapping_add(byte as u32).wrapping_sub(113)
+  })
+}
+
+impl<'a> GdbIndex<'a> {
+  pub fn parse(data: &'a [u8]) -> Option<GdbIndex<'a>> {
+    let header = |index: usize| data.get(index * 4..index * 4 + 4).map(LittleEndian::read_u32);
+    let version = header(0)?;
+    if !(4..=9).contains(&version) {
+      return None;
+    }
+    //version 9 adds the shortcut table before the constant pool
+    let mut offsets = vec![header(1)?, header(2)?, header(3)?, header(4)?, header(5)?];
+    if version >= 9 {
+      offsets.push(header(6)?);
+    }
+    let area = |index: usize| data.get(offsets[index] as usize..*offsets.get(index + 1).unwrap_or(&(data.len() as u32)) as usize);
+    let constant_pool = data.get(*offsets.last()? as usize..)?;
+    let compilation_units = area(0)?.chunks_exact(16)
+      .map(|entry| IndexedUnit { offset: LittleEndian::read_u64(&entry[..8]), length: LittleEndian::read_u64(&entry[8..]) })
+      .collect();
+    let type_units = area(1)?.chunks_exact(24)
+      .map(|entry| IndexedTypeUnit {
+        offset: LittleEndian::read_u64(&entry[..8]),
+        type_offset: LittleEndian::read_u64(&entry[8..16]),
+        signature: LittleEndian::read_u64(&entry[16..]),
+      })
+      .collect();
+    let address_ranges = area(2)?.chunks_exact(20)
+      .map(|entry| IndexedRange {
+        low: LittleEndian::read_u64(&entry[..8]),
+        high: LittleEndian::read_u64(&entry[8..16]),
+        unit_index: LittleEndian::read_u32(&entry[16..]),
+      })
+      .collect();
+    let (main_language, main_name) = match area(4).filter(|_| version >= 9) {
+      Some(shortcuts) if shortcuts.len() >= 8 => {
+        let name_offset = LittleEndian::read_u32(&shortcuts[4..8]);
+        //offset 0 when the producer found no main
+        (Some(LittleEndian::read_u32(&shortcuts[..4])), Some(name_offset).filter(|&offset| offset != 0).and_then(|offset| read_str(constant_pool, offset as usize)))
+      },
+      _ => (None, None),
+    };
+    Some(GdbIndex {
+      version,
+      compilation_units,
+      type_units,
+      address_ranges,
+      main_language,
+      main_name,
+      symbol_table: area(3)?,
+      constant_pool,
+    })
+  }
+
+  pub fn lookup(&self, name: &str) -> Vec<GdbIndexEntry> {
+    let slots = self.symbol_table.len() / 8;
+    if slots == 0 || !slots.is_power_of_two() {
+      return Vec::new();
+    }
+    let hash = gdb_index_hash(self.version, name) as usize;
+    let mask = slots - 1;
+    let step = ((hash.wrapping_mul(17)) & mask) | 1;
+    let mut slot = hash & mask;
+    for _ in 0..slots {
+      let (name_offset, vector_offset) = self.slot(slot);
+      if name_offset == 0 && vector_offset == 0 {
+        break;
+      }
+      if read_str(self.constant_pool, name_offset as usize) == Some(name) {
+        return self.entries(vector_offset);
+      }
+      slot = (slot + step) & mask;
+    }
+    Vec::new()
+  }
+
+  pub fn names(&self) -> Vec<(&'a str, Vec<GdbIndexEntry>)> {
+    (0..self.symbol_table.len() / 8)
+      .map(|slot| self.slot(slot))
+      .filter(|&(name_offset, vector_offset)| name_offset != 0 || vector_offset != 0)
+      .filter_map(|(name_offset, vector_offset)| Some((read_str(self.constant_pool, name_offset as usize)?, self.entries(vector_offset))))
+      .collect()
+  }
+
+  pub fn unit_for_address(&self, address: u64) -> Option<&IndexedUnit> {
+    let range = self.address_ranges.iter().find(|range| address >= range.low && address < range.high)?;
+    self.compilation_units.get(range.unit_index as usize)
+  }
+
+  fn slot(&self, slot: usize) -> (u32, u32) {
+    let entry = &self.symbol_table[slot * 8..slot * 8 + 8];
+    (LittleEndian::read_u32(&entry[..4]), LittleEndian::read_u32(&entry[4..]))
+  }
+
+  fn entries(&self, vector_offset: u32) -> Vec<GdbIndexEntry> {
+    let vector = match self.constant_pool.get(vector_offset as usize..) {
+      Some(vector) if vector.len() >= 4 => vector,
+      _ => return Vec::new(),
+    };
+    let count = LittleEndian::read_u32(vector) as usize;
+    vector[4..].chunks_exact(4).take(count)
+      .map(|value| {
+        let value = LittleEndian::read_u32(value);
+        let kind = match (self.version >= 7, (value >> 28) & 0x7) {
+          (false, _) | (true, 0) => IndexedSymbolKind::None,
+          (true, 1) => IndexedSymbolKind::Type,
+          (true, 2) => IndexedSymbolKind::Variable,
+          (true, 3) => IndexedSymbolKind::Function,
+          _ => IndexedSymbolKind::Other,
+        };
+        GdbIndexEntry { unit_index: value & 0x00ff_ffff, kind, is_static: self.version >= 7 && value & 0x8000_0000 != 0 }
+      })
+      .collect()
+  }
+}
+
+pub const DW_IDX_COMPILE_UNIT: u64 = 1;
+pub const DW_IDX_TYPE_UNIT: u64 = 2;
+pub const DW_IDX_DIE_OFFSET: u64 = 3;
+pub const DW_IDX_PARENT: u64 = 4;
+pub const DW_IDX_TYPE_HASH: u64 = 5;
+
+struct NameAbbreviation {
+  tag: u64,
+  attributes: Vec<(u64, u64)>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct NameEntry {
+  pub tag: u64,
+  pub unit_index: Option<u64>,
+  pub type_unit_index: Option<u64>,
+  //relative to the unit
+  pub die_offset: Option<u64>,
+  //offset of the parent entry in the entry pool, None when the entry has no indexed parent
+  pub parent: Option<u64>,
+  pub type_hash: Option<u64>,
+}
+
+impl NameEntry {
+  pub fn kind(&self) -> IndexedSymbolKind {
+    match self.tag {
+      0x2e => IndexedSymbolKind::Function,
+      0x34 => IndexedSymbolKind::Variable,
+      0x02 | 0x04 | 0x13 | 0x16 | 0x17 | 0x24 => IndexedSymbolKind::Type,
+      _ => IndexedSymbolKind::Other,
+    }
+  }
+}
+
+//One name index of .debug_names (DWARF 5), linked objects usually have a single one.
+pub struct DebugNames<'a> {
+  pub version: u16,
+  pub compilation_units: Vec<u64>,
+  pub local_type_units: Vec<u64>,
+  pub foreign_type_units: Vec<u64>,
+  big_endian: bool,
+  offset_size: usize,
+  bucket_count: usize,
+  name_count: usize,
+  buckets: &'a [u8],
+  hashes: &'a [u8],
+  string_offsets: &'a [u8],
+  entry_offsets: &'a [u8],
+  abbreviations: HashMap<u64, NameAbbreviation>,
+  entry_pool: &'a [u8],
+  strings: &'a [u8],
+}
+
+fn debug_names_hash(name: &str) -> u32 {
+  name.bytes().fold(5381u32, |hash, byte| hash.wrapping_mul(33).wrapping_add(byte.to_ascii_lowercase() as u32))
+}
+
+impl<'a> DebugNames<'a> {
+  pub fn parse_all(data: &'a [u8], strings: &'a [u8], big_endian: bool) -> Vec<DebugNames<'a>> {
+    let mut indexes = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+      let result = if big_endian {
+        DebugNames::parse_one::<BigEndian>(data, offset, strings, true)
+      } else {
+        DebugNames::parse_one::<LittleEndian>(data, offset, strings, false)
+      };
+      match result {
+        Some((index, next)) => {
+          indexes.push(index);
+          offset = next;
+        },
+        None => break,
+      }
+    }
+    indexes
+  }
+
+  fn parse_one<E: ByteOrder>(data: &'a [u8], start: usize, strings: &'a [u8], big_endian: bool) -> Option<(DebugNames<'a>, usize)> {
+    let mut offset = start;
+    let mut take = |size: usize| -> Option<&'a [u8]> {
+      let bytes = data.get(offset..offset.checked_add(size)?)?;
+      offset += size;
+      Some(bytes)
+    };
+    let (offset_size, unit_length) = match E::read_u32(take(4)?) {
+      0xffff_ffff => (8, E::read_u64(take(8)?) as usize),
+      length => (4, length as usize),
+    };
+    let end = (start + if offset_size == 8 { 12 } else { 4 }).checked_add(unit_length)?;
+    let version = E::read_u16(take(2)?);
+    take(2)?;
+    let compilation_unit_count = E::read_u32(take(4)?) as usize;
+    let local_type_unit_count = E::read_u32(take(4)?) as usize;
+    let foreign_type_unit_count = E::read_u32(take(4)?) as usize;
+    let bucket_count = E::read_u32(take(4)?) as usize;
+    let name_count = E::read_u32(take(4)?) as usize;
+    let abbreviation_size = E::read_u32(take(4)?) as usize;
+    let augmentation_size = E::read_u32(take(4)?) as usize;
+    take((augmentation_size + 3) & !3)?;
+    let read_offset = |bytes: &[u8]| if offset_size == 8 { E::read_u64(bytes) } else { E::read_u32(bytes) as u64 };
+    let compilation_units = take(compilation_unit_count.checked_mul(offset_size)?)?.chunks_exact(offset_size).map(read_offset).collect();
+    let local_type_units = take(local_type_unit_count.checked_mul(offset_size)?)?.chunks_exact(offset_size).map(read_offset).collect();
+    let foreign_type_units = take(foreign_type_unit_count.checked_mul(8)?)?.chunks_exact(8).map(E::read_u64).collect();
+    let buckets = take(bucket_count.checked_mul(4)?)?;
+    let hashes = if bucket_count > 0 { take(name_count.checked_mul(4)?)? } else { &[] };
+    let string_offsets = take(name_count.checked_mul(offset_size)?)?;
+    let entry_offsets = take(name_count.checked_mul(offset_size)?)?;
+    let abbreviation_table = take(abbreviation_size)?;
+    let entry_pool = data.get(offset..end.min(data.len()))?;
+
+    let mut abbreviations = HashMap::new();
+    let mut position = 0;
+    loop {
+      let code = read_uleb128(abbreviation_table, &mut position)?;
+      if code == 0 {
+        break;
+      }
+      let tag = read_uleb128(abbreviation_table, &mut position)?;
+      let mut attributes = Vec::new();
+      loop {
+        let attribute = read_uleb128(abbreviation_table, &mut position)?;
+        let form = read_uleb128(abbreviation_table, &mut position)?;
+        if attribute == 0 && form == 0 {
+          break;
+        }
+        attributes.push((attribute, form));
+      }
+      abbreviations.insert(code, NameAbbreviation { tag, attributes });
+    }
+
+    let index = DebugNames {
+      version,
+      compilation_units,
+      local_type_units,
+      foreign_type_units,
+      big_endian,
+      offset_size,
+      bucket_count,
+      name_count,
+      buckets,
+      hashes,
+      string_offsets,
+      entry_offsets,
+      abbreviations,
+      entry_pool,
+      strings,
+    };
+    Some((index, end))
+  }
+
+  fn read_u32(&self, bytes: &[u8]) -> u32 {
+    if self.big_endian { BigEndian::read_u32(bytes) } else { LittleEndian::read_u32(bytes) }
+  }
+
+  fn read(&self, bytes: &[u8]) -> u64 {
+    match (bytes.len(), self.big_endian) {
+      (1, _) => bytes[0] as u64,
+      (2, true) => BigEndian::read_u16(bytes) as u64,
+      (2, false) => LittleEndian::read_u16(bytes) as u64,
+      (4, _) => self.read_u32(bytes) as u64,
+      (_, true) => BigEndian::read_u64(bytes),
+      (_, false) => LittleEndian::read_u64(bytes),
+    }
+  }
+
+  fn name_at(&self, index: usize) -> Option<&'a str> {
+    let offset = self.read(&self.string_offsets[index * self.offset_size..(index + 1) * self.offset_size]);
+    read_str(self.strings, offset as usize)
+  }
+
+  fn entries_at(&self, index: usize) -> Vec<NameEntry> {
+    let mut position = self.read(&self.entry_offsets[index * self.offset_size..(index + 1) * self.offset_size]) as usize;
+    let mut entries = Vec::new();
+    while let Some(code) = read_uleb128(self.entry_pool, &mut position) {
+      let abbreviation = match self.abbreviations.get(&code) {
+        Some(abbreviation) if code != 0 => abbreviation,
+        _ => break,
+      };
+      let mut entry = NameEntry { tag: abbreviation.tag, ..Default::default() };
+      for &(attribute, form) in &abbreviation.attributes {
+        let size = match form {
+          0x11 | 0x0b => Some(1),
+          0x12 | 0x05 => Some(2),
+          0x13 | 0x06 => Some(4),
+          0x14 | 0x07 | 0x20 => Some(8),
+          0x19 => Some(0),
+          _ => None,
+        };
+        let value = match size {
+          Some(0) => Some(1),
+          Some(size) => self.entry_pool.get(position..position + size).map(|bytes| {
+            position += size;
+            self.read(bytes)
+          }),
+          None => read_uleb128(self.entry_pool, &mut position),
+        };
+        let value = match value {
+          Some(value) => value,
+          None => return entries,
+        };
+        match attribute {
+          DW_IDX_COMPILE_UNIT => entry.unit_index = Some(value),
+          DW_IDX_TYPE_UNIT => entry.type_unit_index = Some(value),
+          DW_IDX_DIE_OFFSET => entry.die_offset = Some(value),
+          //DW_FORM_flag_present: the parent of the entry is not indexed
+          DW_IDX_PARENT if form == 0x19 => entry.parent = None,
+          DW_IDX_PARENT => entry.parent = Some(value),
+          DW_IDX_TYPE_HASH => entry.type_hash = Some(value),
+          _ => {},
+        };
+      }
+      if entry.unit_index.is_none() && entry.type_unit_index.is_none() && self.compilation_units.len() == 1 {
+        entry.unit_index = Some(0);
+      }
+      entries.push(entry);
+    }
+    entries
+  }
+
+  pub fn lookup(&self, name: &str) -> Vec<NameEntry> {
+    if self.bucket_count == 0 {
+      return (0..self.name_count).filter(|&index| self.name_at(index) == Some(name)).flat_map(|index| self.entries_at(index)).collect();
+    }
+    let hash = debug_names_hash(name);
+    let bucket = hash as usize % self.bucket_count;
+    let first = self.read_u32(&self.buckets[bucket * 4..bucket * 4 + 4]) as usize;
+    if first == 0 {
+      return Vec::new();
+    }
+    let mut entries = Vec::new();
+    for index in first - 1..self.name_count {
+      let name_hash = self.read_u32(&self.hashes[index * 4..index * 4 + 4]);
+      if name_hash as usize % self.bucket_count != bucket {
+        break;
+      }
+      if name_hash == hash && self.name_at(index) == Some(name) {
+        entries.extend(self.entries_at(index));
+      }
+    }
+    entries
+  }
+
+  pub fn names(&self) -> Vec<(&'a str, Vec<NameEntry>)> {
+    (0..self.name_count)
+      .filter_map(|index| Some((self.name_at(index)?, self.entries_at(index))))
+      .collect()
+  }
+}
+
+pub struct IndexedName {
+  //offset of the unit in .debug_info
+  pub unit_offset: u64,
+  //offset of the DIE in .debug_info, when the index records it
+  pub die_offset: Option<u64>,
+  pub kind: IndexedSymbolKind,
+}
+
+impl Elf {
+  pub fn gdb_index(&self) -> Option<GdbIndex<'_>> {
+    GdbIndex::parse(self.section_data(self.section_by_name(".gdb_index")?))
+  }
+
+  pub fn debug_names(&self) -> Vec<DebugNames<'_>> {
+    if self.section_by_name(".debug_names").is_none() {
+      return Vec::new();
+    }
+    let strings = self.debug_section(".debug_str");
+    DebugNames::parse_all(self.debug_section(".debug_names"), strings, self.header.identification.endianness == 2)
+  }
+
+  //Looks a name up in whichever accelerated index the object ships, .debug_names first.
+  pub fn lookup_indexed_name(&self, name: &str) -> Vec<IndexedName> {
+    let mut found = Vec::new();
+    for index in self.debug_names() {
+      for entry in index.lookup(name) {
+        let unit_offset = match entry.unit_index.and_then(|unit| index.compilation_units.get(unit as usize)) {
+          Some(&unit_offset) => unit_offset,
+          None => continue,
+        };
+        found.push(IndexedName {
+          unit_offset,
+          die_offset: entry.die_offset.map(|die_offset| unit_offset + die_offset),
+          kind: entry.kind(),
+        });
+      }
+    }
+    if !found.is_empty() {
+      return found;
+    }
+    if let Some(index) = self.gdb_index() {
+      for entry in index.lookup(name) {
+        if let Some(unit) = index.compilation_units.get(entry.unit_index as usize) {
+          found.push(IndexedName { unit_offset: unit.offset, die_offset: None, kind: entry.kind });
+        }
+      }
+    }
+    found
+  }
+}
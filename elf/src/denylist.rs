@@ -0,0 +1,115 @@
+use std::sync::Arc;
+use crate::call_sites::CallSite;
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::symbol::Symbol;
+
+//C library functions security reviews usually ban: no bounds, shell injection, or racy
+//temporary names.
+pub const DEFAULT_DENYLIST: &[&str] = &[
+  "gets", "strcpy", "strcat", "sprintf", "vsprintf", "system", "popen", "tmpnam", "tempnam", "mktemp", "getwd",
+];
+
+//glibc redirects the C99 and C23 scanf family to these
+const SCANF_PREFIXES: &[&str] = &["__isoc99_", "__isoc23_"];
+
+fn listed_name<'a>(symbol: &str, denylist: &[&'a str]) -> Option<&'a str> {
+  let base = symbol.split('@').next().unwrap_or(symbol);
+  let base = SCANF_PREFIXES.iter().find_map(|prefix| base.strip_prefix(prefix)).unwrap_or(base);
+  denylist.iter().find(|name| **name == base).copied()
+}
+
+//the index of the finding for `symbol`, added if it is new. The symbol table repeats imports
+//with their version, e.g. gets@GLIBC_2.2.5, those are the same finding.
+fn finding(findings: &mut Vec<DenylistFinding>, name: &str, symbol: &Arc<str>, imported: bool) -> usize {
+  let unversioned = symbol.split('@').next().unwrap_or(symbol);
+  match findings.iter().position(|finding| &*finding.symbol == unversioned) {
+    Some(index) => index,
+    None => {
+      findings.push(DenylistFinding { name: name.to_string(), symbol: Arc::from(unversioned), imported, calls: Vec::new() });
+      findings.len() - 1
+    },
+  }
+}
+
+#[derive(Clone, Debug)]
+pub struct DenylistCall {
+  pub site: CallSite,
+  //the function the call is in, when the symbol table covers it
+  pub caller: Option<Arc<str>>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DenylistFinding {
+  //the denylist entry
+  pub name: String,
+  //the symbol that matched it, without a version, e.g. __isoc99_sscanf for sscanf
+  pub symbol: Arc<str>,
+  //false when the function is linked into the file
+  pub imported: bool,
+  //empty when it is only reached through a pointer, or the code could not be decoded
+  pub calls: Vec<DenylistCall>,
+}
+
+impl Elf {
+  //Denylisted functions the file imports, links in or, in a relocatable object, refers to,
+  //with the call sites found for each (see call_sites). Findings come in denylist order.
+  pub fn scan_denylist(&self, denylist: &[&str]) -> Vec<DenylistFinding> {
+    let mut functions: Vec<&Symbol> = self.symbol_table().iter().chain(self.dynamic_symbol_table())
+      .filter(|symbol| !symbol.is_undefined() && symbol.symbol_type == STT_FUNC && symbol.size > 0)
+      .collect();
+    functions.sort_by_key(|symbol| symbol.value);
+    let caller = |address: u64| -> Option<Arc<str>> {
+      let index = functions.partition_point(|symbol| symbol.value <= address);
+      functions[..index].iter().rev().find(|symbol| address - symbol.value < symbol.size).map(|symbol| symbol.name.clone())
+    };
+    let call = |site: CallSite| DenylistCall { caller: caller(site.address), site };
+
+    let mut findings: Vec<DenylistFinding> = Vec::new();
+    if self.header.description.obj_type == ET_REL {
+      let mut sites = Vec::new();
+      for (symbol, site) in self.relocated_call_sites() {
+        if let Some(name) = listed_name(&symbol, denylist) {
+          let imported = self.symbol_table().iter().filter(|candidate| candidate.name == symbol).all(Symbol::is_undefined);
+          sites.push((finding(&mut findings, name, &symbol, imported), site));
+        }
+      }
+      for (index, site) in sites {
+        findings[index].calls.push(call(site));
+      }
+    } else {
+      let plt = self.plt_entries();
+      let slots = self.got_slot_symbols();
+      let mut symbols: Vec<(&str, &Symbol)> = Vec::new();
+      for symbol in self.dynamic_symbol_table().iter().chain(self.symbol_table()) {
+        let is_function = symbol.symbol_type == STT_FUNC || symbol.symbol_type == STT_GNU_IFUNC || (symbol.is_undefined() && symbol.symbol_type == STT_NOTYPE);
+        if let Some(name) = listed_name(&symbol.name, denylist).filter(|_| is_function) {
+          symbols.push((name, symbol));
+        }
+      }
+      let mut targets = Vec::new();
+      for (name, symbol) in symbols {
+        let index = finding(&mut findings, name, &symbol.name, symbol.is_undefined());
+        if !symbol.is_undefined() {
+          findings[index].imported = false;
+        }
+        let stubs: Vec<u64> = plt.iter().filter(|entry| entry.name == symbol.name).map(|entry| entry.address).collect();
+        let got: Vec<u64> = slots.iter().filter(|(_, name)| **name == symbol.name).map(|(&slot, _)| slot).collect();
+        let defined: Vec<u64> = Some(symbol.value).filter(|_| !symbol.is_undefined()).into_iter().collect();
+        targets.push((index, stubs.into_iter().chain(defined).collect::<Vec<u64>>(), got));
+      }
+      for (index, direct, got) in targets {
+        for site in self.call_sites(&direct, &got) {
+          if findings[index].calls.iter().all(|known| known.site.address != site.address) {
+            findings[index].calls.push(call(site));
+          }
+        }
+      }
+    }
+    findings.sort_by_key(|finding| denylist.iter().position(|name| *name == finding.name));
+    for finding in &mut findings {
+      finding.calls.sort_by_key(|call| call.site.address);
+    }
+    findings
+  }
+}
@@ -0,0 +1,106 @@
+//A reader for the DER of PKCS#7 and X.509, low tag numbers and definite lengths only, which
+//is all DER has room for in these structures.
+use std::io;
+
+pub(crate) const DER_INTEGER: u8 = 0x02;
+pub(crate) const DER_BIT_STRING: u8 = 0x03;
+pub(crate) const DER_OCTET_STRING: u8 = 0x04;
+pub(crate) const DER_OID: u8 = 0x06;
+pub(crate) const DER_SEQUENCE: u8 = 0x30;
+pub(crate) const DER_SET: u8 = 0x31;
+//[n] of a constructed, context specific field
+pub(crate) const fn der_context(n: u8) -> u8 {
+  0xa0 | n
+}
+
+pub(crate) fn malformed(what: &str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, format!("malformed {}", what))
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct DerValue<'a> {
+  pub tag: u8,
+  pub contents: &'a [u8],
+  //tag and length included
+  pub encoded: &'a [u8],
+}
+
+impl<'a> DerValue<'a> {
+  pub fn reader(&self) -> DerReader<'a> {
+    DerReader::new(self.contents)
+  }
+}
+
+pub(crate) struct DerReader<'a> {
+  data: &'a [u8],
+}
+
+impl<'a> DerReader<'a> {
+  pub fn new(data: &'a [u8]) -> DerReader<'a> {
+    DerReader { data }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.data.is_empty()
+  }
+
+  pub fn peek_tag(&self) -> Option<u8> {
+    self.data.first().copied()
+  }
+
+  pub fn read(&mut self) -> io::Result<DerValue<'a>> {
+    let data = self.data;
+    let tag = *data.first().ok_or_else(|| malformed("DER, value expected"))?;
+    if tag & 0x1f == 0x1f {
+      return Err(malformed("DER, high tag number"));
+    }
+    let first = *data.get(1).ok_or_else(|| malformed("DER length"))? as usize;
+    let (length, header) = if first < 0x80 {
+      (first, 2)
+    } else {
+      let count = first & 0x7f;
+      if count == 0 || count > 4 {
+        return Err(malformed("DER length, indefinite or too long"));
+      }
+      let bytes = data.get(2..2 + count).ok_or_else(|| malformed("DER length"))?;
+      (bytes.iter().fold(0usize, |length, &byte| length << 8 | byte as usize), 2 + count)
+    };
+    let end = header.checked_add(length).filter(|&end| end <= data.len()).ok_or_else(|| malformed("DER, value past its container"))?;
+    self.data = &data[end..];
+    Ok(DerValue { tag, contents: &data[header..end], encoded: &data[..end] })
+  }
+
+  pub fn expect(&mut self, tag: u8, what: &str) -> io::Result<DerValue<'a>> {
+    match self.read()? {
+      value if value.tag == tag => Ok(value),
+      _ => Err(malformed(what)),
+    }
+  }
+
+  pub fn optional(&mut self, tag: u8) -> io::Result<Option<DerValue<'a>>> {
+    match self.peek_tag() {
+      Some(next) if next == tag => self.read().map(Some),
+      _ => Ok(None),
+    }
+  }
+}
+
+//dotted form, e.g. "1.2.840.113549.1.7.2"
+pub(crate) fn oid_string(contents: &[u8]) -> String {
+  let mut parts = Vec::new();
+  let mut value: u64 = 0;
+  for &byte in contents {
+    value = value << 7 | (byte & 0x7f) as u64;
+    if byte & 0x80 == 0 {
+      if parts.is_empty() {
+        let first = (value / 40).min(2);
+        parts.push(first);
+        parts.push(value - first * 40);
+      } else {
+        parts.push(value);
+      }
+      value = 0;
+    }
+  }
+  parts.iter().map(|part| part.to_string()).collect::<Vec<_>>().join(".")
+}
@@ -0,0 +1,366 @@
+//Hash functions for ids and digests of file contents, kept in the crate so digests need no
+//extra dependency.
+
+pub(crate) fn sha1(data: &[u8]) -> [u8; 20] {
+  let mut state: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+  let mut message = data.to_vec();
+  message.push(0x80);
+  while message.len() % 64 != 56 {
+    message.push(0);
+  }
+  message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_be_bytes());
+  for block in message.chunks_exact(64) {
+    let mut words = [0u32; 80];
+    for (index, word) in block.chunks_exact(4).enumerate() {
+      words[index] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for index in 16..80 {
+      words[index] = (words[index - 3] ^ words[index - 8] ^ words[index - 14] ^ words[index - 16]).rotate_left(1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e] = state;
+    for (index, &word) in words.iter().enumerate() {
+      let (f, k) = match index {
+        0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+        20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+        40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+        _ => (b ^ c ^ d, 0xca62_c1d6),
+      };
+      let next = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(word);
+      e = d;
+      d = c;
+      c = b.rotate_left(30);
+      b = a;
+      a = next;
+    }
+    for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+      *value = value.wrapping_add(add);
+    }
+  }
+  let mut digest = [0u8; 20];
+  for (bytes, value) in digest.chunks_exact_mut(4).zip(state) {
+    bytes.copy_from_slice(&value.to_be_bytes());
+  }
+  digest
+}
+
+#[cfg(feature = "sha2")]
+const SHA512_ROUND_CONSTANTS: [u64; 80] = [
+  0x428a_2f98_d728_ae22, 0x7137_4491_23ef_65cd, 0xb5c0_fbcf_ec4d_3b2f, 0xe9b5_dba5_8189_dbbc,
+  0x3956_c25b_f348_b538, 0x59f1_11f1_b605_d019, 0x923f_82a4_af19_4f9b, 0xab1c_5ed5_da6d_8118,
+  0xd807_aa98_a303_0242, 0x1283_5b01_4570_6fbe, 0x2431_85be_4ee4_b28c, 0x550c_7dc3_d5ff_b4e2,
+  0x72be_5d74_f27b_896f, 0x80de_b1fe_3b16_96b1, 0x9bdc_06a7_25c7_1235, 0xc19b_f174_cf69_2694,
+  0xe49b_69c1_9ef1_4ad2, 0xefbe_4786_384f_25e3, 0x0fc1_9dc6_8b8c_d5b5, 0x240c_a1cc_77ac_9c65,
+  0x2de9_2c6f_592b_0275, 0x4a74_84aa_6ea6_e483, 0x5cb0_a9dc_bd41_fbd4, 0x76f9_88da_8311_53b5,
+  0x983e_5152_ee66_dfab, 0xa831_c66d_2db4_3210, 0xb003_27c8_98fb_213f, 0xbf59_7fc7_beef_0ee4,
+  0xc6e0_0bf3_3da8_8fc2, 0xd5a7_9147_930a_a725, 0x06ca_6351_e003_826f, 0x1429_2967_0a0e_6e70,
+  0x27b7_0a85_46d2_2ffc, 0x2e1b_2138_5c26_c926, 0x4d2c_6dfc_5ac4_2aed, 0x5338_0d13_9d95_b3df,
+  0x650a_7354_8baf_63de, 0x766a_0abb_3c77_b2a8, 0x81c2_c92e_47ed_aee6, 0x9272_2c85_1482_353b,
+  0xa2bf_e8a1_4cf1_0364, 0xa81a_664b_bc42_3001, 0xc24b_8b70_d0f8_9791, 0xc76c_51a3_0654_be30,
+  0xd192_e819_d6ef_5218, 0xd699_0624_5565_a910, 0xf40e_3585_5771_202a, 0x106a_a070_32bb_d1b8,
+  0x19a4_c116_b8d2_d0c8, 0x1e37_6c08_5141_ab53, 0x2748_774c_df8e_eb99, 0x34b0_bcb5_e19b_48a8,
+  0x391c_0cb3_c5c9_5a63, 0x4ed8_aa4a_e341_8acb, 0x5b9c_ca4f_7763_e373, 0x682e_6ff3_d6b2_b8a3,
+  0x748f_82ee_5def_b2fc, 0x78a5_636f_4317_2f60, 0x84c8_7814_a1f0_ab72, 0x8cc7_0208_1a64_39ec,
+  0x90be_fffa_2363_1e28, 0xa450_6ceb_de82_bde9, 0xbef9_a3f7_b2c6_7915, 0xc671_78f2_e372_532b,
+  0xca27_3ece_ea26_619c, 0xd186_b8c7_21c0_c207, 0xeada_7dd6_cde0_eb1e, 0xf57d_4f7f_ee6e_d178,
+  0x06f0_67aa_7217_6fba, 0x0a63_7dc5_a2c8_98a6, 0x113f_9804_bef9_0dae, 0x1b71_0b35_131c_471b,
+  0x28db_77f5_2304_7d84, 0x32ca_ab7b_40c7_2493, 0x3c9e_be0a_15c9_bebc, 0x431d_67c4_9c10_0d4c,
+  0x4cc5_d4be_cb3e_42b6, 0x597f_299c_fc65_7e2a, 0x5fcb_6fab_3ad6_faec, 0x6c44_198c_4a47_5817,
+];
+
+//SHA-256 uses the high halves of the SHA-512 round constants and initial state, SHA-224 and
+//SHA-384 differ from the one they are cut from only in their initial state.
+#[cfg(any(feature = "sha2", feature = "blake3"))]
+const SHA512_INITIAL: [u64; 8] = [
+  0x6a09_e667_f3bc_c908, 0xbb67_ae85_84ca_a73b, 0x3c6e_f372_fe94_f82b, 0xa54f_f53a_5f1d_36f1,
+  0x510e_527f_ade6_82d1, 0x9b05_688c_2b3e_6c1f, 0x1f83_d9ab_fb41_bd6b, 0x5be0_cd19_137e_2179,
+];
+#[cfg(feature = "sha2")]
+const SHA384_INITIAL: [u64; 8] = [
+  0xcbbb_9d5d_c105_9ed8, 0x629a_292a_367c_d507, 0x9159_015a_3070_dd17, 0x152f_ecd8_f70e_5939,
+  0x6733_2667_ffc0_0b31, 0x8eb4_4a87_6858_1511, 0xdb0c_2e0d_64f9_8fa7, 0x47b5_481d_befa_4fa4,
+];
+#[cfg(feature = "sha2")]
+const SHA224_INITIAL: [u32; 8] = [0xc105_9ed8, 0x367c_d507, 0x3070_dd17, 0xf70e_5939, 0xffc0_0b31, 0x6858_1511, 0x64f9_8fa7, 0xbefa_4fa4];
+
+#[cfg(feature = "sha2")]
+fn sha256_state(initial: [u32; 8], data: &[u8]) -> [u32; 8] {
+  let mut state = initial;
+  let mut message = data.to_vec();
+  message.push(0x80);
+  while message.len() % 64 != 56 {
+    message.push(0);
+  }
+  message.extend_from_slice(&((data.len() as u64).wrapping_mul(8)).to_be_bytes());
+  for block in message.chunks_exact(64) {
+    let mut words = [0u32; 64];
+    for (index, word) in block.chunks_exact(4).enumerate() {
+      words[index] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+    }
+    for index in 16..64 {
+      let s0 = words[index - 15].rotate_right(7) ^ words[index - 15].rotate_right(18) ^ (words[index - 15] >> 3);
+      let s1 = words[index - 2].rotate_right(17) ^ words[index - 2].rotate_right(19) ^ (words[index - 2] >> 10);
+      words[index] = words[index - 16].wrapping_add(s0).wrapping_add(words[index - 7]).wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+    for (&word, &constant) in words.iter().zip(SHA512_ROUND_CONSTANTS.iter()) {
+      let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+      let choice = (e & f) ^ (!e & g);
+      let t1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add((constant >> 32) as u32).wrapping_add(word);
+      let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+      let majority = (a & b) ^ (a & c) ^ (b & c);
+      let t2 = s0.wrapping_add(majority);
+      h = g;
+      g = f;
+      f = e;
+      e = d.wrapping_add(t1);
+      d = c;
+      c = b;
+      b = a;
+      a = t1.wrapping_add(t2);
+    }
+    for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+      *value = value.wrapping_add(add);
+    }
+  }
+  state
+}
+
+#[cfg(feature = "sha2")]
+fn sha512_state(initial: [u64; 8], data: &[u8]) -> [u64; 8] {
+  let mut state = initial;
+  let mut message = data.to_vec();
+  message.push(0x80);
+  while message.len() % 128 != 112 {
+    message.push(0);
+  }
+  message.extend_from_slice(&((data.len() as u128).wrapping_mul(8)).to_be_bytes());
+  for block in message.chunks_exact(128) {
+    let mut words = [0u64; 80];
+    for (index, word) in block.chunks_exact(8).enumerate() {
+      words[index] = u64::from_be_bytes([word[0], word[1], word[2], word[3], word[4], word[5], word[6], word[7]]);
+    }
+    for index in 16..80 {
+      let s0 = words[index - 15].rotate_right(1) ^ words[index - 15].rotate_right(8) ^ (words[index - 15] >> 7);
+      let s1 = words[index - 2].rotate_right(19) ^ words[index - 2].rotate_right(61) ^ (words[index - 2] >> 6);
+      words[index] = words[index - 16].wrapping_add(s0).wrapping_add(words[index - 7]).wrapping_add(s1);
+    }
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = state;
+    for (&word, &constant) in words.iter().zip(SHA512_ROUND_CONSTANTS.iter()) {
+      let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+      let choice = (e & f) ^ (!e & g);
+      let t1 = h.wrapping_add(s1).wrapping_add(choice).wrapping_add(constant).wrapping_add(word);
+      let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+      let majority = (a & b) ^ (a & c) ^ (b & c);
+      let t2 = s0.wrapping_add(majority);
+      h = g;
+      g = f;
+      f = e;
+      e = d.wrapping_add(t1);
+      d = c;
+      c = b;
+      b = a;
+      a = t1.wrapping_add(t2);
+    }
+    for (value, add) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+      *value = value.wrapping_add(add);
+    }
+  }
+  state
+}
+
+#[cfg(feature = "sha2")]
+fn be_bytes_32(state: &[u32]) -> Vec<u8> {
+  state.iter().flat_map(|value| value.to_be_bytes()).collect()
+}
+
+#[cfg(feature = "sha2")]
+fn be_bytes_64(state: &[u64]) -> Vec<u8> {
+  state.iter().flat_map(|value| value.to_be_bytes()).collect()
+}
+
+#[cfg(feature = "sha2")]
+pub(crate) fn sha224(data: &[u8]) -> Vec<u8> {
+  be_bytes_32(&sha256_state(SHA224_INITIAL, data)[..7])
+}
+
+#[cfg(feature = "sha2")]
+pub(crate) fn sha256(data: &[u8]) -> Vec<u8> {
+  be_bytes_32(&sha256_state(SHA512_INITIAL.map(|value| (value >> 32) as u32), data))
+}
+
+#[cfg(feature = "sha2")]
+pub(crate) fn sha384(data: &[u8]) -> Vec<u8> {
+  be_bytes_64(&sha512_state(SHA384_INITIAL, data)[..6])
+}
+
+#[cfg(feature = "sha2")]
+pub(crate) fn sha512(data: &[u8]) -> [u8; 64] {
+  let mut digest = [0u8; 64];
+  digest.copy_from_slice(&be_bytes_64(&sha512_state(SHA512_INITIAL, data)));
+  digest
+}
+
+const XXH_PRIME_1: u64 = 0x9e37_79b1_85eb_ca87;
+const XXH_PRIME_2: u64 = 0xc2b2_ae3d_27d4_eb4f;
+const XXH_PRIME_3: u64 = 0x1656_67b1_9e37_79f9;
+const XXH_PRIME_4: u64 = 0x85eb_ca77_c2b2_ae63;
+const XXH_PRIME_5: u64 = 0x27d4_eb2f_1656_67c5;
+
+fn xxh64_round(accumulator: u64, input: u64) -> u64 {
+  accumulator.wrapping_add(input.wrapping_mul(XXH_PRIME_2)).rotate_left(31).wrapping_mul(XXH_PRIME_1)
+}
+
+fn xxh64_merge(hash: u64, accumulator: u64) -> u64 {
+  (hash ^ xxh64_round(0, accumulator)).wrapping_mul(XXH_PRIME_1).wrapping_add(XXH_PRIME_4)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+  u64::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
+}
+
+pub(crate) fn xxhash64(data: &[u8], seed: u64) -> u64 {
+  let mut rest = data;
+  let mut hash = if data.len() >= 32 {
+    let mut accumulators = [
+      seed.wrapping_add(XXH_PRIME_1).wrapping_add(XXH_PRIME_2),
+      seed.wrapping_add(XXH_PRIME_2),
+      seed,
+      seed.wrapping_sub(XXH_PRIME_1),
+    ];
+    while rest.len() >= 32 {
+      for (lane, accumulator) in accumulators.iter_mut().enumerate() {
+        *accumulator = xxh64_round(*accumulator, read_u64(&rest[lane * 8..]));
+      }
+      rest = &rest[32..];
+    }
+    let [a, b, c, d] = accumulators;
+    let hash = a.rotate_left(1).wrapping_add(b.rotate_left(7)).wrapping_add(c.rotate_left(12)).wrapping_add(d.rotate_left(18));
+    accumulators.iter().fold(hash, |hash, &accumulator| xxh64_merge(hash, accumulator))
+  } else {
+    seed.wrapping_add(XXH_PRIME_5)
+  };
+  hash = hash.wrapping_add(data.len() as u64);
+  while rest.len() >= 8 {
+    hash ^= xxh64_round(0, read_u64(rest));
+    hash = hash.rotate_left(27).wrapping_mul(XXH_PRIME_1).wrapping_add(XXH_PRIME_4);
+    rest = &rest[8..];
+  }
+  if rest.len() >= 4 {
+    hash ^= (u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as u64).wrapping_mul(XXH_PRIME_1);
+    hash = hash.rotate_left(23).wrapping_mul(XXH_PRIME_2).wrapping_add(XXH_PRIME_3);
+    rest = &rest[4..];
+  }
+  for &byte in rest {
+    hash ^= (byte as u64).wrapping_mul(XXH_PRIME_5);
+    hash = hash.rotate_left(11).wrapping_mul(XXH_PRIME_1);
+  }
+  hash ^= hash >> 33;
+  hash = hash.wrapping_mul(XXH_PRIME_2);
+  hash ^= hash >> 29;
+  hash = hash.wrapping_mul(XXH_PRIME_3);
+  hash ^ (hash >> 32)
+}
+
+//BLAKE3 with the default 32 byte output, no keyed or derive-key mode.
+#[cfg(feature = "blake3")]
+mod blake3 {
+  use super::SHA512_INITIAL;
+
+  const CHUNK_SIZE: usize = 1024;
+  const BLOCK_SIZE: usize = 64;
+  const CHUNK_START: u32 = 1;
+  const CHUNK_END: u32 = 2;
+  const PARENT: u32 = 4;
+  const ROOT: u32 = 8;
+  const PERMUTATION: [usize; 16] = [2, 6, 3, 10, 7, 0, 4, 13, 1, 11, 12, 5, 9, 14, 15, 8];
+
+  //the SHA-256 initial state
+  fn iv() -> [u32; 8] {
+    SHA512_INITIAL.map(|value| (value >> 32) as u32)
+  }
+
+  fn mix(state: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize, x: u32, y: u32) {
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(x);
+    state[d] = (state[d] ^ state[a]).rotate_right(16);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(12);
+    state[a] = state[a].wrapping_add(state[b]).wrapping_add(y);
+    state[d] = (state[d] ^ state[a]).rotate_right(8);
+    state[c] = state[c].wrapping_add(state[d]);
+    state[b] = (state[b] ^ state[c]).rotate_right(7);
+  }
+
+  fn compress(chaining: &[u32; 8], block: &[u8], counter: u64, flags: u32) -> [u32; 8] {
+    let mut words = [0u32; 16];
+    for (index, bytes) in block.chunks(4).enumerate() {
+      let mut word = [0u8; 4];
+      word[..bytes.len()].copy_from_slice(bytes);
+      words[index] = u32::from_le_bytes(word);
+    }
+    let iv = iv();
+    let mut state = [
+      chaining[0], chaining[1], chaining[2], chaining[3], chaining[4], chaining[5], chaining[6], chaining[7],
+      iv[0], iv[1], iv[2], iv[3], counter as u32, (counter >> 32) as u32, block.len() as u32, flags,
+    ];
+    for round in 0..7 {
+      mix(&mut state, 0, 4, 8, 12, words[0], words[1]);
+      mix(&mut state, 1, 5, 9, 13, words[2], words[3]);
+      mix(&mut state, 2, 6, 10, 14, words[4], words[5]);
+      mix(&mut state, 3, 7, 11, 15, words[6], words[7]);
+      mix(&mut state, 0, 5, 10, 15, words[8], words[9]);
+      mix(&mut state, 1, 6, 11, 12, words[10], words[11]);
+      mix(&mut state, 2, 7, 8, 13, words[12], words[13]);
+      mix(&mut state, 3, 4, 9, 14, words[14], words[15]);
+      if round < 6 {
+        words = PERMUTATION.map(|index| words[index]);
+      }
+    }
+    let mut output = [0u32; 8];
+    for (index, value) in output.iter_mut().enumerate() {
+      *value = state[index] ^ state[index + 8];
+    }
+    output
+  }
+
+  fn chunk(data: &[u8], counter: u64, root: u32) -> [u32; 8] {
+    let blocks: Vec<&[u8]> = if data.is_empty() { vec![data] } else { data.chunks(BLOCK_SIZE).collect() };
+    let mut chaining = iv();
+    for (index, block) in blocks.iter().enumerate() {
+      let mut flags = if index == 0 { CHUNK_START } else { 0 };
+      if index == blocks.len() - 1 {
+        flags |= CHUNK_END | root;
+      }
+      chaining = compress(&chaining, block, counter, flags);
+    }
+    chaining
+  }
+
+  //the chaining value of the subtree over `data`, whose first chunk is number `counter`
+  fn subtree(data: &[u8], counter: u64, root: u32) -> [u32; 8] {
+    let chunks = data.len().div_ceil(CHUNK_SIZE).max(1);
+    if chunks == 1 {
+      return chunk(data, counter, root);
+    }
+    //the left subtree takes the largest power of two of chunks that leaves some for the right
+    let left_chunks = 1usize << (usize::BITS - 1 - (chunks - 1).leading_zeros());
+    let split = left_chunks * CHUNK_SIZE;
+    let left = subtree(&data[..split], counter, 0);
+    let right = subtree(&data[split..], counter + left_chunks as u64, 0);
+    let mut block = [0u8; BLOCK_SIZE];
+    for (bytes, word) in block.chunks_exact_mut(4).zip(left.iter().chain(right.iter())) {
+      bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    compress(&iv(), &block, 0, PARENT | root)
+  }
+
+  pub(crate) fn blake3(data: &[u8]) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    for (bytes, word) in digest.chunks_exact_mut(4).zip(subtree(data, 0, ROOT)) {
+      bytes.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+  }
+}
+
+#[cfg(feature = "blake3")]
+pub(crate) use self::blake3::blake3;
@@ -0,0 +1,1104 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::convert::TryFrom;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::leb128::{read_sleb128, read_uleb128};
+
+pub const DW_TAG_ARRAY_TYPE: u64 = 0x01;
+pub const DW_TAG_CLASS_TYPE: u64 = 0x02;
+pub const DW_TAG_ENUMERATION_TYPE: u64 = 0x04;
+pub const DW_TAG_FORMAL_PARAMETER: u64 = 0x05;
+pub const DW_TAG_LEXICAL_BLOCK: u64 = 0x0b;
+pub const DW_TAG_MEMBER: u64 = 0x0d;
+pub const DW_TAG_POINTER_TYPE: u64 = 0x0f;
+pub const DW_TAG_REFERENCE_TYPE: u64 = 0x10;
+pub const DW_TAG_COMPILE_UNIT: u64 = 0x11;
+pub const DW_TAG_STRUCTURE_TYPE: u64 = 0x13;
+pub const DW_TAG_SUBROUTINE_TYPE: u64 = 0x15;
+pub const DW_TAG_TYPEDEF: u64 = 0x16;
+pub const DW_TAG_UNION_TYPE: u64 = 0x17;
+pub const DW_TAG_UNSPECIFIED_PARAMETERS: u64 = 0x18;
+pub const DW_TAG_INHERITANCE: u64 = 0x1c;
+pub const DW_TAG_INLINED_SUBROUTINE: u64 = 0x1d;
+pub const DW_TAG_SUBRANGE_TYPE: u64 = 0x21;
+pub const DW_TAG_BASE_TYPE: u64 = 0x24;
+pub const DW_TAG_CONST_TYPE: u64 = 0x26;
+pub const DW_TAG_ENUMERATOR: u64 = 0x28;
+pub const DW_TAG_SUBPROGRAM: u64 = 0x2e;
+pub const DW_TAG_VARIABLE: u64 = 0x34;
+pub const DW_TAG_VOLATILE_TYPE: u64 = 0x35;
+pub const DW_TAG_RESTRICT_TYPE: u64 = 0x37;
+pub const DW_TAG_NAMESPACE: u64 = 0x39;
+pub const DW_TAG_PARTIAL_UNIT: u64 = 0x3c;
+pub const DW_TAG_RVALUE_REFERENCE_TYPE: u64 = 0x42;
+pub const DW_TAG_ATOMIC_TYPE: u64 = 0x47;
+pub const DW_TAG_SKELETON_UNIT: u64 = 0x4a;
+
+pub const DW_AT_SIBLING: u64 = 0x01;
+pub const DW_AT_LOCATION: u64 = 0x02;
+pub const DW_AT_NAME: u64 = 0x03;
+pub const DW_AT_BYTE_SIZE: u64 = 0x0b;
+pub const DW_AT_BIT_OFFSET: u64 = 0x0c;
+pub const DW_AT_BIT_SIZE: u64 = 0x0d;
+pub const DW_AT_STMT_LIST: u64 = 0x10;
+pub const DW_AT_LOW_PC: u64 = 0x11;
+pub const DW_AT_HIGH_PC: u64 = 0x12;
+pub const DW_AT_LANGUAGE: u64 = 0x13;
+pub const DW_AT_COMP_DIR: u64 = 0x1b;
+pub const DW_AT_CONST_VALUE: u64 = 0x1c;
+pub const DW_AT_INLINE: u64 = 0x20;
+pub const DW_AT_PRODUCER: u64 = 0x25;
+pub const DW_AT_PROTOTYPED: u64 = 0x27;
+pub const DW_AT_COUNT: u64 = 0x37;
+pub const DW_AT_UPPER_BOUND: u64 = 0x2f;
+pub const DW_AT_ABSTRACT_ORIGIN: u64 = 0x31;
+pub const DW_AT_DATA_MEMBER_LOCATION: u64 = 0x38;
+pub const DW_AT_DECL_FILE: u64 = 0x3a;
+pub const DW_AT_DECL_LINE: u64 = 0x3b;
+pub const DW_AT_DECLARATION: u64 = 0x3c;
+pub const DW_AT_EXTERNAL: u64 = 0x3f;
+pub const DW_AT_SPECIFICATION: u64 = 0x47;
+pub const DW_AT_TYPE: u64 = 0x49;
+pub const DW_AT_ENTRY_PC: u64 = 0x52;
+pub const DW_AT_RANGES: u64 = 0x55;
+pub const DW_AT_CALL_FILE: u64 = 0x58;
+pub const DW_AT_CALL_LINE: u64 = 0x59;
+pub const DW_AT_DATA_BIT_OFFSET: u64 = 0x6b;
+pub const DW_AT_LINKAGE_NAME: u64 = 0x6e;
+pub const DW_AT_STR_OFFSETS_BASE: u64 = 0x72;
+pub const DW_AT_ADDR_BASE: u64 = 0x73;
+pub const DW_AT_RNGLISTS_BASE: u64 = 0x74;
+pub const DW_AT_MIPS_LINKAGE_NAME: u64 = 0x2007;
+
+pub const DW_FORM_ADDR: u64 = 0x01;
+pub const DW_FORM_BLOCK2: u64 = 0x03;
+pub const DW_FORM_BLOCK4: u64 = 0x04;
+pub const DW_FORM_DATA2: u64 = 0x05;
+pub const DW_FORM_DATA4: u64 = 0x06;
+pub const DW_FORM_DATA8: u64 = 0x07;
+pub const DW_FORM_STRING: u64 = 0x08;
+pub const DW_FORM_BLOCK: u64 = 0x09;
+pub const DW_FORM_BLOCK1: u64 = 0x0a;
+pub const DW_FORM_DATA1: u64 = 0x0b;
+pub const DW_FORM_FLAG: u64 = 0x0c;
+pub const DW_FORM_SDATA: u64 = 0x0d;
+pub const DW_FORM_STRP: u64 = 0x0e;
+pub const DW_FORM_UDATA: u64 = 0x0f;
+pub const DW_FORM_REF_ADDR: u64 = 0x10;
+pub const DW_FORM_REF1: u64 = 0x11;
+pub const DW_FORM_REF2: u64 = 0x12;
+pub const DW_FORM_REF4: u64 = 0x13;
+pub const DW_FORM_REF8: u64 = 0x14;
+pub const DW_FORM_REF_UDATA: u64 = 0x15;
+pub const DW_FORM_INDIRECT: u64 = 0x16;
+pub const DW_FORM_SEC_OFFSET: u64 = 0x17;
+pub const DW_FORM_EXPRLOC: u64 = 0x18;
+pub const DW_FORM_FLAG_PRESENT: u64 = 0x19;
+pub const DW_FORM_STRX: u64 = 0x1a;
+pub const DW_FORM_ADDRX: u64 = 0x1b;
+pub const DW_FORM_REF_SUP4: u64 = 0x1c;
+pub const DW_FORM_STRP_SUP: u64 = 0x1d;
+pub const DW_FORM_DATA16: u64 = 0x1e;
+pub const DW_FORM_LINE_STRP: u64 = 0x1f;
+pub const DW_FORM_REF_SIG8: u64 = 0x20;
+pub const DW_FORM_IMPLICIT_CONST: u64 = 0x21;
+pub const DW_FORM_LOCLISTX: u64 = 0x22;
+pub const DW_FORM_RNGLISTX: u64 = 0x23;
+pub const DW_FORM_REF_SUP8: u64 = 0x24;
+pub const DW_FORM_STRX1: u64 = 0x25;
+pub const DW_FORM_STRX2: u64 = 0x26;
+pub const DW_FORM_STRX3: u64 = 0x27;
+pub const DW_FORM_STRX4: u64 = 0x28;
+pub const DW_FORM_ADDRX1: u64 = 0x29;
+pub const DW_FORM_ADDRX2: u64 = 0x2a;
+pub const DW_FORM_ADDRX3: u64 = 0x2b;
+pub const DW_FORM_ADDRX4: u64 = 0x2c;
+pub const DW_FORM_GNU_ADDR_INDEX: u64 = 0x1f01;
+pub const DW_FORM_GNU_STR_INDEX: u64 = 0x1f02;
+pub const DW_FORM_GNU_REF_ALT: u64 = 0x1f20;
+pub const DW_FORM_GNU_STRP_ALT: u64 = 0x1f21;
+
+#[derive(Clone, Copy, Debug)]
+pub enum AttributeValue<'a> {
+  Address(u64),
+  Unsigned(u64),
+  Signed(i64),
+  Flag(bool),
+  String(&'a [u8]),
+  //absolute offset in .debug_info
+  Reference(u64),
+  TypeSignature(u64),
+  //offset into another section (line table, ranges, ...)
+  SectionOffset(u64),
+  Block(&'a [u8]),
+  Expression(&'a [u8]),
+  //resolved against the unit bases once the unit DIE is known
+  StringIndex(u64),
+  AddressIndex(u64),
+  RangeListIndex(u64),
+  LocationListIndex(u64),
+  //refers to a supplementary object file, left unresolved
+  Supplementary(u64),
+}
+
+pub struct Die<'a> {
+  //absolute offset in .debug_info
+  pub offset: u64,
+  pub tag: u64,
+  pub depth: usize,
+  pub has_children: bool,
+  pub attributes: Vec<(u64, AttributeValue<'a>)>,
+}
+
+impl<'a> Die<'a> {
+  pub fn attribute(&self, attribute: u64) -> Option<AttributeValue<'a>> {
+    self.attributes.iter().find(|(name, _)| *name == attribute).map(|&(_, value)| value)
+  }
+
+  pub fn string(&self, attribute: u64) -> Option<Cow<'a, str>> {
+    match self.attribute(attribute)? {
+      AttributeValue::String(bytes) => Some(String::from_utf8_lossy(bytes)),
+      _ => None,
+    }
+  }
+
+  pub fn unsigned(&self, attribute: u64) -> Option<u64> {
+    match self.attribute(attribute)? {
+      AttributeValue::Address(value) | AttributeValue::Unsigned(value) | AttributeValue::SectionOffset(value) => Some(value),
+      AttributeValue::Signed(value) => Some(value as u64),
+      AttributeValue::Flag(value) => Some(value as u64),
+      _ => None,
+    }
+  }
+
+  pub fn reference(&self, attribute: u64) -> Option<u64> {
+    match self.attribute(attribute)? {
+      AttributeValue::Reference(offset) => Some(offset),
+      _ => None,
+    }
+  }
+
+  pub fn flag(&self, attribute: u64) -> bool {
+    matches!(self.attribute(attribute), Some(AttributeValue::Flag(true)))
+  }
+}
+
+#[derive(Clone)]
+pub struct UnitHeader {
+  //offset of the unit in .debug_info
+  pub offset: u64,
+  pub version: u16,
+  pub unit_type: u8,
+  pub address_size: u8,
+  pub offset_size: u8,
+  pub abbrev_offset: u64,
+  //offset of the first DIE in .debug_info
+  pub dies_offset: u64,
+  pub end: u64,
+}
+
+pub struct Unit<'a> {
+  pub header: UnitHeader,
+  pub dies: Vec<Die<'a>>,
+  //parent index of every DIE, None for the unit DIE
+  pub parents: Vec<Option<usize>>,
+  indices: HashMap<u64, usize>,
+  pub str_offsets_base: u64,
+  pub addr_base: u64,
+  pub rnglists_base: u64,
+}
+
+impl<'a> Unit<'a> {
+  pub fn root(&self) -> Option<&Die<'a>> {
+    self.dies.first()
+  }
+
+  pub fn index_of(&self, offset: u64) -> Option<usize> {
+    self.indices.get(&offset).copied()
+  }
+
+  pub fn die_at(&self, offset: u64) -> Option<&Die<'a>> {
+    self.index_of(offset).map(|index| &self.dies[index])
+  }
+
+  pub fn name(&self) -> Option<Cow<'a, str>> {
+    self.root()?.string(DW_AT_NAME)
+  }
+
+  pub fn comp_dir(&self) -> Option<Cow<'a, str>> {
+    self.root()?.string(DW_AT_COMP_DIR)
+  }
+
+  pub fn children(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+    let depth = self.dies[index].depth;
+    self.dies[index + 1..].iter().enumerate()
+      .take_while(move |(_, die)| die.depth > depth)
+      .filter(move |(_, die)| die.depth == depth + 1)
+      .map(move |(position, _)| index + 1 + position)
+  }
+
+  //Follows DW_AT_SPECIFICATION and DW_AT_ABSTRACT_ORIGIN until a DIE carries the attribute.
+  pub fn inherited_attribute(&self, index: usize, attribute: u64) -> Option<AttributeValue<'a>> {
+    let mut die = &self.dies[index];
+    for _ in 0..8 {
+      if let Some(value) = die.attribute(attribute) {
+        return Some(value);
+      }
+      let origin = die.reference(DW_AT_SPECIFICATION).or_else(|| die.reference(DW_AT_ABSTRACT_ORIGIN))?;
+      die = self.die_at(origin)?;
+    }
+    None
+  }
+
+  pub fn inherited_string(&self, index: usize, attribute: u64) -> Option<Cow<'a, str>> {
+    match self.inherited_attribute(index, attribute)? {
+      AttributeValue::String(bytes) => Some(String::from_utf8_lossy(bytes)),
+      _ => None,
+    }
+  }
+
+  //Name qualified by the enclosing namespaces and types, "ns::Type::method".
+  pub fn qualified_name(&self, index: usize) -> Option<String> {
+    let name = self.inherited_string(index, DW_AT_NAME)?;
+    let mut scopes = Vec::new();
+    let die = &self.dies[index];
+    let mut scope = die.reference(DW_AT_SPECIFICATION)
+      .or_else(|| die.reference(DW_AT_ABSTRACT_ORIGIN).and_then(|origin| self.die_at(origin)?.reference(DW_AT_SPECIFICATION)))
+      .and_then(|declaration| self.index_of(declaration))
+      .unwrap_or(index);
+    while let Some(parent) = self.parents[scope] {
+      let parent_die = &self.dies[parent];
+      match parent_die.tag {
+        DW_TAG_NAMESPACE | DW_TAG_CLASS_TYPE | DW_TAG_STRUCTURE_TYPE | DW_TAG_UNION_TYPE | DW_TAG_ENUMERATION_TYPE => {
+          scopes.push(parent_die.string(DW_AT_NAME).unwrap_or(Cow::Borrowed("(anonymous namespace)")).into_owned());
+        },
+        _ => {},
+      };
+      scope = parent;
+    }
+    scopes.reverse();
+    scopes.push(name.into_owned());
+    Some(scopes.join("::"))
+  }
+}
+
+pub struct LineFile {
+  pub name: String,
+  pub directory_index: u64,
+  pub md5: Option<[u8; 16]>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct LineRow {
+  pub address: u64,
+  //as encoded: 1-based before DWARF 5, 0-based since
+  pub file_index: u64,
+  pub line: u64,
+  pub column: u64,
+  pub is_stmt: bool,
+  pub end_sequence: bool,
+  pub discriminator: u64,
+}
+
+pub struct LineProgram {
+  pub version: u16,
+  pub directories: Vec<String>,
+  pub files: Vec<LineFile>,
+  pub rows: Vec<LineRow>,
+}
+
+impl LineProgram {
+  //DWARF 5 numbers files from 0 (the primary source file), earlier versions from 1.
+  pub fn file(&self, file_index: u64) -> Option<&LineFile> {
+    let index = if self.version >= 5 { file_index } else { file_index.checked_sub(1)? };
+    self.files.get(index as usize)
+  }
+
+  pub fn file_path(&self, file_index: u64, comp_dir: Option<&str>) -> Option<String> {
+    let file = self.file(file_index)?;
+    if file.name.starts_with('/') {
+      return Some(file.name.clone());
+    }
+    let directory = if self.version >= 5 {
+      self.directories.get(file.directory_index as usize).map(|directory| directory.as_str())
+    } else if file.directory_index == 0 {
+      comp_dir
+    } else {
+      self.directories.get(file.directory_index as usize - 1).map(|directory| directory.as_str())
+    };
+    let mut path = String::new();
+    if let Some(directory) = directory {
+      if !directory.starts_with('/') {
+        if let Some(comp_dir) = comp_dir {
+          path.push_str(comp_dir);
+          path.push('/');
+        }
+      }
+      path.push_str(directory);
+      path.push('/');
+    } else if let Some(comp_dir) = comp_dir {
+      path.push_str(comp_dir);
+      path.push('/');
+    }
+    path.push_str(&file.name);
+    Some(path)
+  }
+}
+
+pub struct DwarfFunction {
+  pub name: String,
+  pub linkage_name: Option<String>,
+  pub ranges: Vec<(u64, u64)>,
+  pub unit_offset: u64,
+  pub die_offset: u64,
+}
+
+struct Abbreviation {
+  tag: u64,
+  has_children: bool,
+  //attribute, form, implicit_const value
+  attributes: Vec<(u64, u64, i64)>,
+}
+
+pub struct Dwarf<'a> {
+  pub big_endian: bool,
+  pub debug_info: &'a [u8],
+  pub debug_abbrev: &'a [u8],
+  pub debug_str: &'a [u8],
+  pub debug_line: &'a [u8],
+  pub debug_line_str: &'a [u8],
+  pub debug_addr: &'a [u8],
+  pub debug_str_offsets: &'a [u8],
+  pub debug_ranges: &'a [u8],
+  pub debug_rnglists: &'a [u8],
+}
+
+pub(crate) struct Reader<'a> {
+  pub(crate) data: &'a [u8],
+  pub(crate) position: usize,
+  pub(crate) big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+  pub(crate) fn bytes(&mut self, size: usize) -> Option<&'a [u8]> {
+    let bytes = self.data.get(self.position..self.position.checked_add(size)?)?;
+    self.position += size;
+    Some(bytes)
+  }
+
+  pub(crate) fn unsigned(&mut self, size: usize) -> Option<u64> {
+    let bytes = self.bytes(size)?;
+    Some(read_unsigned(bytes, self.big_endian))
+  }
+
+  pub(crate) fn uleb(&mut self) -> Option<u64> {
+    read_uleb128(self.data, &mut self.position)
+  }
+
+  pub(crate) fn sleb(&mut self) -> Option<i64> {
+    read_sleb128(self.data, &mut self.position)
+  }
+
+  pub(crate) fn c_string(&mut self) -> Option<&'a [u8]> {
+    let rest = self.data.get(self.position..)?;
+    let end = rest.iter().position(|&b| b == 0)?;
+    self.position += end + 1;
+    Some(&rest[..end])
+  }
+
+  //returns (offset_size, unit end)
+  pub(crate) fn initial_length(&mut self) -> Option<(u8, usize)> {
+    let length = self.unsigned(4)?;
+    if length == 0xffff_ffff {
+      let length = self.unsigned(8)? as usize;
+      Some((8, self.position.checked_add(length)?))
+    } else {
+      Some((4, self.position.checked_add(length as usize)?))
+    }
+  }
+}
+
+pub(crate) fn read_unsigned(bytes: &[u8], big_endian: bool) -> u64 {
+  match (bytes.len(), big_endian) {
+    (1, _) => bytes[0] as u64,
+    (2, false) => LittleEndian::read_u16(bytes) as u64,
+    (2, true) => BigEndian::read_u16(bytes) as u64,
+    (3, false) => LittleEndian::read_u24(bytes) as u64,
+    (3, true) => BigEndian::read_u24(bytes) as u64,
+    (4, false) => LittleEndian::read_u32(bytes) as u64,
+    (4, true) => BigEndian::read_u32(bytes) as u64,
+    (8, false) => LittleEndian::read_u64(bytes),
+    (8, true) => BigEndian::read_u64(bytes),
+    _ => 0,
+  }
+}
+
+fn section_slice(data: &[u8], offset: u64, size: usize) -> Option<&[u8]> {
+  let start = usize::try_from(offset).ok()?;
+  data.get(start..start.checked_add(size)?)
+}
+
+fn c_string_at(data: &[u8], offset: u64) -> Option<&[u8]> {
+  let rest = data.get(offset as usize..)?;
+  let end = rest.iter().position(|&b| b == 0)?;
+  Some(&rest[..end])
+}
+
+impl<'a> Dwarf<'a> {
+  fn reader(&self, data: &'a [u8], position: usize) -> Reader<'a> {
+    Reader { data, position, big_endian: self.big_endian }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.debug_info.is_empty()
+  }
+
+  pub fn unit_headers(&self) -> Vec<UnitHeader> {
+    let mut headers = Vec::new();
+    let mut offset = 0;
+    while offset < self.debug_info.len() {
+      match self.unit_header(offset) {
+        Some(header) => {
+          offset = header.end as usize;
+          headers.push(header);
+        },
+        None => break,
+      }
+    }
+    headers
+  }
+
+  fn unit_header(&self, offset: usize) -> Option<UnitHeader> {
+    let mut reader = self.reader(self.debug_info, offset);
+    let (offset_size, end) = reader.initial_length()?;
+    let version = reader.unsigned(2)? as u16;
+    let (unit_type, address_size, abbrev_offset) = if version >= 5 {
+      let unit_type = reader.unsigned(1)? as u8;
+      let address_size = reader.unsigned(1)? as u8;
+      let abbrev_offset = reader.unsigned(offset_size as usize)?;
+      match unit_type {
+        //skeleton and split compile units carry a dwo id
+        4 | 5 => { reader.bytes(8)?; },
+        //type units carry a signature and a type offset
+        2 | 6 => { reader.bytes(8 + offset_size as usize)?; },
+        _ => {},
+      };
+      (unit_type, address_size, abbrev_offset)
+    } else if (2..=4).contains(&version) {
+      let abbrev_offset = reader.unsigned(offset_size as usize)?;
+      let address_size = reader.unsigned(1)? as u8;
+      (1, address_size, abbrev_offset)
+    } else {
+      return None;
+    };
+    if end > self.debug_info.len() {
+      return None;
+    }
+    Some(UnitHeader {
+      offset: offset as u64,
+      version,
+      unit_type,
+      address_size,
+      offset_size,
+      abbrev_offset,
+      dies_offset: reader.position as u64,
+      end: end as u64,
+    })
+  }
+
+  fn abbreviations(&self, offset: u64) -> HashMap<u64, Abbreviation> {
+    let mut abbreviations = HashMap::new();
+    let mut reader = self.reader(self.debug_abbrev, offset as usize);
+    while let Some(code) = reader.uleb() {
+      if code == 0 {
+        break;
+      }
+      let (tag, has_children) = match (reader.uleb(), reader.unsigned(1)) {
+        (Some(tag), Some(children)) => (tag, children != 0),
+        _ => break,
+      };
+      let mut attributes = Vec::new();
+      while let (Some(attribute), Some(form)) = (reader.uleb(), reader.uleb()) {
+        if attribute == 0 && form == 0 {
+          break;
+        }
+        let implicit = if form == DW_FORM_IMPLICIT_CONST { reader.sleb().unwrap_or(0) } else { 0 };
+        attributes.push((attribute, form, implicit));
+      }
+      abbreviations.insert(code, Abbreviation { tag, has_children, attributes });
+    }
+    abbreviations
+  }
+
+  pub fn units(&self) -> Vec<Unit<'a>> {
+    self.unit_headers().into_iter().filter_map(|header| self.unit(header)).collect()
+  }
+
+  pub fn unit(&self, header: UnitHeader) -> Option<Unit<'a>> {
+    let abbreviations = self.abbreviations(header.abbrev_offset);
+    let mut reader = self.reader(self.debug_info.get(..header.end as usize)?, header.dies_offset as usize);
+    let mut dies = Vec::new();
+    let mut parents = Vec::new();
+    let mut indices = HashMap::new();
+    let mut stack: Vec<usize> = Vec::new();
+    while reader.position < header.end as usize {
+      let offset = reader.position as u64;
+      let code = reader.uleb()?;
+      if code == 0 {
+        stack.pop();
+        if stack.is_empty() {
+          break;
+        }
+        continue;
+      }
+      let abbreviation = abbreviations.get(&code)?;
+      let mut attributes = Vec::with_capacity(abbreviation.attributes.len());
+      for &(attribute, form, implicit) in &abbreviation.attributes {
+        let value = self.attribute_value(&mut reader, &header, form, implicit)?;
+        attributes.push((attribute, value));
+      }
+      let index = dies.len();
+      indices.insert(offset, index);
+      parents.push(stack.last().copied());
+      dies.push(Die { offset, tag: abbreviation.tag, depth: stack.len(), has_children: abbreviation.has_children, attributes });
+      if abbreviation.has_children {
+        stack.push(index);
+      } else if stack.is_empty() {
+        break;
+      }
+    }
+
+    let root = dies.first();
+    let base = |attribute| root.and_then(|die: &Die| die.unsigned(attribute));
+    let str_offsets_base = base(DW_AT_STR_OFFSETS_BASE).unwrap_or(if header.offset_size == 8 { 16 } else { 8 });
+    let addr_base = base(DW_AT_ADDR_BASE).unwrap_or(8);
+    let rnglists_base = base(DW_AT_RNGLISTS_BASE).unwrap_or(if header.offset_size == 8 { 20 } else { 12 });
+    let mut unit = Unit { header, dies, parents, indices, str_offsets_base, addr_base, rnglists_base };
+    self.resolve_indices(&mut unit);
+    Some(unit)
+  }
+
+  fn resolve_indices(&self, unit: &mut Unit<'a>) {
+    let offset_size = unit.header.offset_size as usize;
+    let address_size = unit.header.address_size as usize;
+    let (str_offsets_base, addr_base) = (unit.str_offsets_base, unit.addr_base);
+    for die in unit.dies.iter_mut() {
+      for (_, value) in die.attributes.iter_mut() {
+        match *value {
+          AttributeValue::StringIndex(index) => {
+            let entry = index.checked_mul(offset_size as u64).and_then(|entry| entry.checked_add(str_offsets_base));
+            if let Some(bytes) = entry.and_then(|entry| section_slice(self.debug_str_offsets, entry, offset_size)) {
+              if let Some(string) = c_string_at(self.debug_str, read_unsigned(bytes, self.big_endian)) {
+                *value = AttributeValue::String(string);
+              }
+            }
+          },
+          AttributeValue::AddressIndex(index) => {
+            if let Some(address) = self.address_at(addr_base, index, address_size) {
+              *value = AttributeValue::Address(address);
+            }
+          },
+          _ => {},
+        };
+      }
+    }
+  }
+
+  fn address_at(&self, addr_base: u64, index: u64, address_size: usize) -> Option<u64> {
+    let entry = addr_base.checked_add(index.checked_mul(address_size as u64)?)?;
+    section_slice(self.debug_addr, entry, address_size).map(|bytes| read_unsigned(bytes, self.big_endian))
+  }
+
+  fn attribute_value(&self, reader: &mut Reader<'a>, header: &UnitHeader, form: u64, implicit: i64) -> Option<AttributeValue<'a>> {
+    let offset_size = header.offset_size as usize;
+    let address_size = header.address_size as usize;
+    let value = match form {
+      DW_FORM_ADDR => AttributeValue::Address(reader.unsigned(address_size)?),
+      DW_FORM_BLOCK1 => { let size = reader.unsigned(1)? as usize; AttributeValue::Block(reader.bytes(size)?) },
+      DW_FORM_BLOCK2 => { let size = reader.unsigned(2)? as usize; AttributeValue::Block(reader.bytes(size)?) },
+      DW_FORM_BLOCK4 => { let size = reader.unsigned(4)? as usize; AttributeValue::Block(reader.bytes(size)?) },
+      DW_FORM_BLOCK => { let size = reader.uleb()? as usize; AttributeValue::Block(reader.bytes(size)?) },
+      DW_FORM_EXPRLOC => { let size = reader.uleb()? as usize; AttributeValue::Expression(reader.bytes(size)?) },
+      DW_FORM_DATA1 => AttributeValue::Unsigned(reader.unsigned(1)?),
+      DW_FORM_DATA2 => AttributeValue::Unsigned(reader.unsigned(2)?),
+      DW_FORM_DATA4 => AttributeValue::Unsigned(reader.unsigned(4)?),
+      DW_FORM_DATA8 => AttributeValue::Unsigned(reader.unsigned(8)?),
+      DW_FORM_DATA16 => AttributeValue::Block(reader.bytes(16)?),
+      DW_FORM_SDATA => AttributeValue::Signed(reader.sleb()?),
+      DW_FORM_UDATA => AttributeValue::Unsigned(reader.uleb()?),
+      DW_FORM_IMPLICIT_CONST => AttributeValue::Signed(implicit),
+      DW_FORM_FLAG => AttributeValue::Flag(reader.unsigned(1)? != 0),
+      DW_FORM_FLAG_PRESENT => AttributeValue::Flag(true),
+      DW_FORM_STRING => AttributeValue::String(reader.c_string()?),
+      DW_FORM_STRP => AttributeValue::String(c_string_at(self.debug_str, reader.unsigned(offset_size)?).unwrap_or(&[])),
+      DW_FORM_LINE_STRP => AttributeValue::String(c_string_at(self.debug_line_str, reader.unsigned(offset_size)?).unwrap_or(&[])),
+      DW_FORM_STRX | DW_FORM_GNU_STR_INDEX => AttributeValue::StringIndex(reader.uleb()?),
+      DW_FORM_STRX1 => AttributeValue::StringIndex(reader.unsigned(1)?),
+      DW_FORM_STRX2 => AttributeValue::StringIndex(reader.unsigned(2)?),
+      DW_FORM_STRX3 => AttributeValue::StringIndex(reader.unsigned(3)?),
+      DW_FORM_STRX4 => AttributeValue::StringIndex(reader.unsigned(4)?),
+      DW_FORM_ADDRX | DW_FORM_GNU_ADDR_INDEX => AttributeValue::AddressIndex(reader.uleb()?),
+      DW_FORM_ADDRX1 => AttributeValue::AddressIndex(reader.unsigned(1)?),
+      DW_FORM_ADDRX2 => AttributeValue::AddressIndex(reader.unsigned(2)?),
+      DW_FORM_ADDRX3 => AttributeValue::AddressIndex(reader.unsigned(3)?),
+      DW_FORM_ADDRX4 => AttributeValue::AddressIndex(reader.unsigned(4)?),
+      DW_FORM_REF1 => AttributeValue::Reference(header.offset.checked_add(reader.unsigned(1)?)?),
+      DW_FORM_REF2 => AttributeValue::Reference(header.offset.checked_add(reader.unsigned(2)?)?),
+      DW_FORM_REF4 => AttributeValue::Reference(header.offset.checked_add(reader.unsigned(4)?)?),
+      DW_FORM_REF8 => AttributeValue::Reference(header.offset.checked_add(reader.unsigned(8)?)?),
+      DW_FORM_REF_UDATA => AttributeValue::Reference(header.offset.checked_add(reader.uleb()?)?),
+      DW_FORM_REF_ADDR => {
+        let size = if header.version <= 2 { address_size } else { offset_size };
+        AttributeValue::Reference(reader.unsigned(size)?)
+      },
+      DW_FORM_REF_SIG8 => AttributeValue::TypeSignature(reader.unsigned(8)?),
+      DW_FORM_SEC_OFFSET => AttributeValue::SectionOffset(reader.unsigned(offset_size)?),
+      DW_FORM_LOCLISTX => AttributeValue::LocationListIndex(reader.uleb()?),
+      DW_FORM_RNGLISTX => AttributeValue::RangeListIndex(reader.uleb()?),
+      DW_FORM_REF_SUP4 => AttributeValue::Supplementary(reader.unsigned(4)?),
+      DW_FORM_REF_SUP8 => AttributeValue::Supplementary(reader.unsigned(8)?),
+      DW_FORM_STRP_SUP | DW_FORM_GNU_STRP_ALT | DW_FORM_GNU_REF_ALT => AttributeValue::Supplementary(reader.unsigned(offset_size)?),
+      DW_FORM_INDIRECT => {
+        let form = reader.uleb()?;
+        return self.attribute_value(reader, header, form, implicit);
+      },
+      _ => return None,
+    };
+    Some(value)
+  }
+
+  //Address ranges covered by a DIE, from low/high pc or DW_AT_RANGES.
+  pub fn die_ranges(&self, unit: &Unit<'a>, die: &Die<'a>) -> Vec<(u64, u64)> {
+    if let (Some(low), None) = (die.unsigned(DW_AT_LOW_PC), die.attribute(DW_AT_RANGES)) {
+      let high = match die.attribute(DW_AT_HIGH_PC) {
+        Some(AttributeValue::Address(high)) => high,
+        Some(AttributeValue::Unsigned(size)) => low.wrapping_add(size),
+        Some(AttributeValue::Signed(size)) => low.wrapping_add(size as u64),
+        _ => low.wrapping_add(1),
+      };
+      return if high > low { vec![(low, high)] } else { Vec::new() };
+    }
+    let base = unit.root().and_then(|root| root.unsigned(DW_AT_LOW_PC)).unwrap_or(0);
+    match die.attribute(DW_AT_RANGES) {
+      Some(AttributeValue::SectionOffset(offset)) | Some(AttributeValue::Unsigned(offset)) if unit.header.version >= 5 => {
+        self.range_list(unit, offset, base)
+      },
+      Some(AttributeValue::SectionOffset(offset)) | Some(AttributeValue::Unsigned(offset)) => self.legacy_ranges(unit, offset, base),
+      Some(AttributeValue::RangeListIndex(index)) => {
+        let offset_size = unit.header.offset_size as usize;
+        let entry = index.checked_mul(offset_size as u64).and_then(|entry| entry.checked_add(unit.rnglists_base));
+        let offset = entry.and_then(|entry| section_slice(self.debug_rnglists, entry, offset_size))
+          .and_then(|bytes| unit.rnglists_base.checked_add(read_unsigned(bytes, self.big_endian)));
+        match offset {
+          Some(offset) => self.range_list(unit, offset, base),
+          None => Vec::new(),
+        }
+      },
+      _ => Vec::new(),
+    }
+  }
+
+  fn legacy_ranges(&self, unit: &Unit<'a>, offset: u64, mut base: u64) -> Vec<(u64, u64)> {
+    let size = unit.header.address_size as usize;
+    let max = if size == 4 { 0xffff_ffff } else { u64::MAX };
+    let mut reader = self.reader(self.debug_ranges, offset as usize);
+    let mut ranges = Vec::new();
+    while let (Some(begin), Some(end)) = (reader.unsigned(size), reader.unsigned(size)) {
+      if begin == 0 && end == 0 {
+        break;
+      }
+      if begin == max {
+        base = end;
+      } else if end > begin {
+        ranges.push((base.wrapping_add(begin), base.wrapping_add(end)));
+      }
+    }
+    ranges
+  }
+
+  fn range_list(&self, unit: &Unit<'a>, offset: u64, mut base: u64) -> Vec<(u64, u64)> {
+    let size = unit.header.address_size as usize;
+    let address = |index| self.address_at(unit.addr_base, index, size).unwrap_or(0);
+    let mut reader = self.reader(self.debug_rnglists, offset as usize);
+    let mut ranges = Vec::new();
+    loop {
+      let (begin, end) = match reader.unsigned(1) {
+        Some(1) => { base = address(reader.uleb().unwrap_or(0)); continue; },
+        Some(2) => match (reader.uleb(), reader.uleb()) {
+          (Some(begin), Some(end)) => (address(begin), address(end)),
+          _ => break,
+        },
+        Some(3) => match (reader.uleb(), reader.uleb()) {
+          (Some(begin), Some(length)) => { let begin = address(begin); (begin, begin.wrapping_add(length)) },
+          _ => break,
+        },
+        Some(4) => match (reader.uleb(), reader.uleb()) {
+          (Some(begin), Some(end)) => (base.wrapping_add(begin), base.wrapping_add(end)),
+          _ => break,
+        },
+        Some(5) => { base = reader.unsigned(size).unwrap_or(0); continue; },
+        Some(6) => match (reader.unsigned(size), reader.unsigned(size)) {
+          (Some(begin), Some(end)) => (begin, end),
+          _ => break,
+        },
+        Some(7) => match (reader.unsigned(size), reader.uleb()) {
+          (Some(begin), Some(length)) => (begin, begin.wrapping_add(length)),
+          _ => break,
+        },
+        _ => break,
+      };
+      if end > begin {
+        ranges.push((begin, end));
+      }
+    }
+    ranges
+  }
+
+  pub fn functions(&self) -> Vec<DwarfFunction> {
+    self.units().iter().flat_map(|unit| self.unit_functions(unit)).collect()
+  }
+
+  //Concrete (out of line) functions of a unit, declarations and abstract instances are skipped.
+  pub fn unit_functions(&self, unit: &Unit<'a>) -> Vec<DwarfFunction> {
+    let mut functions = Vec::new();
+    for (index, die) in unit.dies.iter().enumerate() {
+      if die.tag != DW_TAG_SUBPROGRAM || die.flag(DW_AT_DECLARATION) {
+        continue;
+      }
+      let ranges = self.die_ranges(unit, die);
+      if ranges.is_empty() {
+        continue;
+      }
+      let linkage_name = match unit.inherited_attribute(index, DW_AT_LINKAGE_NAME).or_else(|| unit.inherited_attribute(index, DW_AT_MIPS_LINKAGE_NAME)) {
+        Some(AttributeValue::String(bytes)) => Some(String::from_utf8_lossy(bytes).into_owned()),
+        _ => None,
+      };
+      functions.push(DwarfFunction {
+        name: unit.qualified_name(index).unwrap_or_else(|| String::from("<unknown>")),
+        linkage_name,
+        ranges,
+        unit_offset: unit.header.offset,
+        die_offset: die.offset,
+      });
+    }
+    functions
+  }
+
+  pub fn line_program(&self, unit: &Unit<'a>) -> Option<LineProgram> {
+    let offset = unit.root()?.unsigned(DW_AT_STMT_LIST)?;
+    self.line_program_at(offset, unit.header.address_size)
+  }
+
+  pub fn line_program_at(&self, offset: u64, address_size: u8) -> Option<LineProgram> {
+    let mut reader = self.reader(self.debug_line, offset as usize);
+    let (offset_size, end) = reader.initial_length()?;
+    let version = reader.unsigned(2)? as u16;
+    let mut address_size = address_size as usize;
+    if version >= 5 {
+      address_size = reader.unsigned(1)? as usize;
+      reader.unsigned(1)?;
+    }
+    let header_length = reader.unsigned(offset_size as usize)?;
+    let program_start = usize::try_from(header_length).ok()?.checked_add(reader.position).filter(|&start| start <= end)?;
+    let minimum_instruction_length = reader.unsigned(1)?;
+    if version >= 4 {
+      reader.unsigned(1)?;
+    }
+    let default_is_stmt = reader.unsigned(1)? != 0;
+    let line_base = reader.unsigned(1)? as u8 as i8 as i64;
+    let line_range = reader.unsigned(1)?;
+    let opcode_base = reader.unsigned(1)? as u8;
+    let mut standard_lengths = Vec::new();
+    for _ in 1..opcode_base {
+      standard_lengths.push(reader.unsigned(1)? as u8);
+    }
+
+    let mut directories = Vec::new();
+    let mut files = Vec::new();
+    if version >= 5 {
+      for entry in self.line_entries(&mut reader, offset_size)? {
+        directories.push(entry.name);
+      }
+      files = self.line_entries(&mut reader, offset_size)?;
+    } else {
+      while let Some(directory) = reader.c_string() {
+        if directory.is_empty() {
+          break;
+        }
+        directories.push(String::from_utf8_lossy(directory).into_owned());
+      }
+      while let Some(name) = reader.c_string() {
+        if name.is_empty() {
+          break;
+        }
+        let directory_index = reader.uleb()?;
+        reader.uleb()?;
+        reader.uleb()?;
+        files.push(LineFile { name: String::from_utf8_lossy(name).into_owned(), directory_index, md5: None });
+      }
+    }
+
+    reader.position = program_start;
+    reader.data = &self.debug_line[..end.min(self.debug_line.len())];
+    let initial = LineRow { file_index: 1, line: 1, is_stmt: default_is_stmt, ..Default::default() };
+    let mut row = initial;
+    let mut rows = Vec::new();
+    while reader.position < reader.data.len() {
+      let opcode = reader.unsigned(1)? as u8;
+      if opcode >= opcode_base {
+        let adjusted = (opcode - opcode_base) as u64;
+        row.address = row.address.wrapping_add(adjusted / line_range.max(1) * minimum_instruction_length);
+        row.line = row.line.checked_add_signed(line_base + (adjusted % line_range.max(1)) as i64)?;
+        rows.push(row);
+        row.discriminator = 0;
+        continue;
+      }
+      match opcode {
+        0 => {
+          let length = usize::try_from(reader.uleb()?).ok().filter(|&length| length > 0)?;
+          let next = reader.position.checked_add(length)?;
+          match reader.unsigned(1)? {
+            1 => {
+              row.end_sequence = true;
+              rows.push(row);
+              row = initial;
+            },
+            //DW_LNE_set_address, an address of the unit's size
+            2 if length.checked_sub(1)? != address_size => return None,
+            2 => row.address = reader.unsigned(address_size)?,
+            4 => row.discriminator = reader.uleb()?,
+            _ => {},
+          };
+          reader.position = next;
+        },
+        1 => {
+          rows.push(row);
+          row.discriminator = 0;
+        },
+        2 => row.address = row.address.wrapping_add(reader.uleb()?.wrapping_mul(minimum_instruction_length)),
+        3 => row.line = row.line.checked_add_signed(reader.sleb()?)?,
+        4 => row.file_index = reader.uleb()?,
+        5 => row.column = reader.uleb()?,
+        6 => row.is_stmt = !row.is_stmt,
+        7 => {},
+        8 => {
+          let adjusted = (255 - opcode_base) as u64;
+          row.address = row.address.wrapping_add(adjusted / line_range.max(1) * minimum_instruction_length);
+        },
+        9 => row.address = row.address.wrapping_add(reader.unsigned(2)?),
+        10 | 11 => {},
+        12 => { reader.uleb()?; },
+        _ => {
+          for _ in 0..standard_lengths.get(opcode as usize - 1).copied().unwrap_or(0) {
+            reader.uleb()?;
+          }
+        },
+      };
+    }
+    Some(LineProgram { version, directories, files, rows })
+  }
+
+  fn line_entries(&self, reader: &mut Reader<'a>, offset_size: u8) -> Option<Vec<LineFile>> {
+    let format_count = reader.unsigned(1)?;
+    let mut formats = Vec::new();
+    for _ in 0..format_count {
+      formats.push((reader.uleb()?, reader.uleb()?));
+    }
+    let count = reader.uleb()?;
+    //every entry takes a byte at least
+    if count > 0 && (formats.is_empty() || count > reader.data.len().saturating_sub(reader.position) as u64) {
+      return None;
+    }
+    let mut entries = Vec::new();
+    for _ in 0..count {
+      let mut entry = LineFile { name: String::new(), directory_index: 0, md5: None };
+      for &(content, form) in &formats {
+        let mut string = None;
+        let mut number = None;
+        match form {
+          DW_FORM_STRING => string = reader.c_string(),
+          DW_FORM_LINE_STRP => string = c_string_at(self.debug_line_str, reader.unsigned(offset_size as usize)?),
+          DW_FORM_STRP => string = c_string_at(self.debug_str, reader.unsigned(offset_size as usize)?),
+          DW_FORM_UDATA => number = reader.uleb(),
+          DW_FORM_DATA1 => number = reader.unsigned(1),
+          DW_FORM_DATA2 => number = reader.unsigned(2),
+          DW_FORM_DATA4 => number = reader.unsigned(4),
+          DW_FORM_DATA8 => number = reader.unsigned(8),
+          DW_FORM_DATA16 => {
+            let bytes = reader.bytes(16)?;
+            if content == 5 {
+              let mut md5 = [0; 16];
+              md5.copy_from_slice(bytes);
+              entry.md5 = Some(md5);
+            }
+          },
+          DW_FORM_BLOCK => { let size = reader.uleb()? as usize; reader.bytes(size)?; },
+          _ => return None,
+        };
+        match content {
+          1 => entry.name = String::from_utf8_lossy(string.unwrap_or(&[])).into_owned(),
+          2 => entry.directory_index = number.unwrap_or(0),
+          _ => {},
+        };
+      }
+      entries.push(entry);
+    }
+    Some(entries)
+  }
+}
+
+//The .debug_* sections of an ET_REL object with their relocations applied, built on first
+//use. In an object file every DW_FORM_strp, DW_FORM_line_strp and DW_AT_stmt_list is 0 plus
+//a relocation against the target section.
+#[derive(Default)]
+pub(crate) struct RelocatedSections {
+  sections: OnceLock<HashMap<usize, Box<[u8]>>>,
+}
+
+impl RelocatedSections {
+  pub(crate) fn memory_usage(&self) -> usize {
+    self.sections.get().map_or(0, |sections| sections.values().map(|data| data.len()).sum())
+  }
+}
+
+#[derive(Clone, Copy)]
+enum DebugRelocation {
+  //S + A
+  Absolute(usize),
+  //the target plus or minus S + A, RISC-V label differences
+  Add(usize),
+  Subtract(usize),
+}
+
+fn write_unsigned(bytes: &mut [u8], value: u64, big_endian: bool) {
+  match (bytes.len(), big_endian) {
+    (1, _) => bytes[0] = value as u8,
+    (2, false) => LittleEndian::write_u16(bytes, value as u16),
+    (2, true) => BigEndian::write_u16(bytes, value as u16),
+    (4, false) => LittleEndian::write_u32(bytes, value as u32),
+    (4, true) => BigEndian::write_u32(bytes, value as u32),
+    (8, false) => LittleEndian::write_u64(bytes, value),
+    (8, true) => BigEndian::write_u64(bytes, value),
+    _ => {},
+  }
+}
+
+impl Elf {
+  //The data relocation types compilers emit into debug sections. Others are left as they are.
+  fn debug_relocation(&self, relocation_type: u32) -> Option<DebugRelocation> {
+    use DebugRelocation::*;
+    let relocation = match (self.header.description.machine, relocation_type) {
+      //R_X86_64_64, R_X86_64_DTPOFF64
+      (EM_X86_64, 1 | 17) => Absolute(8),
+      //R_X86_64_32, R_X86_64_32S, R_X86_64_DTPOFF32
+      (EM_X86_64, 10 | 11 | 21) => Absolute(4),
+      //R_386_32, R_386_TLS_LDO_32
+      (EM_386, 1 | 32) => Absolute(4),
+      //R_AARCH64_ABS64, ABS32, ABS16
+      (EM_AARCH64, 257) => Absolute(8),
+      (EM_AARCH64, 258) => Absolute(4),
+      (EM_AARCH64, 259) => Absolute(2),
+      //R_ARM_ABS32, R_ARM_TLS_LDO32
+      (EM_ARM, 2 | 106) => Absolute(4),
+      (EM_RISCV, 1) => Absolute(4),
+      (EM_RISCV, 2) => Absolute(8),
+      (EM_RISCV, 33) => Add(1),
+      (EM_RISCV, 34) => Add(2),
+      (EM_RISCV, 35) => Add(4),
+      (EM_RISCV, 36) => Add(8),
+      (EM_RISCV, 37) => Subtract(1),
+      (EM_RISCV, 38) => Subtract(2),
+      (EM_RISCV, 39) => Subtract(4),
+      (EM_RISCV, 40) => Subtract(8),
+      //R_RISCV_SET8, SET16, SET32
+      (EM_RISCV, 54) => Absolute(1),
+      (EM_RISCV, 55) => Absolute(2),
+      (EM_RISCV, 56) => Absolute(4),
+      //R_PPC_ADDR32, R_PPC64_ADDR64
+      (EM_PPC | EM_PPC64, 1) => Absolute(4),
+      (EM_PPC64, 38) => Absolute(8),
+      //R_390_32, R_390_64
+      (EM_S390, 4) => Absolute(4),
+      (EM_S390, 22) => Absolute(8),
+      //R_LARCH_32, R_LARCH_64
+      (EM_LOONGARCH, 1) => Absolute(4),
+      (EM_LOONGARCH, 2) => Absolute(8),
+      //R_MIPS_32
+      (EM_MIPS, 2) if self.header.identification.class == ELFCLASS32 => Absolute(4),
+      _ => return None,
+    };
+    Some(relocation)
+  }
+
+  fn relocate_debug_sections(&self) -> HashMap<usize, Box<[u8]>> {
+    let mut sections = HashMap::new();
+    if self.header.description.obj_type != ET_REL {
+      return sections;
+    }
+    let big_endian = self.header.identification.endianness == 2;
+    let symbols = self.symbol_table();
+    for relocation in self.relocations() {
+      let target = match self.section_headers.get(relocation.section_index) {
+        Some(section) => section.info as usize,
+        None => continue,
+      };
+      let section = match self.section_headers.get(target) {
+        Some(section) if self.section_name(section).is_some_and(|name| name.starts_with(".debug_")) => section,
+        _ => continue,
+      };
+      let (kind, size) = match self.debug_relocation(relocation.relocation_type) {
+        Some(kind @ (DebugRelocation::Absolute(size) | DebugRelocation::Add(size) | DebugRelocation::Subtract(size))) => (kind, size),
+        None => continue,
+      };
+      let data = sections.entry(target).or_insert_with(|| Box::from(self.section_data(section)));
+      let bytes = match usize::try_from(relocation.offset).ok().and_then(|start| data.get_mut(start..start.checked_add(size)?)) {
+        Some(bytes) => bytes,
+        None => continue,
+      };
+      let symbol = symbols.get(relocation.symbol_index as usize).map_or(0, |symbol| symbol.value);
+      let current = read_unsigned(bytes, big_endian);
+      //REL keeps the addend at the target
+      let value = symbol.wrapping_add(relocation.addend.map_or(current, |addend| addend as u64));
+      let value = match kind {
+        DebugRelocation::Absolute(_) => value,
+        DebugRelocation::Add(_) => current.wrapping_add(value),
+        DebugRelocation::Subtract(_) => current.wrapping_sub(value),
+      };
+      write_unsigned(bytes, value, big_endian);
+    }
+    sections
+  }
+
+  //A debug section's contents, relocated in ET_REL objects.
+  pub(crate) fn debug_section(&self, name: &str) -> &[u8] {
+    let index = match self.section_index_by_name(name) {
+      Some(index) => index,
+      None => return &[],
+    };
+    match self.relocated_sections.sections.get_or_init(|| self.relocate_debug_sections()).get(&index) {
+      Some(data) => data,
+      None => self.section_headers.get(index).map_or(&[], |section| self.section_data(section)),
+    }
+  }
+}
+
+impl Elf {
+  pub fn dwarf(&self) -> Dwarf<'_> {
+    let section = |name: &str| self.debug_section(name);
+    Dwarf {
+      big_endian: self.header.identification.endianness == 2,
+      debug_info: section(".debug_info"),
+      debug_abbrev: section(".debug_abbrev"),
+      debug_str: section(".debug_str"),
+      debug_line: section(".debug_line"),
+      debug_line_str: section(".debug_line_str"),
+      debug_addr: section(".debug_addr"),
+      debug_str_offsets: section(".debug_str_offsets"),
+      debug_ranges: section(".debug_ranges"),
+      debug_rnglists: section(".debug_rnglists"),
+    }
+  }
+}
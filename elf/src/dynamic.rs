@@ -0,0 +1,215 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::{read_bytes, Elf};
+use crate::entry_table::EntryTable;
+use crate::layout::*;
+
+#[derive(Default, Clone, Copy)]
+pub struct DynamicEntry {
+  pub tag: u64,
+  pub value: u64,
+}
+
+const DF_NAMES: &[(u64, &str)] = &[
+  (DF_ORIGIN, "ORIGIN"), (DF_SYMBOLIC, "SYMBOLIC"), (DF_TEXTREL, "TEXTREL"), (DF_BIND_NOW, "BIND_NOW"), (DF_STATIC_TLS, "STATIC_TLS"),
+];
+
+const DF_1_NAMES: &[(u64, &str)] = &[
+  (DF_1_NOW, "NOW"), (DF_1_GLOBAL, "GLOBAL"), (DF_1_GROUP, "GROUP"), (DF_1_NODELETE, "NODELETE"), (DF_1_LOADFLTR, "LOADFLTR"),
+  (DF_1_INITFIRST, "INITFIRST"), (DF_1_NOOPEN, "NOOPEN"), (DF_1_ORIGIN, "ORIGIN"), (DF_1_DIRECT, "DIRECT"), (DF_1_TRANS, "TRANS"),
+  (DF_1_INTERPOSE, "INTERPOSE"), (DF_1_NODEFLIB, "NODEFLIB"), (DF_1_NODUMP, "NODUMP"), (DF_1_CONFALT, "CONFALT"),
+  (DF_1_ENDFILTEE, "ENDFILTEE"), (DF_1_DISPRELDNE, "DISPRELDNE"), (DF_1_DISPRELPND, "DISPRELPND"), (DF_1_NODIRECT, "NODIRECT"),
+  (DF_1_IGNMULDEF, "IGNMULDEF"), (DF_1_NOKSYMS, "NOKSYMS"), (DF_1_NOHDR, "NOHDR"), (DF_1_EDITED, "EDITED"), (DF_1_NORELOC, "NORELOC"),
+  (DF_1_SYMINTPOSE, "SYMINTPOSE"), (DF_1_GLOBAUDIT, "GLOBAUDIT"), (DF_1_SINGLETON, "SINGLETON"), (DF_1_STUB, "STUB"), (DF_1_PIE, "PIE"),
+  (DF_1_KMOD, "KMOD"), (DF_1_WEAKFILTER, "WEAKFILTER"), (DF_1_NOCOMMON, "NOCOMMON"),
+];
+
+//names of the set bits, bits without a name as hex
+fn flag_names(value: u64, names: &[(u64, &'static str)]) -> Vec<String> {
+  let mut known = 0;
+  let mut decoded: Vec<String> = names.iter()
+    .filter(|&&(bit, _)| value & bit != 0)
+    .map(|&(bit, name)| {
+      known |= bit;
+      name.to_string()
+    })
+    .collect();
+  if value & !known != 0 {
+    decoded.push(format!("{:#x}", value & !known));
+  }
+  decoded
+}
+
+//DT_FLAGS and DT_FLAGS_1, 0 when the entry is missing.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct DynamicFlags {
+  pub flags: u64,
+  pub flags_1: u64,
+}
+
+impl DynamicFlags {
+  //linkers emit each entry once, repeated ones are merged
+  pub fn from_entries(entries: &[DynamicEntry]) -> DynamicFlags {
+    let mut flags: DynamicFlags = Default::default();
+    for entry in entries {
+      match entry.tag {
+        DT_FLAGS => flags.flags |= entry.value,
+        DT_FLAGS_1 => flags.flags_1 |= entry.value,
+        _ => {},
+      };
+    }
+    flags
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.flags == 0 && self.flags_1 == 0
+  }
+
+  //"ORIGIN", "BIND_NOW", ...
+  pub fn flag_names(&self) -> Vec<String> {
+    flag_names(self.flags, DF_NAMES)
+  }
+
+  //"NOW", "NODELETE", "PIE", ...
+  pub fn flag_1_names(&self) -> Vec<String> {
+    flag_names(self.flags_1, DF_1_NAMES)
+  }
+
+  pub fn bind_now(&self) -> bool {
+    self.flags & DF_BIND_NOW != 0 || self.flags_1 & DF_1_NOW != 0
+  }
+
+  pub fn origin(&self) -> bool {
+    self.flags & DF_ORIGIN != 0 || self.flags_1 & DF_1_ORIGIN != 0
+  }
+}
+
+//"FLAGS BIND_NOW FLAGS_1 NOW PIE", "none" without either entry
+impl fmt::Display for DynamicFlags {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut parts = Vec::new();
+    if self.flags != 0 {
+      parts.push(format!("FLAGS {}", self.flag_names().join(" ")));
+    }
+    if self.flags_1 != 0 {
+      parts.push(format!("FLAGS_1 {}", self.flag_1_names().join(" ")));
+    }
+    match parts.is_empty() {
+      true => write!(f, "none"),
+      false => write!(f, "{}", parts.join(" ")),
+    }
+  }
+}
+
+impl Elf {
+  pub fn dynamic_flags(&self) -> DynamicFlags {
+    DynamicFlags::from_entries(&self.dynamic_entries())
+  }
+
+  //Read through PT_DYNAMIC like the loader does, falling back to the SHT_DYNAMIC section.
+  pub fn dynamic_entries(&self) -> Vec<DynamicEntry> {
+    let segment = self.program_headers.iter()
+      .find(|ph| ph.entry_type == PT_DYNAMIC)
+      .map(|ph| (ph.offset, ph.file_size));
+    let section = self.section_headers.iter()
+      .find(|section| section.section_type == SHT_DYNAMIC)
+      .map(|section| (section.offset, section.size));
+    let (offset, size) = match segment.or(section) {
+      Some(range) => range,
+      None => return Vec::new(),
+    };
+    let data = match self.data.get(offset as usize..offset.saturating_add(size) as usize) {
+      Some(data) => data,
+      None => return Vec::new(),
+    };
+    match self.header.identification.endianness {
+      1 => self.load_dynamic_entries_with_byteorder::<LittleEndian>(data),
+      2 => self.load_dynamic_entries_with_byteorder::<BigEndian>(data),
+      _ => panic!("unknown endianness"),
+    }
+  }
+
+  pub fn dynamic_value(&self, tag: u64) -> Option<u64> {
+    self.dynamic_entries().iter().find(|entry| entry.tag == tag).map(|entry| entry.value)
+  }
+
+  //A string of the dynamic string table, DT_STRTAB through the segments or .dynstr: like
+  //section_name, dynamic_string_str without the reason there is none.
+  pub fn dynamic_string(&self, offset: u64) -> Option<&str> {
+    self.dynamic_string_str(offset).ok()
+  }
+
+  pub fn dynamic_string_bytes(&self, offset: u64) -> Option<&[u8]> {
+    let table = match self.dynamic_value(DT_STRTAB).and_then(|address| self.address_to_offset(address)) {
+      Some(start) => {
+        let size = self.dynamic_value(DT_STRSZ).unwrap_or_else(|| (self.data.len() as u64).saturating_sub(start));
+        self.data.get(start as usize..start.saturating_add(size).min(self.data.len() as u64) as usize)?
+      },
+      None => {
+        let section = self.section_headers.iter().find(|section| section.section_type == SHT_DYNAMIC)?;
+        self.section_data(self.section_headers.get(section.link as usize)?)
+      },
+    };
+    read_bytes(table, offset as usize)
+  }
+
+  //invalid UTF-8 replaced by U+FFFD
+  pub fn dynamic_string_lossy(&self, offset: u64) -> Option<Cow<'_, str>> {
+    self.dynamic_string_bytes(offset).map(String::from_utf8_lossy)
+  }
+
+  //An error rather than None, telling a missing string from one that is not UTF-8.
+  pub fn dynamic_string_str(&self, offset: u64) -> io::Result<&str> {
+    let bytes = self.dynamic_string_bytes(offset)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no dynamic string at {}", offset)))?;
+    std::str::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("dynamic string {:?}: {}", String::from_utf8_lossy(bytes), error)))
+  }
+
+  fn dynamic_strings(&self, tag: u64) -> Vec<String> {
+    self.dynamic_entries().iter()
+      .filter(|entry| entry.tag == tag)
+      .filter_map(|entry| self.dynamic_string(entry.value))
+      .map(|string| string.to_string())
+      .collect()
+  }
+
+  pub fn needed_libraries(&self) -> Vec<String> {
+    self.dynamic_strings(DT_NEEDED)
+  }
+
+  pub fn soname(&self) -> Option<String> {
+    self.dynamic_strings(DT_SONAME).into_iter().next()
+  }
+
+  //DT_RPATH entries, unexpanded
+  pub fn rpath(&self) -> Vec<String> {
+    self.dynamic_strings(DT_RPATH).iter().flat_map(|rpath| rpath.split(':')).filter(|path| !path.is_empty()).map(String::from).collect()
+  }
+
+  pub fn runpath(&self) -> Vec<String> {
+    self.dynamic_strings(DT_RUNPATH).iter().flat_map(|runpath| runpath.split(':')).filter(|path| !path.is_empty()).map(String::from).collect()
+  }
+
+  pub(crate) fn load_dynamic_entries_with_byteorder<E: ByteOrder>(&self, data: &[u8]) -> Vec<DynamicEntry> {
+    match self.header.identification.class {
+      1 => Elf::load_dynamic_entries_with_layout::<E, Elf32>(data),
+      2 => Elf::load_dynamic_entries_with_layout::<E, Elf64>(data),
+      _ => panic!("unknown class"),
+    }
+  }
+
+  fn load_dynamic_entries_with_layout<E: ByteOrder, L: ElfLayout>(data: &[u8]) -> Vec<DynamicEntry> {
+    let mut entries = Vec::new();
+    for entry in EntryTable::new(data, L::Dynamic::SIZE as u64, L::Dynamic::SIZE).iter() {
+      let entry = L::Dynamic::read::<E>(entry).unwrap().widen();
+      if entry.tag == DT_NULL {
+        break;
+      }
+      entries.push(entry);
+    }
+    entries
+  }
+}
@@ -0,0 +1,328 @@
+//Ed25519 (RFC 8032) after TweetNaCl: field elements are 16 limbs of 16 bits in i64, points
+//are extended coordinates. Small and constant time, not fast, which is plenty for one
+//signature per file.
+use crate::digest::sha512;
+
+type Field = [i64; 16];
+type Point = [Field; 4];
+
+const ZERO: Field = [0; 16];
+const ONE: Field = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+//d of the curve and 2d
+const D: Field = [0x78a3, 0x1359, 0x4dca, 0x75eb, 0xd8ab, 0x4141, 0x0a4d, 0x0070, 0xe898, 0x7779, 0x4079, 0x8cc7, 0xfe73, 0x2b6f, 0x6cee, 0x5203];
+const D2: Field = [0xf159, 0x26b2, 0x9b94, 0xebd6, 0xb156, 0x8283, 0x149a, 0x00e0, 0xd130, 0xeef3, 0x80f2, 0x198e, 0xfce7, 0x56df, 0xd9dc, 0x2406];
+//the base point
+const X: Field = [0xd51a, 0x8f25, 0x2d60, 0xc956, 0xa7b2, 0x9525, 0xc760, 0x692c, 0xdc5c, 0xfdd6, 0xe231, 0xc0a4, 0x53fe, 0xcd6e, 0x36d3, 0x2169];
+const Y: Field = [0x6658, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666];
+//sqrt(-1)
+const I: Field = [0xa0b0, 0x4a0e, 0x1b27, 0xc4ee, 0xe478, 0xad2f, 0x1806, 0x2f43, 0xd7a7, 0x3dfb, 0x0099, 0x2b4d, 0xdf0b, 0x4fc1, 0x2480, 0x2b83];
+//the group order, little endian
+const L: [i64; 32] = [
+  0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+  0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+];
+
+fn carry(o: &mut Field) {
+  for i in 0..16 {
+    o[i] += 1 << 16;
+    let c = o[i] >> 16;
+    if i < 15 {
+      o[i + 1] += c - 1;
+    } else {
+      o[0] += 38 * (c - 1);
+    }
+    o[i] -= c << 16;
+  }
+}
+
+//swaps p and q when b is 1, without branching on it
+fn select(p: &mut Field, q: &mut Field, b: i64) {
+  let mask = !(b - 1);
+  for i in 0..16 {
+    let t = mask & (p[i] ^ q[i]);
+    p[i] ^= t;
+    q[i] ^= t;
+  }
+}
+
+fn pack_field(n: &Field) -> [u8; 32] {
+  let mut t = *n;
+  carry(&mut t);
+  carry(&mut t);
+  carry(&mut t);
+  for _ in 0..2 {
+    let mut m = ZERO;
+    m[0] = t[0] - 0xffed;
+    for i in 1..15 {
+      m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+      m[i - 1] &= 0xffff;
+    }
+    m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+    let b = (m[15] >> 16) & 1;
+    m[14] &= 0xffff;
+    select(&mut t, &mut m, 1 - b);
+  }
+  let mut o = [0u8; 32];
+  for i in 0..16 {
+    o[2 * i] = (t[i] & 0xff) as u8;
+    o[2 * i + 1] = (t[i] >> 8) as u8;
+  }
+  o
+}
+
+fn unpack_field(n: &[u8; 32]) -> Field {
+  let mut o = ZERO;
+  for i in 0..16 {
+    o[i] = n[2 * i] as i64 + ((n[2 * i + 1] as i64) << 8);
+  }
+  o[15] &= 0x7fff;
+  o
+}
+
+fn not_equal(a: &Field, b: &Field) -> bool {
+  pack_field(a) != pack_field(b)
+}
+
+fn parity(a: &Field) -> u8 {
+  pack_field(a)[0] & 1
+}
+
+fn add(a: &Field, b: &Field) -> Field {
+  let mut o = ZERO;
+  for i in 0..16 {
+    o[i] = a[i] + b[i];
+  }
+  o
+}
+
+fn sub(a: &Field, b: &Field) -> Field {
+  let mut o = ZERO;
+  for i in 0..16 {
+    o[i] = a[i] - b[i];
+  }
+  o
+}
+
+fn mul(a: &Field, b: &Field) -> Field {
+  let mut t = [0i64; 31];
+  for i in 0..16 {
+    for j in 0..16 {
+      t[i + j] += a[i] * b[j];
+    }
+  }
+  for i in 0..15 {
+    t[i] += 38 * t[i + 16];
+  }
+  let mut o = ZERO;
+  o.copy_from_slice(&t[..16]);
+  carry(&mut o);
+  carry(&mut o);
+  o
+}
+
+fn square(a: &Field) -> Field {
+  mul(a, a)
+}
+
+fn invert(i: &Field) -> Field {
+  let mut c = *i;
+  for a in (0..=253).rev() {
+    c = square(&c);
+    if a != 2 && a != 4 {
+      c = mul(&c, i);
+    }
+  }
+  c
+}
+
+//i^((p-5)/8), for the square root in decompression
+fn pow2523(i: &Field) -> Field {
+  let mut c = *i;
+  for a in (0..=250).rev() {
+    c = square(&c);
+    if a != 1 {
+      c = mul(&c, i);
+    }
+  }
+  c
+}
+
+fn point_add(p: &mut Point, q: &Point) {
+  let a = mul(&sub(&p[1], &p[0]), &sub(&q[1], &q[0]));
+  let b = mul(&add(&p[0], &p[1]), &add(&q[0], &q[1]));
+  let c = mul(&mul(&p[3], &q[3]), &D2);
+  let d = mul(&p[2], &q[2]);
+  let d = add(&d, &d);
+  let e = sub(&b, &a);
+  let f = sub(&d, &c);
+  let g = add(&d, &c);
+  let h = add(&b, &a);
+  p[0] = mul(&e, &f);
+  p[1] = mul(&h, &g);
+  p[2] = mul(&g, &f);
+  p[3] = mul(&e, &h);
+}
+
+fn point_swap(p: &mut Point, q: &mut Point, b: i64) {
+  for (p, q) in p.iter_mut().zip(q.iter_mut()) {
+    select(p, q, b);
+  }
+}
+
+fn pack_point(p: &Point) -> [u8; 32] {
+  let zi = invert(&p[2]);
+  let tx = mul(&p[0], &zi);
+  let ty = mul(&p[1], &zi);
+  let mut r = pack_field(&ty);
+  r[31] ^= parity(&tx) << 7;
+  r
+}
+
+fn scalar_mult(q: &mut Point, s: &[u8; 32]) -> Point {
+  let mut p = [ZERO, ONE, ONE, ZERO];
+  for i in (0..256).rev() {
+    let b = ((s[i / 8] >> (i & 7)) & 1) as i64;
+    point_swap(&mut p, q, b);
+    point_add(q, &p);
+    let double = p;
+    point_add(&mut p, &double);
+    point_swap(&mut p, q, b);
+  }
+  p
+}
+
+fn scalar_base(s: &[u8; 32]) -> Point {
+  let mut q = [X, Y, ONE, mul(&X, &Y)];
+  scalar_mult(&mut q, s)
+}
+
+//x mod L, x a 64 byte little endian number in bytes of up to 64 bits
+fn mod_l(x: &mut [i64; 64]) -> [u8; 32] {
+  for i in (32..64).rev() {
+    let mut carry = 0;
+    let mut j = i - 32;
+    while j < i - 12 {
+      x[j] += carry - 16 * x[i] * L[j - (i - 32)];
+      carry = (x[j] + 128) >> 8;
+      x[j] -= carry << 8;
+      j += 1;
+    }
+    x[j] += carry;
+    x[i] = 0;
+  }
+  let mut carry = 0;
+  for j in 0..32 {
+    x[j] += carry - (x[31] >> 4) * L[j];
+    carry = x[j] >> 8;
+    x[j] &= 255;
+  }
+  for j in 0..32 {
+    x[j] -= carry * L[j];
+  }
+  let mut r = [0u8; 32];
+  for i in 0..32 {
+    x[i + 1] += x[i] >> 8;
+    r[i] = (x[i] & 255) as u8;
+  }
+  r
+}
+
+fn reduce(hash: &[u8; 64]) -> [u8; 32] {
+  let mut x = [0i64; 64];
+  for (x, &byte) in x.iter_mut().zip(hash.iter()) {
+    *x = byte as i64;
+  }
+  mod_l(&mut x)
+}
+
+//the point with the given encoding, negated, None when it is not on the curve
+fn unpack_negated(encoded: &[u8; 32]) -> Option<Point> {
+  let y = unpack_field(encoded);
+  let num = square(&y);
+  let den = mul(&num, &D);
+  let num = sub(&num, &ONE);
+  let den = add(&ONE, &den);
+  let den2 = square(&den);
+  let den4 = square(&den2);
+  let den6 = mul(&den4, &den2);
+  let mut t = mul(&mul(&den6, &num), &den);
+  t = pow2523(&t);
+  t = mul(&mul(&mul(&t, &num), &den), &den);
+  let mut x = mul(&t, &den);
+  if not_equal(&mul(&square(&x), &den), &num) {
+    x = mul(&x, &I);
+  }
+  if not_equal(&mul(&square(&x), &den), &num) {
+    return None;
+  }
+  if parity(&x) == encoded[31] >> 7 {
+    x = sub(&ZERO, &x);
+  }
+  Some([x, y, ONE, mul(&x, &y)])
+}
+
+fn expand_seed(seed: &[u8; 32]) -> [u8; 64] {
+  let mut expanded = sha512(seed);
+  expanded[0] &= 248;
+  expanded[31] &= 127;
+  expanded[31] |= 64;
+  expanded
+}
+
+fn scalar(bytes: &[u8]) -> [u8; 32] {
+  let mut scalar = [0u8; 32];
+  scalar.copy_from_slice(&bytes[..32]);
+  scalar
+}
+
+fn challenge(r: &[u8], public_key: &[u8; 32], message: &[u8]) -> [u8; 32] {
+  let mut input = Vec::with_capacity(64 + message.len());
+  input.extend_from_slice(r);
+  input.extend_from_slice(public_key);
+  input.extend_from_slice(message);
+  reduce(&sha512(&input))
+}
+
+pub(crate) fn public_key(seed: &[u8; 32]) -> [u8; 32] {
+  pack_point(&scalar_base(&scalar(&expand_seed(seed))))
+}
+
+pub(crate) fn sign(seed: &[u8; 32], message: &[u8]) -> [u8; 64] {
+  let expanded = expand_seed(seed);
+  let public_key = pack_point(&scalar_base(&scalar(&expanded)));
+  let mut nonce_input = expanded[32..].to_vec();
+  nonce_input.extend_from_slice(message);
+  let nonce = reduce(&sha512(&nonce_input));
+  let r = pack_point(&scalar_base(&nonce));
+  let h = challenge(&r, &public_key, message);
+  let mut x = [0i64; 64];
+  for (x, &byte) in x.iter_mut().zip(nonce.iter()) {
+    *x = byte as i64;
+  }
+  for i in 0..32 {
+    for j in 0..32 {
+      x[i + j] += h[i] as i64 * expanded[j] as i64;
+    }
+  }
+  let s = mod_l(&mut x);
+  let mut signature = [0u8; 64];
+  signature[..32].copy_from_slice(&r);
+  signature[32..].copy_from_slice(&s);
+  signature
+}
+
+pub(crate) fn verify(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+  //s must be below L, otherwise s + L would be a second valid signature
+  let s = scalar(&signature[32..]);
+  if s.iter().rev().zip(L.iter().rev()).find(|(s, l)| **s as i64 != **l).is_none_or(|(s, l)| *s as i64 > *l) {
+    return false;
+  }
+  let mut q = match unpack_negated(public_key) {
+    Some(point) => point,
+    None => return false,
+  };
+  let h = challenge(&signature[..32], public_key, message);
+  let mut p = scalar_mult(&mut q, &h);
+  point_add(&mut p, &scalar_base(&s));
+  pack_point(&p)[..] == signature[..32]
+}
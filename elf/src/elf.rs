@@ -1,13 +1,29 @@
-use std::io::{self, Cursor, Read};
-use byteorder::{BigEndian, ReadBytesExt, ByteOrder, LittleEndian};
+use std::borrow::Cow;
+use std::io::{self, Read};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
 use std::path::Path;
 use std::fs::File;
+use std::sync::Arc;
+use crate::consts::*;
+use crate::entry_table::EntryTable;
+use crate::interner::Interner;
+use crate::layout::*;
+use crate::lookup::NameMaps;
+use crate::dwarf::RelocatedSections;
 
+//Elf holds no interior mutability, every query takes &self, so an Arc<Elf> can be
+//shared by a thread pool. Later caches must keep it that way (e.g. OnceLock, not RefCell).
+//Lookups by name are cached on first use: after editing the public fields directly, call
+//invalidate_caches, or reload_tables when the headers should be read again from `data`.
 pub struct Elf {
   pub data: Box<[u8]>,
   pub header: ElfHeader,
   pub section_headers: Vec<SectionHeader>,
   pub program_headers: Vec<ProgramHeader>,
+  pub bias: u64,
+  pub interner: Arc<Interner>,
+  pub(crate) name_maps: NameMaps,
+  pub(crate) relocated_sections: RelocatedSections,
 }
 
 #[derive(Default)]
@@ -43,7 +59,7 @@ pub struct ElfDescription {
   pub section_hdr_str_index: u16,
 }
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct SectionHeader {
   pub name_index: u32,
   pub section_type: u32,
@@ -70,21 +86,47 @@ pub struct ProgramHeader {
   pub align: u64,
 }
 
+fn assert_send_sync<T: Send + Sync>() {}
+const _: fn() = assert_send_sync::<Elf>;
+
+//the NUL terminated string at `offset`, None past the table or without a terminator
+pub(crate) fn read_bytes(table: &[u8], offset: usize) -> Option<&[u8]> {
+  let bytes = table.get(offset..)?;
+  let end = bytes.iter().position(|&b| b == 0)?;
+  Some(&bytes[..end])
+}
+
+pub(crate) fn read_str(table: &[u8], offset: usize) -> Option<&str> {
+  std::str::from_utf8(read_bytes(table, offset)?).ok()
+}
+
 impl Elf {
+  //Reads and parses a file with the default ParseOptions, an error rather than a panic for
+  //files whose identification cannot be read.
   pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Elf> {
     let mut file = File::open(path)?;
     let mut data = Vec::new();
     file.read_to_end(&mut data)?;
     data.shrink_to_fit();
-    Ok(Elf::new(data.into_boxed_slice()))
+    Elf::parse(data.into_boxed_slice(), &Default::default()).map(|(elf, _)| elf)
   }
 
+  //Trusts the identification and panics on an unknown class or byte order or a truncated
+  //header, Elf::parse checks them first.
   pub fn new(data: Box<[u8]>) -> Elf {
+    Elf::with_interner(data, Arc::new(Interner::new()))
+  }
+
+  pub fn with_interner(data: Box<[u8]>, interner: Arc<Interner>) -> Elf {
     let mut elf = Elf {
       data,
       header: Default::default(),
       section_headers: Vec::new(),
       program_headers: Vec::new(),
+      bias: 0,
+      interner,
+      name_maps: Default::default(),
+      relocated_sections: Default::default(),
     };
     elf.load_identification();
     elf.load_description();
@@ -93,6 +135,45 @@ impl Elf {
     elf
   }
 
+  pub fn address_to_offset(&self, address: u64) -> Option<u64> {
+    self.program_headers.iter()
+      .filter(|ph| ph.entry_type == PT_LOAD)
+      .find(|ph| address >= ph.virtual_address && address - ph.virtual_address < ph.file_size)
+      .and_then(|ph| ph.offset.checked_add(address - ph.virtual_address))
+  }
+
+  pub fn section_data(&self, section: &SectionHeader) -> &[u8] {
+    if section.section_type == SHT_NOBITS {
+      return &[];
+    }
+    //malformed headers point anywhere, they get no data rather than a panic
+    let start = section.offset as usize;
+    self.data.get(start..start.saturating_add(section.size as usize)).unwrap_or(&[])
+  }
+
+  //section_name_str without the reason there is no name, for finding sections by a name
+  //that is known to be UTF-8. section_name_lossy names every section that has one.
+  pub fn section_name(&self, section: &SectionHeader) -> Option<&str> {
+    self.section_name_str(section).ok()
+  }
+
+  pub fn section_name_bytes(&self, section: &SectionHeader) -> Option<&[u8]> {
+    let table = self.section_headers.get(self.header.description.section_hdr_str_index as usize)?;
+    read_bytes(self.data.get(table.offset as usize..)?, section.name_index as usize)
+  }
+
+  //invalid UTF-8 replaced by U+FFFD
+  pub fn section_name_lossy(&self, section: &SectionHeader) -> Option<Cow<'_, str>> {
+    self.section_name_bytes(section).map(String::from_utf8_lossy)
+  }
+
+  //An error rather than None, telling a missing name from one that is not UTF-8.
+  pub fn section_name_str(&self, section: &SectionHeader) -> io::Result<&str> {
+    let bytes = self.section_name_bytes(section)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no section name at {}", section.name_index)))?;
+    std::str::from_utf8(bytes).map_err(|error| io::Error::new(io::ErrorKind::InvalidData, format!("section name {:?}: {}", String::from_utf8_lossy(bytes), error)))
+  }
+
   fn load_identification(&mut self) {
     self.header.identification.magic = BigEndian::read_u32(&self.data[0..4]);
     self.header.identification.class = self.data[4];
@@ -111,30 +192,23 @@ impl Elf {
   }
 
   fn load_description_with_byteorder<E: ByteOrder>(&mut self) {
-    let mut cursor = Cursor::new(&self.data[16..]);
-    self.header.description.obj_type = cursor.read_u16::<E>().unwrap();
-    self.header.description.machine = cursor.read_u16::<E>().unwrap();
-    self.header.description.version = cursor.read_u32::<E>().unwrap();
     match self.header.identification.class {
-      1 => {
-        self.header.description.entry = cursor.read_u32::<E>().unwrap() as u64;
-        self.header.description.program_hdr_offset = cursor.read_u32::<E>().unwrap() as u64;
-        self.header.description.section_hdr_offset = cursor.read_u32::<E>().unwrap() as u64;
-      },
-      2 => {
-        self.header.description.entry = cursor.read_u64::<E>().unwrap();
-        self.header.description.program_hdr_offset = cursor.read_u64::<E>().unwrap();
-        self.header.description.section_hdr_offset = cursor.read_u64::<E>().unwrap();
-      },
+      1 => self.load_description_with_layout::<E, Elf32>(),
+      2 => self.load_description_with_layout::<E, Elf64>(),
       _ => panic!("unknown class"),
     };
-    self.header.description.flags = cursor.read_u32::<E>().unwrap();
-    self.header.description.elf_hdr_size = cursor.read_u16::<E>().unwrap();
-    self.header.description.program_hdr_entry_size = cursor.read_u16::<E>().unwrap();
-    self.header.description.program_hdr_num = cursor.read_u16::<E>().unwrap();
-    self.header.description.section_hdr_entry_size = cursor.read_u16::<E>().unwrap();
-    self.header.description.section_hdr_num = cursor.read_u16::<E>().unwrap();
-    self.header.description.section_hdr_str_index = cursor.read_u16::<E>().unwrap();
+  }
+
+  fn load_description_with_layout<E: ByteOrder, L: ElfLayout>(&mut self) {
+    self.header.description = L::Header::read::<E>(&self.data).unwrap().widen().description;
+  }
+
+  pub fn reload_tables(&mut self) {
+    self.section_headers.clear();
+    self.program_headers.clear();
+    self.invalidate_caches();
+    self.load_section_headers();
+    self.load_program_headers();
   }
 
   fn load_section_headers(&mut self) {
@@ -146,35 +220,46 @@ impl Elf {
   }
 
   fn load_section_headers_with_byteorder<E: ByteOrder>(&mut self) {
-    let mut cursor = Cursor::new(&self.data[self.header.description.section_hdr_offset as usize..]);
-    for _ in 0..self.header.description.section_hdr_num {
-      let mut entry: SectionHeader = Default::default();
-      entry.name_index = cursor.read_u32::<E>().unwrap();
-      entry.section_type = cursor.read_u32::<E>().unwrap();
-      match self.header.identification.class {
-        1 => {
-          entry.flags = cursor.read_u32::<E>().unwrap() as u64;
-          entry.address = cursor.read_u32::<E>().unwrap() as u64;
-          entry.offset = cursor.read_u32::<E>().unwrap() as u64;
-          entry.size = cursor.read_u32::<E>().unwrap() as u64;
-          entry.link = cursor.read_u32::<E>().unwrap();
-          entry.info = cursor.read_u32::<E>().unwrap();
-          entry.align = cursor.read_u32::<E>().unwrap() as u64;
-          entry.entry_size = cursor.read_u32::<E>().unwrap() as u64;
-        },
-        2 => {
-          entry.flags = cursor.read_u64::<E>().unwrap();
-          entry.address = cursor.read_u64::<E>().unwrap();
-          entry.offset = cursor.read_u64::<E>().unwrap();
-          entry.size = cursor.read_u64::<E>().unwrap();
-          entry.link = cursor.read_u32::<E>().unwrap();
-          entry.info = cursor.read_u32::<E>().unwrap();
-          entry.align = cursor.read_u64::<E>().unwrap();
-          entry.entry_size = cursor.read_u64::<E>().unwrap();
-        },
-        _ => panic!("unknown class"),
-      };
-      self.section_headers.push(entry);
+    match self.header.identification.class {
+      1 => self.load_section_headers_with_layout::<E, Elf32>(),
+      2 => self.load_section_headers_with_layout::<E, Elf64>(),
+      _ => panic!("unknown class"),
+    };
+  }
+
+  fn load_section_headers_with_layout<E: ByteOrder, L: ElfLayout>(&mut self) {
+    //stripped and packed files often have no section header table at all, offset 0 would
+    //read the ELF header as one
+    if self.header.description.section_hdr_offset == 0 {
+      return;
+    }
+    let table = self.data.get(self.header.description.section_hdr_offset as usize..).unwrap_or(&[]);
+    let table = EntryTable::new(table, self.header.description.section_hdr_entry_size as u64, L::SectionHeader::SIZE);
+    let mut num = self.header.description.section_hdr_num as usize;
+    //e_shnum 0 with a table present: the count is in sh_size of entry 0
+    if num == 0 {
+      if let Some(first) = table.get(0) {
+        num = L::SectionHeader::read::<E>(first).unwrap().widen().size as usize;
+      }
+    }
+    for entry in table.iter().take(num) {
+      self.section_headers.push(L::SectionHeader::read::<E>(entry).unwrap().widen());
+    }
+  }
+
+  pub(crate) fn read_section_header<E: ByteOrder>(class: u8, data: &[u8]) -> SectionHeader {
+    match class {
+      1 => SectionHeader32::read::<E>(data).unwrap().widen(),
+      2 => SectionHeader64::read::<E>(data).unwrap().widen(),
+      _ => panic!("unknown class"),
+    }
+  }
+
+  pub(crate) fn write_section_header<E: ByteOrder>(class: u8, data: &mut [u8], entry: &SectionHeader) {
+    match class {
+      1 => SectionHeader32::narrow(entry).write::<E>(data).unwrap(),
+      2 => SectionHeader64::narrow(entry).write::<E>(data).unwrap(),
+      _ => panic!("unknown class"),
     }
   }
 
@@ -187,33 +272,26 @@ impl Elf {
   }
 
   fn load_program_headers_with_byteorder<E: ByteOrder>(&mut self) {
-    let mut cursor = Cursor::new(&self.data[self.header.description.program_hdr_offset as usize..]);
-    for _ in 0..self.header.description.program_hdr_num {
-      let mut entry: ProgramHeader = Default::default();
-      match self.header.identification.class {
-        1 => {
-          entry.entry_type = cursor.read_u32::<E>().unwrap();
-          entry.offset = cursor.read_u32::<E>().unwrap() as u64;
-          entry.virtual_address = cursor.read_u32::<E>().unwrap() as u64;
-          entry.physical_address = cursor.read_u32::<E>().unwrap() as u64;
-          entry.file_size = cursor.read_u32::<E>().unwrap() as u64;
-          entry.memory_size = cursor.read_u32::<E>().unwrap() as u64;
-          entry.flags = cursor.read_u32::<E>().unwrap();
-          entry.align = cursor.read_u32::<E>().unwrap() as u64;
-        },
-        2 => {
-          entry.entry_type = cursor.read_u32::<E>().unwrap();
-          entry.flags = cursor.read_u32::<E>().unwrap();
-          entry.offset = cursor.read_u64::<E>().unwrap();
-          entry.virtual_address = cursor.read_u64::<E>().unwrap();
-          entry.physical_address = cursor.read_u64::<E>().unwrap();
-          entry.file_size = cursor.read_u64::<E>().unwrap();
-          entry.memory_size = cursor.read_u64::<E>().unwrap();
-          entry.align = cursor.read_u64::<E>().unwrap();
-        },
-        _ => panic!("unknown class"),
-      };
-      self.program_headers.push(entry);
+    match self.header.identification.class {
+      1 => self.load_program_headers_with_layout::<E, Elf32>(),
+      2 => self.load_program_headers_with_layout::<E, Elf64>(),
+      _ => panic!("unknown class"),
+    };
+  }
+
+  fn load_program_headers_with_layout<E: ByteOrder, L: ElfLayout>(&mut self) {
+    let table = self.data.get(self.header.description.program_hdr_offset as usize..).unwrap_or(&[]);
+    let table = EntryTable::new(table, self.header.description.program_hdr_entry_size as u64, L::ProgramHeader::SIZE);
+    for entry in table.iter().take(self.header.description.program_hdr_num as usize) {
+      self.program_headers.push(L::ProgramHeader::read::<E>(entry).unwrap().widen());
+    }
+  }
+
+  pub(crate) fn read_program_header<E: ByteOrder>(class: u8, data: &[u8]) -> ProgramHeader {
+    match class {
+      1 => ProgramHeader32::read::<E>(data).unwrap().widen(),
+      2 => ProgramHeader64::read::<E>(data).unwrap().widen(),
+      _ => panic!("unknown class"),
     }
   }
 }
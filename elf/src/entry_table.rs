@@ -0,0 +1,48 @@
+//A table of fixed size entries found by index times the declared entry size rather than by
+//reading one after the other, so padded or extended entries do not shift the ones after them.
+#[derive(Clone, Copy)]
+pub struct EntryTable<'a> {
+  data: &'a [u8],
+  stride: usize,
+  entry_size: usize,
+}
+
+impl<'a> EntryTable<'a> {
+  //`entry_size` is what a decoder reads of an entry, `declared` the entry size the file
+  //claims. A declared size that cannot hold an entry (0 is common for sh_entsize) falls back
+  //to `entry_size`.
+  pub fn new(data: &'a [u8], declared: u64, entry_size: usize) -> EntryTable<'a> {
+    let stride = if declared >= entry_size as u64 && declared <= data.len() as u64 { declared as usize } else { entry_size };
+    EntryTable { data, stride, entry_size }
+  }
+
+  pub fn stride(&self) -> usize {
+    self.stride
+  }
+
+  //the last entry only needs the bytes a decoder reads, not its padding
+  pub fn len(&self) -> usize {
+    match self.data.len().checked_sub(self.entry_size) {
+      Some(rest) if self.entry_size > 0 => rest / self.stride + 1,
+      _ => 0,
+    }
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  //the entry including any bytes past the standard fields
+  pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+    if index >= self.len() {
+      return None;
+    }
+    let start = index * self.stride;
+    Some(&self.data[start..(start + self.stride).min(self.data.len())])
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = &'a [u8]> + 'a {
+    let table = *self;
+    (0..table.len()).filter_map(move |index| table.get(index))
+  }
+}
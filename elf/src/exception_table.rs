@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use crate::cfi::*;
+use crate::consts::*;
+use crate::dwarf::{read_unsigned, Reader};
+use crate::elf::Elf;
+
+//A std::type_info a catch clause or exception specification names.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypeInfo {
+  //the typeinfo object, for indirect encodings the slot holding its address
+  pub address: u64,
+  //the typeinfo symbol, "_ZTIi" for int, when it can be found
+  pub name: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExceptionAction {
+  //run destructors, then keep unwinding
+  Cleanup,
+  //a catch clause, None for catch (...)
+  Catch(Option<TypeInfo>),
+  //a dynamic exception specification: the types throw(A, B) lets through, none for throw()
+  Filter(Vec<TypeInfo>),
+}
+
+//A call-site table entry: an exception thrown from [start, end) goes to the landing pad,
+//which runs the actions in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TryRange {
+  pub start: u64,
+  pub end: u64,
+  //None when the range has no handler and unwinding continues through it
+  pub landing_pad: Option<u64>,
+  //empty without a landing pad
+  pub actions: Vec<ExceptionAction>,
+}
+
+//The LSDA (language specific data area) of one function, in GCC's format that
+//__gxx_personality_v0 and the other GNU personality routines read.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExceptionTable {
+  //the function, from its FDE
+  pub function: u64,
+  pub function_size: u64,
+  pub personality: Option<u64>,
+  pub address: u64,
+  //what landing pad offsets are relative to, the function start unless the LSDA says
+  pub landing_pad_base: u64,
+  //in address order, as the table has them
+  pub ranges: Vec<TryRange>,
+}
+
+impl ExceptionTable {
+  pub fn range_at(&self, address: u64) -> Option<&TryRange> {
+    self.ranges.iter().find(|range| range.start <= address && address < range.end)
+  }
+
+  //landing pads that catch something, cleanups only left out
+  pub fn handlers(&self) -> impl Iterator<Item = &TryRange> {
+    self.ranges.iter().filter(|range| range.actions.iter().any(|action| !matches!(action, ExceptionAction::Cleanup)))
+  }
+}
+
+fn pointer_size(encoding: u8, address_size: u8) -> Option<usize> {
+  match encoding & 0x0f {
+    DW_EH_PE_ABSPTR => Some(address_size as usize),
+    DW_EH_PE_UDATA2 | DW_EH_PE_SDATA2 => Some(2),
+    DW_EH_PE_UDATA4 | DW_EH_PE_SDATA4 => Some(4),
+    DW_EH_PE_UDATA8 | DW_EH_PE_SDATA8 => Some(8),
+    _ => None,
+  }
+}
+
+//Finds the typeinfo symbols type table entries point at, directly or through a slot that a
+//relocation or the file itself fills in.
+struct TypeNames {
+  symbols: HashMap<u64, String>,
+  //slot address => the symbol or the address a relocation puts there
+  slots: HashMap<u64, (Option<String>, Option<u64>)>,
+}
+
+impl TypeNames {
+  fn new(elf: &Elf) -> TypeNames {
+    let mut symbols = HashMap::new();
+    for symbol in elf.symbol_table().iter().chain(elf.dynamic_symbol_table()) {
+      if !symbol.is_undefined() && !symbol.name.is_empty() && symbol.symbol_type != STT_SECTION {
+        symbols.entry(symbol.value).or_insert_with(|| symbol.name.to_string());
+      }
+    }
+    let mut slots = HashMap::new();
+    for relocation in elf.relocations() {
+      let name = elf.relocation_symbol(relocation.section_index, relocation.symbol_index)
+        .filter(|symbol| !symbol.name.is_empty() && symbol.symbol_type != STT_SECTION)
+        .map(|symbol| symbol.name.to_string());
+      slots.insert(relocation.offset, (name, relocation.addend.map(|addend| addend as u64)));
+    }
+    TypeNames { symbols, slots }
+  }
+
+  fn name(&self, elf: &Elf, address: u64, indirect: bool) -> Option<String> {
+    if !indirect {
+      return self.symbols.get(&address).cloned().or_else(|| self.slots.get(&address).and_then(|(name, _)| name.clone()));
+    }
+    let target = match self.slots.get(&address) {
+      Some((Some(name), _)) => return Some(name.clone()),
+      Some((None, Some(addend))) => *addend,
+      _ => {
+        let size = if elf.header.identification.class == ELFCLASS64 { 8 } else { 4 };
+        let offset = elf.address_to_offset(address)? as usize;
+        read_unsigned(elf.data.get(offset..offset + size)?, elf.header.identification.endianness == 2)
+      },
+    };
+    self.symbols.get(&target).cloned()
+  }
+}
+
+impl Elf {
+  //Decodes the LSDA at `address` for the function its FDE describes.
+  fn exception_table(&self, frame: &FrameDescription, address: u64, names: &TypeNames) -> Option<ExceptionTable> {
+    let section = self.section_headers.iter()
+      .find(|section| section.flags & SHF_ALLOC != 0 && section.section_type != SHT_NOBITS && section.address <= address && address - section.address < section.size)?;
+    let data = self.section_data(section);
+    let big_endian = self.header.identification.endianness == 2;
+    let address_size = if self.header.identification.class == ELFCLASS64 { 8 } else { 4 };
+    let bases = PointerBases {
+      text: self.section_by_name(".text").map_or(0, |text| text.address),
+      data: self.section_by_name(".got").map_or(0, |got| got.address),
+      function: frame.address,
+    };
+    let mut reader = Reader { data, position: (address - section.address) as usize, big_endian };
+
+    let landing_pad_encoding = reader.unsigned(1)? as u8;
+    let landing_pad_base = if landing_pad_encoding == DW_EH_PE_OMIT {
+      frame.address
+    } else {
+      read_encoded_pointer(&mut reader, landing_pad_encoding, section.address, address_size, &bases)?
+    };
+    let type_encoding = reader.unsigned(1)? as u8;
+    //the type table ends here, entries are indexed from its end backwards
+    let type_base = if type_encoding == DW_EH_PE_OMIT {
+      None
+    } else {
+      let offset = reader.uleb()? as usize;
+      Some(reader.position.checked_add(offset)?)
+    };
+    let call_site_encoding = reader.unsigned(1)? as u8;
+    let call_site_length = reader.uleb()? as usize;
+    let call_site_end = reader.position.checked_add(call_site_length)?;
+    let action_table = call_site_end;
+
+    let type_entry = |index: u64| -> Option<TypeInfo> {
+      let size = pointer_size(type_encoding, address_size)?;
+      let position = type_base?.checked_sub(usize::try_from(index).ok()?.checked_mul(size)?)?;
+      let mut entry = Reader { data, position, big_endian };
+      let value = read_encoded_pointer(&mut entry, type_encoding, section.address, address_size, &bases)?;
+      let raw = read_unsigned(data.get(position..position.checked_add(size)?)?, big_endian);
+      //a zero entry is catch (...), whatever the encoding would add to it
+      if raw == 0 {
+        return None;
+      }
+      let name = names.name(self, value, type_encoding & DW_EH_PE_INDIRECT != 0);
+      Some(TypeInfo { address: value, name })
+    };
+    let actions = |first: u64| -> Option<Vec<ExceptionAction>> {
+      let mut actions = Vec::new();
+      //no action record is a cleanup-only landing pad
+      if first == 0 {
+        actions.push(ExceptionAction::Cleanup);
+        return Some(actions);
+      }
+      let mut position = action_table.checked_add(usize::try_from(first - 1).ok()?)?;
+      let mut seen = HashSet::new();
+      while seen.insert(position) {
+        let mut record = Reader { data, position, big_endian };
+        let filter = record.sleb()?;
+        let next_position = record.position;
+        let next = record.sleb()?;
+        actions.push(match filter {
+          0 => ExceptionAction::Cleanup,
+          filter if filter > 0 => ExceptionAction::Catch(type_entry(filter as u64)),
+          filter => {
+            let index = usize::try_from(filter.checked_neg()? - 1).ok()?;
+            let mut list = Reader { data, position: type_base?.checked_add(index)?, big_endian };
+            let mut types = Vec::new();
+            loop {
+              match list.uleb()? {
+                0 => break,
+                index => types.extend(type_entry(index)),
+              };
+            }
+            ExceptionAction::Filter(types)
+          },
+        });
+        if next == 0 {
+          break;
+        }
+        position = usize::try_from(i64::try_from(next_position).ok()?.checked_add(next)?).ok()?;
+      }
+      Some(actions)
+    };
+
+    let mut ranges = Vec::new();
+    while reader.position < call_site_end {
+      //call-site offsets are from the function start, whatever the encoding says
+      let start = read_encoded_pointer(&mut reader, call_site_encoding & 0x0f, 0, address_size, &bases)?;
+      let length = read_encoded_pointer(&mut reader, call_site_encoding & 0x0f, 0, address_size, &bases)?;
+      let landing_pad = read_encoded_pointer(&mut reader, call_site_encoding & 0x0f, 0, address_size, &bases)?;
+      let action = reader.uleb()?;
+      ranges.push(TryRange {
+        start: frame.address.wrapping_add(start),
+        end: frame.address.wrapping_add(start).wrapping_add(length),
+        landing_pad: if landing_pad == 0 { None } else { Some(landing_pad_base.wrapping_add(landing_pad)) },
+        actions: if landing_pad == 0 { Vec::new() } else { actions(action)? },
+      });
+    }
+    Some(ExceptionTable {
+      function: frame.address,
+      function_size: frame.size,
+      personality: frame.personality,
+      address,
+      landing_pad_base,
+      ranges,
+    })
+  }
+
+  //The exception tables the .eh_frame FDEs point at, usually in .gcc_except_table, by
+  //function address. Linked files only: in relocatable objects the LSDA pointers are
+  //relocations.
+  pub fn exception_tables(&self) -> Vec<ExceptionTable> {
+    let eh_frame = match self.eh_frame() {
+      Some(eh_frame) if self.header.description.obj_type != ET_REL => eh_frame,
+      _ => return Vec::new(),
+    };
+    let names = TypeNames::new(self);
+    let mut tables: Vec<ExceptionTable> = eh_frame.frame_descriptions().iter()
+      .filter_map(|frame| self.exception_table(frame, frame.lsda?, &names))
+      .collect();
+    tables.sort_by_key(|table| table.function);
+    tables
+  }
+}
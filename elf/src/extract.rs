@@ -0,0 +1,532 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::call_sites::CallKind;
+use crate::consts::*;
+use crate::elf::{Elf, SectionHeader};
+use crate::layout::*;
+use crate::relocation::Relocation;
+use crate::section_writer::{self, ObjectWriter};
+use crate::symbol::Symbol;
+
+#[derive(Clone, Debug)]
+pub struct SnippetRelocation {
+  //from the start of the code or data
+  pub offset: u64,
+  pub relocation_type: u32,
+  //the function, one of the data or an undefined symbol, empty for none
+  pub symbol: Arc<str>,
+  //None with REL, the addend is in the code
+  pub addend: Option<i64>,
+}
+
+//Data the function refers to, copied from the file with its relocations: a whole section, or
+//only a symbol's bytes when every reference into the section names a symbol with a size.
+#[derive(Clone, Debug)]
+pub struct SnippetData {
+  //what the relocations refer to it by, the section name for a whole section
+  pub name: Arc<str>,
+  pub section_name: String,
+  pub section_type: u32,
+  pub flags: u64,
+  pub align: u64,
+  //sh_entsize of the section, what SHF_MERGE sections are merged by
+  pub entry_size: u64,
+  //empty for SHT_NOBITS and common symbols, `size` is what they take in memory
+  pub bytes: Vec<u8>,
+  pub size: u64,
+  pub relocations: Vec<SnippetRelocation>,
+}
+
+#[derive(Clone, Debug)]
+pub struct FunctionSnippet {
+  pub name: Arc<str>,
+  //in the file, section relative in relocatable objects
+  pub address: u64,
+  pub code: Vec<u8>,
+  pub align: u64,
+  pub relocations: Vec<SnippetRelocation>,
+  pub data: Vec<SnippetData>,
+  class: u8,
+  endianness: u8,
+  os_abi: u8,
+  machine: u16,
+  flags: u32,
+  rela: bool,
+  //the size of the file it came from, no alignment is larger
+  file_size: u64,
+}
+
+//what a relocation in the function refers to
+enum Referent<'a> {
+  None,
+  //the function's own section, at this offset from the function
+  Function(i64),
+  //a data section, at this offset into it, and the symbol with a size it was named by
+  Data { section: usize, offset: i64, symbol: Option<&'a Symbol> },
+  Common(&'a Symbol),
+  External(Arc<str>),
+}
+
+fn unversioned(name: &str) -> &str {
+  name.split('@').next().unwrap_or(name)
+}
+
+//sh_addralign and the alignment of common symbols come from the file: 0 or 1 for none, else a
+//power of two that is not larger than the file, so that padding to it stays bounded
+fn check_align(align: u64, file_size: u64, name: &str) -> io::Result<()> {
+  if align > 1 && (!align.is_power_of_two() || align > file_size) {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} has an alignment of {:#x}", name, align)));
+  }
+  Ok(())
+}
+
+//a name not taken yet by the data, `name`, then `name.1`, `name.2`...
+fn unique_name(data: &[SnippetData], name: &str) -> Arc<str> {
+  let mut candidate = name.to_string();
+  let mut count = 0;
+  while data.iter().any(|data| *data.name == *candidate) {
+    count += 1;
+    candidate = format!("{}.{}", name, count);
+  }
+  Arc::from(candidate)
+}
+
+//Copies what the relocations of the function refer to into the snippet, and then what the
+//relocations of the copies refer to.
+struct SnippetCopier<'a> {
+  elf: &'a Elf,
+  function: &'a Symbol,
+  //the static relocations by the section they apply to
+  relocations: HashMap<usize, Vec<Relocation>>,
+  //the data index of sections copied whole, of symbols and of common symbols
+  whole: HashMap<usize, usize>,
+  symbols: HashMap<(u16, u64), usize>,
+  commons: HashMap<Arc<str>, usize>,
+  //data to relocate: the index, the section and the address of the start in it
+  pending: Vec<(usize, usize, u64)>,
+  data: Vec<SnippetData>,
+}
+
+impl<'a> SnippetCopier<'a> {
+  fn copy(&mut self, section: usize, start: u64, size: u64, name: &str, section_name: String) -> io::Result<usize> {
+    let header = &self.elf.section_headers[section];
+    check_align(header.align, self.elf.data.len() as u64, &section_name)?;
+    let bytes = if header.section_type == SHT_NOBITS {
+      Vec::new()
+    } else {
+      let offset = start.wrapping_sub(header.address) as usize;
+      self.elf.section_data(header).get(offset..offset.saturating_add(size as usize))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} is outside its section", name)))?
+        .to_vec()
+    };
+    let name = unique_name(&self.data, name);
+    let data = SnippetData {
+      name,
+      section_name,
+      section_type: header.section_type,
+      flags: header.flags,
+      align: header.align,
+      entry_size: header.entry_size,
+      bytes,
+      size,
+      relocations: Vec::new(),
+    };
+    self.data.push(data);
+    if header.section_type != SHT_NOBITS {
+      self.pending.push((self.data.len() - 1, section, start));
+    }
+    Ok(self.data.len() - 1)
+  }
+
+  fn whole_section(&mut self, section: usize) -> io::Result<usize> {
+    if let Some(&index) = self.whole.get(&section) {
+      return Ok(index);
+    }
+    let header = &self.elf.section_headers[section];
+    let section_name = self.elf.section_name(header).unwrap_or("").to_string();
+    let index = self.copy(section, header.address, header.size, &section_name.clone(), section_name)?;
+    self.whole.insert(section, index);
+    Ok(index)
+  }
+
+  //the symbol and the change to the addend for a referent
+  fn resolve(&mut self, referent: &Referent<'a>) -> io::Result<(Arc<str>, i64)> {
+    Ok(match *referent {
+      Referent::None => (Arc::from(""), 0),
+      Referent::Function(offset) => (Arc::from(unversioned(&self.function.name)), offset),
+      Referent::External(ref name) => (name.clone(), 0),
+      Referent::Data { section, offset, symbol } => {
+        if let Some(&index) = self.whole.get(&section) {
+          return Ok((self.data[index].name.clone(), offset));
+        }
+        let symbol = match symbol {
+          Some(symbol) => symbol,
+          None => {
+            let index = self.whole_section(section)?;
+            return Ok((self.data[index].name.clone(), offset));
+          },
+        };
+        let index = match self.symbols.get(&(symbol.section_index, symbol.value)) {
+          Some(&index) => index,
+          None => {
+            let name = unversioned(&symbol.name);
+            let section_name = format!("{}.{}", self.elf.section_name(&self.elf.section_headers[section]).unwrap_or(""), name);
+            let index = self.copy(section, symbol.value, symbol.size, name, section_name)?;
+            self.symbols.insert((symbol.section_index, symbol.value), index);
+            index
+          },
+        };
+        (self.data[index].name.clone(), 0)
+      },
+      Referent::Common(symbol) => {
+        let index = match self.commons.get(&symbol.name) {
+          Some(&index) => index,
+          None => {
+            let name = unique_name(&self.data, unversioned(&symbol.name));
+            let section_name = format!(".bss.{}", name);
+            //the value of a common symbol is its alignment
+            check_align(symbol.value, self.elf.data.len() as u64, &name)?;
+            let data = SnippetData {
+              name,
+              section_name,
+              section_type: SHT_NOBITS,
+              flags: SHF_ALLOC | SHF_WRITE,
+              align: symbol.value,
+              entry_size: 0,
+              bytes: Vec::new(),
+              size: symbol.size,
+              relocations: Vec::new(),
+            };
+            self.data.push(data);
+            self.commons.insert(symbol.name.clone(), self.data.len() - 1);
+            self.data.len() - 1
+          },
+        };
+        (self.data[index].name.clone(), 0)
+      },
+    })
+  }
+
+  //the relocations of `bytes`, copied from `section` at `start`
+  fn relocate(&mut self, section: usize, bytes: &mut [u8], start: u64) -> io::Result<Vec<SnippetRelocation>> {
+    let end = start.saturating_add(bytes.len() as u64);
+    let relocations: Vec<Relocation> = self.relocations.get(&section).into_iter().flatten()
+      .filter(|relocation| relocation.offset >= start && relocation.offset < end)
+      .cloned()
+      .collect();
+    let referents: Vec<Referent<'a>> = relocations.iter().map(|relocation| self.elf.snippet_referent(self.function, relocation)).collect();
+    //a section is copied whole when something refers into it by the section or an unsized symbol
+    for referent in &referents {
+      if let Referent::Data { section, symbol: None, .. } = *referent {
+        self.whole_section(section)?;
+      }
+    }
+    let mut copied = Vec::new();
+    for (relocation, referent) in relocations.iter().zip(&referents) {
+      let (symbol, adjustment) = self.resolve(referent)?;
+      let offset = relocation.offset - start;
+      let addend = match relocation.addend {
+        Some(addend) => Some(addend.wrapping_add(adjustment)),
+        None if adjustment == 0 => None,
+        None => {
+          //the implicit addends of R_386_32 and R_386_PC32, other REL fields are encoded in the instruction
+          let movable = self.elf.header.description.machine == EM_386 && (relocation.relocation_type == 1 || relocation.relocation_type == 2);
+          let field = bytes.get_mut(offset as usize..offset as usize + 4)
+            .filter(|_| movable)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, format!("cannot move the implicit addend of relocation type {}", relocation.relocation_type)))?;
+          let value = i32::from_le_bytes([field[0], field[1], field[2], field[3]]).wrapping_add(adjustment as i32);
+          field.copy_from_slice(&value.to_le_bytes());
+          None
+        },
+      };
+      copied.push(SnippetRelocation { offset, relocation_type: relocation.relocation_type, symbol, addend });
+    }
+    Ok(copied)
+  }
+}
+
+impl Elf {
+  //The function `name` as a snippet that can be linked or emulated on its own. A relocatable
+  //object, or a file linked with --emit-relocs, gives the relocations of the code: references
+  //to data come with a copy of the data, anything else becomes an undefined symbol. Other
+  //linked files only give the calls and jumps out of the function (see call_sites), their
+  //data references are left as offsets into the original file.
+  pub fn extract_function(&self, name: &str) -> io::Result<FunctionSnippet> {
+    let function = self.symbol_table().iter().chain(self.dynamic_symbol_table())
+      .find(|symbol| (symbol.symbol_type == STT_FUNC || symbol.symbol_type == STT_GNU_IFUNC) && !symbol.is_undefined() && unversioned(&symbol.name) == name)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no function {}", name)))?;
+    if function.size == 0 {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("function {} has no size", name)));
+    }
+    let section = self.section_headers.get(function.section_index as usize).filter(|_| function.section_index < SHN_LORESERVE)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("function {} is not in a section", name)))?;
+    let start = function.value.wrapping_sub(section.address) as usize;
+    let code = self.section_data(section).get(start..start.saturating_add(function.size as usize))
+      .filter(|_| section.section_type != SHT_NOBITS)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("function {} is outside its section", name)))?;
+    check_align(section.align, self.data.len() as u64, name)?;
+
+    //relocations left by the linker are not loaded, the dynamic ones are
+    let relocation_sections: Vec<usize> = self.section_headers.iter().enumerate()
+      .filter(|(_, relocations)| (relocations.section_type == SHT_REL || relocations.section_type == SHT_RELA) && relocations.flags & SHF_ALLOC == 0)
+      .filter(|(_, relocations)| relocations.info == function.section_index as u32)
+      .map(|(index, _)| index)
+      .collect();
+    let mut snippet = FunctionSnippet {
+      name: Arc::from(name),
+      address: function.value,
+      code: code.to_vec(),
+      align: section.align,
+      relocations: Vec::new(),
+      data: Vec::new(),
+      class: self.header.identification.class,
+      endianness: self.header.identification.endianness,
+      os_abi: self.header.identification.os_abi,
+      machine: self.header.description.machine,
+      flags: self.header.description.flags,
+      rela: self.header.description.machine != EM_386,
+      file_size: self.data.len() as u64,
+    };
+    if self.header.description.obj_type == ET_REL || !relocation_sections.is_empty() {
+      if let Some(&index) = relocation_sections.first() {
+        snippet.rela = self.section_headers[index].section_type == SHT_RELA;
+      }
+      self.copy_snippet_relocations(function, &mut snippet)?;
+    } else {
+      self.decode_snippet_calls(function, &mut snippet);
+    }
+    Ok(snippet)
+  }
+
+  fn snippet_referent<'a>(&'a self, function: &Symbol, relocation: &Relocation) -> Referent<'a> {
+    let symbol = match self.relocation_symbol(relocation.section_index, relocation.symbol_index) {
+      Some(symbol) if relocation.symbol_index != 0 => symbol,
+      _ => return Referent::None,
+    };
+    let external = || Referent::External(Arc::from(unversioned(&symbol.name)));
+    if symbol.section_index == SHN_COMMON {
+      return Referent::Common(symbol);
+    }
+    let section = match self.section_headers.get(symbol.section_index as usize) {
+      Some(section) if !symbol.is_undefined() && symbol.section_index < SHN_LORESERVE => section,
+      _ => return external(),
+    };
+    if symbol.symbol_type == STT_SECTION {
+      if symbol.section_index == function.section_index {
+        return Referent::Function(section.address.wrapping_sub(function.value) as i64);
+      }
+      if section.flags & SHF_EXECINSTR != 0 {
+        return Referent::External(Arc::from(self.section_name(section).unwrap_or("")));
+      }
+      return Referent::Data { section: symbol.section_index as usize, offset: 0, symbol: None };
+    }
+    if symbol.section_index == function.section_index && symbol.value == function.value && symbol.name == function.name {
+      return Referent::Function(0);
+    }
+    if section.flags & SHF_EXECINSTR != 0 {
+      return external();
+    }
+    let sized = Some(symbol).filter(|symbol| symbol.size > 0);
+    Referent::Data { section: symbol.section_index as usize, offset: symbol.value.wrapping_sub(section.address) as i64, symbol: sized }
+  }
+
+  fn copy_snippet_relocations(&self, function: &Symbol, snippet: &mut FunctionSnippet) -> io::Result<()> {
+    let mut relocations: HashMap<usize, Vec<Relocation>> = HashMap::new();
+    for relocation in self.relocations() {
+      let section = &self.section_headers[relocation.section_index];
+      if section.flags & SHF_ALLOC == 0 {
+        relocations.entry(section.info as usize).or_default().push(relocation);
+      }
+    }
+    if self.header.description.obj_type != ET_REL && relocations.values().flatten().any(|relocation| relocation.addend.is_none()) {
+      return Err(io::Error::new(io::ErrorKind::Unsupported, "REL relocations of a linked file hold resolved values, not addends"));
+    }
+    let mut copier = SnippetCopier { elf: self, function, relocations, whole: HashMap::new(), symbols: HashMap::new(), commons: HashMap::new(), pending: Vec::new(), data: Vec::new() };
+    let mut code = std::mem::take(&mut snippet.code);
+    snippet.relocations = copier.relocate(function.section_index as usize, &mut code, function.value)?;
+    snippet.code = code;
+    //the data copied, then what its relocations bring in
+    while let Some((index, section, start)) = copier.pending.pop() {
+      let mut bytes = std::mem::take(&mut copier.data[index].bytes);
+      copier.data[index].relocations = copier.relocate(section, &mut bytes, start)?;
+      copier.data[index].bytes = bytes;
+    }
+    snippet.data = copier.data;
+    Ok(())
+  }
+
+  fn decode_snippet_calls(&self, function: &Symbol, snippet: &mut FunctionSnippet) {
+    let end = function.value + function.size;
+    let mut names: HashMap<u64, Arc<str>> = HashMap::new();
+    for symbol in self.symbol_table().iter().chain(self.dynamic_symbol_table()) {
+      if (symbol.symbol_type == STT_FUNC || symbol.symbol_type == STT_GNU_IFUNC) && !symbol.is_undefined() {
+        names.entry(symbol.value).or_insert_with(|| Arc::from(unversioned(&symbol.name)));
+      }
+    }
+    for entry in self.plt_entries() {
+      names.insert(entry.address, Arc::from(unversioned(&entry.name)));
+    }
+    let slots = self.got_slot_symbols();
+    let targets: Vec<u64> = names.keys().copied().filter(|&address| address < function.value || address >= end).collect();
+    let slot_addresses: Vec<u64> = slots.keys().copied().collect();
+    for site in self.call_sites(&targets, &slot_addresses) {
+      if site.address < function.value || site.address >= end {
+        continue;
+      }
+      let offset = site.address - function.value;
+      let symbol = match names.get(&site.target).or_else(|| slots.get(&site.target)) {
+        Some(name) => Arc::from(unversioned(name)),
+        None => continue,
+      };
+      //R_X86_64_PLT32, R_X86_64_GOTPCRELX, R_386_PC32, R_AARCH64_CALL26 and JUMP26
+      let (field, relocation_type, addend) = match (self.header.description.machine, site.kind) {
+        (EM_X86_64, CallKind::Call | CallKind::Jump) => (offset + 1, 4, Some(-4)),
+        (EM_X86_64, CallKind::IndirectCall | CallKind::IndirectJump) => (offset + 2, 41, Some(-4)),
+        (EM_386, CallKind::Call | CallKind::Jump) => (offset + 1, 2, None),
+        (EM_AARCH64, CallKind::Call) => (offset, 283, Some(0)),
+        (EM_AARCH64, CallKind::Jump) => (offset, 282, Some(0)),
+        _ => continue,
+      };
+      let bytes = match snippet.code.get_mut(field as usize..field as usize + 4) {
+        Some(bytes) => bytes,
+        None => continue,
+      };
+      //the field is cleared, or holds the implicit addend, so nothing of the old layout is left.
+      //A64 instructions are little-endian in big-endian images too.
+      if self.header.description.machine == EM_AARCH64 {
+        let word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) & 0xfc00_0000;
+        bytes.copy_from_slice(&word.to_le_bytes());
+      } else {
+        bytes.copy_from_slice(&addend.map_or(-4i32, |_| 0).to_le_bytes());
+      }
+      snippet.relocations.push(SnippetRelocation { offset: field, relocation_type, symbol, addend });
+    }
+  }
+}
+
+impl FunctionSnippet {
+  //The snippet as a relocatable object: the code in .text under the function's name, global,
+  //each data in a section of its own under a local symbol, and an undefined global symbol for
+  //each other name the relocations refer to. InvalidData when an alignment was changed to one
+  //extract_function would have rejected.
+  pub fn to_object(&self) -> io::Result<Vec<u8>> {
+    check_align(self.align, self.file_size, &self.name)?;
+    for data in &self.data {
+      check_align(data.align, self.file_size, &data.name)?;
+    }
+    Ok(match self.endianness {
+      1 => self.to_object_with_byteorder::<LittleEndian>(),
+      2 => self.to_object_with_byteorder::<BigEndian>(),
+      _ => panic!("unknown endianness"),
+    })
+  }
+
+  fn to_object_with_byteorder<E: ByteOrder>(&self) -> Vec<u8> {
+    match self.class {
+      1 => self.to_object_with_layout::<E, Elf32>(),
+      2 => self.to_object_with_layout::<E, Elf64>(),
+      _ => panic!("unknown class"),
+    }
+  }
+
+  fn to_object_with_layout<E: ByteOrder, L: ElfLayout>(&self) -> Vec<u8> {
+    let mut w = ObjectWriter::<E, L>::new(self.endianness, ET_REL, self.machine, 0);
+    w.header.identification.os_abi = self.os_abi;
+    w.header.description.flags = self.flags;
+    let word_size = ObjectWriter::<E, L>::word_size();
+
+    w.align(self.align);
+    let text = w.bytes(&self.code);
+    w.section(".text", SectionHeader { section_type: SHT_PROGBITS, flags: SHF_ALLOC | SHF_EXECINSTR, offset: text, size: self.code.len() as u64, align: self.align, ..Default::default() });
+    for data in &self.data {
+      w.align(data.align);
+      let offset = w.bytes(&data.bytes);
+      let section = SectionHeader {
+        section_type: data.section_type,
+        flags: data.flags,
+        offset,
+        size: data.size,
+        align: data.align,
+        entry_size: data.entry_size,
+        ..Default::default()
+      };
+      w.section(&data.section_name, section);
+    }
+    //an empty .note.GNU-stack, or the linker makes the stack executable
+    let offset = w.position();
+    w.section(".note.GNU-stack", SectionHeader { section_type: SHT_PROGBITS, offset, align: 1, ..Default::default() });
+
+    //locals come before globals
+    let mut strtab = b"\0".to_vec();
+    let mut indices: HashMap<&str, u32> = HashMap::new();
+    w.align(word_size);
+    let symtab = w.position();
+    w.symbol(0, &Symbol::default());
+    for (index, data) in self.data.iter().enumerate() {
+      let symbol_type = if data.flags & SHF_TLS != 0 { STT_TLS } else { STT_OBJECT };
+      let symbol = Symbol { size: data.size, symbol_type, binding: STB_LOCAL, section_index: 2 + index as u16, ..Default::default() };
+      w.symbol(section_writer::string(&mut strtab, &data.name), &symbol);
+      indices.insert(&data.name, 1 + index as u32);
+    }
+    let first_global = 1 + self.data.len() as u32;
+    let function = Symbol { size: self.code.len() as u64, symbol_type: STT_FUNC, binding: STB_GLOBAL, section_index: 1, ..Default::default() };
+    w.symbol(section_writer::string(&mut strtab, &self.name), &function);
+    indices.insert(&self.name, first_global);
+    for relocation in self.relocations.iter().chain(self.data.iter().flat_map(|data| &data.relocations)) {
+      if !relocation.symbol.is_empty() && !indices.contains_key(&*relocation.symbol) {
+        let external = Symbol { symbol_type: STT_NOTYPE, binding: STB_GLOBAL, ..Default::default() };
+        w.symbol(section_writer::string(&mut strtab, &relocation.symbol), &external);
+        indices.insert(&relocation.symbol, indices.len() as u32 + 1);
+      }
+    }
+    let symtab_size = w.position() - symtab;
+    let strtab_offset = w.bytes(&strtab);
+
+    //the relocations of .text and of each data, as (section index, data index)
+    let relocated: Vec<(u32, Option<usize>)> = Some((1, None)).into_iter().filter(|_| !self.relocations.is_empty())
+      .chain(self.data.iter().enumerate().filter(|(_, data)| !data.relocations.is_empty()).map(|(index, _)| (2 + index as u32, Some(index))))
+      .collect();
+    let symtab_index = w.section_count() + relocated.len() as u32;
+    for (target, data) in relocated {
+      let (relocations, target_name) = match data {
+        Some(index) => (&self.data[index].relocations, self.data[index].section_name.as_str()),
+        None => (&self.relocations, ".text"),
+      };
+      w.align(word_size);
+      let start = w.position();
+      for relocation in relocations {
+        let symbol_index = indices.get(&*relocation.symbol).copied().unwrap_or(0);
+        let raw = Relocation { section_index: 0, offset: relocation.offset, symbol_index, relocation_type: relocation.relocation_type, addend: relocation.addend };
+        w.relocation(&raw, self.rela);
+      }
+      let (section_type, prefix) = if self.rela { (SHT_RELA, ".rela") } else { (SHT_REL, ".rel") };
+      let section = SectionHeader {
+        section_type,
+        flags: SHF_INFO_LINK,
+        offset: start,
+        size: w.position() - start,
+        link: symtab_index,
+        info: target,
+        align: word_size,
+        entry_size: ObjectWriter::<E, L>::relocation_size(self.rela),
+        ..Default::default()
+      };
+      w.section(&format!("{}{}", prefix, target_name), section);
+    }
+    let symbols = SectionHeader {
+      section_type: SHT_SYMTAB,
+      offset: symtab,
+      size: symtab_size,
+      link: symtab_index + 1,
+      info: first_global,
+      align: word_size,
+      entry_size: ObjectWriter::<E, L>::symbol_size(),
+      ..Default::default()
+    };
+    w.section(".symtab", symbols);
+    w.section(".strtab", SectionHeader { section_type: SHT_STRTAB, offset: strtab_offset, size: strtab.len() as u64, align: 1, ..Default::default() });
+    w.finish()
+  }
+}
@@ -0,0 +1,206 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::dynamic::DynamicEntry;
+use crate::elf::{ProgramHeader, SectionHeader};
+use crate::layout::{Elf32, Elf64, ElfLayout};
+use crate::section_writer::ObjectWriter;
+use crate::symbol::Symbol;
+
+//One cell of the fixture matrix.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FixtureSpec {
+  pub class: u8,
+  pub endianness: u8,
+  pub obj_type: u16,
+  pub machine: u16,
+}
+
+pub const FIXTURE_MACHINES: &[u16] = &[EM_386, EM_MIPS, EM_PPC, EM_PPC64, EM_S390, EM_ARM, EM_X86_64, EM_AARCH64, EM_RISCV];
+
+const FIXTURE_TYPES: &[u16] = &[ET_REL, ET_EXEC, ET_DYN, ET_CORE];
+
+//Every combination of class, byte order, object type and FIXTURE_MACHINES.
+pub fn fixture_matrix() -> Vec<(FixtureSpec, Vec<u8>)> {
+  let mut fixtures = Vec::new();
+  for &class in &[ELFCLASS32, ELFCLASS64] {
+    for &endianness in &[ELFDATA2LSB, ELFDATA2MSB] {
+      for &obj_type in FIXTURE_TYPES {
+        for &machine in FIXTURE_MACHINES {
+          let spec = FixtureSpec { class, endianness, obj_type, machine };
+          fixtures.push((spec, fixture(spec)));
+        }
+      }
+    }
+  }
+  fixtures
+}
+
+//A minimal file the parsers of this crate accept strictly and `readelf -a` and `objdump -x`
+//print without a warning (tests/fixtures.rs checks both):
+//- ET_REL: .text, .symtab, .strtab and .shstrtab
+//- ET_EXEC: the same behind a PT_LOAD, with the entry point at _start
+//- ET_DYN: adds .dynsym, .dynstr and .dynamic with a DT_SONAME, and PT_DYNAMIC
+//- ET_CORE: a PT_NOTE holding an empty NT_PRSTATUS and one PT_LOAD, no sections
+//.text is zero filled, the fixtures are for parsers and not meant to run.
+pub fn fixture(spec: FixtureSpec) -> Vec<u8> {
+  match spec.endianness {
+    ELFDATA2LSB => fixture_with_byteorder::<LittleEndian>(spec),
+    ELFDATA2MSB => fixture_with_byteorder::<BigEndian>(spec),
+    _ => panic!("unknown endianness"),
+  }
+}
+
+fn fixture_with_byteorder<E: ByteOrder>(spec: FixtureSpec) -> Vec<u8> {
+  match spec.class {
+    ELFCLASS32 => fixture_with_layout::<E, Elf32>(spec),
+    ELFCLASS64 => fixture_with_layout::<E, Elf64>(spec),
+    _ => panic!("unknown class"),
+  }
+}
+
+fn segment(entry_type: u32, flags: u32, offset: u64, address: u64, file_size: u64, memory_size: u64, align: u64) -> ProgramHeader {
+  ProgramHeader { entry_type, flags, offset, virtual_address: address, physical_address: address, file_size, memory_size, align }
+}
+
+fn fixture_with_layout<E: ByteOrder, L: ElfLayout>(spec: FixtureSpec) -> Vec<u8> {
+  let word_size = ObjectWriter::<E, L>::word_size();
+  let header_size = ObjectWriter::<E, L>::header_size();
+  let program_entry_size = ObjectWriter::<E, L>::program_header_size();
+  let symbol_size = ObjectWriter::<E, L>::symbol_size();
+  let base: u64 = match spec.obj_type {
+    ET_EXEC if L::CLASS == ELFCLASS64 => 0x40_0000,
+    ET_EXEC => 0x0804_8000,
+    _ => 0,
+  };
+  let program_headers: u64 = match spec.obj_type {
+    ET_EXEC => 2,
+    ET_DYN => 4,
+    ET_CORE => 2,
+    _ => 0,
+  };
+  let mut w = ObjectWriter::<E, L>::new(spec.endianness, spec.obj_type, spec.machine, program_headers as usize);
+  //e_phentsize is set in files without program headers too
+  w.header.description.program_hdr_entry_size = program_entry_size as u16;
+  let function = |name: &str, value: u64| Symbol {
+    name: name.into(),
+    value,
+    size: 16,
+    symbol_type: STT_FUNC,
+    binding: STB_GLOBAL,
+    section_index: 1,
+    ..Default::default()
+  };
+
+  if spec.obj_type == ET_CORE {
+    w.align(4);
+    let note = w.position();
+    w.u32(5);
+    w.u32(8 * word_size as u32);
+    w.u32(1);
+    w.bytes(b"CORE\0\0\0\0");
+    w.pad_to(w.position() + 8 * word_size);
+    let note_size = w.position() - note;
+    w.align(0x1000);
+    let memory = w.position();
+    w.pad_to(memory + 0x10);
+    w.segments.push(segment(PT_NOTE, 4, note, 0, note_size, 0, 4));
+    w.segments.push(segment(PT_LOAD, 6, memory, 0x1_0000, 0x10, 0x1000, 0x1000));
+    return w.finish();
+  }
+
+  let allocated = if spec.obj_type == ET_REL { 0 } else { SHF_ALLOC };
+  let address = |offset: u64| if allocated != 0 { base + offset } else { 0 };
+  w.align(16);
+  let text = w.bytes(&[0; 16]);
+  w.section(".text", SectionHeader {
+    section_type: SHT_PROGBITS,
+    flags: allocated | SHF_EXECINSTR,
+    address: address(text),
+    offset: text,
+    size: 16,
+    align: 16,
+    ..Default::default()
+  });
+  if spec.obj_type == ET_EXEC {
+    w.header.description.entry = address(text);
+  }
+  let text_end = w.position();
+  if spec.obj_type != ET_REL {
+    w.segments.push(segment(PT_LOAD, 5, 0, base, text_end, text_end, 0x1000));
+  }
+
+  if spec.obj_type == ET_DYN {
+    let dynstr_bytes = b"\0libfixture.so\0fixture_function\0";
+    w.align(word_size);
+    let dynsym = w.position();
+    w.symbol(0, &Symbol::default());
+    w.symbol(15, &function("fixture_function", address(text)));
+    let dynstr = w.bytes(dynstr_bytes);
+    w.align(word_size);
+    let dynamic = w.position();
+    for &(tag, value) in &[(DT_SONAME, 1), (DT_STRTAB, address(dynstr)), (DT_SYMTAB, address(dynsym)), (DT_STRSZ, dynstr_bytes.len() as u64), (DT_SYMENT, symbol_size), (DT_NULL, 0)] {
+      w.dynamic(&DynamicEntry { tag, value });
+    }
+    let dynamic_end = w.position();
+    let dynstr_index = w.section_count() + 1;
+    w.section(".dynsym", SectionHeader {
+      section_type: SHT_DYNSYM,
+      flags: SHF_ALLOC,
+      address: address(dynsym),
+      offset: dynsym,
+      size: 2 * symbol_size,
+      link: dynstr_index,
+      info: 1,
+      align: word_size,
+      entry_size: symbol_size,
+      ..Default::default()
+    });
+    w.section(".dynstr", SectionHeader {
+      section_type: SHT_STRTAB,
+      flags: SHF_ALLOC,
+      address: address(dynstr),
+      offset: dynstr,
+      size: dynstr_bytes.len() as u64,
+      align: 1,
+      ..Default::default()
+    });
+    w.section(".dynamic", SectionHeader {
+      section_type: SHT_DYNAMIC,
+      flags: SHF_ALLOC | SHF_WRITE,
+      address: address(dynamic),
+      offset: dynamic,
+      size: dynamic_end - dynamic,
+      link: dynstr_index,
+      align: word_size,
+      entry_size: ObjectWriter::<E, L>::dynamic_size(),
+      ..Default::default()
+    });
+    w.segments.push(segment(PT_LOAD, 6, dynsym, base + dynsym, dynamic_end - dynsym, dynamic_end - dynsym, 0x1000));
+    w.segments.push(segment(PT_DYNAMIC, 6, dynamic, base + dynamic, dynamic_end - dynamic, dynamic_end - dynamic, word_size));
+  }
+  if spec.obj_type != ET_REL {
+    //PT_PHDR comes first
+    let size = program_headers * program_entry_size;
+    w.segments.insert(0, segment(PT_PHDR, 4, header_size, base + header_size, size, size, word_size));
+  }
+
+  let strtab_bytes = b"\0_start\0";
+  w.align(word_size);
+  let symtab = w.position();
+  w.symbol(0, &Symbol::default());
+  w.symbol(1, &function("_start", address(text)));
+  let strtab = w.bytes(strtab_bytes);
+  let strtab_index = w.section_count() + 1;
+  w.section(".symtab", SectionHeader {
+    section_type: SHT_SYMTAB,
+    offset: symtab,
+    size: 2 * symbol_size,
+    link: strtab_index,
+    info: 1,
+    align: word_size,
+    entry_size: symbol_size,
+    ..Default::default()
+  });
+  w.section(".strtab", SectionHeader { section_type: SHT_STRTAB, offset: strtab, size: strtab_bytes.len() as u64, align: 1, ..Default::default() });
+  w.finish()
+}
@@ -0,0 +1,124 @@
+//Entry points for fuzz targets. Invariant violations panic so a fuzzer records them as crashes,
+//see fuzz/fuzz_targets in the crate for the cargo-fuzz target and tests/fuzz.rs for the
+//corpus every test run replays.
+use crate::cfi::UnwindSource;
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::loader::StackOptions;
+use crate::parse_options::{ParseOptions, UnknownValuePolicy};
+
+//Which parsers accepted the input.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct OracleOutcome {
+  pub strict: bool,
+  pub lenient: bool,
+}
+
+fn same_tables(a: &Elf, b: &Elf) -> bool {
+  a.section_headers.len() == b.section_headers.len()
+    && a.program_headers.len() == b.program_headers.len()
+    && a.section_headers.iter().zip(&b.section_headers).all(|(a, b)| {
+      (a.name_index, a.section_type, a.flags, a.address, a.offset, a.size, a.link, a.info, a.align, a.entry_size)
+        == (b.name_index, b.section_type, b.flags, b.address, b.offset, b.size, b.link, b.info, b.align, b.entry_size)
+    })
+    && a.program_headers.iter().zip(&b.program_headers).all(|(a, b)| {
+      (a.entry_type, a.flags, a.offset, a.virtual_address, a.physical_address, a.file_size, a.memory_size, a.align)
+        == (b.entry_type, b.flags, b.offset, b.virtual_address, b.physical_address, b.file_size, b.memory_size, b.align)
+    })
+}
+
+//The strict parser: unknown values are errors, the entry sizes fit the class and the header
+//tables, every section with contents and every segment lie inside the file. The lenient one
+//is Elf::parse keeping unknown values.
+fn strict_parse(bytes: &[u8]) -> Option<Elf> {
+  let (elf, _) = Elf::parse(bytes.into(), &ParseOptions { unknown_values: UnknownValuePolicy::Error }).ok()?;
+  elf.validate_entry_sizes().ok()?;
+  let inside = |offset: u64, size: u64| offset.checked_add(size).is_some_and(|end| end <= bytes.len() as u64);
+  let description = &elf.header.description;
+  //e_shnum 0 with a table means the count is in the first entry
+  let all_sections_read = description.section_hdr_num == 0 || elf.section_headers.len() == description.section_hdr_num as usize;
+  let tables_inside = inside(description.program_hdr_offset, description.program_hdr_num as u64 * description.program_hdr_entry_size as u64)
+    && inside(description.section_hdr_offset, elf.section_headers.len() as u64 * description.section_hdr_entry_size as u64)
+    && all_sections_read;
+  let sections_inside = elf.section_headers.iter().all(|section| section.section_type == SHT_NOBITS || inside(section.offset, section.size));
+  let segments_inside = elf.program_headers.iter().all(|ph| inside(ph.offset, ph.file_size));
+  (tables_inside && sections_inside && segments_inside).then_some(elf)
+}
+
+//The decoders an input reaches past the tables, they only need to finish.
+fn decode_everything(elf: &Elf) {
+  let _ = elf.symbols();
+  let _ = elf.dynamic_symbols();
+  let _ = elf.relocations();
+  let _ = elf.dynamic_entries();
+  let _ = elf.notes();
+  let _ = elf.unknown_values();
+  let _ = elf.validate_entry_sizes();
+  for index in 0..elf.section_headers.len() {
+    let _ = elf.section_content(index);
+  }
+
+  let dwarf = elf.dwarf();
+  for unit in dwarf.units() {
+    let _ = dwarf.unit_functions(&unit);
+    let _ = dwarf.line_program(&unit);
+  }
+  let _ = elf.source_files();
+  let _ = elf.type_layout("S");
+  let _ = elf.gdb_index().map(|index| index.names());
+  for index in elf.debug_names() {
+    let _ = index.names();
+  }
+  let _ = elf.unwind_table();
+  let _ = elf.sframe().map(|sframe| sframe.frame_descriptions());
+  let _ = elf.exception_tables();
+  let _ = elf.cpp_classes();
+  let map = elf.function_map();
+  let _ = map.symbolize(elf.header.description.entry);
+  let _ = elf.jump_tables();
+  let _ = elf.security_report();
+  let _ = elf.syscall_inventory();
+  let _ = elf.cpu_requirements();
+  //a small limit keeps huge p_memsz values from costing the fuzzer its memory budget
+  if let Ok(image) = elf.load_with_limit(None, 1 << 24) {
+    let _ = image.initial_stack(&StackOptions::default());
+  }
+}
+
+//Runs the strict and the lenient parser over `bytes` and checks that
+//- strict acceptance implies lenient acceptance of the same structure,
+//- sections of a strictly accepted file have all of their bytes,
+//- every query and decoder the lenient result answers runs without panicking,
+//- every span lies inside the input,
+//- writing the parsed header back reproduces the input and parses to the same tables.
+pub fn parse_oracle(bytes: &[u8]) -> OracleOutcome {
+  let strict = strict_parse(bytes);
+  let lenient = Elf::parse(bytes.into(), &ParseOptions { unknown_values: UnknownValuePolicy::Preserve }).ok();
+  let outcome = OracleOutcome { strict: strict.is_some(), lenient: lenient.is_some() };
+  let elf = match lenient {
+    Some((elf, _)) => elf,
+    None => {
+      assert!(strict.is_none(), "strict parser accepted what the lenient one refused");
+      return outcome;
+    },
+  };
+  if let Some(strict) = &strict {
+    assert!(same_tables(strict, &elf), "strict and lenient parsers disagree");
+    for section in strict.section_headers.iter().filter(|section| section.section_type != SHT_NOBITS) {
+      assert!(strict.section_data(section).len() as u64 == section.size, "a section inside the file lost bytes");
+    }
+  }
+
+  decode_everything(&elf);
+
+  for span in elf.spans() {
+    assert!(span.start <= span.end && span.end <= bytes.len() as u64, "span {} {:#x}..{:#x} outside the input", span.label, span.start, span.end);
+  }
+
+  let mut written = Elf::new(bytes.into());
+  written.write_header();
+  assert!(*written.data == *bytes, "writing the parsed header changed the input");
+  let reparsed = Elf::new(written.data.clone());
+  assert!(same_tables(&reparsed, &elf), "the written header parses to different tables");
+  outcome
+}
@@ -0,0 +1,539 @@
+use std::convert::TryFrom;
+use std::io;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::{read_str, Elf, ProgramHeader, SectionHeader};
+use crate::layout::*;
+
+pub const ELF_MAGIC: u32 = 0x7f45_4c46;
+pub const EV_CURRENT: u32 = 1;
+pub const SHN_UNDEF: u16 = 0;
+pub const SHN_XINDEX: u16 = 0xffff;
+
+pub struct HeaderFix {
+  pub field: &'static str,
+  pub old: u64,
+  pub new: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ObjectType {
+  None,
+  Relocatable,
+  Executable,
+  SharedObject,
+  Core,
+  //ET_LOOS to ET_HIOS, the raw value
+  OsSpecific(u16),
+  //ET_LOPROC to ET_HIPROC, the raw value
+  ProcessorSpecific(u16),
+  Unknown(u16),
+}
+
+impl ObjectType {
+  pub fn from_raw(obj_type: u16) -> ObjectType {
+    match obj_type {
+      ET_NONE => ObjectType::None,
+      ET_REL => ObjectType::Relocatable,
+      ET_EXEC => ObjectType::Executable,
+      ET_DYN => ObjectType::SharedObject,
+      ET_CORE => ObjectType::Core,
+      ET_LOOS..=ET_HIOS => ObjectType::OsSpecific(obj_type),
+      ET_LOPROC..=ET_HIPROC => ObjectType::ProcessorSpecific(obj_type),
+      _ => ObjectType::Unknown(obj_type),
+    }
+  }
+
+  pub fn raw(self) -> u16 {
+    match self {
+      ObjectType::None => ET_NONE,
+      ObjectType::Relocatable => ET_REL,
+      ObjectType::Executable => ET_EXEC,
+      ObjectType::SharedObject => ET_DYN,
+      ObjectType::Core => ET_CORE,
+      ObjectType::OsSpecific(obj_type) | ObjectType::ProcessorSpecific(obj_type) | ObjectType::Unknown(obj_type) => obj_type,
+    }
+  }
+}
+
+fn class_sizes<L: ElfLayout>() -> (u16, u16, u16) {
+  (L::Header::SIZE as u16, L::ProgramHeader::SIZE as u16, L::SectionHeader::SIZE as u16)
+}
+
+fn invalid(message: String) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+impl Elf {
+  pub fn object_type(&self) -> ObjectType {
+    ObjectType::from_raw(self.header.description.obj_type)
+  }
+
+  //e_ehsize, e_phentsize and e_shentsize of the class, ELFCLASS64's for unknown classes
+  fn class_sizes(&self) -> (u16, u16, u16) {
+    match self.header.identification.class {
+      1 => class_sizes::<Elf32>(),
+      _ => class_sizes::<Elf64>(),
+    }
+  }
+
+  pub fn expected_elf_hdr_size(&self) -> u16 {
+    self.class_sizes().0
+  }
+
+  pub fn expected_program_hdr_entry_size(&self) -> u16 {
+    self.class_sizes().1
+  }
+
+  pub fn expected_section_hdr_entry_size(&self) -> u16 {
+    self.class_sizes().2
+  }
+
+  //Distance between entries of the tables. Larger e_phentsize and e_shentsize values are
+  //vendor extensions with extra bytes after the standard fields, smaller ones cannot hold an
+  //entry and the class size is used.
+  pub fn program_hdr_stride(&self) -> u16 {
+    self.header.description.program_hdr_entry_size.max(self.expected_program_hdr_entry_size())
+  }
+
+  pub fn section_hdr_stride(&self) -> u16 {
+    self.header.description.section_hdr_entry_size.max(self.expected_section_hdr_entry_size())
+  }
+
+  //Entry sizes too small for the class, only checked for tables the file has.
+  pub fn validate_entry_sizes(&self) -> io::Result<()> {
+    let description = &self.header.description;
+    let tables = [
+      ("e_phentsize", description.program_hdr_num != 0, description.program_hdr_entry_size, self.expected_program_hdr_entry_size()),
+      ("e_shentsize", description.section_hdr_offset != 0, description.section_hdr_entry_size, self.expected_section_hdr_entry_size()),
+    ];
+    for &(field, present, size, expected) in &tables {
+      if present && size < expected {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} {} is smaller than the class entry ({})", field, size, expected)));
+      }
+    }
+    Ok(())
+  }
+
+  pub fn set_magic(&mut self, magic: u32) -> io::Result<()> {
+    if magic != ELF_MAGIC {
+      return Err(invalid(format!("magic {:#010x} is not an ELF magic", magic)));
+    }
+    self.header.identification.magic = magic;
+    self.write_header();
+    Ok(())
+  }
+
+  //Only the header is converted, the tables keep the layout they were written with. e_ehsize
+  //and the entry sizes of the tables the file has become those of the new class.
+  pub fn set_class(&mut self, class: u8) -> io::Result<()> {
+    if class != ELFCLASS32 && class != ELFCLASS64 {
+      return Err(invalid(format!("unknown class {}", class)));
+    }
+    self.header.identification.class = class;
+    self.header.description.elf_hdr_size = self.expected_elf_hdr_size();
+    if self.header.description.program_hdr_entry_size != 0 {
+      self.header.description.program_hdr_entry_size = self.expected_program_hdr_entry_size();
+    }
+    if self.header.description.section_hdr_entry_size != 0 {
+      self.header.description.section_hdr_entry_size = self.expected_section_hdr_entry_size();
+    }
+    self.write_header();
+    Ok(())
+  }
+
+  //Only the header is converted, the tables keep the byte order they were written with.
+  pub fn set_endianness(&mut self, endianness: u8) -> io::Result<()> {
+    if endianness != ELFDATA2LSB && endianness != ELFDATA2MSB {
+      return Err(invalid(format!("unknown endianness {}", endianness)));
+    }
+    self.header.identification.endianness = endianness;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_identification_version(&mut self, version: u8) -> io::Result<()> {
+    if version as u32 != EV_CURRENT {
+      return Err(invalid(format!("unknown identification version {}", version)));
+    }
+    self.header.identification.version = version;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_os_abi(&mut self, os_abi: u8) -> io::Result<()> {
+    self.header.identification.os_abi = os_abi;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_abi_version(&mut self, abi_version: u8) -> io::Result<()> {
+    self.header.identification.abi_version = abi_version;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_obj_type(&mut self, obj_type: u16) -> io::Result<()> {
+    if let ObjectType::Unknown(_) = ObjectType::from_raw(obj_type) {
+      return Err(invalid(format!("unknown object type {:#x}", obj_type)));
+    }
+    self.header.description.obj_type = obj_type;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_machine(&mut self, machine: u16) -> io::Result<()> {
+    self.header.description.machine = machine;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_version(&mut self, version: u32) -> io::Result<()> {
+    if version != EV_CURRENT {
+      return Err(invalid(format!("unknown version {}", version)));
+    }
+    self.header.description.version = version;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_entry(&mut self, entry: u64) -> io::Result<()> {
+    let mapped = self.program_headers.iter()
+      .any(|ph| ph.entry_type == PT_LOAD && entry >= ph.virtual_address && entry - ph.virtual_address < ph.memory_size);
+    if entry != 0 && !mapped {
+      return Err(invalid(format!("entry {:#x} is outside of every PT_LOAD", entry)));
+    }
+    self.header.description.entry = entry;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_program_hdr_offset(&mut self, offset: u64) -> io::Result<()> {
+    let description = &self.header.description;
+    self.check_table("program header", offset, description.program_hdr_num, description.program_hdr_entry_size)?;
+    self.header.description.program_hdr_offset = offset;
+    self.write_header();
+    self.reload_tables();
+    Ok(())
+  }
+
+  pub fn set_section_hdr_offset(&mut self, offset: u64) -> io::Result<()> {
+    let description = &self.header.description;
+    self.check_table("section header", offset, description.section_hdr_num, description.section_hdr_entry_size)?;
+    self.header.description.section_hdr_offset = offset;
+    self.write_header();
+    self.reload_tables();
+    Ok(())
+  }
+
+  pub fn set_flags(&mut self, flags: u32) -> io::Result<()> {
+    self.header.description.flags = flags;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_elf_hdr_size(&mut self, size: u16) -> io::Result<()> {
+    if size != self.expected_elf_hdr_size() {
+      return Err(invalid(format!("header size {} does not match the class ({})", size, self.expected_elf_hdr_size())));
+    }
+    self.header.description.elf_hdr_size = size;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_program_hdr_entry_size(&mut self, size: u16) -> io::Result<()> {
+    if size < self.expected_program_hdr_entry_size() {
+      return Err(invalid(format!("program header size {} is smaller than the class entry ({})", size, self.expected_program_hdr_entry_size())));
+    }
+    self.header.description.program_hdr_entry_size = size;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_program_hdr_num(&mut self, num: u16) -> io::Result<()> {
+    let description = &self.header.description;
+    self.check_table("program header", description.program_hdr_offset, num, description.program_hdr_entry_size)?;
+    self.header.description.program_hdr_num = num;
+    self.write_header();
+    self.reload_tables();
+    Ok(())
+  }
+
+  pub fn set_section_hdr_entry_size(&mut self, size: u16) -> io::Result<()> {
+    if size < self.expected_section_hdr_entry_size() {
+      return Err(invalid(format!("section header size {} is smaller than the class entry ({})", size, self.expected_section_hdr_entry_size())));
+    }
+    self.header.description.section_hdr_entry_size = size;
+    self.write_header();
+    Ok(())
+  }
+
+  pub fn set_section_hdr_num(&mut self, num: u16) -> io::Result<()> {
+    let description = &self.header.description;
+    self.check_table("section header", description.section_hdr_offset, num, description.section_hdr_entry_size)?;
+    self.header.description.section_hdr_num = num;
+    self.write_header();
+    self.reload_tables();
+    Ok(())
+  }
+
+  pub fn set_section_hdr_str_index(&mut self, index: u16) -> io::Result<()> {
+    if index != SHN_UNDEF && index != SHN_XINDEX {
+      match self.section_headers.get(index as usize) {
+        Some(section) if section.section_type == SHT_STRTAB => {},
+        Some(_) => return Err(invalid(format!("section {} is not a string table", index))),
+        None => return Err(invalid(format!("section {} does not exist", index))),
+      };
+    }
+    self.header.description.section_hdr_str_index = index;
+    self.invalidate_caches();
+    self.write_header();
+    Ok(())
+  }
+
+  fn check_table(&self, table: &str, offset: u64, num: u16, entry_size: u16) -> io::Result<()> {
+    let end = offset.checked_add(num as u64 * entry_size as u64);
+    match end {
+      Some(end) if num == 0 || (offset >= self.expected_elf_hdr_size() as u64 && end <= self.data.len() as u64) => Ok(()),
+      _ => Err(invalid(format!("{} table at {:#x} with {} entries does not fit in the file", table, offset, num))),
+    }
+  }
+
+  pub fn write_header(&mut self) {
+    match self.header.identification.endianness {
+      1 => self.write_header_with_byteorder::<LittleEndian>(),
+      2 => self.write_header_with_byteorder::<BigEndian>(),
+      _ => panic!("unknown endianness"),
+    };
+  }
+
+  fn write_header_with_byteorder<E: ByteOrder>(&mut self) {
+    match self.header.identification.class {
+      1 => self.write_header_with_layout::<E, Elf32>(),
+      2 => self.write_header_with_layout::<E, Elf64>(),
+      _ => panic!("unknown class"),
+    };
+  }
+
+  fn write_header_with_layout<E: ByteOrder, L: ElfLayout>(&mut self) {
+    //EI_PAD is not part of the parsed header, whatever the file has there stays
+    let mut padding = [0u8; 7];
+    padding.copy_from_slice(&self.data[9..16]);
+    L::Header::narrow(&self.header).write::<E>(&mut self.data).unwrap();
+    self.data[9..16].copy_from_slice(&padding);
+  }
+
+  //Recomputes the table describing fields of the header from the tables that are actually in the file.
+  pub fn repair(&mut self) -> Vec<HeaderFix> {
+    let mut fixes = Vec::new();
+    let mut fix = |field: &'static str, old: u64, new: u64| {
+      if old != new {
+        fixes.push(HeaderFix { field, old, new });
+      }
+      new
+    };
+    let description = &self.header.description;
+    let elf_hdr_size = fix("e_ehsize", description.elf_hdr_size as u64, self.expected_elf_hdr_size() as u64) as u16;
+    let program_hdr_entry_size = fix("e_phentsize", description.program_hdr_entry_size as u64, self.program_hdr_stride() as u64) as u16;
+    let section_hdr_entry_size = fix("e_shentsize", description.section_hdr_entry_size as u64, self.section_hdr_stride() as u64) as u16;
+
+    let (program_hdr_offset, program_hdr_num) = if description.program_hdr_offset == 0 && description.obj_type == ET_REL {
+      (0, 0)
+    } else {
+      let offset = if self.check_table("program header", description.program_hdr_offset, 1, program_hdr_entry_size).is_ok() {
+        description.program_hdr_offset
+      } else {
+        elf_hdr_size as u64
+      };
+      (offset, self.count_program_headers(offset, program_hdr_entry_size))
+    };
+    let program_hdr_offset = fix("e_phoff", description.program_hdr_offset, program_hdr_offset);
+    let program_hdr_num = fix("e_phnum", description.program_hdr_num as u64, program_hdr_num as u64) as u16;
+
+    let declared = self.count_section_headers(description.section_hdr_offset, section_hdr_entry_size);
+    let (section_hdr_offset, section_hdr_num) = if declared > 0 {
+      (description.section_hdr_offset, declared)
+    } else {
+      self.find_section_headers(section_hdr_entry_size).unwrap_or((0, 0))
+    };
+    let section_hdr_offset = fix("e_shoff", description.section_hdr_offset, section_hdr_offset);
+    //SHN_LORESERVE entries or more: e_shnum 0 and the count in sh_size of entry 0
+    let extended = section_hdr_num >= SHN_LORESERVE as usize;
+    let section_hdr_num = fix("e_shnum", description.section_hdr_num as u64, if extended { 0 } else { section_hdr_num as u64 }) as u16;
+    //SHN_XINDEX: the index of the name table is in sh_link of entry 0
+    let old_str_index = match description.section_hdr_str_index {
+      SHN_XINDEX => self.section_header_at(description.section_hdr_offset).map_or(SHN_XINDEX as usize, |first| first.link as usize),
+      index => index as usize,
+    };
+    let sections = self.section_headers_at(section_hdr_offset, section_hdr_entry_size, self.count_section_headers(section_hdr_offset, section_hdr_entry_size));
+    if extended {
+      if let Some(mut first) = self.section_header_at(section_hdr_offset) {
+        first.size = fix("sh_size of section 0", first.size, sections.len() as u64);
+        self.write_section_header_at(section_hdr_offset, &first);
+      }
+    }
+
+    self.header.description.elf_hdr_size = elf_hdr_size;
+    self.header.description.program_hdr_entry_size = program_hdr_entry_size;
+    self.header.description.section_hdr_entry_size = section_hdr_entry_size;
+    self.header.description.program_hdr_offset = program_hdr_offset;
+    self.header.description.program_hdr_num = program_hdr_num;
+    self.header.description.section_hdr_offset = section_hdr_offset;
+    self.header.description.section_hdr_num = section_hdr_num;
+    self.reload_tables();
+
+    let section_hdr_str_index = self.find_section_name_table(&sections).unwrap_or(old_str_index);
+    fix("e_shstrndx", old_str_index as u64, section_hdr_str_index as u64);
+    //SHN_LORESERVE or more: e_shstrndx SHN_XINDEX and the index in sh_link of entry 0
+    self.header.description.section_hdr_str_index = if section_hdr_str_index >= SHN_LORESERVE as usize {
+      if let Some(mut first) = self.section_header_at(section_hdr_offset) {
+        first.link = fix("sh_link of section 0", first.link as u64, section_hdr_str_index as u64) as u32;
+        self.write_section_header_at(section_hdr_offset, &first);
+      }
+      SHN_XINDEX
+    } else {
+      section_hdr_str_index as u16
+    };
+    self.invalidate_caches();
+    self.write_header();
+    fixes
+  }
+
+  fn header_bytes(&self, offset: u64, size: u16) -> Option<&[u8]> {
+    let start = usize::try_from(offset).ok()?;
+    self.data.get(start..start.checked_add(size as usize)?)
+  }
+
+  fn program_header_at(&self, offset: u64) -> Option<ProgramHeader> {
+    let bytes = self.header_bytes(offset, self.expected_program_hdr_entry_size())?;
+    let class = self.header.identification.class;
+    match self.header.identification.endianness {
+      1 => Some(Elf::read_program_header::<LittleEndian>(class, bytes)),
+      2 => Some(Elf::read_program_header::<BigEndian>(class, bytes)),
+      _ => None,
+    }
+  }
+
+  fn section_header_at(&self, offset: u64) -> Option<SectionHeader> {
+    let bytes = self.header_bytes(offset, self.expected_section_hdr_entry_size())?;
+    let class = self.header.identification.class;
+    match self.header.identification.endianness {
+      1 => Some(Elf::read_section_header::<LittleEndian>(class, bytes)),
+      2 => Some(Elf::read_section_header::<BigEndian>(class, bytes)),
+      _ => None,
+    }
+  }
+
+  //the entries are read from the file, the table may not be loaded yet
+  fn section_headers_at(&self, offset: u64, entry_size: u16, count: usize) -> Vec<SectionHeader> {
+    (0..count as u64)
+      .map_while(|index| offset.checked_add(index * entry_size as u64).and_then(|at| self.section_header_at(at)))
+      .collect()
+  }
+
+  fn write_section_header_at(&mut self, offset: u64, entry: &SectionHeader) {
+    let size = self.expected_section_hdr_entry_size() as usize;
+    let class = self.header.identification.class;
+    let endianness = self.header.identification.endianness;
+    let data = match usize::try_from(offset).ok().and_then(|start| self.data.get_mut(start..start.checked_add(size)?)) {
+      Some(data) => data,
+      None => return,
+    };
+    match endianness {
+      1 => Elf::write_section_header::<LittleEndian>(class, data, entry),
+      2 => Elf::write_section_header::<BigEndian>(class, data, entry),
+      _ => panic!("unknown endianness"),
+    };
+  }
+
+  fn plausible_program_header(&self, entry: &ProgramHeader) -> bool {
+    let known_type = entry.entry_type <= PT_TLS || (0x6000_0000..=0x7fff_ffff).contains(&entry.entry_type);
+    let in_file = entry.offset.checked_add(entry.file_size).is_some_and(|end| end <= self.data.len() as u64);
+    let not_empty = entry.entry_type != PT_NULL || entry.file_size != 0 || entry.virtual_address != 0;
+    known_type && in_file && not_empty && entry.flags <= 7
+  }
+
+  fn plausible_section_header(&self, entry: &SectionHeader) -> bool {
+    let known_type = entry.section_type <= 19 || entry.section_type >= 0x6000_0000;
+    let in_file = entry.section_type == SHT_NOBITS
+      || entry.offset.checked_add(entry.size).is_some_and(|end| end <= self.data.len() as u64);
+    known_type && in_file && (entry.align == 0 || entry.align.is_power_of_two())
+  }
+
+  fn count_program_headers(&self, offset: u64, entry_size: u16) -> u16 {
+    if let Some(first) = self.program_header_at(offset) {
+      if first.entry_type == PT_PHDR && first.offset == offset && first.file_size % entry_size as u64 == 0 {
+        return (first.file_size / entry_size as u64).min(0xffff) as u16;
+      }
+    }
+    let mut count = 0u16;
+    while count < 0xffff {
+      match offset.checked_add(count as u64 * entry_size as u64).and_then(|at| self.program_header_at(at)) {
+        Some(entry) if self.plausible_program_header(&entry) => count += 1,
+        _ => break,
+      }
+    }
+    count
+  }
+
+  //Counts the entries of a section header table starting with the mandatory null entry. Its
+  //sh_size and sh_link may hold the extended e_shnum and e_shstrndx.
+  fn count_section_headers(&self, offset: u64, entry_size: u16) -> usize {
+    let null_entry = self.header_bytes(offset, entry_size).and_then(|_| self.section_header_at(offset)).is_some_and(|entry| {
+      entry.name_index == 0 && entry.section_type == SHT_NULL && entry.flags == 0 && entry.address == 0 && entry.offset == 0
+        && entry.info == 0 && entry.align == 0 && entry.entry_size == 0
+    });
+    if offset == 0 || !null_entry {
+      return 0;
+    }
+    let mut count = 1usize;
+    loop {
+      let at = (count as u64).checked_mul(entry_size as u64).and_then(|distance| offset.checked_add(distance));
+      match at.and_then(|at| self.section_header_at(at)) {
+        Some(entry) if entry.section_type != SHT_NULL && self.plausible_section_header(&entry) => count += 1,
+        _ => break,
+      }
+    }
+    count
+  }
+
+  fn find_section_headers(&self, entry_size: u16) -> Option<(u64, usize)> {
+    let align = match self.header.identification.class {
+      1 => 4,
+      _ => 8,
+    };
+    let mut best: Option<(u64, usize)> = None;
+    let mut offset = self.expected_elf_hdr_size() as u64;
+    while offset + entry_size as u64 <= self.data.len() as u64 {
+      let count = self.count_section_headers(offset, entry_size);
+      if count > 1 && best.is_none_or(|(_, best_count)| count > best_count) {
+        best = Some((offset, count));
+      }
+      offset += align;
+    }
+    best
+  }
+
+  fn find_section_name_table(&self, sections: &[SectionHeader]) -> Option<usize> {
+    let mut best: Option<(usize, usize)> = None;
+    for (index, table) in sections.iter().enumerate() {
+      if table.section_type != SHT_STRTAB {
+        continue;
+      }
+      let data = match self.data.get(table.offset as usize..) {
+        Some(data) => data,
+        None => continue,
+      };
+      if read_str(data, table.name_index as usize) == Some(".shstrtab") {
+        return Some(index);
+      }
+      let names = sections.iter()
+        .filter(|section| read_str(data, section.name_index as usize).is_some_and(|name| name.starts_with('.')))
+        .count();
+      if names > 0 && best.is_none_or(|(_, best_names)| names > best_names) {
+        best = Some((index, names));
+      }
+    }
+    best.map(|(index, _)| index)
+  }
+}
@@ -0,0 +1,150 @@
+use std::fmt::Write as _;
+use std::ops::Range;
+use crate::consts::*;
+use crate::elf::Elf;
+
+//A labelled byte range of the file. Depth 0 are headers, tables and sections, 1 their
+//entries and 2 the fields of an entry.
+#[derive(Clone, Debug)]
+pub struct Span {
+  pub start: u64,
+  pub end: u64,
+  pub depth: u8,
+  pub label: String,
+}
+
+fn push_fields(spans: &mut Vec<Span>, start: u64, fields: &[(&str, u64)]) {
+  let mut offset = start;
+  for &(name, size) in fields {
+    spans.push(Span { start: offset, end: offset + size, depth: 2, label: name.to_string() });
+    offset += size;
+  }
+}
+
+impl Elf {
+  //Layout of every structure the file describes, cut to the file and sorted by start offset
+  //and depth.
+  pub fn spans(&self) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let word = match self.header.identification.class {
+      1 => 4,
+      2 => 8,
+      _ => panic!("unknown class"),
+    };
+    let description = &self.header.description;
+
+    spans.push(Span { start: 0, end: self.expected_elf_hdr_size() as u64, depth: 0, label: "ELF header".to_string() });
+    push_fields(&mut spans, 0, &[
+      ("e_ident magic", 4), ("EI_CLASS", 1), ("EI_DATA", 1), ("EI_VERSION", 1), ("EI_OSABI", 1), ("EI_ABIVERSION", 1), ("EI_PAD", 7),
+      ("e_type", 2), ("e_machine", 2), ("e_version", 4), ("e_entry", word), ("e_phoff", word), ("e_shoff", word), ("e_flags", 4),
+      ("e_ehsize", 2), ("e_phentsize", 2), ("e_phnum", 2), ("e_shentsize", 2), ("e_shnum", 2), ("e_shstrndx", 2),
+    ]);
+
+    let program_entry = self.program_hdr_stride() as u64;
+    if !self.program_headers.is_empty() {
+      let start = description.program_hdr_offset;
+      spans.push(Span { start, end: start + program_entry * self.program_headers.len() as u64, depth: 0, label: "program header table".to_string() });
+      for (index, ph) in self.program_headers.iter().enumerate() {
+        let entry = start + program_entry * index as u64;
+        spans.push(Span { start: entry, end: entry + program_entry, depth: 1, label: format!("program header {} ({:#x})", index, ph.entry_type) });
+        if word == 8 {
+          push_fields(&mut spans, entry, &[("p_type", 4), ("p_flags", 4), ("p_offset", 8), ("p_vaddr", 8), ("p_paddr", 8), ("p_filesz", 8), ("p_memsz", 8), ("p_align", 8)]);
+        } else {
+          push_fields(&mut spans, entry, &[("p_type", 4), ("p_offset", 4), ("p_vaddr", 4), ("p_paddr", 4), ("p_filesz", 4), ("p_memsz", 4), ("p_flags", 4), ("p_align", 4)]);
+        }
+      }
+    }
+
+    let section_entry = self.section_hdr_stride() as u64;
+    if !self.section_headers.is_empty() {
+      let start = description.section_hdr_offset;
+      spans.push(Span { start, end: start + section_entry * self.section_headers.len() as u64, depth: 0, label: "section header table".to_string() });
+    }
+    let dynamic_symbols = self.dynamic_symbol_table();
+    let symbols = self.symbol_table();
+    for (index, section) in self.section_headers.iter().enumerate() {
+      let name = self.section_name(section).unwrap_or("");
+      let entry = description.section_hdr_offset + section_entry * index as u64;
+      spans.push(Span { start: entry, end: entry + section_entry, depth: 1, label: format!("section header {} ({})", index, name) });
+      push_fields(&mut spans, entry, &[
+        ("sh_name", 4), ("sh_type", 4), ("sh_flags", word), ("sh_addr", word), ("sh_offset", word), ("sh_size", word),
+        ("sh_link", 4), ("sh_info", 4), ("sh_addralign", word), ("sh_entsize", word),
+      ]);
+      if section.section_type == SHT_NOBITS || section.size == 0 {
+        continue;
+      }
+      spans.push(Span { start: section.offset, end: section.offset.saturating_add(section.size), depth: 0, label: format!("section {}", name) });
+
+      //entries of the fixed size tables
+      let (entry_size, kind) = match section.section_type {
+        SHT_SYMTAB | SHT_DYNSYM => (if word == 8 { 24 } else { 16 }, "symbol"),
+        SHT_REL => (2 * word, "relocation"),
+        SHT_RELA => (3 * word, "relocation"),
+        SHT_DYNAMIC => (2 * word, "dynamic entry"),
+        _ => continue,
+      };
+      let table = match section.section_type {
+        SHT_SYMTAB => Some(&symbols),
+        SHT_DYNSYM => Some(&dynamic_symbols),
+        _ => None,
+      };
+      for position in 0..section.size.min((self.data.len() as u64).saturating_sub(section.offset)) / entry_size {
+        let start = section.offset + position * entry_size;
+        let label = match table.and_then(|table| table.get(position as usize)) {
+          Some(symbol) if !symbol.name.is_empty() => format!("{} {} ({})", kind, position, symbol.name),
+          _ => format!("{} {}", kind, position),
+        };
+        spans.push(Span { start, end: start + entry_size, depth: 1, label });
+      }
+    }
+    //headers of malformed files describe bytes past the end
+    let len = self.data.len() as u64;
+    spans.retain(|span| span.start < len);
+    for span in &mut spans {
+      span.end = span.end.min(len);
+    }
+    spans.sort_by_key(|span| (span.start, span.depth));
+    spans
+  }
+
+  //16 bytes per row with the offset, hex, ASCII and the labels of every span starting in
+  //the row, e.g.
+  //00000010  03 00 3e 00 01 00 00 00  ...  |..>.....|  e_type, e_machine, e_version, e_entry
+  pub fn annotated_hexdump(&self, range: Range<u64>) -> String {
+    let mut spans = self.spans();
+    //by start, each row takes the labels from its first span on
+    spans.sort_by_key(|span| span.start);
+    let end = range.end.min(self.data.len() as u64);
+    let mut output = String::new();
+    let mut row = range.start & !15;
+    while row < end {
+      let _ = write!(output, "{:08x}  ", row);
+      let mut ascii = String::new();
+      for offset in row..row + 16 {
+        if offset >= range.start && offset < end {
+          let byte = self.data[offset as usize];
+          let _ = write!(output, "{:02x} ", byte);
+          ascii.push(if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' });
+        } else {
+          output.push_str("   ");
+          ascii.push(' ');
+        }
+        if offset == row + 7 {
+          output.push(' ');
+        }
+      }
+      let first = spans.partition_point(|span| span.start < row.max(range.start));
+      let labels: Vec<&str> = spans[first..].iter()
+        .take_while(|span| span.start < (row + 16).min(end))
+        .map(|span| span.label.as_str())
+        .collect();
+      let _ = write!(output, " |{}|", ascii);
+      if !labels.is_empty() {
+        let _ = write!(output, "  {}", labels.join(", "));
+      }
+      output.push('\n');
+      row += 16;
+    }
+    output
+  }
+}
@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::io;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::loader::LoadedImage;
+use crate::symbol::Symbol;
+
+//An IRELATIVE slot whose resolver would have to run, with everything an evaluator may look at.
+pub struct IfuncRequest<'a> {
+  pub image: &'a LoadedImage,
+  //biased resolver address
+  pub resolver: u64,
+  pub resolver_name: Option<&'a str>,
+  //biased address of the GOT slot
+  pub slot: u64,
+  //what glibc passes the resolver
+  pub hwcap: u64,
+  pub hwcap2: u64,
+}
+
+pub type IfuncEvaluator = Box<dyn FnMut(&IfuncRequest<'_>) -> Option<u64>>;
+
+//Where IRELATIVE results come from: known results first, the evaluator for the rest.
+#[derive(Default)]
+pub struct IfuncResolvers {
+  //link time resolver address to link time implementation address
+  pub results: HashMap<u64, u64>,
+  //ifunc symbol to implementation symbol, e.g. "memcpy" to "__memcpy_avx_unaligned"
+  pub named_results: HashMap<String, String>,
+  pub hwcap: u64,
+  pub hwcap2: u64,
+  //returns the biased implementation address, the image is read only so resolver code can
+  //be emulated without touching the process being built
+  evaluator: Option<IfuncEvaluator>,
+}
+
+pub struct IfuncSlot {
+  //biased
+  pub slot: u64,
+  pub resolver: u64,
+  pub resolver_name: Option<String>,
+  //the biased address written to the slot, None when nobody knew the result
+  pub value: Option<u64>,
+}
+
+impl IfuncResolvers {
+  pub fn new() -> IfuncResolvers {
+    Default::default()
+  }
+
+  pub fn add_result(&mut self, resolver: u64, implementation: u64) {
+    self.results.insert(resolver, implementation);
+  }
+
+  pub fn add_named_result(&mut self, resolver: &str, implementation: &str) {
+    self.named_results.insert(resolver.to_string(), implementation.to_string());
+  }
+
+  pub fn set_evaluator<F: FnMut(&IfuncRequest<'_>) -> Option<u64> + 'static>(&mut self, evaluator: F) {
+    self.evaluator = Some(Box::new(evaluator));
+  }
+}
+
+impl Elf {
+  //Fills the IRELATIVE slots of a loaded image from `resolvers`. Slots without a known result
+  //keep their file contents and are reported with value None.
+  pub fn apply_ifunc_relocations(&self, image: &mut LoadedImage, resolvers: &mut IfuncResolvers) -> io::Result<Vec<IfuncSlot>> {
+    match self.header.identification.endianness {
+      1 => self.apply_ifunc_relocations_with_byteorder::<LittleEndian>(image, resolvers),
+      2 => self.apply_ifunc_relocations_with_byteorder::<BigEndian>(image, resolvers),
+      _ => panic!("unknown endianness"),
+    }
+  }
+
+  fn apply_ifunc_relocations_with_byteorder<E: ByteOrder>(&self, image: &mut LoadedImage, resolvers: &mut IfuncResolvers) -> io::Result<Vec<IfuncSlot>> {
+    let irelative_type = match self.irelative_relocation_type() {
+      Some(irelative_type) => irelative_type,
+      None => return Ok(Vec::new()),
+    };
+    let word_size = match self.header.identification.class {
+      1 => 4,
+      2 => 8,
+      _ => panic!("unknown class"),
+    };
+    //the STT_GNU_IFUNC symbol names the function, plain symbols at the address are helpers
+    //like glibc's strlen_ifunc
+    let mut resolver_names: HashMap<u64, &Symbol> = HashMap::new();
+    for symbol in self.symbol_table().iter().chain(self.dynamic_symbol_table()) {
+      if symbol.is_undefined() || !matches!(symbol.symbol_type, STT_GNU_IFUNC | STT_FUNC) {
+        continue;
+      }
+      let named = resolver_names.entry(symbol.value).or_insert(symbol);
+      if named.symbol_type != STT_GNU_IFUNC && symbol.symbol_type == STT_GNU_IFUNC {
+        *named = symbol;
+      }
+    }
+    let mut slots = Vec::new();
+    for relocation in self.relocations() {
+      if relocation.relocation_type != irelative_type {
+        continue;
+      }
+      let slot = relocation.offset.wrapping_add(image.bias);
+      let resolver = match relocation.addend {
+        Some(addend) => addend as u64,
+        //REL keeps the link time resolver address in the slot
+        None => {
+          let word = image.read(slot, word_size)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("IRELATIVE slot {:#x} is not mapped", slot)))?;
+          if word_size == 4 { E::read_u32(word) as u64 } else { E::read_u64(word) }
+        },
+      };
+      let resolver_name = resolver_names.get(&resolver).map(|symbol| symbol.name.to_string());
+
+      let mut value = resolvers.results.get(&resolver).map(|implementation| implementation.wrapping_add(image.bias));
+      if value.is_none() {
+        value = resolver_name.as_ref()
+          .and_then(|name| resolvers.named_results.get(name))
+          .and_then(|implementation| self.symbol_by_name(implementation))
+          .map(|symbol| symbol.value.wrapping_add(image.bias));
+      }
+      if value.is_none() {
+        if let Some(evaluator) = resolvers.evaluator.as_mut() {
+          value = evaluator(&IfuncRequest {
+            image,
+            resolver: resolver.wrapping_add(image.bias),
+            resolver_name: resolver_name.as_deref(),
+            slot,
+            hwcap: resolvers.hwcap,
+            hwcap2: resolvers.hwcap2,
+          });
+        }
+      }
+      if let Some(value) = value {
+        let mut word = [0u8; 8];
+        if word_size == 4 {
+          E::write_u32(&mut word, value as u32);
+        } else {
+          E::write_u64(&mut word, value);
+        }
+        image.write(slot, &word[..word_size])?;
+      }
+      slots.push(IfuncSlot { slot, resolver: resolver.wrapping_add(image.bias), resolver_name, value });
+    }
+    Ok(slots)
+  }
+}
@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use crate::dwarf::*;
+use crate::elf::Elf;
+
+//One address range of an inlined copy of a function.
+#[derive(Clone, Debug)]
+pub struct InlinedRange {
+  pub start: u64,
+  pub end: u64,
+  pub callee: String,
+  //the function or inlined copy the call was in
+  pub inlined_into: String,
+  //the out of line function the code ends up in
+  pub function: String,
+  //1 when inlined straight into `function`, more for inlines of inlines
+  pub depth: usize,
+  pub call_file: Option<String>,
+  pub call_line: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InlineStats {
+  pub callee: String,
+  //inlined copies, across all functions
+  pub instances: usize,
+  //functions it was inlined into
+  pub callers: usize,
+  //code of its copies, including what was inlined into them in turn
+  pub bytes: u64,
+  //code of its copies not attributed to a deeper inline
+  pub exclusive_bytes: u64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InlineReport {
+  //by start address
+  pub ranges: Vec<InlinedRange>,
+  //by bytes, largest first
+  pub callees: Vec<InlineStats>,
+}
+
+impl InlineReport {
+  pub fn stats(&self, callee: &str) -> Option<&InlineStats> {
+    self.callees.iter().find(|stats| stats.callee == callee)
+  }
+
+  //the inlined copies covering `address`, outermost first
+  pub fn at(&self, address: u64) -> Vec<&InlinedRange> {
+    let mut ranges: Vec<&InlinedRange> = self.ranges.iter().filter(|range| range.start <= address && address < range.end).collect();
+    ranges.sort_by_key(|range| range.depth);
+    ranges
+  }
+}
+
+impl fmt::Display for InlineReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "{:>10} {:>10} {:>9} {:>7}  callee", "bytes", "exclusive", "instances", "callers")?;
+    for stats in &self.callees {
+      writeln!(f, "{:>10} {:>10} {:>9} {:>7}  {}", stats.bytes, stats.exclusive_bytes, stats.instances, stats.callers, stats.callee)?;
+    }
+    Ok(())
+  }
+}
+
+fn covered_bytes(mut ranges: Vec<(u64, u64)>) -> u64 {
+  ranges.sort_unstable();
+  let mut total = 0;
+  let mut end = 0;
+  for (start, range_end) in ranges {
+    let start = start.max(end);
+    if range_end > start {
+      total += range_end - start;
+      end = range_end;
+    }
+  }
+  total
+}
+
+impl Elf {
+  //Which inlined functions contributed the code of each address range, from the
+  //DW_TAG_inlined_subroutine entries of .debug_info, and per callee how many bytes its
+  //inlining accounts for. Callees are named from their abstract instance; one whose
+  //abstract instance is in another unit reads "<unknown>".
+  pub fn inline_attribution(&self) -> InlineReport {
+    let dwarf = self.dwarf();
+    let mut ranges = Vec::new();
+    let mut instances: HashMap<String, usize> = HashMap::new();
+    //ranges of each out of line function, exclusive bytes are worked out within one
+    let mut by_function: Vec<Vec<usize>> = Vec::new();
+    for unit in dwarf.units() {
+      let lines = dwarf.line_program(&unit);
+      let comp_dir = unit.comp_dir();
+      let mut functions: HashMap<usize, usize> = HashMap::new();
+      for (index, die) in unit.dies.iter().enumerate() {
+        if die.tag != DW_TAG_INLINED_SUBROUTINE {
+          continue;
+        }
+        let mut depth = 1;
+        let mut inlined_into = None;
+        let mut function = None;
+        let mut parent = unit.parents[index];
+        while let Some(scope) = parent {
+          match unit.dies[scope].tag {
+            DW_TAG_INLINED_SUBROUTINE => {
+              depth += 1;
+              inlined_into.get_or_insert(scope);
+            },
+            DW_TAG_SUBPROGRAM => {
+              function = Some(scope);
+              break;
+            },
+            _ => {},
+          };
+          parent = unit.parents[scope];
+        }
+        //inlines into an abstract instance have no code
+        let function = match function {
+          Some(function) => function,
+          None => continue,
+        };
+        let name = |index: usize| unit.qualified_name(index).unwrap_or_else(|| String::from("<unknown>"));
+        let callee = name(index);
+        *instances.entry(callee.clone()).or_insert(0) += 1;
+        let call_file = die.unsigned(DW_AT_CALL_FILE)
+          .and_then(|file| lines.as_ref()?.file_path(file, comp_dir.as_deref()));
+        let group = *functions.entry(function).or_insert_with(|| {
+          by_function.push(Vec::new());
+          by_function.len() - 1
+        });
+        for (start, end) in dwarf.die_ranges(&unit, die) {
+          by_function[group].push(ranges.len());
+          ranges.push(InlinedRange {
+            start,
+            end,
+            callee: callee.clone(),
+            inlined_into: name(inlined_into.unwrap_or(function)),
+            function: name(function),
+            depth,
+            call_file: call_file.clone(),
+            call_line: die.unsigned(DW_AT_CALL_LINE),
+          });
+        }
+      }
+    }
+
+    //split each function's code at every range boundary and give each piece to the
+    //deepest inline covering it
+    let mut exclusive: HashMap<&str, u64> = HashMap::new();
+    for group in &by_function {
+      let mut boundaries: Vec<u64> = group.iter().flat_map(|&range| [ranges[range].start, ranges[range].end]).collect();
+      boundaries.sort_unstable();
+      boundaries.dedup();
+      for piece in boundaries.windows(2) {
+        let owner = group.iter().map(|&range| &ranges[range])
+          .filter(|range| range.start <= piece[0] && piece[1] <= range.end)
+          .max_by_key(|range| range.depth);
+        if let Some(owner) = owner {
+          *exclusive.entry(&owner.callee).or_insert(0) += piece[1] - piece[0];
+        }
+      }
+    }
+    let mut covered: BTreeMap<&str, Vec<(u64, u64)>> = BTreeMap::new();
+    let mut callers: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for range in &ranges {
+      covered.entry(&range.callee).or_default().push((range.start, range.end));
+      callers.entry(&range.callee).or_default().insert(&range.function);
+    }
+    let mut callees: Vec<InlineStats> = covered.into_iter().map(|(callee, spans)| InlineStats {
+      callee: callee.to_string(),
+      instances: instances.get(callee).copied().unwrap_or(0),
+      callers: callers.get(callee).map_or(0, HashSet::len),
+      bytes: covered_bytes(spans),
+      exclusive_bytes: exclusive.get(callee).copied().unwrap_or(0),
+    }).collect();
+    callees.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.callee.cmp(&b.callee)));
+    ranges.sort_by_key(|range| (range.start, range.depth));
+    InlineReport { ranges, callees }
+  }
+}
@@ -0,0 +1,103 @@
+use std::collections::HashSet;
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::mem::size_of;
+use std::sync::{Arc, Mutex};
+use crate::elf::{Elf, ProgramHeader, SectionHeader};
+
+//locks a parallel symbol parse spreads over
+const SHARDS: usize = 64;
+
+//Hands out one shared allocation per distinct string. A single interner can be shared by
+//every Elf of a process so repeated names (C++ templates, libc imports) are stored once.
+//Strings stay until purge finds nothing else holding them.
+pub struct Interner {
+  shards: Vec<Mutex<HashSet<Arc<str>>>>,
+  hasher: RandomState,
+}
+
+impl Default for Interner {
+  fn default() -> Interner {
+    Interner { shards: (0..SHARDS).map(|_| Default::default()).collect(), hasher: RandomState::new() }
+  }
+}
+
+impl Interner {
+  pub fn new() -> Interner {
+    Default::default()
+  }
+
+  fn shard(&self, string: &str) -> &Mutex<HashSet<Arc<str>>> {
+    &self.shards[self.hasher.hash_one(string) as usize % SHARDS]
+  }
+
+  pub fn intern(&self, string: &str) -> Arc<str> {
+    let mut strings = self.shard(string).lock().unwrap();
+    if let Some(interned) = strings.get(string) {
+      return interned.clone();
+    }
+    let interned: Arc<str> = Arc::from(string);
+    strings.insert(interned.clone());
+    interned
+  }
+
+  //Drops the strings only the interner still holds, those of dropped or reloaded Elfs, and
+  //returns how many.
+  pub fn purge(&self) -> usize {
+    self.shards.iter().map(|shard| {
+      let mut strings = shard.lock().unwrap();
+      let before = strings.len();
+      strings.retain(|string| Arc::strong_count(string) > 1);
+      strings.shrink_to_fit();
+      before - strings.len()
+    }).sum()
+  }
+
+  pub fn len(&self) -> usize {
+    self.shards.iter().map(|shard| shard.lock().unwrap().len()).sum()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len() == 0
+  }
+
+  //Bytes held by the interned strings, including the Arc counters and the sets themselves.
+  pub fn memory_usage(&self) -> usize {
+    self.shards.iter().map(|shard| {
+      let strings = shard.lock().unwrap();
+      let contents: usize = strings.iter().map(|string| string.len() + 2 * size_of::<usize>()).sum();
+      contents + strings.capacity() * size_of::<Arc<str>>()
+    }).sum()
+  }
+}
+
+pub struct MemoryUsage {
+  pub data: usize,
+  pub section_headers: usize,
+  pub program_headers: usize,
+  pub interned_strings: usize,
+  //the lookup maps built so far, the names themselves are in interned_strings
+  pub name_maps: usize,
+  //debug sections of an ET_REL object copied to apply their relocations
+  pub relocated_sections: usize,
+}
+
+impl MemoryUsage {
+  pub fn total(&self) -> usize {
+    self.data + self.section_headers + self.program_headers + self.interned_strings + self.name_maps + self.relocated_sections
+  }
+}
+
+impl Elf {
+  //interned_strings covers the whole interner, which may be shared with other Elfs
+  pub fn memory_usage(&self) -> MemoryUsage {
+    MemoryUsage {
+      data: self.data.len(),
+      section_headers: self.section_headers.capacity() * size_of::<SectionHeader>(),
+      program_headers: self.program_headers.capacity() * size_of::<ProgramHeader>(),
+      interned_strings: self.interner.memory_usage(),
+      name_maps: self.name_maps.memory_usage(),
+      relocated_sections: self.relocated_sections.memory_usage(),
+    }
+  }
+}
@@ -0,0 +1,281 @@
+use std::io::{self, BufRead, Read, Write};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+
+pub const JITDUMP_MAGIC: u32 = 0x4a69_5444;
+pub const JITDUMP_VERSION: u32 = 1;
+const JITDUMP_HEADER_SIZE: u32 = 40;
+const JIT_RECORD_HEADER_SIZE: u32 = 16;
+
+pub const JIT_CODE_LOAD: u32 = 0;
+pub const JIT_CODE_MOVE: u32 = 1;
+pub const JIT_CODE_DEBUG_INFO: u32 = 2;
+pub const JIT_CODE_CLOSE: u32 = 3;
+pub const JIT_CODE_UNWINDING_INFO: u32 = 4;
+
+//A symbol that does not come from an ELF symbol table, e.g. JIT generated code.
+#[derive(Clone)]
+pub struct SyntheticSymbol {
+  pub address: u64,
+  pub size: u64,
+  pub name: String,
+}
+
+pub fn read_perf_map<R: BufRead>(reader: R) -> io::Result<Vec<SyntheticSymbol>> {
+  let mut symbols = Vec::new();
+  for line in reader.lines() {
+    let line = line?;
+    let mut fields = line.trim_end().splitn(3, ' ');
+    let (address, size, name) = match (fields.next(), fields.next(), fields.next()) {
+      (Some(address), Some(size), Some(name)) => (address, size, name),
+      _ => continue,
+    };
+    let parse = |field: &str| u64::from_str_radix(field.trim_start_matches("0x"), 16)
+      .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, format!("invalid perf map line: {}", line)));
+    symbols.push(SyntheticSymbol { address: parse(address)?, size: parse(size)?, name: name.to_string() });
+  }
+  Ok(symbols)
+}
+
+pub fn write_perf_map<W: Write>(mut writer: W, symbols: &[SyntheticSymbol]) -> io::Result<()> {
+  for symbol in symbols {
+    writeln!(writer, "{:x} {:x} {}", symbol.address, symbol.size, symbol.name)?;
+  }
+  Ok(())
+}
+
+#[derive(Default, Clone)]
+pub struct JitDumpHeader {
+  pub version: u32,
+  pub elf_mach: u32,
+  pub pid: u32,
+  pub timestamp: u64,
+  pub flags: u64,
+}
+
+#[derive(Clone)]
+pub struct JitDebugEntry {
+  pub address: u64,
+  pub line: u32,
+  pub discriminator: u32,
+  pub file_name: String,
+}
+
+#[derive(Clone)]
+pub enum JitRecord {
+  CodeLoad { timestamp: u64, pid: u32, tid: u32, vma: u64, code_address: u64, code_index: u64, name: String, code: Vec<u8> },
+  CodeMove { timestamp: u64, pid: u32, tid: u32, vma: u64, old_code_address: u64, new_code_address: u64, code_size: u64, code_index: u64 },
+  DebugInfo { timestamp: u64, code_address: u64, entries: Vec<JitDebugEntry> },
+  Close { timestamp: u64 },
+  UnwindingInfo { timestamp: u64, eh_frame_hdr_size: u64, mapped_size: u64, data: Vec<u8> },
+  Unknown { id: u32, timestamp: u64, data: Vec<u8> },
+}
+
+#[derive(Default, Clone)]
+pub struct JitDump {
+  pub header: JitDumpHeader,
+  pub records: Vec<JitRecord>,
+}
+
+fn read_c_string(data: &mut &[u8]) -> io::Result<String> {
+  let end = data.iter().position(|&b| b == 0)
+    .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "unterminated string in jitdump record"))?;
+  let string = String::from_utf8_lossy(&data[..end]).into_owned();
+  *data = &data[end + 1..];
+  Ok(string)
+}
+
+//Reads size bytes through take, so a size field larger than the input fails without allocating it.
+fn read_bytes<R: Read>(reader: &mut R, size: u64, what: &str) -> io::Result<Vec<u8>> {
+  let mut bytes = Vec::new();
+  reader.take(size).read_to_end(&mut bytes)?;
+  if (bytes.len() as u64) < size {
+    return Err(io::Error::new(io::ErrorKind::InvalidData, format!("truncated jitdump {}", what)));
+  }
+  Ok(bytes)
+}
+
+impl JitDump {
+  //The magic is written in the byte order of the producer, both orders are accepted.
+  pub fn read<R: Read>(mut reader: R) -> io::Result<JitDump> {
+    let magic = reader.read_u32::<LittleEndian>()?;
+    match magic {
+      JITDUMP_MAGIC => JitDump::read_with_byteorder::<LittleEndian, R>(reader),
+      _ if magic.swap_bytes() == JITDUMP_MAGIC => JitDump::read_with_byteorder::<BigEndian, R>(reader),
+      _ => Err(io::Error::new(io::ErrorKind::InvalidData, "not a jitdump file")),
+    }
+  }
+
+  fn read_with_byteorder<E: ByteOrder, R: Read>(mut reader: R) -> io::Result<JitDump> {
+    let mut dump: JitDump = Default::default();
+    dump.header.version = reader.read_u32::<E>()?;
+    let header_size = reader.read_u32::<E>()?;
+    dump.header.elf_mach = reader.read_u32::<E>()?;
+    reader.read_u32::<E>()?;
+    dump.header.pid = reader.read_u32::<E>()?;
+    dump.header.timestamp = reader.read_u64::<E>()?;
+    dump.header.flags = reader.read_u64::<E>()?;
+    read_bytes(&mut reader, header_size.saturating_sub(JITDUMP_HEADER_SIZE) as u64, "header")?;
+
+    loop {
+      let id = match reader.read_u32::<E>() {
+        Ok(id) => id,
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => break,
+        Err(error) => return Err(error),
+      };
+      let total_size = reader.read_u32::<E>()?;
+      let timestamp = reader.read_u64::<E>()?;
+      let body = read_bytes(&mut reader, total_size.saturating_sub(JIT_RECORD_HEADER_SIZE) as u64, "record")?;
+      dump.records.push(JitDump::parse_record::<E>(id, timestamp, &body)?);
+    }
+    Ok(dump)
+  }
+
+  fn parse_record<E: ByteOrder>(id: u32, timestamp: u64, body: &[u8]) -> io::Result<JitRecord> {
+    let mut data = body;
+    let record = match id {
+      JIT_CODE_LOAD => {
+        let pid = data.read_u32::<E>()?;
+        let tid = data.read_u32::<E>()?;
+        let vma = data.read_u64::<E>()?;
+        let code_address = data.read_u64::<E>()?;
+        let code_size = data.read_u64::<E>()? as usize;
+        let code_index = data.read_u64::<E>()?;
+        let name = read_c_string(&mut data)?;
+        let code = data.get(..code_size)
+          .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "truncated jitdump code"))?
+          .to_vec();
+        JitRecord::CodeLoad { timestamp, pid, tid, vma, code_address, code_index, name, code }
+      },
+      JIT_CODE_MOVE => JitRecord::CodeMove {
+        timestamp,
+        pid: data.read_u32::<E>()?,
+        tid: data.read_u32::<E>()?,
+        vma: data.read_u64::<E>()?,
+        old_code_address: data.read_u64::<E>()?,
+        new_code_address: data.read_u64::<E>()?,
+        code_size: data.read_u64::<E>()?,
+        code_index: data.read_u64::<E>()?,
+      },
+      JIT_CODE_DEBUG_INFO => {
+        let code_address = data.read_u64::<E>()?;
+        let count = data.read_u64::<E>()?;
+        let mut entries = Vec::new();
+        for _ in 0..count {
+          entries.push(JitDebugEntry {
+            address: data.read_u64::<E>()?,
+            line: data.read_u32::<E>()?,
+            discriminator: data.read_u32::<E>()?,
+            file_name: read_c_string(&mut data)?,
+          });
+        }
+        JitRecord::DebugInfo { timestamp, code_address, entries }
+      },
+      JIT_CODE_CLOSE => JitRecord::Close { timestamp },
+      JIT_CODE_UNWINDING_INFO => {
+        let unwinding_size = data.read_u64::<E>()? as usize;
+        let eh_frame_hdr_size = data.read_u64::<E>()?;
+        let mapped_size = data.read_u64::<E>()?;
+        let data = data.get(..unwinding_size)
+          .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "truncated jitdump unwinding info"))?
+          .to_vec();
+        JitRecord::UnwindingInfo { timestamp, eh_frame_hdr_size, mapped_size, data }
+      },
+      _ => JitRecord::Unknown { id, timestamp, data: body.to_vec() },
+    };
+    Ok(record)
+  }
+
+  //Written little-endian, with records padded to 8 bytes as perf does.
+  pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+    writer.write_u32::<LittleEndian>(JITDUMP_MAGIC)?;
+    writer.write_u32::<LittleEndian>(self.header.version)?;
+    writer.write_u32::<LittleEndian>(JITDUMP_HEADER_SIZE)?;
+    writer.write_u32::<LittleEndian>(self.header.elf_mach)?;
+    writer.write_u32::<LittleEndian>(0)?;
+    writer.write_u32::<LittleEndian>(self.header.pid)?;
+    writer.write_u64::<LittleEndian>(self.header.timestamp)?;
+    writer.write_u64::<LittleEndian>(self.header.flags)?;
+    for record in &self.records {
+      let mut body = Vec::new();
+      let (id, timestamp) = JitDump::encode_record(record, &mut body)?;
+      body.resize((body.len() + JIT_RECORD_HEADER_SIZE as usize).div_ceil(8) * 8 - JIT_RECORD_HEADER_SIZE as usize, 0);
+      writer.write_u32::<LittleEndian>(id)?;
+      writer.write_u32::<LittleEndian>(body.len() as u32 + JIT_RECORD_HEADER_SIZE)?;
+      writer.write_u64::<LittleEndian>(timestamp)?;
+      writer.write_all(&body)?;
+    }
+    Ok(())
+  }
+
+  fn encode_record(record: &JitRecord, body: &mut Vec<u8>) -> io::Result<(u32, u64)> {
+    let header = match record {
+      JitRecord::CodeLoad { timestamp, pid, tid, vma, code_address, code_index, name, code } => {
+        body.write_u32::<LittleEndian>(*pid)?;
+        body.write_u32::<LittleEndian>(*tid)?;
+        body.write_u64::<LittleEndian>(*vma)?;
+        body.write_u64::<LittleEndian>(*code_address)?;
+        body.write_u64::<LittleEndian>(code.len() as u64)?;
+        body.write_u64::<LittleEndian>(*code_index)?;
+        body.write_all(name.as_bytes())?;
+        body.write_u8(0)?;
+        body.write_all(code)?;
+        (JIT_CODE_LOAD, *timestamp)
+      },
+      JitRecord::CodeMove { timestamp, pid, tid, vma, old_code_address, new_code_address, code_size, code_index } => {
+        body.write_u32::<LittleEndian>(*pid)?;
+        body.write_u32::<LittleEndian>(*tid)?;
+        body.write_u64::<LittleEndian>(*vma)?;
+        body.write_u64::<LittleEndian>(*old_code_address)?;
+        body.write_u64::<LittleEndian>(*new_code_address)?;
+        body.write_u64::<LittleEndian>(*code_size)?;
+        body.write_u64::<LittleEndian>(*code_index)?;
+        (JIT_CODE_MOVE, *timestamp)
+      },
+      JitRecord::DebugInfo { timestamp, code_address, entries } => {
+        body.write_u64::<LittleEndian>(*code_address)?;
+        body.write_u64::<LittleEndian>(entries.len() as u64)?;
+        for entry in entries {
+          body.write_u64::<LittleEndian>(entry.address)?;
+          body.write_u32::<LittleEndian>(entry.line)?;
+          body.write_u32::<LittleEndian>(entry.discriminator)?;
+          body.write_all(entry.file_name.as_bytes())?;
+          body.write_u8(0)?;
+        }
+        (JIT_CODE_DEBUG_INFO, *timestamp)
+      },
+      JitRecord::Close { timestamp } => (JIT_CODE_CLOSE, *timestamp),
+      JitRecord::UnwindingInfo { timestamp, eh_frame_hdr_size, mapped_size, data } => {
+        body.write_u64::<LittleEndian>(data.len() as u64)?;
+        body.write_u64::<LittleEndian>(*eh_frame_hdr_size)?;
+        body.write_u64::<LittleEndian>(*mapped_size)?;
+        body.write_all(data)?;
+        (JIT_CODE_UNWINDING_INFO, *timestamp)
+      },
+      JitRecord::Unknown { id, timestamp, data } => {
+        body.write_all(data)?;
+        (*id, *timestamp)
+      },
+    };
+    Ok(header)
+  }
+
+  //Code currently mapped according to the dump, with moves applied.
+  pub fn symbols(&self) -> Vec<SyntheticSymbol> {
+    let mut symbols: Vec<(u64, SyntheticSymbol)> = Vec::new();
+    for record in &self.records {
+      match record {
+        JitRecord::CodeLoad { code_address, code_index, name, code, .. } => {
+          symbols.push((*code_index, SyntheticSymbol { address: *code_address, size: code.len() as u64, name: name.clone() }));
+        },
+        JitRecord::CodeMove { old_code_address, new_code_address, code_index, .. } => {
+          let moved = symbols.iter_mut().find(|(index, symbol)| index == code_index && symbol.address == *old_code_address);
+          if let Some((_, symbol)) = moved {
+            symbol.address = *new_code_address;
+          }
+        },
+        _ => {},
+      };
+    }
+    symbols.into_iter().map(|(_, symbol)| symbol).collect()
+  }
+}
@@ -0,0 +1,951 @@
+use std::io::{self, Cursor, Read, Write};
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
+use crate::consts::*;
+use crate::dynamic::DynamicEntry;
+use crate::elf::{ElfDescription, ElfHeader, ElfIdentification, ProgramHeader, SectionHeader};
+use crate::relocation::Relocation;
+use crate::symbol::Symbol;
+
+//A structure exactly as one ELF class lays it out in the file. Fields are named and typed
+//as in the specification (Elf64_Ehdr and friends), the byte order is the caller's: the
+//file's EI_DATA, LittleEndian for 1 and BigEndian for 2.
+pub trait RawLayout: Sized {
+  const SIZE: usize;
+  //UnexpectedEof when `data` is shorter than SIZE, bytes past it are ignored
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<Self>;
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()>;
+}
+
+//Between a raw structure and the class independent view the parser keeps, where every
+//address, offset and size is a u64.
+pub trait Widen<T>: RawLayout {
+  fn widen(&self) -> T;
+  //values too large for an Elf32 field are truncated, what a 32-bit file cannot hold
+  fn narrow(wide: &T) -> Self;
+}
+
+//The raw structures of one class, Elf32 or Elf64, so that code reading or writing them
+//is written once, generic over the class and the byte order.
+pub trait ElfLayout {
+  const CLASS: u8;
+  type Header: Widen<ElfHeader>;
+  type SectionHeader: Widen<SectionHeader>;
+  type ProgramHeader: Widen<ProgramHeader>;
+  type Symbol: Widen<Symbol> + RawSymbol;
+  type Dynamic: Widen<DynamicEntry>;
+  type Rel: Widen<Relocation>;
+  type Rela: Widen<Relocation>;
+}
+
+//st_name of a symbol table entry: the widened Symbol carries the name itself, and narrowing
+//one leaves st_name 0 for the writer to set.
+pub trait RawSymbol {
+  fn name_index(&self) -> u32;
+  fn set_name_index(&mut self, name_index: u32);
+}
+
+pub struct Elf32;
+pub struct Elf64;
+
+impl ElfLayout for Elf32 {
+  const CLASS: u8 = ELFCLASS32;
+  type Header = ElfHeader32;
+  type SectionHeader = SectionHeader32;
+  type ProgramHeader = ProgramHeader32;
+  type Symbol = Symbol32;
+  type Dynamic = Dynamic32;
+  type Rel = Rel32;
+  type Rela = Rela32;
+}
+
+impl ElfLayout for Elf64 {
+  const CLASS: u8 = ELFCLASS64;
+  type Header = ElfHeader64;
+  type SectionHeader = SectionHeader64;
+  type ProgramHeader = ProgramHeader64;
+  type Symbol = Symbol64;
+  type Dynamic = Dynamic64;
+  type Rel = Rel64;
+  type Rela = Rela64;
+}
+
+fn reader(data: &[u8], size: usize) -> io::Result<Cursor<&[u8]>> {
+  match data.get(..size) {
+    Some(data) => Ok(Cursor::new(data)),
+    None => Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("{} bytes where {} are needed", data.len(), size))),
+  }
+}
+
+fn writer(data: &mut [u8], size: usize) -> io::Result<Cursor<&mut [u8]>> {
+  let length = data.len();
+  match data.get_mut(..size) {
+    Some(data) => Ok(Cursor::new(data)),
+    None => Err(io::Error::new(io::ErrorKind::WriteZero, format!("{} bytes where {} are needed", length, size))),
+  }
+}
+
+fn identification(ident: &[u8; 16]) -> ElfIdentification {
+  ElfIdentification {
+    magic: BigEndian::read_u32(&ident[0..4]),
+    class: ident[4],
+    endianness: ident[5],
+    version: ident[6],
+    os_abi: ident[7],
+    abi_version: ident[8],
+  }
+}
+
+//EI_PAD zeroed
+fn ident(identification: &ElfIdentification) -> [u8; 16] {
+  let mut ident = [0u8; 16];
+  BigEndian::write_u32(&mut ident[0..4], identification.magic);
+  ident[4..9].copy_from_slice(&[identification.class, identification.endianness, identification.version, identification.os_abi, identification.abi_version]);
+  ident
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ElfHeader32 {
+  pub e_ident: [u8; 16],
+  pub e_type: u16,
+  pub e_machine: u16,
+  pub e_version: u32,
+  pub e_entry: u32,
+  pub e_phoff: u32,
+  pub e_shoff: u32,
+  pub e_flags: u32,
+  pub e_ehsize: u16,
+  pub e_phentsize: u16,
+  pub e_phnum: u16,
+  pub e_shentsize: u16,
+  pub e_shnum: u16,
+  pub e_shstrndx: u16,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ElfHeader64 {
+  pub e_ident: [u8; 16],
+  pub e_type: u16,
+  pub e_machine: u16,
+  pub e_version: u32,
+  pub e_entry: u64,
+  pub e_phoff: u64,
+  pub e_shoff: u64,
+  pub e_flags: u32,
+  pub e_ehsize: u16,
+  pub e_phentsize: u16,
+  pub e_phnum: u16,
+  pub e_shentsize: u16,
+  pub e_shnum: u16,
+  pub e_shstrndx: u16,
+}
+
+impl RawLayout for ElfHeader32 {
+  const SIZE: usize = 52;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<ElfHeader32> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    let mut e_ident = [0u8; 16];
+    cursor.read_exact(&mut e_ident)?;
+    Ok(ElfHeader32 {
+      e_ident,
+      e_type: cursor.read_u16::<E>()?,
+      e_machine: cursor.read_u16::<E>()?,
+      e_version: cursor.read_u32::<E>()?,
+      e_entry: cursor.read_u32::<E>()?,
+      e_phoff: cursor.read_u32::<E>()?,
+      e_shoff: cursor.read_u32::<E>()?,
+      e_flags: cursor.read_u32::<E>()?,
+      e_ehsize: cursor.read_u16::<E>()?,
+      e_phentsize: cursor.read_u16::<E>()?,
+      e_phnum: cursor.read_u16::<E>()?,
+      e_shentsize: cursor.read_u16::<E>()?,
+      e_shnum: cursor.read_u16::<E>()?,
+      e_shstrndx: cursor.read_u16::<E>()?,
+    })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_all(&self.e_ident)?;
+    cursor.write_u16::<E>(self.e_type)?;
+    cursor.write_u16::<E>(self.e_machine)?;
+    cursor.write_u32::<E>(self.e_version)?;
+    cursor.write_u32::<E>(self.e_entry)?;
+    cursor.write_u32::<E>(self.e_phoff)?;
+    cursor.write_u32::<E>(self.e_shoff)?;
+    cursor.write_u32::<E>(self.e_flags)?;
+    cursor.write_u16::<E>(self.e_ehsize)?;
+    cursor.write_u16::<E>(self.e_phentsize)?;
+    cursor.write_u16::<E>(self.e_phnum)?;
+    cursor.write_u16::<E>(self.e_shentsize)?;
+    cursor.write_u16::<E>(self.e_shnum)?;
+    cursor.write_u16::<E>(self.e_shstrndx)
+  }
+}
+
+impl Widen<ElfHeader> for ElfHeader32 {
+  fn widen(&self) -> ElfHeader {
+    ElfHeader {
+      identification: identification(&self.e_ident),
+      description: ElfDescription {
+        obj_type: self.e_type,
+        machine: self.e_machine,
+        version: self.e_version,
+        entry: self.e_entry as u64,
+        program_hdr_offset: self.e_phoff as u64,
+        section_hdr_offset: self.e_shoff as u64,
+        flags: self.e_flags,
+        elf_hdr_size: self.e_ehsize,
+        program_hdr_entry_size: self.e_phentsize,
+        program_hdr_num: self.e_phnum,
+        section_hdr_entry_size: self.e_shentsize,
+        section_hdr_num: self.e_shnum,
+        section_hdr_str_index: self.e_shstrndx,
+      },
+    }
+  }
+
+  fn narrow(wide: &ElfHeader) -> ElfHeader32 {
+    let description = &wide.description;
+    ElfHeader32 {
+      e_ident: ident(&wide.identification),
+      e_type: description.obj_type,
+      e_machine: description.machine,
+      e_version: description.version,
+      e_entry: description.entry as u32,
+      e_phoff: description.program_hdr_offset as u32,
+      e_shoff: description.section_hdr_offset as u32,
+      e_flags: description.flags,
+      e_ehsize: description.elf_hdr_size,
+      e_phentsize: description.program_hdr_entry_size,
+      e_phnum: description.program_hdr_num,
+      e_shentsize: description.section_hdr_entry_size,
+      e_shnum: description.section_hdr_num,
+      e_shstrndx: description.section_hdr_str_index,
+    }
+  }
+}
+
+impl RawLayout for ElfHeader64 {
+  const SIZE: usize = 64;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<ElfHeader64> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    let mut e_ident = [0u8; 16];
+    cursor.read_exact(&mut e_ident)?;
+    Ok(ElfHeader64 {
+      e_ident,
+      e_type: cursor.read_u16::<E>()?,
+      e_machine: cursor.read_u16::<E>()?,
+      e_version: cursor.read_u32::<E>()?,
+      e_entry: cursor.read_u64::<E>()?,
+      e_phoff: cursor.read_u64::<E>()?,
+      e_shoff: cursor.read_u64::<E>()?,
+      e_flags: cursor.read_u32::<E>()?,
+      e_ehsize: cursor.read_u16::<E>()?,
+      e_phentsize: cursor.read_u16::<E>()?,
+      e_phnum: cursor.read_u16::<E>()?,
+      e_shentsize: cursor.read_u16::<E>()?,
+      e_shnum: cursor.read_u16::<E>()?,
+      e_shstrndx: cursor.read_u16::<E>()?,
+    })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_all(&self.e_ident)?;
+    cursor.write_u16::<E>(self.e_type)?;
+    cursor.write_u16::<E>(self.e_machine)?;
+    cursor.write_u32::<E>(self.e_version)?;
+    cursor.write_u64::<E>(self.e_entry)?;
+    cursor.write_u64::<E>(self.e_phoff)?;
+    cursor.write_u64::<E>(self.e_shoff)?;
+    cursor.write_u32::<E>(self.e_flags)?;
+    cursor.write_u16::<E>(self.e_ehsize)?;
+    cursor.write_u16::<E>(self.e_phentsize)?;
+    cursor.write_u16::<E>(self.e_phnum)?;
+    cursor.write_u16::<E>(self.e_shentsize)?;
+    cursor.write_u16::<E>(self.e_shnum)?;
+    cursor.write_u16::<E>(self.e_shstrndx)
+  }
+}
+
+impl Widen<ElfHeader> for ElfHeader64 {
+  fn widen(&self) -> ElfHeader {
+    ElfHeader {
+      identification: identification(&self.e_ident),
+      description: ElfDescription {
+        obj_type: self.e_type,
+        machine: self.e_machine,
+        version: self.e_version,
+        entry: self.e_entry,
+        program_hdr_offset: self.e_phoff,
+        section_hdr_offset: self.e_shoff,
+        flags: self.e_flags,
+        elf_hdr_size: self.e_ehsize,
+        program_hdr_entry_size: self.e_phentsize,
+        program_hdr_num: self.e_phnum,
+        section_hdr_entry_size: self.e_shentsize,
+        section_hdr_num: self.e_shnum,
+        section_hdr_str_index: self.e_shstrndx,
+      },
+    }
+  }
+
+  fn narrow(wide: &ElfHeader) -> ElfHeader64 {
+    let description = &wide.description;
+    ElfHeader64 {
+      e_ident: ident(&wide.identification),
+      e_type: description.obj_type,
+      e_machine: description.machine,
+      e_version: description.version,
+      e_entry: description.entry,
+      e_phoff: description.program_hdr_offset,
+      e_shoff: description.section_hdr_offset,
+      e_flags: description.flags,
+      e_ehsize: description.elf_hdr_size,
+      e_phentsize: description.program_hdr_entry_size,
+      e_phnum: description.program_hdr_num,
+      e_shentsize: description.section_hdr_entry_size,
+      e_shnum: description.section_hdr_num,
+      e_shstrndx: description.section_hdr_str_index,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SectionHeader32 {
+  pub sh_name: u32,
+  pub sh_type: u32,
+  pub sh_flags: u32,
+  pub sh_addr: u32,
+  pub sh_offset: u32,
+  pub sh_size: u32,
+  pub sh_link: u32,
+  pub sh_info: u32,
+  pub sh_addralign: u32,
+  pub sh_entsize: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SectionHeader64 {
+  pub sh_name: u32,
+  pub sh_type: u32,
+  pub sh_flags: u64,
+  pub sh_addr: u64,
+  pub sh_offset: u64,
+  pub sh_size: u64,
+  pub sh_link: u32,
+  pub sh_info: u32,
+  pub sh_addralign: u64,
+  pub sh_entsize: u64,
+}
+
+impl RawLayout for SectionHeader32 {
+  const SIZE: usize = 40;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<SectionHeader32> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(SectionHeader32 {
+      sh_name: cursor.read_u32::<E>()?,
+      sh_type: cursor.read_u32::<E>()?,
+      sh_flags: cursor.read_u32::<E>()?,
+      sh_addr: cursor.read_u32::<E>()?,
+      sh_offset: cursor.read_u32::<E>()?,
+      sh_size: cursor.read_u32::<E>()?,
+      sh_link: cursor.read_u32::<E>()?,
+      sh_info: cursor.read_u32::<E>()?,
+      sh_addralign: cursor.read_u32::<E>()?,
+      sh_entsize: cursor.read_u32::<E>()?,
+    })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    for value in [self.sh_name, self.sh_type, self.sh_flags, self.sh_addr, self.sh_offset, self.sh_size, self.sh_link, self.sh_info, self.sh_addralign, self.sh_entsize] {
+      cursor.write_u32::<E>(value)?;
+    }
+    Ok(())
+  }
+}
+
+impl Widen<SectionHeader> for SectionHeader32 {
+  fn widen(&self) -> SectionHeader {
+    SectionHeader {
+      name_index: self.sh_name,
+      section_type: self.sh_type,
+      flags: self.sh_flags as u64,
+      address: self.sh_addr as u64,
+      offset: self.sh_offset as u64,
+      size: self.sh_size as u64,
+      link: self.sh_link,
+      info: self.sh_info,
+      align: self.sh_addralign as u64,
+      entry_size: self.sh_entsize as u64,
+    }
+  }
+
+  fn narrow(wide: &SectionHeader) -> SectionHeader32 {
+    SectionHeader32 {
+      sh_name: wide.name_index,
+      sh_type: wide.section_type,
+      sh_flags: wide.flags as u32,
+      sh_addr: wide.address as u32,
+      sh_offset: wide.offset as u32,
+      sh_size: wide.size as u32,
+      sh_link: wide.link,
+      sh_info: wide.info,
+      sh_addralign: wide.align as u32,
+      sh_entsize: wide.entry_size as u32,
+    }
+  }
+}
+
+impl RawLayout for SectionHeader64 {
+  const SIZE: usize = 64;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<SectionHeader64> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(SectionHeader64 {
+      sh_name: cursor.read_u32::<E>()?,
+      sh_type: cursor.read_u32::<E>()?,
+      sh_flags: cursor.read_u64::<E>()?,
+      sh_addr: cursor.read_u64::<E>()?,
+      sh_offset: cursor.read_u64::<E>()?,
+      sh_size: cursor.read_u64::<E>()?,
+      sh_link: cursor.read_u32::<E>()?,
+      sh_info: cursor.read_u32::<E>()?,
+      sh_addralign: cursor.read_u64::<E>()?,
+      sh_entsize: cursor.read_u64::<E>()?,
+    })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u32::<E>(self.sh_name)?;
+    cursor.write_u32::<E>(self.sh_type)?;
+    cursor.write_u64::<E>(self.sh_flags)?;
+    cursor.write_u64::<E>(self.sh_addr)?;
+    cursor.write_u64::<E>(self.sh_offset)?;
+    cursor.write_u64::<E>(self.sh_size)?;
+    cursor.write_u32::<E>(self.sh_link)?;
+    cursor.write_u32::<E>(self.sh_info)?;
+    cursor.write_u64::<E>(self.sh_addralign)?;
+    cursor.write_u64::<E>(self.sh_entsize)
+  }
+}
+
+impl Widen<SectionHeader> for SectionHeader64 {
+  fn widen(&self) -> SectionHeader {
+    SectionHeader {
+      name_index: self.sh_name,
+      section_type: self.sh_type,
+      flags: self.sh_flags,
+      address: self.sh_addr,
+      offset: self.sh_offset,
+      size: self.sh_size,
+      link: self.sh_link,
+      info: self.sh_info,
+      align: self.sh_addralign,
+      entry_size: self.sh_entsize,
+    }
+  }
+
+  fn narrow(wide: &SectionHeader) -> SectionHeader64 {
+    SectionHeader64 {
+      sh_name: wide.name_index,
+      sh_type: wide.section_type,
+      sh_flags: wide.flags,
+      sh_addr: wide.address,
+      sh_offset: wide.offset,
+      sh_size: wide.size,
+      sh_link: wide.link,
+      sh_info: wide.info,
+      sh_addralign: wide.align,
+      sh_entsize: wide.entry_size,
+    }
+  }
+}
+
+//p_flags moves: after p_memsz in Elf32, after p_type in Elf64
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProgramHeader32 {
+  pub p_type: u32,
+  pub p_offset: u32,
+  pub p_vaddr: u32,
+  pub p_paddr: u32,
+  pub p_filesz: u32,
+  pub p_memsz: u32,
+  pub p_flags: u32,
+  pub p_align: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProgramHeader64 {
+  pub p_type: u32,
+  pub p_flags: u32,
+  pub p_offset: u64,
+  pub p_vaddr: u64,
+  pub p_paddr: u64,
+  pub p_filesz: u64,
+  pub p_memsz: u64,
+  pub p_align: u64,
+}
+
+impl RawLayout for ProgramHeader32 {
+  const SIZE: usize = 32;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<ProgramHeader32> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(ProgramHeader32 {
+      p_type: cursor.read_u32::<E>()?,
+      p_offset: cursor.read_u32::<E>()?,
+      p_vaddr: cursor.read_u32::<E>()?,
+      p_paddr: cursor.read_u32::<E>()?,
+      p_filesz: cursor.read_u32::<E>()?,
+      p_memsz: cursor.read_u32::<E>()?,
+      p_flags: cursor.read_u32::<E>()?,
+      p_align: cursor.read_u32::<E>()?,
+    })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    for value in [self.p_type, self.p_offset, self.p_vaddr, self.p_paddr, self.p_filesz, self.p_memsz, self.p_flags, self.p_align] {
+      cursor.write_u32::<E>(value)?;
+    }
+    Ok(())
+  }
+}
+
+impl Widen<ProgramHeader> for ProgramHeader32 {
+  fn widen(&self) -> ProgramHeader {
+    ProgramHeader {
+      entry_type: self.p_type,
+      flags: self.p_flags,
+      offset: self.p_offset as u64,
+      virtual_address: self.p_vaddr as u64,
+      physical_address: self.p_paddr as u64,
+      file_size: self.p_filesz as u64,
+      memory_size: self.p_memsz as u64,
+      align: self.p_align as u64,
+    }
+  }
+
+  fn narrow(wide: &ProgramHeader) -> ProgramHeader32 {
+    ProgramHeader32 {
+      p_type: wide.entry_type,
+      p_offset: wide.offset as u32,
+      p_vaddr: wide.virtual_address as u32,
+      p_paddr: wide.physical_address as u32,
+      p_filesz: wide.file_size as u32,
+      p_memsz: wide.memory_size as u32,
+      p_flags: wide.flags,
+      p_align: wide.align as u32,
+    }
+  }
+}
+
+impl RawLayout for ProgramHeader64 {
+  const SIZE: usize = 56;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<ProgramHeader64> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(ProgramHeader64 {
+      p_type: cursor.read_u32::<E>()?,
+      p_flags: cursor.read_u32::<E>()?,
+      p_offset: cursor.read_u64::<E>()?,
+      p_vaddr: cursor.read_u64::<E>()?,
+      p_paddr: cursor.read_u64::<E>()?,
+      p_filesz: cursor.read_u64::<E>()?,
+      p_memsz: cursor.read_u64::<E>()?,
+      p_align: cursor.read_u64::<E>()?,
+    })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u32::<E>(self.p_type)?;
+    cursor.write_u32::<E>(self.p_flags)?;
+    for value in [self.p_offset, self.p_vaddr, self.p_paddr, self.p_filesz, self.p_memsz, self.p_align] {
+      cursor.write_u64::<E>(value)?;
+    }
+    Ok(())
+  }
+}
+
+impl Widen<ProgramHeader> for ProgramHeader64 {
+  fn widen(&self) -> ProgramHeader {
+    ProgramHeader {
+      entry_type: self.p_type,
+      flags: self.p_flags,
+      offset: self.p_offset,
+      virtual_address: self.p_vaddr,
+      physical_address: self.p_paddr,
+      file_size: self.p_filesz,
+      memory_size: self.p_memsz,
+      align: self.p_align,
+    }
+  }
+
+  fn narrow(wide: &ProgramHeader) -> ProgramHeader64 {
+    ProgramHeader64 {
+      p_type: wide.entry_type,
+      p_flags: wide.flags,
+      p_offset: wide.offset,
+      p_vaddr: wide.virtual_address,
+      p_paddr: wide.physical_address,
+      p_filesz: wide.file_size,
+      p_memsz: wide.memory_size,
+      p_align: wide.align,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Symbol32 {
+  pub st_name: u32,
+  pub st_value: u32,
+  pub st_size: u32,
+  pub st_info: u8,
+  pub st_other: u8,
+  pub st_shndx: u16,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Symbol64 {
+  pub st_name: u32,
+  pub st_info: u8,
+  pub st_other: u8,
+  pub st_shndx: u16,
+  pub st_value: u64,
+  pub st_size: u64,
+}
+
+impl RawLayout for Symbol32 {
+  const SIZE: usize = 16;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<Symbol32> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(Symbol32 {
+      st_name: cursor.read_u32::<E>()?,
+      st_value: cursor.read_u32::<E>()?,
+      st_size: cursor.read_u32::<E>()?,
+      st_info: cursor.read_u8()?,
+      st_other: cursor.read_u8()?,
+      st_shndx: cursor.read_u16::<E>()?,
+    })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u32::<E>(self.st_name)?;
+    cursor.write_u32::<E>(self.st_value)?;
+    cursor.write_u32::<E>(self.st_size)?;
+    cursor.write_u8(self.st_info)?;
+    cursor.write_u8(self.st_other)?;
+    cursor.write_u16::<E>(self.st_shndx)
+  }
+}
+
+fn widen_symbol(value: u64, size: u64, info: u8, other: u8, section_index: u16) -> Symbol {
+  Symbol {
+    value,
+    size,
+    symbol_type: info & 0xf,
+    binding: info >> 4,
+    visibility: other & 0x3,
+    section_index,
+    ..Default::default()
+  }
+}
+
+impl Widen<Symbol> for Symbol32 {
+  fn widen(&self) -> Symbol {
+    widen_symbol(self.st_value as u64, self.st_size as u64, self.st_info, self.st_other, self.st_shndx)
+  }
+
+  fn narrow(wide: &Symbol) -> Symbol32 {
+    Symbol32 {
+      st_name: 0,
+      st_value: wide.value as u32,
+      st_size: wide.size as u32,
+      st_info: wide.binding << 4 | (wide.symbol_type & 0xf),
+      st_other: wide.visibility,
+      st_shndx: wide.section_index,
+    }
+  }
+}
+
+impl RawSymbol for Symbol32 {
+  fn name_index(&self) -> u32 {
+    self.st_name
+  }
+
+  fn set_name_index(&mut self, name_index: u32) {
+    self.st_name = name_index;
+  }
+}
+
+impl RawLayout for Symbol64 {
+  const SIZE: usize = 24;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<Symbol64> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(Symbol64 {
+      st_name: cursor.read_u32::<E>()?,
+      st_info: cursor.read_u8()?,
+      st_other: cursor.read_u8()?,
+      st_shndx: cursor.read_u16::<E>()?,
+      st_value: cursor.read_u64::<E>()?,
+      st_size: cursor.read_u64::<E>()?,
+    })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u32::<E>(self.st_name)?;
+    cursor.write_u8(self.st_info)?;
+    cursor.write_u8(self.st_other)?;
+    cursor.write_u16::<E>(self.st_shndx)?;
+    cursor.write_u64::<E>(self.st_value)?;
+    cursor.write_u64::<E>(self.st_size)
+  }
+}
+
+impl Widen<Symbol> for Symbol64 {
+  fn widen(&self) -> Symbol {
+    widen_symbol(self.st_value, self.st_size, self.st_info, self.st_other, self.st_shndx)
+  }
+
+  fn narrow(wide: &Symbol) -> Symbol64 {
+    Symbol64 {
+      st_name: 0,
+      st_info: wide.binding << 4 | (wide.symbol_type & 0xf),
+      st_other: wide.visibility,
+      st_shndx: wide.section_index,
+      st_value: wide.value,
+      st_size: wide.size,
+    }
+  }
+}
+
+impl RawSymbol for Symbol64 {
+  fn name_index(&self) -> u32 {
+    self.st_name
+  }
+
+  fn set_name_index(&mut self, name_index: u32) {
+    self.st_name = name_index;
+  }
+}
+
+//d_tag is signed in the specification, tags are read zero extended like every other value
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dynamic32 {
+  pub d_tag: u32,
+  pub d_val: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dynamic64 {
+  pub d_tag: u64,
+  pub d_val: u64,
+}
+
+impl RawLayout for Dynamic32 {
+  const SIZE: usize = 8;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<Dynamic32> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(Dynamic32 { d_tag: cursor.read_u32::<E>()?, d_val: cursor.read_u32::<E>()? })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u32::<E>(self.d_tag)?;
+    cursor.write_u32::<E>(self.d_val)
+  }
+}
+
+impl Widen<DynamicEntry> for Dynamic32 {
+  fn widen(&self) -> DynamicEntry {
+    DynamicEntry { tag: self.d_tag as u64, value: self.d_val as u64 }
+  }
+
+  fn narrow(wide: &DynamicEntry) -> Dynamic32 {
+    Dynamic32 { d_tag: wide.tag as u32, d_val: wide.value as u32 }
+  }
+}
+
+impl RawLayout for Dynamic64 {
+  const SIZE: usize = 16;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<Dynamic64> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(Dynamic64 { d_tag: cursor.read_u64::<E>()?, d_val: cursor.read_u64::<E>()? })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u64::<E>(self.d_tag)?;
+    cursor.write_u64::<E>(self.d_val)
+  }
+}
+
+impl Widen<DynamicEntry> for Dynamic64 {
+  fn widen(&self) -> DynamicEntry {
+    DynamicEntry { tag: self.d_tag, value: self.d_val }
+  }
+
+  fn narrow(wide: &DynamicEntry) -> Dynamic64 {
+    Dynamic64 { d_tag: wide.tag, d_val: wide.value }
+  }
+}
+
+//Relocations widen with section_index 0, the section holding them is not part of an entry.
+//r_info packs the symbol index over the type: 24 and 8 bits in Elf32, 32 and 32 in Elf64.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rel32 {
+  pub r_offset: u32,
+  pub r_info: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rela32 {
+  pub r_offset: u32,
+  pub r_info: u32,
+  pub r_addend: i32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rel64 {
+  pub r_offset: u64,
+  pub r_info: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rela64 {
+  pub r_offset: u64,
+  pub r_info: u64,
+  pub r_addend: i64,
+}
+
+fn widen_relocation32(offset: u32, info: u32, addend: Option<i64>) -> Relocation {
+  Relocation { section_index: 0, offset: offset as u64, symbol_index: info >> 8, relocation_type: info & 0xff, addend }
+}
+
+fn widen_relocation64(offset: u64, info: u64, addend: Option<i64>) -> Relocation {
+  Relocation { section_index: 0, offset, symbol_index: (info >> 32) as u32, relocation_type: info as u32, addend }
+}
+
+fn info32(wide: &Relocation) -> u32 {
+  wide.symbol_index << 8 | (wide.relocation_type & 0xff)
+}
+
+fn info64(wide: &Relocation) -> u64 {
+  (wide.symbol_index as u64) << 32 | wide.relocation_type as u64
+}
+
+impl RawLayout for Rel32 {
+  const SIZE: usize = 8;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<Rel32> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(Rel32 { r_offset: cursor.read_u32::<E>()?, r_info: cursor.read_u32::<E>()? })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u32::<E>(self.r_offset)?;
+    cursor.write_u32::<E>(self.r_info)
+  }
+}
+
+//the addend of a REL entry is at the target, narrowing drops the one a Relocation has
+impl Widen<Relocation> for Rel32 {
+  fn widen(&self) -> Relocation {
+    widen_relocation32(self.r_offset, self.r_info, None)
+  }
+
+  fn narrow(wide: &Relocation) -> Rel32 {
+    Rel32 { r_offset: wide.offset as u32, r_info: info32(wide) }
+  }
+}
+
+impl RawLayout for Rela32 {
+  const SIZE: usize = 12;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<Rela32> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(Rela32 { r_offset: cursor.read_u32::<E>()?, r_info: cursor.read_u32::<E>()?, r_addend: cursor.read_i32::<E>()? })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u32::<E>(self.r_offset)?;
+    cursor.write_u32::<E>(self.r_info)?;
+    cursor.write_i32::<E>(self.r_addend)
+  }
+}
+
+impl Widen<Relocation> for Rela32 {
+  fn widen(&self) -> Relocation {
+    widen_relocation32(self.r_offset, self.r_info, Some(self.r_addend as i64))
+  }
+
+  fn narrow(wide: &Relocation) -> Rela32 {
+    Rela32 { r_offset: wide.offset as u32, r_info: info32(wide), r_addend: wide.addend.unwrap_or(0) as i32 }
+  }
+}
+
+impl RawLayout for Rel64 {
+  const SIZE: usize = 16;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<Rel64> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(Rel64 { r_offset: cursor.read_u64::<E>()?, r_info: cursor.read_u64::<E>()? })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u64::<E>(self.r_offset)?;
+    cursor.write_u64::<E>(self.r_info)
+  }
+}
+
+impl Widen<Relocation> for Rel64 {
+  fn widen(&self) -> Relocation {
+    widen_relocation64(self.r_offset, self.r_info, None)
+  }
+
+  fn narrow(wide: &Relocation) -> Rel64 {
+    Rel64 { r_offset: wide.offset, r_info: info64(wide) }
+  }
+}
+
+impl RawLayout for Rela64 {
+  const SIZE: usize = 24;
+
+  fn read<E: ByteOrder>(data: &[u8]) -> io::Result<Rela64> {
+    let mut cursor = reader(data, Self::SIZE)?;
+    Ok(Rela64 { r_offset: cursor.read_u64::<E>()?, r_info: cursor.read_u64::<E>()?, r_addend: cursor.read_i64::<E>()? })
+  }
+
+  fn write<E: ByteOrder>(&self, data: &mut [u8]) -> io::Result<()> {
+    let mut cursor = writer(data, Self::SIZE)?;
+    cursor.write_u64::<E>(self.r_offset)?;
+    cursor.write_u64::<E>(self.r_info)?;
+    cursor.write_i64::<E>(self.r_addend)
+  }
+}
+
+impl Widen<Relocation> for Rela64 {
+  fn widen(&self) -> Relocation {
+    widen_relocation64(self.r_offset, self.r_info, Some(self.r_addend))
+  }
+
+  fn narrow(wide: &Relocation) -> Rela64 {
+    Rela64 { r_offset: wide.offset, r_info: info64(wide), r_addend: wide.addend.unwrap_or(0) }
+  }
+}
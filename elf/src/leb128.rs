@@ -0,0 +1,46 @@
+pub(crate) fn read_uleb128(data: &[u8], offset: &mut usize) -> Option<u64> {
+  let mut value = 0u64;
+  let mut shift = 0;
+  loop {
+    let byte = *data.get(*offset)?;
+    *offset += 1;
+    if shift < 64 {
+      value |= ((byte & 0x7f) as u64) << shift;
+    }
+    shift += 7;
+    if byte & 0x80 == 0 {
+      return Some(value);
+    }
+  }
+}
+
+pub(crate) fn read_sleb128(data: &[u8], offset: &mut usize) -> Option<i64> {
+  let mut value = 0i64;
+  let mut shift = 0;
+  loop {
+    let byte = *data.get(*offset)?;
+    *offset += 1;
+    if shift < 64 {
+      value |= ((byte & 0x7f) as i64) << shift;
+    }
+    shift += 7;
+    if byte & 0x80 == 0 {
+      if shift < 64 && byte & 0x40 != 0 {
+        value |= -1 << shift;
+      }
+      return Some(value);
+    }
+  }
+}
+
+pub(crate) fn write_uleb128(out: &mut Vec<u8>, mut value: u64) {
+  loop {
+    let byte = (value & 0x7f) as u8;
+    value >>= 7;
+    if value == 0 {
+      out.push(byte);
+      return;
+    }
+    out.push(byte | 0x80);
+  }
+}
@@ -1,2 +1,139 @@
+mod abi;
+mod archive;
+mod aslr;
+mod build_attributes;
+mod build_id;
+mod build_id_index;
+mod call_sites;
+mod cfi;
+mod conflicts;
+mod control_flow;
+mod consts;
+mod cpu_features;
+mod dead_exports;
+mod debug_index;
+mod denylist;
+#[cfg(feature = "sha2")]
+mod der;
+mod digest;
+mod dynamic;
+mod dwarf;
+#[cfg(feature = "sha2")]
+mod ed25519;
 mod elf;
+mod entry_table;
+mod exception_table;
+mod extract;
+mod fixtures;
+pub mod fuzz;
+mod header;
+mod hexdump;
+mod ifunc;
+mod inlining;
+mod interner;
+mod jit;
+mod layout;
+mod leb128;
+mod libc_compat;
+mod linkage;
+mod linker_map;
+mod loader;
+mod lookup;
+#[cfg(feature = "sha2")]
+mod module_signature;
+mod mutator;
+mod note;
+mod offload;
+mod parallel;
+mod parse_options;
+pub mod query;
+mod relocation;
+mod rebase;
+#[cfg(feature = "sha2")]
+mod rsa;
+mod rtti;
+mod sanitizer;
+mod seccomp;
+mod section_compare;
+mod section_content;
+mod section_decoder;
+mod section_digest;
+mod section_writer;
+mod security;
+mod shim;
+#[cfg(feature = "sha2")]
+mod signing;
+mod sframe;
+mod source_files;
+mod symbol;
+mod symbol_cache;
+mod symbol_map;
+mod syscall;
+mod tls;
+mod tricks;
+mod type_layout;
+mod versions;
+mod watch;
+mod workspace;
+pub use abi::*;
+pub use archive::*;
+pub use aslr::*;
+pub use build_attributes::*;
+pub use build_id::*;
+pub use build_id_index::*;
+pub use call_sites::*;
+pub use cfi::*;
+pub use conflicts::*;
+pub use control_flow::*;
+pub use consts::*;
+pub use cpu_features::*;
+pub use dead_exports::*;
+pub use debug_index::*;
+pub use denylist::*;
+pub use dynamic::*;
+pub use dwarf::*;
 pub use elf::*;
+pub use entry_table::*;
+pub use exception_table::*;
+pub use extract::*;
+pub use fixtures::*;
+pub use header::*;
+pub use hexdump::*;
+pub use ifunc::*;
+pub use inlining::*;
+pub use interner::*;
+pub use jit::*;
+pub use layout::*;
+pub use libc_compat::*;
+pub use linkage::*;
+pub use linker_map::*;
+pub use loader::*;
+#[cfg(feature = "sha2")]
+pub use module_signature::*;
+pub use mutator::*;
+pub use note::*;
+pub use offload::*;
+pub use parse_options::*;
+pub use relocation::*;
+pub use rtti::*;
+pub use sanitizer::*;
+pub use seccomp::*;
+pub use section_compare::*;
+pub use section_content::*;
+pub use section_decoder::*;
+pub use section_digest::*;
+pub use security::*;
+#[cfg(feature = "sha2")]
+pub use signing::*;
+pub use sframe::*;
+pub use source_files::*;
+pub use symbol::*;
+pub use symbol_cache::*;
+pub use symbol_map::*;
+pub use syscall::*;
+pub use tls::*;
+pub use tricks::*;
+pub use type_layout::*;
+pub use versions::*;
+pub use watch::*;
+pub use workspace::*;
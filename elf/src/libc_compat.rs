@@ -0,0 +1,162 @@
+use std::fmt;
+use crate::consts::*;
+use crate::elf::Elf;
+
+//libraries only glibc ships under these names, musl has everything in libc.so
+const GLIBC_LIBRARIES: &[&str] = &[
+  "libc.so.6", "libm.so.6", "libpthread.so.0", "libdl.so.2", "librt.so.1", "libresolv.so.2", "libutil.so.1",
+  "libanl.so.1", "libnsl.so.1",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct GlibcVersion {
+  pub major: u32,
+  pub minor: u32,
+  pub patch: u32,
+}
+
+impl GlibcVersion {
+  pub fn new(major: u32, minor: u32, patch: u32) -> GlibcVersion {
+    GlibcVersion { major, minor, patch }
+  }
+
+  //"2.17", "2.2.5" or the version name "GLIBC_2.34". Names without a number, GLIBC_PRIVATE
+  //and the GLIBC_ABI_* feature versions, are None.
+  pub fn parse(text: &str) -> Option<GlibcVersion> {
+    let text = text.strip_prefix("GLIBC_").unwrap_or(text);
+    let mut parts = text.split('.').map(|part| part.parse::<u32>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    match parts.next() {
+      Some(_) => None,
+      None => Some(GlibcVersion { major, minor, patch }),
+    }
+  }
+}
+
+impl fmt::Display for GlibcVersion {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.patch {
+      0 => write!(f, "{}.{}", self.major, self.minor),
+      patch => write!(f, "{}.{}.{}", self.major, self.minor, patch),
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LibcKind {
+  Glibc,
+  Musl,
+  //no interpreter and no DT_NEEDED, the libc is linked in
+  Static,
+  Unknown,
+}
+
+pub struct LibcRequirements {
+  pub libc: LibcKind,
+  pub interpreter: Option<String>,
+  //the oldest glibc providing every GLIBC_* version and dynamic tag the file uses, None
+  //when it references none
+  pub minimum_glibc: Option<GlibcVersion>,
+  //imported symbols with the glibc version they are bound to, newest first
+  pub glibc_symbols: Vec<(String, GlibcVersion)>,
+  //GLIBC_PRIVATE imports, only the glibc build the file was linked against is sure to have them
+  pub private_symbols: Vec<String>,
+  //why the file would not run on a musl system
+  pub musl_blockers: Vec<String>,
+}
+
+impl LibcRequirements {
+  pub fn runs_on_glibc(&self, version: GlibcVersion) -> bool {
+    self.libc != LibcKind::Musl && self.minimum_glibc.is_none_or(|minimum| minimum <= version)
+  }
+
+  //without gcompat or another glibc shim
+  pub fn runs_on_musl(&self) -> bool {
+    self.musl_blockers.is_empty()
+  }
+}
+
+fn base_name(path: &str) -> &str {
+  path.rsplit('/').next().unwrap_or(path)
+}
+
+fn is_glibc_interpreter(path: &str) -> bool {
+  //ld-linux-x86-64.so.2, ld-linux-aarch64.so.1, ld64.so.2 on ppc64le, ld.so.1 on mips and ppc
+  let name = base_name(path);
+  name.starts_with("ld-linux") || name.starts_with("ld64.so.") || name == "ld.so.1"
+}
+
+impl Elf {
+  pub fn libc_kind(&self) -> LibcKind {
+    let interpreter = self.interpreter();
+    let needed = self.needed_libraries();
+    if interpreter.as_deref().is_some_and(|path| base_name(path).starts_with("ld-musl"))
+      || needed.iter().any(|name| name == "libc.so" || name.starts_with("libc.musl")) {
+      LibcKind::Musl
+    } else if interpreter.as_deref().is_some_and(is_glibc_interpreter) || needed.iter().any(|name| name == "libc.so.6") {
+      LibcKind::Glibc
+    } else if interpreter.is_none() && needed.is_empty() {
+      LibcKind::Static
+    } else {
+      LibcKind::Unknown
+    }
+  }
+
+  //What the file needs from the C library: the glibc versions its imports are bound to and
+  //the dynamic tags that need a newer loader (DT_RELR, glibc 2.36), and what keeps it off musl.
+  pub fn libc_requirements(&self) -> LibcRequirements {
+    let libc = self.libc_kind();
+    let interpreter = self.interpreter();
+    let mut minimum: Option<GlibcVersion> = None;
+    let mut require = |version: GlibcVersion| minimum = Some(minimum.map_or(version, |minimum| minimum.max(version)));
+    for requirement in self.version_requirements() {
+      match requirement.name.as_str() {
+        "GLIBC_ABI_DT_RELR" => require(GlibcVersion::new(2, 36, 0)),
+        name => {
+          if let Some(version) = GlibcVersion::parse(name).filter(|_| name.starts_with("GLIBC_")) {
+            require(version);
+          }
+        },
+      };
+    }
+    if libc == LibcKind::Glibc && self.dynamic_value(DT_RELR).is_some() {
+      require(GlibcVersion::new(2, 36, 0));
+    }
+
+    let mut glibc_symbols = Vec::new();
+    let mut private_symbols = Vec::new();
+    for (symbol, version) in self.dynamic_symbols().iter().zip(self.symbol_versions()) {
+      let version = match version {
+        Some(version) if symbol.is_undefined() && version.name.starts_with("GLIBC_") => version,
+        _ => continue,
+      };
+      if version.name == "GLIBC_PRIVATE" {
+        private_symbols.push(symbol.name.to_string());
+      } else if let Some(number) = GlibcVersion::parse(&version.name) {
+        glibc_symbols.push((symbol.name.to_string(), number));
+      }
+    }
+    glibc_symbols.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    private_symbols.sort();
+
+    let mut musl_blockers = Vec::new();
+    if libc != LibcKind::Musl && libc != LibcKind::Static {
+      if let Some(path) = interpreter.as_deref().filter(|path| is_glibc_interpreter(path)) {
+        musl_blockers.push(format!("interpreter {} is glibc's", path));
+      }
+      for name in self.needed_libraries().iter().filter(|name| GLIBC_LIBRARIES.contains(&name.as_str())) {
+        musl_blockers.push(format!("needs {}", name));
+      }
+      for name in &private_symbols {
+        musl_blockers.push(format!("{} is GLIBC_PRIVATE", name));
+      }
+      for symbol in self.imports().iter().filter(|symbol| symbol.name.starts_with("__") && symbol.name.ends_with("_chk")) {
+        musl_blockers.push(format!("{} is a glibc fortify function", symbol.name));
+      }
+    }
+
+    LibcRequirements { libc, interpreter, minimum_glibc: minimum, glibc_symbols, private_symbols, musl_blockers }
+  }
+}
@@ -0,0 +1,96 @@
+use crate::consts::*;
+use crate::elf::Elf;
+
+//Linked in only when the program calls them, and they dlopen libnss_* or gconv modules.
+//dlopen itself is in every static glibc binary, libc uses it internally, so only calls to it
+//count.
+const LOADING_FUNCTIONS: &[&str] = &[
+  "getpwnam", "getpwuid", "getgrnam", "getgrgid", "getaddrinfo", "gethostbyname", "gethostbyname_r",
+  "getservbyname", "iconv_open",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Linkage {
+  //no interpreter and no dynamic section
+  Static,
+  //self relocating, no interpreter and nothing needed
+  StaticPie,
+  //an interpreter or needed libraries, shared libraries included
+  Dynamic,
+  //static or static-PIE, but loading shared objects at runtime through dlopen, NSS or iconv
+  StaticWithDlopen,
+}
+
+pub struct LinkageReport {
+  pub linkage: Linkage,
+  //what the classification rests on, e.g. "PT_INTERP /lib64/ld-linux-x86-64.so.2"
+  pub evidence: Vec<String>,
+}
+
+impl Elf {
+  pub fn linkage(&self) -> LinkageReport {
+    let mut evidence = Vec::new();
+    let interpreter = self.interpreter();
+    let needed = self.needed_libraries();
+    let has_dynamic = self.program_headers.iter().any(|ph| ph.entry_type == PT_DYNAMIC);
+    if let Some(path) = &interpreter {
+      evidence.push(format!("PT_INTERP {}", path));
+    }
+    for name in &needed {
+      evidence.push(format!("DT_NEEDED {}", name));
+    }
+    if interpreter.is_some() || !needed.is_empty() {
+      return LinkageReport { linkage: Linkage::Dynamic, evidence };
+    }
+
+    let linkage = match (self.header.description.obj_type, has_dynamic) {
+      (ET_DYN, true) if self.dynamic_flags().flags_1 & DF_1_PIE != 0 => {
+        evidence.push(String::from("DF_1_PIE without PT_INTERP"));
+        Linkage::StaticPie
+      },
+      (ET_DYN, _) if self.symbol_by_name("_dl_relocate_static_pie").is_some() => {
+        evidence.push(String::from("_dl_relocate_static_pie without PT_INTERP"));
+        Linkage::StaticPie
+      },
+      //a shared library that happens to need nothing
+      (ET_DYN, _) => {
+        evidence.push(String::from("ET_DYN without PT_INTERP or DF_1_PIE"));
+        return LinkageReport { linkage: Linkage::Dynamic, evidence };
+      },
+      (_, true) => {
+        evidence.push(String::from("PT_DYNAMIC without PT_INTERP or DT_NEEDED"));
+        Linkage::Static
+      },
+      (_, false) => {
+        evidence.push(String::from("no PT_INTERP and no PT_DYNAMIC"));
+        Linkage::Static
+      },
+    };
+
+    let mut loads = false;
+    let symbols = self.symbol_table();
+    if symbols.is_empty() {
+      //the NSS module prefix survives stripping, the gconv path is in every static glibc
+      if self.data.windows(7).any(|window| window == b"libnss_") {
+        evidence.push(String::from("no symbol table, but the file contains \"libnss_\""));
+        loads = true;
+      }
+    } else {
+      for name in LOADING_FUNCTIONS {
+        if symbols.iter().any(|symbol| !symbol.is_undefined() && symbol.symbol_type == STT_FUNC && &*symbol.name == *name) {
+          evidence.push(format!("links in {}", name));
+          loads = true;
+        }
+      }
+      let dlopen = symbols.iter().find(|symbol| !symbol.is_undefined() && symbol.symbol_type == STT_FUNC && &*symbol.name == "dlopen");
+      if let Some(dlopen) = dlopen {
+        let calls = self.call_sites(&[dlopen.value], &[]).len();
+        if calls > 0 {
+          evidence.push(format!("direct calls to dlopen: {}", calls));
+          loads = true;
+        }
+      }
+    }
+    LinkageReport { linkage: if loads { Linkage::StaticWithDlopen } else { linkage }, evidence }
+  }
+}
@@ -0,0 +1,325 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::io::ErrorKind;
+use std::path::Path;
+use crate::consts::*;
+use crate::elf::Elf;
+
+#[derive(Clone, Debug)]
+pub struct MapOutputSection {
+  pub name: String,
+  pub address: u64,
+  pub size: u64,
+}
+
+//An input section placed in (or discarded from) the output.
+#[derive(Clone, Debug)]
+pub struct MapInputSection {
+  //empty for discarded sections
+  pub output_section: String,
+  pub section: String,
+  //as the map spells it: a path, "lib.a(member.o)", or lld's "<internal>"
+  pub file: String,
+  pub address: u64,
+  pub size: u64,
+  //symbols the map lists in the section, with their address
+  pub symbols: Vec<(String, u64)>,
+}
+
+impl MapInputSection {
+  //("lib.a", "member.o") for an archive member
+  pub fn archive_member(&self) -> Option<(&str, &str)> {
+    let open = self.file.strip_suffix(')')?.rfind('(')?;
+    Some((&self.file[..open], &self.file[open + 1..self.file.len() - 1]))
+  }
+}
+
+//GNU ld's "Archive member included to satisfy reference by file (symbol)" entry.
+#[derive(Clone, Debug)]
+pub struct ArchiveInclusion {
+  //"lib.a(member.o)"
+  pub member: String,
+  pub referenced_by: String,
+  pub symbol: String,
+}
+
+//A GNU ld or lld -Map file.
+#[derive(Clone, Debug, Default)]
+pub struct LinkerMap {
+  pub output_sections: Vec<MapOutputSection>,
+  //in map order, which is address order within each output section
+  pub input_sections: Vec<MapInputSection>,
+  //GNU ld only, --gc-sections and /DISCARD/ victims
+  pub discarded: Vec<MapInputSection>,
+  //GNU ld only
+  pub archive_inclusions: Vec<ArchiveInclusion>,
+}
+
+//An input section with its place in the binary the map describes.
+#[derive(Clone, Debug)]
+pub struct MappedRange {
+  //index into LinkerMap::input_sections
+  pub input_index: usize,
+  pub section_index: usize,
+  pub address: u64,
+  //None for SHT_NOBITS
+  pub offset: Option<u64>,
+  pub size: u64,
+}
+
+fn hex(token: &str) -> Option<u64> {
+  u64::from_str_radix(token.strip_prefix("0x")?, 16).ok()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum GnuPart {
+  Preamble,
+  Archive,
+  Discarded,
+  Map,
+}
+
+fn parse_gnu(text: &str) -> LinkerMap {
+  let mut map = LinkerMap::default();
+  let mut part = GnuPart::Preamble;
+  //a section name too long for its column, the address and size follow on the next line
+  let mut pending_output: Option<String> = None;
+  let mut pending_input: Option<String> = None;
+  let mut output = String::new();
+  let mut member: Option<String> = None;
+  for line in text.lines() {
+    let heading = match line {
+      "Archive member included to satisfy reference by file (symbol)" => Some(GnuPart::Archive),
+      "Discarded input sections" => Some(GnuPart::Discarded),
+      "Linker script and memory map" => Some(GnuPart::Map),
+      _ if !line.starts_with(' ') && (line.starts_with("Memory Configuration") || line.starts_with("Allocating common symbols")
+        || line.starts_with("Merging program properties") || line.starts_with("As-needed library")) => Some(GnuPart::Preamble),
+      _ => None,
+    };
+    if let Some(heading) = heading {
+      part = heading;
+      continue;
+    }
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.is_empty() {
+      continue;
+    }
+    match part {
+      GnuPart::Preamble => {},
+      GnuPart::Archive => {
+        //"lib.a(member.o)   file (symbol)", the reason on the next line for long members
+        let reason = if line.starts_with(' ') {
+          line.trim()
+        } else {
+          member = Some(tokens[0].to_string());
+          line[tokens[0].len()..].trim()
+        };
+        if let (Some(name), Some((file, symbol))) = (&member, reason.rsplit_once(" (")) {
+          map.archive_inclusions.push(ArchiveInclusion {
+            member: name.clone(),
+            referenced_by: file.to_string(),
+            symbol: symbol.trim_end_matches(')').to_string(),
+          });
+          member = None;
+        }
+      },
+      GnuPart::Discarded | GnuPart::Map => {
+        let indent = line.len() - line.trim_start().len();
+        let (name, numbers) = match (pending_output.take(), pending_input.take()) {
+          (Some(name), _) if indent > 1 => ((0, name), &tokens[..]),
+          (_, Some(name)) if indent > 1 => ((1, name), &tokens[..]),
+          _ if indent == 0 => ((0, tokens[0].to_string()), &tokens[1..]),
+          _ if indent == 1 && !tokens[0].starts_with('*') => ((1, tokens[0].to_string()), &tokens[1..]),
+          _ if indent == 1 => continue,
+          //"0x... symbol", but not "0x... . = ALIGN (0x8)", "[!provide] PROVIDE (...)" or
+          //"0x2c (size before relaxing)"
+          _ => {
+            if let (Some(address), Some(input)) = (hex(tokens[0]), map.input_sections.last_mut().filter(|_| part == GnuPart::Map)) {
+              let symbol = line.trim_start()[tokens[0].len()..].trim();
+              if tokens.len() >= 2 && !symbol.contains(" = ") && !symbol.starts_with("PROVIDE") && !symbol.starts_with('(') {
+                input.symbols.push((symbol.to_string(), address));
+              }
+            }
+            continue;
+          },
+        };
+        let (level, name) = name;
+        let (address, size) = match (numbers.first().and_then(|token| hex(token)), numbers.get(1).and_then(|token| hex(token))) {
+          (Some(address), Some(size)) => (address, size),
+          _ if numbers.is_empty() => {
+            //LOAD, OUTPUT(...) and the like have more tokens, a bare name is a wrapped section
+            if tokens.len() == 1 && (level == 1 || name.starts_with('.') || part == GnuPart::Discarded) {
+              if level == 0 && part == GnuPart::Map {
+                output = name.clone();
+                pending_output = Some(name);
+              } else {
+                pending_input = Some(name);
+              }
+            } else if level == 0 && part == GnuPart::Map {
+              output = name;
+            }
+            continue;
+          },
+          _ => continue,
+        };
+        if level == 0 && part == GnuPart::Map {
+          output = name.clone();
+          map.output_sections.push(MapOutputSection { name, address, size });
+          continue;
+        }
+        let file = numbers[2..].join(" ");
+        if file.is_empty() {
+          continue;
+        }
+        let input = MapInputSection { output_section: String::new(), section: name, file, address, size, symbols: Vec::new() };
+        if part == GnuPart::Discarded {
+          map.discarded.push(input);
+        } else {
+          map.input_sections.push(MapInputSection { output_section: output.clone(), ..input });
+        }
+      },
+    };
+  }
+  map
+}
+
+//lld's table: "VMA LMA Size Align Out In Symbol", the column the text starts in tells an
+//output section from an input one or a symbol. lld before 7 had no LMA column.
+fn parse_lld(text: &str) -> Option<LinkerMap> {
+  let mut lines = text.lines().skip_while(|line| line.trim().is_empty());
+  let header = lines.next()?;
+  let numbers = header.split_whitespace().take_while(|column| *column != "Out").count();
+  let input_column = header.find(" In ")? + 1;
+  let symbol_column = header.find("Symbol")?;
+  let mut map = LinkerMap::default();
+  for line in lines {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() <= numbers {
+      continue;
+    }
+    let values: Vec<u64> = match tokens[..numbers].iter().map(|token| u64::from_str_radix(token, 16).ok()).collect() {
+      Some(values) => values,
+      None => continue,
+    };
+    let (address, size) = (values[0], values[numbers - 2]);
+    let mut position = 0;
+    for token in &tokens[..numbers] {
+      position = line[position..].find(token).map_or(position, |at| position + at + token.len());
+    }
+    let column = position + line[position..].len() - line[position..].trim_start().len();
+    let text = line[column..].trim_end();
+    if column >= symbol_column {
+      if let Some(input) = map.input_sections.last_mut().filter(|_| !text.contains(" = ")) {
+        input.symbols.push((text.to_string(), address));
+      }
+    } else if column >= input_column {
+      if let Some((file, section)) = text.rsplit_once(":(") {
+        map.input_sections.push(MapInputSection {
+          output_section: map.output_sections.last().map_or_else(String::new, |output| output.name.clone()),
+          section: section.trim_end_matches(')').to_string(),
+          file: file.to_string(),
+          address,
+          size,
+          symbols: Vec::new(),
+        });
+      }
+    } else if !text.contains(" = ") {
+      map.output_sections.push(MapOutputSection { name: text.to_string(), address, size });
+    }
+  }
+  Some(map)
+}
+
+impl LinkerMap {
+  pub fn parse(text: &str) -> io::Result<LinkerMap> {
+    let first = text.lines().find(|line| !line.trim().is_empty()).unwrap_or("");
+    let map = if first.contains("VMA") || (first.contains("Address") && first.contains("Symbol")) {
+      parse_lld(text).unwrap_or_default()
+    } else if text.lines().any(|line| line == "Linker script and memory map") {
+      parse_gnu(text)
+    } else {
+      return Err(io::Error::new(ErrorKind::InvalidData, "not a GNU ld or lld map file"));
+    };
+    Ok(map)
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<LinkerMap> {
+    LinkerMap::parse(&fs::read_to_string(path)?)
+  }
+
+  //The input section the byte at `address` came from, None for padding and linker made
+  //data. Sections that are not loaded, .comment and .debug_*, are at 0 and not searched.
+  pub fn input_at(&self, address: u64) -> Option<&MapInputSection> {
+    let unloaded: HashSet<&str> = self.output_sections.iter().filter(|output| output.address == 0).map(|output| output.name.as_str()).collect();
+    self.input_sections.iter()
+      .find(|input| input.address <= address && address - input.address < input.size && !unloaded.contains(input.output_section.as_str()))
+  }
+
+  //Why an archive member is in the link: the member, then the member that pulled that one
+  //in, and so on back to a file named on the command line.
+  pub fn inclusion_chain(&self, member: &str) -> Vec<&ArchiveInclusion> {
+    let mut chain = Vec::new();
+    let mut current = member;
+    while let Some(inclusion) = self.archive_inclusions.iter().find(|inclusion| inclusion.member == current) {
+      if chain.iter().any(|known: &&ArchiveInclusion| known.member == inclusion.member) {
+        break;
+      }
+      chain.push(inclusion);
+      current = &inclusion.referenced_by;
+    }
+    chain
+  }
+}
+
+impl Elf {
+  //Places the map's input sections in this file, by output section name and address.
+  //An output section the file has at another address or with another size means the map
+  //is from a different link and is an error; ones the file lacks (stripped) are skipped.
+  pub fn map_ranges(&self, map: &LinkerMap) -> io::Result<Vec<MappedRange>> {
+    let mut sections = HashMap::new();
+    for output in &map.output_sections {
+      let found = self.section_headers.iter().enumerate()
+        .find(|(_, section)| self.section_name(section) == Some(output.name.as_str()) && section.flags & SHF_ALLOC != 0);
+      if let Some((index, section)) = found {
+        if section.address != output.address || section.size != output.size {
+          return Err(io::Error::new(ErrorKind::InvalidData, format!(
+            "{} is {:#x}+{:#x} in the file but {:#x}+{:#x} in the map", output.name, section.address, section.size, output.address, output.size)));
+        }
+        sections.insert(output.name.as_str(), index);
+      }
+    }
+    let mut ranges = Vec::new();
+    for (input_index, input) in map.input_sections.iter().enumerate() {
+      let section_index = match sections.get(input.output_section.as_str()) {
+        Some(&index) if input.size > 0 => index,
+        _ => continue,
+      };
+      let section = &self.section_headers[section_index];
+      //an input section outside its output section does not describe this file
+      let start = match input.address.checked_sub(section.address) {
+        Some(start) if start.checked_add(input.size).is_some_and(|end| end <= section.size) => start,
+        _ => continue,
+      };
+      let offset = match section.offset.checked_add(start) {
+        _ if section.section_type == SHT_NOBITS => None,
+        Some(offset) => Some(offset),
+        None => continue,
+      };
+      ranges.push(MappedRange { input_index, section_index, address: input.address, offset, size: input.size });
+    }
+    Ok(ranges)
+  }
+  //Bytes each file contributes to the loaded sections of this file, largest first. Debug
+  //information, symbol tables and other sections that are not SHF_ALLOC are not counted.
+  pub fn size_by_file<'a>(&self, map: &'a LinkerMap) -> io::Result<Vec<(&'a str, u64)>> {
+    let mut sizes: HashMap<&str, u64> = HashMap::new();
+    for range in self.map_ranges(map)? {
+      let size = sizes.entry(&map.input_sections[range.input_index].file).or_insert(0);
+      *size = size.saturating_add(range.size);
+    }
+    let mut sizes: Vec<(&str, u64)> = sizes.into_iter().collect();
+    sizes.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    Ok(sizes)
+  }
+}
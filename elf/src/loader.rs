@@ -0,0 +1,412 @@
+use std::convert::TryFrom;
+use std::io;
+use std::path::PathBuf;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::Elf;
+
+pub const PAGE_SIZE: u64 = 0x1000;
+//how much memory the PT_LOAD segments of one object may map
+pub const DEFAULT_LOAD_LIMIT: u64 = 4 << 30;
+//the default RLIMIT_STACK
+pub const DEFAULT_STACK_LIMIT: u64 = 8 << 20;
+
+//A zeroed buffer, an error instead of an abort when it cannot be allocated.
+pub(crate) fn zeroed(size: u64) -> io::Result<Vec<u8>> {
+  let size = usize::try_from(size).map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, format!("cannot map {:#x} bytes", size)))?;
+  let mut data = Vec::new();
+  data.try_reserve_exact(size).map_err(|_| io::Error::new(io::ErrorKind::OutOfMemory, format!("cannot map {:#x} bytes", size)))?;
+  data.resize(size, 0);
+  Ok(data)
+}
+
+//One PT_LOAD as the kernel maps it: page aligned, file bytes followed by zeroes.
+pub struct LoadedSegment {
+  pub address: u64,
+  pub data: Vec<u8>,
+  //PF_R, PF_W, PF_X
+  pub flags: u32,
+}
+
+pub struct LoadedImage {
+  pub class: u8,
+  pub endianness: u8,
+  pub machine: u16,
+  pub bias: u64,
+  //biased entry point
+  pub entry: u64,
+  //biased address of the program header table, for AT_PHDR
+  pub program_headers_address: u64,
+  pub program_header_entry_size: u16,
+  pub program_header_num: u16,
+  //PT_INTERP path
+  pub interpreter: Option<String>,
+  pub segments: Vec<LoadedSegment>,
+}
+
+#[derive(Clone)]
+pub struct StackOptions {
+  pub arguments: Vec<String>,
+  pub environment: Vec<String>,
+  //highest stack address, a default for the class when None
+  pub stack_top: Option<u64>,
+  //the 16 bytes AT_RANDOM points at, glibc seeds the stack protector canary from them
+  pub random: [u8; 16],
+  pub platform: Option<String>,
+  //AT_EXECFN, the first argument when None
+  pub executable_name: Option<String>,
+  pub page_size: u64,
+  pub hwcap: u64,
+  pub hwcap2: u64,
+  pub uid: u64,
+  pub gid: u64,
+  //load address of the dynamic loader, AT_BASE
+  pub interpreter_base: Option<u64>,
+  //address of a vDSO image, AT_SYSINFO_EHDR
+  pub vdso: Option<u64>,
+  //largest stack initial_stack lays out
+  pub stack_limit: u64,
+}
+
+impl Default for StackOptions {
+  fn default() -> StackOptions {
+    StackOptions {
+      arguments: Vec::new(),
+      environment: Vec::new(),
+      stack_top: None,
+      random: [0; 16],
+      platform: None,
+      executable_name: None,
+      page_size: PAGE_SIZE,
+      hwcap: 0,
+      hwcap2: 0,
+      uid: 0,
+      gid: 0,
+      interpreter_base: None,
+      vdso: None,
+      stack_limit: DEFAULT_STACK_LIMIT,
+    }
+  }
+}
+
+//The stack a process starts with, data covers stack_pointer up to the stack top.
+pub struct InitialStack {
+  pub stack_pointer: u64,
+  pub data: Vec<u8>,
+  pub argv: u64,
+  pub envp: u64,
+  pub auxv: u64,
+  pub auxiliary_vector: Vec<(u64, u64)>,
+}
+
+impl LoadedImage {
+  pub fn segment_at(&self, address: u64) -> Option<&LoadedSegment> {
+    self.segments.iter().find(|segment| address >= segment.address && address - segment.address < segment.data.len() as u64)
+  }
+
+  pub fn read(&self, address: u64, size: usize) -> Option<&[u8]> {
+    let segment = self.segment_at(address)?;
+    let start = (address - segment.address) as usize;
+    segment.data.get(start..start.checked_add(size)?)
+  }
+
+  pub fn write(&mut self, address: u64, bytes: &[u8]) -> io::Result<()> {
+    let segment = self.segments.iter_mut()
+      .find(|segment| address >= segment.address && address - segment.address < segment.data.len() as u64)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("{:#x} is not mapped", address)))?;
+    let start = (address - segment.address) as usize;
+    let target = segment.data.get_mut(start..start + bytes.len())
+      .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("write at {:#x} crosses the end of its segment", address)))?;
+    target.copy_from_slice(bytes);
+    Ok(())
+  }
+
+  //first address past the highest segment
+  pub fn end(&self) -> u64 {
+    self.segments.iter().map(|segment| segment.address + segment.data.len() as u64).max().unwrap_or(self.bias)
+  }
+
+  pub fn auxiliary_vector(&self, options: &StackOptions) -> Vec<(u64, u64)> {
+    let mut auxv = vec![
+      (AT_PHDR, self.program_headers_address),
+      (AT_PHENT, self.program_header_entry_size as u64),
+      (AT_PHNUM, self.program_header_num as u64),
+      (AT_PAGESZ, options.page_size),
+      (AT_BASE, options.interpreter_base.unwrap_or(0)),
+      (AT_FLAGS, 0),
+      (AT_ENTRY, self.entry),
+      (AT_UID, options.uid),
+      (AT_EUID, options.uid),
+      (AT_GID, options.gid),
+      (AT_EGID, options.gid),
+      (AT_SECURE, 0),
+      (AT_HWCAP, options.hwcap),
+      (AT_HWCAP2, options.hwcap2),
+      (AT_CLKTCK, 100),
+    ];
+    if let Some(vdso) = options.vdso {
+      auxv.insert(0, (AT_SYSINFO_EHDR, vdso));
+    }
+    auxv
+  }
+
+  //Lays the stack out like the Linux kernel: argc, argv, envp and auxv at the 16-byte aligned
+  //stack pointer, then the AT_RANDOM bytes, the platform string, the argument and environment
+  //strings, AT_EXECFN and a null word at the top.
+  pub fn initial_stack(&self, options: &StackOptions) -> io::Result<InitialStack> {
+    match self.endianness {
+      1 => self.initial_stack_with_byteorder::<LittleEndian>(options),
+      2 => self.initial_stack_with_byteorder::<BigEndian>(options),
+      _ => panic!("unknown endianness"),
+    }
+  }
+
+  fn initial_stack_with_byteorder<E: ByteOrder>(&self, options: &StackOptions) -> io::Result<InitialStack> {
+    let word: usize = match self.class {
+      1 => 4,
+      2 => 8,
+      _ => panic!("unknown class"),
+    };
+    let top = options.stack_top.unwrap_or(if self.class == 1 { 0xbfff_f000 } else { 0x7fff_ffff_f000 });
+    //strings are collected bottom up, then placed below the null word at the top
+    let mut strings = Vec::new();
+    let mut place = |string: &str| {
+      let offset = strings.len() as u64;
+      strings.extend_from_slice(string.as_bytes());
+      strings.push(0);
+      offset
+    };
+    let arguments: Vec<u64> = options.arguments.iter().map(|argument| place(argument)).collect();
+    let environment: Vec<u64> = options.environment.iter().map(|variable| place(variable)).collect();
+    let executable_name = options.executable_name.as_deref().or(options.arguments.first().map(|argument| argument.as_str())).unwrap_or("");
+    let executable_name = place(executable_name);
+    let too_large = || io::Error::new(io::ErrorKind::InvalidInput, format!("the initial stack does not fit below {:#x} in {:#x} bytes", top, options.stack_limit));
+    let strings_start = top.checked_sub(word as u64 + strings.len() as u64).ok_or_else(too_large)?;
+    let mut cursor = strings_start;
+    let platform = match &options.platform {
+      Some(platform) => {
+        cursor = cursor.checked_sub(platform.len() as u64 + 1).ok_or_else(too_large)?;
+        Some(cursor)
+      },
+      None => None,
+    };
+    cursor = cursor.checked_sub(16).ok_or_else(too_large)?;
+    let random = cursor;
+    cursor &= !15;
+
+    let mut auxv = self.auxiliary_vector(options);
+    if let Some(platform) = platform {
+      auxv.push((AT_PLATFORM, platform));
+    }
+    auxv.push((AT_RANDOM, random));
+    auxv.push((AT_EXECFN, strings_start + executable_name));
+    auxv.push((AT_NULL, 0));
+    let words = 1 + arguments.len() + 1 + environment.len() + 1 + auxv.len() * 2;
+    let stack_pointer = cursor.checked_sub((words * word) as u64).ok_or_else(too_large)? & !15;
+    if top - stack_pointer > options.stack_limit {
+      return Err(too_large());
+    }
+
+    let mut data = zeroed(top - stack_pointer)?;
+    let mut vector = Vec::with_capacity(words);
+    vector.push(arguments.len() as u64);
+    vector.extend(arguments.iter().map(|offset| strings_start + offset));
+    vector.push(0);
+    vector.extend(environment.iter().map(|offset| strings_start + offset));
+    vector.push(0);
+    for &(tag, value) in &auxv {
+      vector.push(tag);
+      vector.push(value);
+    }
+    for (index, value) in vector.iter().enumerate() {
+      let slot = &mut data[index * word..(index + 1) * word];
+      match word {
+        4 => E::write_u32(slot, *value as u32),
+        _ => E::write_u64(slot, *value),
+      };
+    }
+    let at = |address: u64| (address - stack_pointer) as usize;
+    data[at(random)..at(random) + 16].copy_from_slice(&options.random);
+    if let (Some(address), Some(string)) = (platform, &options.platform) {
+      data[at(address)..at(address) + string.len()].copy_from_slice(string.as_bytes());
+    }
+    data[at(strings_start)..at(strings_start) + strings.len()].copy_from_slice(&strings);
+
+    let argv = stack_pointer + word as u64;
+    let envp = argv + (arguments.len() as u64 + 1) * word as u64;
+    Ok(InitialStack {
+      stack_pointer,
+      data,
+      argv,
+      envp,
+      auxv: envp + (environment.len() as u64 + 1) * word as u64,
+      auxiliary_vector: auxv,
+    })
+  }
+}
+
+impl Elf {
+  pub fn interpreter(&self) -> Option<String> {
+    let ph = self.program_headers.iter().find(|ph| ph.entry_type == PT_INTERP)?;
+    let bytes = self.data.get(ph.offset as usize..ph.offset.checked_add(ph.file_size)? as usize)?;
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+  }
+
+  //Maps every PT_LOAD the way execve does, without applying relocations. ET_DYN objects
+  //are placed with their lowest segment at `base`, ET_EXEC objects only load at their
+  //link address.
+  pub fn load(&self, base: Option<u64>) -> io::Result<LoadedImage> {
+    self.load_with_limit(base, DEFAULT_LOAD_LIMIT)
+  }
+
+  //load, failing when the segments map more than `limit` bytes in total
+  pub fn load_with_limit(&self, base: Option<u64>, limit: u64) -> io::Result<LoadedImage> {
+    let loads: Vec<_> = self.program_headers.iter().filter(|ph| ph.entry_type == PT_LOAD).collect();
+    let (lowest_address, _) = self.load_span()
+      .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "no PT_LOAD segments"))?;
+    let bias = match (self.header.description.obj_type, base) {
+      (ET_DYN, Some(base)) => base.wrapping_sub(lowest_address),
+      (ET_DYN, None) => 0,
+      (ET_EXEC, Some(base)) if base != lowest_address => {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("ET_EXEC objects load at {:#x}", lowest_address)));
+      },
+      (ET_EXEC, _) => 0,
+      _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "only ET_EXEC and ET_DYN objects can be loaded")),
+    };
+
+    let mut segments = Vec::new();
+    let mut mapped: u64 = 0;
+    for ph in &loads {
+      if ph.file_size > ph.memory_size {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("PT_LOAD at {:#x} has more file than memory bytes", ph.virtual_address)));
+      }
+      if ph.offset.checked_add(ph.file_size).is_none_or(|end| end > self.data.len() as u64) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("PT_LOAD at {:#x} reaches past the end of the file", ph.virtual_address)));
+      }
+      let start = ph.virtual_address & !(PAGE_SIZE - 1);
+      let end = ph.virtual_address.checked_add(ph.memory_size).and_then(|end| end.checked_next_multiple_of(PAGE_SIZE))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "PT_LOAD wraps around the address space"))?;
+      mapped = mapped.checked_add(end - start).filter(|&mapped| mapped <= limit)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("PT_LOAD segments map more than {:#x} bytes", limit)))?;
+      let mut data = zeroed(end - start)?;
+      let lead = ph.virtual_address - start;
+      let file_start = ph.offset.saturating_sub(lead) as usize;
+      let file_end = (ph.offset + ph.file_size) as usize;
+      if file_start < file_end {
+        let destination = (lead - (ph.offset - file_start as u64)) as usize;
+        data[destination..destination + file_end - file_start].copy_from_slice(&self.data[file_start..file_end]);
+      }
+      segments.push(LoadedSegment { address: start.wrapping_add(bias), data, flags: ph.flags });
+    }
+
+    let header = &self.header.description;
+    let program_headers_address = match self.program_headers.iter().find(|ph| ph.entry_type == PT_PHDR) {
+      Some(ph) => ph.virtual_address,
+      None => loads.iter()
+        .find(|ph| header.program_hdr_offset >= ph.offset && header.program_hdr_offset - ph.offset < ph.file_size)
+        .map_or(0, |ph| ph.virtual_address.wrapping_add(header.program_hdr_offset - ph.offset)),
+    };
+    Ok(LoadedImage {
+      class: self.header.identification.class,
+      endianness: self.header.identification.endianness,
+      machine: header.machine,
+      bias,
+      entry: header.entry.wrapping_add(bias),
+      program_headers_address: program_headers_address.wrapping_add(bias),
+      program_header_entry_size: header.program_hdr_entry_size,
+      program_header_num: header.program_hdr_num,
+      interpreter: self.interpreter(),
+      segments,
+    })
+  }
+}
+
+pub struct LoadOptions {
+  //where ET_DYN executables go, the non-randomized Linux default when None
+  pub base: Option<u64>,
+  pub load_interpreter: bool,
+  //where the interpreter goes, just below the default mmap base when None
+  pub interpreter_base: Option<u64>,
+  //PT_INTERP is looked up below this directory instead of the host root
+  pub sysroot: Option<PathBuf>,
+  //most memory the segments of each object may map
+  pub memory_limit: u64,
+}
+
+impl Default for LoadOptions {
+  fn default() -> LoadOptions {
+    LoadOptions { base: None, load_interpreter: true, interpreter_base: None, sysroot: None, memory_limit: DEFAULT_LOAD_LIMIT }
+  }
+}
+
+pub struct ProcessImage {
+  pub executable: LoadedImage,
+  pub interpreter: Option<LoadedImage>,
+  pub interpreter_path: Option<PathBuf>,
+}
+
+impl ProcessImage {
+  //where execution starts, inside the interpreter when there is one
+  pub fn entry(&self) -> u64 {
+    self.interpreter.as_ref().unwrap_or(&self.executable).entry
+  }
+
+  //The executable's stack with AT_BASE pointing at the loaded interpreter.
+  pub fn initial_stack(&self, options: &StackOptions) -> io::Result<InitialStack> {
+    match &self.interpreter {
+      Some(interpreter) if options.interpreter_base.is_none() => {
+        let options = StackOptions { interpreter_base: Some(interpreter.bias), ..options.clone() };
+        self.executable.initial_stack(&options)
+      },
+      _ => self.executable.initial_stack(options),
+    }
+  }
+}
+
+impl Elf {
+  //lowest and end address of the pages PT_LOAD segments cover, before any bias
+  pub fn load_span(&self) -> Option<(u64, u64)> {
+    let loads = self.program_headers.iter().filter(|ph| ph.entry_type == PT_LOAD);
+    let start = loads.clone().map(|ph| ph.virtual_address & !(PAGE_SIZE - 1)).min()?;
+    let end = loads.map(|ph| ph.virtual_address.saturating_add(ph.memory_size).checked_next_multiple_of(PAGE_SIZE).unwrap_or(!(PAGE_SIZE - 1))).max()?;
+    Some((start, end))
+  }
+
+  //Loads the executable and, when it names one, its PT_INTERP object the way the kernel
+  //does, so that execution can start in the real dynamic loader.
+  pub fn load_process(&self, options: &LoadOptions) -> io::Result<ProcessImage> {
+    let is_64 = self.header.identification.class == 2;
+    let base = match (self.header.description.obj_type, options.base) {
+      (ET_DYN, None) => Some(if is_64 { 0x5555_5555_4000 } else { 0x5655_5000 }),
+      (_, base) => base,
+    };
+    let executable = self.load_with_limit(base, options.memory_limit)?;
+    let path = match (&executable.interpreter, options.load_interpreter) {
+      (Some(path), true) => path.clone(),
+      _ => return Ok(ProcessImage { executable, interpreter: None, interpreter_path: None }),
+    };
+    let path = match &options.sysroot {
+      Some(sysroot) => sysroot.join(path.trim_start_matches('/')),
+      None => PathBuf::from(path),
+    };
+    let interpreter = Elf::open(&path).map_err(|error| io::Error::new(error.kind(), format!("{}: {}", path.display(), error)))?;
+    if interpreter.interpreter().is_some() {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} requests an interpreter itself", path.display())));
+    }
+    if interpreter.header.description.machine != self.header.description.machine || interpreter.header.identification.class != self.header.identification.class {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} does not match the executable's machine", path.display())));
+    }
+    let interpreter_base = match options.interpreter_base {
+      Some(base) => base,
+      None => {
+        let (start, end) = interpreter.load_span().unwrap_or((0, 0));
+        let mmap_base: u64 = if is_64 { 0x7fff_f7ff_f000 } else { 0xf7ff_f000 };
+        mmap_base.checked_sub(end - start)
+          .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} does not fit below the mmap base", path.display())))?
+      },
+    };
+    let interpreter = interpreter.load_with_limit(Some(interpreter_base), options.memory_limit)?;
+    Ok(ProcessImage { executable, interpreter: Some(interpreter), interpreter_path: Some(path) })
+  }
+}
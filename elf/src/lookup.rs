@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::mem::{self, size_of};
+use std::sync::{Arc, OnceLock};
+use crate::consts::*;
+use crate::elf::{Elf, SectionHeader};
+use crate::relocation::Relocation;
+use crate::symbol::Symbol;
+
+#[derive(Default)]
+pub(crate) struct NameMaps {
+  sections: OnceLock<HashMap<String, usize>>,
+  symbols: OnceLock<SymbolMap>,
+  dynamic_symbols: OnceLock<SymbolMap>,
+}
+
+impl NameMaps {
+  pub(crate) fn memory_usage(&self) -> usize {
+    let sections = self.sections.get().map_or(0, |sections| {
+      sections.capacity() * size_of::<(String, usize)>() + sections.keys().map(String::capacity).sum::<usize>()
+    });
+    let symbols = [&self.symbols, &self.dynamic_symbols].iter().filter_map(|map| map.get()).map(SymbolMap::memory_usage).sum::<usize>();
+    sections + symbols
+  }
+}
+
+struct SymbolMap {
+  symbols: Vec<Symbol>,
+  by_name: HashMap<Arc<str>, Vec<usize>>,
+}
+
+impl SymbolMap {
+  fn new(symbols: Vec<Symbol>) -> SymbolMap {
+    let mut by_name: HashMap<Arc<str>, Vec<usize>> = HashMap::new();
+    for (index, symbol) in symbols.iter().enumerate() {
+      if !symbol.name.is_empty() {
+        by_name.entry(symbol.name.clone()).or_default().push(index);
+      }
+    }
+    SymbolMap { symbols, by_name }
+  }
+
+  fn memory_usage(&self) -> usize {
+    let raw_names: usize = self.symbols.iter().filter_map(|symbol| symbol.raw_name()).map(|name| name.len() + 2 * size_of::<usize>()).sum();
+    let indices: usize = self.by_name.values().map(|indices| indices.capacity() * size_of::<usize>()).sum();
+    self.symbols.capacity() * size_of::<Symbol>() + raw_names + self.by_name.capacity() * size_of::<(Arc<str>, Vec<usize>)>() + indices
+  }
+
+  fn indices(&self, name: &str) -> &[usize] {
+    self.by_name.get(name).map_or(&[], |indices| &indices[..])
+  }
+
+  fn defined(&self, name: &str) -> Option<&Symbol> {
+    self.indices(name).iter().map(|&index| &self.symbols[index]).find(|symbol| !symbol.is_undefined())
+  }
+}
+
+impl Elf {
+  //Drops the name maps and relocated debug sections, for callers that edited `data`,
+  //`section_headers` or `program_headers` directly. The setters and add_section do it already.
+  pub fn invalidate_caches(&mut self) {
+    self.name_maps = Default::default();
+    self.relocated_sections = Default::default();
+  }
+
+  //Takes over the decoded symbol tables of an earlier version of the file whose symbol and
+  //string tables are byte for byte the same.
+  pub(crate) fn adopt_symbol_maps(&mut self, previous: &mut Elf) {
+    self.name_maps.symbols = mem::take(&mut previous.name_maps.symbols);
+    self.name_maps.dynamic_symbols = mem::take(&mut previous.name_maps.dynamic_symbols);
+  }
+
+  pub fn section_index_by_name(&self, name: &str) -> Option<usize> {
+    let sections = self.name_maps.sections.get_or_init(|| {
+      let mut sections = HashMap::new();
+      for (index, section) in self.section_headers.iter().enumerate() {
+        if let Some(name) = self.section_name(section) {
+          sections.entry(name.to_string()).or_insert(index);
+        }
+      }
+      sections
+    });
+    //an index the headers were edited under since the map was built is looked up again
+    let named = |index: &usize| self.section_headers.get(*index).and_then(|section| self.section_name(section)) == Some(name);
+    match sections.get(name) {
+      Some(index) if named(index) => Some(*index),
+      Some(_) => (0..self.section_headers.len()).find(named),
+      None => None,
+    }
+  }
+
+  pub fn section_by_name(&self, name: &str) -> Option<&SectionHeader> {
+    self.section_index_by_name(name).and_then(|index| self.section_headers.get(index))
+  }
+
+  pub fn symbol_table(&self) -> &[Symbol] {
+    &self.symbol_map().symbols
+  }
+
+  pub fn dynamic_symbol_table(&self) -> &[Symbol] {
+    &self.dynamic_symbol_map().symbols
+  }
+
+  //indices into symbol_table()
+  pub fn symbol_indices_by_name(&self, name: &str) -> &[usize] {
+    self.symbol_map().indices(name)
+  }
+
+  //indices into dynamic_symbol_table()
+  pub fn dynamic_symbol_indices_by_name(&self, name: &str) -> &[usize] {
+    self.dynamic_symbol_map().indices(name)
+  }
+
+  //First defined symbol with that name, .symtab before .dynsym.
+  pub fn symbol_by_name(&self, name: &str) -> Option<&Symbol> {
+    self.symbol_map().defined(name).or_else(|| self.dynamic_symbol_map().defined(name))
+  }
+
+  fn symbol_map(&self) -> &SymbolMap {
+    self.name_maps.symbols.get_or_init(|| SymbolMap::new(self.symbols()))
+  }
+
+  fn dynamic_symbol_map(&self) -> &SymbolMap {
+    self.name_maps.dynamic_symbols.get_or_init(|| SymbolMap::new(self.dynamic_symbols()))
+  }
+}
+
+//Cross references between sections, segments, symbols and relocations, what an interactive
+//browser shows next to an item.
+impl Elf {
+  //index of the allocated section holding `address`
+  pub fn section_at_address(&self, address: u64) -> Option<usize> {
+    self.section_headers.iter().position(|section| {
+      section.flags & SHF_ALLOC != 0 && address >= section.address && address - section.address < section.size
+    })
+  }
+
+  //file offset of `address` in the section holding it, None in SHT_NOBITS sections
+  pub fn section_offset_of(&self, address: u64) -> Option<u64> {
+    let section = &self.section_headers[self.section_at_address(address)?];
+    if section.section_type == SHT_NOBITS {
+      return None;
+    }
+    section.offset.checked_add(address - section.address)
+  }
+
+  //program headers whose memory image starts the section
+  pub fn segments_of_section(&self, index: usize) -> Vec<usize> {
+    let section = match self.section_headers.get(index) {
+      Some(section) if section.flags & SHF_ALLOC != 0 => section,
+      _ => return Vec::new(),
+    };
+    (0..self.program_headers.len()).filter(|&position| {
+      let segment = &self.program_headers[position];
+      section.address >= segment.virtual_address && section.address - segment.virtual_address < segment.memory_size
+    }).collect()
+  }
+
+  //allocated sections starting inside the program header's memory image
+  pub fn sections_of_segment(&self, index: usize) -> Vec<usize> {
+    let segment = match self.program_headers.get(index) {
+      Some(segment) => segment,
+      None => return Vec::new(),
+    };
+    (0..self.section_headers.len()).filter(|&position| {
+      let section = &self.section_headers[position];
+      section.flags & SHF_ALLOC != 0 && section.address >= segment.virtual_address && section.address - segment.virtual_address < segment.memory_size
+    }).collect()
+  }
+
+  //Relocations naming the symbol through the symbol table they link to, plus relative ones
+  //whose addend is the address of a defined symbol.
+  pub fn symbol_references(&self, symbol: &Symbol) -> Vec<Relocation> {
+    let relative = self.relative_relocation_type();
+    let (symbols, dynamic_symbols) = (self.symbol_table(), self.dynamic_symbol_table());
+    self.relocations().into_iter().filter(|relocation| {
+      if relocation.symbol_index == 0 {
+        return !symbol.is_undefined() && Some(relocation.relocation_type) == relative && relocation.addend == Some(symbol.value as i64);
+      }
+      let table = match self.section_headers.get(relocation.section_index).and_then(|section| self.section_headers.get(section.link as usize)) {
+        Some(linked) if linked.section_type == SHT_DYNSYM => dynamic_symbols,
+        _ => symbols,
+      };
+      table.get(relocation.symbol_index as usize).is_some_and(|target| target.name == symbol.name)
+    }).collect()
+  }
+}
@@ -0,0 +1,418 @@
+use std::fmt;
+use std::io;
+use crate::consts::*;
+use crate::der::*;
+use crate::digest::{sha1, sha224, sha256, sha384, sha512};
+use crate::elf::Elf;
+use crate::rsa;
+
+pub const MODULE_SIGNATURE_MAGIC: &[u8] = b"~Module signature appended~\n";
+//struct module_signature: algo, hash, id_type, signer_len, key_id_len, 3 bytes of padding
+//and a big endian sig_len
+const MODULE_SIGNATURE_INFO_SIZE: usize = 12;
+
+const OID_SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+const OID_MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+const OID_RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+const OID_SUBJECT_KEY_IDENTIFIER: &str = "2.5.29.14";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum HashAlgorithm {
+  Sha1,
+  Sha224,
+  Sha256,
+  Sha384,
+  Sha512,
+}
+
+impl HashAlgorithm {
+  pub fn from_oid(oid: &str) -> Option<HashAlgorithm> {
+    match oid {
+      "1.3.14.3.2.26" => Some(HashAlgorithm::Sha1),
+      "2.16.840.1.101.3.4.2.4" => Some(HashAlgorithm::Sha224),
+      "2.16.840.1.101.3.4.2.1" => Some(HashAlgorithm::Sha256),
+      "2.16.840.1.101.3.4.2.2" => Some(HashAlgorithm::Sha384),
+      "2.16.840.1.101.3.4.2.3" => Some(HashAlgorithm::Sha512),
+      _ => None,
+    }
+  }
+
+  pub fn digest(self, data: &[u8]) -> Vec<u8> {
+    match self {
+      HashAlgorithm::Sha1 => sha1(data).to_vec(),
+      HashAlgorithm::Sha224 => sha224(data),
+      HashAlgorithm::Sha256 => sha256(data),
+      HashAlgorithm::Sha384 => sha384(data),
+      HashAlgorithm::Sha512 => sha512(data).to_vec(),
+    }
+  }
+
+  //the DER DigestInfo up to the digest bytes, RFC 8017 section 9.2
+  fn digest_info_prefix(self) -> &'static [u8] {
+    match self {
+      HashAlgorithm::Sha1 => &[0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14],
+      HashAlgorithm::Sha224 => &[0x30, 0x2d, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x04, 0x05, 0x00, 0x04, 0x1c],
+      HashAlgorithm::Sha256 => &[0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05, 0x00, 0x04, 0x20],
+      HashAlgorithm::Sha384 => &[0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02, 0x05, 0x00, 0x04, 0x30],
+      HashAlgorithm::Sha512 => &[0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03, 0x05, 0x00, 0x04, 0x40],
+    }
+  }
+}
+
+//An X.501 name, kept as DER for matching and decoded for display.
+#[derive(Clone, Default)]
+pub struct DistinguishedName {
+  pub der: Vec<u8>,
+  //("CN", "Build time autogenerated kernel key"), the dotted OID for unknown attributes
+  pub attributes: Vec<(String, String)>,
+}
+
+impl DistinguishedName {
+  fn parse(value: &DerValue) -> io::Result<DistinguishedName> {
+    let mut attributes = Vec::new();
+    let mut sets = value.reader();
+    while !sets.is_empty() {
+      let mut set = sets.expect(DER_SET, "name")?.reader();
+      while !set.is_empty() {
+        let mut attribute = set.expect(DER_SEQUENCE, "name attribute")?.reader();
+        let oid = oid_string(attribute.expect(DER_OID, "name attribute")?.contents);
+        let text = String::from_utf8_lossy(attribute.read()?.contents).into_owned();
+        let name = match oid.as_str() {
+          "2.5.4.3" => "CN",
+          "2.5.4.6" => "C",
+          "2.5.4.7" => "L",
+          "2.5.4.8" => "ST",
+          "2.5.4.10" => "O",
+          "2.5.4.11" => "OU",
+          "1.2.840.113549.1.9.1" => "emailAddress",
+          _ => oid.as_str(),
+        };
+        attributes.push((name.to_string(), text));
+      }
+    }
+    Ok(DistinguishedName { der: value.encoded.to_vec(), attributes })
+  }
+
+  pub fn common_name(&self) -> Option<&str> {
+    self.attributes.iter().find(|(name, _)| name == "CN").map(|(_, value)| value.as_str())
+  }
+}
+
+impl fmt::Display for DistinguishedName {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let parts: Vec<String> = self.attributes.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+    write!(f, "{}", parts.join(", "))
+  }
+}
+
+#[derive(Clone)]
+pub enum PublicKey {
+  //big endian, as encoded
+  Rsa { modulus: Vec<u8>, exponent: Vec<u8> },
+  //the dotted algorithm OID, e.g. id-ecPublicKey
+  Other { algorithm: String },
+}
+
+#[derive(Clone)]
+pub struct Certificate {
+  pub serial: Vec<u8>,
+  pub issuer: DistinguishedName,
+  pub subject: DistinguishedName,
+  pub public_key: PublicKey,
+  pub subject_key_id: Option<Vec<u8>>,
+}
+
+fn algorithm_oid(value: &DerValue) -> io::Result<String> {
+  Ok(oid_string(value.reader().expect(DER_OID, "algorithm identifier")?.contents))
+}
+
+impl Certificate {
+  pub fn from_der(der: &[u8]) -> io::Result<Certificate> {
+    let certificate = DerReader::new(der).expect(DER_SEQUENCE, "certificate")?;
+    let mut tbs = certificate.reader().expect(DER_SEQUENCE, "TBSCertificate")?.reader();
+    tbs.optional(der_context(0))?;
+    let serial = tbs.expect(DER_INTEGER, "certificate serial")?.contents.to_vec();
+    tbs.expect(DER_SEQUENCE, "certificate signature algorithm")?;
+    let issuer = DistinguishedName::parse(&tbs.expect(DER_SEQUENCE, "certificate issuer")?)?;
+    tbs.expect(DER_SEQUENCE, "certificate validity")?;
+    let subject = DistinguishedName::parse(&tbs.expect(DER_SEQUENCE, "certificate subject")?)?;
+    let mut key_info = tbs.expect(DER_SEQUENCE, "subject public key info")?.reader();
+    let algorithm = algorithm_oid(&key_info.expect(DER_SEQUENCE, "public key algorithm")?)?;
+    let key_bits = key_info.expect(DER_BIT_STRING, "public key")?.contents;
+    let public_key = if algorithm == OID_RSA_ENCRYPTION {
+      let mut key = DerReader::new(key_bits.get(1..).unwrap_or(&[])).expect(DER_SEQUENCE, "RSA public key")?.reader();
+      let modulus = key.expect(DER_INTEGER, "RSA modulus")?.contents.to_vec();
+      let exponent = key.expect(DER_INTEGER, "RSA exponent")?.contents.to_vec();
+      PublicKey::Rsa { modulus, exponent }
+    } else {
+      PublicKey::Other { algorithm }
+    };
+    let mut subject_key_id = None;
+    tbs.optional(der_context(1))?;
+    tbs.optional(der_context(2))?;
+    if let Some(extensions) = tbs.optional(der_context(3))? {
+      let mut extensions = extensions.reader().expect(DER_SEQUENCE, "certificate extensions")?.reader();
+      while !extensions.is_empty() {
+        let mut extension = extensions.expect(DER_SEQUENCE, "certificate extension")?.reader();
+        let oid = oid_string(extension.expect(DER_OID, "certificate extension")?.contents);
+        //the critical flag
+        extension.optional(0x01)?;
+        let value = extension.expect(DER_OCTET_STRING, "certificate extension value")?;
+        if oid == OID_SUBJECT_KEY_IDENTIFIER {
+          subject_key_id = Some(DerReader::new(value.contents).expect(DER_OCTET_STRING, "subject key identifier")?.contents.to_vec());
+        }
+      }
+    }
+    Ok(Certificate { serial, issuer, subject, public_key, subject_key_id })
+  }
+
+  //Every CERTIFICATE block of a PEM file, e.g. a distribution's module signing key.
+  pub fn from_pem(text: &str) -> io::Result<Vec<Certificate>> {
+    let mut certificates = Vec::new();
+    let mut block: Option<String> = None;
+    for line in text.lines().map(str::trim) {
+      match (&mut block, line) {
+        (None, "-----BEGIN CERTIFICATE-----") => block = Some(String::new()),
+        (Some(body), "-----END CERTIFICATE-----") => {
+          certificates.push(Certificate::from_der(&base64_decode(body)?)?);
+          block = None;
+        },
+        (Some(body), line) => body.push_str(line),
+        (None, _) => {},
+      };
+    }
+    Ok(certificates)
+  }
+
+  fn verifies(&self, algorithm: HashAlgorithm, message: &[u8], signature: &[u8]) -> bool {
+    match &self.public_key {
+      PublicKey::Rsa { modulus, exponent } => {
+        let mut digest_info = algorithm.digest_info_prefix().to_vec();
+        digest_info.extend_from_slice(&algorithm.digest(message));
+        rsa::verify_pkcs1(modulus, exponent, signature, &digest_info)
+      },
+      PublicKey::Other { .. } => false,
+    }
+  }
+}
+
+fn base64_decode(text: &str) -> io::Result<Vec<u8>> {
+  let mut bytes = Vec::new();
+  let mut bits = 0u32;
+  let mut count = 0;
+  for c in text.bytes().filter(|&c| c != b'=' && !c.is_ascii_whitespace()) {
+    let value = match c {
+      b'A'..=b'Z' => c - b'A',
+      b'a'..=b'z' => c - b'a' + 26,
+      b'0'..=b'9' => c - b'0' + 52,
+      b'+' => 62,
+      b'/' => 63,
+      _ => return Err(malformed("PEM, not base64")),
+    };
+    bits = bits << 6 | value as u32;
+    count += 6;
+    if count >= 8 {
+      count -= 8;
+      bytes.push((bits >> count) as u8);
+    }
+  }
+  Ok(bytes)
+}
+
+//One SignerInfo of the PKCS#7 SignedData.
+#[derive(Clone)]
+pub struct SignerInfo {
+  //issuer and serial of the signing certificate, or its subject key identifier (sign-file -k)
+  pub issuer: Option<DistinguishedName>,
+  pub serial: Option<Vec<u8>>,
+  pub subject_key_id: Option<Vec<u8>>,
+  pub digest_algorithm: String,
+  pub signature_algorithm: String,
+  //the authenticated attributes as signed, re-tagged as a SET; None for sign-file's -noattr
+  //signatures, which sign the module itself
+  pub signed_attributes: Option<Vec<u8>>,
+  //the messageDigest attribute
+  pub message_digest: Option<Vec<u8>>,
+  pub signature: Vec<u8>,
+}
+
+impl SignerInfo {
+  fn parse(value: &DerValue) -> io::Result<SignerInfo> {
+    let mut info = value.reader();
+    info.expect(DER_INTEGER, "signer info version")?;
+    let mut signer = SignerInfo {
+      issuer: None,
+      serial: None,
+      subject_key_id: None,
+      digest_algorithm: String::new(),
+      signature_algorithm: String::new(),
+      signed_attributes: None,
+      message_digest: None,
+      signature: Vec::new(),
+    };
+    let id = info.read()?;
+    match id.tag {
+      DER_SEQUENCE => {
+        let mut id = id.reader();
+        signer.issuer = Some(DistinguishedName::parse(&id.expect(DER_SEQUENCE, "signer issuer")?)?);
+        signer.serial = Some(id.expect(DER_INTEGER, "signer serial")?.contents.to_vec());
+      },
+      //[0] IMPLICIT SubjectKeyIdentifier
+      0x80 => signer.subject_key_id = Some(id.contents.to_vec()),
+      _ => return Err(malformed("signer identifier")),
+    };
+    signer.digest_algorithm = algorithm_oid(&info.expect(DER_SEQUENCE, "signer digest algorithm")?)?;
+    if let Some(attributes) = info.optional(der_context(0))? {
+      let mut reader = attributes.reader();
+      while !reader.is_empty() {
+        let mut attribute = reader.expect(DER_SEQUENCE, "authenticated attribute")?.reader();
+        let oid = oid_string(attribute.expect(DER_OID, "authenticated attribute")?.contents);
+        let values = attribute.expect(DER_SET, "authenticated attribute values")?;
+        if oid == OID_MESSAGE_DIGEST {
+          signer.message_digest = Some(values.reader().expect(DER_OCTET_STRING, "message digest")?.contents.to_vec());
+        }
+      }
+      let mut signed = attributes.encoded.to_vec();
+      signed[0] = DER_SET;
+      signer.signed_attributes = Some(signed);
+    }
+    signer.signature_algorithm = algorithm_oid(&info.expect(DER_SEQUENCE, "signer signature algorithm")?)?;
+    signer.signature = info.expect(DER_OCTET_STRING, "signature")?.contents.to_vec();
+    Ok(signer)
+  }
+
+  pub fn issued_by(&self, certificate: &Certificate) -> bool {
+    match (&self.issuer, &self.serial, &self.subject_key_id) {
+      (Some(issuer), Some(serial), _) => issuer.der == certificate.issuer.der && *serial == certificate.serial,
+      (_, _, Some(key_id)) => certificate.subject_key_id.as_ref() == Some(key_id),
+      _ => false,
+    }
+  }
+}
+
+//The signature block the kernel's sign-file appends to a module.
+#[derive(Clone)]
+pub struct ModuleSignature {
+  //PKEY_ID_PKCS7 on every kernel since 4.3, the older PGP and X.509 ids are not parsed
+  pub id_type: u8,
+  pub algorithm: u8,
+  pub hash: u8,
+  pub signer: Vec<u8>,
+  pub key_id: Vec<u8>,
+  //the raw signature, the DER of a PKCS#7 SignedData for PKEY_ID_PKCS7
+  pub signature: Vec<u8>,
+  //bytes of the file the signature covers, everything before the block
+  pub signed_size: u64,
+  pub signers: Vec<SignerInfo>,
+  //certificates carried in the SignedData, sign-file leaves them out
+  pub certificates: Vec<Certificate>,
+}
+
+fn parse_signed_data(der: &[u8]) -> io::Result<(Vec<SignerInfo>, Vec<Certificate>)> {
+  let mut content_info = DerReader::new(der).expect(DER_SEQUENCE, "PKCS#7 ContentInfo")?.reader();
+  if oid_string(content_info.expect(DER_OID, "PKCS#7 content type")?.contents) != OID_SIGNED_DATA {
+    return Err(malformed("PKCS#7, not SignedData"));
+  }
+  let explicit = content_info.expect(der_context(0), "PKCS#7 content")?;
+  let mut signed_data = explicit.reader().expect(DER_SEQUENCE, "PKCS#7 SignedData")?.reader();
+  signed_data.expect(DER_INTEGER, "SignedData version")?;
+  signed_data.expect(DER_SET, "SignedData digest algorithms")?;
+  signed_data.expect(DER_SEQUENCE, "SignedData content")?;
+  let mut certificates = Vec::new();
+  if let Some(embedded) = signed_data.optional(der_context(0))? {
+    let mut reader = embedded.reader();
+    while !reader.is_empty() {
+      certificates.push(Certificate::from_der(reader.read()?.encoded)?);
+    }
+  }
+  signed_data.optional(der_context(1))?;
+  let mut infos = signed_data.expect(DER_SET, "SignedData signer infos")?.reader();
+  let mut signers = Vec::new();
+  while !infos.is_empty() {
+    signers.push(SignerInfo::parse(&infos.expect(DER_SEQUENCE, "signer info")?)?);
+  }
+  Ok((signers, certificates))
+}
+
+impl Elf {
+  //NotFound for unsigned files, InvalidData for a block that does not fit the file.
+  pub fn module_signature(&self) -> io::Result<ModuleSignature> {
+    let data = &self.data[..];
+    if !data.ends_with(MODULE_SIGNATURE_MAGIC) {
+      return Err(io::Error::new(io::ErrorKind::NotFound, "no appended module signature"));
+    }
+    let info_end = data.len() - MODULE_SIGNATURE_MAGIC.len();
+    let info = info_end.checked_sub(MODULE_SIGNATURE_INFO_SIZE).map(|start| &data[start..info_end]).ok_or_else(|| malformed("module signature info"))?;
+    let signer_size = info[3] as usize;
+    let key_id_size = info[4] as usize;
+    let signature_size = u32::from_be_bytes([info[8], info[9], info[10], info[11]]) as usize;
+    let signed_size = (info_end - MODULE_SIGNATURE_INFO_SIZE).checked_sub(signer_size + key_id_size + signature_size)
+      .ok_or_else(|| malformed("module signature, larger than the file"))?;
+    let signer_start = signed_size;
+    let key_id_start = signer_start + signer_size;
+    let signature_start = key_id_start + key_id_size;
+    let signature = data[signature_start..signature_start + signature_size].to_vec();
+    let (signers, certificates) = match info[2] {
+      PKEY_ID_PKCS7 => parse_signed_data(&signature)?,
+      _ => (Vec::new(), Vec::new()),
+    };
+    Ok(ModuleSignature {
+      id_type: info[2],
+      algorithm: info[0],
+      hash: info[1],
+      signer: data[signer_start..key_id_start].to_vec(),
+      key_id: data[key_id_start..signature_start].to_vec(),
+      signature,
+      signed_size: signed_size as u64,
+      signers,
+      certificates,
+    })
+  }
+
+  //Checks the appended signature the way the kernel does against its keyring: a signer issued
+  //by one of `certificates`, and a valid RSA signature of the module, or of the authenticated
+  //attributes whose messageDigest is the module's digest. Returns the index of the key.
+  pub fn verify_module_signature(&self, certificates: &[Certificate]) -> io::Result<usize> {
+    let signature = self.module_signature()?;
+    if signature.id_type != PKEY_ID_PKCS7 {
+      return Err(io::Error::new(io::ErrorKind::Unsupported, format!("module signature id type {} is not PKCS#7", signature.id_type)));
+    }
+    let module = &self.data[..signature.signed_size as usize];
+    let mut reason = String::from("no signer info");
+    for signer in &signature.signers {
+      let algorithm = match HashAlgorithm::from_oid(&signer.digest_algorithm) {
+        Some(algorithm) => algorithm,
+        None => {
+          reason = format!("unsupported digest algorithm {}", signer.digest_algorithm);
+          continue;
+        },
+      };
+      let message = match (&signer.signed_attributes, &signer.message_digest) {
+        (Some(attributes), Some(digest)) if *digest == algorithm.digest(module) => &attributes[..],
+        (Some(_), _) => {
+          reason = String::from("the messageDigest attribute does not match the module");
+          continue;
+        },
+        (None, _) => module,
+      };
+      let issuers: Vec<usize> = (0..certificates.len()).filter(|&index| signer.issued_by(&certificates[index])).collect();
+      if issuers.is_empty() {
+        reason = match &signer.issuer {
+          Some(issuer) => format!("no certificate for the signer issued by {}", issuer),
+          None => String::from("no certificate with the signer's key identifier"),
+        };
+        continue;
+      }
+      for index in issuers {
+        if certificates[index].verifies(algorithm, message, &signer.signature) {
+          return Ok(index);
+        }
+        reason = match certificates[index].public_key {
+          PublicKey::Rsa { .. } => format!("the signature does not verify with {}", certificates[index].subject),
+          PublicKey::Other { ref algorithm } => format!("unsupported key algorithm {}", algorithm),
+        };
+      }
+    }
+    Err(io::Error::new(io::ErrorKind::InvalidData, reason))
+  }
+}
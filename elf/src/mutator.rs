@@ -0,0 +1,244 @@
+use std::convert::TryFrom;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::{Elf, ElfDescription, ElfHeader, ProgramHeader, SectionHeader};
+use crate::layout::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MutationKind {
+  //the file ends early
+  Truncation,
+  //an offset points past the end, at an unaligned place or makes offset + size overflow
+  OffsetBomb,
+  //a count or size larger than what the file holds
+  CountOverflow,
+  //an entry size that does not match the class or the table
+  EntrySize,
+  //a link or index to an entry that does not exist
+  BadIndex,
+  //a table that loses its terminator
+  Unterminated,
+}
+
+pub struct Mutation {
+  pub kind: MutationKind,
+  //what was broken, e.g. "e_shoff unaligned" or "section 5 (.dynsym) sh_size past the end"
+  pub label: String,
+  pub data: Vec<u8>,
+}
+
+//one field of the ELF header in its class independent form
+type HeaderField<T> = fn(&mut ElfDescription) -> &mut T;
+
+//Systematically corrupted variants of a valid file, each breaking one property, for parser
+//robustness suites.
+pub struct Mutator<'a> {
+  elf: &'a Elf,
+}
+
+impl<'a> Mutator<'a> {
+  pub fn new(elf: &'a Elf) -> Mutator<'a> {
+    Mutator { elf }
+  }
+
+  //copy of the file with the structure at `offset` read, changed by `edit` and written back,
+  //values too large for an Elf32 field are cut
+  fn patched<E: ByteOrder, R: Widen<W>, W>(&self, offset: u64, edit: impl FnOnce(&mut W)) -> Option<Vec<u8>> {
+    let mut data = self.elf.data.to_vec();
+    let raw = data.get_mut(usize::try_from(offset).ok()?..)?;
+    let mut wide = R::read::<E>(raw).ok()?.widen();
+    edit(&mut wide);
+    R::narrow(&wide).write::<E>(raw).ok()?;
+    //EI_PAD is not part of the widened header, whatever the file has there stays
+    if let Some(padding) = self.elf.data.get(9..16) {
+      data[9..16].copy_from_slice(padding);
+    }
+    Some(data)
+  }
+
+  fn push(&self, mutations: &mut Vec<Mutation>, kind: MutationKind, label: String, data: Option<Vec<u8>>) {
+    if let Some(data) = data {
+      if *data != *self.elf.data {
+        mutations.push(Mutation { kind, label, data });
+      }
+    }
+  }
+
+  pub fn mutations(&self) -> Vec<Mutation> {
+    let mut mutations = Vec::new();
+    self.truncations(&mut mutations);
+    match self.elf.header.identification.endianness {
+      1 => self.mutations_with_byteorder::<LittleEndian>(&mut mutations),
+      2 => self.mutations_with_byteorder::<BigEndian>(&mut mutations),
+      _ => panic!("unknown endianness"),
+    };
+    mutations
+  }
+
+  fn mutations_with_byteorder<E: ByteOrder>(&self, mutations: &mut Vec<Mutation>) {
+    match self.elf.header.identification.class {
+      1 => self.mutations_with_layout::<E, Elf32>(mutations),
+      2 => self.mutations_with_layout::<E, Elf64>(mutations),
+      _ => panic!("unknown class"),
+    };
+  }
+
+  fn mutations_with_layout<E: ByteOrder, L: ElfLayout>(&self, mutations: &mut Vec<Mutation>) {
+    self.header_mutations::<E, L>(mutations);
+    self.section_mutations::<E, L>(mutations);
+    self.segment_mutations::<E, L>(mutations);
+  }
+
+  fn word_max<L: ElfLayout>() -> u64 {
+    if L::CLASS == ELFCLASS32 { u32::MAX as u64 } else { u64::MAX }
+  }
+
+  fn truncations(&self, mutations: &mut Vec<Mutation>) {
+    let description = &self.elf.header.description;
+    let len = self.elf.data.len() as u64;
+    let mut cuts = vec![
+      (0, "empty file".to_string()),
+      (4, "only the magic".to_string()),
+      (16, "only e_ident".to_string()),
+      (self.elf.expected_elf_hdr_size() as u64 - 1, "ELF header cut by one byte".to_string()),
+      (len / 2, "half the file".to_string()),
+      (len.saturating_sub(1), "last byte missing".to_string()),
+    ];
+    if description.program_hdr_num > 0 {
+      cuts.push((description.program_hdr_offset + self.elf.expected_program_hdr_entry_size() as u64 / 2, "inside program header 0".to_string()));
+    }
+    if !self.elf.section_headers.is_empty() {
+      let table_end = description.section_hdr_offset + self.elf.section_hdr_stride() as u64 * self.elf.section_headers.len() as u64;
+      cuts.push((table_end.saturating_sub(1), "inside the last section header".to_string()));
+    }
+    for (index, section) in self.elf.section_headers.iter().enumerate() {
+      if section.section_type != SHT_NOBITS && section.size > 1 && section.offset + section.size <= len {
+        cuts.push((section.offset + section.size / 2, format!("inside {}", self.section_label(index))));
+      }
+    }
+    for (cut, label) in cuts {
+      if cut < len {
+        mutations.push(Mutation { kind: MutationKind::Truncation, label: format!("truncated at {:#x}, {}", cut, label), data: self.elf.data[..cut as usize].to_vec() });
+      }
+    }
+  }
+
+  fn header_mutations<E: ByteOrder, L: ElfLayout>(&self, mutations: &mut Vec<Mutation>) {
+    let description = &self.elf.header.description;
+    let len = self.elf.data.len() as u64;
+    let word_max = Mutator::word_max::<L>();
+    let header = |edit: &dyn Fn(&mut ElfDescription)| self.patched::<E, L::Header, ElfHeader>(0, |header| edit(&mut header.description));
+    let offsets: [(&str, u64, HeaderField<u64>); 2] = [
+      ("e_phoff", description.program_hdr_offset, |description| &mut description.program_hdr_offset),
+      ("e_shoff", description.section_hdr_offset, |description| &mut description.section_hdr_offset),
+    ];
+    for &(field, value, offset) in &offsets {
+      if value == 0 {
+        continue;
+      }
+      for &(new, what) in &[(len, "at the end of the file"), (word_max, "at the largest address"), (value.wrapping_add(1), "unaligned"), (1, "inside e_ident")] {
+        self.push(mutations, MutationKind::OffsetBomb, format!("{} {}", field, what), header(&|description| *offset(description) = new));
+      }
+    }
+    let counts: [(&str, HeaderField<u16>); 2] = [
+      ("e_phnum", |description| &mut description.program_hdr_num),
+      ("e_shnum", |description| &mut description.section_hdr_num),
+    ];
+    for &(field, count) in &counts {
+      self.push(mutations, MutationKind::CountOverflow, format!("{} 0xffff", field), header(&|description| *count(description) = 0xffff));
+    }
+    let entry_sizes: [(&str, HeaderField<u16>); 2] = [
+      ("e_phentsize", |description| &mut description.program_hdr_entry_size),
+      ("e_shentsize", |description| &mut description.section_hdr_entry_size),
+    ];
+    for &(field, entry_size) in &entry_sizes {
+      for &size in &[0u16, 1, 0xffff] {
+        self.push(mutations, MutationKind::EntrySize, format!("{} {}", field, size), header(&|description| *entry_size(description) = size));
+      }
+    }
+    let sections = self.elf.section_headers.len() as u16;
+    self.push(mutations, MutationKind::BadIndex, "e_shstrndx one past the last section".to_string(), header(&|description| description.section_hdr_str_index = sections));
+    self.push(mutations, MutationKind::BadIndex, "e_shstrndx SHN_XINDEX without an extended index".to_string(), header(&|description| description.section_hdr_str_index = 0xffff));
+  }
+
+  fn section_label(&self, index: usize) -> String {
+    let section = &self.elf.section_headers[index];
+    format!("section {} ({})", index, self.elf.section_name(section).unwrap_or(""))
+  }
+
+  fn section_mutations<E: ByteOrder, L: ElfLayout>(&self, mutations: &mut Vec<Mutation>) {
+    let description = &self.elf.header.description;
+    let len = self.elf.data.len() as u64;
+    let stride = self.elf.section_hdr_stride() as u64;
+    let word_max = Mutator::word_max::<L>();
+    for (index, section) in self.elf.section_headers.iter().enumerate() {
+      if section.section_type == SHT_NULL {
+        continue;
+      }
+      let entry = description.section_hdr_offset + stride * index as u64;
+      let label = self.section_label(index);
+      let header = |edit: &dyn Fn(&mut SectionHeader)| self.patched::<E, L::SectionHeader, SectionHeader>(entry, edit);
+      if section.section_type != SHT_NOBITS {
+        self.push(mutations, MutationKind::OffsetBomb, format!("{} sh_offset past the end", label), header(&|section| section.offset = len + 1));
+        self.push(mutations, MutationKind::OffsetBomb, format!("{} sh_offset + sh_size overflows", label), header(&|section| section.offset = word_max - section.size / 2));
+        self.push(mutations, MutationKind::CountOverflow, format!("{} sh_size past the end", label), header(&|section| section.size = len - section.offset.min(len) + 1));
+        self.push(mutations, MutationKind::CountOverflow, format!("{} sh_size at its maximum", label), header(&|section| section.size = word_max));
+      }
+      if section.link != 0 {
+        let sections = self.elf.section_headers.len() as u32;
+        self.push(mutations, MutationKind::BadIndex, format!("{} sh_link one past the last section", label), header(&|section| section.link = sections));
+      }
+      if section.entry_size != 0 {
+        self.push(mutations, MutationKind::EntrySize, format!("{} sh_entsize 1", label), header(&|section| section.entry_size = 1));
+        self.push(mutations, MutationKind::EntrySize, format!("{} sh_entsize larger than the section", label), header(&|section| section.entry_size = section.size + 1));
+        if section.size > section.entry_size {
+          self.push(mutations, MutationKind::EntrySize, format!("{} sh_size not a multiple of sh_entsize", label), header(&|section| section.size -= 1));
+        }
+      }
+      let contents = self.elf.section_data(section);
+      match section.section_type {
+        SHT_STRTAB if contents.last() == Some(&0) => {
+          let mut data = self.elf.data.to_vec();
+          data[(section.offset + section.size - 1) as usize] = b'A';
+          mutations.push(Mutation { kind: MutationKind::Unterminated, label: format!("{} last NUL replaced", label), data });
+        },
+        SHT_DYNAMIC => {
+          //every DT_NULL, so the table runs to the end of the section
+          let mut data = self.elf.data.to_vec();
+          let mut changed = false;
+          for position in (0..contents.len() / L::Dynamic::SIZE).map(|position| section.offset as usize + position * L::Dynamic::SIZE) {
+            let mut entry = L::Dynamic::read::<E>(&data[position..]).unwrap().widen();
+            if entry.tag == DT_NULL {
+              entry.tag = 0x7f;
+              L::Dynamic::narrow(&entry).write::<E>(&mut data[position..]).unwrap();
+              changed = true;
+            }
+          }
+          if changed {
+            mutations.push(Mutation { kind: MutationKind::Unterminated, label: format!("{} without DT_NULL", label), data });
+          }
+        },
+        _ => {},
+      }
+    }
+  }
+
+  fn segment_mutations<E: ByteOrder, L: ElfLayout>(&self, mutations: &mut Vec<Mutation>) {
+    let description = &self.elf.header.description;
+    let len = self.elf.data.len() as u64;
+    let stride = self.elf.program_hdr_stride() as u64;
+    let word_max = Mutator::word_max::<L>();
+    for (index, segment) in self.elf.program_headers.iter().enumerate() {
+      let entry = description.program_hdr_offset + stride * index as u64;
+      let label = format!("program header {} ({:#x})", index, segment.entry_type);
+      let header = |edit: &dyn Fn(&mut ProgramHeader)| self.patched::<E, L::ProgramHeader, ProgramHeader>(entry, edit);
+      //one byte past it, an empty PT_GNU_STACK at the end of the file would still be inside
+      self.push(mutations, MutationKind::OffsetBomb, format!("{} p_offset past the end", label), header(&|segment| segment.offset = len + 1));
+      self.push(mutations, MutationKind::OffsetBomb, format!("{} p_offset + p_filesz overflows", label), header(&|segment| segment.offset = word_max - segment.file_size / 2));
+      self.push(mutations, MutationKind::CountOverflow, format!("{} p_filesz at its maximum", label), header(&|segment| segment.file_size = word_max));
+      if segment.file_size > 1 {
+        self.push(mutations, MutationKind::CountOverflow, format!("{} p_memsz below p_filesz", label), header(&|segment| segment.memory_size = segment.file_size / 2));
+      }
+    }
+  }
+}
@@ -0,0 +1,152 @@
+use std::io::Cursor;
+use byteorder::{BigEndian, ReadBytesExt, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::Elf;
+
+#[derive(Default, Clone)]
+pub struct Note {
+  //index of the SHT_NOTE section, or of the PT_NOTE segment when there are no sections
+  pub source_index: usize,
+  //file offset of the note entry
+  pub offset: u64,
+  pub name: Vec<u8>,
+  pub note_type: u32,
+  pub desc: Vec<u8>,
+  //file offset of the descriptor
+  pub desc_offset: u64,
+}
+
+//One entry of an NT_GNU_PROPERTY_TYPE_0 note.
+#[derive(Clone)]
+pub struct GnuProperty {
+  pub property_type: u32,
+  pub data: Vec<u8>,
+}
+
+impl GnuProperty {
+  //the 4 byte bitmask most properties carry
+  pub fn bits(&self, big_endian: bool) -> Option<u32> {
+    let bytes = self.data.get(..4)?;
+    Some(if big_endian { BigEndian::read_u32(bytes) } else { LittleEndian::read_u32(bytes) })
+  }
+}
+
+impl Note {
+  pub fn name_str(&self) -> &str {
+    let name = match self.name.iter().position(|&b| b == 0) {
+      Some(end) => &self.name[..end],
+      None => &self.name[..],
+    };
+    std::str::from_utf8(name).unwrap_or("")
+  }
+}
+
+impl Elf {
+  pub fn notes(&self) -> Vec<Note> {
+    let sources: Vec<(usize, u64, u64, u64)> = if self.section_headers.is_empty() {
+      self.program_headers.iter().enumerate()
+        .filter(|(_, ph)| ph.entry_type == PT_NOTE)
+        .map(|(index, ph)| (index, ph.offset, ph.file_size, ph.align))
+        .collect()
+    } else {
+      self.section_headers.iter().enumerate()
+        .filter(|(_, section)| section.section_type == SHT_NOTE)
+        .map(|(index, section)| (index, section.offset, section.size, section.align))
+        .collect()
+    };
+    let mut notes = Vec::new();
+    for (source_index, offset, size, align) in sources {
+      let align = if align == 8 { 8 } else { 4 };
+      match self.header.identification.endianness {
+        1 => self.load_notes_with_byteorder::<LittleEndian>(source_index, offset, size, align, &mut notes),
+        2 => self.load_notes_with_byteorder::<BigEndian>(source_index, offset, size, align, &mut notes),
+        //the sizes of a note cannot be read in an unknown byte order
+        _ => {},
+      };
+    }
+    notes
+  }
+
+  pub fn notes_in_section(&self, name: &str) -> Vec<Note> {
+    let index = self.section_headers.iter().position(|section| self.section_name(section) == Some(name));
+    match index {
+      Some(index) => self.notes().into_iter().filter(|note| note.source_index == index).collect(),
+      None => Vec::new(),
+    }
+  }
+
+  pub fn build_id(&self) -> Option<Vec<u8>> {
+    self.notes().into_iter()
+      .find(|note| note.note_type == NT_GNU_BUILD_ID && note.name_str() == "GNU")
+      .map(|note| note.desc)
+  }
+
+  pub fn gnu_properties(&self) -> Vec<GnuProperty> {
+    match self.header.identification.endianness {
+      1 => self.gnu_properties_with_byteorder::<LittleEndian>(),
+      2 => self.gnu_properties_with_byteorder::<BigEndian>(),
+      _ => panic!("unknown endianness"),
+    }
+  }
+
+  //bitmask value of a property, None when the binary does not carry it
+  pub fn gnu_property(&self, property_type: u32) -> Option<u32> {
+    let big_endian = self.header.identification.endianness == 2;
+    self.gnu_properties().iter().find(|property| property.property_type == property_type)?.bits(big_endian)
+  }
+
+  //Property entries are padded to 8 bytes in 64-bit objects and 4 bytes in 32-bit ones.
+  fn gnu_properties_with_byteorder<E: ByteOrder>(&self) -> Vec<GnuProperty> {
+    let align = if self.header.identification.class == 2 { 8 } else { 4 };
+    let mut properties = Vec::new();
+    for note in self.notes() {
+      if note.note_type != NT_GNU_PROPERTY_TYPE_0 || note.name_str() != "GNU" {
+        continue;
+      }
+      let mut position = 0;
+      while position + 8 <= note.desc.len() {
+        let property_type = E::read_u32(&note.desc[position..]);
+        let size = E::read_u32(&note.desc[position + 4..]) as usize;
+        let data = match note.desc.get(position + 8..position + 8 + size) {
+          Some(data) => data,
+          None => break,
+        };
+        properties.push(GnuProperty { property_type, data: data.to_vec() });
+        position += 8 + size.div_ceil(align) * align;
+      }
+    }
+    properties
+  }
+
+  fn load_notes_with_byteorder<E: ByteOrder>(&self, source_index: usize, offset: u64, size: u64, align: u64, notes: &mut Vec<Note>) {
+    let data = match self.data.get(offset as usize..offset.saturating_add(size) as usize) {
+      Some(data) => data,
+      None => return,
+    };
+    let pad = |length: u64| (length + align - 1) & !(align - 1);
+    let mut cursor = Cursor::new(data);
+    while cursor.position() + 12 <= size {
+      let entry_offset = cursor.position();
+      let name_size = cursor.read_u32::<E>().unwrap() as u64;
+      let desc_size = cursor.read_u32::<E>().unwrap() as u64;
+      let note_type = cursor.read_u32::<E>().unwrap();
+      let name_start = cursor.position();
+      //the descriptor is aligned relative to the entry, which only differs from padding the
+      //name on its own for 8 byte aligned notes
+      let desc_start = entry_offset + pad(name_start - entry_offset + name_size);
+      let desc_end = desc_start + desc_size;
+      if name_start + name_size > size || desc_end > size {
+        break;
+      }
+      notes.push(Note {
+        source_index,
+        offset: offset + entry_offset,
+        name: data[name_start as usize..(name_start + name_size) as usize].to_vec(),
+        note_type,
+        desc: data[desc_start as usize..desc_end as usize].to_vec(),
+        desc_offset: offset + desc_start,
+      });
+      cursor.set_position(desc_start + pad(desc_size));
+    }
+  }
+}
@@ -0,0 +1,330 @@
+use std::io;
+use std::io::ErrorKind;
+use crate::dwarf::Reader;
+use crate::elf::Elf;
+use crate::parse_options::ParseOptions;
+
+pub const FATBIN_MAGIC: u32 = 0xba55_ed50;
+pub const CLANG_OFFLOAD_BUNDLE_MAGIC: &[u8; 24] = b"__CLANG_OFFLOAD_BUNDLE__";
+pub const COMPRESSED_OFFLOAD_BUNDLE_MAGIC: &[u8; 4] = b"CCOB";
+pub const OFFLOAD_BINARY_MAGIC: &[u8; 4] = b"\x10\xff\x10\xad";
+
+//sections host objects and executables keep device code in
+pub const OFFLOAD_SECTIONS: [&str; 4] = [".nv_fatbin", "__nv_relfatbin", ".hip_fatbin", ".llvm.offloading"];
+
+const FATBIN_KIND_PTX: u64 = 1;
+const FATBIN_KIND_ELF: u64 = 2;
+const FATBIN_FLAG_64BIT: u64 = 0x1;
+const FATBIN_FLAG_COMPRESSED: u64 = 0x2000;
+//containers in containers offload_elf_images looks into, an OffloadBinary entry holding a
+//fatbin is one level
+const MAX_NESTING_DEPTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OffloadFormat {
+  //nvcc's fatbinary: cubins and PTX per sm_ architecture
+  CudaFatbin,
+  //clang-offload-bundler, what HIP uses: one entry per target ID
+  ClangOffloadBundle,
+  //the same compressed with zstd or zlib, the entries are not readable without inflating it
+  CompressedOffloadBundle,
+  //LLVM's OffloadBinary of the new offload driver, in .llvm.offloading
+  OffloadBinary,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeviceImageKind {
+  Elf,
+  Ptx,
+  Bitcode,
+  //a container itself, see DeviceImage::nested
+  Fatbin,
+  Unknown,
+}
+
+//A device image of an offload container, a cubin, a code object or PTX for one target.
+#[derive(Clone, Debug)]
+pub struct DeviceImage<'a> {
+  pub format: OffloadFormat,
+  //the section the container is in
+  pub section: String,
+  //of the container in the section, or in the parent's data for nested images
+  pub container_offset: u64,
+  //of the image in the section, or in the parent's data
+  pub offset: u64,
+  //"cuda", "hip", "hipv4", "openmp", "host"
+  pub offload_kind: String,
+  //"nvptx64-nvidia-cuda", "amdgcn-amd-amdhsa", empty when the container does not say
+  pub triple: String,
+  //"sm_80", "gfx90a:xnack+", empty for host entries
+  pub arch: String,
+  pub kind: DeviceImageKind,
+  pub compressed: bool,
+  pub data: &'a [u8],
+}
+
+impl<'a> DeviceImage<'a> {
+  //The image as an ELF file: cubins and AMDGPU code objects are ordinary ELF files.
+  pub fn parse(&self) -> io::Result<Elf> {
+    if self.compressed {
+      return Err(io::Error::new(ErrorKind::Unsupported, format!("{} image for {} is compressed", self.offload_kind, self.arch)));
+    }
+    if self.kind != DeviceImageKind::Elf {
+      return Err(io::Error::new(ErrorKind::InvalidData, format!("{} image for {} is {:?}, not ELF", self.offload_kind, self.arch, self.kind)));
+    }
+    Elf::parse(self.data.to_vec().into_boxed_slice(), &ParseOptions::default()).map(|(elf, _)| elf)
+  }
+
+  //The images of a container image: an OffloadBinary entry holding a CUDA fatbin, or a
+  //fatbin nested in another. Images that are not strictly inside the container, an entry
+  //covering the whole of it included, are left out.
+  pub fn nested(&self) -> Vec<DeviceImage<'a>> {
+    let parent = self.data.as_ptr_range();
+    let mut images = offload_images(self.data);
+    images.retain(|image| {
+      let range = image.data.as_ptr_range();
+      image.data.len() < self.data.len() && range.start >= parent.start && range.end <= parent.end
+    });
+    for image in &mut images {
+      image.section = self.section.clone();
+    }
+    images
+  }
+}
+
+fn reader(data: &[u8], position: usize) -> Reader<'_> {
+  Reader { data, position, big_endian: false }
+}
+
+fn content_kind(data: &[u8]) -> DeviceImageKind {
+  match data {
+    [0x7f, b'E', b'L', b'F', ..] => DeviceImageKind::Elf,
+    [b'B', b'C', 0xc0, 0xde, ..] | [0xde, 0xc0, 0x17, 0x0b, ..] => DeviceImageKind::Bitcode,
+    [0x50, 0xed, 0x55, 0xba, ..] => DeviceImageKind::Fatbin,
+    _ if data.starts_with(CLANG_OFFLOAD_BUNDLE_MAGIC) || data.starts_with(OFFLOAD_BINARY_MAGIC) => DeviceImageKind::Fatbin,
+    _ if data.starts_with(b"//") || data.starts_with(b".version") => DeviceImageKind::Ptx,
+    _ => DeviceImageKind::Unknown,
+  }
+}
+
+fn c_string(data: &[u8], offset: u64) -> String {
+  let rest = data.get(offset as usize..).unwrap_or(&[]);
+  let end = rest.iter().position(|&byte| byte == 0).unwrap_or(rest.len());
+  String::from_utf8_lossy(&rest[..end]).into_owned()
+}
+
+fn image<'a>(format: OffloadFormat, container_offset: usize, offset: usize, offload_kind: &str, triple: String, arch: String, data: &'a [u8]) -> DeviceImage<'a> {
+  DeviceImage {
+    format,
+    section: String::new(),
+    container_offset: container_offset as u64,
+    offset: offset as u64,
+    offload_kind: offload_kind.to_string(),
+    triple,
+    arch,
+    kind: content_kind(data),
+    compressed: false,
+    data,
+  }
+}
+
+//A fatbinary: a 16 byte header, then entries with a 64 byte (or longer) header each. Returns
+//the end of the container.
+fn fatbin_images<'a>(data: &'a [u8], start: usize, images: &mut Vec<DeviceImage<'a>>) -> Option<usize> {
+  let mut header = reader(data, start + 4);
+  header.unsigned(2)?;
+  let header_size = header.unsigned(2)? as usize;
+  let end = (start + header_size).checked_add(header.unsigned(8)? as usize)?.min(data.len());
+  let mut position = start + header_size;
+  while position + 64 <= end {
+    let mut entry = reader(data, position);
+    let kind = entry.unsigned(2)?;
+    entry.unsigned(2)?;
+    let entry_header_size = entry.unsigned(4)? as usize;
+    let size = entry.unsigned(8)? as usize;
+    let compressed_size = entry.unsigned(4)? as usize;
+    entry.position = position + 28;
+    let arch = entry.unsigned(4)?;
+    entry.position = position + 40;
+    let flags = entry.unsigned(8)?;
+    let payload_start = position.checked_add(entry_header_size)?;
+    let compressed = flags & FATBIN_FLAG_COMPRESSED != 0;
+    let payload_size = if compressed && compressed_size > 0 { compressed_size.min(size) } else { size };
+    let mut payload = data.get(payload_start..payload_start.checked_add(payload_size)?)?;
+    if kind == FATBIN_KIND_PTX && !compressed {
+      //PTX is padded with NULs to the entry size
+      let length = payload.iter().rposition(|&byte| byte != 0).map_or(0, |last| last + 1);
+      payload = &payload[..length];
+    }
+    let triple = if flags & FATBIN_FLAG_64BIT != 0 { "nvptx64-nvidia-cuda" } else { "nvptx-nvidia-cuda" };
+    let mut device = image(OffloadFormat::CudaFatbin, start, payload_start, "cuda", triple.to_string(), format!("sm_{}", arch), payload);
+    device.compressed = compressed;
+    device.kind = match kind {
+      FATBIN_KIND_PTX => DeviceImageKind::Ptx,
+      FATBIN_KIND_ELF => DeviceImageKind::Elf,
+      _ if compressed => DeviceImageKind::Unknown,
+      _ => device.kind,
+    };
+    images.push(device);
+    position = payload_start.checked_add(size)?;
+  }
+  Some(end)
+}
+
+//"__CLANG_OFFLOAD_BUNDLE__", the entry count, then offset, size, ID length and ID of each
+//entry, the offsets from the start of the bundle. IDs are "kind-triple-target id".
+fn bundle_images<'a>(data: &'a [u8], start: usize, images: &mut Vec<DeviceImage<'a>>) -> Option<usize> {
+  let mut header = reader(data, start + CLANG_OFFLOAD_BUNDLE_MAGIC.len());
+  let count = header.unsigned(8)?;
+  let mut end = header.position;
+  for _ in 0..count {
+    let offset = start.checked_add(header.unsigned(8)? as usize)?;
+    let size = header.unsigned(8)? as usize;
+    let id_size = header.unsigned(8)? as usize;
+    let id = String::from_utf8_lossy(header.bytes(id_size)?).into_owned();
+    let payload = data.get(offset..offset.checked_add(size)?)?;
+    end = end.max(header.position).max(offset + size);
+    //the triple has four components, the target ID follows after another dash. Old IDs
+    //have three component triples: "hip-amdgcn-amd-amdhsa-gfx906".
+    let mut parts = id.splitn(6, '-');
+    let offload_kind = parts.next().unwrap_or("");
+    let mut triple: Vec<&str> = parts.by_ref().take(4).collect();
+    let mut arch = parts.next().unwrap_or("").to_string();
+    if arch.is_empty() && triple.last().is_some_and(|last| last.starts_with("gfx") || last.starts_with("sm_")) {
+      arch = triple.pop().unwrap_or("").to_string();
+    }
+    let triple = triple.join("-").trim_end_matches('-').to_string();
+    images.push(image(OffloadFormat::ClangOffloadBundle, start, offset, offload_kind, triple, arch, payload));
+  }
+  Some(end)
+}
+
+//"CCOB", version, method, then the sizes: 32-bit before version 3 (and no total size in
+//version 1), 64-bit since.
+fn compressed_bundle_image<'a>(data: &'a [u8], start: usize, images: &mut Vec<DeviceImage<'a>>) -> Option<usize> {
+  let mut header = reader(data, start + 4);
+  let version = header.unsigned(2)?;
+  header.unsigned(2)?;
+  let end = match version {
+    1 => data.len(),
+    2 => start.checked_add(header.unsigned(4)? as usize)?,
+    _ => start.checked_add(header.unsigned(8)? as usize)?,
+  }.min(data.len());
+  let mut bundle = image(OffloadFormat::CompressedOffloadBundle, start, start, "", String::new(), String::new(), &data[start..end]);
+  bundle.kind = DeviceImageKind::Fatbin;
+  bundle.compressed = true;
+  images.push(bundle);
+  Some(end)
+}
+
+//The 32 byte header (magic, version, size, entry offset and size), an entry with image and
+//offload kinds, the image and its "triple" and "arch" strings, all offsets from the start.
+fn offload_binary_images<'a>(data: &'a [u8], start: usize, images: &mut Vec<DeviceImage<'a>>) -> Option<usize> {
+  let mut header = reader(data, start + 4);
+  header.unsigned(4)?;
+  let end = start.checked_add(header.unsigned(8)? as usize)?.min(data.len());
+  let binary = &data[start..end];
+  let mut entry = reader(binary, header.unsigned(8)? as usize);
+  let image_kind = entry.unsigned(2)?;
+  let offload_kind = match entry.unsigned(2)? {
+    1 => "openmp",
+    2 => "cuda",
+    3 => "hip",
+    _ => "",
+  };
+  entry.unsigned(4)?;
+  let strings = entry.unsigned(8)? as usize;
+  let string_count = entry.unsigned(8)?;
+  let image_offset = entry.unsigned(8)? as usize;
+  let image_size = entry.unsigned(8)? as usize;
+  let (mut triple, mut arch) = (String::new(), String::new());
+  let mut table = reader(binary, strings);
+  for _ in 0..string_count {
+    let (key, value) = (table.unsigned(8)?, table.unsigned(8)?);
+    match c_string(binary, key).as_str() {
+      "triple" => triple = c_string(binary, value),
+      "arch" => arch = c_string(binary, value),
+      _ => {},
+    };
+  }
+  let payload = binary.get(image_offset..image_offset.checked_add(image_size)?)?;
+  let mut device = image(OffloadFormat::OffloadBinary, start, start + image_offset, offload_kind, triple, arch, payload);
+  device.kind = match image_kind {
+    1 | 3 => DeviceImageKind::Elf,
+    2 => DeviceImageKind::Bitcode,
+    4 => DeviceImageKind::Fatbin,
+    5 => DeviceImageKind::Ptx,
+    _ => device.kind,
+  };
+  images.push(device);
+  Some(end)
+}
+
+//Every container in the data: linkers concatenate one per translation unit, aligned, so the
+//next one is searched for after each.
+fn offload_images(data: &[u8]) -> Vec<DeviceImage<'_>> {
+  let mut images = Vec::new();
+  let mut position = 0;
+  while position + 4 <= data.len() {
+    let rest = &data[position..];
+    let end = if reader(rest, 0).unsigned(4) == Some(FATBIN_MAGIC as u64) {
+      fatbin_images(data, position, &mut images)
+    } else if rest.starts_with(CLANG_OFFLOAD_BUNDLE_MAGIC) {
+      bundle_images(data, position, &mut images)
+    } else if rest.starts_with(COMPRESSED_OFFLOAD_BUNDLE_MAGIC) {
+      compressed_bundle_image(data, position, &mut images)
+    } else if rest.starts_with(OFFLOAD_BINARY_MAGIC) {
+      offload_binary_images(data, position, &mut images)
+    } else {
+      position += 4;
+      continue;
+    };
+    //a truncated container ends the section
+    match end {
+      Some(end) if end > position => position = end.next_multiple_of(4),
+      _ => break,
+    };
+  }
+  images
+}
+
+impl Elf {
+  //The device images of the GPU offload containers in the file's OFFLOAD_SECTIONS: CUDA
+  //fatbins, HIP offload bundles and LLVM offload binaries. Host entries of bundles, which
+  //are empty in executables, are included.
+  pub fn offload_images(&self) -> Vec<DeviceImage<'_>> {
+    let mut images = Vec::new();
+    for section in &self.section_headers {
+      let name = match self.section_name(section) {
+        Some(name) if OFFLOAD_SECTIONS.contains(&name) => name,
+        _ => continue,
+      };
+      for mut image in offload_images(self.section_data(section)) {
+        image.section = name.to_string();
+        images.push(image);
+      }
+    }
+    images
+  }
+
+  //The device images that are ELF files, those in nested containers included, parsed:
+  //cuobjdump -xelf and clang-offload-bundler -unbundle without the tools.
+  pub fn offload_elf_images(&self) -> Vec<(DeviceImage<'_>, io::Result<Elf>)> {
+    let mut pending: Vec<(DeviceImage<'_>, usize)> = self.offload_images().into_iter().map(|image| (image, 0)).collect();
+    let mut images = Vec::new();
+    while let Some((image, depth)) = pending.pop() {
+      match image.kind {
+        DeviceImageKind::Fatbin if !image.compressed && depth < MAX_NESTING_DEPTH => {
+          pending.extend(image.nested().into_iter().map(|nested| (nested, depth + 1)));
+        },
+        DeviceImageKind::Elf if !image.data.is_empty() => {
+          let elf = image.parse();
+          images.push((image, elf));
+        },
+        _ => {},
+      };
+    }
+    images.sort_by_key(|(image, _)| (image.section.clone(), image.container_offset, image.offset));
+    images
+  }
+}
@@ -0,0 +1,45 @@
+use crate::entry_table::EntryTable;
+
+//Decodes a table of fixed-size entries, in parallel chunks when the rayon feature is enabled.
+#[cfg(feature = "rayon")]
+pub(crate) fn map_entries<T, F>(table: EntryTable<'_>, decode: F) -> Vec<T>
+where
+  T: Send,
+  F: Fn(&[u8]) -> T + Sync + Send,
+{
+  use rayon::prelude::*;
+  //below this the thread pool costs more than it saves
+  const PARALLEL_THRESHOLD: usize = 4096;
+  if table.len() < PARALLEL_THRESHOLD {
+    return table.iter().map(decode).collect();
+  }
+  (0..table.len()).into_par_iter().filter_map(|index| table.get(index)).map(decode).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn map_entries<T, F>(table: EntryTable<'_>, decode: F) -> Vec<T>
+where
+  F: Fn(&[u8]) -> T,
+{
+  table.iter().map(decode).collect()
+}
+
+//Keeps the items `keep` accepts, in order, testing them in parallel when the rayon feature
+//is enabled.
+#[cfg(feature = "rayon")]
+pub(crate) fn filter_items<T, F>(items: Vec<T>, keep: F) -> Vec<T>
+where
+  T: Send,
+  F: Fn(&T) -> bool + Sync + Send,
+{
+  use rayon::prelude::*;
+  items.into_par_iter().filter(|item| keep(item)).collect()
+}
+
+#[cfg(not(feature = "rayon"))]
+pub(crate) fn filter_items<T, F>(items: Vec<T>, keep: F) -> Vec<T>
+where
+  F: Fn(&T) -> bool,
+{
+  items.into_iter().filter(|item| keep(item)).collect()
+}
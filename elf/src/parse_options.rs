@@ -0,0 +1,125 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+use crate::elf::Elf;
+use crate::header::ObjectType;
+use crate::interner::Interner;
+use crate::layout::{Elf32, Elf64, ElfLayout, RawLayout};
+
+//What to do with values outside the ones the specifications define.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UnknownValuePolicy {
+  //refuse the file, what a loader wants
+  Error,
+  //keep the raw value and return it next to the parsed file
+  Warn,
+  //keep the raw value quietly, what an analyst poking at odd files wants
+  Preserve,
+}
+
+#[derive(Clone, Debug)]
+pub struct ParseOptions {
+  pub unknown_values: UnknownValuePolicy,
+}
+
+impl Default for ParseOptions {
+  fn default() -> ParseOptions {
+    ParseOptions { unknown_values: UnknownValuePolicy::Preserve }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnknownValue {
+  //e_machine, e_type, EI_OSABI or sh_type
+  pub field: &'static str,
+  pub value: u64,
+  pub section_index: Option<usize>,
+}
+
+impl fmt::Display for UnknownValue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.section_index {
+      Some(index) => write!(f, "unknown {} {:#x} in section {}", self.field, self.value, index),
+      None => write!(f, "unknown {} {:#x}", self.field, self.value),
+    }
+  }
+}
+
+//registered in the gABI machine table, the reserved gaps excluded
+fn is_known_machine(machine: u16) -> bool {
+  matches!(machine, 0..=10 | 15 | 17..=23 | 36..=120 | 131..=144 | 160..=181 | 183..=227 | 243 | 244 | 247 | 252 | 258)
+}
+
+fn is_known_os_abi(os_abi: u8) -> bool {
+  //ELFOSABI_NONE to ELFOSABI_OPENVOS, ARM_AEABI, ARM and STANDALONE
+  matches!(os_abi, 0..=18 | 64 | 97 | 255)
+}
+
+fn is_known_section_type(section_type: u32) -> bool {
+  //everything from SHT_LOOS up falls in the OS, processor or user ranges
+  matches!(section_type, 0..=11 | 14..=19 | 0x60000000..=0xffffffff)
+}
+
+impl Elf {
+  //Values the specifications do not define, the raw values stay in the parsed structures.
+  pub fn unknown_values(&self) -> Vec<UnknownValue> {
+    let mut unknown = Vec::new();
+    let description = &self.header.description;
+    if !is_known_machine(description.machine) {
+      unknown.push(UnknownValue { field: "e_machine", value: description.machine as u64, section_index: None });
+    }
+    if let ObjectType::Unknown(_) = ObjectType::from_raw(description.obj_type) {
+      unknown.push(UnknownValue { field: "e_type", value: description.obj_type as u64, section_index: None });
+    }
+    if !is_known_os_abi(self.header.identification.os_abi) {
+      unknown.push(UnknownValue { field: "EI_OSABI", value: self.header.identification.os_abi as u64, section_index: None });
+    }
+    for (index, section) in self.section_headers.iter().enumerate() {
+      if !is_known_section_type(section.section_type) {
+        unknown.push(UnknownValue { field: "sh_type", value: section.section_type as u64, section_index: Some(index) });
+      }
+    }
+    unknown
+  }
+
+  //Elf::new without the panics: a class or byte order the parser cannot read, and a file too
+  //short for its header, are errors whatever the policy. The returned list is empty unless
+  //the policy is Warn.
+  pub fn parse(data: Box<[u8]>, options: &ParseOptions) -> io::Result<(Elf, Vec<UnknownValue>)> {
+    Elf::parse_with_interner(data, options, Arc::new(Interner::new()))
+  }
+
+  //parse, interning names in `interner`
+  pub fn parse_with_interner(data: Box<[u8]>, options: &ParseOptions, interner: Arc<Interner>) -> io::Result<(Elf, Vec<UnknownValue>)> {
+    if data.len() < 16 || data[0..4] != [0x7f, b'E', b'L', b'F'] {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "not an ELF file"));
+    }
+    let header_size = match data[4] {
+      1 => <Elf32 as ElfLayout>::Header::SIZE,
+      2 => <Elf64 as ElfLayout>::Header::SIZE,
+      class => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown class {}", class))),
+    };
+    if data[5] != 1 && data[5] != 2 {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown endianness {}", data[5])));
+    }
+    if data.len() < header_size {
+      return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated ELF header"));
+    }
+    let elf = Elf::with_interner(data, interner);
+    let unknown = elf.unknown_values();
+    match options.unknown_values {
+      UnknownValuePolicy::Error if !unknown.is_empty() => {
+        let messages: Vec<String> = unknown.iter().map(|value| value.to_string()).collect();
+        Err(io::Error::new(io::ErrorKind::InvalidData, messages.join(", ")))
+      },
+      UnknownValuePolicy::Warn => Ok((elf, unknown)),
+      _ => Ok((elf, Vec::new())),
+    }
+  }
+
+  pub fn open_with_options<P: AsRef<Path>>(path: P, options: &ParseOptions) -> io::Result<(Elf, Vec<UnknownValue>)> {
+    Elf::parse(fs::read(path)?.into_boxed_slice(), options)
+  }
+}
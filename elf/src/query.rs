@@ -0,0 +1,479 @@
+//Filter expressions over ELF files, and a scanner that evaluates one over a directory tree:
+//
+//  machine == aarch64 && !pie && needs("libssl.so.1.1")
+//
+//Terms are boolean properties (pie, nx, relro, full_relro, canary, fortified, bind_now,
+//stripped, debug, static), comparisons of a property with a word, number or "string"
+//(machine, class, endian, type, size, sections, interpreter, soname, build_id, with ==, !=,
+//<, <=, > and >=), and tests of one "string" argument: needs, imports, exports, defines,
+//section and rpath. Strings compared or passed may contain * and ? wildcards. Terms combine
+//with !, && and || (in that order of precedence) and parentheses.
+use std::cell::OnceCell;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{ErrorKind, Read};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::parallel::filter_items;
+use crate::parse_options::ParseOptions;
+use crate::security::{Relro, SecurityReport};
+
+const BOOLEAN_PROPERTIES: &[&str] = &["pie", "nx", "relro", "full_relro", "canary", "fortified", "bind_now", "stripped", "debug", "static"];
+const VALUE_PROPERTIES: &[&str] = &["machine", "class", "endian", "type", "size", "sections", "interpreter", "soname", "build_id"];
+const FUNCTIONS: &[&str] = &["needs", "imports", "exports", "defines", "section", "rpath"];
+//parentheses, negations and operands of a chain of && or || an expression may nest, beyond
+//this parsing, evaluating and dropping it would recurse deep enough to overflow the stack
+const MAX_DEPTH: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+  Equal,
+  NotEqual,
+  Less,
+  LessOrEqual,
+  Greater,
+  GreaterOrEqual,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Literal {
+  Number(u64),
+  //a bare word or a quoted string
+  Text(String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expression {
+  And(Box<Expression>, Box<Expression>),
+  Or(Box<Expression>, Box<Expression>),
+  Not(Box<Expression>),
+  Property(String),
+  Compare(String, Comparison, Literal),
+  Call(String, String),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+  Word(String),
+  Text(String),
+  And,
+  Or,
+  Not,
+  Compare(Comparison),
+  Open,
+  Close,
+}
+
+fn syntax_error(position: usize, message: &str) -> io::Error {
+  io::Error::new(ErrorKind::InvalidInput, format!("{} at {}", message, position))
+}
+
+fn tokenize(text: &str) -> io::Result<Vec<(usize, Token)>> {
+  let bytes = text.as_bytes();
+  let mut tokens = Vec::new();
+  let mut position = 0;
+  while position < bytes.len() {
+    let start = position;
+    let two = bytes.get(position..position + 2).unwrap_or(&[]);
+    let token = match bytes[position] {
+      byte if byte.is_ascii_whitespace() => {
+        position += 1;
+        continue;
+      },
+      _ if two == b"&&" => Token::And,
+      _ if two == b"||" => Token::Or,
+      _ if two == b"==" => Token::Compare(Comparison::Equal),
+      _ if two == b"!=" => Token::Compare(Comparison::NotEqual),
+      _ if two == b"<=" => Token::Compare(Comparison::LessOrEqual),
+      _ if two == b">=" => Token::Compare(Comparison::GreaterOrEqual),
+      b'<' => Token::Compare(Comparison::Less),
+      b'>' => Token::Compare(Comparison::Greater),
+      b'!' => Token::Not,
+      b'(' => Token::Open,
+      b')' => Token::Close,
+      b'"' => {
+        let mut value = String::new();
+        let mut chars = text[position + 1..].char_indices();
+        loop {
+          match chars.next() {
+            Some((at, '"')) => {
+              position += at + 2;
+              break;
+            },
+            Some((_, '\\')) => match chars.next() {
+              Some((_, escaped)) => value.push(escaped),
+              None => return Err(syntax_error(start, "unterminated string")),
+            },
+            Some((_, character)) => value.push(character),
+            None => return Err(syntax_error(start, "unterminated string")),
+          };
+        }
+        tokens.push((start, Token::Text(value)));
+        continue;
+      },
+      byte if byte.is_ascii_alphanumeric() || b"_.-+:/*?".contains(&byte) => {
+        let length = text[position..].find(|character: char| !(character.is_ascii_alphanumeric() || "_.-+:/*?".contains(character)))
+          .unwrap_or(text.len() - position);
+        tokens.push((start, Token::Word(text[position..position + length].to_string())));
+        position += length;
+        continue;
+      },
+      _ => return Err(syntax_error(start, "unexpected character")),
+    };
+    position += match token {
+      Token::Not | Token::Open | Token::Close | Token::Compare(Comparison::Less) | Token::Compare(Comparison::Greater) => 1,
+      _ => 2,
+    };
+    tokens.push((start, token));
+  }
+  Ok(tokens)
+}
+
+struct Parser {
+  tokens: Vec<(usize, Token)>,
+  position: usize,
+  end: usize,
+  depth: usize,
+}
+
+impl Parser {
+  fn peek(&self) -> Option<&Token> {
+    self.tokens.get(self.position).map(|(_, token)| token)
+  }
+
+  fn offset(&self) -> usize {
+    self.tokens.get(self.position).map_or(self.end, |(offset, _)| *offset)
+  }
+
+  fn next(&mut self) -> Option<Token> {
+    let token = self.peek().cloned();
+    self.position += 1;
+    token
+  }
+
+  fn descend(&mut self, offset: usize) -> io::Result<()> {
+    self.depth += 1;
+    if self.depth > MAX_DEPTH {
+      return Err(syntax_error(offset, "expression nested too deeply"));
+    }
+    Ok(())
+  }
+
+  fn or(&mut self) -> io::Result<Expression> {
+    let depth = self.depth;
+    let mut left = self.and()?;
+    while self.peek() == Some(&Token::Or) {
+      self.descend(self.offset())?;
+      self.position += 1;
+      left = Expression::Or(Box::new(left), Box::new(self.and()?));
+    }
+    self.depth = depth;
+    Ok(left)
+  }
+
+  fn and(&mut self) -> io::Result<Expression> {
+    let depth = self.depth;
+    let mut left = self.unary()?;
+    while self.peek() == Some(&Token::And) {
+      self.descend(self.offset())?;
+      self.position += 1;
+      left = Expression::And(Box::new(left), Box::new(self.unary()?));
+    }
+    self.depth = depth;
+    Ok(left)
+  }
+
+  fn unary(&mut self) -> io::Result<Expression> {
+    let offset = self.offset();
+    let depth = self.depth;
+    let expression = match self.next() {
+      Some(Token::Not) => {
+        self.descend(offset)?;
+        Ok(Expression::Not(Box::new(self.unary()?)))
+      },
+      Some(Token::Open) => {
+        self.descend(offset)?;
+        let inner = self.or()?;
+        match self.next() {
+          Some(Token::Close) => Ok(inner),
+          _ => Err(syntax_error(self.offset(), "expected )")),
+        }
+      },
+      Some(Token::Word(name)) => self.term(offset, name),
+      Some(_) => Err(syntax_error(offset, "expected a property")),
+      None => Err(syntax_error(offset, "unexpected end")),
+    };
+    self.depth = depth;
+    expression
+  }
+
+  fn term(&mut self, offset: usize, name: String) -> io::Result<Expression> {
+    match self.peek().cloned() {
+      Some(Token::Open) if FUNCTIONS.contains(&name.as_str()) => {
+        self.position += 1;
+        let argument = match self.next() {
+          Some(Token::Text(argument)) | Some(Token::Word(argument)) => argument,
+          _ => return Err(syntax_error(self.offset(), "expected an argument")),
+        };
+        match self.next() {
+          Some(Token::Close) => Ok(Expression::Call(name, argument)),
+          _ => Err(syntax_error(self.offset(), "expected )")),
+        }
+      },
+      Some(Token::Compare(comparison)) if VALUE_PROPERTIES.contains(&name.as_str()) || BOOLEAN_PROPERTIES.contains(&name.as_str()) => {
+        self.position += 1;
+        let literal = match self.next() {
+          Some(Token::Word(word)) => match parse_number(&word) {
+            Some(number) => Literal::Number(number),
+            None => Literal::Text(word),
+          },
+          Some(Token::Text(text)) => Literal::Text(text),
+          _ => return Err(syntax_error(self.offset(), "expected a value")),
+        };
+        Ok(Expression::Compare(name, comparison, literal))
+      },
+      _ if BOOLEAN_PROPERTIES.contains(&name.as_str()) => Ok(Expression::Property(name)),
+      _ if VALUE_PROPERTIES.contains(&name.as_str()) => Err(syntax_error(offset, &format!("{} needs a comparison", name))),
+      _ if FUNCTIONS.contains(&name.as_str()) => Err(syntax_error(offset, &format!("{} needs an argument", name))),
+      _ => Err(syntax_error(offset, &format!("unknown property {}", name))),
+    }
+  }
+}
+
+fn parse_number(word: &str) -> Option<u64> {
+  match word.strip_prefix("0x") {
+    Some(hex) => u64::from_str_radix(hex, 16).ok(),
+    None => word.parse().ok(),
+  }
+}
+
+//* matches any run of characters, ? any one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+  let (pattern, text): (Vec<char>, Vec<char>) = (pattern.chars().collect(), text.chars().collect());
+  let (mut p, mut t) = (0, 0);
+  let mut backtrack: Option<(usize, usize)> = None;
+  while t < text.len() {
+    match pattern.get(p) {
+      Some('*') => {
+        backtrack = Some((p, t));
+        p += 1;
+      },
+      Some(&character) if character == '?' || character == text[t] => {
+        p += 1;
+        t += 1;
+      },
+      _ => match backtrack {
+        Some((star, position)) => {
+          p = star + 1;
+          t = position + 1;
+          backtrack = Some((star, position + 1));
+        },
+        None => return false,
+      },
+    };
+  }
+  pattern[p..].iter().all(|&character| character == '*')
+}
+
+//names a machine answers to, the first as uname -m has it
+fn machine_names(machine: u16, class: u8) -> &'static [&'static str] {
+  match (machine, class) {
+    (EM_X86_64, _) => &["x86_64", "amd64", "x86-64"],
+    (EM_386, _) => &["i386", "i686", "x86"],
+    (EM_AARCH64, _) => &["aarch64", "arm64"],
+    (EM_ARM, _) => &["arm"],
+    (EM_RISCV, 1) => &["riscv32", "riscv"],
+    (EM_RISCV, _) => &["riscv64", "riscv"],
+    (EM_MIPS, 1) => &["mips"],
+    (EM_MIPS, _) => &["mips64", "mips"],
+    (EM_PPC, _) => &["ppc", "powerpc"],
+    (EM_PPC64, _) => &["ppc64", "powerpc64"],
+    (EM_S390, _) => &["s390x", "s390"],
+    (EM_SPARCV9, _) => &["sparc64", "sparcv9"],
+    (EM_LOONGARCH, _) => &["loongarch64", "loongarch"],
+    _ => &[],
+  }
+}
+
+fn symbol_matches(pattern: &str, name: &str) -> bool {
+  glob_match(pattern, name) || name.split_once('@').is_some_and(|(base, _)| glob_match(pattern, base))
+}
+
+fn compare_numbers(value: u64, comparison: Comparison, literal: &Literal) -> bool {
+  let expected = match literal {
+    Literal::Number(number) => *number,
+    Literal::Text(_) => return false,
+  };
+  match comparison {
+    Comparison::Equal => value == expected,
+    Comparison::NotEqual => value != expected,
+    Comparison::Less => value < expected,
+    Comparison::LessOrEqual => value <= expected,
+    Comparison::Greater => value > expected,
+    Comparison::GreaterOrEqual => value >= expected,
+  }
+}
+
+//Strings are only equal or not; ordering comparisons of strings are false.
+fn compare_texts(values: &[String], comparison: Comparison, literal: &Literal) -> bool {
+  let pattern = match literal {
+    Literal::Number(number) => number.to_string(),
+    Literal::Text(text) => text.clone(),
+  };
+  let equal = values.iter().any(|value| glob_match(&pattern, value));
+  match comparison {
+    Comparison::Equal => equal,
+    Comparison::NotEqual => !equal,
+    _ => false,
+  }
+}
+
+//The file a query is evaluated on, with what several properties are read from computed once.
+struct Subject<'a> {
+  elf: &'a Elf,
+  security: OnceCell<SecurityReport>,
+}
+
+impl Subject<'_> {
+  fn security(&self) -> &SecurityReport {
+    self.security.get_or_init(|| self.elf.security_report())
+  }
+}
+
+fn boolean_property(subject: &Subject<'_>, name: &str) -> bool {
+  let elf = subject.elf;
+  match name {
+    "pie" => subject.security().pie,
+    "nx" => subject.security().nx,
+    "relro" => subject.security().relro != Relro::None,
+    "full_relro" => subject.security().relro == Relro::Full,
+    "canary" => subject.security().stack_canary,
+    "fortified" => !subject.security().fortify.fortified.is_empty(),
+    "bind_now" => elf.bind_now(),
+    "stripped" => !elf.section_headers.iter().any(|section| section.section_type == SHT_SYMTAB),
+    "debug" => elf.section_by_name(".debug_info").is_some(),
+    "static" => !elf.program_headers.iter().any(|ph| ph.entry_type == PT_INTERP || ph.entry_type == PT_DYNAMIC),
+    _ => false,
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Query {
+  pub expression: Expression,
+}
+
+impl Query {
+  pub fn parse(text: &str) -> io::Result<Query> {
+    let mut parser = Parser { tokens: tokenize(text)?, position: 0, end: text.len(), depth: 0 };
+    let expression = parser.or()?;
+    if parser.position < parser.tokens.len() {
+      return Err(syntax_error(parser.offset(), "unexpected token"));
+    }
+    Ok(Query { expression })
+  }
+
+  pub fn matches(&self, elf: &Elf) -> bool {
+    evaluate(&self.expression, &Subject { elf, security: OnceCell::new() })
+  }
+
+  //Every ELF file under `root` the query matches, sorted. The tree is walked like
+  //BuildIdIndex::scan: symbolic links are not followed and unreadable entries are skipped.
+  //The files are read and tested in parallel with the rayon feature, a file the library
+  //panics on is skipped instead of ending the scan.
+  pub fn scan<P: AsRef<Path>>(&self, root: P) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.as_ref().to_path_buf()];
+    let mut first = true;
+    while let Some(directory) = pending.pop() {
+      let entries = match fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(err) if first => return Err(err),
+        Err(_) => continue,
+      };
+      first = false;
+      for entry in entries.flatten() {
+        let path = entry.path();
+        match fs::symlink_metadata(&path) {
+          Ok(metadata) if metadata.is_dir() => pending.push(path),
+          Ok(metadata) if metadata.is_file() && metadata.len() >= 52 => files.push(path),
+          _ => {},
+        };
+      }
+    }
+    let mut matches = filter_items(files, |path| panic::catch_unwind(AssertUnwindSafe(|| self.matches_file(path))).unwrap_or(false));
+    matches.sort();
+    Ok(matches)
+  }
+
+  //false for files that are not ELF or cannot be read
+  pub fn matches_file<P: AsRef<Path>>(&self, path: P) -> bool {
+    let path = path.as_ref();
+    let mut magic = [0u8; 4];
+    if File::open(path).and_then(|mut file| file.read_exact(&mut magic)).is_err() || magic != [0x7f, b'E', b'L', b'F'] {
+      return false;
+    }
+    match fs::read(path).and_then(|data| Elf::parse(data.into_boxed_slice(), &ParseOptions::default())) {
+      Ok((elf, _)) => self.matches(&elf),
+      Err(_) => false,
+    }
+  }
+}
+
+fn evaluate(expression: &Expression, subject: &Subject<'_>) -> bool {
+  let elf = subject.elf;
+  match expression {
+    Expression::And(left, right) => evaluate(left, subject) && evaluate(right, subject),
+    Expression::Or(left, right) => evaluate(left, subject) || evaluate(right, subject),
+    Expression::Not(inner) => !evaluate(inner, subject),
+    Expression::Property(name) => boolean_property(subject, name),
+    Expression::Compare(name, comparison, literal) => {
+      let identification = &elf.header.identification;
+      let texts = |values: Vec<String>| compare_texts(&values, *comparison, literal);
+      match name.as_str() {
+        "machine" => match literal {
+          Literal::Number(_) => compare_numbers(elf.header.description.machine as u64, *comparison, literal),
+          Literal::Text(_) => texts(machine_names(elf.header.description.machine, identification.class).iter().map(|name| name.to_string()).collect()),
+        },
+        "class" => compare_numbers(if identification.class == ELFCLASS64 { 64 } else { 32 }, *comparison, literal),
+        "endian" => texts(vec![String::from(if identification.endianness == 2 { "big" } else { "little" })]),
+        "type" => texts(match elf.header.description.obj_type {
+          ET_REL => vec![String::from("rel")],
+          ET_EXEC => vec![String::from("exec")],
+          ET_DYN => vec![String::from("dyn")],
+          ET_CORE => vec![String::from("core")],
+          other => vec![other.to_string()],
+        }),
+        "size" => compare_numbers(elf.data.len() as u64, *comparison, literal),
+        "sections" => compare_numbers(elf.section_headers.len() as u64, *comparison, literal),
+        "interpreter" => texts(elf.interpreter().into_iter().collect()),
+        "soname" => texts(elf.soname().into_iter().collect()),
+        "build_id" => texts(elf.build_id().map(|id| id.iter().map(|byte| format!("{:02x}", byte)).collect()).into_iter().collect()),
+        _ => {
+          let value = boolean_property(subject, name) as u64;
+          match literal {
+            Literal::Text(text) if text == "true" || text == "false" => compare_numbers(value, *comparison, &Literal::Number((text == "true") as u64)),
+            _ => compare_numbers(value, *comparison, literal),
+          }
+        },
+      }
+    },
+    Expression::Call(name, argument) => match name.as_str() {
+      "needs" => elf.needed_libraries().iter().any(|library| glob_match(argument, library)),
+      "imports" => elf.imports().iter().any(|symbol| symbol_matches(argument, &symbol.name)),
+      "exports" => elf.exports().iter().any(|symbol| symbol_matches(argument, &symbol.name)),
+      "defines" => elf.symbols().iter().chain(elf.exports().iter())
+        .any(|symbol| !symbol.is_undefined() && symbol_matches(argument, &symbol.name)),
+      "section" => elf.section_headers.iter().any(|section| elf.section_name(section).is_some_and(|name| glob_match(argument, name))),
+      "rpath" => elf.rpath().iter().chain(elf.runpath().iter()).any(|path| glob_match(argument, path)),
+      _ => false,
+    },
+  }
+}
+
+//Parses `expression` and scans `root` with it.
+pub fn scan<P: AsRef<Path>>(root: P, expression: &str) -> io::Result<Vec<PathBuf>> {
+  Query::parse(expression)?.scan(root)
+}
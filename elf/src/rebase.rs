@@ -0,0 +1,100 @@
+use std::io;
+use std::ops::Range;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::Elf;
+
+impl Elf {
+  //Applies every relative relocation, SHT_RELR ones included, as if the lowest PT_LOAD had
+  //been mapped at `new_base`. Already rebased images can be rebased again, the previous bias
+  //is taken into account.
+  pub fn rebase(&mut self, new_base: u64) -> io::Result<usize> {
+    if self.header.description.obj_type != ET_DYN {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "only ET_DYN objects can be rebased"));
+    }
+    let relative_type = self.relative_relocation_type()
+      .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "no relative relocation type for this machine"))?;
+    let lowest_address = self.program_headers.iter()
+      .filter(|ph| ph.entry_type == PT_LOAD)
+      .map(|ph| ph.virtual_address & !(ph.align.max(1) - 1))
+      .min()
+      .unwrap_or(0);
+    let bias = new_base.wrapping_sub(lowest_address);
+    let applied = match self.header.identification.endianness {
+      1 => self.rebase_with_byteorder::<LittleEndian>(relative_type, bias)?,
+      2 => self.rebase_with_byteorder::<BigEndian>(relative_type, bias)?,
+      endianness => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown endianness {}", endianness))),
+    };
+    self.bias = bias;
+    Ok(applied)
+  }
+
+  fn rebase_with_byteorder<E: ByteOrder>(&mut self, relative_type: u32, bias: u64) -> io::Result<usize> {
+    let word_size = match self.header.identification.class {
+      1 => 4,
+      2 => 8,
+      class => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unknown class {}", class))),
+    };
+    let mut targets = Vec::new();
+    for relocation in self.relocations() {
+      if relocation.relocation_type == relative_type {
+        targets.push((self.relocation_target(relocation.offset, word_size)?, relocation.addend));
+      }
+    }
+    for address in self.relr_addresses::<E>(word_size)? {
+      targets.push((self.relocation_target(address, word_size)?, None));
+    }
+    let previous_bias = self.bias;
+    for (range, addend) in &targets {
+      let word = &mut self.data[range.clone()];
+      if word_size == 4 {
+        let value = match addend {
+          Some(addend) => bias.wrapping_add(*addend as u64),
+          None => (E::read_u32(word) as u64).wrapping_sub(previous_bias).wrapping_add(bias),
+        };
+        E::write_u32(word, value as u32);
+      } else {
+        let value = match addend {
+          Some(addend) => bias.wrapping_add(*addend as u64),
+          None => E::read_u64(word).wrapping_sub(previous_bias).wrapping_add(bias),
+        };
+        E::write_u64(word, value);
+      }
+    }
+    Ok(targets.len())
+  }
+
+  //the bytes of the word at `address`, all of them have to be in the file
+  fn relocation_target(&self, address: u64, word_size: usize) -> io::Result<Range<usize>> {
+    self.address_to_offset(address)
+      .and_then(|offset| Some(offset as usize..(offset as usize).checked_add(word_size)?))
+      .filter(|range| range.end <= self.data.len())
+      .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("relocation target {:#x} is not backed by the file", address)))
+  }
+
+  //SHT_RELR packs relative relocations into addresses, each followed by bitmaps marking which
+  //of the next 31 or 63 words are relocated too. Their addends are at the targets, as for SHT_REL.
+  fn relr_addresses<E: ByteOrder>(&self, word_size: usize) -> io::Result<Vec<u64>> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "SHT_RELR bitmap without a valid address before it");
+    let bits = word_size as u64 * 8 - 1;
+    let mut addresses = Vec::new();
+    for section in self.section_headers.iter().filter(|section| section.section_type == SHT_RELR) {
+      //the word after the last relocated one
+      let mut next = None;
+      for entry in self.section_data(section).chunks_exact(word_size) {
+        let entry = if word_size == 4 { E::read_u32(entry) as u64 } else { E::read_u64(entry) };
+        if entry & 1 == 0 {
+          addresses.push(entry);
+          next = entry.checked_add(word_size as u64);
+          continue;
+        }
+        let base = next.ok_or_else(invalid)?;
+        for bit in (0..bits).filter(|bit| entry >> (bit + 1) & 1 == 1) {
+          addresses.push(base.checked_add(bit * word_size as u64).ok_or_else(invalid)?);
+        }
+        next = base.checked_add(bits * word_size as u64);
+      }
+    }
+    Ok(addresses)
+  }
+}
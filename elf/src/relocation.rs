@@ -0,0 +1,128 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::{Elf, SectionHeader};
+use crate::entry_table::EntryTable;
+use crate::layout::*;
+use crate::parallel::map_entries;
+
+#[derive(Default, Clone)]
+pub struct Relocation {
+  pub section_index: usize,
+  pub offset: u64,
+  pub symbol_index: u32,
+  pub relocation_type: u32,
+  //REL => None, the addend is stored at the target
+  pub addend: Option<i64>,
+}
+
+impl Elf {
+  pub fn relocations(&self) -> Vec<Relocation> {
+    let mut relocations = Vec::new();
+    for (index, section) in self.section_headers.iter().enumerate() {
+      if section.section_type != SHT_REL && section.section_type != SHT_RELA {
+        continue;
+      }
+      match self.header.identification.endianness {
+        1 => self.load_relocations_with_byteorder::<LittleEndian>(index, section, &mut relocations),
+        2 => self.load_relocations_with_byteorder::<BigEndian>(index, section, &mut relocations),
+        _ => panic!("unknown endianness"),
+      };
+    }
+    relocations
+  }
+
+  pub fn relative_relocation_type(&self) -> Option<u32> {
+    match self.header.description.machine {
+      EM_386 => Some(8),
+      EM_X86_64 => Some(8),
+      EM_ARM => Some(23),
+      EM_AARCH64 => Some(1027),
+      EM_RISCV => Some(3),
+      EM_LOONGARCH => Some(3),
+      EM_PPC => Some(22),
+      EM_PPC64 => Some(22),
+      EM_S390 => Some(12),
+      EM_SPARCV9 => Some(22),
+      _ => None,
+    }
+  }
+
+  //R_*_IRELATIVE: the slot gets what the ifunc resolver at the addend returns
+  pub fn irelative_relocation_type(&self) -> Option<u32> {
+    match self.header.description.machine {
+      EM_386 => Some(42),
+      EM_X86_64 => Some(37),
+      EM_ARM => Some(160),
+      EM_AARCH64 => Some(1032),
+      EM_RISCV => Some(58),
+      EM_LOONGARCH => Some(12),
+      EM_PPC => Some(248),
+      EM_PPC64 => Some(248),
+      EM_S390 => Some(61),
+      EM_SPARCV9 => Some(249),
+      _ => None,
+    }
+  }
+
+  //How many bytes at the offset a relocation type writes, None when the machine or type is
+  //not known. Types that only mark code for the linker (R_*_NONE, TLSDESC_CALL, RISC-V
+  //RELAX) write none.
+  pub fn relocation_size(&self, relocation_type: u32) -> Option<u64> {
+    let word = if self.header.identification.class == ELFCLASS64 { 8 } else { 4 };
+    let size = match (self.header.description.machine, relocation_type) {
+      (EM_X86_64, 0 | 5 | 35) => 0,
+      (EM_X86_64, 14 | 15) => 1,
+      (EM_X86_64, 12 | 13) => 2,
+      (EM_X86_64, 1 | 6..=8 | 16..=18 | 24 | 25 | 27..=31 | 33 | 37 | 38) => 8,
+      (EM_X86_64, 36) => 16,
+      (EM_X86_64, 2..=4 | 9..=11 | 19..=23 | 26 | 32 | 34 | 41..=45) => 4,
+      (EM_386, 0 | 5 | 40) => 0,
+      (EM_386, 22 | 23) => 1,
+      (EM_386, 20 | 21) => 2,
+      (EM_386, 41) => 8,
+      (EM_386, 1..=43) => 4,
+      (EM_AARCH64, 0 | 256 | 1024) => 0,
+      (EM_AARCH64, 259 | 262) => 2,
+      (EM_AARCH64, 257 | 260 | 1025..=1030 | 1032) => 8,
+      (EM_AARCH64, 1031) => 16,
+      //data words, and every instruction relocation
+      (EM_AARCH64, 258 | 261 | 263..=1023) => 4,
+      (EM_ARM, 0 | 20) => 0,
+      (EM_ARM, 8) => 1,
+      (EM_ARM, 5 | 102 | 103) => 2,
+      (EM_ARM, 1..=255) => 4,
+      (EM_RISCV, 0 | 4 | 43 | 51) => 0,
+      (EM_RISCV, 33 | 37 | 52..=54) => 1,
+      (EM_RISCV, 34 | 38 | 44 | 45 | 55) => 2,
+      (EM_RISCV, 1 | 16 | 17 | 20..=32 | 35 | 39 | 56 | 57) => 4,
+      (EM_RISCV, 2 | 36 | 40) => 8,
+      //auipc and jalr
+      (EM_RISCV, 18 | 19) => 8,
+      (EM_RISCV, 3 | 5..=11 | 58) => word,
+      _ => return None,
+    };
+    Some(size)
+  }
+
+  fn load_relocations_with_byteorder<E: ByteOrder>(&self, section_index: usize, section: &SectionHeader, relocations: &mut Vec<Relocation>) {
+    match self.header.identification.class {
+      1 => self.load_relocations_with_layout::<E, Elf32>(section_index, section, relocations),
+      2 => self.load_relocations_with_layout::<E, Elf64>(section_index, section, relocations),
+      _ => panic!("unknown class"),
+    };
+  }
+
+  fn load_relocations_with_layout<E: ByteOrder, L: ElfLayout>(&self, section_index: usize, section: &SectionHeader, relocations: &mut Vec<Relocation>) {
+    if section.section_type == SHT_RELA {
+      self.read_relocations::<E, L::Rela>(section_index, section, relocations);
+    } else {
+      self.read_relocations::<E, L::Rel>(section_index, section, relocations);
+    }
+  }
+
+  fn read_relocations<E: ByteOrder, R: Widen<Relocation>>(&self, section_index: usize, section: &SectionHeader, relocations: &mut Vec<Relocation>) {
+    relocations.extend(map_entries(EntryTable::new(self.section_data(section), section.entry_size, R::SIZE), |entry| {
+      Relocation { section_index, ..R::read::<E>(entry).unwrap().widen() }
+    }));
+  }
+}
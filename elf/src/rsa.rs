@@ -0,0 +1,95 @@
+//RSA signature checks (PKCS#1 v1.5) on big-endian byte strings. The arithmetic is bit by
+//bit, slow but short: a 4096 bit check with e = 65537 takes a fraction of a second.
+use std::cmp::Ordering;
+
+//little endian 32 bit limbs, all of one length
+type Number = Vec<u32>;
+
+fn from_be(bytes: &[u8], limbs: usize) -> Number {
+  let mut number = vec![0u32; limbs];
+  for (index, &byte) in bytes.iter().rev().enumerate() {
+    number[index / 4] |= (byte as u32) << (8 * (index % 4));
+  }
+  number
+}
+
+fn to_be(number: &Number, size: usize) -> Vec<u8> {
+  (0..size).rev().map(|index| (number[index / 4] >> (8 * (index % 4))) as u8).collect()
+}
+
+fn compare(a: &Number, b: &Number) -> Ordering {
+  a.iter().rev().cmp(b.iter().rev())
+}
+
+fn subtract(a: &mut Number, b: &Number) {
+  let mut borrow = 0i64;
+  for (a, &b) in a.iter_mut().zip(b.iter()) {
+    let difference = *a as i64 - b as i64 - borrow;
+    borrow = (difference < 0) as i64;
+    *a = difference as u32;
+  }
+}
+
+//a + b, or 2a when b is None, reduced mod n; a and b below n, one spare limb on top
+fn add_mod(a: &mut Number, b: Option<&Number>, n: &Number) {
+  let mut carry = 0u64;
+  for index in 0..a.len() {
+    let addend = b.map_or(a[index], |b| b[index]) as u64;
+    let sum = a[index] as u64 + addend + carry;
+    a[index] = sum as u32;
+    carry = sum >> 32;
+  }
+  if compare(a, n) != Ordering::Less {
+    subtract(a, n);
+  }
+}
+
+fn mul_mod(a: &Number, b: &Number, n: &Number) -> Number {
+  let mut result = vec![0u32; n.len()];
+  for bit in (0..b.len() * 32).rev() {
+    add_mod(&mut result, None, n);
+    if b[bit / 32] >> (bit % 32) & 1 == 1 {
+      add_mod(&mut result, Some(a), n);
+    }
+  }
+  result
+}
+
+fn pow_mod(base: &Number, exponent: &[u8], n: &Number) -> Number {
+  let mut result = vec![0u32; n.len()];
+  result[0] = 1;
+  for byte in exponent {
+    for bit in (0..8).rev() {
+      result = mul_mod(&result, &result, n);
+      if byte >> bit & 1 == 1 {
+        result = mul_mod(&result, base, n);
+      }
+    }
+  }
+  result
+}
+
+fn trim(bytes: &[u8]) -> &[u8] {
+  let start = bytes.iter().position(|&byte| byte != 0).unwrap_or(bytes.len());
+  &bytes[start..]
+}
+
+//`digest_info` is the DER DigestInfo of the message digest, what EMSA-PKCS1-v1_5 pads.
+pub(crate) fn verify_pkcs1(modulus: &[u8], exponent: &[u8], signature: &[u8], digest_info: &[u8]) -> bool {
+  let modulus = trim(modulus);
+  let size = modulus.len();
+  if size < digest_info.len() + 11 || trim(signature).len() > size {
+    return false;
+  }
+  let limbs = size.div_ceil(4) + 1;
+  let n = from_be(modulus, limbs);
+  let s = from_be(trim(signature), limbs);
+  if compare(&s, &n) != Ordering::Less {
+    return false;
+  }
+  let mut expected = vec![0x00, 0x01];
+  expected.resize(size - digest_info.len() - 1, 0xff);
+  expected.push(0x00);
+  expected.extend_from_slice(digest_info);
+  to_be(&pow_mod(&s, trim(exponent), &n), size) == expected
+}
@@ -0,0 +1,592 @@
+use std::convert::TryFrom;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::consts::*;
+use crate::dwarf::read_unsigned;
+use crate::elf::{read_bytes, Elf};
+
+//the vtables of the ABI's type_info classes, what class typeinfo objects point at
+const CLASS_TYPE_INFO: &str = "_ZTVN10__cxxabiv117__class_type_infoE";
+const SI_CLASS_TYPE_INFO: &str = "_ZTVN10__cxxabiv120__si_class_type_infoE";
+const VMI_CLASS_TYPE_INFO: &str = "_ZTVN10__cxxabiv121__vmi_class_type_infoE";
+//offset_to_top of a secondary vtable is minus the offset of its base, never this far
+const MAX_OFFSET_TO_TOP: i64 = 1 << 24;
+//how deep types nest and how long a demangled name gets before demangle_type gives up, the
+//mangling comes from the file and substitutions can double a name each time
+const MAX_TYPE_DEPTH: usize = 64;
+const MAX_TYPE_NAME: usize = 4096;
+
+//A base class as a typeinfo object names it, defined here or imported ("_ZTISt9exception").
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BaseClass {
+  //the base's typeinfo, None when it is in another file
+  pub typeinfo: Option<u64>,
+  pub name: String,
+  //of the base in the derived object, for virtual bases the offset of its vbase offset in
+  //the vtable
+  pub offset: i64,
+  pub is_virtual: bool,
+  pub is_public: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ClassKind {
+  //__class_type_info, no bases
+  Class,
+  //__si_class_type_info, one public non-virtual base at offset 0
+  SingleInheritance,
+  //__vmi_class_type_info, with its flags: 1 non-diamond repeat, 2 diamond shaped
+  MultipleInheritance(u32),
+}
+
+//A class typeinfo object (_ZTI) of the Itanium C++ ABI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassTypeInfo {
+  pub address: u64,
+  //the type's mangled name from its _ZTS string, "N3foo3BarE"
+  pub mangled_name: String,
+  //demangled, "foo::Bar", or the mangled name when demangle_type cannot read it
+  pub name: String,
+  pub kind: ClassKind,
+  pub bases: Vec<BaseClass>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VirtualFunction {
+  //None for functions imported from another file, and for the null slots GCC leaves for
+  //the destructors of abstract classes
+  pub address: Option<u64>,
+  //"__cxa_pure_virtual" for pure virtual slots
+  pub symbol: Option<String>,
+}
+
+//One virtual table, a primary one or a secondary one for a base at a non-zero offset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VirtualTable {
+  //the offset_to_top slot; vcall and vbase offsets of classes with virtual bases come before
+  pub address: u64,
+  //where object vptrs point, the first virtual function slot
+  pub address_point: u64,
+  //0 in a primary vtable, minus the base's offset in a secondary one
+  pub offset_to_top: i64,
+  pub typeinfo: u64,
+  //the _ZTV symbol when it is not stripped, _ZTC for construction vtables
+  pub symbol: Option<String>,
+  pub functions: Vec<VirtualFunction>,
+}
+
+//A polymorphic class put together from its typeinfo and vtables.
+#[derive(Clone, Debug)]
+pub struct CppClass {
+  pub typeinfo: ClassTypeInfo,
+  //the primary vtable first, secondary ones by address
+  pub vtables: Vec<VirtualTable>,
+}
+
+//A word in the loaded image: what relocations or the file put there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Word {
+  Value(u64),
+  //a pointer to a symbol of another file, with the addend
+  Import(String, i64),
+}
+
+//The file's data as the dynamic loader leaves it, one word at a time.
+struct Image<'a> {
+  elf: &'a Elf,
+  word: usize,
+  big_endian: bool,
+  relocations: HashMap<u64, Word>,
+}
+
+impl<'a> Image<'a> {
+  fn new(elf: &'a Elf) -> Image<'a> {
+    let word = if elf.header.identification.class == ELFCLASS64 { 8 } else { 4 };
+    let big_endian = elf.header.identification.endianness == 2;
+    let mut image = Image { elf, word, big_endian, relocations: HashMap::new() };
+    for relocation in elf.relocations() {
+      //REL keeps the addend in the word itself
+      let addend = relocation.addend.unwrap_or_else(|| image.raw(relocation.offset).unwrap_or(0) as i64);
+      let value = match elf.relocation_symbol(relocation.section_index, relocation.symbol_index) {
+        Some(symbol) if symbol.is_undefined() && !symbol.name.is_empty() => Word::Import(symbol.name.to_string(), addend),
+        Some(symbol) if relocation.symbol_index != 0 => Word::Value(symbol.value.wrapping_add(addend as u64)),
+        _ => Word::Value(addend as u64),
+      };
+      image.relocations.insert(relocation.offset, value);
+    }
+    image
+  }
+
+  fn raw(&self, address: u64) -> Option<u64> {
+    let offset = usize::try_from(self.elf.address_to_offset(address)?).ok()?;
+    Some(read_unsigned(self.elf.data.get(offset..offset.checked_add(self.word)?)?, self.big_endian))
+  }
+
+  fn read(&self, address: u64) -> Option<Word> {
+    match self.relocations.get(&address) {
+      Some(word) => Some(word.clone()),
+      None => self.raw(address).map(Word::Value),
+    }
+  }
+
+  fn relocated(&self, address: u64) -> bool {
+    self.relocations.contains_key(&address)
+  }
+
+  fn u32(&self, address: u64) -> Option<u32> {
+    let offset = usize::try_from(self.elf.address_to_offset(address)?).ok()?;
+    Some(read_unsigned(self.elf.data.get(offset..offset.checked_add(4)?)?, self.big_endian) as u32)
+  }
+
+  fn string(&self, address: u64) -> Option<String> {
+    let offset = usize::try_from(self.elf.address_to_offset(address)?).ok()?;
+    let bytes = read_bytes(&self.elf.data, offset)?;
+    std::str::from_utf8(bytes).ok().filter(|name| !name.is_empty() && name.is_ascii()).map(str::to_string)
+  }
+}
+
+//Reads Itanium ABI <type> manglings: names, nested names, std:: abbreviations, templates
+//with type and integer arguments, qualifiers, pointers, references and substitutions.
+struct TypeDemangler<'a> {
+  text: &'a [u8],
+  position: usize,
+  substitutions: Vec<String>,
+  depth: usize,
+}
+
+fn builtin_type(code: u8) -> Option<&'static str> {
+  Some(match code {
+    b'v' => "void",
+    b'b' => "bool",
+    b'c' => "char",
+    b'a' => "signed char",
+    b'h' => "unsigned char",
+    b's' => "short",
+    b't' => "unsigned short",
+    b'i' => "int",
+    b'j' => "unsigned int",
+    b'l' => "long",
+    b'm' => "unsigned long",
+    b'x' => "long long",
+    b'y' => "unsigned long long",
+    b'n' => "__int128",
+    b'o' => "unsigned __int128",
+    b'f' => "float",
+    b'd' => "double",
+    b'e' => "long double",
+    b'w' => "wchar_t",
+    b'z' => "...",
+    _ => return None,
+  })
+}
+
+impl<'a> TypeDemangler<'a> {
+  fn peek(&self) -> Option<u8> {
+    self.text.get(self.position).copied()
+  }
+
+  fn eat(&mut self, byte: u8) -> bool {
+    let matched = self.peek() == Some(byte);
+    if matched {
+      self.position += 1;
+    }
+    matched
+  }
+
+  fn number(&mut self) -> Option<u64> {
+    let start = self.position;
+    while self.peek().is_some_and(|byte| byte.is_ascii_digit()) {
+      self.position += 1;
+    }
+    std::str::from_utf8(&self.text[start..self.position]).ok()?.parse().ok()
+  }
+
+  fn source_name(&mut self) -> Option<String> {
+    let length = usize::try_from(self.number()?).ok()?;
+    let name = self.text.get(self.position..self.position.checked_add(length)?)?;
+    self.position += length;
+    let name = std::str::from_utf8(name).ok()?;
+    //the names of anonymous namespaces
+    Some(if name.starts_with("_GLOBAL__N") { String::from("(anonymous namespace)") } else { name.to_string() })
+  }
+
+  //S_, S<seq-id>_ and the std abbreviations
+  fn substitution(&mut self) -> Option<String> {
+    if !self.eat(b'S') {
+      return None;
+    }
+    let abbreviation = match self.peek()? {
+      b't' => "std",
+      b'a' => "std::allocator",
+      b'b' => "std::basic_string",
+      b's' => "std::string",
+      b'i' => "std::istream",
+      b'o' => "std::ostream",
+      b'd' => "std::iostream",
+      _ => {
+        let mut index: usize = 0;
+        if !self.eat(b'_') {
+          while let Some(byte) = self.peek().filter(|&byte| byte != b'_') {
+            let digit = match byte {
+              b'0'..=b'9' => byte - b'0',
+              b'A'..=b'Z' => byte - b'A' + 10,
+              _ => return None,
+            };
+            index = index.checked_mul(36)?.checked_add(digit as usize)?;
+            self.position += 1;
+          }
+          self.position += 1;
+          index = index.checked_add(1)?;
+        }
+        return self.substitutions.get(index).cloned();
+      },
+    };
+    self.position += 1;
+    Some(abbreviation.to_string())
+  }
+
+  fn template_arguments(&mut self) -> Option<String> {
+    let mut arguments = Vec::new();
+    while !self.eat(b'E') {
+      if self.eat(b'L') {
+        let kind = self.peek()?;
+        self.position += 1;
+        let negative = self.eat(b'n');
+        let value = self.number()?;
+        self.eat(b'E').then_some(())?;
+        arguments.push(match kind {
+          b'b' => String::from(if value == 0 { "false" } else { "true" }),
+          _ => format!("{}{}", if negative { "-" } else { "" }, value),
+        });
+      } else {
+        arguments.push(self.parse_type()?);
+      }
+    }
+    let joined = arguments.join(", ");
+    Some(if joined.ends_with('>') { format!("<{} >", joined) } else { format!("<{}>", joined) })
+  }
+
+  fn nested_name(&mut self) -> Option<String> {
+    self.eat(b'K');
+    let mut name = String::new();
+    while !self.eat(b'E') {
+      let component = match self.peek()? {
+        b'S' if name.is_empty() => {
+          let substitution = self.substitution()?;
+          if substitution == "std" {
+            name = substitution;
+            continue;
+          }
+          substitution
+        },
+        b'I' => {
+          self.position += 1;
+          name.push_str(&self.template_arguments()?);
+          self.substitutions.push(name.clone());
+          continue;
+        },
+        b'0'..=b'9' => self.source_name()?,
+        _ => return None,
+      };
+      let known = name.is_empty() && self.substitutions.contains(&component);
+      name = if name.is_empty() { component } else { format!("{}::{}", name, component) };
+      if !known {
+        self.substitutions.push(name.clone());
+      }
+    }
+    Some(name)
+  }
+
+  fn parse_type(&mut self) -> Option<String> {
+    if self.depth == MAX_TYPE_DEPTH {
+      return None;
+    }
+    self.depth += 1;
+    let parsed = self.parse_type_at_depth();
+    self.depth -= 1;
+    parsed.filter(|parsed| parsed.len() <= MAX_TYPE_NAME)
+  }
+
+  fn parse_type_at_depth(&mut self) -> Option<String> {
+    let code = self.peek()?;
+    let parsed = match code {
+      b'P' | b'R' | b'O' | b'K' | b'V' => {
+        self.position += 1;
+        let inner = self.parse_type()?;
+        match code {
+          b'P' => format!("{}*", inner),
+          b'R' => format!("{}&", inner),
+          b'O' => format!("{}&&", inner),
+          b'K' => format!("{} const", inner),
+          _ => format!("{} volatile", inner),
+        }
+      },
+      b'N' => {
+        self.position += 1;
+        return self.nested_name();
+      },
+      b'S' => {
+        let name = self.substitution()?;
+        let name = match name.as_str() {
+          "std" => format!("std::{}", self.source_name()?),
+          _ => name,
+        };
+        if self.eat(b'I') {
+          if !self.substitutions.contains(&name) {
+            self.substitutions.push(name.clone());
+          }
+          format!("{}{}", name, self.template_arguments()?)
+        } else {
+          return Some(name);
+        }
+      },
+      b'0'..=b'9' => {
+        let name = self.source_name()?;
+        if self.eat(b'I') {
+          self.substitutions.push(name.clone());
+          format!("{}{}", name, self.template_arguments()?)
+        } else {
+          name
+        }
+      },
+      b'D' => {
+        self.position += 1;
+        let code = self.peek()?;
+        self.position += 1;
+        return Some(String::from(match code {
+          b's' => "char16_t",
+          b'i' => "char32_t",
+          b'u' => "char8_t",
+          b'n' => "decltype(nullptr)",
+          _ => return None,
+        }));
+      },
+      code => {
+        self.position += 1;
+        return builtin_type(code).map(str::to_string);
+      },
+    };
+    //compound types are substitution candidates too, after the parts they are made of
+    self.substitutions.push(parsed.clone());
+    Some(parsed)
+  }
+}
+
+//Demangles the <type> of a typeinfo name (_ZTS string) or a _ZTI/_ZTV/_ZTS symbol with the
+//prefix taken off: "N3foo3BarE" is "foo::Bar". None for what it cannot read.
+pub fn demangle_type(mangled: &str) -> Option<String> {
+  let mut demangler = TypeDemangler { text: mangled.as_bytes(), position: 0, substitutions: Vec::new(), depth: 0 };
+  let name = demangler.parse_type()?;
+  if demangler.position == mangled.len() { Some(name) } else { None }
+}
+
+fn type_name(mangled: &str) -> String {
+  demangle_type(mangled).unwrap_or_else(|| mangled.to_string())
+}
+
+impl Elf {
+  //The class typeinfo objects in the file's data, found by what they start with: a pointer
+  //into one of the ABI's type_info vtables, through a relocation in PIC code or written by
+  //the static linker. Symbols are not needed, stripped files work as well.
+  pub fn class_type_infos(&self) -> Vec<ClassTypeInfo> {
+    self.class_type_infos_in(&Image::new(self))
+  }
+
+  //Aligned addresses of the words in allocated data sections, only those the file holds
+  //whatever sh_size claims.
+  fn data_words(&self, word: usize) -> impl Iterator<Item = u64> + '_ {
+    self.section_headers.iter()
+      .filter(|section| section.flags & SHF_ALLOC != 0 && section.flags & SHF_EXECINSTR == 0 && section.section_type == SHT_PROGBITS && section.address != 0)
+      .filter_map(move |section| {
+        let end = section.address.checked_add(self.section_data(section).len() as u64)?;
+        let start = section.address.checked_next_multiple_of(word as u64)?;
+        Some((start..end).step_by(word).take_while(move |address| end - address >= word as u64))
+      })
+      .flatten()
+  }
+
+  fn class_type_infos_in(&self, image: &Image) -> Vec<ClassTypeInfo> {
+    let word = image.word as u64;
+    let mut abi_vtables: HashMap<u64, &str> = HashMap::new();
+    for symbol in self.symbol_table().iter().chain(self.dynamic_symbol_table()) {
+      let name = &*symbol.name;
+      if !symbol.is_undefined() && (name == CLASS_TYPE_INFO || name == SI_CLASS_TYPE_INFO || name == VMI_CLASS_TYPE_INFO) {
+        abi_vtables.insert(symbol.value.wrapping_add(2 * word), match name {
+          CLASS_TYPE_INFO => CLASS_TYPE_INFO,
+          SI_CLASS_TYPE_INFO => SI_CLASS_TYPE_INFO,
+          _ => VMI_CLASS_TYPE_INFO,
+        });
+      }
+    }
+    let base_of = |address: u64| -> Option<(Option<u64>, String)> {
+      match image.read(address)? {
+        Word::Value(base) => Some((Some(base), String::new())),
+        Word::Import(name, _) => Some((None, type_name(name.strip_prefix("_ZTI")?))),
+      }
+    };
+    let mut typeinfos = Vec::new();
+    for address in self.data_words(image.word) {
+      let kind = match image.read(address) {
+        Some(Word::Import(name, _)) if name == CLASS_TYPE_INFO || name == SI_CLASS_TYPE_INFO || name == VMI_CLASS_TYPE_INFO => name,
+        Some(Word::Value(value)) => match abi_vtables.get(&value) {
+          Some(name) => name.to_string(),
+          None => continue,
+        },
+        _ => continue,
+      };
+      //data_words() leaves a word after each address, the typeinfo's name
+      let mangled_name = match image.read(address + word) {
+        Some(Word::Value(name)) => match image.string(name) {
+          Some(name) => name,
+          None => continue,
+        },
+        _ => continue,
+      };
+      let mut bases = Vec::new();
+      let kind = match kind.as_str() {
+        CLASS_TYPE_INFO => ClassKind::Class,
+        SI_CLASS_TYPE_INFO => {
+          if let Some((typeinfo, name)) = address.checked_add(2 * word).and_then(base_of) {
+            bases.push(BaseClass { typeinfo, name, offset: 0, is_virtual: false, is_public: true });
+          }
+          ClassKind::SingleInheritance
+        },
+        _ => {
+          let flags = address.checked_add(2 * word).and_then(|flags| image.u32(flags)).unwrap_or(0);
+          let count = address.checked_add(2 * word + 4).and_then(|count| image.u32(count)).unwrap_or(0).min(256) as u64;
+          for index in 0..count {
+            let entry = match address.checked_add(2 * word + 8 + index * 2 * word) {
+              Some(entry) => entry,
+              None => break,
+            };
+            let (typeinfo, name) = match base_of(entry) {
+              Some(base) => base,
+              None => break,
+            };
+            let offset_flags = entry.checked_add(word).and_then(|offset_flags| image.raw(offset_flags)).unwrap_or(0);
+            //a long: sign extend the 32-bit ones
+            let offset_flags = if word == 4 { offset_flags as u32 as i32 as i64 } else { offset_flags as i64 };
+            bases.push(BaseClass { typeinfo, name, offset: offset_flags >> 8, is_virtual: offset_flags & 1 != 0, is_public: offset_flags & 2 != 0 });
+          }
+          ClassKind::MultipleInheritance(flags)
+        },
+      };
+      typeinfos.push(ClassTypeInfo { address, name: type_name(&mangled_name), mangled_name, kind, bases });
+    }
+    //bases defined in the file are named after their typeinfo
+    let names: HashMap<u64, String> = typeinfos.iter().map(|typeinfo| (typeinfo.address, typeinfo.name.clone())).collect();
+    for typeinfo in &mut typeinfos {
+      for base in &mut typeinfo.bases {
+        if let Some(name) = base.typeinfo.and_then(|address| names.get(&address)) {
+          base.name = name.clone();
+        }
+      }
+    }
+    typeinfos
+  }
+
+  //The vtables of the classes class_type_infos() finds: a typeinfo pointer preceded by an
+  //offset_to_top that no relocation touches and followed by pointers to code or imported
+  //functions.
+  pub fn vtables(&self) -> Vec<VirtualTable> {
+    let image = Image::new(self);
+    self.vtables_in(&image, &self.class_type_infos_in(&image))
+  }
+
+  fn vtables_in(&self, image: &Image, typeinfos: &[ClassTypeInfo]) -> Vec<VirtualTable> {
+    let word = image.word as u64;
+    let typeinfo_addresses: HashSet<u64> = typeinfos.iter().map(|typeinfo| typeinfo.address).collect();
+    let code: Vec<(u64, u64)> = self.section_headers.iter()
+      .filter(|section| section.flags & SHF_ALLOC != 0 && section.flags & SHF_EXECINSTR != 0)
+      .map(|section| (section.address, section.address.saturating_add(section.size)))
+      .collect();
+    let mut functions: HashMap<u64, String> = HashMap::new();
+    //vtable symbols by start, to name the tables a symbol covers
+    let mut vtable_symbols: BTreeMap<u64, (u64, String)> = BTreeMap::new();
+    for symbol in self.symbol_table().iter().chain(self.dynamic_symbol_table()) {
+      if symbol.is_undefined() || symbol.name.is_empty() {
+        continue;
+      }
+      if symbol.symbol_type == STT_FUNC {
+        functions.entry(symbol.value).or_insert_with(|| symbol.name.to_string());
+      } else if symbol.name.starts_with("_ZTV") || symbol.name.starts_with("_ZTC") {
+        vtable_symbols.insert(symbol.value, (symbol.size, symbol.name.to_string()));
+      }
+    }
+    let imported_functions: HashSet<&str> = self.dynamic_symbol_table().iter()
+      .filter(|symbol| symbol.is_undefined() && (symbol.symbol_type == STT_FUNC || symbol.symbol_type == STT_NOTYPE))
+      .map(|symbol| &*symbol.name)
+      .collect();
+
+    let mut vtables = Vec::new();
+    for address in self.data_words(image.word) {
+      let typeinfo = match image.read(address) {
+        Some(Word::Value(typeinfo)) if typeinfo_addresses.contains(&typeinfo) => typeinfo,
+        _ => continue,
+      };
+      let start = match address.checked_sub(word) {
+        Some(start) if !image.relocated(start) => start,
+        _ => continue,
+      };
+      let offset_to_top = match image.raw(start) {
+        Some(value) if word == 4 => value as u32 as i32 as i64,
+        Some(value) => value as i64,
+        None => continue,
+      };
+      if offset_to_top > 0 || offset_to_top <= -MAX_OFFSET_TO_TOP {
+        continue;
+      }
+      let symbol = vtable_symbols.range(..=start).next_back()
+        .filter(|(&symbol_start, (size, _))| start < symbol_start.saturating_add((*size).max(1)))
+        .map(|(_, (_, name))| name.clone());
+      let symbol_end = vtable_symbols.range(..=start).next_back().map(|(&symbol_start, (size, _))| symbol_start.saturating_add(*size)).filter(|_| symbol.is_some());
+      let address_point = address + word;
+      let mut slots = Vec::new();
+      let mut slot = address_point;
+      loop {
+        if symbol_end.is_some_and(|end| slot.saturating_add(word) > end) {
+          break;
+        }
+        match image.read(slot) {
+          Some(Word::Value(target)) if code.iter().any(|&(low, high)| target >= low && target < high) => {
+            slots.push(VirtualFunction { address: Some(target), symbol: functions.get(&target).cloned() });
+          },
+          Some(Word::Import(name, 0)) if imported_functions.contains(name.as_str()) => {
+            slots.push(VirtualFunction { address: None, symbol: Some(name) });
+          },
+          Some(Word::Value(0)) if !image.relocated(slot) => {
+            slots.push(VirtualFunction { address: None, symbol: None });
+          },
+          _ => break,
+        };
+        slot = match slot.checked_add(word) {
+          Some(slot) => slot,
+          None => break,
+        };
+      }
+      //null words after the table are not slots of it
+      while slots.last().is_some_and(|slot| slot.address.is_none() && slot.symbol.is_none()) {
+        slots.pop();
+      }
+      vtables.push(VirtualTable { address: start, address_point, offset_to_top, typeinfo, symbol, functions: slots });
+    }
+    vtables
+  }
+
+  //Classes with their vtables, by name. Classes without virtual functions have typeinfo
+  //only when something throws or dynamic_casts them, and no vtable.
+  pub fn cpp_classes(&self) -> Vec<CppClass> {
+    let image = Image::new(self);
+    let typeinfos = self.class_type_infos_in(&image);
+    let mut vtables: HashMap<u64, Vec<VirtualTable>> = HashMap::new();
+    for vtable in self.vtables_in(&image, &typeinfos) {
+      vtables.entry(vtable.typeinfo).or_default().push(vtable);
+    }
+    let mut classes: Vec<CppClass> = typeinfos.into_iter().map(|typeinfo| {
+      let mut tables = vtables.remove(&typeinfo.address).unwrap_or_default();
+      tables.sort_by_key(|table| (table.offset_to_top != 0, table.address));
+      CppClass { typeinfo, vtables: tables }
+    }).collect();
+    classes.sort_by(|a, b| a.typeinfo.name.cmp(&b.typeinfo.name));
+    classes
+  }
+}
@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use crate::consts::*;
+use crate::elf::Elf;
+
+//Shadow byte values, from compiler-rt asan_internal.h
+pub const ASAN_HEAP_LEFT_REDZONE_MAGIC: u8 = 0xfa;
+pub const ASAN_HEAP_FREE_MAGIC: u8 = 0xfd;
+pub const ASAN_STACK_LEFT_REDZONE_MAGIC: u8 = 0xf1;
+pub const ASAN_STACK_MID_REDZONE_MAGIC: u8 = 0xf2;
+pub const ASAN_STACK_RIGHT_REDZONE_MAGIC: u8 = 0xf3;
+pub const ASAN_STACK_AFTER_RETURN_MAGIC: u8 = 0xf5;
+pub const ASAN_INITIALIZATION_ORDER_MAGIC: u8 = 0xf6;
+pub const ASAN_USER_POISONED_MEMORY_MAGIC: u8 = 0xf7;
+pub const ASAN_STACK_USE_AFTER_SCOPE_MAGIC: u8 = 0xf8;
+pub const ASAN_GLOBAL_REDZONE_MAGIC: u8 = 0xf9;
+pub const ASAN_CONTIGUOUS_CONTAINER_OOB_MAGIC: u8 = 0xfc;
+pub const ASAN_ARRAY_COOKIE_MAGIC: u8 = 0xac;
+pub const ASAN_INTRA_OBJECT_REDZONE: u8 = 0xbb;
+pub const ASAN_ALLOCA_LEFT_MAGIC: u8 = 0xca;
+pub const ASAN_ALLOCA_RIGHT_MAGIC: u8 = 0xcb;
+
+//shadow = (address >> scale) + offset
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShadowLayout {
+  pub scale: u8,
+  pub offset: u64,
+  //the runtime picks the offset, the instrumented code reads __asan_shadow_memory_dynamic_address
+  pub dynamic: bool,
+  //last user space address
+  pub high_memory_end: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShadowRegionKind {
+  LowMemory,
+  LowShadow,
+  //must stay unmapped so that computing the shadow of a shadow address faults
+  ShadowGap,
+  HighShadow,
+  HighMemory,
+}
+
+//inclusive bounds, as asan_mapping.h states them
+#[derive(Clone, Copy, Debug)]
+pub struct ShadowRegion {
+  pub kind: ShadowRegionKind,
+  pub start: u64,
+  pub end: u64,
+}
+
+impl ShadowLayout {
+  //Default Linux mapping of compiler-rt for a machine, None where ASan has no Linux port.
+  pub fn for_machine(machine: u16, class: u8) -> Option<ShadowLayout> {
+    let (offset, high_memory_end) = match (machine, class) {
+      (EM_X86_64, 2) => (0x7fff_8000, 0x7fff_ffff_ffff),
+      (EM_386, _) | (EM_X86_64, 1) => (0x2000_0000, 0xbfff_ffff),
+      (EM_ARM, _) => (0x2000_0000, 0xbfff_ffff),
+      (EM_AARCH64, _) => (1 << 36, (1 << 48) - 1),
+      (EM_MIPS, 1) => (0x0aaa_0000, 0x7fff_ffff),
+      (EM_MIPS, _) => (1 << 37, (1 << 40) - 1),
+      (EM_PPC64, _) => (1 << 44, (1 << 47) - 1),
+      (EM_S390, 2) => (1 << 52, (1 << 53) - 1),
+      (EM_RISCV, 2) => (0xd_5555_0000, (1 << 47) - 1),
+      (EM_LOONGARCH, 2) => (1 << 46, (1 << 47) - 1),
+      (EM_SPARCV9, _) => (1 << 43, (1 << 52) - 1),
+      _ => return None,
+    };
+    Some(ShadowLayout { scale: 3, offset, dynamic: false, high_memory_end })
+  }
+
+  pub fn granularity(&self) -> u64 {
+    1 << self.scale
+  }
+
+  pub fn shadow_address(&self, address: u64) -> u64 {
+    (address >> self.scale).wrapping_add(self.offset)
+  }
+
+  pub fn is_shadow(&self, address: u64) -> bool {
+    self.regions().iter().any(|region| {
+      matches!(region.kind, ShadowRegionKind::LowShadow | ShadowRegionKind::HighShadow) && address >= region.start && address <= region.end
+    })
+  }
+
+  //The address space split of asan_mapping.h. Where low memory and its shadow meet high
+  //memory (32-bit targets) there is no high region.
+  pub fn regions(&self) -> Vec<ShadowRegion> {
+    let low_memory_end = self.offset.saturating_sub(1);
+    let low_shadow_end = self.shadow_address(low_memory_end);
+    let high_shadow_end = self.shadow_address(self.high_memory_end);
+    let high_memory_start = high_shadow_end + 1;
+    let high_shadow_start = self.shadow_address(high_memory_start);
+    let mut regions = vec![
+      ShadowRegion { kind: ShadowRegionKind::LowMemory, start: 0, end: low_memory_end },
+      ShadowRegion { kind: ShadowRegionKind::LowShadow, start: self.offset, end: low_shadow_end },
+    ];
+    if high_shadow_start > low_shadow_end + 1 {
+      regions.push(ShadowRegion { kind: ShadowRegionKind::ShadowGap, start: low_shadow_end + 1, end: high_shadow_start - 1 });
+      regions.push(ShadowRegion { kind: ShadowRegionKind::HighShadow, start: high_shadow_start, end: high_shadow_end });
+      regions.push(ShadowRegion { kind: ShadowRegionKind::HighMemory, start: high_memory_start, end: self.high_memory_end });
+    } else {
+      regions.push(ShadowRegion { kind: ShadowRegionKind::HighShadow, start: low_shadow_end + 1, end: high_shadow_end });
+      regions.push(ShadowRegion { kind: ShadowRegionKind::HighMemory, start: high_shadow_end + 1, end: self.high_memory_end });
+    }
+    regions
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AsanHelperKind {
+  Init,
+  //__asan_report_{load,store}{1,2,4,8,16,_n}, size None for the _n variants
+  Report { write: bool, size: Option<u8> },
+  //out of line checks, __asan_{load,store}N
+  Check { write: bool, size: Option<u8> },
+  Poison,
+  Unpoison,
+  //__asan_set_shadow_XX writes XX over a shadow range
+  SetShadow(u8),
+  RegisterGlobals,
+  UnregisterGlobals,
+  //fake stack frames for use-after-return detection, with their size class
+  StackMalloc(u8),
+  StackFree(u8),
+  //memcpy/memset/memmove replacements
+  Intrinsic,
+  Other,
+}
+
+pub struct AsanHelper {
+  pub name: String,
+  pub kind: AsanHelperKind,
+  //address of the definition, when the binary carries the runtime or a copy relocation
+  pub address: Option<u64>,
+  //GOT slots the dynamic linker fills in for an imported helper, patch them to hook it
+  pub slots: Vec<u64>,
+}
+
+fn access_size(suffix: &str) -> Option<Option<u8>> {
+  match suffix {
+    "_n" | "N" => Some(None),
+    "1" | "2" | "4" | "8" | "16" => Some(suffix.parse().ok()),
+    _ => None,
+  }
+}
+
+pub fn asan_helper_kind(name: &str) -> Option<AsanHelperKind> {
+  let name = name.strip_prefix("__asan_")?;
+  let name = name.strip_suffix("_noabort").unwrap_or(name);
+  let kind = if name == "init" || name.starts_with("version_mismatch_check") {
+    AsanHelperKind::Init
+  } else if let Some(rest) = name.strip_prefix("report_") {
+    let rest = rest.strip_prefix("exp_").unwrap_or(rest);
+    match (rest.strip_prefix("load"), rest.strip_prefix("store")) {
+      (Some(size), _) => AsanHelperKind::Report { write: false, size: access_size(size)? },
+      (_, Some(size)) => AsanHelperKind::Report { write: true, size: access_size(size)? },
+      _ => AsanHelperKind::Other,
+    }
+  } else if let Some(size) = name.strip_prefix("load").and_then(access_size) {
+    AsanHelperKind::Check { write: false, size }
+  } else if let Some(size) = name.strip_prefix("store").and_then(access_size) {
+    AsanHelperKind::Check { write: true, size }
+  } else if let Some(value) = name.strip_prefix("set_shadow_") {
+    AsanHelperKind::SetShadow(u8::from_str_radix(value, 16).ok()?)
+  } else if let Some(class) = name.strip_prefix("stack_malloc_") {
+    let class = class.strip_prefix("always_").unwrap_or(class);
+    AsanHelperKind::StackMalloc(class.parse().ok()?)
+  } else if let Some(class) = name.strip_prefix("stack_free_") {
+    AsanHelperKind::StackFree(class.parse().ok()?)
+  } else {
+    match name {
+      "poison_memory_region" | "poison_stack_memory" | "alloca_poison" | "poison_cxx_array_cookie" | "poison_intra_object_redzone" => AsanHelperKind::Poison,
+      "unpoison_memory_region" | "unpoison_stack_memory" | "allocas_unpoison" | "unpoison_intra_object_redzone" | "handle_no_return" => AsanHelperKind::Unpoison,
+      "register_globals" | "register_elf_globals" | "register_image_globals" => AsanHelperKind::RegisterGlobals,
+      "unregister_globals" | "unregister_elf_globals" | "unregister_image_globals" => AsanHelperKind::UnregisterGlobals,
+      "memcpy" | "memset" | "memmove" => AsanHelperKind::Intrinsic,
+      _ => AsanHelperKind::Other,
+    }
+  };
+  Some(kind)
+}
+
+impl Elf {
+  pub fn asan_instrumented(&self) -> bool {
+    self.symbol_table().iter().chain(self.dynamic_symbol_table())
+      .any(|symbol| &*symbol.name == "__asan_init" || symbol.name.starts_with("__asan_version_mismatch_check"))
+  }
+
+  //Layout the instrumented code was compiled against, None for uninstrumented binaries.
+  pub fn asan_shadow_layout(&self) -> Option<ShadowLayout> {
+    if !self.asan_instrumented() {
+      return None;
+    }
+    let mut layout = ShadowLayout::for_machine(self.header.description.machine, self.header.identification.class)?;
+    let dynamic = "__asan_shadow_memory_dynamic_address";
+    layout.dynamic = !self.symbol_indices_by_name(dynamic).is_empty() || !self.dynamic_symbol_indices_by_name(dynamic).is_empty();
+    Some(layout)
+  }
+
+  pub fn asan_helpers(&self) -> Vec<AsanHelper> {
+    let mut helpers: Vec<AsanHelper> = Vec::new();
+    let mut by_name: HashMap<String, usize> = HashMap::new();
+    for symbol in self.symbol_table().iter().chain(self.dynamic_symbol_table()) {
+      let kind = match asan_helper_kind(&symbol.name) {
+        Some(kind) => kind,
+        None => continue,
+      };
+      let index = *by_name.entry(symbol.name.to_string()).or_insert_with(|| {
+        helpers.push(AsanHelper { name: symbol.name.to_string(), kind, address: None, slots: Vec::new() });
+        helpers.len() - 1
+      });
+      if !symbol.is_undefined() {
+        helpers[index].address = Some(symbol.value);
+      }
+    }
+
+    let dynamic_symbols = self.dynamic_symbol_table();
+    for relocation in self.relocations() {
+      let linked = self.section_headers[relocation.section_index].link as usize;
+      if self.section_headers.get(linked).is_none_or(|section| section.section_type != SHT_DYNSYM) {
+        continue;
+      }
+      let name = match dynamic_symbols.get(relocation.symbol_index as usize) {
+        Some(symbol) => &symbol.name,
+        None => continue,
+      };
+      if let Some(&index) = by_name.get(&**name) {
+        helpers[index].slots.push(relocation.offset);
+      }
+    }
+    helpers.sort_by(|a, b| a.name.cmp(&b.name));
+    helpers
+  }
+}
@@ -0,0 +1,90 @@
+use std::io::{self, Write};
+use crate::consts::*;
+use crate::syscall::{syscall_table, SyscallInventory, SyscallSite};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SeccompFormat {
+  //the Docker/OCI seccomp profile layout
+  Json,
+  //seccomp_rule_add calls for a libseccomp filter
+  Libseccomp,
+}
+
+pub fn seccomp_architecture(machine: u16, class: u8) -> Option<&'static str> {
+  match (machine, class) {
+    (EM_X86_64, 2) => Some("SCMP_ARCH_X86_64"),
+    (EM_X86_64, 1) => Some("SCMP_ARCH_X32"),
+    (EM_386, _) => Some("SCMP_ARCH_X86"),
+    (EM_AARCH64, _) => Some("SCMP_ARCH_AARCH64"),
+    (EM_ARM, _) => Some("SCMP_ARCH_ARM"),
+    (EM_RISCV, 2) => Some("SCMP_ARCH_RISCV64"),
+    (EM_PPC64, _) => Some("SCMP_ARCH_PPC64"),
+    (EM_S390, 2) => Some("SCMP_ARCH_S390X"),
+    _ => None,
+  }
+}
+
+impl SyscallInventory {
+  //Direct sites whose number could not be recovered, a policy built from names misses them.
+  pub fn unknown_sites(&self) -> Vec<u64> {
+    self.uses.iter().filter_map(|entry| match entry.site {
+      SyscallSite::Instruction(address) if entry.name.is_none() => Some(address),
+      _ => None,
+    }).collect()
+  }
+
+  //A draft allowlist that denies everything the inventory did not see. Syscalls made by the
+  //dynamic loader and by libraries are not part of a single binary's inventory, the draft is
+  //a starting point to review rather than a policy to deploy. Machines without a seccomp
+  //architecture or a syscall table here are an error rather than a deny-all draft.
+  pub fn write_seccomp_policy<W: Write>(&self, format: SeccompFormat, writer: &mut W) -> io::Result<()> {
+    let architecture = seccomp_architecture(self.machine, self.class)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, format!("no seccomp architecture for machine {}", self.machine)))?;
+    if syscall_table(self.machine, self.class).is_none() {
+      return Err(io::Error::new(io::ErrorKind::Unsupported, format!("no syscall table for {}, every syscall would be denied", architecture)));
+    }
+    let names = self.names();
+    let unknown = self.unknown_sites();
+    let mut notes = Vec::new();
+    if self.indirect {
+      notes.push("the binary calls syscall(2) with numbers only known at runtime".to_string());
+    }
+    if !unknown.is_empty() {
+      let sites: Vec<String> = unknown.iter().map(|address| format!("{:#x}", address)).collect();
+      notes.push(format!("syscall sites with unknown numbers: {}", sites.join(" ")));
+    }
+    match format {
+      SeccompFormat::Json => {
+        writeln!(writer, "{{")?;
+        writeln!(writer, "  \"defaultAction\": \"SCMP_ACT_ERRNO\",")?;
+        writeln!(writer, "  \"architectures\": [\"{}\"],", architecture)?;
+        writeln!(writer, "  \"syscalls\": [")?;
+        writeln!(writer, "    {{")?;
+        writeln!(writer, "      \"names\": [")?;
+        for (index, name) in names.iter().enumerate() {
+          writeln!(writer, "        \"{}\"{}", name, if index + 1 < names.len() { "," } else { "" })?;
+        }
+        writeln!(writer, "      ],")?;
+        writeln!(writer, "      \"action\": \"SCMP_ACT_ALLOW\"{}", if notes.is_empty() { "" } else { "," })?;
+        //Docker's profiles annotate rules with "comment", runtimes ignore it
+        if !notes.is_empty() {
+          writeln!(writer, "      \"comment\": \"draft from static analysis, {}\"", notes.join("; "))?;
+        }
+        writeln!(writer, "    }}")?;
+        writeln!(writer, "  ]")?;
+        writeln!(writer, "}}")?;
+      },
+      SeccompFormat::Libseccomp => {
+        writeln!(writer, "/* draft allowlist for {} derived from static analysis */", architecture)?;
+        for note in &notes {
+          writeln!(writer, "/* {} */", note)?;
+        }
+        writeln!(writer, "scmp_filter_ctx ctx = seccomp_init(SCMP_ACT_ERRNO(EPERM));")?;
+        for name in &names {
+          writeln!(writer, "seccomp_rule_add(ctx, SCMP_ACT_ALLOW, SCMP_SYS({}), 0);", name)?;
+        }
+      },
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,208 @@
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use crate::consts::*;
+use crate::elf::Elf;
+
+//bytes masked for a relocation type relocation_size() does not know
+const UNKNOWN_RELOCATION_SIZE: u64 = 4;
+
+//What a relocation refers to, comparable across files: the symbol name, the section for
+//section symbols, and the addend (the bytes at the offset for REL).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RelocationTarget {
+  pub relocation_type: u32,
+  pub symbol: String,
+  pub addend: Option<i64>,
+  pub implicit_addend: Vec<u8>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SectionComparison {
+  pub name: String,
+  pub old_size: u64,
+  pub new_size: u64,
+  //[start, end) offsets of bytes that differ outside the relocated fields of either file,
+  //past the end of the shorter section included
+  pub differences: Vec<(u64, u64)>,
+  //offset and the relocation there in the old and the new file, where they differ
+  pub relocation_differences: Vec<(u64, Option<RelocationTarget>, Option<RelocationTarget>)>,
+  //bytes left out of the comparison for being relocated
+  pub masked_bytes: u64,
+}
+
+impl SectionComparison {
+  pub fn is_equivalent(&self) -> bool {
+    self.old_size == self.new_size && self.differences.is_empty() && self.relocation_differences.is_empty()
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ObjectComparison {
+  //only sections that are not equivalent, unless compare_sections_all
+  pub sections: Vec<SectionComparison>,
+  pub only_in_old: Vec<String>,
+  pub only_in_new: Vec<String>,
+}
+
+impl ObjectComparison {
+  pub fn is_equivalent(&self) -> bool {
+    self.only_in_old.is_empty() && self.only_in_new.is_empty() && self.sections.iter().all(SectionComparison::is_equivalent)
+  }
+}
+
+fn merge_ranges(offsets: impl Iterator<Item = u64>) -> Vec<(u64, u64)> {
+  let mut ranges: Vec<(u64, u64)> = Vec::new();
+  for offset in offsets {
+    match ranges.last_mut() {
+      Some(last) if last.1 == offset => last.1 += 1,
+      _ => ranges.push((offset, offset + 1)),
+    };
+  }
+  ranges
+}
+
+impl Elf {
+  //Allocated sections by name, repeated names (COMDAT groups) told apart by their order.
+  fn comparable_sections(&self) -> Vec<(String, usize)> {
+    let mut seen: HashMap<&str, usize> = HashMap::new();
+    let mut sections = Vec::new();
+    for (index, section) in self.section_headers.iter().enumerate() {
+      let name = match self.section_name(section) {
+        Some(name) if section.flags & SHF_ALLOC != 0 => name,
+        _ => continue,
+      };
+      let count = seen.entry(name).or_insert(0);
+      sections.push((if *count == 0 { name.to_string() } else { format!("{}#{}", name, count) }, index));
+      *count += 1;
+    }
+    sections
+  }
+
+  //The relocations against each section, by section offset: those of relocatable objects
+  //and --emit-relocs output through sh_info, dynamic ones by the address they patch.
+  fn relocation_targets(&self) -> HashMap<usize, BTreeMap<u64, RelocationTarget>> {
+    let relocatable = self.header.description.obj_type == ET_REL;
+    //the allocated sections by address, TLS ones aside since they overlap what follows them
+    let mut by_address: Vec<(u64, usize)> = self.section_headers.iter().enumerate()
+      .filter(|(_, section)| section.flags & SHF_ALLOC != 0 && !(section.flags & SHF_TLS != 0 && section.section_type == SHT_NOBITS))
+      .map(|(index, section)| (section.address, index))
+      .collect();
+    by_address.sort_unstable();
+    let mut targets: HashMap<usize, BTreeMap<u64, RelocationTarget>> = HashMap::new();
+    for relocation in self.relocations() {
+      let relocation_section = &self.section_headers[relocation.section_index];
+      let linked = relocation_section.info as usize;
+      let section_index = if linked != 0 && (relocatable || relocation_section.flags & SHF_INFO_LINK != 0) {
+        linked
+      } else {
+        let candidate = by_address.partition_point(|&(address, _)| address <= relocation.offset).checked_sub(1).map(|position| by_address[position]);
+        match candidate {
+          Some((address, index)) if relocation.offset - address < self.section_headers[index].size => index,
+          _ => continue,
+        }
+      };
+      let section = match self.section_headers.get(section_index) {
+        Some(section) => section,
+        None => continue,
+      };
+      let offset = if relocatable { relocation.offset } else { relocation.offset.wrapping_sub(section.address) };
+      let symbol = match self.relocation_symbol(relocation.section_index, relocation.symbol_index) {
+        Some(symbol) if symbol.symbol_type == STT_SECTION => {
+          let name = self.section_headers.get(symbol.section_index as usize).and_then(|target| self.section_name(target));
+          format!("section {}", name.unwrap_or("?"))
+        },
+        Some(symbol) => symbol.name.to_string(),
+        None => String::new(),
+      };
+      let size = self.relocation_size(relocation.relocation_type).unwrap_or(UNKNOWN_RELOCATION_SIZE);
+      let implicit_addend = match relocation.addend {
+        None => usize::try_from(offset).ok()
+          .and_then(|start| self.section_data(section).get(start..start.checked_add(size as usize)?))
+          .unwrap_or(&[])
+          .to_vec(),
+        Some(_) => Vec::new(),
+      };
+      targets.entry(section_index).or_default().insert(offset, RelocationTarget {
+        relocation_type: relocation.relocation_type,
+        symbol,
+        addend: relocation.addend,
+        implicit_addend,
+      });
+    }
+    targets
+  }
+
+  //Compares the allocated sections of two builds, sections matched by name, with the bytes
+  //relocations patch left out: they hold addresses that depend on the layout of the link,
+  //not the code. The relocations are compared instead, by offset, type, target and addend.
+  //Sections that are equivalent are not returned.
+  pub fn compare_sections(&self, other: &Elf) -> ObjectComparison {
+    let mut comparison = self.compare_sections_all(other);
+    comparison.sections.retain(|section| !section.is_equivalent());
+    comparison
+  }
+
+  //compare_sections, with the sections that are equivalent too
+  pub fn compare_sections_all(&self, other: &Elf) -> ObjectComparison {
+    let old_sections = self.comparable_sections();
+    let new_sections: HashMap<String, usize> = other.comparable_sections().into_iter().collect();
+    let (old_targets, new_targets) = (self.relocation_targets(), other.relocation_targets());
+    let empty = BTreeMap::new();
+    let mut comparison = ObjectComparison::default();
+    for (name, old_index) in &old_sections {
+      let new_index = match new_sections.get(name) {
+        Some(&index) => index,
+        None => {
+          comparison.only_in_old.push(name.clone());
+          continue;
+        },
+      };
+      let (old, new) = (&self.section_headers[*old_index], &other.section_headers[new_index]);
+      let (old_relocations, new_relocations) = (old_targets.get(old_index).unwrap_or(&empty), new_targets.get(&new_index).unwrap_or(&empty));
+      //SHT_NOBITS sections have no bytes to compare, only sizes
+      let (old_data, new_data) = (self.section_data(old), other.section_data(new));
+      let length = old_data.len().max(new_data.len());
+      let mut masked = vec![false; length];
+      let mut mask = |relocations: &BTreeMap<u64, RelocationTarget>, elf: &Elf| {
+        for (&offset, target) in relocations {
+          let size = elf.relocation_size(target.relocation_type).unwrap_or(UNKNOWN_RELOCATION_SIZE);
+          for byte in masked.iter_mut().skip(offset as usize).take(size as usize) {
+            *byte = true;
+          }
+        }
+      };
+      mask(old_relocations, self);
+      mask(new_relocations, other);
+      let differences = merge_ranges((0..length).filter(|&offset| {
+        !masked.get(offset).copied().unwrap_or(false) && old_data.get(offset) != new_data.get(offset)
+      }).map(|offset| offset as u64));
+      let mut relocation_differences = Vec::new();
+      for (&offset, target) in old_relocations {
+        if new_relocations.get(&offset) != Some(target) {
+          relocation_differences.push((offset, Some(target.clone()), new_relocations.get(&offset).cloned()));
+        }
+      }
+      for (&offset, target) in new_relocations {
+        if !old_relocations.contains_key(&offset) {
+          relocation_differences.push((offset, None, Some(target.clone())));
+        }
+      }
+      relocation_differences.sort_by_key(|(offset, _, _)| *offset);
+      comparison.sections.push(SectionComparison {
+        name: name.clone(),
+        old_size: old.size,
+        new_size: new.size,
+        differences,
+        relocation_differences,
+        masked_bytes: masked.iter().filter(|&&byte| byte).count() as u64,
+      });
+    }
+    let old_names: HashMap<&str, usize> = old_sections.iter().map(|(name, index)| (name.as_str(), *index)).collect();
+    for (name, _) in other.comparable_sections() {
+      if !old_names.contains_key(name.as_str()) {
+        comparison.only_in_new.push(name);
+      }
+    }
+    comparison
+  }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use byteorder::{BigEndian, LittleEndian};
+use crate::cfi::{read_encoded_pointer, PointerBases, DW_EH_PE_OMIT};
+use crate::consts::*;
+use crate::dwarf::{read_unsigned, Reader};
+use crate::dynamic::DynamicEntry;
+use crate::elf::Elf;
+use crate::note::Note;
+use crate::relocation::Relocation;
+use crate::section_decoder::DecodedValue;
+use crate::symbol::Symbol;
+
+//.eh_frame_hdr: where .eh_frame is and the binary search table the unwinder uses.
+pub struct EhFrameHeader {
+  pub version: u8,
+  pub eh_frame: Option<u64>,
+  pub fde_count: u64,
+  //(initial location, FDE address), sorted by location
+  pub table: Vec<(u64, u64)>,
+}
+
+impl EhFrameHeader {
+  //address of the FDE that may cover `address`, the last one starting at or before it
+  pub fn lookup(&self, address: u64) -> Option<u64> {
+    let position = self.table.partition_point(|&(location, _)| location <= address);
+    position.checked_sub(1).map(|position| self.table[position].1)
+  }
+}
+
+//A section decoded according to its type or well-known name.
+pub enum SectionContent<'a> {
+  //SHT_NULL and SHT_NOBITS
+  Empty,
+  Dynamic(Vec<DynamicEntry>),
+  //.got and .got.plt, address sized words
+  Pointers(Vec<u64>),
+  //SHT_INIT_ARRAY, SHT_FINI_ARRAY, SHT_PREINIT_ARRAY, .ctors and .dtors
+  FunctionPointers(Vec<u64>),
+  EhFrameHeader(EhFrameHeader),
+  Symbols(Vec<Symbol>),
+  Relocations(Vec<Relocation>),
+  Notes(Vec<Note>),
+  //(offset, string) for every NUL terminated string
+  Strings(Vec<(usize, &'a [u8])>),
+  Raw(&'a [u8]),
+  //from a registered SectionDecoder
+  Custom(DecodedValue),
+}
+
+impl Elf {
+  pub fn section_content(&self, index: usize) -> Option<SectionContent<'_>> {
+    let section = self.section_headers.get(index)?;
+    let name = self.section_name(section).unwrap_or("");
+    let data = self.section_data(section);
+    let content = match section.section_type {
+      SHT_NULL | SHT_NOBITS => SectionContent::Empty,
+      SHT_DYNAMIC => SectionContent::Dynamic(match self.header.identification.endianness {
+        1 => self.load_dynamic_entries_with_byteorder::<LittleEndian>(data),
+        2 => self.load_dynamic_entries_with_byteorder::<BigEndian>(data),
+        _ => panic!("unknown endianness"),
+      }),
+      SHT_INIT_ARRAY | SHT_FINI_ARRAY | SHT_PREINIT_ARRAY => SectionContent::FunctionPointers(self.pointer_words(index)),
+      SHT_SYMTAB => SectionContent::Symbols(self.symbols()),
+      SHT_DYNSYM => SectionContent::Symbols(self.dynamic_symbols()),
+      SHT_REL | SHT_RELA => SectionContent::Relocations(self.relocations().into_iter().filter(|relocation| relocation.section_index == index).collect()),
+      SHT_NOTE => SectionContent::Notes(self.notes().into_iter().filter(|note| note.source_index == index).collect()),
+      SHT_STRTAB => {
+        let mut strings = Vec::new();
+        let mut start = 0;
+        for (position, &byte) in data.iter().enumerate() {
+          if byte == 0 {
+            strings.push((start, &data[start..position]));
+            start = position + 1;
+          }
+        }
+        SectionContent::Strings(strings)
+      },
+      _ => match name {
+        ".got" | ".got.plt" => SectionContent::Pointers(self.pointer_words(index)),
+        ".ctors" | ".dtors" => SectionContent::FunctionPointers(self.pointer_words(index)),
+        ".eh_frame_hdr" => match self.eh_frame_header(index) {
+          Some(header) => SectionContent::EhFrameHeader(header),
+          None => SectionContent::Raw(data),
+        },
+        _ => SectionContent::Raw(data),
+      },
+    };
+    Some(content)
+  }
+
+  //Address sized words of a section. Words a relative RELA relocation fills are zero in the
+  //file, they get the relocated value instead.
+  fn pointer_words(&self, index: usize) -> Vec<u64> {
+    let section = &self.section_headers[index];
+    let size = match self.header.identification.class {
+      1 => 4,
+      2 => 8,
+      _ => panic!("unknown class"),
+    };
+    let big_endian = self.header.identification.endianness == 2;
+    let relative = self.relative_relocation_type();
+    let relocated: HashMap<u64, i64> = self.relocations().into_iter()
+      .filter(|relocation| Some(relocation.relocation_type) == relative)
+      .filter_map(|relocation| Some((relocation.offset, relocation.addend?)))
+      .collect();
+    self.section_data(section).chunks_exact(size).enumerate().map(|(position, word)| {
+      let address = section.address + (position * size) as u64;
+      match relocated.get(&address) {
+        Some(&addend) => self.bias.wrapping_add(addend as u64),
+        None => read_unsigned(word, big_endian),
+      }
+    }).collect()
+  }
+
+  fn eh_frame_header(&self, index: usize) -> Option<EhFrameHeader> {
+    let section = &self.section_headers[index];
+    let mut reader = Reader { data: self.section_data(section), position: 0, big_endian: self.header.identification.endianness == 2 };
+    let version = reader.unsigned(1)? as u8;
+    let eh_frame_encoding = reader.unsigned(1)? as u8;
+    let count_encoding = reader.unsigned(1)? as u8;
+    let table_encoding = reader.unsigned(1)? as u8;
+    let address_size = if self.header.identification.class == 2 { 8 } else { 4 };
+    //table entries are datarel, relative to the start of .eh_frame_hdr
+    let bases = PointerBases { text: 0, data: section.address, function: 0 };
+    let eh_frame = read_encoded_pointer(&mut reader, eh_frame_encoding, section.address, address_size, &bases);
+    let fde_count = match count_encoding {
+      DW_EH_PE_OMIT => 0,
+      _ => read_encoded_pointer(&mut reader, count_encoding, section.address, address_size, &bases)?,
+    };
+    let mut table = Vec::new();
+    if table_encoding != DW_EH_PE_OMIT {
+      for _ in 0..fde_count {
+        let location = read_encoded_pointer(&mut reader, table_encoding, section.address, address_size, &bases)?;
+        let fde = read_encoded_pointer(&mut reader, table_encoding, section.address, address_size, &bases)?;
+        table.push((location, fde));
+      }
+    }
+    Some(EhFrameHeader { version, eh_frame, fde_count, table })
+  }
+}
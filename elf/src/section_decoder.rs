@@ -0,0 +1,228 @@
+use std::fmt;
+use std::io::{self, Write};
+use crate::dynamic::DynamicFlags;
+use crate::elf::{Elf, SectionHeader};
+use crate::section_content::SectionContent;
+
+//Output of a user decoder, a tree that renders both as indented text and as JSON.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DecodedValue {
+  Unsigned(u64),
+  Signed(i64),
+  Bool(bool),
+  String(String),
+  Bytes(Vec<u8>),
+  List(Vec<DecodedValue>),
+  Fields(Vec<(String, DecodedValue)>),
+}
+
+impl DecodedValue {
+  fn write_text(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+    match self {
+      DecodedValue::Unsigned(value) => write!(f, "{:#x}", value),
+      DecodedValue::Signed(value) => write!(f, "{}", value),
+      DecodedValue::Bool(value) => write!(f, "{}", value),
+      DecodedValue::String(value) => write!(f, "{:?}", value),
+      DecodedValue::Bytes(bytes) => {
+        for byte in bytes {
+          write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+      },
+      DecodedValue::List(values) => {
+        for (index, value) in values.iter().enumerate() {
+          write!(f, "\n{:width$}[{}] ", "", index, width = indent)?;
+          value.write_text(f, indent + 2)?;
+        }
+        Ok(())
+      },
+      DecodedValue::Fields(fields) => {
+        for (name, value) in fields {
+          write!(f, "\n{:width$}{}: ", "", name, width = indent)?;
+          value.write_text(f, indent + 2)?;
+        }
+        Ok(())
+      },
+    }
+  }
+
+  pub fn write_json<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    match self {
+      DecodedValue::Unsigned(value) => write!(writer, "{}", value),
+      DecodedValue::Signed(value) => write!(writer, "{}", value),
+      DecodedValue::Bool(value) => write!(writer, "{}", value),
+      DecodedValue::String(value) => write_json_string(writer, value),
+      DecodedValue::Bytes(bytes) => {
+        write!(writer, "\"")?;
+        for byte in bytes {
+          write!(writer, "{:02x}", byte)?;
+        }
+        write!(writer, "\"")
+      },
+      DecodedValue::List(values) => {
+        write!(writer, "[")?;
+        for (index, value) in values.iter().enumerate() {
+          if index > 0 {
+            write!(writer, ",")?;
+          }
+          value.write_json(writer)?;
+        }
+        write!(writer, "]")
+      },
+      DecodedValue::Fields(fields) => {
+        write!(writer, "{{")?;
+        for (index, (name, value)) in fields.iter().enumerate() {
+          if index > 0 {
+            write!(writer, ",")?;
+          }
+          write_json_string(writer, name)?;
+          write!(writer, ":")?;
+          value.write_json(writer)?;
+        }
+        write!(writer, "}}")
+      },
+    }
+  }
+}
+
+impl fmt::Display for DecodedValue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.write_text(f, 2)
+  }
+}
+
+fn write_json_string<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+  write!(writer, "\"")?;
+  for c in value.chars() {
+    match c {
+      '"' => write!(writer, "\\\"")?,
+      '\\' => write!(writer, "\\\\")?,
+      '\n' => write!(writer, "\\n")?,
+      c if (c as u32) < 0x20 => write!(writer, "\\u{:04x}", c as u32)?,
+      c => write!(writer, "{}", c)?,
+    }
+  }
+  write!(writer, "\"")
+}
+
+//Teaches the crate a section layout it does not know. Returning None falls back to the built
+//in decoding.
+pub trait SectionDecoder: Send + Sync {
+  fn decode(&self, elf: &Elf, section: &SectionHeader, data: &[u8]) -> Option<DecodedValue>;
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SectionKey {
+  Name(String),
+  Type(u32),
+}
+
+//Registered decoders. Name keys are tried before type keys, so a decoder for one vendor
+//section wins over one for its whole SHT_LOUSER range.
+#[derive(Default)]
+pub struct SectionDecoders {
+  decoders: Vec<(SectionKey, Box<dyn SectionDecoder>)>,
+}
+
+impl SectionDecoders {
+  pub fn new() -> SectionDecoders {
+    Default::default()
+  }
+
+  pub fn register<D: SectionDecoder + 'static>(&mut self, key: SectionKey, decoder: D) {
+    self.decoders.push((key, Box::new(decoder)));
+  }
+
+  pub fn decoder_for(&self, elf: &Elf, section: &SectionHeader) -> Option<&dyn SectionDecoder> {
+    let name = elf.section_name(section);
+    self.decoders.iter()
+      .find(|(key, _)| matches!(key, SectionKey::Name(key) if Some(key.as_str()) == name))
+      .or_else(|| self.decoders.iter().find(|(key, _)| *key == SectionKey::Type(section.section_type)))
+      .map(|(_, decoder)| &**decoder)
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DumpFormat {
+  Text,
+  Json,
+}
+
+fn content_summary(content: &SectionContent<'_>) -> String {
+  match content {
+    SectionContent::Empty => "empty".to_string(),
+    SectionContent::Dynamic(entries) => match DynamicFlags::from_entries(entries) {
+      flags if flags.is_empty() => format!("{} dynamic entries", entries.len()),
+      flags => format!("{} dynamic entries, {}", entries.len(), flags),
+    },
+    SectionContent::Pointers(words) => format!("{} pointers", words.len()),
+    SectionContent::FunctionPointers(words) => format!("{} function pointers", words.len()),
+    SectionContent::EhFrameHeader(header) => format!("{} FDEs", header.fde_count),
+    SectionContent::Symbols(symbols) => format!("{} symbols", symbols.len()),
+    SectionContent::Relocations(relocations) => format!("{} relocations", relocations.len()),
+    SectionContent::Notes(notes) => format!("{} notes", notes.len()),
+    SectionContent::Strings(strings) => format!("{} strings", strings.len()),
+    SectionContent::Raw(data) => format!("{} bytes", data.len()),
+    SectionContent::Custom(_) => "custom".to_string(),
+  }
+}
+
+impl Elf {
+  //section_content with the registered decoders tried first
+  pub fn section_content_with(&self, index: usize, decoders: &SectionDecoders) -> Option<SectionContent<'_>> {
+    let section = self.section_headers.get(index)?;
+    if let Some(decoder) = decoders.decoder_for(self, section) {
+      if let Some(value) = decoder.decode(self, section, self.section_data(section)) {
+        return Some(SectionContent::Custom(value));
+      }
+    }
+    self.section_content(index)
+  }
+
+  //Every section with its address, size and decoded contents: the tree a registered decoder
+  //returned, a summary of the built in decoding otherwise.
+  pub fn write_section_dump<W: Write>(&self, decoders: &SectionDecoders, format: DumpFormat, writer: &mut W) -> io::Result<()> {
+    if format == DumpFormat::Json {
+      write!(writer, "[")?;
+    }
+    for (index, section) in self.section_headers.iter().enumerate() {
+      let name = self.section_name(section).unwrap_or("");
+      let content = match self.section_content_with(index, decoders) {
+        Some(content) => content,
+        None => continue,
+      };
+      match format {
+        DumpFormat::Text => {
+          write!(writer, "[{:2}] {} type {:#x} address {:#x} size {:#x}: ", index, name, section.section_type, section.address, section.size)?;
+          match &content {
+            SectionContent::Custom(value) => writeln!(writer, "{}", value)?,
+            content => writeln!(writer, "{}", content_summary(content))?,
+          }
+        },
+        DumpFormat::Json => {
+          if index > 0 {
+            write!(writer, ",")?;
+          }
+          write!(writer, "{{\"index\":{},\"name\":", index)?;
+          write_json_string(writer, name)?;
+          write!(writer, ",\"type\":{},\"address\":{},\"size\":{},", section.section_type, section.address, section.size)?;
+          match &content {
+            SectionContent::Custom(value) => {
+              write!(writer, "\"decoded\":")?;
+              value.write_json(writer)?;
+            },
+            content => {
+              write!(writer, "\"summary\":")?;
+              write_json_string(writer, &content_summary(content))?;
+            },
+          }
+          write!(writer, "}}")?;
+        },
+      }
+    }
+    if format == DumpFormat::Json {
+      writeln!(writer, "]")?;
+    }
+    Ok(())
+  }
+}
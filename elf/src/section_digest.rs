@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use crate::consts::*;
+use crate::elf::Elf;
+#[cfg(feature = "blake3")]
+use crate::digest::blake3;
+#[cfg(feature = "sha2")]
+use crate::digest::sha256;
+
+//What section_digests hashes with, each behind the feature of the same name. More can come,
+//so matches need a wildcard arm.
+#[non_exhaustive]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DigestAlgorithm {
+  #[cfg(feature = "sha2")]
+  Sha256,
+  #[cfg(feature = "blake3")]
+  Blake3,
+}
+
+impl DigestAlgorithm {
+  //without either feature there is nothing to hash with
+  #[cfg_attr(not(any(feature = "sha2", feature = "blake3")), allow(unused_variables))]
+  pub fn digest(self, data: &[u8]) -> Vec<u8> {
+    match self {
+      #[cfg(feature = "sha2")]
+      DigestAlgorithm::Sha256 => sha256(data),
+      #[cfg(feature = "blake3")]
+      DigestAlgorithm::Blake3 => blake3(data).to_vec(),
+    }
+  }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum DigestTarget {
+  Section { index: usize, name: String },
+  //a PT_LOAD, by its index in the program header table
+  Segment { index: usize },
+}
+
+impl DigestTarget {
+  //what the target is matched by across builds: the section name, or "LOAD" and the number
+  //of the PT_LOAD among the loads
+  fn key(&self, load_number: usize) -> String {
+    match self {
+      DigestTarget::Section { name, .. } => name.clone(),
+      DigestTarget::Segment { .. } => format!("LOAD[{}]", load_number),
+    }
+  }
+}
+
+#[derive(Clone)]
+pub struct SectionDigest {
+  pub target: DigestTarget,
+  pub offset: u64,
+  //sh_size or p_filesz. SHT_NOBITS sections hash no bytes, their size still tells builds apart.
+  pub size: u64,
+  pub digest: Vec<u8>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DigestChangeKind {
+  Added,
+  Removed,
+  Changed,
+}
+
+#[derive(Clone)]
+pub struct DigestChange {
+  //a section name or "LOAD[n]"
+  pub name: String,
+  pub kind: DigestChangeKind,
+}
+
+fn keyed(digests: &[SectionDigest]) -> Vec<(String, &SectionDigest)> {
+  let mut loads = 0;
+  let mut seen: HashMap<String, usize> = HashMap::new();
+  digests.iter().map(|digest| {
+    let mut key = digest.target.key(loads);
+    if let DigestTarget::Segment { .. } = digest.target {
+      loads += 1;
+    }
+    //sections of one name, e.g. the .text of each function in objects built with
+    //-ffunction-sections and no unique names, match in order
+    let count = seen.entry(key.clone()).or_insert(0);
+    if *count > 0 {
+      key = format!("{}#{}", key, count);
+    }
+    *count += 1;
+    (key, digest)
+  }).collect()
+}
+
+//What differs between two baselines of section_digests, taken with the same algorithm. The
+//sections are matched by name, so a section that only moved is not a change.
+pub fn digest_changes(before: &[SectionDigest], after: &[SectionDigest]) -> Vec<DigestChange> {
+  let before = keyed(before);
+  let after = keyed(after);
+  let old: HashMap<&str, &SectionDigest> = before.iter().map(|(key, digest)| (key.as_str(), *digest)).collect();
+  let new: HashMap<&str, &SectionDigest> = after.iter().map(|(key, digest)| (key.as_str(), *digest)).collect();
+  let mut changes = Vec::new();
+  for (key, digest) in &after {
+    match old.get(key.as_str()) {
+      None => changes.push(DigestChange { name: key.clone(), kind: DigestChangeKind::Added }),
+      Some(previous) if previous.digest != digest.digest || previous.size != digest.size => {
+        changes.push(DigestChange { name: key.clone(), kind: DigestChangeKind::Changed });
+      },
+      Some(_) => {},
+    };
+  }
+  for (key, _) in before.iter().filter(|(key, _)| !new.contains_key(key.as_str())) {
+    changes.push(DigestChange { name: key.clone(), kind: DigestChangeKind::Removed });
+  }
+  changes
+}
+
+impl Elf {
+  //A digest of the file bytes of every section, then of every PT_LOAD segment. Sections
+  //cover what the linker produced, segments what is mapped, headers and padding included.
+  pub fn section_digests(&self, algorithm: DigestAlgorithm) -> Vec<SectionDigest> {
+    let mut digests = Vec::new();
+    for (index, section) in self.section_headers.iter().enumerate() {
+      let data = self.section_data(section);
+      let name = self.section_name(section).unwrap_or("").to_string();
+      digests.push(SectionDigest { target: DigestTarget::Section { index, name }, offset: section.offset, size: section.size, digest: algorithm.digest(data) });
+    }
+    for (index, ph) in self.program_headers.iter().enumerate().filter(|(_, ph)| ph.entry_type == PT_LOAD) {
+      let data = self.data.get(ph.offset as usize..ph.offset.saturating_add(ph.file_size) as usize).unwrap_or(&[]);
+      digests.push(SectionDigest { target: DigestTarget::Segment { index }, offset: ph.offset, size: ph.file_size, digest: algorithm.digest(data) });
+    }
+    digests
+  }
+}
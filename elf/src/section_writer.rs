@@ -0,0 +1,252 @@
+use std::io;
+use std::marker::PhantomData;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::dynamic::DynamicEntry;
+use crate::elf::{Elf, ElfDescription, ElfHeader, ElfIdentification, ProgramHeader, SectionHeader};
+use crate::header::{ELF_MAGIC, EV_CURRENT};
+use crate::layout::*;
+use crate::relocation::Relocation;
+use crate::symbol::Symbol;
+
+//appends to a string table, returns the offset
+pub(crate) fn string(table: &mut Vec<u8>, name: &str) -> u32 {
+  let offset = table.len() as u32;
+  table.extend_from_slice(name.as_bytes());
+  table.push(0);
+  offset
+}
+
+//A file written from nothing. The caller lays out the contents in file order and describes
+//the sections and segments with the wide types, finish() adds .shstrtab and the section header
+//table and writes the header and program headers in front through the raw structures of L.
+pub(crate) struct ObjectWriter<E: ByteOrder, L: ElfLayout> {
+  pub(crate) header: ElfHeader,
+  pub(crate) segments: Vec<ProgramHeader>,
+  sections: Vec<SectionHeader>,
+  section_names: Vec<u8>,
+  data: Vec<u8>,
+  program_headers: usize,
+  layout: PhantomData<(E, L)>,
+}
+
+impl<E: ByteOrder, L: ElfLayout> ObjectWriter<E, L> {
+  //the contents start after the header and room for `program_headers` entries
+  pub(crate) fn new(endianness: u8, obj_type: u16, machine: u16, program_headers: usize) -> ObjectWriter<E, L> {
+    let header = ElfHeader {
+      identification: ElfIdentification { magic: ELF_MAGIC, class: L::CLASS, endianness, version: EV_CURRENT as u8, ..Default::default() },
+      description: ElfDescription {
+        obj_type,
+        machine,
+        version: EV_CURRENT,
+        elf_hdr_size: L::Header::SIZE as u16,
+        section_hdr_entry_size: L::SectionHeader::SIZE as u16,
+        ..Default::default()
+      },
+    };
+    ObjectWriter {
+      header,
+      segments: Vec::new(),
+      sections: vec![SectionHeader::default()],
+      section_names: b"\0".to_vec(),
+      data: vec![0; L::Header::SIZE + program_headers * L::ProgramHeader::SIZE],
+      program_headers,
+      layout: PhantomData,
+    }
+  }
+
+  pub(crate) fn header_size() -> u64 {
+    L::Header::SIZE as u64
+  }
+
+  pub(crate) fn program_header_size() -> u64 {
+    L::ProgramHeader::SIZE as u64
+  }
+
+  //address sized
+  pub(crate) fn word_size() -> u64 {
+    if L::CLASS == ELFCLASS64 { 8 } else { 4 }
+  }
+
+  pub(crate) fn symbol_size() -> u64 {
+    L::Symbol::SIZE as u64
+  }
+
+  pub(crate) fn relocation_size(rela: bool) -> u64 {
+    if rela { L::Rela::SIZE as u64 } else { L::Rel::SIZE as u64 }
+  }
+
+  pub(crate) fn dynamic_size() -> u64 {
+    L::Dynamic::SIZE as u64
+  }
+
+  pub(crate) fn position(&self) -> u64 {
+    self.data.len() as u64
+  }
+
+  pub(crate) fn align(&mut self, align: u64) {
+    let position = self.position().next_multiple_of(align.max(1));
+    self.pad_to(position);
+  }
+
+  //zeros up to `offset`, which is not behind the contents so far
+  pub(crate) fn pad_to(&mut self, offset: u64) {
+    debug_assert!(offset >= self.position());
+    self.data.resize(offset as usize, 0);
+  }
+
+  //returns the offset of the bytes
+  pub(crate) fn bytes(&mut self, bytes: &[u8]) -> u64 {
+    let offset = self.position();
+    self.data.extend_from_slice(bytes);
+    offset
+  }
+
+  pub(crate) fn u16(&mut self, value: u16) {
+    let mut bytes = [0; 2];
+    E::write_u16(&mut bytes, value);
+    self.data.extend_from_slice(&bytes);
+  }
+
+  pub(crate) fn u32(&mut self, value: u32) {
+    let mut bytes = [0; 4];
+    E::write_u32(&mut bytes, value);
+    self.data.extend_from_slice(&bytes);
+  }
+
+  pub(crate) fn u64(&mut self, value: u64) {
+    let mut bytes = [0; 8];
+    E::write_u64(&mut bytes, value);
+    self.data.extend_from_slice(&bytes);
+  }
+
+  fn raw<R: RawLayout>(&mut self, raw: &R) {
+    let start = self.data.len();
+    self.data.resize(start + R::SIZE, 0);
+    raw.write::<E>(&mut self.data[start..]).unwrap();
+  }
+
+  pub(crate) fn symbol(&mut self, name_index: u32, symbol: &Symbol) {
+    let mut raw = L::Symbol::narrow(symbol);
+    raw.set_name_index(name_index);
+    self.raw(&raw);
+  }
+
+  pub(crate) fn dynamic(&mut self, entry: &DynamicEntry) {
+    self.raw(&L::Dynamic::narrow(entry));
+  }
+
+  pub(crate) fn relocation(&mut self, relocation: &Relocation, rela: bool) {
+    if rela {
+      self.raw(&L::Rela::narrow(relocation));
+    } else {
+      self.raw(&L::Rel::narrow(relocation));
+    }
+  }
+
+  //the next section index
+  pub(crate) fn section_count(&self) -> u32 {
+    self.sections.len() as u32
+  }
+
+  //`section` with its name_index set, returns its index
+  pub(crate) fn section(&mut self, name: &str, mut section: SectionHeader) -> u32 {
+    section.name_index = string(&mut self.section_names, name);
+    self.sections.push(section);
+    self.sections.len() as u32 - 1
+  }
+
+  pub(crate) fn finish(mut self) -> Vec<u8> {
+    assert!(self.segments.len() <= self.program_headers, "more program headers than room was left for");
+    if self.sections.len() > 1 {
+      let name_index = string(&mut self.section_names, ".shstrtab");
+      let names = std::mem::take(&mut self.section_names);
+      let offset = self.bytes(&names);
+      self.header.description.section_hdr_str_index = self.sections.len() as u16;
+      self.sections.push(SectionHeader { name_index, section_type: SHT_STRTAB, offset, size: names.len() as u64, align: 1, ..Default::default() });
+      self.align(Self::word_size());
+      self.header.description.section_hdr_offset = self.position();
+      self.header.description.section_hdr_num = self.sections.len() as u16;
+      for section in std::mem::take(&mut self.sections) {
+        self.raw(&L::SectionHeader::narrow(&section));
+      }
+    }
+    if !self.segments.is_empty() {
+      self.header.description.program_hdr_offset = Self::header_size();
+      self.header.description.program_hdr_entry_size = L::ProgramHeader::SIZE as u16;
+      self.header.description.program_hdr_num = self.segments.len() as u16;
+    }
+    L::Header::narrow(&self.header).write::<E>(&mut self.data).unwrap();
+    for (index, segment) in self.segments.iter().enumerate() {
+      let start = L::Header::SIZE + index * L::ProgramHeader::SIZE;
+      L::ProgramHeader::narrow(segment).write::<E>(&mut self.data[start..]).unwrap();
+    }
+    self.data
+  }
+}
+
+impl Elf {
+  //Appends a section that is not loaded, e.g. a note for tools. The contents, a copy of the
+  //section name string table with the new name and a new section header table go to the end
+  //of the file, the old table and names are left in place unreferenced. Returns the index of
+  //the new section.
+  pub fn add_section(&mut self, name: &str, section_type: u32, flags: u64, align: u64, contents: &[u8]) -> io::Result<usize> {
+    if flags & SHF_ALLOC != 0 {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "an SHF_ALLOC section needs a segment to be loaded from"));
+    }
+    if self.header.description.section_hdr_offset == 0 || self.section_headers.is_empty() {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "the file has no section header table"));
+    }
+    let string_index = self.header.description.section_hdr_str_index as usize;
+    //extended numbering keeps the counts in entry 0, not rewritten here
+    if self.section_headers.len() + 1 >= SHN_LORESERVE as usize || string_index >= self.section_headers.len() {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "extended section numbering is not supported"));
+    }
+    let word = if self.header.identification.class == 1 { 4 } else { 8 };
+    let pad = |data: &mut Vec<u8>, align: u64| {
+      while !(data.len() as u64).is_multiple_of(align.max(1)) {
+        data.push(0);
+      }
+    };
+
+    let mut data = self.data.to_vec();
+    pad(&mut data, align);
+    let mut section = SectionHeader { section_type, flags, offset: data.len() as u64, size: contents.len() as u64, align, ..Default::default() };
+    data.extend_from_slice(contents);
+
+    let mut strings = self.section_data(&self.section_headers[string_index]).to_vec();
+    section.name_index = strings.len() as u32;
+    strings.extend_from_slice(name.as_bytes());
+    strings.push(0);
+    let mut string_table = self.section_headers[string_index].clone();
+    string_table.offset = data.len() as u64;
+    string_table.size = strings.len() as u64;
+    data.extend_from_slice(&strings);
+
+    pad(&mut data, word);
+    let table_offset = data.len();
+    let entry_size = self.expected_section_hdr_entry_size() as usize;
+    let mut sections = self.section_headers.clone();
+    sections[string_index] = string_table;
+    sections.push(section);
+    data.resize(table_offset + sections.len() * entry_size, 0);
+    let class = self.header.identification.class;
+    for (index, entry) in sections.iter().enumerate() {
+      let start = table_offset + index * entry_size;
+      let entry_data = &mut data[start..start + entry_size];
+      match self.header.identification.endianness {
+        1 => Elf::write_section_header::<LittleEndian>(class, entry_data, entry),
+        2 => Elf::write_section_header::<BigEndian>(class, entry_data, entry),
+        _ => panic!("unknown endianness"),
+      };
+    }
+
+    self.data = data.into_boxed_slice();
+    self.header.description.section_hdr_offset = table_offset as u64;
+    self.header.description.section_hdr_entry_size = entry_size as u16;
+    self.header.description.section_hdr_num = sections.len() as u16;
+    self.write_header();
+    self.reload_tables();
+    Ok(sections.len() - 1)
+  }
+}
@@ -0,0 +1,102 @@
+use crate::build_attributes::HardeningFlags;
+use crate::consts::*;
+use crate::dynamic::DynamicFlags;
+use crate::elf::Elf;
+
+pub const FORTIFIABLE_FUNCTIONS: &[&str] = &[
+  "asprintf", "confstr", "dprintf", "explicit_bzero", "fgets", "fgets_unlocked", "fgetws",
+  "fgetws_unlocked", "fprintf", "fread", "fread_unlocked", "fwprintf", "getcwd", "getdomainname",
+  "getgroups", "gethostname", "getlogin_r", "gets", "getwd", "longjmp", "mbsnrtowcs", "mbsrtowcs",
+  "mbstowcs", "memcpy", "memmove", "mempcpy", "memset", "obstack_printf", "obstack_vprintf", "poll",
+  "ppoll", "pread", "pread64", "printf", "ptsname_r", "read", "readlink", "readlinkat", "realpath",
+  "recv", "recvfrom", "snprintf", "sprintf", "stpcpy", "stpncpy", "strcat", "strcpy", "strncat",
+  "strncpy", "swprintf", "syslog", "ttyname_r", "vasprintf", "vdprintf", "vfprintf", "vfwprintf",
+  "vprintf", "vsnprintf", "vsprintf", "vswprintf", "vsyslog", "vwprintf", "wcpcpy", "wcpncpy",
+  "wcrtomb", "wcscat", "wcscpy", "wcsncat", "wcsncpy", "wcsnrtombs", "wcsrtombs", "wcstombs", "wctomb",
+  "wmemcpy", "wmemmove", "wmempcpy", "wmemset", "wprintf",
+];
+
+//checked helpers of macros, which expand inline and have no unprotected import; __fdelt_chk
+//checks the descriptor of FD_SET, FD_CLR and FD_ISSET
+pub const FORTIFIED_MACROS: &[(&str, &str)] = &[("__fdelt_chk", "FD_SET")];
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum Relro {
+  None,
+  Partial,
+  Full,
+}
+
+#[derive(Default)]
+pub struct FortifyReport {
+  //fortifiable functions imported through their __*_chk variant
+  pub fortified: Vec<String>,
+  //fortifiable functions imported without protection
+  pub unprotected: Vec<String>,
+}
+
+pub struct SecurityReport {
+  pub pie: bool,
+  pub nx: bool,
+  pub relro: Relro,
+  pub stack_canary: bool,
+  pub fortify: FortifyReport,
+  pub hardening: HardeningFlags,
+  pub dynamic_flags: DynamicFlags,
+}
+
+fn base_name(name: &str) -> &str {
+  match name.find('@') {
+    Some(end) => &name[..end],
+    None => name,
+  }
+}
+
+impl Elf {
+  pub fn fortify_report(&self) -> FortifyReport {
+    let mut report: FortifyReport = Default::default();
+    let mut imports: Vec<String> = self.imports().iter().map(|symbol| base_name(&symbol.name).to_string()).collect();
+    imports.sort();
+    imports.dedup();
+    for name in &imports {
+      if let Some(&(_, function)) = FORTIFIED_MACROS.iter().find(|(helper, _)| helper == name) {
+        report.fortified.push(function.to_string());
+      } else if let Some(function) = name.strip_prefix("__").and_then(|name| name.strip_suffix("_chk")) {
+        if FORTIFIABLE_FUNCTIONS.contains(&function) {
+          report.fortified.push(function.to_string());
+        }
+      } else if FORTIFIABLE_FUNCTIONS.contains(&name.as_str()) {
+        report.unprotected.push(name.clone());
+      }
+    }
+    report
+  }
+
+  pub fn bind_now(&self) -> bool {
+    let entries = self.dynamic_entries();
+    entries.iter().any(|entry| entry.tag == DT_BIND_NOW) || DynamicFlags::from_entries(&entries).bind_now()
+  }
+
+  pub fn security_report(&self) -> SecurityReport {
+    let stack = self.program_headers.iter().find(|ph| ph.entry_type == PT_GNU_STACK);
+    let relro = match self.program_headers.iter().any(|ph| ph.entry_type == PT_GNU_RELRO) {
+      false => Relro::None,
+      true if self.bind_now() => Relro::Full,
+      true => Relro::Partial,
+    };
+    let dynamic_flags = self.dynamic_flags();
+    let stack_canary = self.imports().iter().chain(self.symbols().iter())
+      .any(|symbol| matches!(base_name(&symbol.name), "__stack_chk_fail" | "__stack_chk_guard" | "__stack_chk_fail_local"));
+    SecurityReport {
+      pie: self.header.description.obj_type == ET_DYN
+        && (self.program_headers.iter().any(|ph| ph.entry_type == PT_INTERP) || dynamic_flags.flags_1 & DF_1_PIE != 0),
+      nx: stack.is_some_and(|ph| ph.flags & PF_X == 0),
+      relro,
+      stack_canary,
+      fortify: self.fortify_report(),
+      //no build attributes can be read in an unknown byte order
+      hardening: self.hardening_flags().unwrap_or_default(),
+      dynamic_flags,
+    }
+  }
+}
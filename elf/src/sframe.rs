@@ -0,0 +1,210 @@
+use crate::cfi::*;
+use crate::consts::*;
+use crate::dwarf::Reader;
+use crate::elf::Elf;
+
+pub const SFRAME_MAGIC: u64 = 0xdee2;
+pub const SFRAME_F_FDE_SORTED: u8 = 0x1;
+pub const SFRAME_F_FRAME_POINTER: u8 = 0x2;
+//function start addresses are relative to the FDE's own field, not the section
+pub const SFRAME_F_FDE_FUNC_START_PCREL: u8 = 0x4;
+pub const SFRAME_ABI_AARCH64_ENDIAN_BIG: u8 = 1;
+pub const SFRAME_ABI_AARCH64_ENDIAN_LITTLE: u8 = 2;
+pub const SFRAME_ABI_AMD64_ENDIAN_LITTLE: u8 = 3;
+pub const SFRAME_ABI_S390X_ENDIAN_BIG: u8 = 4;
+
+const HEADER_SIZE: usize = 28;
+const FRE_TYPE_ADDR1: u8 = 0;
+const FRE_TYPE_ADDR2: u8 = 1;
+const FRE_TYPE_ADDR4: u8 = 2;
+const FDE_TYPE_PCMASK: u8 = 1;
+//version 1 has no repetition size in the FDE, its PCMASK functions are PLTs of 16 byte entries
+const DEFAULT_REPETITION_SIZE: u64 = 16;
+//rows a PCMASK function expands to, a PLT of 32768 entries with two rows each
+const MAX_PCMASK_ROWS: usize = 1 << 16;
+
+#[derive(Clone, Debug)]
+pub struct SFrameHeader {
+  pub version: u8,
+  pub flags: u8,
+  pub abi_arch: u8,
+  //0 when the offset is recorded in every FRE instead
+  pub cfa_fixed_fp_offset: i8,
+  pub cfa_fixed_ra_offset: i8,
+  pub auxiliary_header_size: u8,
+  pub fde_count: u32,
+  pub fre_count: u32,
+  pub fre_size: u32,
+  //relative to the end of the header, the auxiliary one included
+  pub fde_offset: u32,
+  pub fre_offset: u32,
+}
+
+impl SFrameHeader {
+  //(stack pointer, frame pointer, return address) in DWARF numbering, None for ABIs whose
+  //FREs are not plain CFA offsets (s390x scales them and can name registers)
+  fn registers(&self) -> Option<(u16, u16, u16)> {
+    match self.abi_arch {
+      SFRAME_ABI_AMD64_ENDIAN_LITTLE => Some((7, 6, 16)),
+      SFRAME_ABI_AARCH64_ENDIAN_BIG | SFRAME_ABI_AARCH64_ENDIAN_LITTLE => Some((31, 29, 30)),
+      _ => None,
+    }
+  }
+}
+
+//A .sframe section, the stack trace format of binutils 2.40 and later: per function, the
+//CFA as an offset from the stack or frame pointer and where the frame pointer and return
+//address are saved, enough to unwind without frame pointers or running CFI programs.
+pub struct SFrame<'a> {
+  pub data: &'a [u8],
+  //virtual address of the section
+  pub address: u64,
+  pub big_endian: bool,
+}
+
+impl<'a> SFrame<'a> {
+  fn reader(&self, position: usize) -> Reader<'a> {
+    Reader { data: self.data, position, big_endian: self.big_endian }
+  }
+
+  //None when the data is not SFrame version 1 or 2
+  pub fn header(&self) -> Option<SFrameHeader> {
+    let mut reader = self.reader(0);
+    if reader.unsigned(2)? != SFRAME_MAGIC {
+      return None;
+    }
+    let version = reader.unsigned(1)? as u8;
+    if version != 1 && version != 2 {
+      return None;
+    }
+    Some(SFrameHeader {
+      version,
+      flags: reader.unsigned(1)? as u8,
+      abi_arch: reader.unsigned(1)? as u8,
+      cfa_fixed_fp_offset: reader.unsigned(1)? as u8 as i8,
+      cfa_fixed_ra_offset: reader.unsigned(1)? as u8 as i8,
+      auxiliary_header_size: reader.unsigned(1)? as u8,
+      fde_count: reader.unsigned(4)? as u32,
+      fre_count: reader.unsigned(4)? as u32,
+      fre_size: reader.unsigned(4)? as u32,
+      fde_offset: reader.unsigned(4)? as u32,
+      fre_offset: reader.unsigned(4)? as u32,
+    })
+  }
+
+  fn frame_description(&self, header: &SFrameHeader, position: usize) -> Option<FrameDescription> {
+    let (stack_pointer, frame_pointer, return_address) = header.registers()?;
+    let mut reader = self.reader(position);
+    let start = reader.unsigned(4)? as u32 as i32 as i64;
+    let size = reader.unsigned(4)?;
+    let fre_start = reader.unsigned(4)? as usize;
+    let fre_count = reader.unsigned(4)?;
+    let info = reader.unsigned(1)? as u8;
+    let repetition_size = if header.version >= 2 { reader.unsigned(1)? } else { 0 };
+    let base = if header.flags & SFRAME_F_FDE_FUNC_START_PCREL != 0 { self.address.wrapping_add(position as u64) } else { self.address };
+    let address = base.wrapping_add(start as u64);
+
+    let address_size = match info & 0x0f {
+      FRE_TYPE_ADDR1 => 1,
+      FRE_TYPE_ADDR2 => 2,
+      FRE_TYPE_ADDR4 => 4,
+      _ => return None,
+    };
+    let fres = HEADER_SIZE + header.auxiliary_header_size as usize + header.fre_offset as usize;
+    let mut reader = self.reader(fres.checked_add(fre_start)?);
+    //(start offset in the function, row without its address)
+    let mut entries = Vec::new();
+    for _ in 0..fre_count {
+      let start = reader.unsigned(address_size)?;
+      let fre_info = reader.unsigned(1)? as u8;
+      let offset_size = match (fre_info >> 5) & 0x3 {
+        3 => return None,
+        size => 1 << size,
+      };
+      let offsets = (0..((fre_info >> 1) & 0x0f))
+        .map(|_| reader.unsigned(offset_size).map(|offset| (offset << (64 - 8 * offset_size)) as i64 >> (64 - 8 * offset_size)))
+        .collect::<Option<Vec<i64>>>()?;
+      let cfa_register = if fre_info & 0x1 != 0 { stack_pointer } else { frame_pointer };
+      let mut row = UnwindRow { address: 0, cfa: CfaRule::RegisterOffset(cfa_register, *offsets.first()?), registers: Vec::new() };
+      //a fixed offset in the header leaves the value out of the FREs: [CFA, RA, FP] without
+      //either, [CFA, FP] on x86-64. Bit 7 marks an AArch64 return address signed with PAC.
+      let fixed_ra = Some(header.cfa_fixed_ra_offset as i64).filter(|&offset| offset != 0);
+      let fixed_fp = Some(header.cfa_fixed_fp_offset as i64).filter(|&offset| offset != 0);
+      let ra = fixed_ra.or_else(|| offsets.get(1).copied());
+      let fp = fixed_fp.or_else(|| offsets.get(if fixed_ra.is_some() { 1 } else { 2 }).copied());
+      row.set_register(return_address, ra.map(RegisterRule::Offset));
+      row.set_register(frame_pointer, fp.map(RegisterRule::Offset));
+      entries.push((start, row));
+    }
+
+    //PCMASK functions (PLTs) repeat their rows every repetition_size bytes
+    let repetition = match (info >> 4) & 0x1 {
+      FDE_TYPE_PCMASK if repetition_size > 0 => repetition_size,
+      FDE_TYPE_PCMASK => DEFAULT_REPETITION_SIZE,
+      _ => size.max(1),
+    };
+    let mut rows = Vec::new();
+    let mut block = 0;
+    'blocks: while block < size {
+      for (start, row) in &entries {
+        match block.checked_add(*start) {
+          Some(offset) if offset < size => {
+            if rows.len() == MAX_PCMASK_ROWS {
+              break 'blocks;
+            }
+            rows.push(UnwindRow { address: address.wrapping_add(offset), ..row.clone() });
+          },
+          _ => {},
+        }
+      }
+      block = match block.checked_add(repetition) {
+        Some(block) => block,
+        None => break,
+      };
+    }
+    Some(FrameDescription {
+      address,
+      size,
+      return_address_register: return_address,
+      signal_frame: false,
+      personality: None,
+      lsda: None,
+      rows,
+    })
+  }
+}
+
+impl<'a> UnwindSource for SFrame<'a> {
+  fn frame_descriptions(&self) -> Vec<FrameDescription> {
+    let header = match self.header() {
+      Some(header) => header,
+      None => return Vec::new(),
+    };
+    let fde_size = if header.version == 1 { 17 } else { 20 };
+    let fdes = HEADER_SIZE + header.auxiliary_header_size as usize + header.fde_offset as usize;
+    let mut frames = Vec::new();
+    for index in 0..header.fde_count as usize {
+      match self.frame_description(&header, fdes + index * fde_size) {
+        Some(frame) if frame.size > 0 => frames.push(frame),
+        Some(_) => {},
+        None => break,
+      };
+    }
+    frames
+  }
+}
+
+impl Elf {
+  //The .sframe section, or the PT_GNU_SFRAME segment of a stripped file.
+  pub fn sframe(&self) -> Option<SFrame<'_>> {
+    let big_endian = self.header.identification.endianness == 2;
+    let section = self.section_headers.iter()
+      .find(|section| section.section_type == SHT_GNU_SFRAME || self.section_name(section) == Some(".sframe"));
+    if let Some(section) = section {
+      return Some(SFrame { data: self.section_data(section), address: section.address, big_endian });
+    }
+    let segment = self.program_headers.iter().find(|ph| ph.entry_type == PT_GNU_SFRAME)?;
+    let data = self.data.get(segment.offset as usize..segment.offset.saturating_add(segment.file_size) as usize)?;
+    Some(SFrame { data, address: segment.virtual_address, big_endian })
+  }
+}
@@ -0,0 +1,282 @@
+use std::io;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::dynamic::DynamicEntry;
+use crate::elf::{Elf, ProgramHeader, SectionHeader};
+use crate::layout::Elf64;
+use crate::relocation::Relocation;
+use crate::section_writer::{self, ObjectWriter};
+use crate::symbol::Symbol;
+
+//PLT entries are this long, a jump through the .got.plt slot and padding
+const STUB_SIZE: u64 = 16;
+
+//the SysV hash of .hash and of version names
+fn elf_hash(name: &str) -> u32 {
+  let mut hash: u32 = 0;
+  for &byte in name.as_bytes() {
+    hash = (hash << 4).wrapping_add(byte as u32);
+    let high = hash & 0xf000_0000;
+    if high != 0 {
+      hash ^= high >> 24;
+    }
+    hash &= !high;
+  }
+  hash
+}
+
+fn align_to(value: u64, align: u64) -> u64 {
+  value.div_ceil(align) * align
+}
+
+//the strings of the version definitions and requirements
+struct ShimNames<'a> {
+  soname: &'a str,
+  version: &'a str,
+  target: &'a str,
+  //the versions of this library the symbols are imported at
+  requirements: &'a [String],
+}
+
+//one re-exported function
+struct ShimSymbol<'a> {
+  name: &'a str,
+  //the version of this library it is imported at, as an index of the version requirements
+  requirement: Option<usize>,
+}
+
+impl Elf {
+  //A shared object named `soname` that defines each of `symbols` as a PLT entry jumping to the
+  //function of the same name in this library, which becomes its DT_NEEDED. The .got.plt slots
+  //are filled by JUMP_SLOT relocations, bound at load time (DF_BIND_NOW) so that the PLT needs
+  //no lazy resolver and nothing runs in the shim. x86-64 and AArch64.
+  //
+  //The shim defines its symbols at `version`, and its imports of the same names must not
+  //resolve to those. A function this library versions is imported at its version, which the
+  //shim's definition does not match, and programs can be linked against the shim. One it does
+  //not version is defined hidden, name@version: unversioned lookups, the shim's import and
+  //those of programs linked against the library the shim stands in for, skip it and land in
+  //this library. Such a shim is a drop-in only, under the soname of a library programs were
+  //already linked against: linking against the shim itself leaves those functions undefined.
+  pub fn build_shim(&self, soname: &str, version: &str, symbols: &[&str]) -> io::Result<Vec<u8>> {
+    match self.header.description.machine {
+      EM_X86_64 | EM_AARCH64 => (),
+      _ => return Err(io::Error::new(io::ErrorKind::Unsupported, "shims are built for x86-64 and AArch64")),
+    };
+    if self.header.identification.class != ELFCLASS64 {
+      return Err(io::Error::new(io::ErrorKind::Unsupported, "shims are built for 64-bit libraries"));
+    }
+    let target = self.soname().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the library has no DT_SONAME to be needed by"))?;
+    let dynamic_symbols = self.dynamic_symbols();
+    let versions = self.symbol_versions();
+    let mut requirements: Vec<String> = Vec::new();
+    let mut shim_symbols = Vec::new();
+    for &name in symbols {
+      //the default version when there are several
+      let found = dynamic_symbols.iter().zip(&versions)
+        .filter(|(symbol, _)| symbol.is_exported() && *symbol.name == *name)
+        .min_by_key(|(_, version)| version.as_ref().is_some_and(|version| version.hidden))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{} does not export {}", target, name)))?;
+      if found.0.symbol_type != STT_FUNC && found.0.symbol_type != STT_GNU_IFUNC {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not a function, only functions are forwarded", name)));
+      }
+      let requirement = found.1.as_ref().filter(|version| version.file.is_none()).map(|version| {
+        requirements.iter().position(|known| *known == version.name).unwrap_or_else(|| {
+          requirements.push(version.name.clone());
+          requirements.len() - 1
+        })
+      });
+      shim_symbols.push(ShimSymbol { name, requirement });
+    }
+    let names = ShimNames { soname, version, target: &target, requirements: &requirements };
+    Ok(match self.header.identification.endianness {
+      1 => self.shim_with_byteorder::<LittleEndian>(&names, &shim_symbols),
+      2 => self.shim_with_byteorder::<BigEndian>(&names, &shim_symbols),
+      _ => panic!("unknown endianness"),
+    })
+  }
+
+  fn shim_with_byteorder<E: ByteOrder>(&self, names: &ShimNames, symbols: &[ShimSymbol]) -> Vec<u8> {
+    type Writer<E> = ObjectWriter<E, Elf64>;
+    let machine = self.header.description.machine;
+    let (jump_slot, page) = if machine == EM_X86_64 { (7, 0x1000) } else { (1026, 0x1_0000) };
+    let requirements = names.requirements;
+    //version indices: 1 is the shim, the versions of this library follow, then the shim's
+    //version. Unversioned lookups take a definition at index 2 even when hidden, so it is 3 or more.
+    let version_index = (2 + requirements.len() as u16).max(3);
+
+    let mut dynstr = b"\0".to_vec();
+    let soname_name = section_writer::string(&mut dynstr, names.soname);
+    let target_name = section_writer::string(&mut dynstr, names.target);
+    let version_name = section_writer::string(&mut dynstr, names.version);
+    let requirement_names: Vec<u32> = requirements.iter().map(|requirement| section_writer::string(&mut dynstr, requirement)).collect();
+    let symbol_names: Vec<u32> = symbols.iter().map(|symbol| section_writer::string(&mut dynstr, symbol.name)).collect();
+    //the null symbol, the definitions, then the imports
+    let count = 1 + 2 * symbols.len();
+
+    //the read-only segment starts with the headers, the tables follow in this order. Elf64_Verdef
+    //and Elf64_Verdaux are 20 and 8 bytes, Elf64_Verneed and Elf64_Vernaux 16 each.
+    let program_headers = 5;
+    let header_size = Writer::<E>::header_size() + program_headers as u64 * Writer::<E>::program_header_size();
+    let hash_offset = align_to(header_size, 8);
+    let hash_size = (2 + 2 * count) as u64 * 4;
+    let dynsym_offset = align_to(hash_offset + hash_size, 8);
+    let dynstr_offset = dynsym_offset + count as u64 * Writer::<E>::symbol_size();
+    let versym_offset = align_to(dynstr_offset + dynstr.len() as u64, 2);
+    let verdef_offset = align_to(versym_offset + count as u64 * 2, 8);
+    let verdef_size = 2 * (20 + 8);
+    let verneed_offset = verdef_offset + verdef_size;
+    let verneed_size = if requirements.is_empty() { 0 } else { 16 + 16 * requirements.len() as u64 };
+    let rela_offset = verneed_offset + verneed_size;
+    let rela_size = symbols.len() as u64 * Writer::<E>::relocation_size(true);
+    let text_offset = align_to(rela_offset + rela_size, page);
+    let text_size = symbols.len() as u64 * STUB_SIZE;
+    let data_offset = align_to(text_offset + text_size, page);
+    let mut dynamic_entries = vec![
+      (DT_NEEDED, target_name as u64), (DT_SONAME, soname_name as u64), (DT_HASH, hash_offset), (DT_STRTAB, dynstr_offset),
+      (DT_SYMTAB, dynsym_offset), (DT_STRSZ, dynstr.len() as u64), (DT_SYMENT, Writer::<E>::symbol_size()), (DT_PLTGOT, 0),
+      (DT_JMPREL, rela_offset), (DT_PLTRELSZ, rela_size), (DT_PLTREL, DT_RELA), (DT_FLAGS, DF_BIND_NOW), (DT_FLAGS_1, DF_1_NOW),
+      (DT_VERSYM, versym_offset), (DT_VERDEF, verdef_offset), (DT_VERDEFNUM, 2),
+    ];
+    if !requirements.is_empty() {
+      dynamic_entries.push((DT_VERNEED, verneed_offset));
+      dynamic_entries.push((DT_VERNEEDNUM, 1));
+    }
+    dynamic_entries.push((DT_NULL, 0));
+    let dynamic_size = dynamic_entries.len() as u64 * Writer::<E>::dynamic_size();
+    //.got.plt: the address of .dynamic, two words for a lazy resolver, then the slots
+    let got_offset = data_offset + dynamic_size;
+    let got_size = (3 + symbols.len() as u64) * 8;
+    for entry in dynamic_entries.iter_mut().filter(|entry| entry.0 == DT_PLTGOT) {
+      entry.1 = got_offset;
+    }
+    let stub = |index: usize| text_offset + index as u64 * STUB_SIZE;
+    let slot = |index: usize| got_offset + (3 + index as u64) * 8;
+
+    let mut w = Writer::<E>::new(self.header.identification.endianness, ET_DYN, machine, program_headers);
+    w.header.description.flags = self.header.description.flags;
+
+    w.pad_to(hash_offset);
+    w.u32(count as u32);
+    w.u32(count as u32);
+    let mut buckets = vec![0u32; count];
+    let mut chains = vec![0u32; count];
+    for (index, symbol) in symbols.iter().chain(symbols).enumerate().map(|(index, symbol)| (index + 1, symbol)) {
+      let bucket = elf_hash(symbol.name) as usize % count;
+      chains[index] = buckets[bucket];
+      buckets[bucket] = index as u32;
+    }
+    for value in buckets.into_iter().chain(chains) {
+      w.u32(value);
+    }
+
+    //.plt is section 8
+    w.pad_to(dynsym_offset);
+    w.symbol(0, &Symbol::default());
+    for (index, &name) in symbol_names.iter().enumerate() {
+      w.symbol(name, &Symbol { value: stub(index), size: STUB_SIZE, symbol_type: STT_FUNC, binding: STB_GLOBAL, section_index: 8, ..Default::default() });
+    }
+    for &name in &symbol_names {
+      w.symbol(name, &Symbol { symbol_type: STT_FUNC, binding: STB_GLOBAL, ..Default::default() });
+    }
+    w.bytes(&dynstr);
+
+    w.pad_to(versym_offset);
+    w.u16(VER_NDX_LOCAL);
+    for symbol in symbols {
+      w.u16(if symbol.requirement.is_some() { version_index } else { version_index | VERSYM_HIDDEN });
+    }
+    for symbol in symbols {
+      w.u16(symbol.requirement.map_or(VER_NDX_GLOBAL, |index| 2 + index as u16));
+    }
+
+    //a Verdef and its Verdaux for the file, then for the version
+    w.pad_to(verdef_offset);
+    for &(flags, index, name, string) in &[(VER_FLG_BASE, 1, names.soname, soname_name), (0, version_index, names.version, version_name)] {
+      w.u16(1);
+      w.u16(flags);
+      w.u16(index);
+      w.u16(1);
+      w.u32(elf_hash(name));
+      w.u32(20);
+      w.u32(if flags == VER_FLG_BASE { 28 } else { 0 });
+      w.u32(string);
+      w.u32(0);
+    }
+
+    //a Verneed for this library and a Vernaux per version
+    if !requirements.is_empty() {
+      w.u16(1);
+      w.u16(requirements.len() as u16);
+      w.u32(target_name);
+      w.u32(16);
+      w.u32(0);
+      for (index, (requirement, &string)) in requirements.iter().zip(&requirement_names).enumerate() {
+        w.u32(elf_hash(requirement));
+        w.u16(0);
+        w.u16(2 + index as u16);
+        w.u32(string);
+        w.u32(if index + 1 < requirements.len() { 16 } else { 0 });
+      }
+    }
+
+    for index in 0..symbols.len() {
+      let relocation = Relocation { section_index: 0, offset: slot(index), symbol_index: (1 + symbols.len() + index) as u32, relocation_type: jump_slot, addend: Some(0) };
+      w.relocation(&relocation, true);
+    }
+
+    //jmp *slot(%rip), or adrp x16 and ldr x17 of the slot then br x17. Instructions are
+    //little endian on AArch64 whatever the data is.
+    w.pad_to(text_offset);
+    for index in 0..symbols.len() {
+      let (address, target) = (stub(index), slot(index));
+      let mut code = Vec::new();
+      if machine == EM_X86_64 {
+        code.extend_from_slice(&[0xff, 0x25]);
+        code.extend_from_slice(&((target.wrapping_sub(address + 6)) as u32).to_le_bytes());
+      } else {
+        let pages = ((target >> 12).wrapping_sub(address >> 12)) as u32;
+        let adrp = 0x9000_0010 | (pages & 3) << 29 | ((pages >> 2) & 0x7_ffff) << 5;
+        let ldr = 0xf940_0211 | (((target & 0xfff) / 8) as u32) << 10;
+        for word in [adrp, ldr, 0xd61f_0220, 0xd503_201f] {
+          code.extend_from_slice(&u32::to_le_bytes(word));
+        }
+      }
+      code.resize(STUB_SIZE as usize, if machine == EM_X86_64 { 0xcc } else { 0 });
+      w.bytes(&code);
+    }
+
+    w.pad_to(data_offset);
+    for &(tag, value) in &dynamic_entries {
+      w.dynamic(&DynamicEntry { tag, value });
+    }
+    w.u64(data_offset);
+    w.pad_to(got_offset + got_size);
+    let data_end = w.position();
+
+    let section = |section_type: u32, flags: u64, offset: u64, size: u64, link: u32, info: u32, align: u64, entry_size: u64| {
+      SectionHeader { section_type, flags, address: offset, offset, size, link, info, align, entry_size, ..Default::default() }
+    };
+    //dynsym is 2, dynstr 3, .got.plt 10
+    w.section(".hash", section(SHT_HASH, SHF_ALLOC, hash_offset, hash_size, 2, 0, 8, 4));
+    w.section(".dynsym", section(SHT_DYNSYM, SHF_ALLOC, dynsym_offset, count as u64 * Writer::<E>::symbol_size(), 3, 1, 8, Writer::<E>::symbol_size()));
+    w.section(".dynstr", section(SHT_STRTAB, SHF_ALLOC, dynstr_offset, dynstr.len() as u64, 0, 0, 1, 0));
+    w.section(".gnu.version", section(SHT_GNU_VERSYM, SHF_ALLOC, versym_offset, count as u64 * 2, 2, 0, 2, 2));
+    w.section(".gnu.version_d", section(SHT_GNU_VERDEF, SHF_ALLOC, verdef_offset, verdef_size, 3, 2, 8, 0));
+    w.section(".gnu.version_r", section(SHT_GNU_VERNEED, SHF_ALLOC, verneed_offset, verneed_size, 3, !requirements.is_empty() as u32, 8, 0));
+    w.section(".rela.plt", section(SHT_RELA, SHF_ALLOC | SHF_INFO_LINK, rela_offset, rela_size, 2, 10, 8, Writer::<E>::relocation_size(true)));
+    w.section(".plt", section(SHT_PROGBITS, SHF_ALLOC | SHF_EXECINSTR, text_offset, text_size, 0, 0, STUB_SIZE, STUB_SIZE));
+    w.section(".dynamic", section(SHT_DYNAMIC, SHF_ALLOC | SHF_WRITE, data_offset, dynamic_size, 3, 0, 8, Writer::<E>::dynamic_size()));
+    w.section(".got.plt", section(SHT_PROGBITS, SHF_ALLOC | SHF_WRITE, got_offset, got_size, 0, 0, 8, 8));
+
+    let segment = |entry_type: u32, flags: u32, offset: u64, size: u64, align: u64| {
+      ProgramHeader { entry_type, flags, offset, virtual_address: offset, physical_address: offset, file_size: size, memory_size: size, align }
+    };
+    w.segments.push(segment(PT_LOAD, PF_R, 0, rela_offset + rela_size, page));
+    w.segments.push(segment(PT_LOAD, PF_R | PF_X, text_offset, text_size, page));
+    w.segments.push(segment(PT_LOAD, PF_R | PF_W, data_offset, data_end - data_offset, page));
+    w.segments.push(segment(PT_DYNAMIC, PF_R | PF_W, data_offset, dynamic_size, 8));
+    w.segments.push(segment(PT_GNU_STACK, PF_R | PF_W, 0, 0, 16));
+    w.finish()
+  }
+}
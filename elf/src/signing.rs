@@ -0,0 +1,163 @@
+use std::io;
+use std::ops::Range;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::ed25519;
+use crate::elf::Elf;
+
+pub const SIGNATURE_SECTION: &str = ".note.walker.signature";
+pub const SIGNATURE_OWNER: &str = "Walker";
+//prefixed to the signed view, a signature over it is never valid for another kind of message
+const SIGNATURE_CONTEXT: &[u8] = b"Walker ELF signature v1\0";
+//the public key, then the signature
+const SIGNATURE_DESC_SIZE: usize = 96;
+
+pub struct SigningKey {
+  seed: [u8; 32],
+  public_key: [u8; 32],
+}
+
+impl SigningKey {
+  //the 32 byte ed25519 private key of RFC 8032
+  pub fn from_seed(seed: [u8; 32]) -> SigningKey {
+    SigningKey { seed, public_key: ed25519::public_key(&seed) }
+  }
+
+  pub fn public_key(&self) -> [u8; 32] {
+    self.public_key
+  }
+
+  //the plain ed25519 signature of `message`, without the context files are signed with
+  pub fn sign_message(&self, message: &[u8]) -> [u8; 64] {
+    ed25519::sign(&self.seed, message)
+  }
+}
+
+pub fn verify_message(public_key: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+  ed25519::verify(public_key, message, signature)
+}
+
+//The contents of a signature note.
+#[derive(Clone, Copy)]
+pub struct FileSignature {
+  pub public_key: [u8; 32],
+  pub signature: [u8; 64],
+  //file offset of the note descriptor
+  pub desc_offset: u64,
+}
+
+impl Elf {
+  pub fn signature(&self) -> Option<FileSignature> {
+    let note = self.notes().into_iter()
+      .find(|note| note.note_type == NT_WALKER_SIGNATURE && note.name_str() == SIGNATURE_OWNER && note.desc.len() == SIGNATURE_DESC_SIZE)?;
+    let mut public_key = [0u8; 32];
+    let mut signature = [0u8; 64];
+    public_key.copy_from_slice(&note.desc[..32]);
+    signature.copy_from_slice(&note.desc[32..]);
+    Some(FileSignature { public_key, signature, desc_offset: note.desc_offset })
+  }
+
+  //What is signed: the header fields that decide how the file is loaded, every program
+  //header, and the loaded content. The section header fields of the ELF header are zeroed in
+  //it, adding the signature section moves the table, and the signature note itself is not
+  //loaded. Everything else, debug information and symbol tables included, can be stripped
+  //without breaking the signature. Files without segments are not loaded as they are, for
+  //them every section and the section header table are signed instead.
+  fn signed_view(&self) -> Vec<u8> {
+    let description = &self.header.description;
+    let identification = &self.header.identification;
+    let mut view = SIGNATURE_CONTEXT.to_vec();
+    view.extend_from_slice(&[identification.class, identification.endianness, identification.version, identification.os_abi, identification.abi_version]);
+    for field in [description.obj_type as u64, description.machine as u64, description.version as u64, description.entry, description.flags as u64] {
+      view.extend_from_slice(&field.to_le_bytes());
+    }
+    for ph in &self.program_headers {
+      for field in [ph.entry_type as u64, ph.flags as u64, ph.offset, ph.virtual_address, ph.physical_address, ph.file_size, ph.memory_size, ph.align] {
+        view.extend_from_slice(&field.to_le_bytes());
+      }
+    }
+    let masked: [Range<u64>; 2] = match identification.class {
+      1 => [0x20..0x24, 0x2e..0x34],
+      _ => [0x28..0x30, 0x3a..0x40],
+    };
+    if self.program_headers.iter().any(|ph| ph.entry_type == PT_LOAD) {
+      view.extend_from_slice(&self.loaded_content(&masked));
+    } else {
+      self.extend_with_sections(&mut view);
+    }
+    view
+  }
+
+  //The relocations, symbols and strings of an object decide what it links to as much as its
+  //code does. Every section with file content goes in, the signature note with its descriptor
+  //zeroed, then the section header table.
+  fn extend_with_sections(&self, view: &mut Vec<u8>) {
+    let descriptor = self.signature().map(|signature| signature.desc_offset..signature.desc_offset + SIGNATURE_DESC_SIZE as u64);
+    let mut ranges: Vec<(u64, u64)> = self.section_headers.iter()
+      .filter(|section| section.section_type != SHT_NULL && section.section_type != SHT_NOBITS)
+      .map(|section| (section.offset, section.size))
+      .collect();
+    let stride = self.section_hdr_stride() as u64;
+    ranges.push((self.header.description.section_hdr_offset, self.section_headers.len() as u64 * stride));
+    for (offset, size) in ranges {
+      let bytes = self.data.get(offset as usize..offset.saturating_add(size) as usize).unwrap_or(&[]);
+      view.extend_from_slice(&(bytes.len() as u64).to_le_bytes());
+      let start = view.len();
+      view.extend_from_slice(bytes);
+      if let Some(descriptor) = &descriptor {
+        for position in descriptor.start.max(offset)..descriptor.end.min(offset + bytes.len() as u64) {
+          view[start + (position - offset) as usize] = 0;
+        }
+      }
+    }
+  }
+
+  //Signs the file with `key` and stores the signature in a SIGNATURE_SECTION note, replacing
+  //the signature of an already signed file. The section is appended if the file has none.
+  pub fn sign(&mut self, key: &SigningKey) -> io::Result<()> {
+    let desc_offset = match self.signature() {
+      Some(existing) => existing.desc_offset,
+      None => {
+        let index = self.add_section(SIGNATURE_SECTION, SHT_NOTE, 0, 4, &self.signature_note())?;
+        //namesz, descsz and type, then the name padded to 4
+        self.section_headers[index].offset + 12 + ((SIGNATURE_OWNER.len() as u64 + 1 + 3) & !3)
+      },
+    };
+    let signature = ed25519::sign(&key.seed, &self.signed_view());
+    let start = desc_offset as usize;
+    self.data[start..start + 32].copy_from_slice(&key.public_key);
+    self.data[start + 32..start + SIGNATURE_DESC_SIZE].copy_from_slice(&signature);
+    Ok(())
+  }
+
+  //an empty signature note, in the byte order of the file
+  fn signature_note(&self) -> Vec<u8> {
+    let mut note = vec![0u8; 12];
+    let fields = [SIGNATURE_OWNER.len() as u32 + 1, SIGNATURE_DESC_SIZE as u32, NT_WALKER_SIGNATURE];
+    match self.header.identification.endianness {
+      1 => LittleEndian::write_u32_into(&fields, &mut note),
+      2 => BigEndian::write_u32_into(&fields, &mut note),
+      _ => panic!("unknown endianness"),
+    };
+    note.extend_from_slice(SIGNATURE_OWNER.as_bytes());
+    note.push(0);
+    while !note.len().is_multiple_of(4) {
+      note.push(0);
+    }
+    note.resize(note.len() + SIGNATURE_DESC_SIZE, 0);
+    note
+  }
+
+  //NotFound when the file carries no signature, InvalidData when it was signed with another
+  //key or its content changed after signing.
+  pub fn verify_signature(&self, public_key: &[u8; 32]) -> io::Result<()> {
+    let signature = self.signature().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "the file is not signed"))?;
+    if signature.public_key != *public_key {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "the file is signed with another key"));
+    }
+    if !ed25519::verify(public_key, &self.signed_view(), &signature.signature) {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, "the signature does not match the file content"));
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,101 @@
+use std::collections::BTreeSet;
+use crate::dwarf::*;
+use crate::elf::Elf;
+
+//A file of a compilation unit's line table.
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+  //as LineRow::file_index has it: from 1 before DWARF 5, from 0 since
+  pub index: u64,
+  //the name the table records, often relative
+  pub name: String,
+  //resolved against the include directory and DW_AT_comp_dir, "." and ".." taken out
+  pub path: String,
+  pub md5: Option<[u8; 16]>,
+}
+
+#[derive(Clone, Debug)]
+pub struct UnitSourceFiles {
+  //offset of the unit in .debug_info
+  pub unit_offset: u64,
+  //DW_AT_name, the primary source file, resolved like the file paths
+  pub name: Option<String>,
+  pub comp_dir: Option<String>,
+  pub producer: Option<String>,
+  //empty when the unit has no line table
+  pub files: Vec<SourceFile>,
+}
+
+//Takes "." and ".." out of a path without looking at the file system: ".." of a symlinked
+//directory may be elsewhere, which is what the compiler saw too.
+pub fn normalize_path(path: &str) -> String {
+  let absolute = path.starts_with('/');
+  let mut parts: Vec<&str> = Vec::new();
+  for part in path.split('/') {
+    match part {
+      "" | "." => {},
+      ".." => match parts.last() {
+        Some(&last) if last != ".." => {
+          parts.pop();
+        },
+        //above the root is the root
+        _ if absolute => {},
+        _ => parts.push(".."),
+      },
+      _ => parts.push(part),
+    };
+  }
+  let joined = parts.join("/");
+  match (absolute, joined.is_empty()) {
+    (true, _) => format!("/{}", joined),
+    (false, true) => String::from("."),
+    (false, false) => joined,
+  }
+}
+
+impl Elf {
+  //The files each compilation unit's line table names, sources and headers, with their
+  //directories resolved. DWARF 5 tables list the primary file twice, as 0 and 1, and both
+  //entries are kept so that indices match the line rows.
+  pub fn source_files(&self) -> Vec<UnitSourceFiles> {
+    let dwarf = self.dwarf();
+    let mut units = Vec::new();
+    for unit in dwarf.units() {
+      let comp_dir = unit.comp_dir().map(|comp_dir| comp_dir.into_owned());
+      let resolve = |name: &str| match &comp_dir {
+        Some(comp_dir) if !name.starts_with('/') => normalize_path(&format!("{}/{}", comp_dir, name)),
+        _ => normalize_path(name),
+      };
+      let mut files = Vec::new();
+      if let Some(program) = dwarf.line_program(&unit) {
+        let first = if program.version >= 5 { 0 } else { 1 };
+        for index in first..first + program.files.len() as u64 {
+          let (file, path) = match (program.file(index), program.file_path(index, comp_dir.as_deref())) {
+            (Some(file), Some(path)) => (file, path),
+            _ => continue,
+          };
+          files.push(SourceFile { index, name: file.name.clone(), path: normalize_path(&path), md5: file.md5 });
+        }
+      }
+      let root = unit.root();
+      units.push(UnitSourceFiles {
+        unit_offset: unit.header.offset,
+        name: unit.name().map(|name| resolve(&name)),
+        producer: root.and_then(|root| root.string(DW_AT_PRODUCER)).map(|producer| producer.into_owned()),
+        comp_dir,
+        files,
+      });
+    }
+    units
+  }
+
+  //Every source path of source_files() once, sorted, the unit names included.
+  pub fn source_paths(&self) -> Vec<String> {
+    let mut paths = BTreeSet::new();
+    for unit in self.source_files() {
+      paths.extend(unit.name);
+      paths.extend(unit.files.into_iter().map(|file| file.path));
+    }
+    paths.into_iter().collect()
+  }
+}
@@ -0,0 +1,124 @@
+use std::borrow::Cow;
+use std::io;
+use std::sync::Arc;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::{read_bytes, Elf, SectionHeader};
+use crate::entry_table::EntryTable;
+use crate::interner::Interner;
+use crate::layout::*;
+use crate::parallel::map_entries;
+
+#[derive(Default, Clone)]
+pub struct Symbol {
+  pub name: Arc<str>,
+  pub value: u64,
+  pub size: u64,
+  pub symbol_type: u8,
+  pub binding: u8,
+  pub visibility: u8,
+  pub section_index: u16,
+  //the name as stored when it is not UTF-8, `name` then has U+FFFD in place of the bad bytes
+  pub(crate) raw_name: Option<Arc<[u8]>>,
+}
+
+impl Symbol {
+  pub fn is_undefined(&self) -> bool {
+    self.section_index == 0
+  }
+
+  pub fn is_global(&self) -> bool {
+    self.binding == STB_GLOBAL || self.binding == STB_WEAK || self.binding == STB_GNU_UNIQUE
+  }
+
+  //the name as stored in the string table when it is not UTF-8
+  pub fn raw_name(&self) -> Option<&[u8]> {
+    self.raw_name.as_deref()
+  }
+
+  pub fn name_bytes(&self) -> &[u8] {
+    self.raw_name().unwrap_or(self.name.as_bytes())
+  }
+
+  //`name`, which has U+FFFD in place of invalid UTF-8 already
+  pub fn name_lossy(&self) -> Cow<'_, str> {
+    Cow::Borrowed(&self.name)
+  }
+
+  pub fn name_str(&self) -> io::Result<&str> {
+    match &self.raw_name {
+      Some(_) => Err(io::Error::new(io::ErrorKind::InvalidData, format!("symbol name {:?} is not UTF-8", self.name))),
+      None => Ok(&self.name),
+    }
+  }
+
+  //defined, global and visible to other modules
+  pub fn is_exported(&self) -> bool {
+    !self.is_undefined() && self.is_global() && self.visibility != STV_HIDDEN && self.visibility != STV_INTERNAL
+  }
+}
+
+impl Elf {
+  pub fn symbols(&self) -> Vec<Symbol> {
+    self.symbols_of_type(SHT_SYMTAB)
+  }
+
+  pub fn dynamic_symbols(&self) -> Vec<Symbol> {
+    self.symbols_of_type(SHT_DYNSYM)
+  }
+
+  pub fn imports(&self) -> Vec<Symbol> {
+    self.dynamic_symbols().into_iter()
+      .filter(|symbol| symbol.is_undefined() && !symbol.name.is_empty())
+      .collect()
+  }
+
+  pub fn exports(&self) -> Vec<Symbol> {
+    self.dynamic_symbols().into_iter()
+      .filter(Symbol::is_exported)
+      .collect()
+  }
+
+  fn symbols_of_type(&self, section_type: u32) -> Vec<Symbol> {
+    let section = match self.section_headers.iter().find(|section| section.section_type == section_type) {
+      Some(section) => section,
+      None => return Vec::new(),
+    };
+    match self.header.identification.endianness {
+      1 => self.load_symbols_with_byteorder::<LittleEndian>(section),
+      2 => self.load_symbols_with_byteorder::<BigEndian>(section),
+      _ => panic!("unknown endianness"),
+    }
+  }
+
+  fn load_symbols_with_byteorder<E: ByteOrder>(&self, section: &SectionHeader) -> Vec<Symbol> {
+    match self.header.identification.class {
+      1 => self.load_symbols_with_layout::<E, Elf32>(section),
+      2 => self.load_symbols_with_layout::<E, Elf64>(section),
+      _ => panic!("unknown class"),
+    }
+  }
+
+  fn load_symbols_with_layout<E: ByteOrder, L: ElfLayout>(&self, section: &SectionHeader) -> Vec<Symbol> {
+    let strings = self.section_headers.get(section.link as usize)
+      .and_then(|table| self.data.get(table.offset as usize..table.offset.saturating_add(table.size) as usize))
+      .unwrap_or(&[]);
+    let data = self.data.get(section.offset as usize..section.offset.saturating_add(section.size) as usize).unwrap_or(&[]);
+    let interner = &*self.interner;
+    map_entries(EntryTable::new(data, section.entry_size, L::Symbol::SIZE), |entry| Elf::read_symbol::<E, L>(strings, interner, entry))
+  }
+
+  fn read_symbol<E: ByteOrder, L: ElfLayout>(strings: &[u8], interner: &Interner, data: &[u8]) -> Symbol {
+    let raw = L::Symbol::read::<E>(data).unwrap();
+    let mut entry = raw.widen();
+    let name = read_bytes(strings, raw.name_index() as usize).unwrap_or(b"");
+    match std::str::from_utf8(name) {
+      Ok(name) => entry.name = interner.intern(name),
+      Err(_) => {
+        entry.name = interner.intern(&String::from_utf8_lossy(name));
+        entry.raw_name = Some(Arc::from(name));
+      },
+    };
+    entry
+  }
+}
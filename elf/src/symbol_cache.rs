@@ -0,0 +1,267 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::elf::Elf;
+use crate::leb128::{read_uleb128, write_uleb128};
+use crate::symbol_map::{FunctionMap, MapFunction};
+
+const MAP_MAGIC: &[u8; 8] = b"WFNMAP\0\0";
+const MAP_VERSION: u32 = 1;
+const ENTRY_EXTENSION: &str = "fnmap";
+
+struct MapReader<'a> {
+  data: &'a [u8],
+  position: usize,
+}
+
+impl<'a> MapReader<'a> {
+  fn uleb(&mut self) -> io::Result<u64> {
+    read_uleb128(self.data, &mut self.position).ok_or_else(|| io::Error::new(ErrorKind::UnexpectedEof, "truncated function map"))
+  }
+
+  fn usize(&mut self) -> io::Result<usize> {
+    let value = self.uleb()?;
+    //every counted item takes a byte at least, larger counts are corrupt
+    if value > (self.data.len() - self.position) as u64 {
+      return Err(io::Error::new(ErrorKind::InvalidData, "function map count past its end"));
+    }
+    Ok(value as usize)
+  }
+
+  fn string(&mut self) -> io::Result<String> {
+    let length = self.usize()?;
+    let bytes = &self.data[self.position..self.position + length];
+    self.position += length;
+    String::from_utf8(bytes.to_vec()).map_err(|_| io::Error::new(ErrorKind::InvalidData, "function map string is not UTF-8"))
+  }
+}
+
+fn write_string(out: &mut Vec<u8>, string: &str) {
+  write_uleb128(out, string.len() as u64);
+  out.extend_from_slice(string.as_bytes());
+}
+
+//Addresses are stored as ULEB128 deltas from the previous function, line or public, which
+//keeps a map a fraction of the size of the DWARF it comes from.
+impl FunctionMap {
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut out = MAP_MAGIC.to_vec();
+    out.extend_from_slice(&MAP_VERSION.to_le_bytes());
+    write_uleb128(&mut out, self.files.len() as u64);
+    for file in &self.files {
+      write_string(&mut out, file);
+    }
+    write_uleb128(&mut out, self.functions.len() as u64);
+    let mut previous = 0;
+    for function in &self.functions {
+      write_uleb128(&mut out, function.address.wrapping_sub(previous));
+      previous = function.address;
+      write_uleb128(&mut out, function.size);
+      write_string(&mut out, &function.name);
+      write_uleb128(&mut out, function.lines.len() as u64);
+      let mut line_previous = function.address;
+      for &(address, size, line, file) in &function.lines {
+        write_uleb128(&mut out, address.wrapping_sub(line_previous));
+        line_previous = address;
+        write_uleb128(&mut out, size);
+        write_uleb128(&mut out, line);
+        write_uleb128(&mut out, file as u64);
+      }
+    }
+    write_uleb128(&mut out, self.publics.len() as u64);
+    let mut previous = 0;
+    for (address, name) in &self.publics {
+      write_uleb128(&mut out, address.wrapping_sub(previous));
+      previous = *address;
+      write_string(&mut out, name);
+    }
+    out
+  }
+
+  pub fn from_bytes(data: &[u8]) -> io::Result<FunctionMap> {
+    if !data.starts_with(MAP_MAGIC) || data.len() < MAP_MAGIC.len() + 4 {
+      return Err(io::Error::new(ErrorKind::InvalidData, "not a function map"));
+    }
+    let version = u32::from_le_bytes([data[8], data[9], data[10], data[11]]);
+    if version != MAP_VERSION {
+      return Err(io::Error::new(ErrorKind::InvalidData, format!("function map version {} is not supported", version)));
+    }
+    let mut reader = MapReader { data, position: MAP_MAGIC.len() + 4 };
+    let files = (0..reader.usize()?).map(|_| reader.string()).collect::<io::Result<Vec<_>>>()?;
+    let count = reader.usize()?;
+    let mut functions = Vec::with_capacity(count);
+    let mut previous = 0u64;
+    for _ in 0..count {
+      let address = previous.wrapping_add(reader.uleb()?);
+      previous = address;
+      let size = reader.uleb()?;
+      let name = reader.string()?;
+      let line_count = reader.usize()?;
+      let mut lines = Vec::with_capacity(line_count);
+      let mut line_previous = address;
+      for _ in 0..line_count {
+        let line_address = line_previous.wrapping_add(reader.uleb()?);
+        line_previous = line_address;
+        let (size, line, file) = (reader.uleb()?, reader.uleb()?, reader.uleb()? as usize);
+        if file >= files.len() {
+          return Err(io::Error::new(ErrorKind::InvalidData, format!("line record names file {} of {}", file, files.len())));
+        }
+        lines.push((line_address, size, line, file));
+      }
+      functions.push(MapFunction { address, size, name, lines });
+    }
+    let count = reader.usize()?;
+    let mut publics = Vec::with_capacity(count);
+    let mut previous = 0u64;
+    for _ in 0..count {
+      let address = previous.wrapping_add(reader.uleb()?);
+      previous = address;
+      publics.push((address, reader.string()?));
+    }
+    Ok(FunctionMap { files, functions, publics })
+  }
+
+  pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+    let path = path.as_ref();
+    //named after the process, two runs filling the same cache do not write one file
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(format!(".{}.tmp", std::process::id()));
+    fs::write(&temporary, self.to_bytes())?;
+    fs::rename(&temporary, path)
+  }
+
+  pub fn load<P: AsRef<Path>>(path: P) -> io::Result<FunctionMap> {
+    FunctionMap::from_bytes(&fs::read(path)?)
+  }
+}
+
+//A directory of function maps keyed by build-id and the modification time of the file the
+//DWARF comes from, so that symbolizing a binary again reads a map instead of parsing its
+//DWARF. The build-id names the code, the time catches a file rewritten without a new one
+//(a debug file regenerated with other options, a build-id set by hand).
+#[derive(Clone, Debug)]
+pub struct SymbolCache {
+  pub directory: PathBuf,
+  //past this many bytes of maps, the least recently used ones are removed when one is added
+  pub max_bytes: Option<u64>,
+}
+
+impl SymbolCache {
+  //The directory is created on the first insert.
+  pub fn new<P: AsRef<Path>>(directory: P) -> SymbolCache {
+    SymbolCache { directory: directory.as_ref().to_path_buf(), max_bytes: None }
+  }
+
+  fn entry_path(&self, build_id: &[u8], modified: SystemTime) -> Option<PathBuf> {
+    let time = modified.duration_since(UNIX_EPOCH).ok()?;
+    let id: String = build_id.iter().map(|byte| format!("{:02x}", byte)).collect();
+    Some(self.directory.join(format!("{}-{}.{:09}.{}", id, time.as_secs(), time.subsec_nanos(), ENTRY_EXTENSION)))
+  }
+
+  //A hit counts as a use for max_bytes. Unreadable or corrupt entries are misses.
+  pub fn get(&self, build_id: &[u8], modified: SystemTime) -> Option<FunctionMap> {
+    let path = self.entry_path(build_id, modified)?;
+    let map = FunctionMap::load(&path).ok()?;
+    if self.max_bytes.is_some() {
+      let _ = fs::File::options().write(true).open(&path).and_then(|file| file.set_modified(SystemTime::now()));
+    }
+    Some(map)
+  }
+
+  pub fn insert(&self, build_id: &[u8], modified: SystemTime, map: &FunctionMap) -> io::Result<()> {
+    let path = self.entry_path(build_id, modified)
+      .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "modification time before the Unix epoch"))?;
+    fs::create_dir_all(&self.directory)?;
+    map.save(path)?;
+    if self.max_bytes.is_some() {
+      self.trim()?;
+    }
+    Ok(())
+  }
+
+  //The function map of the binary at `path`, from the cache or by parsing its DWARF and
+  //adding the result. Files without a build-id are parsed every time. Adding it is best
+  //effort: a map that cannot be written, to a read-only directory say, is still returned.
+  pub fn function_map<P: AsRef<Path>>(&self, path: P) -> io::Result<FunctionMap> {
+    let path = path.as_ref();
+    let modified = fs::metadata(path)?.modified().ok();
+    let elf = Elf::open(path)?;
+    self.cached(&elf, modified, |elf| elf.function_map())
+  }
+
+  //Same as function_map with the DWARF from a separate debug file. The build-id is the
+  //binary's, the later of the two modification times is the key's.
+  pub fn function_map_with_debug<P: AsRef<Path>, Q: AsRef<Path>>(&self, path: P, debug_path: Q) -> io::Result<FunctionMap> {
+    let (path, debug_path) = (path.as_ref(), debug_path.as_ref());
+    let modified = fs::metadata(path)?.modified().ok().zip(fs::metadata(debug_path)?.modified().ok()).map(|(binary, debug)| binary.max(debug));
+    let elf = Elf::open(path)?;
+    let debug = Elf::open(debug_path)?;
+    self.cached(&elf, modified, |elf| elf.function_map_with_debug(&debug))
+  }
+
+  fn cached<F: FnOnce(&Elf) -> FunctionMap>(&self, elf: &Elf, modified: Option<SystemTime>, build: F) -> io::Result<FunctionMap> {
+    let key = elf.build_id().filter(|build_id| !build_id.is_empty()).zip(modified);
+    if let Some((build_id, modified)) = &key {
+      if let Some(map) = self.get(build_id, *modified) {
+        return Ok(map);
+      }
+    }
+    let map = build(elf);
+    if let Some((build_id, modified)) = &key {
+      let _ = self.insert(build_id, *modified, &map);
+    }
+    Ok(map)
+  }
+
+  //(path, size, last use) of the maps in the directory
+  fn entries(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+    let entries = match fs::read_dir(&self.directory) {
+      Ok(entries) => entries,
+      Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+      Err(err) => return Err(err),
+    };
+    Ok(entries.flatten()
+      .map(|entry| entry.path())
+      .filter(|path| path.extension().is_some_and(|extension| extension == ENTRY_EXTENSION))
+      .filter_map(|path| {
+        let metadata = fs::metadata(&path).ok()?;
+        Some((path, metadata.len(), metadata.modified().unwrap_or(UNIX_EPOCH)))
+      })
+      .collect())
+  }
+
+  //Removes the least recently used maps until the rest fit in max_bytes, and returns how
+  //many. Nothing without a limit. Maps another process removed first are counted as removed.
+  pub fn trim(&self) -> io::Result<usize> {
+    let max_bytes = match self.max_bytes {
+      Some(max_bytes) => max_bytes,
+      None => return Ok(0),
+    };
+    let mut entries = self.entries()?;
+    entries.sort_by_key(|entry| entry.2);
+    let mut total: u64 = entries.iter().map(|entry| entry.1).sum();
+    let mut removed = 0;
+    for (path, size, _) in entries {
+      if total <= max_bytes {
+        break;
+      }
+      match fs::remove_file(&path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => return Err(err),
+        _ => {},
+      };
+      total -= size;
+      removed += 1;
+    }
+    Ok(removed)
+  }
+
+  //Removes every map and returns how many, other files in the directory are left alone.
+  pub fn clear(&self) -> io::Result<usize> {
+    let entries = self.entries()?;
+    for (path, _, _) in &entries {
+      fs::remove_file(path)?;
+    }
+    Ok(entries.len())
+  }
+}
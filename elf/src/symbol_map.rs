@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::header::SHN_UNDEF;
+use crate::cfi::{CfaRule, RegisterRule, UnwindRow};
+use crate::symbol::Symbol;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SymbolMapFormat {
+  //Breakpad .sym: MODULE, FILE, FUNC with line records, PUBLIC
+  Breakpad,
+  //"address type name" lines like nm -n
+  Nm,
+  //section:offset publics listing in the style of an MSVC linker map
+  MsvcMap,
+}
+
+//One FUNC record, a function split into several ranges produces one record per range.
+pub struct MapFunction {
+  pub address: u64,
+  pub size: u64,
+  pub name: String,
+  //address, size, line, index into files
+  pub lines: Vec<(u64, u64, u64, usize)>,
+}
+
+pub struct FunctionMap {
+  pub files: Vec<String>,
+  pub functions: Vec<MapFunction>,
+  //address, name: symbol table functions not covered by a FUNC record
+  pub publics: Vec<(u64, String)>,
+}
+
+//Where an address is according to a FunctionMap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MapLocation<'a> {
+  pub name: &'a str,
+  pub offset: u64,
+  //None for publics and addresses without a line record
+  pub file: Option<&'a str>,
+  pub line: Option<u64>,
+}
+
+impl FunctionMap {
+  //The function record covering `address` with its line, else the closest public below it,
+  //which has no size to check against.
+  pub fn symbolize(&self, address: u64) -> Option<MapLocation<'_>> {
+    let position = self.functions.partition_point(|function| function.address <= address);
+    if let Some(function) = position.checked_sub(1).map(|position| &self.functions[position]) {
+      if address - function.address < function.size {
+        let line = function.lines.partition_point(|line| line.0 <= address).checked_sub(1)
+          .map(|position| function.lines[position])
+          .filter(|line| address - line.0 < line.1);
+        return Some(MapLocation {
+          name: &function.name,
+          offset: address - function.address,
+          file: line.and_then(|line| self.files.get(line.3)).map(String::as_str),
+          line: line.map(|line| line.2),
+        });
+      }
+    }
+    let position = self.publics.partition_point(|public| public.0 <= address);
+    let (public_address, name) = &self.publics[position.checked_sub(1)?];
+    Some(MapLocation { name, offset: address - public_address, file: None, line: None })
+  }
+}
+
+pub fn breakpad_arch(machine: u16, class: u8) -> &'static str {
+  match (machine, class) {
+    (EM_386, _) => "x86",
+    (EM_X86_64, _) => "x86_64",
+    (EM_ARM, _) => "arm",
+    (EM_AARCH64, _) => "arm64",
+    (EM_MIPS, 1) => "mips",
+    (EM_MIPS, _) => "mips64",
+    (EM_PPC, _) => "ppc",
+    (EM_PPC64, _) => "ppc64",
+    (EM_S390, _) => "s390",
+    (EM_SPARCV9, _) => "sparcv9",
+    (EM_RISCV, 1) => "riscv",
+    (EM_RISCV, _) => "riscv64",
+    (EM_LOONGARCH, _) => "loongarch64",
+    _ => "unknown",
+  }
+}
+
+//Register names as the Breakpad stack walkers expect them.
+pub fn breakpad_register_name(machine: u16, register: u16) -> Option<String> {
+  const X86_64: [&str; 17] = ["$rax", "$rdx", "$rcx", "$rbx", "$rsi", "$rdi", "$rbp", "$rsp",
+    "$r8", "$r9", "$r10", "$r11", "$r12", "$r13", "$r14", "$r15", "$rip"];
+  const X86: [&str; 9] = ["$eax", "$ecx", "$edx", "$ebx", "$esp", "$ebp", "$esi", "$edi", "$eip"];
+  let name = match (machine, register) {
+    (EM_X86_64, register) => X86_64.get(register as usize)?.to_string(),
+    (EM_386, register) => X86.get(register as usize)?.to_string(),
+    (EM_AARCH64, 31) => String::from("sp"),
+    (EM_AARCH64, 0..=30) => format!("x{}", register),
+    (EM_ARM, 13) => String::from("sp"),
+    (EM_ARM, 14) => String::from("lr"),
+    (EM_ARM, 15) => String::from("pc"),
+    (EM_ARM, 0..=12) => format!("r{}", register),
+    (EM_MIPS, 0..=31) => format!("${}", register),
+    (EM_RISCV, 0..=31) => format!("x{}", register),
+    _ => return None,
+  };
+  Some(name)
+}
+
+//The rules of a STACK CFI record, only those that changed when `previous` is given.
+//None when the row cannot be expressed.
+fn breakpad_cfi_rules(machine: u16, return_register: u16, row: &UnwindRow, previous: Option<&UnwindRow>) -> Option<String> {
+  let mut rules = Vec::new();
+  if previous.is_none_or(|previous| previous.cfa != row.cfa) {
+    match &row.cfa {
+      CfaRule::RegisterOffset(register, offset) => rules.push(format!(".cfa: {} {} +", breakpad_register_name(machine, *register)?, offset)),
+      CfaRule::Expression(_) => return None,
+    };
+  }
+  let mut registers: Vec<u16> = row.registers.iter().map(|(register, _)| *register).collect();
+  if let Some(previous) = previous {
+    registers.extend(previous.registers.iter().map(|(register, _)| *register).filter(|register| row.register(*register).is_none()));
+  }
+  registers.sort_by_key(|&register| (register != return_register, register));
+  for register in registers {
+    let rule = row.register(register);
+    if previous.is_some_and(|previous| previous.register(register) == rule) {
+      continue;
+    }
+    let name = if register == return_register { String::from(".ra") } else {
+      match breakpad_register_name(machine, register) {
+        Some(name) => name,
+        None => continue,
+      }
+    };
+    let value = match rule {
+      Some(RegisterRule::Offset(offset)) => format!(".cfa {} + ^", offset),
+      Some(RegisterRule::ValOffset(offset)) => format!(".cfa {} +", offset),
+      Some(RegisterRule::Register(other)) => match breakpad_register_name(machine, *other) {
+        Some(other) => other,
+        None => continue,
+      },
+      //a rule dropped by DW_CFA_restore means the register was not saved
+      Some(RegisterRule::SameValue) | None if register != return_register => name.clone(),
+      _ => continue,
+    };
+    rules.push(format!("{}: {}", name, value));
+  }
+  Some(rules.join(" "))
+}
+
+impl Elf {
+  //Breakpad identifies a module by the first 16 bytes of its build id, read as a
+  //little-endian GUID, followed by an age of 0. Without a build id it hashes .text.
+  pub fn breakpad_debug_id(&self) -> String {
+    let mut identifier = [0u8; 16];
+    match self.build_id() {
+      Some(build_id) => {
+        let length = build_id.len().min(16);
+        identifier[..length].copy_from_slice(&build_id[..length]);
+      },
+      None => {
+        if let Some(text) = self.section_by_name(".text") {
+          let text = self.section_data(text);
+          for chunk in text[..text.len().min(4096)].chunks(16) {
+            for (byte, value) in identifier.iter_mut().zip(chunk) {
+              *byte ^= value;
+            }
+          }
+        }
+      },
+    };
+    identifier[0..4].reverse();
+    identifier[4..6].reverse();
+    identifier[6..8].reverse();
+    let mut id: String = identifier.iter().map(|byte| format!("{:02X}", byte)).collect();
+    id.push('0');
+    id
+  }
+
+  //Addresses in a Breakpad file are relative to the lowest loadable address.
+  pub fn load_address(&self) -> u64 {
+    self.program_headers.iter()
+      .filter(|ph| ph.entry_type == PT_LOAD)
+      .map(|ph| ph.virtual_address)
+      .min()
+      .unwrap_or(0)
+  }
+
+  //Function records from DWARF, with their line records, plus symbol table functions
+  //DWARF does not cover. Addresses are absolute, sorted, and deduplicated.
+  pub fn function_map(&self) -> FunctionMap {
+    self.function_map_with_debug(self)
+  }
+
+  //Same as function_map with the DWARF taken from a separate debug file.
+  pub fn function_map_with_debug(&self, debug: &Elf) -> FunctionMap {
+    let dwarf = debug.dwarf();
+    let mut files = Vec::new();
+    let mut file_indices: HashMap<String, usize> = HashMap::new();
+    let mut functions = Vec::new();
+    for unit in dwarf.units() {
+      let unit_functions = dwarf.unit_functions(&unit);
+      if unit_functions.is_empty() {
+        continue;
+      }
+      let comp_dir = unit.comp_dir().map(|comp_dir| comp_dir.into_owned());
+      //address, end, line, file index as encoded
+      let mut line_ranges = Vec::new();
+      let program = dwarf.line_program(&unit);
+      if let Some(program) = &program {
+        for pair in program.rows.windows(2) {
+          if !pair[0].end_sequence && pair[1].address > pair[0].address {
+            line_ranges.push((pair[0].address, pair[1].address, pair[0].line, pair[0].file_index));
+          }
+        }
+        line_ranges.sort_by_key(|range| range.0);
+      }
+      for function in unit_functions {
+        let name = function.name;
+        for (low, high) in function.ranges {
+          let mut lines = Vec::new();
+          let first = line_ranges.partition_point(|range| range.1 <= low);
+          for &(start, end, line, file_index) in line_ranges[first..].iter().take_while(|range| range.0 < high) {
+            let start = start.max(low);
+            //rows of overlapping sequences can end before the function starts
+            let size = match end.min(high).checked_sub(start) {
+              Some(size) if size > 0 => size,
+              _ => continue,
+            };
+            let path = match program.as_ref().and_then(|program| program.file_path(file_index, comp_dir.as_deref())) {
+              Some(path) => path,
+              None => continue,
+            };
+            let file = *file_indices.entry(path).or_insert_with_key(|path| {
+              files.push(path.clone());
+              files.len() - 1
+            });
+            lines.push((start, size, line, file));
+          }
+          let size = match high.checked_sub(low) {
+            Some(size) => size,
+            None => continue,
+          };
+          functions.push(MapFunction { address: low, size, name: name.clone(), lines });
+        }
+      }
+    }
+    functions.sort_by_key(|function| function.address);
+    functions.dedup_by_key(|function| function.address);
+
+    let mut publics: Vec<(u64, String)> = self.map_symbols(debug).into_iter()
+      .filter(|symbol| matches!(symbol.symbol_type, STT_FUNC | STT_GNU_IFUNC) && !symbol.is_undefined() && symbol.value != 0 && !symbol.name.is_empty())
+      .filter(|symbol| {
+        let position = functions.partition_point(|function| function.address <= symbol.value);
+        position.checked_sub(1).is_none_or(|position| symbol.value - functions[position].address >= functions[position].size)
+      })
+      .map(|symbol| (symbol.value, symbol.name.to_string()))
+      .collect();
+    publics.sort();
+    publics.dedup_by_key(|public| public.0);
+    FunctionMap { files, functions, publics }
+  }
+
+  //.symtab when present, the debug file's .symtab, then .dynsym for stripped binaries
+  fn map_symbols(&self, debug: &Elf) -> Vec<Symbol> {
+    if !self.symbol_table().is_empty() {
+      self.symbol_table().to_vec()
+    } else if !debug.symbol_table().is_empty() {
+      debug.symbol_table().to_vec()
+    } else {
+      self.dynamic_symbol_table().to_vec()
+    }
+  }
+
+  pub fn write_symbol_map<W: Write>(&self, format: SymbolMapFormat, module_name: &str, writer: &mut W) -> io::Result<()> {
+    match format {
+      SymbolMapFormat::Breakpad => self.write_breakpad_symbols(module_name, writer),
+      SymbolMapFormat::Nm => self.write_nm_map(writer),
+      SymbolMapFormat::MsvcMap => self.write_msvc_map(module_name, writer),
+    }
+  }
+
+  pub fn write_breakpad_symbols<W: Write>(&self, module_name: &str, writer: &mut W) -> io::Result<()> {
+    self.write_breakpad_symbols_with_debug(module_name, self, writer)
+  }
+
+  //What dump_syms writes for a binary and its debug file: module identity, FILE, FUNC and
+  //line records from DWARF, PUBLIC for the rest of the symbol table and STACK CFI from
+  //the binary's unwind tables.
+  pub fn write_breakpad_symbols_with_debug<W: Write>(&self, module_name: &str, debug: &Elf, writer: &mut W) -> io::Result<()> {
+    let arch = breakpad_arch(self.header.description.machine, self.header.identification.class);
+    writeln!(writer, "MODULE Linux {} {} {}", arch, self.breakpad_debug_id(), module_name)?;
+    if let Some(build_id) = self.build_id() {
+      let code_id: String = build_id.iter().map(|byte| format!("{:02x}", byte)).collect();
+      writeln!(writer, "INFO CODE_ID {}", code_id)?;
+    }
+    let base = self.load_address();
+    let map = self.function_map_with_debug(debug);
+    for (index, file) in map.files.iter().enumerate() {
+      writeln!(writer, "FILE {} {}", index, file)?;
+    }
+    for function in &map.functions {
+      writeln!(writer, "FUNC {:x} {:x} 0 {}", function.address.wrapping_sub(base), function.size, function.name)?;
+      for &(address, size, line, file) in &function.lines {
+        writeln!(writer, "{:x} {:x} {} {}", address.wrapping_sub(base), size, line, file)?;
+      }
+    }
+    for (address, name) in &map.publics {
+      writeln!(writer, "PUBLIC {:x} 0 {}", address.wrapping_sub(base), name)?;
+    }
+    self.write_breakpad_stack_records(writer)
+  }
+
+  pub fn write_breakpad_stack_records<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    let machine = self.header.description.machine;
+    let base = self.load_address();
+    let table = self.unwind_table();
+    for frame in &table.frames {
+      let mut previous: Option<&UnwindRow> = None;
+      for row in &frame.rows {
+        //Breakpad rules are postfix arithmetic over registers, DWARF expressions do not translate
+        let rules = match breakpad_cfi_rules(machine, frame.return_address_register, row, previous) {
+          Some(rules) => rules,
+          None => break,
+        };
+        match previous {
+          None => writeln!(writer, "STACK CFI INIT {:x} {:x} {}", frame.address.wrapping_sub(base), frame.size, rules)?,
+          Some(_) if rules.is_empty() => {},
+          Some(_) => writeln!(writer, "STACK CFI {:x} {}", row.address.wrapping_sub(base), rules)?,
+        };
+        previous = Some(row);
+      }
+    }
+    Ok(())
+  }
+
+  //The letter nm prints for a symbol, lower case for local symbols.
+  pub fn nm_type(&self, symbol: &Symbol) -> char {
+    let weak = symbol.binding == STB_WEAK;
+    let object = symbol.symbol_type == STT_OBJECT;
+    let letter = match symbol.section_index {
+      SHN_UNDEF if weak => return if object { 'v' } else { 'w' },
+      SHN_UNDEF => return 'U',
+      SHN_ABS => 'A',
+      SHN_COMMON => return 'C',
+      _ if symbol.symbol_type == STT_GNU_IFUNC => return 'i',
+      _ if weak => return if object { 'V' } else { 'W' },
+      _ if symbol.binding == STB_GNU_UNIQUE => return 'u',
+      index => match self.section_headers.get(index as usize) {
+        Some(section) if section.flags & SHF_EXECINSTR != 0 => 'T',
+        Some(section) if section.flags & SHF_ALLOC != 0 && section.section_type == SHT_NOBITS => 'B',
+        Some(section) if section.flags & SHF_ALLOC != 0 && section.flags & SHF_WRITE != 0 => 'D',
+        Some(section) if section.flags & SHF_ALLOC != 0 => 'R',
+        Some(_) => 'N',
+        None => '?',
+      },
+    };
+    if symbol.binding == STB_LOCAL { letter.to_ascii_lowercase() } else { letter }
+  }
+
+  pub fn write_nm_map<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+    let width = if self.header.identification.class == 1 { 8 } else { 16 };
+    let mut symbols: Vec<Symbol> = self.map_symbols(self).into_iter()
+      .filter(|symbol| !symbol.name.is_empty() && symbol.symbol_type != STT_SECTION && symbol.symbol_type != STT_FILE)
+      .collect();
+    symbols.sort_by(|a, b| (a.value, &a.name).cmp(&(b.value, &b.name)));
+    for symbol in &symbols {
+      let kind = self.nm_type(symbol);
+      if symbol.is_undefined() {
+        writeln!(writer, "{:width$} {} {}", "", kind, symbol.name, width = width)?;
+      } else {
+        writeln!(writer, "{:0width$x} {} {}", symbol.value, kind, symbol.name, width = width)?;
+      }
+    }
+    Ok(())
+  }
+
+  pub fn write_msvc_map<W: Write>(&self, module_name: &str, writer: &mut W) -> io::Result<()> {
+    let base = self.load_address();
+    writeln!(writer, " {}", module_name)?;
+    writeln!(writer)?;
+    writeln!(writer, " Preferred load address is {:016x}", base)?;
+    writeln!(writer)?;
+    writeln!(writer, " Start         Length     Name                   Class")?;
+    for (index, section) in self.section_headers.iter().enumerate() {
+      if section.flags & SHF_ALLOC == 0 {
+        continue;
+      }
+      let class = if section.flags & SHF_EXECINSTR != 0 { "CODE" } else { "DATA" };
+      let name = self.section_name(section).unwrap_or("");
+      writeln!(writer, " {:04x}:{:08x} {:08x}H {:<22} {}", index, 0, section.size, name, class)?;
+    }
+    writeln!(writer)?;
+    writeln!(writer, "  Address         Publics by Value              Rva+Base               Lib:Object")?;
+    writeln!(writer)?;
+
+    //compilation unit names stand in for the object file column
+    let dwarf = self.dwarf();
+    let mut unit_ranges: Vec<(u64, u64, String)> = Vec::new();
+    for unit in dwarf.units() {
+      let name = unit.name().map(|name| name.rsplit('/').next().unwrap_or("").to_string()).unwrap_or_default();
+      if let Some(root) = unit.root() {
+        for (low, high) in dwarf.die_ranges(&unit, root) {
+          unit_ranges.push((low, high, name.clone()));
+        }
+      }
+    }
+    unit_ranges.sort();
+
+    let mut symbols: Vec<Symbol> = self.map_symbols(self).into_iter()
+      .filter(|symbol| !symbol.is_undefined() && !symbol.name.is_empty() && matches!(symbol.symbol_type, STT_FUNC | STT_OBJECT | STT_GNU_IFUNC | STT_NOTYPE))
+      .filter(|symbol| (symbol.section_index as usize) < self.section_headers.len())
+      .collect();
+    symbols.sort_by_key(|symbol| (symbol.section_index, symbol.value));
+    for symbol in &symbols {
+      let section = &self.section_headers[symbol.section_index as usize];
+      let flag = if matches!(symbol.symbol_type, STT_FUNC | STT_GNU_IFUNC) { "f" } else { " " };
+      let position = unit_ranges.partition_point(|range| range.0 <= symbol.value);
+      let object = position.checked_sub(1)
+        .map(|position| &unit_ranges[position])
+        .filter(|range| symbol.value < range.1)
+        .map_or("", |range| range.2.as_str());
+      let line = format!(" {:04x}:{:08x}       {:<30} {:016x} {}   {}",
+        symbol.section_index, symbol.value.wrapping_sub(section.address), symbol.name, symbol.value, flag, object);
+      writeln!(writer, "{}", line.trim_end())?;
+    }
+    writeln!(writer)?;
+    writeln!(writer, " entry point at        {:016x}", self.header.description.entry)?;
+    Ok(())
+  }
+}
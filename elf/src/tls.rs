@@ -0,0 +1,335 @@
+use std::convert::TryFrom;
+use std::io;
+use std::sync::Arc;
+use crate::consts::*;
+use crate::dwarf::read_unsigned;
+use crate::loader::zeroed;
+use crate::workspace::Workspace;
+
+//Variant I puts the TCB at the thread pointer with the TLS blocks above it, variant II puts
+//the blocks below the thread pointer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsVariant {
+  I,
+  II,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TlsAbi {
+  pub variant: TlsVariant,
+  //TCB bytes between the thread pointer and the first block, variant I only
+  pub tcb_size: u64,
+  //the thread pointer points this far past the end of the TCB (0x7000 on ppc and mips)
+  pub thread_pointer_bias: u64,
+  //DTPOFF values are stored minus this, __tls_get_addr adds it back
+  pub dtv_bias: u64,
+}
+
+impl TlsAbi {
+  //glibc's tls.h values, None for machines without a TLS ABI here.
+  pub fn for_machine(machine: u16) -> Option<TlsAbi> {
+    let (variant, tcb_size, thread_pointer_bias, dtv_bias) = match machine {
+      EM_386 | EM_X86_64 | EM_S390 | EM_SPARCV9 => (TlsVariant::II, 0, 0, 0),
+      EM_AARCH64 => (TlsVariant::I, 16, 0, 0),
+      EM_ARM => (TlsVariant::I, 8, 0, 0),
+      EM_RISCV => (TlsVariant::I, 0, 0, 0x800),
+      EM_LOONGARCH => (TlsVariant::I, 0, 0, 0),
+      EM_PPC | EM_PPC64 | EM_MIPS => (TlsVariant::I, 0, 0x7000, 0x8000),
+      _ => return None,
+    };
+    Some(TlsAbi { variant, tcb_size, thread_pointer_bias, dtv_bias })
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsAccessModel {
+  GlobalDynamic,
+  LocalDynamic,
+  InitialExec,
+  LocalExec,
+  Descriptor,
+}
+
+//What a dynamic TLS relocation asks the loader for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TlsRelocationKind {
+  ModuleId,
+  DtvOffset,
+  ThreadPointerOffset,
+  //i386 R_386_TLS_TPOFF32 stores the offset negated
+  NegatedThreadPointerOffset,
+  Descriptor,
+}
+
+//Dynamic TLS relocations for x86, x86_64, aarch64, arm and riscv.
+pub fn tls_relocation_kind(machine: u16, relocation_type: u32) -> Option<TlsRelocationKind> {
+  let kind = match (machine, relocation_type) {
+    (EM_X86_64, 16) | (EM_386, 35) | (EM_AARCH64, 1028) | (EM_ARM, 17) | (EM_RISCV, 6) | (EM_RISCV, 7) => TlsRelocationKind::ModuleId,
+    (EM_X86_64, 17) | (EM_386, 36) | (EM_AARCH64, 1029) | (EM_ARM, 18) | (EM_RISCV, 8) | (EM_RISCV, 9) => TlsRelocationKind::DtvOffset,
+    (EM_X86_64, 18) | (EM_386, 14) | (EM_AARCH64, 1030) | (EM_ARM, 19) | (EM_RISCV, 10) | (EM_RISCV, 11) => TlsRelocationKind::ThreadPointerOffset,
+    (EM_386, 37) => TlsRelocationKind::NegatedThreadPointerOffset,
+    (EM_X86_64, 36) | (EM_386, 41) | (EM_AARCH64, 1031) | (EM_ARM, 13) | (EM_RISCV, 12) => TlsRelocationKind::Descriptor,
+    _ => return None,
+  };
+  Some(kind)
+}
+
+//The access model a link time TLS relocation of a relocatable object belongs to.
+pub fn tls_access_model(machine: u16, relocation_type: u32) -> Option<TlsAccessModel> {
+  let model = match (machine, relocation_type) {
+    (EM_X86_64, 19) | (EM_386, 18) | (EM_ARM, 104) | (EM_RISCV, 22) => TlsAccessModel::GlobalDynamic,
+    (EM_AARCH64, 512..=516) => TlsAccessModel::GlobalDynamic,
+    (EM_X86_64, 20) | (EM_X86_64, 21) | (EM_386, 19) | (EM_386, 32) | (EM_ARM, 105) | (EM_ARM, 106) => TlsAccessModel::LocalDynamic,
+    //TLSLD_*, including the LDST128 ones numbered after the descriptors
+    (EM_AARCH64, 517..=538) | (EM_AARCH64, 572) | (EM_AARCH64, 573) => TlsAccessModel::LocalDynamic,
+    (EM_X86_64, 22) | (EM_386, 15) | (EM_386, 16) | (EM_386, 33) | (EM_ARM, 107) | (EM_RISCV, 21) => TlsAccessModel::InitialExec,
+    (EM_AARCH64, 539..=543) => TlsAccessModel::InitialExec,
+    (EM_X86_64, 23) | (EM_386, 17) | (EM_386, 34) | (EM_ARM, 108) | (EM_RISCV, 29..=32) => TlsAccessModel::LocalExec,
+    (EM_AARCH64, 544..=559) | (EM_AARCH64, 570) | (EM_AARCH64, 571) => TlsAccessModel::LocalExec,
+    (EM_X86_64, 34) | (EM_X86_64, 35) | (EM_386, 39) | (EM_386, 40) | (EM_ARM, 90..=93) | (EM_ARM, 129) | (EM_ARM, 130) | (EM_RISCV, 62..=65) => TlsAccessModel::Descriptor,
+    (EM_AARCH64, 560..=569) => TlsAccessModel::Descriptor,
+    _ => return None,
+  };
+  Some(model)
+}
+
+pub struct TlsModule {
+  pub object_index: usize,
+  //DTV index, 1 based and in load order
+  pub module_id: u64,
+  //biased address of the initialization image
+  pub image_address: u64,
+  pub image_size: u64,
+  pub memory_size: u64,
+  pub align: u64,
+  //block address minus the thread pointer, None for modules allocated lazily by __tls_get_addr
+  pub thread_pointer_offset: Option<i64>,
+}
+
+pub struct TlsLayout {
+  pub abi: TlsAbi,
+  pub modules: Vec<TlsModule>,
+  //bytes of static TLS, the blocks plus alignment padding but not the TCB
+  pub static_size: u64,
+  pub static_align: u64,
+}
+
+//One DTV slot, None until the block of a lazily allocated module is touched.
+pub struct DtvEntry {
+  pub module_id: u64,
+  pub block: Option<u64>,
+}
+
+pub struct TlsRelocation {
+  pub object_index: usize,
+  //biased address of the slot
+  pub address: u64,
+  pub relocation_type: u32,
+  pub kind: TlsRelocationKind,
+  pub access_model: TlsAccessModel,
+  //None for module relative relocations, which use the referencing module
+  pub symbol: Option<Arc<str>>,
+  //module id and offset in its block, None when the symbol is unresolved
+  pub target: Option<(u64, u64)>,
+  //the word the loader stores, for descriptors the argument the static resolver receives.
+  //None when unresolved or when a descriptor has to go through __tls_get_addr.
+  pub value: Option<u64>,
+}
+
+fn align_up(value: u64, align: u64) -> Option<u64> {
+  value.checked_next_multiple_of(align.max(1))
+}
+
+fn overflow() -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, "the static TLS blocks do not fit the address space")
+}
+
+impl TlsLayout {
+  pub fn module(&self, module_id: u64) -> Option<&TlsModule> {
+    self.modules.iter().find(|module| module.module_id == module_id)
+  }
+
+  pub fn module_of(&self, object_index: usize) -> Option<&TlsModule> {
+    self.modules.iter().find(|module| module.object_index == object_index)
+  }
+
+  pub fn block_address(&self, module_id: u64, thread_pointer: u64) -> Option<u64> {
+    Some(thread_pointer.wrapping_add(self.module(module_id)?.thread_pointer_offset? as u64))
+  }
+
+  //What __tls_get_addr returns for a (module id, DTPOFF value) pair of a static module.
+  pub fn address(&self, module_id: u64, dtv_offset: u64, thread_pointer: u64) -> Option<u64> {
+    Some(self.block_address(module_id, thread_pointer)?.wrapping_add(dtv_offset).wrapping_add(self.abi.dtv_bias))
+  }
+
+  //The DTV of a freshly created thread.
+  pub fn dtv(&self, thread_pointer: u64) -> Vec<DtvEntry> {
+    self.modules.iter()
+      .map(|module| DtvEntry { module_id: module.module_id, block: self.block_address(module.module_id, thread_pointer) })
+      .collect()
+  }
+
+  //Thread pointer relative start of the static TLS area and its initial contents, .tdata
+  //copied and .tbss zeroed.
+  pub fn static_image(&self, workspace: &Workspace) -> io::Result<(i64, Vec<u8>)> {
+    let placed = || self.modules.iter().filter_map(|module| module.thread_pointer_offset.map(|offset| (module, offset)));
+    let start = placed().map(|(_, offset)| offset).min().unwrap_or(0);
+    let mut end = start;
+    for (module, offset) in placed() {
+      end = end.max(i64::try_from(module.memory_size).ok().and_then(|size| offset.checked_add(size)).ok_or_else(overflow)?);
+    }
+    let mut image = zeroed(end.abs_diff(start))?;
+    for (module, offset) in placed() {
+      let elf = &workspace.objects[module.object_index].elf;
+      let ph = match elf.program_headers.iter().find(|ph| ph.entry_type == PT_TLS) {
+        Some(ph) => ph,
+        None => continue,
+      };
+      let source = ph.offset.checked_add(ph.file_size).and_then(|end| elf.data.get(ph.offset as usize..end as usize))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "PT_TLS reaches past the end of the file"))?;
+      //tls_layout checked file_size <= memory_size
+      let destination = offset.abs_diff(start) as usize;
+      image[destination..destination + source.len()].copy_from_slice(source);
+    }
+    Ok((start, image))
+  }
+}
+
+impl Workspace {
+  //Module ids in load order and the static TLS layout glibc's _dl_determine_tlsoffset picks.
+  //Objects loaded at startup and dlopened objects with DF_STATIC_TLS get static blocks, the
+  //others are allocated on first use.
+  pub fn tls_layout(&self) -> io::Result<TlsLayout> {
+    let first = self.objects.first().ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "the workspace has no objects"))?;
+    let machine = first.elf.header.description.machine;
+    let abi = TlsAbi::for_machine(machine)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, format!("no TLS ABI for machine {}", machine)))?;
+    let mut modules = Vec::new();
+    for (object_index, object) in self.objects.iter().enumerate() {
+      if let Some(ph) = object.elf.program_headers.iter().find(|ph| ph.entry_type == PT_TLS) {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, format!("{}: PT_TLS {}", object.path.display(), message));
+        if ph.file_size > ph.memory_size {
+          return Err(invalid("has more file than memory bytes"));
+        }
+        if ph.align > 1 && !ph.align.is_power_of_two() {
+          return Err(invalid("alignment is not a power of two"));
+        }
+        if i64::try_from(ph.memory_size).is_err() {
+          return Err(invalid("is larger than the address space"));
+        }
+        modules.push(TlsModule {
+          object_index,
+          module_id: modules.len() as u64 + 1,
+          image_address: ph.virtual_address.wrapping_add(object.bias),
+          image_size: ph.file_size,
+          memory_size: ph.memory_size,
+          align: ph.align.max(1),
+          thread_pointer_offset: None,
+        });
+      }
+    }
+
+    //startup modules first, surplus static TLS for DF_STATIC_TLS ones after them
+    let is_static = |module: &TlsModule| {
+      let object = &self.objects[module.object_index];
+      !object.opened_at_runtime || object.elf.dynamic_value(DT_FLAGS).is_some_and(|flags| flags & DF_STATIC_TLS != 0)
+    };
+    let mut order: Vec<usize> = (0..modules.len()).filter(|&index| is_static(&modules[index])).collect();
+    order.sort_by_key(|&index| self.objects[modules[index].object_index].opened_at_runtime);
+    let mut offset = match abi.variant {
+      TlsVariant::I => abi.tcb_size,
+      TlsVariant::II => 0,
+    };
+    let mut static_align = 1;
+    for index in order {
+      let module = &mut modules[index];
+      let vaddr = module.image_address.wrapping_sub(self.objects[module.object_index].bias);
+      let firstbyte = (vaddr & (module.align - 1)).wrapping_neg() & (module.align - 1);
+      static_align = static_align.max(module.align);
+      match abi.variant {
+        TlsVariant::I => {
+          let mut aligned = align_up(offset, module.align).ok_or_else(overflow)?;
+          if aligned - offset < firstbyte {
+            aligned = aligned.checked_add(module.align).ok_or_else(overflow)?;
+          }
+          let block = i64::try_from(aligned - firstbyte).map_err(|_| overflow())?;
+          module.thread_pointer_offset = Some(block - abi.thread_pointer_bias as i64);
+          offset = (aligned - firstbyte).checked_add(module.memory_size).ok_or_else(overflow)?;
+        },
+        TlsVariant::II => {
+          //rounding a negative offset + memory_size - firstbyte up gives 0
+          let end = offset.checked_add(module.memory_size).ok_or_else(overflow)?;
+          offset = align_up(end.saturating_sub(firstbyte), module.align).and_then(|aligned| aligned.checked_add(firstbyte)).ok_or_else(overflow)?;
+          module.thread_pointer_offset = Some(-i64::try_from(offset).map_err(|_| overflow())?);
+        },
+      }
+    }
+    let static_size = match abi.variant {
+      TlsVariant::I => offset - abi.tcb_size,
+      TlsVariant::II => align_up(offset, static_align).ok_or_else(overflow)?,
+    };
+    Ok(TlsLayout { abi, modules, static_size, static_align })
+  }
+
+  //Every dynamic TLS relocation of the link map with the value the loader would store,
+  //using the current symbol bindings.
+  pub fn tls_relocations(&self, layout: &TlsLayout) -> Vec<TlsRelocation> {
+    let mut relocations = Vec::new();
+    for (object_index, object) in self.objects.iter().enumerate() {
+      let elf = &object.elf;
+      let machine = elf.header.description.machine;
+      let word_size = if elf.header.identification.class == 2 { 8 } else { 4 };
+      let dynamic_symbols = elf.dynamic_symbol_table();
+      for relocation in elf.relocations() {
+        let kind = match tls_relocation_kind(machine, relocation.relocation_type) {
+          Some(kind) => kind,
+          None => continue,
+        };
+        let addend = relocation.addend.map(|addend| addend as u64).unwrap_or_else(|| {
+          //REL keeps the addend in the slot, descriptors in their second word
+          let slot = relocation.offset + if kind == TlsRelocationKind::Descriptor { word_size } else { 0 };
+          elf.address_to_offset(slot)
+            .and_then(|offset| elf.data.get(offset as usize..offset as usize + word_size as usize))
+            .map_or(0, |bytes| read_unsigned(bytes, elf.header.identification.endianness == 2))
+        });
+        let symbol = match relocation.symbol_index {
+          0 => None,
+          index => dynamic_symbols.get(index as usize),
+        };
+        let definition = match symbol {
+          None => Some((object_index, 0)),
+          Some(symbol) if symbol.is_global() => self.bindings.iter()
+            .find(|binding| binding.object_index == object_index && binding.name == symbol.name)
+            .and_then(|binding| binding.definition),
+          Some(symbol) => Some((object_index, symbol.value)),
+        };
+        let target = definition.and_then(|(defining, value)| Some((layout.module_of(defining)?.module_id, value.wrapping_add(addend))));
+        let static_offset = target.and_then(|(module_id, offset)| Some(layout.module(module_id)?.thread_pointer_offset?.wrapping_add(offset as i64)));
+        let value = target.and_then(|(module_id, offset)| match kind {
+          TlsRelocationKind::ModuleId => Some(module_id),
+          TlsRelocationKind::DtvOffset => Some(offset.wrapping_sub(layout.abi.dtv_bias)),
+          TlsRelocationKind::ThreadPointerOffset | TlsRelocationKind::Descriptor => static_offset.map(|offset| offset as u64),
+          TlsRelocationKind::NegatedThreadPointerOffset => static_offset.map(|offset| offset.wrapping_neg() as u64),
+        });
+        let access_model = match (kind, symbol.is_some()) {
+          (TlsRelocationKind::ModuleId, false) | (TlsRelocationKind::DtvOffset, false) => TlsAccessModel::LocalDynamic,
+          (TlsRelocationKind::ModuleId, true) | (TlsRelocationKind::DtvOffset, true) => TlsAccessModel::GlobalDynamic,
+          (TlsRelocationKind::Descriptor, _) => TlsAccessModel::Descriptor,
+          _ => TlsAccessModel::InitialExec,
+        };
+        let word_mask = if word_size == 8 { u64::MAX } else { 0xffff_ffff };
+        relocations.push(TlsRelocation {
+          object_index,
+          address: relocation.offset.wrapping_add(object.bias),
+          relocation_type: relocation.relocation_type,
+          kind,
+          access_model,
+          symbol: symbol.map(|symbol| symbol.name.clone()),
+          target: target.map(|(module_id, offset)| (module_id, offset & word_mask)),
+          value: value.map(|value| value & word_mask),
+        });
+      }
+    }
+    relocations
+  }
+}
@@ -0,0 +1,163 @@
+use crate::consts::*;
+use crate::elf::{Elf, SectionHeader};
+
+pub const HUGE_SECTION_NUM: u16 = 0x1000;
+
+pub enum AntiAnalysisTrick {
+  HeaderSizeMismatch { field: &'static str, value: u16, expected: u16 },
+  SectionTableOutsideFile { offset: u64, size: u64 },
+  SectionTableInSegment { segment_index: usize },
+  HugeSectionNum { num: u16 },
+  BogusSectionNameTable { index: u16 },
+  SectionOutsideFile { section_index: usize },
+  OverlappingSections { first_index: usize, second_index: usize },
+  LyingSectionHeader { section_index: usize, claimed_offset: u64, mapped_offset: u64 },
+  SymbolsWithoutSectionTable,
+  OverlappingSegments { first_index: usize, second_index: usize },
+  EntryOutsideExecutableSegment { entry: u64 },
+}
+
+impl AntiAnalysisTrick {
+  pub fn name(&self) -> &'static str {
+    match self {
+      AntiAnalysisTrick::HeaderSizeMismatch { .. } => "header-size-mismatch",
+      AntiAnalysisTrick::SectionTableOutsideFile { .. } => "section-table-outside-file",
+      AntiAnalysisTrick::SectionTableInSegment { .. } => "section-table-in-segment",
+      AntiAnalysisTrick::HugeSectionNum { .. } => "huge-section-num",
+      AntiAnalysisTrick::BogusSectionNameTable { .. } => "bogus-section-name-table",
+      AntiAnalysisTrick::SectionOutsideFile { .. } => "section-outside-file",
+      AntiAnalysisTrick::OverlappingSections { .. } => "overlapping-sections",
+      AntiAnalysisTrick::LyingSectionHeader { .. } => "lying-section-header",
+      AntiAnalysisTrick::SymbolsWithoutSectionTable => "symbols-without-section-table",
+      AntiAnalysisTrick::OverlappingSegments { .. } => "overlapping-segments",
+      AntiAnalysisTrick::EntryOutsideExecutableSegment { .. } => "entry-outside-executable-segment",
+    }
+  }
+}
+
+impl Elf {
+  pub fn detect_tricks(&self) -> Vec<AntiAnalysisTrick> {
+    let mut tricks = Vec::new();
+    self.detect_header_tricks(&mut tricks);
+    self.detect_section_tricks(&mut tricks);
+    self.detect_segment_tricks(&mut tricks);
+    tricks
+  }
+
+  fn detect_header_tricks(&self, tricks: &mut Vec<AntiAnalysisTrick>) {
+    let description = &self.header.description;
+    let sizes = [
+      ("e_ehsize", description.elf_hdr_size, self.expected_elf_hdr_size()),
+      ("e_phentsize", description.program_hdr_entry_size, self.expected_program_hdr_entry_size()),
+      ("e_shentsize", description.section_hdr_entry_size, self.expected_section_hdr_entry_size()),
+    ];
+    for &(field, value, expected) in &sizes {
+      let unused_table = (field == "e_phentsize" && description.program_hdr_num == 0)
+        || (field == "e_shentsize" && description.section_hdr_num == 0);
+      if value != expected && !unused_table {
+        tricks.push(AntiAnalysisTrick::HeaderSizeMismatch { field, value, expected });
+      }
+    }
+
+    if description.section_hdr_num >= HUGE_SECTION_NUM {
+      tricks.push(AntiAnalysisTrick::HugeSectionNum { num: description.section_hdr_num });
+    }
+    //e_shnum 0 with a table: SHN_LORESERVE entries or more, counted in sh_size of entry 0
+    let section_hdr_num = match description.section_hdr_num {
+      0 => self.section_headers.len() as u64,
+      num => num as u64,
+    };
+    let table_size = section_hdr_num * description.section_hdr_entry_size as u64;
+    let table_end = description.section_hdr_offset.checked_add(table_size);
+    if table_size != 0 && table_end.is_none_or(|end| end > self.data.len() as u64) {
+      tricks.push(AntiAnalysisTrick::SectionTableOutsideFile { offset: description.section_hdr_offset, size: table_size });
+    }
+    if table_size != 0 {
+      let table_end = description.section_hdr_offset.saturating_add(table_size);
+      for (segment_index, ph) in self.program_headers.iter().enumerate() {
+        let overlaps = description.section_hdr_offset < ph.offset.saturating_add(ph.file_size) && ph.offset < table_end;
+        if ph.entry_type == PT_LOAD && overlaps {
+          tricks.push(AntiAnalysisTrick::SectionTableInSegment { segment_index });
+        }
+      }
+    }
+
+    let str_index = description.section_hdr_str_index;
+    let name_table_ok = match self.section_headers.get(str_index as usize) {
+      Some(section) => section.section_type == SHT_STRTAB,
+      None => description.section_hdr_num == 0 && str_index == 0,
+    };
+    if !name_table_ok {
+      tricks.push(AntiAnalysisTrick::BogusSectionNameTable { index: str_index });
+    }
+
+    let claims_symbols = self.dynamic_entries().iter().any(|entry| entry.tag == DT_SYMTAB);
+    if self.section_headers.is_empty() && claims_symbols {
+      tricks.push(AntiAnalysisTrick::SymbolsWithoutSectionTable);
+    }
+  }
+
+  fn detect_section_tricks(&self, tricks: &mut Vec<AntiAnalysisTrick>) {
+    let mut by_offset = Vec::new();
+    for (section_index, section) in self.section_headers.iter().enumerate() {
+      if !file_backed(section) {
+        continue;
+      }
+      by_offset.push(section_index);
+      if section.offset.checked_add(section.size).is_none_or(|end| end > self.data.len() as u64) {
+        tricks.push(AntiAnalysisTrick::SectionOutsideFile { section_index });
+      }
+      if section.flags & SHF_ALLOC != 0 {
+        if let Some(mapped_offset) = self.address_to_offset(section.address) {
+          if mapped_offset != section.offset {
+            tricks.push(AntiAnalysisTrick::LyingSectionHeader { section_index, claimed_offset: section.offset, mapped_offset });
+          }
+        }
+      }
+    }
+
+    by_offset.sort_by_key(|&index| self.section_headers[index].offset);
+    let mut furthest: Option<(usize, u64)> = None;
+    for &index in &by_offset {
+      let section = &self.section_headers[index];
+      let end = section.offset.saturating_add(section.size);
+      if let Some((furthest_index, furthest_end)) = furthest {
+        if section.offset < furthest_end {
+          let (first_index, second_index) = (furthest_index.min(index), furthest_index.max(index));
+          tricks.push(AntiAnalysisTrick::OverlappingSections { first_index, second_index });
+        }
+        if end <= furthest_end {
+          continue;
+        }
+      }
+      furthest = Some((index, end));
+    }
+  }
+
+  fn detect_segment_tricks(&self, tricks: &mut Vec<AntiAnalysisTrick>) {
+    let loads: Vec<_> = self.program_headers.iter().enumerate()
+      .filter(|(_, ph)| ph.entry_type == PT_LOAD && ph.memory_size != 0)
+      .collect();
+    for (position, &(first_index, first)) in loads.iter().enumerate() {
+      for &(second_index, second) in &loads[position + 1..] {
+        let overlaps = first.virtual_address < second.virtual_address.saturating_add(second.memory_size)
+          && second.virtual_address < first.virtual_address.saturating_add(first.memory_size);
+        if overlaps {
+          tricks.push(AntiAnalysisTrick::OverlappingSegments { first_index, second_index });
+        }
+      }
+    }
+
+    let entry = self.header.description.entry;
+    let has_entry = matches!(self.header.description.obj_type, ET_EXEC | ET_DYN) && entry != 0 && !loads.is_empty();
+    let executable = loads.iter()
+      .any(|(_, ph)| ph.flags & PF_X != 0 && entry >= ph.virtual_address && entry - ph.virtual_address < ph.memory_size);
+    if has_entry && !executable {
+      tricks.push(AntiAnalysisTrick::EntryOutsideExecutableSegment { entry });
+    }
+  }
+}
+
+fn file_backed(section: &SectionHeader) -> bool {
+  section.section_type != SHT_NOBITS && section.section_type != SHT_NULL && section.size != 0
+}
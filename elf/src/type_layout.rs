@@ -0,0 +1,315 @@
+use std::fmt;
+use std::io;
+use std::io::ErrorKind;
+use crate::abi::type_name;
+use crate::dwarf::*;
+use crate::elf::Elf;
+use crate::leb128::read_uleb128;
+
+const CACHELINE_SIZE: u64 = 64;
+//typedefs, qualifiers and arrays followed before a type is given up on, they may form a cycle
+const MAX_TYPE_DEPTH: usize = 16;
+//DW_OP_plus_uconst, what member locations that are not constants use
+const DW_OP_PLUS_UCONST: u8 = 0x23;
+
+#[derive(Clone, Debug)]
+pub struct TypeMember {
+  //None for base classes and anonymous structs and unions
+  pub name: Option<String>,
+  pub type_name: String,
+  pub offset: u64,
+  //the storage unit for bitfields
+  pub size: u64,
+  //(bits past `offset`, width) for bitfields
+  pub bit_field: Option<(u64, u64)>,
+  pub base_class: bool,
+}
+
+impl TypeMember {
+  //the bits of the type the member takes, [start, end), or None past what a u64 counts
+  fn checked_bit_range(&self) -> Option<(u64, u64)> {
+    let start = self.offset.checked_mul(8)?;
+    match self.bit_field {
+      Some((bit_offset, width)) => {
+        let start = start.checked_add(bit_offset)?;
+        Some((start, start.checked_add(width)?))
+      },
+      None => Some((start, self.offset.checked_add(self.size)?.checked_mul(8)?)),
+    }
+  }
+
+  //the bits of the type the member takes, [start, end)
+  pub fn bit_range(&self) -> (u64, u64) {
+    self.checked_bit_range().unwrap_or((u64::MAX, u64::MAX))
+  }
+
+  //"char name[8]" and "int (*name)(int)" rather than the type then the name
+  fn declaration(&self) -> String {
+    let name = match (&self.name, self.base_class) {
+      (Some(name), _) => name.as_str(),
+      (None, true) => return format!("{} <base>", self.type_name),
+      (None, false) => return self.type_name.clone(),
+    };
+    if let Some(position) = self.type_name.find("(*)") {
+      return format!("{}(*{}){}", &self.type_name[..position], name, &self.type_name[position + 3..]);
+    }
+    match self.type_name.find('[') {
+      Some(position) => format!("{} {}{}", self.type_name[..position].trim_end(), name, &self.type_name[position..]),
+      None => format!("{} {}", self.type_name, name),
+    }
+  }
+}
+
+//Unused bits between members, in bits since bitfields leave holes smaller than a byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LayoutHole {
+  pub bit_offset: u64,
+  pub bits: u64,
+}
+
+//The memory layout of a struct, class or union, printed like pahole does.
+#[derive(Clone, Debug)]
+pub struct TypeLayout {
+  //"struct", "class" or "union"
+  pub keyword: &'static str,
+  pub name: String,
+  pub size: u64,
+  //in offset order
+  pub members: Vec<TypeMember>,
+  //holes before each member, after the previous one ends; unions have none
+  pub holes: Vec<LayoutHole>,
+  //unused bits after the last member
+  pub padding_bits: u64,
+}
+
+impl TypeLayout {
+  pub fn hole_bits(&self) -> u64 {
+    self.holes.iter().map(|hole| hole.bits).sum()
+  }
+
+  pub fn member_bits(&self) -> u64 {
+    self.members.iter().map(|member| {
+      let (start, end) = member.bit_range();
+      end - start
+    }).sum()
+  }
+}
+
+fn bytes_or_bits(bits: u64) -> String {
+  if bits.is_multiple_of(8) {
+    format!("{} byte{}", bits / 8, if bits == 8 { "" } else { "s" })
+  } else {
+    format!("{} bit{}", bits, if bits == 1 { "" } else { "s" })
+  }
+}
+
+impl fmt::Display for TypeLayout {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "{} {} {{", self.keyword, self.name)?;
+    let mut cacheline = 0;
+    for member in &self.members {
+      let (start, _) = member.bit_range();
+      if let Some(hole) = self.holes.iter().find(|hole| hole.bit_offset + hole.bits == start) {
+        writeln!(f)?;
+        writeln!(f, "\t/* XXX {} hole, try to pack */", bytes_or_bits(hole.bits))?;
+        writeln!(f)?;
+      }
+      if member.offset / CACHELINE_SIZE > cacheline {
+        cacheline = member.offset / CACHELINE_SIZE;
+        writeln!(f, "\t/* --- cacheline {} boundary ({} bytes) --- */", cacheline, cacheline * CACHELINE_SIZE)?;
+      }
+      let location = match member.bit_field {
+        Some((bit_offset, width)) => (format!("{}:{}", member.declaration(), width), format!("{:5}:{:2}", member.offset, bit_offset)),
+        None => (member.declaration(), format!("{:5}", member.offset)),
+      };
+      writeln!(f, "\t{:<40} /* {} {:5} */", format!("{};", location.0), location.1, member.size)?;
+    }
+    if !self.members.is_empty() {
+      writeln!(f)?;
+    }
+    let cachelines = self.size.div_ceil(CACHELINE_SIZE);
+    writeln!(f, "\t/* size: {}, cachelines: {}, members: {} */", self.size, cachelines, self.members.len())?;
+    if !self.holes.is_empty() {
+      writeln!(f, "\t/* sum members: {}, holes: {}, sum holes: {} */", bytes_or_bits(self.member_bits()), self.holes.len(), bytes_or_bits(self.hole_bits()))?;
+    }
+    if self.padding_bits > 0 {
+      writeln!(f, "\t/* padding: {} */", bytes_or_bits(self.padding_bits))?;
+    }
+    if !self.size.is_multiple_of(CACHELINE_SIZE) {
+      writeln!(f, "\t/* last cacheline: {} bytes */", self.size % CACHELINE_SIZE)?;
+    }
+    write!(f, "}};")
+  }
+}
+
+//Byte size of the type DIE at `offset`, looking through typedefs and qualifiers; arrays
+//multiply out their subranges and pointers default to the address size.
+fn type_size(unit: &Unit<'_>, offset: Option<u64>, depth: usize) -> Option<u64> {
+  let index = unit.index_of(offset?).filter(|_| depth < MAX_TYPE_DEPTH)?;
+  let die = &unit.dies[index];
+  if let Some(size) = die.unsigned(DW_AT_BYTE_SIZE) {
+    return Some(size);
+  }
+  match die.tag {
+    DW_TAG_POINTER_TYPE | DW_TAG_REFERENCE_TYPE | DW_TAG_RVALUE_REFERENCE_TYPE => Some(unit.header.address_size as u64),
+    DW_TAG_ARRAY_TYPE => {
+      let mut size = type_size(unit, die.reference(DW_AT_TYPE), depth + 1)?;
+      for child in unit.children(index).filter(|&child| unit.dies[child].tag == DW_TAG_SUBRANGE_TYPE) {
+        let subrange = &unit.dies[child];
+        let count = match subrange.unsigned(DW_AT_COUNT) {
+          Some(count) => count,
+          None => subrange.unsigned(DW_AT_UPPER_BOUND).map_or(Some(0), |bound| bound.checked_add(1))?,
+        };
+        size = size.checked_mul(count)?;
+      }
+      Some(size)
+    },
+    DW_TAG_TYPEDEF | DW_TAG_CONST_TYPE | DW_TAG_VOLATILE_TYPE | DW_TAG_RESTRICT_TYPE | DW_TAG_ATOMIC_TYPE => {
+      type_size(unit, die.reference(DW_AT_TYPE), depth + 1)
+    },
+    _ => None,
+  }
+}
+
+//DW_AT_data_member_location, a constant or a DW_OP_plus_uconst expression. Virtual bases
+//are located at run time and give None.
+fn member_location(die: &Die<'_>) -> Option<u64> {
+  match die.attribute(DW_AT_DATA_MEMBER_LOCATION) {
+    None => Some(0),
+    Some(AttributeValue::Expression(bytes)) | Some(AttributeValue::Block(bytes)) => {
+      let mut position = 1;
+      match bytes.first() {
+        Some(&DW_OP_PLUS_UCONST) => read_uleb128(bytes, &mut position).filter(|_| position == bytes.len()),
+        _ => None,
+      }
+    },
+    Some(_) => die.unsigned(DW_AT_DATA_MEMBER_LOCATION),
+  }
+}
+
+fn is_aggregate(die: &Die<'_>) -> bool {
+  matches!(die.tag, DW_TAG_STRUCTURE_TYPE | DW_TAG_CLASS_TYPE | DW_TAG_UNION_TYPE)
+}
+
+impl Elf {
+  //The layout of the struct, class or union `name` (qualified, "ns::Type", and optionally
+  //with its keyword) or of the one a typedef of that name stands for, from .debug_info.
+  //The first definition found is used.
+  pub fn type_layout(&self, name: &str) -> io::Result<TypeLayout> {
+    let dwarf = self.dwarf();
+    if dwarf.is_empty() {
+      return Err(io::Error::new(ErrorKind::NotFound, "no .debug_info"));
+    }
+    let name = ["struct ", "class ", "union "].iter().find_map(|keyword| name.strip_prefix(keyword)).unwrap_or(name);
+    let units = dwarf.units();
+    let matches = |unit: &Unit<'_>, index: usize, name: &str| {
+      let die = &unit.dies[index];
+      die.string(DW_AT_NAME).is_some_and(|plain| plain == name) || unit.qualified_name(index).is_some_and(|qualified| qualified == name)
+    };
+    let definition = |name: &str| units.iter().find_map(|unit| {
+      (0..unit.dies.len()).find(|&index| {
+        let die = &unit.dies[index];
+        is_aggregate(die) && !die.flag(DW_AT_DECLARATION) && die.unsigned(DW_AT_BYTE_SIZE).is_some() && matches(unit, index, name)
+      }).map(|index| (unit, index))
+    });
+    let found = definition(name).or_else(|| units.iter().find_map(|unit| {
+      let typedef = (0..unit.dies.len()).find(|&index| unit.dies[index].tag == DW_TAG_TYPEDEF && matches(unit, index, name))?;
+      let mut target = unit.dies[typedef].reference(DW_AT_TYPE).and_then(|offset| unit.index_of(offset))?;
+      let mut depth = 0;
+      while matches!(unit.dies[target].tag, DW_TAG_TYPEDEF | DW_TAG_CONST_TYPE | DW_TAG_VOLATILE_TYPE) {
+        depth += 1;
+        if depth > MAX_TYPE_DEPTH {
+          return None;
+        }
+        target = unit.dies[target].reference(DW_AT_TYPE).and_then(|offset| unit.index_of(offset))?;
+      }
+      let die = &unit.dies[target];
+      if !is_aggregate(die) {
+        None
+      } else if die.flag(DW_AT_DECLARATION) {
+        definition(&unit.qualified_name(target)?)
+      } else {
+        Some((unit, target))
+      }
+    }));
+    let (unit, index) = found.ok_or_else(|| io::Error::new(ErrorKind::NotFound, format!("no definition of {} in .debug_info", name)))?;
+    let die = &unit.dies[index];
+    let keyword = match die.tag {
+      DW_TAG_CLASS_TYPE => "class",
+      DW_TAG_UNION_TYPE => "union",
+      _ => "struct",
+    };
+    let size = die.unsigned(DW_AT_BYTE_SIZE).unwrap_or(0);
+    let big_endian = self.header.identification.endianness == 2;
+
+    let mut members = Vec::new();
+    for child in unit.children(index) {
+      let member = &unit.dies[child];
+      let base_class = member.tag == DW_TAG_INHERITANCE;
+      //static members are declarations, DWARF 5 makes them variables
+      if !(base_class || member.tag == DW_TAG_MEMBER) || member.flag(DW_AT_DECLARATION) {
+        continue;
+      }
+      let type_offset = member.reference(DW_AT_TYPE);
+      let type_size = type_size(unit, type_offset, 0).unwrap_or(0);
+      let (offset, member_size, bit_field) = match (member.unsigned(DW_AT_BIT_SIZE), member.unsigned(DW_AT_DATA_BIT_OFFSET)) {
+        //DWARF 5 and DWARF 4 with -gstrict-dwarf off: bits from the start of the type
+        (Some(width), Some(bit_offset)) => {
+          let unit_size = type_size.max(1);
+          let offset = bit_offset / 8 / unit_size * unit_size;
+          (offset, unit_size, Some((bit_offset - offset * 8, width)))
+        },
+        //DWARF 4: bits from the most significant bit of the storage unit at the location
+        (Some(width), None) => {
+          let offset = match member_location(member) {
+            Some(offset) => offset,
+            None => continue,
+          };
+          let unit_size = member.unsigned(DW_AT_BYTE_SIZE).unwrap_or(type_size);
+          let from_top = member.unsigned(DW_AT_BIT_OFFSET).unwrap_or(0);
+          let bit_offset = match (unit_size.checked_mul(8), from_top.checked_add(width)) {
+            _ if big_endian => from_top,
+            (Some(bits), Some(used)) => bits.saturating_sub(used),
+            _ => continue,
+          };
+          (offset, unit_size, Some((bit_offset, width)))
+        },
+        (None, _) => match member_location(member) {
+          Some(offset) => (offset, type_size, None),
+          None => continue,
+        },
+      };
+      let member = TypeMember {
+        name: member.string(DW_AT_NAME).map(|name| name.into_owned()),
+        type_name: type_name(unit, type_offset, false, 0),
+        offset,
+        size: member_size,
+        bit_field,
+        base_class,
+      };
+      //a location past the bits a u64 counts is corrupt
+      if member.checked_bit_range().is_some() {
+        members.push(member);
+      }
+    }
+    members.sort_by_key(|member| member.bit_range().0);
+
+    let mut holes = Vec::new();
+    let mut end = 0;
+    for member in &members {
+      let (start, member_end) = member.bit_range();
+      if keyword != "union" && start > end {
+        holes.push(LayoutHole { bit_offset: end, bits: start - end });
+      }
+      end = end.max(member_end);
+    }
+    Ok(TypeLayout {
+      keyword,
+      name: unit.qualified_name(index).unwrap_or_else(|| String::from(name)),
+      size,
+      members,
+      holes,
+      padding_bits: size.saturating_mul(8).saturating_sub(end),
+    })
+  }
+}
@@ -0,0 +1,173 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use crate::consts::*;
+use crate::elf::{read_str, Elf, SectionHeader};
+
+//An entry of .gnu.version_d (SHT_GNU_verdef).
+#[derive(Clone, Debug)]
+pub struct VersionDefinition {
+  pub index: u16,
+  pub flags: u16,
+  pub hash: u32,
+  //the version first, then the versions it inherits from
+  pub names: Vec<String>,
+}
+
+impl VersionDefinition {
+  pub fn name(&self) -> &str {
+    self.names.first().map_or("", |name| name.as_str())
+  }
+
+  //the definition naming the file itself rather than a version
+  pub fn is_base(&self) -> bool {
+    self.flags & VER_FLG_BASE != 0
+  }
+}
+
+//A version needed from a dependency, an auxiliary entry of .gnu.version_r (SHT_GNU_verneed).
+#[derive(Clone, Debug)]
+pub struct VersionRequirement {
+  pub file: String,
+  pub name: String,
+  pub index: u16,
+  pub flags: u16,
+  pub hash: u32,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SymbolVersion {
+  pub name: String,
+  //the dependency the version is needed from, None for versions this file defines
+  pub file: Option<String>,
+  //name@VERSION rather than name@@VERSION, only reachable by explicit binding
+  pub hidden: bool,
+}
+
+impl Elf {
+  fn version_section(&self, section_type: u32) -> Option<(&SectionHeader, &[u8], &[u8])> {
+    let section = self.section_headers.iter().find(|section| section.section_type == section_type)?;
+    let strings = self.section_headers.get(section.link as usize).map_or(&[][..], |table| self.section_data(table));
+    Some((section, self.section_data(section), strings))
+  }
+
+  pub fn version_definitions(&self) -> Vec<VersionDefinition> {
+    match self.header.identification.endianness {
+      1 => self.version_definitions_with_byteorder::<LittleEndian>(),
+      2 => self.version_definitions_with_byteorder::<BigEndian>(),
+      _ => panic!("unknown endianness"),
+    }
+  }
+
+  fn version_definitions_with_byteorder<E: ByteOrder>(&self) -> Vec<VersionDefinition> {
+    let mut definitions = Vec::new();
+    let (section, data, strings) = match self.version_section(SHT_GNU_VERDEF) {
+      Some(section) => section,
+      None => return definitions,
+    };
+    //sh_info holds the number of entries, the chain stops there even if vd_next loops
+    let mut offset = 0usize;
+    for _ in 0..section.info {
+      let entry = match data.get(offset..offset.saturating_add(20)) {
+        Some(entry) => entry,
+        None => break,
+      };
+      let mut definition = VersionDefinition {
+        flags: E::read_u16(&entry[2..4]),
+        index: E::read_u16(&entry[4..6]),
+        hash: E::read_u32(&entry[8..12]),
+        names: Vec::new(),
+      };
+      let mut aux = offset.saturating_add(E::read_u32(&entry[12..16]) as usize);
+      for _ in 0..E::read_u16(&entry[6..8]) {
+        let name = match data.get(aux..aux.saturating_add(8)) {
+          Some(name) => name,
+          None => break,
+        };
+        definition.names.push(read_str(strings, E::read_u32(&name[0..4]) as usize).unwrap_or("").to_string());
+        match E::read_u32(&name[4..8]) {
+          0 => break,
+          next => aux = aux.saturating_add(next as usize),
+        };
+      }
+      definitions.push(definition);
+      match E::read_u32(&entry[16..20]) {
+        0 => break,
+        next => offset = offset.saturating_add(next as usize),
+      };
+    }
+    definitions
+  }
+
+  pub fn version_requirements(&self) -> Vec<VersionRequirement> {
+    match self.header.identification.endianness {
+      1 => self.version_requirements_with_byteorder::<LittleEndian>(),
+      2 => self.version_requirements_with_byteorder::<BigEndian>(),
+      _ => panic!("unknown endianness"),
+    }
+  }
+
+  fn version_requirements_with_byteorder<E: ByteOrder>(&self) -> Vec<VersionRequirement> {
+    let mut requirements = Vec::new();
+    let (section, data, strings) = match self.version_section(SHT_GNU_VERNEED) {
+      Some(section) => section,
+      None => return requirements,
+    };
+    let mut offset = 0usize;
+    for _ in 0..section.info {
+      let entry = match data.get(offset..offset.saturating_add(16)) {
+        Some(entry) => entry,
+        None => break,
+      };
+      let file = read_str(strings, E::read_u32(&entry[4..8]) as usize).unwrap_or("");
+      let mut aux = offset.saturating_add(E::read_u32(&entry[8..12]) as usize);
+      for _ in 0..E::read_u16(&entry[2..4]) {
+        let needed = match data.get(aux..aux.saturating_add(16)) {
+          Some(needed) => needed,
+          None => break,
+        };
+        requirements.push(VersionRequirement {
+          file: file.to_string(),
+          name: read_str(strings, E::read_u32(&needed[8..12]) as usize).unwrap_or("").to_string(),
+          index: E::read_u16(&needed[6..8]),
+          flags: E::read_u16(&needed[4..6]),
+          hash: E::read_u32(&needed[0..4]),
+        });
+        match E::read_u32(&needed[12..16]) {
+          0 => break,
+          next => aux = aux.saturating_add(next as usize),
+        };
+      }
+      match E::read_u32(&entry[12..16]) {
+        0 => break,
+        next => offset = offset.saturating_add(next as usize),
+      };
+    }
+    requirements
+  }
+
+  //One entry per dynamic symbol, in dynamic_symbols() order. Local and unversioned global
+  //symbols, and every symbol of a file without .gnu.version, have None.
+  pub fn symbol_versions(&self) -> Vec<Option<SymbolVersion>> {
+    let count = self.dynamic_symbols().len();
+    let data = match self.version_section(SHT_GNU_VERSYM) {
+      Some((_, data, _)) => data,
+      None => return vec![None; count],
+    };
+    let definitions = self.version_definitions();
+    let requirements = self.version_requirements();
+    let big_endian = self.header.identification.endianness == 2;
+    (0..count).map(|index| {
+      let entry = data.get(index * 2..index * 2 + 2)?;
+      let value = if big_endian { BigEndian::read_u16(entry) } else { LittleEndian::read_u16(entry) };
+      let version = value & !VERSYM_HIDDEN;
+      if version == VER_NDX_LOCAL || version == VER_NDX_GLOBAL {
+        return None;
+      }
+      let hidden = value & VERSYM_HIDDEN != 0;
+      if let Some(definition) = definitions.iter().find(|definition| definition.index == version) {
+        return Some(SymbolVersion { name: definition.name().to_string(), file: None, hidden });
+      }
+      let requirement = requirements.iter().find(|requirement| requirement.index == version)?;
+      Some(SymbolVersion { name: requirement.name.clone(), file: Some(requirement.file.clone()), hidden })
+    }).collect()
+  }
+}
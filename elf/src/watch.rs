@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::parse_options::ParseOptions;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SectionChange {
+  pub name: String,
+  //index in the new version
+  pub index: usize,
+  pub old_size: u64,
+  pub new_size: u64,
+  pub old_address: u64,
+  pub new_address: u64,
+  //the bytes differ, a section can change contents without changing size
+  pub content_changed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SymbolChange {
+  pub name: String,
+  pub old_value: u64,
+  pub new_value: u64,
+  pub old_size: u64,
+  pub new_size: u64,
+}
+
+//What changed between two versions of a file. Sections are matched by name and, for names
+//used more than once, by their position among the sections of that name, symbols by name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StructuralDiff {
+  pub entry: Option<(u64, u64)>,
+  pub sections_added: Vec<String>,
+  pub sections_removed: Vec<String>,
+  pub sections_changed: Vec<SectionChange>,
+  //the program headers differ in number, layout or permissions
+  pub segments_changed: bool,
+  pub symbols_added: Vec<String>,
+  pub symbols_removed: Vec<String>,
+  pub symbols_changed: Vec<SymbolChange>,
+}
+
+impl StructuralDiff {
+  pub fn is_empty(&self) -> bool {
+    *self == StructuralDiff::default()
+  }
+}
+
+//(name, occurrence of the name) to section index, e.g. the second .text is (".text", 1)
+fn section_keys(elf: &Elf) -> HashMap<(&str, usize), usize> {
+  let mut occurrences: HashMap<&str, usize> = HashMap::new();
+  let mut keys = HashMap::new();
+  for (index, section) in elf.section_headers.iter().enumerate() {
+    if let Some(name) = elf.section_name(section) {
+      let occurrence = occurrences.entry(name).or_insert(0);
+      keys.insert((name, *occurrence), index);
+      *occurrence += 1;
+    }
+  }
+  keys
+}
+
+//name to (value, size) of the defined symbols of both tables
+fn defined_symbols(elf: &Elf) -> HashMap<&str, (u64, u64)> {
+  elf.symbol_table().iter().chain(elf.dynamic_symbol_table())
+    .filter(|symbol| !symbol.is_undefined() && !symbol.name.is_empty())
+    .map(|symbol| (&*symbol.name, (symbol.value, symbol.size)))
+    .collect()
+}
+
+impl Elf {
+  pub fn diff(&self, other: &Elf) -> StructuralDiff {
+    let mut diff = StructuralDiff::default();
+    let (old_entry, new_entry) = (self.header.description.entry, other.header.description.entry);
+    if old_entry != new_entry {
+      diff.entry = Some((old_entry, new_entry));
+    }
+
+    let old_sections = section_keys(self);
+    let new_sections = section_keys(other);
+    let mut keys: Vec<_> = new_sections.iter().map(|(&key, &index)| (index, key)).collect();
+    keys.sort_unstable();
+    for (index, key) in keys {
+      let (name, _) = key;
+      let section = &other.section_headers[index];
+      match old_sections.get(&key) {
+        None => diff.sections_added.push(name.to_string()),
+        Some(&old_index) => {
+          let old = &self.section_headers[old_index];
+          let content_changed = self.section_data(old) != other.section_data(section);
+          if content_changed || old.size != section.size || old.address != section.address {
+            diff.sections_changed.push(SectionChange {
+              name: name.to_string(),
+              index,
+              old_size: old.size,
+              new_size: section.size,
+              old_address: old.address,
+              new_address: section.address,
+              content_changed,
+            });
+          }
+        },
+      }
+    }
+    let mut removed: Vec<_> = old_sections.iter().filter(|(key, _)| !new_sections.contains_key(*key)).map(|(&(name, _), &index)| (index, name)).collect();
+    removed.sort_unstable();
+    diff.sections_removed = removed.into_iter().map(|(_, name)| name.to_string()).collect();
+
+    diff.segments_changed = self.program_headers.len() != other.program_headers.len()
+      || self.program_headers.iter().zip(&other.program_headers).any(|(old, new)| {
+        (old.entry_type, old.flags, old.virtual_address, old.file_size, old.memory_size) != (new.entry_type, new.flags, new.virtual_address, new.file_size, new.memory_size)
+      });
+
+    //the same symbol and string table bytes decode to the same symbols
+    if self.symbol_tables_match(other) {
+      return diff;
+    }
+    let old_symbols = defined_symbols(self);
+    let new_symbols = defined_symbols(other);
+    for (&name, &(new_value, new_size)) in &new_symbols {
+      match old_symbols.get(name) {
+        None => diff.symbols_added.push(name.to_string()),
+        Some(&(old_value, old_size)) if (old_value, old_size) != (new_value, new_size) => {
+          diff.symbols_changed.push(SymbolChange { name: name.to_string(), old_value, new_value, old_size, new_size });
+        },
+        Some(_) => {},
+      }
+    }
+    diff.symbols_removed = old_symbols.keys().filter(|name| !new_symbols.contains_key(*name)).map(|name| name.to_string()).collect();
+    diff.symbols_added.sort();
+    diff.symbols_removed.sort();
+    diff.symbols_changed.sort_by(|a, b| a.name.cmp(&b.name));
+    diff
+  }
+
+  //The symbol tables and their string tables sit at the same indices with the same contents.
+  fn symbol_tables_match(&self, other: &Elf) -> bool {
+    let same = |index: usize| match (self.section_headers.get(index), other.section_headers.get(index)) {
+      (Some(old), Some(new)) => (old.section_type, old.link, old.entry_size) == (new.section_type, new.link, new.entry_size) && self.section_data(old) == other.section_data(new),
+      (None, None) => true,
+      _ => false,
+    };
+    let tables = |elf: &Elf| -> Vec<usize> {
+      elf.section_headers.iter().enumerate()
+        .filter(|(_, section)| section.section_type == SHT_SYMTAB || section.section_type == SHT_DYNSYM)
+        .flat_map(|(index, section)| [index, section.link as usize])
+        .collect()
+    };
+    let indices = tables(self);
+    indices == tables(other) && indices.into_iter().all(same)
+  }
+
+  pub fn watch<P: AsRef<Path>, F: FnMut(&Elf, &StructuralDiff) -> bool>(path: P, callback: F) -> io::Result<()> {
+    Elf::watch_with_interval(path, Duration::from_millis(500), callback)
+  }
+
+  //Polls `path` every `interval` and calls `callback` with the new version and its diff to the
+  //previous one whenever the contents change, until the callback returns false. A file is read
+  //once its size and modification time stayed the same for one interval, so a linker still
+  //writing it is not parsed half way, and a version Elf::parse rejects is skipped with the last
+  //good one kept. The new version shares the interner of the old one, names that did not change
+  //are not allocated again and names no version uses any more are purged. When the symbol
+  //tables kept their bytes the decoded tables are carried over instead of decoded again.
+  pub fn watch_with_interval<P: AsRef<Path>, F: FnMut(&Elf, &StructuralDiff) -> bool>(path: P, interval: Duration, mut callback: F) -> io::Result<()> {
+    let path = path.as_ref();
+    let stamp = |path: &Path| -> Option<(u64, SystemTime)> {
+      let metadata = fs::metadata(path).ok()?;
+      Some((metadata.len(), metadata.modified().ok()?))
+    };
+    let mut current = Elf::open(path)?;
+    let mut seen = stamp(path);
+    loop {
+      thread::sleep(interval);
+      let now = stamp(path);
+      if now.is_none() || now == seen {
+        continue;
+      }
+      thread::sleep(interval);
+      if stamp(path) != now {
+        continue;
+      }
+      seen = now;
+      let data = match fs::read(path) {
+        Ok(data) => data,
+        Err(_) => continue,
+      };
+      if *data == *current.data {
+        continue;
+      }
+      let mut next = match Elf::parse_with_interner(data.into_boxed_slice(), &ParseOptions::default(), current.interner.clone()) {
+        Ok((next, _)) => next,
+        Err(_) => continue,
+      };
+      let diff = current.diff(&next);
+      if current.symbol_tables_match(&next) {
+        next.adopt_symbol_maps(&mut current);
+      }
+      current = next;
+      current.interner.purge();
+      if !callback(&current, &diff) {
+        return Ok(());
+      }
+    }
+  }
+}
@@ -0,0 +1,437 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use crate::consts::*;
+use crate::elf::Elf;
+use crate::jit::{read_perf_map, JitDump, SyntheticSymbol};
+use crate::loader::PAGE_SIZE;
+use crate::symbol::Symbol;
+
+pub const RTLD_LAZY: u32 = 0x0001;
+pub const RTLD_NOW: u32 = 0x0002;
+pub const RTLD_NOLOAD: u32 = 0x0004;
+pub const RTLD_DEEPBIND: u32 = 0x0008;
+pub const RTLD_GLOBAL: u32 = 0x0100;
+pub const RTLD_LOCAL: u32 = 0;
+pub const RTLD_NODELETE: u32 = 0x1000;
+
+pub struct LoadedObject {
+  pub path: PathBuf,
+  pub elf: Elf,
+  //added to every address of the object
+  pub bias: u64,
+  pub soname: Option<String>,
+  //part of the global scope every lookup searches first
+  pub global: bool,
+  //RTLD_DEEPBIND: the local scope is searched before the global one
+  pub deep_bind: bool,
+  //the object followed by its dependencies breadth first, indices into Workspace::objects
+  pub local_scope: Vec<usize>,
+  //loaded by dlopen rather than at startup, its TLS is allocated lazily
+  pub opened_at_runtime: bool,
+}
+
+//A dynamic symbol an object references and the definition the lookup rules pick for it.
+pub struct SymbolBinding {
+  pub object_index: usize,
+  pub name: Arc<str>,
+  pub weak: bool,
+  //object index and biased address, None when nothing in scope defines it. TLS symbols give
+  //their offset in the module's TLS block instead.
+  pub definition: Option<(usize, u64)>,
+}
+
+//Values the dynamic loader substitutes for the $LIB and $PLATFORM tokens of DT_RPATH,
+//DT_RUNPATH and DT_NEEDED, $ORIGIN always comes from the requesting object.
+#[derive(Clone, Default, Debug)]
+pub struct PathTokens {
+  //"lib64" for 64-bit objects and "lib" otherwise when None, what upstream glibc uses.
+  //Debian's multiarch glibc expands it to "lib/x86_64-linux-gnu".
+  pub lib: Option<String>,
+  //the kernel's AT_PLATFORM string, guessed from e_machine when None. Paths using it are
+  //dropped when there is no guess, like the loader does for a token it cannot expand.
+  pub platform: Option<String>,
+}
+
+//A simulated address space: ELF objects at their load bias plus symbols that have no ELF
+//backing, such as JIT generated code.
+#[derive(Default)]
+pub struct Workspace {
+  pub objects: Vec<LoadedObject>,
+  pub synthetic_symbols: Vec<SyntheticSymbol>,
+  //searched like LD_LIBRARY_PATH, before the default directories
+  pub library_paths: Vec<PathBuf>,
+  pub bindings: Vec<SymbolBinding>,
+  pub path_tokens: PathTokens,
+}
+
+impl LoadedObject {
+  //TLS symbols are not biased, their value is an offset in the module's TLS block
+  pub fn symbol_address(&self, symbol: &Symbol) -> u64 {
+    if symbol.symbol_type == STT_TLS { symbol.value } else { symbol.value.wrapping_add(self.bias) }
+  }
+}
+
+pub struct Symbolized<'a> {
+  pub name: &'a str,
+  pub offset: u64,
+  //None for synthetic symbols
+  pub object_index: Option<usize>,
+}
+
+fn default_library_paths(machine: u16, class: u8) -> Vec<PathBuf> {
+  let multiarch = match machine {
+    EM_X86_64 if class == 2 => Some("x86_64-linux-gnu"),
+    EM_386 => Some("i386-linux-gnu"),
+    EM_AARCH64 => Some("aarch64-linux-gnu"),
+    EM_ARM => Some("arm-linux-gnueabihf"),
+    EM_RISCV => Some("riscv64-linux-gnu"),
+    _ => None,
+  };
+  let mut paths = Vec::new();
+  if let Some(multiarch) = multiarch {
+    paths.push(PathBuf::from("/lib").join(multiarch));
+    paths.push(PathBuf::from("/usr/lib").join(multiarch));
+  }
+  if class == 2 {
+    paths.push(PathBuf::from("/lib64"));
+    paths.push(PathBuf::from("/usr/lib64"));
+  }
+  paths.push(PathBuf::from("/lib"));
+  paths.push(PathBuf::from("/usr/lib"));
+  paths
+}
+
+impl Workspace {
+  pub fn new() -> Workspace {
+    Default::default()
+  }
+
+  //Adds an object to the global scope, like the executable and its startup dependencies.
+  pub fn add_object<P: AsRef<Path>>(&mut self, path: P, elf: Elf, bias: u64) -> usize {
+    let index = self.objects.len();
+    let soname = elf.soname();
+    self.objects.push(LoadedObject {
+      path: path.as_ref().to_path_buf(),
+      elf,
+      bias,
+      soname,
+      global: true,
+      deep_bind: false,
+      local_scope: vec![index],
+      opened_at_runtime: false,
+    });
+    index
+  }
+
+  pub fn open_object<P: AsRef<Path>>(&mut self, path: P, bias: u64) -> io::Result<usize> {
+    let elf = Elf::open(path.as_ref())?;
+    Ok(self.add_object(path, elf, bias))
+  }
+
+  pub fn add_synthetic_symbols<I: IntoIterator<Item = SyntheticSymbol>>(&mut self, symbols: I) {
+    self.synthetic_symbols.extend(symbols);
+    self.synthetic_symbols.sort_by_key(|symbol| symbol.address);
+  }
+
+  pub fn add_perf_map<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+    let symbols = read_perf_map(BufReader::new(File::open(path)?))?;
+    let count = symbols.len();
+    self.add_synthetic_symbols(symbols);
+    Ok(count)
+  }
+
+  pub fn add_jitdump<P: AsRef<Path>>(&mut self, path: P) -> io::Result<usize> {
+    let symbols = JitDump::read(BufReader::new(File::open(path)?))?.symbols();
+    let count = symbols.len();
+    self.add_synthetic_symbols(symbols);
+    Ok(count)
+  }
+
+  pub fn object_at(&self, address: u64) -> Option<usize> {
+    self.objects.iter().position(|object| {
+      object.elf.program_headers.iter().any(|ph| {
+        let start = ph.virtual_address.wrapping_add(object.bias);
+        ph.entry_type == PT_LOAD && address >= start && address - start < ph.memory_size
+      })
+    })
+  }
+
+  //Synthetic symbols win over ELF symbols, JIT code is usually mapped over anonymous memory.
+  pub fn symbolize(&self, address: u64) -> Option<Symbolized<'_>> {
+    let position = self.synthetic_symbols.partition_point(|symbol| symbol.address <= address);
+    if let Some(symbol) = position.checked_sub(1).map(|position| &self.synthetic_symbols[position]) {
+      if address - symbol.address < symbol.size.max(1) {
+        return Some(Symbolized { name: &symbol.name, offset: address - symbol.address, object_index: None });
+      }
+    }
+    let object_index = self.object_at(address)?;
+    let object = &self.objects[object_index];
+    let relative = address.wrapping_sub(object.bias);
+    object.elf.symbol_table().iter().chain(object.elf.dynamic_symbol_table().iter())
+      .filter(|symbol| matches!(symbol.symbol_type, STT_FUNC | STT_OBJECT | STT_GNU_IFUNC) && !symbol.is_undefined())
+      .filter(|symbol| relative >= symbol.value && relative - symbol.value < symbol.size.max(1))
+      .min_by_key(|symbol| relative - symbol.value)
+      .map(|symbol| Symbolized { name: &symbol.name, offset: relative - symbol.value, object_index: Some(object_index) })
+  }
+
+  //An already loaded object answering to a DT_NEEDED style name or a path.
+  pub fn find_loaded(&self, name: &str) -> Option<usize> {
+    if name.contains('/') {
+      let path = Path::new(name);
+      return self.objects.iter().position(|object| object.path == path);
+    }
+    self.objects.iter().position(|object| {
+      object.soname.as_deref() == Some(name) || object.path.file_name().is_some_and(|file_name| file_name == name)
+    })
+  }
+
+  //`path` with $ORIGIN, $LIB and $PLATFORM (or ${ORIGIN}, ...) expanded for `requester`,
+  //other $ sequences kept literally. $ORIGIN is the directory of the requester's path, made
+  //absolute but with symlinks kept: ld.so only resolves them for the executable, so add that
+  //under its real path. None when a token has no value, the loader skips such paths.
+  pub fn expand_path_tokens(&self, path: &str, requester: Option<usize>) -> Option<String> {
+    let object = requester.map(|index| &self.objects[index]);
+    let (machine, class) = match object.map(|object| &object.elf).or(self.objects.first().map(|object| &object.elf)) {
+      Some(elf) => (elf.header.description.machine, elf.header.identification.class),
+      None => (EM_X86_64, 2),
+    };
+    let mut expanded = String::new();
+    let mut rest = path;
+    while let Some(position) = rest.find('$') {
+      expanded.push_str(&rest[..position]);
+      let after = &rest[position + 1..];
+      let (name, length) = match after.strip_prefix('{') {
+        Some(braced) => braced.find('}').map_or(("", 0), |end| (&braced[..end], end + 2)),
+        None => {
+          let end = after.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(after.len());
+          (&after[..end], end)
+        },
+      };
+      let value = match name {
+        "ORIGIN" => {
+          let path = std::path::absolute(&object?.path).ok()?;
+          Some(path.parent()?.to_string_lossy().into_owned())
+        },
+        "LIB" => Some(self.path_tokens.lib.clone().unwrap_or_else(|| String::from(if class == 2 { "lib64" } else { "lib" }))),
+        "PLATFORM" => Some(match &self.path_tokens.platform {
+          Some(platform) => platform.clone(),
+          None => match machine {
+            EM_X86_64 => String::from("x86_64"),
+            EM_386 => String::from("i686"),
+            EM_AARCH64 => String::from("aarch64"),
+            EM_ARM => String::from("v7l"),
+            _ => return None,
+          },
+        }),
+        _ => None,
+      };
+      match value {
+        Some(value) => {
+          expanded.push_str(&value);
+          rest = &after[length..];
+        },
+        None => {
+          expanded.push('$');
+          rest = after;
+        },
+      };
+    }
+    expanded.push_str(rest);
+    Some(expanded)
+  }
+
+  //Where a library name resolves to for `requester`: its DT_RPATH when it has no DT_RUNPATH,
+  //library_paths, DT_RUNPATH, then the default directories. Candidates for another machine
+  //are skipped like the dynamic loader does.
+  pub fn search_library(&self, name: &str, requester: Option<usize>) -> Option<PathBuf> {
+    let expanded;
+    let name = match name.contains('$') {
+      true => {
+        expanded = self.expand_path_tokens(name, requester)?;
+        expanded.as_str()
+      },
+      false => name,
+    };
+    if name.contains('/') {
+      return Some(PathBuf::from(name));
+    }
+    let (machine, class) = match requester.map(|index| &self.objects[index].elf).or(self.objects.first().map(|object| &object.elf)) {
+      Some(elf) => (elf.header.description.machine, elf.header.identification.class),
+      None => (EM_X86_64, 2),
+    };
+    let mut directories: Vec<PathBuf> = Vec::new();
+    let (rpath, runpath) = match requester {
+      Some(index) => (self.objects[index].elf.rpath(), self.objects[index].elf.runpath()),
+      None => (Vec::new(), Vec::new()),
+    };
+    let expand = |paths: Vec<String>| paths.iter().filter_map(|path| self.expand_path_tokens(path, requester)).map(PathBuf::from).collect::<Vec<_>>();
+    if runpath.is_empty() {
+      directories.extend(expand(rpath));
+    }
+    directories.extend(self.library_paths.iter().cloned());
+    directories.extend(expand(runpath));
+    directories.extend(default_library_paths(machine, class));
+    directories.into_iter().map(|directory| directory.join(name)).find(|candidate| {
+      let mut header = [0u8; 20];
+      let matches = File::open(candidate).and_then(|mut file| io::Read::read_exact(&mut file, &mut header)).is_ok()
+        && header[..4] == [0x7f, b'E', b'L', b'F']
+        && header[4] == class;
+      let machine_bytes = if header[5] == 2 { [(machine >> 8) as u8, machine as u8] } else { [machine as u8, (machine >> 8) as u8] };
+      matches && header[18..20] == machine_bytes
+    })
+  }
+
+  //first page aligned address above every object
+  fn next_base(&self) -> u64 {
+    let is_64 = self.objects.first().is_none_or(|object| object.elf.header.identification.class == 2);
+    let floor: u64 = if is_64 { 0x7fff_f000_0000 } else { 0xf000_0000 };
+    self.objects.iter()
+      .filter_map(|object| object.elf.load_span().map(|(_, end)| end.wrapping_add(object.bias)))
+      .max()
+      .unwrap_or(floor)
+      .max(floor)
+      .div_ceil(PAGE_SIZE) * PAGE_SIZE
+  }
+
+  //Adds an object and its missing dependencies to the link map at runtime, then resolves
+  //symbols again. RTLD_GLOBAL puts the new objects in the global scope (an RTLD_LOCAL object
+  //that is opened again with RTLD_GLOBAL is promoted), RTLD_NOLOAD only returns an object that
+  //is already loaded and RTLD_DEEPBIND makes the object prefer its own scope.
+  pub fn dlopen<P: AsRef<Path>>(&mut self, path: P, flags: u32) -> io::Result<usize> {
+    let name = path.as_ref().to_string_lossy().into_owned();
+    if let Some(index) = self.find_loaded(&name) {
+      if flags & RTLD_GLOBAL != 0 && !self.objects[index].global {
+        for member in self.objects[index].local_scope.clone() {
+          self.objects[member].global = true;
+        }
+        self.resolve();
+      }
+      return Ok(index);
+    }
+    if flags & RTLD_NOLOAD != 0 {
+      return Err(io::Error::new(io::ErrorKind::NotFound, format!("{} is not loaded", name)));
+    }
+    let global = flags & RTLD_GLOBAL != 0;
+    let root = self.load_library(&name, None, global)?;
+    self.objects[root].deep_bind = flags & RTLD_DEEPBIND != 0;
+
+    //breadth first over DT_NEEDED, the order the local scope and symbol lookup use
+    let mut scope = vec![root];
+    let mut queue = VecDeque::from(vec![root]);
+    while let Some(index) = queue.pop_front() {
+      for needed in self.objects[index].elf.needed_libraries() {
+        let dependency = match self.find_loaded(&needed) {
+          Some(dependency) => dependency,
+          None => {
+            let dependency = self.load_library(&needed, Some(index), global)?;
+            queue.push_back(dependency);
+            dependency
+          },
+        };
+        if !scope.contains(&dependency) {
+          scope.push(dependency);
+        }
+      }
+    }
+    self.objects[root].local_scope = scope;
+    self.resolve();
+    Ok(root)
+  }
+
+  fn load_library(&mut self, name: &str, requester: Option<usize>, global: bool) -> io::Result<usize> {
+    let path = self.search_library(name, requester)
+      .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{}: cannot find shared object", name)))?;
+    let elf = Elf::open(&path).map_err(|error| io::Error::new(error.kind(), format!("{}: {}", path.display(), error)))?;
+    let bias = match elf.header.description.obj_type {
+      ET_DYN => {
+        let (start, _) = elf.load_span().unwrap_or((0, 0));
+        self.next_base().wrapping_sub(start)
+      },
+      _ => 0,
+    };
+    let index = self.add_object(path, elf, bias);
+    self.objects[index].global = global;
+    self.objects[index].opened_at_runtime = true;
+    Ok(index)
+  }
+
+  //dlsym(RTLD_DEFAULT, name): the first definition in the global scope.
+  pub fn lookup(&self, name: &str) -> Option<(usize, u64)> {
+    let global: Vec<usize> = (0..self.objects.len()).filter(|&index| self.objects[index].global).collect();
+    self.lookup_in(&global, name)
+  }
+
+  //dlsym(handle, name): the first definition in the object's local scope.
+  pub fn dlsym(&self, handle: usize, name: &str) -> Option<(usize, u64)> {
+    self.lookup_in(&self.objects.get(handle)?.local_scope, name)
+  }
+
+  fn lookup_in(&self, scope: &[usize], name: &str) -> Option<(usize, u64)> {
+    scope.iter().find_map(|&index| {
+      let object = &self.objects[index];
+      object.elf.dynamic_symbol_indices_by_name(name).iter()
+        .map(|&symbol| &object.elf.dynamic_symbol_table()[symbol])
+        .find(|symbol| !symbol.is_undefined() && symbol.is_global() && symbol.visibility != STV_HIDDEN && symbol.visibility != STV_INTERNAL)
+        .map(|symbol| (index, object.symbol_address(symbol)))
+    })
+  }
+
+  //The objects a lookup from `index` searches, in order, given the global scope.
+  pub(crate) fn lookup_scope(&self, index: usize, global: &[usize]) -> Vec<usize> {
+    let object = &self.objects[index];
+    //dependencies of a dlopened object search the scope of the object that pulled them in
+    let local = self.objects.iter().find(|owner| owner.local_scope.contains(&index)).map_or(&object.local_scope, |owner| &owner.local_scope);
+    if object.deep_bind {
+      local.iter().chain(global.iter()).copied().collect()
+    } else {
+      global.iter().chain(local.iter()).copied().collect()
+    }
+  }
+
+  //Binds every referenced dynamic symbol: the global scope in load order, then the object's
+  //local scope, or the other way around for RTLD_DEEPBIND objects.
+  pub fn resolve(&mut self) {
+    let global: Vec<usize> = (0..self.objects.len()).filter(|&index| self.objects[index].global).collect();
+    let mut bindings = Vec::new();
+    for (index, object) in self.objects.iter().enumerate() {
+      let scope = self.lookup_scope(index, &global);
+      //relocations against the object's own preemptible definitions go through the lookup
+      //too, which is what lets an earlier object interpose them
+      let mut referenced = vec![false; object.elf.dynamic_symbol_table().len()];
+      for relocation in object.elf.relocations() {
+        let linked = object.elf.section_headers[relocation.section_index].link as usize;
+        if object.elf.section_headers.get(linked).is_some_and(|section| section.section_type == SHT_DYNSYM) {
+          if let Some(flag) = referenced.get_mut(relocation.symbol_index as usize) {
+            *flag = true;
+          }
+        }
+      }
+      let symbolic = object.elf.dynamic_value(DT_SYMBOLIC).is_some() || object.elf.dynamic_value(DT_FLAGS).is_some_and(|flags| flags & DF_SYMBOLIC != 0);
+      for (symbol_index, symbol) in object.elf.dynamic_symbol_table().iter().enumerate() {
+        if symbol.name.is_empty() || !symbol.is_global() || !(symbol.is_undefined() || referenced[symbol_index]) {
+          continue;
+        }
+        let definition = if !symbol.is_undefined() && (symbolic || symbol.visibility == STV_PROTECTED) {
+          Some((index, object.symbol_address(symbol)))
+        } else {
+          self.lookup_in(&scope, &symbol.name)
+        };
+        bindings.push(SymbolBinding {
+          object_index: index,
+          name: symbol.name.clone(),
+          weak: symbol.binding == STB_WEAK,
+          definition,
+        });
+      }
+    }
+    self.bindings = bindings;
+  }
+
+  //strong references nothing in scope defines, what RTLD_NOW would refuse to load
+  pub fn unresolved(&self) -> impl Iterator<Item = &SymbolBinding> {
+    self.bindings.iter().filter(|binding| binding.definition.is_none() && !binding.weak)
+  }
+}
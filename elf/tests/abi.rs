@@ -0,0 +1,47 @@
+use elf::*;
+
+fn library(name: &str) -> Elf {
+  Elf::new(std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().into_boxed_slice())
+}
+
+//tests/data/abi-v1.so and abi-v2.so are abi.c built without and with -DV2 against abi.map
+#[test]
+fn version_definitions() {
+  let elf = library("abi-v1.so");
+  let definitions = elf.version_definitions();
+  let names: Vec<(&str, bool)> = definitions.iter().map(|definition| (definition.name(), definition.is_base())).collect();
+  assert_eq!(names, [("libabi.so.1", true), ("ABI_1", false), ("ABI_2", false)]);
+  assert_eq!(definitions[2].names, ["ABI_2", "ABI_1"]);
+  let scale = elf.dynamic_symbol_indices_by_name("scale")[0];
+  assert_eq!(elf.symbol_versions()[scale], Some(SymbolVersion { name: "ABI_1".to_string(), file: None, hidden: false }));
+}
+
+#[test]
+fn snapshot_signatures() {
+  let snapshot = library("abi-v1.so").abi_snapshot();
+  assert_eq!(snapshot.soname.as_deref(), Some("libabi.so.1"));
+  //the ABI_1 and ABI_2 version symbols are left out
+  let symbols: Vec<String> = snapshot.symbols.iter().map(|symbol| format!("{}@@{}", symbol.name, symbol.version.as_deref().unwrap())).collect();
+  assert_eq!(symbols, ["counter@@ABI_1", "legacy@@ABI_1", "origin@@ABI_1", "scale@@ABI_1"]);
+  let scale = &snapshot.symbols[3];
+  assert_eq!((scale.signature.as_deref(), scale.signature_key.as_deref()), (Some("length (length)"), Some("int (int)")));
+  assert_eq!(snapshot.symbols[2].signature_key.as_deref(), Some("struct point/8"));
+}
+
+#[test]
+fn compare_versions() {
+  let old = library("abi-v1.so").abi_snapshot();
+  let new = library("abi-v2.so").abi_snapshot();
+  assert!(old.compare(&old).is_empty());
+  let diff = old.compare(&new);
+  let changes: Vec<String> = diff.changes.iter().map(ToString::to_string).collect();
+  assert_eq!(changes, [
+    "breaking: counter@@ABI_1 size 8 -> 16",
+    "breaking: counter@@ABI_1 signature long int -> long int[2]",
+    "breaking: legacy@@ABI_1 removed",
+    "breaking: scale@@ABI_1 signature length (length) -> length (length, int)",
+    "compatible: shift@@ABI_2 added",
+  ]);
+  assert!(!diff.is_compatible());
+  assert_eq!(diff.breaking().count(), 4);
+}
@@ -0,0 +1,111 @@
+use std::convert::TryInto;
+use elf::*;
+
+fn data(name: &str) -> Vec<u8> {
+  std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap()
+}
+
+//the name and size fields of the member header at `offset`
+fn member_at(archive: &[u8], offset: usize) -> (&str, usize) {
+  let header = std::str::from_utf8(&archive[offset..offset + 60]).unwrap();
+  assert!(header.ends_with("`\n"));
+  (header[..16].trim_end(), header[48..58].trim_end().parse().unwrap())
+}
+
+//the "/" index: each symbol with the offset of its member's header
+fn symbol_index(archive: &[u8]) -> Vec<(String, usize)> {
+  let size = member_at(archive, 8).1;
+  let index = &archive[68..68 + size];
+  let count = u32::from_be_bytes(index[..4].try_into().unwrap()) as usize;
+  let names = index[4 + 4 * count..].split(|&byte| byte == 0).map(|name| String::from_utf8(name.to_vec()).unwrap());
+  index[4..4 + 4 * count].chunks(4).zip(names).map(|(offset, name)| (name, u32::from_be_bytes(offset.try_into().unwrap()) as usize)).collect()
+}
+
+#[test]
+fn index_points_at_the_defining_members() {
+  let mut archive = Archive::new();
+  archive.add_member("struct.o", data("struct.o"));
+  archive.add_member("notes.txt", b"not an object".to_vec());
+  archive.add_member("snippet.o", data("snippet.o"));
+  let bytes = archive.to_bytes().unwrap();
+  assert!(bytes.starts_with(ARCHIVE_MAGIC));
+  assert_eq!(member_at(&bytes, 8).0, "/");
+  let index = symbol_index(&bytes);
+  //locals and the undefined symbols are not indexed, commons are
+  let mut names: Vec<&str> = index.iter().map(|(name, _)| &name[..]).collect();
+  names.sort_unstable();
+  assert_eq!(names, ["counter", "f", "pick", "s", "table"]);
+  for (name, offset) in &index {
+    let member = if name == "f" || name == "s" { "struct.o/" } else { "snippet.o/" };
+    assert_eq!(member_at(&bytes, *offset).0, member);
+  }
+  //the members follow the index, each padded to an even offset
+  let (_, size) = member_at(&bytes, index[0].1);
+  let notes = index[0].1 + 60 + size + size % 2;
+  assert_eq!(member_at(&bytes, notes), ("notes.txt/", 13));
+  assert_eq!(&bytes[notes + 60..notes + 73], b"not an object");
+  //nothing in it depends on when or by whom it was written
+  assert_eq!(archive.to_bytes().unwrap(), bytes);
+}
+
+#[test]
+fn long_names_go_to_the_name_table() {
+  let mut archive = Archive::new();
+  archive.add_member("a_member_name_longer_than_sixteen_bytes.o", b"x".to_vec());
+  archive.add_member("short", b"y".to_vec());
+  let bytes = archive.to_bytes().unwrap();
+  //no member defines a symbol, so there is no index
+  assert_eq!(member_at(&bytes, 8), ("//", 44));
+  assert_eq!(&bytes[68..112], b"a_member_name_longer_than_sixteen_bytes.o/\n\n");
+  assert_eq!(member_at(&bytes, 112), ("/0", 1));
+  assert_eq!(member_at(&bytes, 174), ("short/", 1));
+}
+
+#[test]
+fn names_with_a_slash_are_rejected() {
+  let mut archive = Archive::new();
+  archive.add_member("dir/name.o", data("struct.o"));
+  assert_eq!(archive.to_bytes().unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+  assert_eq!(Archive::new().to_bytes().unwrap(), ARCHIVE_MAGIC);
+}
+
+#[test]
+fn round_trip_keeps_members_and_reads_the_index() {
+  let mut archive = Archive::new();
+  archive.add_member("struct.o", data("struct.o"));
+  archive.add_member("a_member_name_longer_than_sixteen_bytes.o", data("struct.o"));
+  archive.add_member("notes.txt", b"not an object".to_vec());
+  let bytes = archive.to_bytes().unwrap();
+  let read = Archive::from_bytes(&bytes).unwrap();
+  let names: Vec<&str> = read.members.iter().map(|member| &member.name[..]).collect();
+  assert_eq!(names, ["struct.o", "a_member_name_longer_than_sixteen_bytes.o", "notes.txt"]);
+  assert!(read.members.iter().zip(&archive.members).all(|(read, written)| read.data == written.data));
+  let symbols: Vec<(String, usize)> = Archive::read_symbol_index(&bytes).unwrap().into_iter().map(|symbol| (symbol.name, symbol.member_index)).collect();
+  assert_eq!(symbols, symbol_index(&bytes).into_iter().enumerate().map(|(entry, (name, _))| (name, entry / 2)).collect::<Vec<_>>());
+  assert_eq!(read.to_bytes().unwrap(), bytes);
+}
+
+#[test]
+fn wide_index_is_read_back() {
+  let mut archive = Archive::new();
+  archive.wide_index = true;
+  archive.add_member("struct.o", data("struct.o"));
+  let bytes = archive.to_bytes().unwrap();
+  assert_eq!(member_at(&bytes, 8).0, "/SYM64/");
+  let mut symbols = Archive::read_symbol_index(&bytes).unwrap();
+  symbols.sort_by(|a, b| a.name.cmp(&b.name));
+  assert_eq!(symbols, [ArchiveSymbol { name: String::from("f"), member_index: 0 }, ArchiveSymbol { name: String::from("s"), member_index: 0 }]);
+}
+
+#[test]
+fn malformed_archives_are_errors() {
+  assert!(Archive::from_bytes(b"!<arch>\nshort").is_err());
+  assert!(Archive::from_bytes(b"not an archive").is_err());
+  assert!(Archive::read_symbol_index(ARCHIVE_MAGIC).unwrap().is_empty());
+  //an index entry pointing between members
+  let mut archive = Archive::new();
+  archive.add_member("struct.o", data("struct.o"));
+  let mut bytes = archive.to_bytes().unwrap();
+  bytes[72..76].copy_from_slice(&9u32.to_be_bytes());
+  assert_eq!(Archive::read_symbol_index(&bytes).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
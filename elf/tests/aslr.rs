@@ -0,0 +1,26 @@
+use std::io::ErrorKind;
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_DYN, machine: EM_X86_64 }).into_boxed_slice())
+}
+
+#[test]
+fn segments_move_with_the_bias() {
+  let elf = library();
+  let layouts = elf.randomized_layouts(4, 1, &AslrOptions::default()).unwrap();
+  for layout in &layouts {
+    assert_eq!(layout.start % 0x1000, 0);
+    assert!(layout.segments.iter().all(|&(start, size, _)| start >= layout.start && start + size <= layout.end));
+  }
+}
+
+#[test]
+fn segment_ending_past_the_address_space_is_rejected() {
+  let mut elf = library();
+  let load = elf.program_headers.iter_mut().find(|ph| ph.entry_type == PT_LOAD).unwrap();
+  load.virtual_address = u64::MAX - 0x10;
+  load.memory_size = 8;
+  let error = elf.randomized_layouts(1, 1, &AslrOptions::default()).unwrap_err();
+  assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
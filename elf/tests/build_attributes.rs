@@ -0,0 +1,55 @@
+use elf::*;
+
+//tests/data/notes.o holds the annobin notes and the .gnu.warning.gets section of notes.c
+fn object() -> Elf {
+  Elf::new(include_bytes!("data/notes.o").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn gnu_warnings() {
+  let warnings = object().gnu_warnings();
+  assert_eq!(warnings.len(), 1);
+  assert_eq!(warnings[0].symbol, "gets");
+  assert_eq!(warnings[0].message, "the `gets' function is dangerous and should not be used.");
+}
+
+#[test]
+fn notes_without_a_descriptor_apply_to_the_previous_range() {
+  let attributes = object().build_attributes().unwrap();
+  let attributes: Vec<_> = attributes.iter().map(|attribute| (attribute.name.as_str(), attribute.start, attribute.end, attribute.function)).collect();
+  assert_eq!(attributes, [
+    ("stack_prot", 0x1000, 0x1100, false),
+    ("FORTIFY", 0x1000, 0x1100, false),
+    ("stack_clash", 0x1000, 0x1100, false),
+    ("stack_prot", 0x1040, 0x1050, true),
+  ]);
+}
+
+#[test]
+fn hardening_keeps_the_weakest_value() {
+  let flags = object().hardening_flags().unwrap();
+  assert_eq!(flags.stack_protector, Some(STACK_PROTECTOR_BASIC));
+  assert_eq!(flags.fortify_source, Some(2));
+  assert_eq!(flags.stack_clash, Some(true));
+  assert_eq!(flags.cf_protection, None);
+}
+
+//tests/data/stack-protector.o covers three ranges built with all, strong and explicit
+#[test]
+fn stack_protector_levels_are_ranked_by_strength() {
+  let data = include_bytes!("data/stack-protector.o");
+  assert_eq!(Elf::new(data.to_vec().into_boxed_slice()).hardening_flags().unwrap().stack_protector, Some(STACK_PROTECTOR_EXPLICIT));
+  //the explicit range rebuilt with all leaves strong the weakest
+  let mut data = data.to_vec();
+  let explicit = data.windows(5).position(|name| name == b"GA*\x02\x04").unwrap();
+  data[explicit + 4] = STACK_PROTECTOR_ALL as u8;
+  assert_eq!(Elf::new(data.into_boxed_slice()).hardening_flags().unwrap().stack_protector, Some(STACK_PROTECTOR_STRONG));
+}
+
+#[test]
+fn unknown_byte_order_is_an_error() {
+  let mut elf = object();
+  elf.header.identification.endianness = 3;
+  assert_eq!(elf.build_attributes().err().map(|error| error.kind()), Some(std::io::ErrorKind::InvalidData));
+  assert!(elf.notes().is_empty());
+}
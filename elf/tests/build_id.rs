@@ -0,0 +1,52 @@
+use elf::*;
+
+fn object(name: &str) -> Elf {
+  Elf::new(std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().into_boxed_slice())
+}
+
+fn note_id(elf: &Elf) -> Vec<u8> {
+  elf.notes().into_iter().find(|note| note.note_type == NT_GNU_BUILD_ID).unwrap().desc.to_vec()
+}
+
+fn section_offset(elf: &Elf, name: &str) -> usize {
+  elf.section_by_name(name).unwrap().offset as usize
+}
+
+#[test]
+fn restamp_sha1() {
+  let mut elf = object("eh.so");
+  let linked = note_id(&elf);
+  let id = elf.restamp_build_id(BuildIdAlgorithm::Sha1).unwrap();
+  assert_eq!(id.len(), 20);
+  assert_ne!(id, linked);
+  assert_eq!(note_id(&elf), id);
+  //the descriptor is not part of the input
+  assert_eq!(elf.compute_build_id(BuildIdAlgorithm::Sha1), id);
+  assert_eq!(object("eh.so").compute_build_id(BuildIdAlgorithm::Sha1), id);
+}
+
+#[test]
+fn only_loaded_bytes_count() {
+  let mut elf = object("eh.so");
+  let id = elf.compute_build_id(BuildIdAlgorithm::Xxhash64);
+  assert_eq!(id.len(), 8);
+  let comment = section_offset(&elf, ".comment");
+  elf.data[comment] ^= 0xff;
+  assert_eq!(elf.compute_build_id(BuildIdAlgorithm::Xxhash64), id);
+  let text = section_offset(&elf, ".text");
+  elf.data[text] ^= 0xff;
+  assert_ne!(elf.compute_build_id(BuildIdAlgorithm::Xxhash64), id);
+}
+
+#[test]
+fn restamp_errors() {
+  //8 bytes of xxhash64 do not fill the 20 byte note
+  let mut elf = object("eh.so");
+  let linked = note_id(&elf);
+  assert_eq!(elf.restamp_build_id(BuildIdAlgorithm::Xxhash64).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+  assert_eq!(note_id(&elf), linked);
+  //struct.o has no note, its id comes from its SHF_ALLOC sections
+  let mut object = object("struct.o");
+  assert_eq!(object.restamp_build_id(BuildIdAlgorithm::Sha1).unwrap_err().kind(), std::io::ErrorKind::NotFound);
+  assert_eq!(object.compute_build_id(BuildIdAlgorithm::Sha1).len(), 20);
+}
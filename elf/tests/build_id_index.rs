@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+use std::time::{Duration, UNIX_EPOCH};
+use elf::*;
+
+fn entry(path: &str, build_id: &[u8]) -> BuildIdEntry {
+  BuildIdEntry {
+    build_id: build_id.to_vec(),
+    path: PathBuf::from(path),
+    size: 4096,
+    modified: Some(UNIX_EPOCH + Duration::new(1_700_000_000, 5)),
+    machine: EM_X86_64,
+    obj_type: ET_DYN,
+    soname: Some("libfixture.so".to_string()),
+    has_symbol_table: true,
+    has_debug_info: false,
+    debug_only: false,
+  }
+}
+
+fn paths(entries: Vec<&BuildIdEntry>) -> Vec<&str> {
+  let mut paths: Vec<&str> = entries.iter().map(|entry| entry.path.to_str().unwrap()).collect();
+  paths.sort_unstable();
+  paths
+}
+
+#[test]
+fn updates_keep_the_lookups_consistent() {
+  let mut index = BuildIdIndex::new();
+  index.insert(entry("/a", b"\x01"));
+  index.insert(entry("/b", b"\x02"));
+  index.insert(entry("/c", b"\x01"));
+  index.insert(entry("/d", b"\x03"));
+  assert_eq!(paths(index.lookup(b"\x01")), ["/a", "/c"]);
+  //a rebuilt file under the same path
+  index.insert(entry("/a", b"\x02"));
+  assert_eq!(index.len(), 4);
+  assert_eq!(paths(index.lookup(b"\x01")), ["/c"]);
+  assert_eq!(paths(index.lookup(b"\x02")), ["/a", "/b"]);
+  assert_eq!(index.remove_path("/b").unwrap().build_id, b"\x02");
+  assert!(index.remove_path("/b").is_none());
+  assert_eq!(paths(index.lookup(b"\x02")), ["/a"]);
+  assert_eq!(paths(index.lookup(b"\x03")), ["/d"]);
+  index.remove_path("/a");
+  assert!(index.lookup(b"\x02").is_empty());
+  assert_eq!(paths(index.lookup_hex("01")), ["/c"]);
+  assert_eq!(paths(index.lookup_hex("03")), ["/d"]);
+  assert_eq!(index.len(), 2);
+}
+
+#[test]
+fn round_trip() {
+  let mut index = BuildIdIndex::new();
+  index.insert(entry("/usr/lib/libfixture.so", b"\xde\xad\xbe\xef"));
+  let read = BuildIdIndex::from_bytes(&index.to_bytes()).unwrap();
+  assert_eq!(read.entries(), index.entries());
+  assert_eq!(read.executable(b"\xde\xad\xbe\xef").unwrap().path, PathBuf::from("/usr/lib/libfixture.so"));
+}
+
+#[test]
+fn invalid_times_are_errors() {
+  let mut index = BuildIdIndex::new();
+  index.insert(entry("/a", b"\x01"));
+  let mut data = index.to_bytes();
+  //the nanoseconds: magic, version and count, the id and the path with their lengths, the
+  //size, the flag and the seconds come before
+  let nanos = 8 + 4 + 8 + 4 + 1 + 4 + 2 + 8 + 1 + 8;
+  data[nanos..nanos + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+  assert_eq!(BuildIdIndex::from_bytes(&data).err().unwrap().kind(), std::io::ErrorKind::InvalidData);
+  data.truncate(nanos);
+  assert_eq!(BuildIdIndex::from_bytes(&data).err().unwrap().kind(), std::io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,58 @@
+use elf::*;
+
+const ADDRESS: u64 = 0x2000;
+
+//a "zR" CIE with pc-relative sdata4 pointers and `def_cfa rsp+8`, and one FDE for
+//0x1000..0x1020 running `instructions`
+fn eh_frame(instructions: &[u8]) -> Vec<u8> {
+  let mut data = Vec::new();
+  data.extend_from_slice(&16u32.to_le_bytes());
+  data.extend_from_slice(&0u32.to_le_bytes());
+  data.extend_from_slice(&[1, b'z', b'R', 0, 1, 0x78, 16, 1, DW_EH_PE_PCREL | DW_EH_PE_SDATA4, 0x0c, 7, 8]);
+  let fde = data.len();
+  data.extend_from_slice(&((4 + 4 + 4 + 1 + instructions.len()) as u32).to_le_bytes());
+  data.extend_from_slice(&((fde + 4) as u32).to_le_bytes());
+  let pc_begin = 0x1000 - (ADDRESS as i64 + data.len() as i64);
+  data.extend_from_slice(&(pc_begin as i32).to_le_bytes());
+  data.extend_from_slice(&0x20u32.to_le_bytes());
+  data.push(0);
+  data.extend_from_slice(instructions);
+  data.extend_from_slice(&0u32.to_le_bytes());
+  data
+}
+
+fn frames(data: &[u8]) -> Vec<FrameDescription> {
+  let information = CallFrameInformation {
+    data,
+    address: ADDRESS,
+    is_eh_frame: true,
+    big_endian: false,
+    address_size: 8,
+    bases: PointerBases::default(),
+  };
+  information.frame_descriptions()
+}
+
+#[test]
+fn set_loc_is_pc_relative() {
+  //DW_CFA_set_loc 0x1010, DW_CFA_def_cfa_offset 16, the set_loc operand sits at offset 38
+  let target = 0x1010 - (ADDRESS as i64 + 38);
+  let mut instructions = vec![0x01];
+  instructions.extend_from_slice(&(target as i32).to_le_bytes());
+  instructions.extend_from_slice(&[0x0e, 16]);
+  let frames = frames(&eh_frame(&instructions));
+  assert_eq!(frames.len(), 1);
+  assert_eq!((frames[0].address, frames[0].size), (0x1000, 0x20));
+  let rows: Vec<_> = frames[0].rows.iter().map(|row| (row.address, row.cfa.clone())).collect();
+  assert_eq!(rows, [(0x1000, CfaRule::RegisterOffset(7, 8)), (0x1010, CfaRule::RegisterOffset(7, 16))]);
+}
+
+#[test]
+fn overflowing_offsets_reject_the_frame() {
+  //DW_CFA_def_cfa_sf rsp, 1 << 62 times the data alignment -8
+  let instructions = [0x12, 7, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0xc0, 0x00];
+  assert!(frames(&eh_frame(&instructions)).is_empty());
+  //DW_CFA_offset_extended with an offset past i64::MAX
+  let instructions = [0x05, 16, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+  assert!(frames(&eh_frame(&instructions)).is_empty());
+}
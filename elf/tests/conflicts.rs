@@ -0,0 +1,38 @@
+use elf::*;
+
+fn path(name: &str) -> String {
+  format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn names(conflicts: &[SymbolConflict]) -> Vec<(&str, Option<usize>, Vec<usize>)> {
+  conflicts.iter().map(|conflict| (&*conflict.name, conflict.scope_root, conflict.definitions.iter().map(|definition| definition.object_index).collect())).collect()
+}
+
+//abi-user.so calls scale@ABI_1, abi-v1.so and abi-v2.so both define it
+#[test]
+fn global_scope() {
+  let mut workspace = Workspace::new();
+  for name in ["abi-user.so", "abi-v1.so", "abi-v2.so"] {
+    workspace.open_object(path(name), 0).unwrap();
+  }
+  workspace.resolve();
+  let conflicts = workspace.symbol_conflicts();
+  //legacy and shift are only in one of them, the linker defined symbols are expected
+  assert_eq!(names(&conflicts), [("origin", None, vec![1, 2]), ("scale", None, vec![1, 2]), ("counter", None, vec![1, 2])]);
+  let scale = &conflicts[1];
+  assert_eq!(scale.winner().version.as_deref(), Some("ABI_1"));
+  assert_eq!(scale.shadowed().len(), 1);
+  assert_eq!(scale.bound_from, [0]);
+}
+
+#[test]
+fn local_scope_of_a_plugin() {
+  let mut workspace = Workspace::new();
+  workspace.open_object(path("abi-v1.so"), 0).unwrap();
+  let plugin = workspace.dlopen(path("abi-v2.so"), RTLD_NOW | RTLD_LOCAL).unwrap();
+  let conflicts = workspace.symbol_conflicts();
+  //abi-v1.so is alone in the global scope, the scope of the plugin has both
+  assert!(conflicts.iter().all(|conflict| conflict.scope_root == Some(plugin)));
+  assert_eq!(conflicts.len(), 3);
+  assert!(conflicts.iter().all(|conflict| conflict.winner().object_index == 0));
+}
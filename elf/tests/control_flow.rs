@@ -0,0 +1,45 @@
+use elf::*;
+
+fn switch() -> Elf {
+  Elf::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/switch.so")).unwrap()
+}
+
+#[test]
+fn pic_switch_tables_are_recovered() {
+  let elf = switch();
+  let dispatch = elf.symbol_by_name("dispatch").unwrap();
+  let tables = elf.jump_tables();
+  assert_eq!(tables.len(), 1);
+  let table = &tables[0];
+  //cmp $0x7 and ja: eight cases, offsets from the table itself
+  assert!(table.bounded);
+  assert_eq!(table.entry_size, 4);
+  assert_eq!(table.entries, JumpTableEntries::Relative { base: table.table, shift: 0, signed: true });
+  assert_eq!(table.targets.len(), 8);
+  assert!(table.targets.iter().all(|&target| target >= dispatch.value && target < dispatch.value + dispatch.size));
+}
+
+#[test]
+fn switch_blocks_lead_to_every_case() {
+  let elf = switch();
+  let dispatch = elf.symbol_by_name("dispatch").unwrap();
+  let graph = elf.control_flow_graph(dispatch.value, dispatch.size).unwrap();
+  assert_eq!(graph.blocks[0].start, dispatch.value);
+  //the range check goes to dispatch.cold, outside the function
+  assert_eq!(graph.blocks[0].exit, BlockExit::Conditional);
+  let switch = graph.blocks.iter().find(|block| block.exit == BlockExit::Switch(0)).unwrap();
+  assert_eq!(switch.successors, graph.jump_tables[0].targets);
+  for &target in &switch.successors {
+    let case = graph.block_at(target).unwrap();
+    assert_eq!(case.start, target);
+    assert_eq!(case.exit, BlockExit::Return);
+    assert_eq!(graph.predecessors(target), vec![switch.start]);
+  }
+}
+
+#[test]
+fn other_machines_are_unsupported() {
+  let elf = Elf::new(fixture(FixtureSpec { class: ELFCLASS32, endianness: ELFDATA2MSB, obj_type: ET_EXEC, machine: EM_PPC }).into_boxed_slice());
+  let error = elf.control_flow_graph(elf.header.description.entry, 16).unwrap_err();
+  assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+}
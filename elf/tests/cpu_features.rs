@@ -0,0 +1,19 @@
+use elf::*;
+
+fn object() -> Elf {
+  Elf::new(include_bytes!("data/features-aarch64.o").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn big_endian_aarch64_code_is_little_endian() {
+  let requirements = object().cpu_requirements();
+  assert_eq!(requirements.used, [(CpuFeature::Crc32, 8), (CpuFeature::Lse, 0), (CpuFeature::Sve, 0x10)]);
+}
+
+#[test]
+fn section_ending_past_the_address_space_is_skipped() {
+  let mut elf = object();
+  let text = elf.section_headers.iter().position(|section| section.flags & SHF_EXECINSTR != 0).unwrap();
+  elf.section_headers[text].address = u64::MAX - 8;
+  assert!(elf.cpu_requirements().used.is_empty());
+}
@@ -0,0 +1,7 @@
+//gcc -O2 -fPIC -shared -nostdlib -Wl,-z,noseparate-code -o abi-user.so abi-user.c abi-v1.so
+//a user of scale@ABI_1 from abi-v1.so
+int scale(int value);
+
+int twice(int value) {
+  return scale(value);
+}
@@ -0,0 +1,32 @@
+//gcc -g -O0 -fPIC -shared -nostdlib -Wl,--version-script=abi.map -Wl,-soname,libabi.so.1 -Wl,-z,noseparate-code -o abi-v1.so abi.c
+//gcc -g -O0 -fPIC -shared -nostdlib -DV2 -Wl,--version-script=abi.map -Wl,-soname,libabi.so.1 -Wl,-z,noseparate-code -o abi-v2.so abi.c
+//version 2 changes the signature of scale, grows counter, removes legacy and adds shift
+typedef int length;
+
+struct point {
+  int x, y;
+};
+
+struct point origin;
+
+#ifdef V2
+long counter[2];
+
+length scale(length value, int factor) {
+  return value * factor;
+}
+
+int shift(int value) {
+  return value + 1;
+}
+#else
+long counter;
+
+length scale(length value) {
+  return value * 2;
+}
+
+int legacy(void) {
+  return 1;
+}
+#endif
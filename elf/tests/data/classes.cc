@@ -0,0 +1,28 @@
+//g++ -O1 -fPIC -shared -Wl,-z,noseparate-code -o classes.so classes.cc
+struct Shape {
+  virtual ~Shape();
+  virtual double area() const = 0;
+};
+
+struct Named {
+  virtual const char *name() const;
+  int id;
+};
+
+struct Square : Shape, Named {
+  double side;
+  double area() const override;
+  const char *name() const override;
+};
+
+struct Node : virtual Named {
+  Node *next;
+};
+
+Shape::~Shape() {}
+const char *Named::name() const { return "named"; }
+double Square::area() const { return side * side; }
+const char *Square::name() const { return "square"; }
+
+Square square;
+Node node;
@@ -0,0 +1,49 @@
+; llc -filetype=obj -accel-tables=Dwarf -o debug-names.o debug-names.ll
+; index.c as LLVM IR with its debug information, llc lists every name in .debug_names
+target triple = "x86_64-pc-linux-gnu"
+
+@counter = dso_local global i32 0, align 4, !dbg !0
+@MixedCase = dso_local global i32 0, align 4, !dbg !5
+
+define internal i32 @helper(i32 %x) !dbg !10 {
+  %r = mul i32 %x, 3, !dbg !20
+  ret i32 %r, !dbg !20
+}
+
+define dso_local i32 @compute(i32 %x) !dbg !14 {
+  %h = call i32 @helper(i32 %x), !dbg !21
+  %c = load i32, i32* @counter, align 4, !dbg !21
+  %m = load i32, i32* @MixedCase, align 4, !dbg !21
+  %s = add i32 %h, %c, !dbg !21
+  %r = add i32 %s, %m, !dbg !21
+  ret i32 %r, !dbg !21
+}
+
+define dso_local i32 @main() !dbg !15 {
+  %r = call i32 @compute(i32 2), !dbg !22
+  ret i32 %r, !dbg !22
+}
+
+!llvm.dbg.cu = !{!2}
+!llvm.module.flags = !{!7, !8}
+
+!0 = !DIGlobalVariableExpression(var: !1, expr: !DIExpression())
+!1 = distinct !DIGlobalVariable(name: "counter", scope: !2, file: !3, line: 3, type: !6, isLocal: false, isDefinition: true)
+!2 = distinct !DICompileUnit(language: DW_LANG_C99, file: !3, producer: "hand", isOptimized: true, runtimeVersion: 0, emissionKind: FullDebug, globals: !4, nameTableKind: Default)
+!3 = !DIFile(filename: "index.c", directory: "/work")
+!4 = !{!0, !5}
+!5 = !DIGlobalVariableExpression(var: !9, expr: !DIExpression())
+!6 = !DIBasicType(name: "int", size: 32, encoding: DW_ATE_signed)
+!7 = !{i32 7, !"Dwarf Version", i32 5}
+!8 = !{i32 2, !"Debug Info Version", i32 3}
+!9 = distinct !DIGlobalVariable(name: "MixedCase", scope: !2, file: !3, line: 4, type: !6, isLocal: false, isDefinition: true)
+!10 = distinct !DISubprogram(name: "helper", scope: !3, file: !3, line: 5, type: !11, scopeLine: 5, spFlags: DISPFlagLocalToUnit | DISPFlagDefinition | DISPFlagOptimized, unit: !2)
+!11 = !DISubroutineType(types: !12)
+!12 = !{!6, !6}
+!14 = distinct !DISubprogram(name: "compute", scope: !3, file: !3, line: 6, type: !11, scopeLine: 6, spFlags: DISPFlagDefinition | DISPFlagOptimized, unit: !2)
+!15 = distinct !DISubprogram(name: "main", scope: !3, file: !3, line: 7, type: !16, scopeLine: 7, spFlags: DISPFlagDefinition | DISPFlagOptimized, unit: !2)
+!16 = !DISubroutineType(types: !17)
+!17 = !{!6}
+!20 = !DILocation(line: 5, column: 28, scope: !10)
+!21 = !DILocation(line: 6, column: 29, scope: !14)
+!22 = !DILocation(line: 7, column: 25, scope: !15)
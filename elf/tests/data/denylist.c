@@ -0,0 +1,29 @@
+//gcc -O2 -fPIC -shared -Wl,-z,noseparate-code -o denylist.so denylist.c
+//gcc -O2 -fPIC -c -o denylist.o denylist.c
+//calls to denylisted libc functions, and a mktemp of its own
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+char *mktemp(char *template) {
+  return template;
+}
+
+int run(const char *command) {
+  return system(command) + 1;
+}
+
+void copy(char *to, const char *from) {
+  strcpy(to, from);
+  to[0] = 'x';
+}
+
+int parse(const char *text) {
+  int value = 0;
+  sscanf(text, "%d", &value);
+  return value;
+}
+
+char *temporary(char *template) {
+  return mktemp(template) + 1;
+}
@@ -0,0 +1,14 @@
+//llvm-mc -triple=aarch64_be -filetype=obj -o dlopen-aarch64.o dlopen-aarch64.s
+//a big-endian object calling a local dlopen twice, A64 code stays little-endian
+  .text
+  .type dlopen, %function
+dlopen:
+  ret
+  .size dlopen, . - dlopen
+
+  .globl load
+  .type load, %function
+load:
+  bl dlopen
+  b dlopen
+  .size load, . - load
@@ -0,0 +1,29 @@
+//g++ -O1 -fPIC -shared -s -Wl,-z,noseparate-code -o eh.so eh.cc
+struct Guard {
+  ~Guard();
+};
+
+void may_throw(int);
+
+int catches_int(int value) {
+  try {
+    may_throw(value);
+  } catch (int error) {
+    return error;
+  }
+  return 0;
+}
+
+int catches_anything(int value) {
+  try {
+    may_throw(value);
+  } catch (...) {
+    return -1;
+  }
+  return 0;
+}
+
+void cleans_up(int value) {
+  Guard guard;
+  may_throw(value);
+}
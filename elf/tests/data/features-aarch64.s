@@ -0,0 +1,17 @@
+//llvm-mc -triple=aarch64_be-linux-gnu -mattr=+lse,+crc,+sve -filetype=obj -o features-aarch64.o features-aarch64.s
+//a big-endian object using LSE, CRC32 and SVE, whose instructions are still little-endian
+  .globl add
+  .type add, %function
+add:
+  ldadd w1, w0, [x2]
+  ret
+  .globl checksum
+  .type checksum, %function
+checksum:
+  crc32w w0, w0, w1
+  ret
+  .globl clear
+  .type clear, %function
+clear:
+  mov z0.d, #0
+  ret
@@ -0,0 +1,21 @@
+//gcc -O2 -D_FORTIFY_SOURCE=2 -fPIC -shared -nostdlib -fstack-protector-strong -Wl,-z,now -Wl,-z,noseparate-code -o fortify.so fortify.c
+#include <string.h>
+#include <sys/select.h>
+
+//the size of the destination is known, so memcpy becomes __memcpy_chk
+int copy(const char *source, size_t length) {
+  char buffer[32];
+  memcpy(buffer, source, length);
+  return buffer[length / 2];
+}
+
+//the size of the destination is not known, so strcpy stays unprotected
+char *duplicate(char *destination, const char *source) {
+  return strcpy(destination, source);
+}
+
+//FD_SET checks the descriptor through __fdelt_chk
+int watch(fd_set *set, int descriptor) {
+  FD_SET(descriptor, set);
+  return FD_ISSET(descriptor + 1, set);
+}
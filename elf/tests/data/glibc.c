@@ -0,0 +1,20 @@
+//gcc -O2 -D_FORTIFY_SOURCE=2 -fPIC -shared -Wl,-z,pack-relative-relocs -Wl,-z,noseparate-code -o glibc.so glibc.c
+//imports from libc.so.6 at two glibc versions, one of them fortified, with DT_RELR
+#include <stdlib.h>
+#include <string.h>
+
+struct item {
+  char label[8];
+  void *values;
+};
+
+static const char *names[] = {"first", "second"};
+
+const char *name(int index) {
+  return names[index];
+}
+
+void grow(struct item *item, size_t count, const char *text) {
+  strcpy(item->label, text);
+  item->values = reallocarray(item->values, count, 16);
+}
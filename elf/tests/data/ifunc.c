@@ -0,0 +1,12 @@
+//gcc -O2 -fPIC -shared -nostdlib -Wl,-z,noseparate-code -o ifunc.so ifunc.c
+//two hidden ifuncs whose addresses are taken, so each pointer gets an R_X86_64_IRELATIVE
+static int slow(void) { return 1; }
+static int fast(void) { return 2; }
+
+static int (*resolve_compute(void))(void) { return slow; }
+static int (*resolve_measure(void))(void) { return fast; }
+
+__attribute__((visibility("hidden"), ifunc("resolve_compute"))) int compute(void);
+__attribute__((visibility("hidden"), ifunc("resolve_measure"))) int measure(void);
+
+int (*const functions[])(void) = { compute, measure };
@@ -0,0 +1,7 @@
+//gcc -g -ggnu-pubnames -O1 -fPIC -shared -nostdlib -fuse-ld=gold -Wl,--gdb-index -o gdb-index.so index.c
+//debug-names.ll is the same program, for llc to write a .debug_names index
+int counter;
+int MixedCase;
+static int helper(int x) { return x * 3; }
+int compute(int x) { return helper(x) + counter + MixedCase; }
+int main(void) { return compute(2); }
@@ -0,0 +1,6 @@
+//gcc -g -O2 -fPIC -shared -nostdlib -o inline.so inline.c
+static inline __attribute__((always_inline)) int square(int x) { return x * x; }
+static inline __attribute__((always_inline)) int sum_of_squares(int a, int b) { return square(a) + square(b); }
+int volatile sink;
+int norm(int a, int b) { return sum_of_squares(a, b); }
+int twice(int a) { sink = square(a); return square(a + 1); }
@@ -0,0 +1,20 @@
+//gcc -O2 -static -nostdlib -Wl,-z,noseparate-code -o static linkage.c
+//gcc -O2 -fPIE -static-pie -nostdlib -Wl,-z,noseparate-code -o static-pie linkage.c
+//gcc -O2 -static -nostdlib -DDLOPEN -Wl,-z,noseparate-code -o static-dlopen linkage.c
+//executables without a libc, the last one calling its own dlopen
+static volatile int result;
+
+#ifdef DLOPEN
+__attribute__((noinline)) void *dlopen(const char *file, int mode) {
+  result = mode;
+  return (void *)file;
+}
+#endif
+
+void _start(void) {
+#ifdef DLOPEN
+  dlopen("libplugin.so", 2);
+#endif
+  result = 1;
+  for (;;) {}
+}
@@ -0,0 +1,24 @@
+//gcc -c -o notes.o notes.c
+//annobin style notes: stack_prot 3 and FORTIFY 2 for 0x1000..0x1100 and stack_clash for the
+//same range, then a function at 0x1040..0x1050 built with stack_prot 1
+__asm__(
+  ".pushsection .gnu.build.attributes, \"\", %note\n"
+  ".balign 4\n"
+  ".long 6, 16, 0x100\n"
+  ".asciz \"GA*\\002\\003\"\n"
+  ".balign 4\n"
+  ".quad 0x1000, 0x1100\n"
+  ".long 13, 0, 0x100\n"
+  ".asciz \"GA*FORTIFY\\000\\002\"\n"
+  ".balign 4\n"
+  ".long 15, 0, 0x100\n"
+  ".asciz \"GA+stack_clash\"\n"
+  ".balign 4\n"
+  ".long 6, 16, 0x101\n"
+  ".asciz \"GA*\\002\\001\"\n"
+  ".balign 4\n"
+  ".quad 0x1040, 0x1050\n"
+  ".popsection\n"
+);
+
+static const char warning[] __attribute__((used, section(".gnu.warning.gets"))) = "the `gets' function is dangerous and should not be used.";
@@ -0,0 +1,4 @@
+//cd build && gcc -g -O0 -c -I../src/../include -fdebug-prefix-map=/tmp/p=/work -o paths.o ./../src/paths.c
+//with paths.h in include/ next to src/ and build/
+#include "paths.h"
+int four(void) { return twice(2); }
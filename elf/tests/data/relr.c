@@ -0,0 +1,9 @@
+//gcc -O2 -fPIC -shared -nostdlib -Wl,-z,pack-relative-relocs -Wl,-z,noseparate-code -o relr.so relr.c
+static int values[16];
+int *const pointers[] = {
+  &values[0], &values[1], &values[2], &values[3], &values[4], &values[5], &values[6], &values[7],
+  &values[8], &values[9], &values[10], &values[11], &values[12], &values[13], &values[14], &values[15],
+};
+int *const *first(void) {
+  return pointers;
+}
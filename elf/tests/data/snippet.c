@@ -0,0 +1,9 @@
+//gcc -O2 -fcommon -fno-asynchronous-unwind-tables -c snippet.c -o snippet.o
+int counter;
+const int table[4] = { 1, 2, 3, 5 };
+static const char *const names[] = { "zero", "one" };
+
+int pick(int which) {
+  counter++;
+  return table[which & 3] + names[which & 1][0];
+}
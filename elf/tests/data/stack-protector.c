@@ -0,0 +1,20 @@
+//gcc -c -o stack-protector.o stack-protector.c
+//annobin style notes: stack_prot 2 (-fstack-protector-all) for 0x1000..0x1100, 3
+//(-fstack-protector-strong) for 0x1100..0x1200 and 4 (-fstack-protector-explicit) for 0x1200..0x1300
+__asm__(
+  ".pushsection .gnu.build.attributes, \"\", %note\n"
+  ".balign 4\n"
+  ".long 6, 16, 0x100\n"
+  ".asciz \"GA*\\002\\002\"\n"
+  ".balign 4\n"
+  ".quad 0x1000, 0x1100\n"
+  ".long 6, 16, 0x100\n"
+  ".asciz \"GA*\\002\\003\"\n"
+  ".balign 4\n"
+  ".quad 0x1100, 0x1200\n"
+  ".long 6, 16, 0x100\n"
+  ".asciz \"GA*\\002\\004\"\n"
+  ".balign 4\n"
+  ".quad 0x1200, 0x1300\n"
+  ".popsection\n"
+);
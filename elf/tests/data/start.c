@@ -0,0 +1,5 @@
+//gcc -O2 -fPIE -pie -nostdlib -Wl,--dynamic-linker=/fortify.so -Wl,-z,noseparate-code -o start start.c
+//an executable whose PT_INTERP is fortify.so, found with tests/data as the sysroot
+void _start(void) {
+  for (;;) {}
+}
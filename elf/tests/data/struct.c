@@ -0,0 +1,4 @@
+//gcc -g -O0 -c -o struct.o struct.c
+struct S { char a; int b; };
+struct S s;
+int f(int x) { return x + s.b; }
@@ -0,0 +1,14 @@
+//gcc -O2 -fPIC -shared -nostdlib -Wl,-z,noseparate-code -o switch.so switch.c
+int dispatch(int value, int other) {
+  switch (value) {
+  case 0: return other * 3;
+  case 1: return other + 7;
+  case 2: return other ^ 0x55;
+  case 3: return other - 11;
+  case 4: return other << 2;
+  case 5: return other / 5;
+  case 6: return other % 13;
+  case 7: return ~other;
+  default: return 0;
+  }
+}
@@ -0,0 +1,6 @@
+//llvm-mc -triple=aarch64_be-linux-gnu -filetype=obj -o syscalls-aarch64.o syscalls-aarch64.s
+//a big-endian object, whose instructions are still little-endian
+  mov w8, #64
+  svc #0
+  mov x8, #93
+  svc #0
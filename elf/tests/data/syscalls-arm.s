@@ -0,0 +1,6 @@
+//llvm-mc -triple=armeb-linux-gnueabi -filetype=obj -o syscalls-arm.o syscalls-arm.s
+//a big-endian object without EF_ARM_BE8, its instructions are big-endian until linked
+  mov r7, #4
+  svc #0
+  mov r7, #248
+  svc #0
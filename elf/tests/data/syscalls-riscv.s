@@ -0,0 +1,5 @@
+//llvm-mc -triple=riscv64-linux-gnu -mattr=+c -filetype=obj -o syscalls-riscv.o syscalls-riscv.s
+  li a7, 64
+  ecall
+  c.li a7, 29
+  ecall
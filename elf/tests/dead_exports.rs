@@ -0,0 +1,45 @@
+use elf::*;
+
+fn path(name: &str) -> String {
+  format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+//abi-user.so binds scale@ABI_1 of abi-v1.so and exports twice, which nothing calls
+fn workspace() -> Workspace {
+  let mut workspace = Workspace::new();
+  for name in ["abi-user.so", "abi-v1.so"] {
+    workspace.open_object(path(name), 0).unwrap();
+  }
+  workspace.resolve();
+  workspace
+}
+
+#[test]
+fn exports_nothing_binds_to() {
+  let dead: Vec<_> = workspace().dead_exports().into_iter().map(|export| (export.object_index, export.name.to_string(), export.version)).collect();
+  //the linker defined symbols and the ABI_1 and ABI_2 version symbols are left out
+  assert_eq!(dead, [
+    (0, String::from("twice"), None),
+    (1, String::from("origin"), Some(String::from("ABI_1"))),
+    (1, String::from("counter"), Some(String::from("ABI_1"))),
+    (1, String::from("legacy"), Some(String::from("ABI_1"))),
+  ]);
+}
+
+#[test]
+fn version_scripts() {
+  let workspace = workspace();
+  assert_eq!(workspace.hiding_version_script(0), "{\n  global:\n    *;\n  local:\n    twice;\n};\n");
+  assert_eq!(workspace.hiding_version_script(1), "ABI_1 {\n  global:\n    scale;\n  local:\n    *;\n};\nABI_2 {\n} ABI_1;\n");
+}
+
+#[test]
+fn alone_everything_is_dead() {
+  let mut workspace = Workspace::new();
+  workspace.open_object(path("abi-v2.so"), 0).unwrap();
+  workspace.resolve();
+  let mut dead: Vec<_> = workspace.dead_exports().into_iter().map(|export| export.name.to_string()).collect();
+  dead.sort_unstable();
+  assert_eq!(dead, ["counter", "origin", "scale", "shift"]);
+  assert_eq!(workspace.hiding_version_script(0), "ABI_1 {\n  local:\n    *;\n};\nABI_2 {\n} ABI_1;\n");
+}
@@ -0,0 +1,172 @@
+use elf::*;
+
+//one unit, one range and "main" in a single slot symbol table
+fn gdb_index(version: u32) -> Vec<u8> {
+  let words = if version >= 9 { 7 } else { 6 };
+  let units = words * 4;
+  let types = units + 16;
+  let ranges = types;
+  let symbols = ranges + 20;
+  let shortcuts = symbols + 8;
+  let pool = if version >= 9 { shortcuts + 8 } else { shortcuts };
+  let mut data = Vec::new();
+  let mut header = vec![version, units, types, ranges, symbols];
+  if version >= 9 {
+    header.push(shortcuts);
+  }
+  header.push(pool);
+  for word in header {
+    data.extend_from_slice(&word.to_le_bytes());
+  }
+  data.extend_from_slice(&0u64.to_le_bytes());
+  data.extend_from_slice(&0x40u64.to_le_bytes());
+  data.extend_from_slice(&0x1000u64.to_le_bytes());
+  data.extend_from_slice(&0x1100u64.to_le_bytes());
+  data.extend_from_slice(&0u32.to_le_bytes());
+  //name at 8, the vector at 0
+  data.extend_from_slice(&8u32.to_le_bytes());
+  data.extend_from_slice(&0u32.to_le_bytes());
+  if version >= 9 {
+    //DW_LANG_C11
+    data.extend_from_slice(&0x1du32.to_le_bytes());
+    data.extend_from_slice(&8u32.to_le_bytes());
+  }
+  data.extend_from_slice(&1u32.to_le_bytes());
+  data.extend_from_slice(&(3u32 << 28).to_le_bytes());
+  data.extend_from_slice(b"main\0");
+  data
+}
+
+#[test]
+fn gdb_index_versions_8_and_9() {
+  for version in [8, 9] {
+    let data = gdb_index(version);
+    let index = GdbIndex::parse(&data).unwrap();
+    assert_eq!(index.compilation_units.len(), 1);
+    assert_eq!(index.unit_for_address(0x1080).map(|unit| unit.length), Some(0x40));
+    let entries = index.lookup("main");
+    assert_eq!(entries.len(), 1, "version {}", version);
+    assert_eq!(entries[0].kind, IndexedSymbolKind::Function);
+    assert_eq!(index.main_name, if version >= 9 { Some("main") } else { None });
+  }
+}
+
+#[test]
+fn gdb_index_rejects_unknown_versions() {
+  assert!(GdbIndex::parse(&gdb_index(10)).is_none());
+  assert!(GdbIndex::parse(&gdb_index(9)[..20]).is_none());
+}
+
+//tests/data/gdb-index.so: a version 7 index gold wrote for index.c, readelf shows its names in
+//slots 258 (MixedCase), 374 (helper), 489 (main), 743 (counter), 754 (int) and 894 (compute)
+fn gdb_index_library() -> Elf {
+  Elf::new(include_bytes!("data/gdb-index.so").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn gdb_index_written_by_a_linker() {
+  let elf = gdb_index_library();
+  let index = elf.gdb_index().unwrap();
+  assert_eq!((index.version, index.compilation_units.len()), (7, 1));
+  let names: Vec<&str> = index.names().iter().map(|(name, _)| *name).collect();
+  assert_eq!(names, ["MixedCase", "helper", "main", "counter", "int", "compute"]);
+  for (name, kind, is_static) in [
+    ("MixedCase", IndexedSymbolKind::Variable, false),
+    ("helper", IndexedSymbolKind::Function, true),
+    ("main", IndexedSymbolKind::Function, false),
+    ("counter", IndexedSymbolKind::Variable, false),
+    ("int", IndexedSymbolKind::Type, true),
+    ("compute", IndexedSymbolKind::Function, false),
+  ] {
+    let entries = index.lookup(name);
+    assert_eq!(entries.len(), 1, "{}", name);
+    assert_eq!((entries[0].unit_index, entries[0].kind, entries[0].is_static), (0, kind, is_static), "{}", name);
+  }
+  assert!(index.lookup("mixedcase").is_empty());
+  assert!(index.lookup("missing").is_empty());
+  assert_eq!(elf.lookup_indexed_name("compute").iter().map(|name| name.unit_offset).collect::<Vec<_>>(), [0]);
+}
+
+//the same table read as version 4, which hashes names without lowercasing them first
+#[test]
+fn gdb_index_hashes_names_by_version() {
+  let elf = gdb_index_library();
+  let offset = elf.section_by_name(".gdb_index").unwrap().offset as usize;
+  let mut data = elf.data.to_vec();
+  data[offset..offset + 4].copy_from_slice(&4u32.to_le_bytes());
+  let elf = Elf::new(data.into_boxed_slice());
+  let index = elf.gdb_index().unwrap();
+  assert_eq!(index.lookup("compute").len(), 1);
+  assert!(index.lookup("MixedCase").is_empty());
+}
+
+//tests/data/debug-names.o: the .debug_names llc wrote for index.c, six buckets with MixedCase,
+//main and compute in the same one
+#[test]
+fn debug_names_written_by_a_compiler() {
+  let elf = Elf::new(include_bytes!("data/debug-names.o").to_vec().into_boxed_slice());
+  let indexes = elf.debug_names();
+  assert_eq!(indexes.len(), 1);
+  assert_eq!(indexes[0].compilation_units, [0]);
+  for (name, die_offset, kind) in [
+    ("helper", 0x3d, IndexedSymbolKind::Function),
+    ("int", 0x2e, IndexedSymbolKind::Type),
+    ("MixedCase", 0x32, IndexedSymbolKind::Variable),
+    ("main", 0x5b, IndexedSymbolKind::Function),
+    ("compute", 0x4c, IndexedSymbolKind::Function),
+    ("counter", 0x23, IndexedSymbolKind::Variable),
+  ] {
+    let entries = indexes[0].lookup(name);
+    assert_eq!(entries.len(), 1, "{}", name);
+    assert_eq!((entries[0].die_offset, entries[0].kind()), (Some(die_offset), kind), "{}", name);
+    let found = elf.lookup_indexed_name(name);
+    assert_eq!(found.iter().map(|name| (name.unit_offset, name.die_offset)).collect::<Vec<_>>(), [(0, Some(die_offset))]);
+  }
+  //names hash lowercased, the strings still have to match
+  assert!(indexes[0].lookup("mixedcase").is_empty());
+  assert!(indexes[0].lookup("missing").is_empty());
+}
+
+//a .debug_names index of one unit with "main" and "inner", whose parent is "main"
+fn debug_names() -> Vec<u8> {
+  let abbreviations: &[u8] = &[
+    //1: DW_TAG_subprogram, DW_IDX_die_offset DW_FORM_ref4, DW_IDX_parent DW_FORM_flag_present
+    1, 0x2e, 3, 0x13, 4, 0x19, 0, 0,
+    //2: DW_TAG_subprogram, DW_IDX_die_offset DW_FORM_ref4, DW_IDX_parent DW_FORM_ref4
+    2, 0x2e, 3, 0x13, 4, 0x13, 0, 0,
+    0,
+  ];
+  let mut entries = vec![1];
+  entries.extend_from_slice(&0x20u32.to_le_bytes());
+  entries.push(0);
+  entries.push(2);
+  entries.extend_from_slice(&0x30u32.to_le_bytes());
+  entries.extend_from_slice(&0u32.to_le_bytes());
+  entries.push(0);
+  let mut data = Vec::new();
+  data.extend_from_slice(&5u16.to_le_bytes());
+  data.extend_from_slice(&0u16.to_le_bytes());
+  for word in [1, 0, 0, 0, 2, abbreviations.len() as u32, 0] {
+    data.extend_from_slice(&word.to_le_bytes());
+  }
+  //the unit, the string offsets, the entry offsets
+  for word in [0u32, 0, 5, 0, 6] {
+    data.extend_from_slice(&word.to_le_bytes());
+  }
+  data.extend_from_slice(abbreviations);
+  data.extend_from_slice(&entries);
+  let mut index = (data.len() as u32).to_le_bytes().to_vec();
+  index.extend_from_slice(&data);
+  index
+}
+
+#[test]
+fn debug_names_parents() {
+  let data = debug_names();
+  let indexes = DebugNames::parse_all(&data, b"main\0inner\0", false);
+  assert_eq!(indexes.len(), 1);
+  let main = indexes[0].lookup("main");
+  assert_eq!((main[0].die_offset, main[0].parent, main[0].unit_index), (Some(0x20), None, Some(0)));
+  let inner = indexes[0].lookup("inner");
+  assert_eq!((inner[0].die_offset, inner[0].parent), (Some(0x30), Some(0)));
+}
@@ -0,0 +1,55 @@
+use elf::*;
+
+fn object(name: &str) -> Elf {
+  Elf::new(std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().into_boxed_slice())
+}
+
+const DENYLIST: &[&str] = &["strcpy", "system", "sscanf", "mktemp"];
+
+//address, kind and caller of each call
+type Calls<'a> = Vec<(u64, CallKind, Option<&'a str>)>;
+
+fn summary(findings: &[DenylistFinding]) -> Vec<(&str, &str, bool, Calls<'_>)> {
+  findings.iter().map(|finding| {
+    let calls = finding.calls.iter().map(|call| (call.site.address, call.site.kind, call.caller.as_deref())).collect();
+    (finding.name.as_str(), &*finding.symbol, finding.imported, calls)
+  }).collect()
+}
+
+#[test]
+fn plt_entries() {
+  let elf = object("denylist.so");
+  let entries: Vec<_> = elf.plt_entries().iter().map(|entry| (entry.address, entry.name.to_string())).collect();
+  assert_eq!(entries, [
+    (0x590, String::from("strcpy")), (0x5a0, String::from("system")), (0x5b0, String::from("mktemp")),
+    (0x5c0, String::from("__isoc99_sscanf")), (0x5d0, String::from("__cxa_finalize")),
+  ]);
+}
+
+//tests/data/denylist.so calls each through its PLT stub, mktemp is its own but interposable
+#[test]
+fn linked_library() {
+  let findings = object("denylist.so").scan_denylist(DENYLIST);
+  assert_eq!(summary(&findings), [
+    ("strcpy", "strcpy", true, vec![(0x6d4, CallKind::Call, Some("copy"))]),
+    ("system", "system", true, vec![(0x6b4, CallKind::Call, Some("run"))]),
+    ("sscanf", "__isoc99_sscanf", true, vec![(0x70a, CallKind::Call, Some("parse"))]),
+    ("mktemp", "mktemp", false, vec![(0x724, CallKind::Call, Some("temporary"))]),
+  ]);
+  assert_eq!(findings[0].calls[0].site.target, 0x590);
+}
+
+//the same code before linking, the sites are the calls before its R_X86_64_PLT32 fields
+#[test]
+fn relocatable_object() {
+  let findings = object("denylist.o").scan_denylist(DENYLIST);
+  assert_eq!(summary(&findings), [
+    ("strcpy", "strcpy", true, vec![(0x34, CallKind::Call, Some("copy"))]),
+    ("system", "system", true, vec![(0x14, CallKind::Call, Some("run"))]),
+    ("sscanf", "__isoc99_sscanf", true, vec![(0x6a, CallKind::Call, Some("parse"))]),
+    ("mktemp", "mktemp", false, vec![(0x84, CallKind::Call, Some("temporary"))]),
+  ]);
+  //none of DEFAULT_DENYLIST but these three is used
+  let names: Vec<_> = object("denylist.o").scan_denylist(DEFAULT_DENYLIST).iter().map(|finding| finding.name.clone()).collect();
+  assert_eq!(names, ["strcpy", "system", "mktemp"]);
+}
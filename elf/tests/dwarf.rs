@@ -0,0 +1,127 @@
+use elf::*;
+
+fn object() -> Elf {
+  Elf::new(include_bytes!("data/struct.o").to_vec().into_boxed_slice())
+}
+
+//version 4 line program header with no directories and a single file, followed by `program`
+fn line_program(program: &[u8]) -> Vec<u8> {
+  let mut header = vec![1, 1, 1, 0xfb, 14, 13, 0, 1, 1, 1, 1, 0, 0, 0, 1, 0, 0, 1, 0];
+  header.extend_from_slice(b"t.c\0\0\0\0\0");
+  let mut data = Vec::new();
+  data.extend_from_slice(&((2 + 4 + header.len() + program.len()) as u32).to_le_bytes());
+  data.extend_from_slice(&4u16.to_le_bytes());
+  data.extend_from_slice(&(header.len() as u32).to_le_bytes());
+  data.extend_from_slice(&header);
+  data.extend_from_slice(program);
+  data
+}
+
+fn dwarf(debug_line: &[u8]) -> Dwarf<'_> {
+  Dwarf {
+    big_endian: false,
+    debug_info: &[],
+    debug_abbrev: &[],
+    debug_str: &[],
+    debug_line,
+    debug_line_str: &[],
+    debug_addr: &[],
+    debug_str_offsets: &[],
+    debug_ranges: &[],
+    debug_rnglists: &[],
+  }
+}
+
+#[test]
+fn relocatable_object_strings_are_relocated() {
+  let elf = object();
+  let dwarf = elf.dwarf();
+  let units = dwarf.units();
+  assert_eq!(units.len(), 1);
+  assert_eq!(units[0].name().as_deref(), Some("struct.c"));
+  let program = dwarf.line_program(&units[0]).unwrap();
+  assert!(program.files.iter().any(|file| file.name == "struct.c"));
+  let functions: Vec<_> = dwarf.functions().into_iter().map(|function| function.name).collect();
+  assert_eq!(functions, ["f"]);
+}
+
+#[test]
+fn line_program_rows() {
+  //DW_LNE_set_address 0x1000, copy, special opcode (+1 line, +1 address), DW_LNE_end_sequence
+  let mut program = vec![0, 9, 2];
+  program.extend_from_slice(&0x1000u64.to_le_bytes());
+  program.extend_from_slice(&[1, 13 + 6 + 14, 0, 1, 1]);
+  let data = line_program(&program);
+  let rows = dwarf(&data).line_program_at(0, 8).unwrap().rows;
+  let rows: Vec<_> = rows.iter().map(|row| (row.address, row.line)).collect();
+  assert_eq!(rows, [(0x1000, 1), (0x1001, 2), (0x1001, 2)]);
+}
+
+#[test]
+fn malformed_line_programs_are_rejected() {
+  //zero length extended opcode
+  let data = line_program(&[0, 0, 1]);
+  assert!(dwarf(&data).line_program_at(0, 8).is_none());
+  //DW_LNS_advance_line below line 0
+  let data = line_program(&[3, 0x7e, 1]);
+  assert!(dwarf(&data).line_program_at(0, 8).is_none());
+  //DW_LNE_set_address with a 4 byte operand in a unit of 8 byte addresses
+  let data = line_program(&[0, 5, 2, 0, 0x10, 0, 0, 1]);
+  assert!(dwarf(&data).line_program_at(0, 8).is_none());
+  assert_eq!(dwarf(&data).line_program_at(0, 4).unwrap().rows[0].address, 0x1000);
+  //header length past the end of the section
+  let mut data = line_program(&[1]);
+  data[6..10].copy_from_slice(&u32::MAX.to_le_bytes());
+  assert!(dwarf(&data).line_program_at(0, 8).is_none());
+}
+
+//A DWARF 4 unit with typedefs A and B naming each other, and a struct S holding an array of
+//int whose size overflows and a member whose location in bits does
+fn cyclic_types() -> Elf {
+  let abbrev = [
+    1, 0x11, 1, 0, 0,
+    2, 0x16, 0, 0x03, 0x08, 0x49, 0x13, 0, 0,
+    3, 0x13, 1, 0x03, 0x08, 0x0b, 0x07, 0, 0,
+    4, 0x0d, 0, 0x03, 0x08, 0x49, 0x13, 0x38, 0x07, 0, 0,
+    5, 0x01, 1, 0x49, 0x13, 0, 0,
+    6, 0x21, 0, 0x37, 0x07, 0, 0,
+    7, 0x24, 0, 0x03, 0x08, 0x0b, 0x0b, 0, 0,
+    0,
+  ];
+  let mut info = vec![0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 8];
+  info.push(1);
+  info.extend_from_slice(b"\x02A\0\x13\0\0\0");
+  info.extend_from_slice(b"\x02B\0\x0c\0\0\0");
+  info.extend_from_slice(b"\x07int\0\x04");
+  info.extend_from_slice(b"\x05\x1a\0\0\0");
+  info.push(6);
+  info.extend_from_slice(&(u64::MAX / 2).to_le_bytes());
+  info.push(0);
+  info.extend_from_slice(b"\x03S\0");
+  info.extend_from_slice(&16u64.to_le_bytes());
+  info.extend_from_slice(b"\x04x\0\x20\0\0\0");
+  info.extend_from_slice(&0u64.to_le_bytes());
+  info.extend_from_slice(b"\x04y\0\x1a\0\0\0");
+  info.extend_from_slice(&(u64::MAX / 4).to_le_bytes());
+  info.extend_from_slice(&[0, 0]);
+  let length = info.len() as u32 - 4;
+  info[..4].copy_from_slice(&length.to_le_bytes());
+  let mut elf = Elf::new(fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_REL, machine: EM_X86_64 }).into_boxed_slice());
+  elf.add_section(".debug_abbrev", SHT_PROGBITS, 0, 1, &abbrev).unwrap();
+  elf.add_section(".debug_info", SHT_PROGBITS, 0, 1, &info).unwrap();
+  elf
+}
+
+#[test]
+fn typedef_cycle_is_not_followed_forever() {
+  let elf = cyclic_types();
+  assert_eq!(elf.type_layout("A").unwrap_err().kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn overflowing_sizes_and_locations_are_dropped() {
+  let layout = cyclic_types().type_layout("S").unwrap();
+  let members: Vec<_> = layout.members.iter().map(|member| (member.name.as_deref(), member.size)).collect();
+  assert_eq!(members, [(Some("x"), 0)]);
+  assert_eq!(layout.padding_bits, 128);
+}
@@ -0,0 +1,67 @@
+use byteorder::{ByteOrder, LittleEndian};
+use elf::*;
+
+fn library() -> Vec<u8> {
+  include_bytes!("data/eh.so").to_vec()
+}
+
+//retags DT_STRSZ so that the string table size has to come from the file
+fn without_strsz(data: &mut [u8]) {
+  let elf = Elf::new(data.to_vec().into_boxed_slice());
+  let section = elf.section_headers.iter().find(|section| section.section_type == SHT_DYNAMIC).unwrap();
+  for offset in (section.offset as usize..(section.offset + section.size) as usize).step_by(16) {
+    if LittleEndian::read_u64(&data[offset..]) == DT_STRSZ {
+      LittleEndian::write_u64(&mut data[offset..], DT_DEBUG);
+    }
+  }
+}
+
+#[test]
+fn dynamic_strings_without_strsz() {
+  let elf = Elf::new(library().into_boxed_slice());
+  let needed = elf.dynamic_value(DT_NEEDED).unwrap();
+  let name = elf.dynamic_string(needed).unwrap().to_string();
+  assert!(name.starts_with("lib"));
+  let mut data = library();
+  without_strsz(&mut data);
+  let elf = Elf::new(data.into_boxed_slice());
+  assert_eq!(elf.dynamic_value(DT_STRSZ), None);
+  assert_eq!(elf.dynamic_string(needed), Some(name.as_str()));
+}
+
+#[test]
+fn string_table_past_the_end_of_the_file() {
+  let mut data = library();
+  without_strsz(&mut data);
+  let mut elf = Elf::new(data.into_boxed_slice());
+  for ph in elf.program_headers.iter_mut().filter(|ph| ph.entry_type == PT_LOAD) {
+    ph.offset = 1 << 40;
+  }
+  assert_eq!(elf.dynamic_string(1), None);
+}
+
+fn object(name: &str) -> Elf {
+  Elf::new(std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().into_boxed_slice())
+}
+
+//fortify.so is linked with -z now, start is a PIE
+#[test]
+fn dynamic_flags() {
+  let fortify = object("fortify.so").dynamic_flags();
+  assert_eq!((fortify.flags, fortify.flags_1), (DF_BIND_NOW, DF_1_NOW));
+  assert!(fortify.bind_now() && !fortify.origin());
+  assert_eq!(fortify.to_string(), "FLAGS BIND_NOW FLAGS_1 NOW");
+  let start = object("start");
+  assert_eq!(start.dynamic_flags().flag_1_names(), ["PIE"]);
+  assert!(start.security_report().pie);
+  assert!(object("eh.so").dynamic_flags().is_empty());
+  assert_eq!(object("eh.so").dynamic_flags().to_string(), "none");
+}
+
+#[test]
+fn unnamed_flag_bits() {
+  let entries = [DynamicEntry { tag: DT_FLAGS_1, value: DF_1_NODELETE | 1 << 40 }, DynamicEntry { tag: DT_FLAGS_1, value: DF_1_ORIGIN }];
+  let flags = DynamicFlags::from_entries(&entries);
+  assert_eq!(flags.flag_1_names(), ["NODELETE", "ORIGIN", "0x10000000000"]);
+  assert!(flags.origin());
+}
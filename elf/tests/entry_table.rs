@@ -0,0 +1,86 @@
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn strides() {
+  let data = [0u8; 20];
+  let table = EntryTable::new(&data, 8, 6);
+  assert_eq!((table.stride(), table.len()), (8, 2));
+  //the last entry is not padded
+  assert_eq!(table.get(1).map(<[u8]>::len), Some(8));
+  assert_eq!(table.get(2), None);
+  //sh_entsize 0 and sizes past the data use the decoder size
+  assert_eq!(EntryTable::new(&data, 0, 6).stride(), 6);
+  assert_eq!(EntryTable::new(&data, 21, 6).stride(), 6);
+  assert!(EntryTable::new(&data[..5], 8, 6).is_empty());
+}
+
+//the section header table of eh.so copied to the end of the file with 8 bytes after each entry
+#[test]
+fn padded_section_headers() {
+  let elf = library();
+  let offset = elf.header.description.section_hdr_offset as usize;
+  let mut data = elf.data.to_vec();
+  let table_offset = data.len();
+  for index in 0..elf.section_headers.len() {
+    data.extend_from_slice(&elf.data[offset + index * 64..offset + (index + 1) * 64]);
+    data.extend_from_slice(&[0xff; 8]);
+  }
+  let mut padded = Elf::new(data.into_boxed_slice());
+  padded.header.description.section_hdr_offset = table_offset as u64;
+  padded.header.description.section_hdr_entry_size = 72;
+  padded.write_header();
+  let padded = Elf::new(padded.data);
+  assert_eq!(padded.section_hdr_stride(), 72);
+  assert!(padded.validate_entry_sizes().is_ok());
+  let names = |elf: &Elf| -> Vec<String> { elf.section_headers.iter().map(|section| elf.section_name(section).unwrap_or("").to_string()).collect() };
+  assert_eq!(names(&padded), names(&elf));
+  assert_eq!(padded.section_headers.last().unwrap().offset, elf.section_headers.last().unwrap().offset);
+}
+
+#[test]
+fn entry_sizes_smaller_than_the_class() {
+  let mut elf = library();
+  assert!(elf.set_program_hdr_entry_size(48).is_err());
+  elf.set_program_hdr_entry_size(64).unwrap();
+  assert_eq!(elf.program_hdr_stride(), 64);
+  elf.header.description.section_hdr_entry_size = 40;
+  assert!(elf.validate_entry_sizes().is_err());
+  assert_eq!(elf.section_hdr_stride(), 64);
+}
+
+//a copy of the section at the end of the file, 8 bytes of padding after every entry
+fn pad_entries(elf: &mut Elf, name: &str) {
+  let index = elf.section_index_by_name(name).unwrap();
+  let section = &elf.section_headers[index];
+  let mut data = elf.data.to_vec();
+  let offset = data.len() as u64;
+  let mut size = 0;
+  for entry in elf.section_data(section).chunks_exact(section.entry_size as usize) {
+    data.extend_from_slice(entry);
+    data.extend_from_slice(&[0xff; 8]);
+    size += section.entry_size + 8;
+  }
+  elf.data = data.into_boxed_slice();
+  let section = &mut elf.section_headers[index];
+  section.offset = offset;
+  section.size = size;
+  section.entry_size += 8;
+}
+
+#[test]
+fn padded_symbols_and_relocations() {
+  let elf = library();
+  let mut padded = library();
+  pad_entries(&mut padded, ".dynsym");
+  pad_entries(&mut padded, ".rela.dyn");
+  let names = |elf: &Elf| -> Vec<String> { elf.dynamic_symbols().iter().map(|symbol| symbol.name.to_string()).collect() };
+  assert_eq!(names(&padded), names(&elf));
+  let relocations = |elf: &Elf| -> Vec<(u64, u32, Option<i64>)> {
+    elf.relocations().iter().map(|relocation| (relocation.offset, relocation.relocation_type, relocation.addend)).collect()
+  };
+  assert_eq!(relocations(&padded), relocations(&elf));
+}
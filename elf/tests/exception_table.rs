@@ -0,0 +1,73 @@
+use elf::*;
+
+fn eh() -> Elf {
+  Elf::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/eh.so")).unwrap()
+}
+
+fn table<'a>(elf: &Elf, tables: &'a [ExceptionTable], function: &str) -> &'a ExceptionTable {
+  let symbol = elf.symbol_by_name(function).unwrap();
+  let table = tables.iter().find(|table| table.function == symbol.value).unwrap();
+  assert_eq!(table.function_size, symbol.size);
+  table
+}
+
+#[test]
+fn catch_clauses_name_their_typeinfo() {
+  let elf = eh();
+  let tables = elf.exception_tables();
+  assert_eq!(tables.len(), 3);
+  let catches_int = table(&elf, &tables, "_Z11catches_inti");
+  let handlers: Vec<&TryRange> = catches_int.handlers().collect();
+  assert_eq!(handlers.len(), 1);
+  match &handlers[0].actions[..] {
+    [ExceptionAction::Catch(Some(typeinfo))] => assert_eq!(typeinfo.name.as_deref(), Some("_ZTIi")),
+    actions => panic!("{:?}", actions),
+  }
+  let landing_pad = handlers[0].landing_pad.unwrap();
+  assert!(landing_pad >= catches_int.function && landing_pad < catches_int.function + catches_int.function_size);
+  assert_eq!(catches_int.range_at(handlers[0].start), Some(handlers[0]));
+  assert_eq!(table(&elf, &tables, "_Z16catches_anythingi").handlers().next().unwrap().actions, vec![ExceptionAction::Catch(None)]);
+}
+
+#[test]
+fn destructors_are_cleanups() {
+  let elf = eh();
+  let tables = elf.exception_tables();
+  let cleans_up = table(&elf, &tables, "_Z9cleans_upi");
+  assert_eq!(cleans_up.handlers().count(), 0);
+  let cleanup = cleans_up.ranges.iter().find(|range| range.landing_pad.is_some()).unwrap();
+  assert_eq!(cleanup.actions, vec![ExceptionAction::Cleanup]);
+  //the call in the landing pad, which _Unwind_Resume leaves through
+  assert!(cleans_up.ranges.iter().any(|range| range.landing_pad.is_none() && range.actions.is_empty()));
+}
+
+#[test]
+fn relocatable_objects_have_no_tables() {
+  let elf = Elf::new(fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_REL, machine: EM_X86_64 }).into_boxed_slice());
+  assert!(elf.exception_tables().is_empty());
+}
+
+//eh.so with the first LSDA in .gcc_except_table replaced by `lsda`
+fn with_first_lsda(lsda: &[u8]) -> (Elf, u64) {
+  let elf = eh();
+  let first = elf.exception_tables().into_iter().min_by_key(|table| table.address).unwrap();
+  let offset = elf.address_to_offset(first.address).unwrap() as usize;
+  let mut data = elf.data.to_vec();
+  data[offset..offset + lsda.len()].copy_from_slice(lsda);
+  (Elf::new(data.into_boxed_slice()), first.function)
+}
+
+#[test]
+fn overflowing_offsets_drop_the_table() {
+  let huge = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+  //a type table offset, a call-site table length and a filter of i64::MIN
+  let type_offset = [&[0xff, 0x9b][..], &huge].concat();
+  let call_site_length = [&[0xff, 0xff, 0x01][..], &huge].concat();
+  let filter = [&[0xff, 0x00, 0x00, 0x01, 0x04, 0x00, 0x01, 0x01, 0x01][..], &[0x80; 9], &[0x7f, 0x00]].concat();
+  for lsda in &[type_offset, call_site_length, filter] {
+    let (elf, function) = with_first_lsda(lsda);
+    let tables = elf.exception_tables();
+    assert_eq!(tables.len(), 2);
+    assert!(tables.iter().all(|table| table.function != function));
+  }
+}
@@ -0,0 +1,104 @@
+use std::io::ErrorKind;
+use std::process::Command;
+use byteorder::{ByteOrder, LittleEndian};
+use elf::*;
+
+fn snippet_data() -> Vec<u8> {
+  std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/snippet.o")).unwrap()
+}
+
+fn strict(data: Vec<u8>) -> Elf {
+  let (elf, unknown) = Elf::parse(data.into_boxed_slice(), &ParseOptions { unknown_values: UnknownValuePolicy::Error }).unwrap();
+  assert!(unknown.is_empty());
+  elf
+}
+
+//snippet.o with the st_value (8) or st_size (16) of the .symtab entry `name` replaced
+fn with_symbol_field(name: &str, field: u64, value: u64) -> Elf {
+  let elf = strict(snippet_data());
+  let symtab = elf.section_by_name(".symtab").unwrap();
+  let index = elf.symbol_indices_by_name(name)[0];
+  let field = (symtab.offset + index as u64 * symtab.entry_size + field) as usize;
+  let mut data = snippet_data();
+  LittleEndian::write_u64(&mut data[field..field + 8], value);
+  strict(data)
+}
+
+fn with_symbol_size(name: &str, size: u64) -> Elf {
+  with_symbol_field(name, 16, size)
+}
+
+//snippet.o with sh_addralign of the section `name` replaced
+fn with_section_align(name: &str, align: u64) -> Elf {
+  let elf = strict(snippet_data());
+  let index = elf.section_headers.iter().position(|section| elf.section_name(section) == Some(name)).unwrap();
+  let field = elf.header.description.section_hdr_offset as usize + index * 64 + 48;
+  let mut data = snippet_data();
+  LittleEndian::write_u64(&mut data[field..field + 8], align);
+  strict(data)
+}
+
+#[test]
+fn extracted_object_keeps_the_data_and_its_entry_size() {
+  let snippet = strict(snippet_data()).extract_function("pick").unwrap();
+  assert_eq!(snippet.relocations.len(), 3);
+  let object = strict(snippet.to_object().unwrap());
+  assert_eq!(object.header.description.obj_type, ET_REL);
+  let strings = object.section_headers.iter().find(|section| section.flags & SHF_STRINGS != 0).unwrap();
+  assert_eq!((object.section_name(strings), strings.flags & SHF_MERGE, strings.entry_size), (Some(".rodata.str1.1"), SHF_MERGE, 1));
+  assert_eq!(object.section_data(strings), b"zero\0one\0");
+  let pick = object.symbol_by_name("pick").unwrap();
+  assert_eq!((pick.size, pick.binding), (40, STB_GLOBAL));
+  let common = object.section_by_name(".bss.counter").unwrap();
+  assert_eq!((common.section_type, common.size), (SHT_NOBITS, 4));
+
+  let path = std::env::temp_dir().join(format!("walker-snippet-{}.o", std::process::id()));
+  std::fs::write(&path, &object.data).unwrap();
+  let readelf = Command::new("readelf").args(["-a", "-W"]).arg(&path).output();
+  std::fs::remove_file(&path).unwrap();
+  match readelf {
+    Ok(output) => assert_eq!(String::from_utf8_lossy(&output.stderr), ""),
+    Err(error) => assert_eq!(error.kind(), ErrorKind::NotFound),
+  }
+}
+
+#[test]
+fn huge_common_symbol_is_not_zero_filled() {
+  let snippet = with_symbol_size("counter", 1 << 40).extract_function("pick").unwrap();
+  let counter = snippet.data.iter().find(|data| &*data.name == "counter").unwrap();
+  assert_eq!((counter.size, counter.bytes.len()), (1 << 40, 0));
+  let object = strict(snippet.to_object().unwrap());
+  assert_eq!(object.section_by_name(".bss.counter").unwrap().size, 1 << 40);
+  assert!(object.data.len() < 0x1000);
+}
+
+#[test]
+fn symbol_past_its_section_is_rejected() {
+  let error = with_symbol_size("table", 1 << 40).extract_function("pick").unwrap_err();
+  assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn hostile_alignments_are_rejected() {
+  //the file is 1576 bytes long
+  for (section, align) in [(".rodata", 24), (".rodata", 1 << 40), (".text", 3), (".text", 1 << 40), (".data.rel.ro.local", 4096)] {
+    let error = with_section_align(section, align).extract_function("pick").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData, "{} aligned to {:#x}", section, align);
+  }
+  //the value of a common symbol is its alignment
+  let error = with_symbol_field("counter", 8, 1 << 40).extract_function("pick").unwrap_err();
+  assert_eq!(error.kind(), ErrorKind::InvalidData);
+  let snippet = with_section_align(".rodata", 1024).extract_function("pick").unwrap();
+  let object = strict(snippet.to_object().unwrap());
+  assert_eq!(object.section_by_name(".rodata.table").unwrap().offset % 1024, 0);
+}
+
+#[test]
+fn changed_alignments_are_not_written() {
+  let mut snippet = strict(snippet_data()).extract_function("pick").unwrap();
+  snippet.data[0].align = 1 << 40;
+  assert_eq!(snippet.to_object().unwrap_err().kind(), ErrorKind::InvalidData);
+  snippet.data[0].align = 16;
+  snippet.align = 12;
+  assert_eq!(snippet.to_object().unwrap_err().kind(), ErrorKind::InvalidData);
+}
@@ -0,0 +1,39 @@
+use std::io::ErrorKind;
+use std::process::Command;
+use elf::*;
+
+//stderr of `tool args file`, None when the tool is not installed
+fn complaints(tool: &str, args: &[&str], path: &std::path::Path) -> Option<String> {
+  match Command::new(tool).args(args).arg(path).output() {
+    Ok(output) => Some(String::from_utf8_lossy(&output.stderr).into_owned()),
+    Err(error) if error.kind() == ErrorKind::NotFound => None,
+    Err(error) => panic!("{}: {}", tool, error),
+  }
+}
+
+#[test]
+fn binutils_accept_every_fixture() {
+  let directory = std::env::temp_dir().join(format!("walker-fixtures-{}", std::process::id()));
+  std::fs::create_dir_all(&directory).unwrap();
+  for (spec, data) in fixture_matrix() {
+    let path = directory.join(format!("{}-{}-{}-{}", spec.class, spec.endianness, spec.obj_type, spec.machine));
+    std::fs::write(&path, data).unwrap();
+    for (tool, args) in &[("readelf", &["-a", "-W"][..]), ("objdump", &["-x"][..])] {
+      if let Some(complaints) = complaints(tool, args, &path) {
+        assert_eq!(complaints, "", "{} {:?}", tool, spec);
+      }
+    }
+  }
+  std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn fixtures_parse_strictly() {
+  for (spec, data) in fixture_matrix() {
+    let (elf, unknown) = Elf::parse(data.into_boxed_slice(), &ParseOptions { unknown_values: UnknownValuePolicy::Error }).unwrap();
+    assert!(unknown.is_empty());
+    assert_eq!((elf.header.identification.class, elf.header.identification.endianness), (spec.class, spec.endianness));
+    assert_eq!((elf.header.description.obj_type, elf.header.description.machine), (spec.obj_type, spec.machine));
+    assert_eq!(elf.section_headers.is_empty(), spec.obj_type == ET_CORE);
+  }
+}
@@ -0,0 +1,56 @@
+use elf::fuzz::{parse_oracle, OracleOutcome};
+
+//xorshift, the corpus has to be the same on every run
+struct Mutations(u64);
+
+impl Mutations {
+  fn next(&mut self) -> u64 {
+    self.0 ^= self.0 << 13;
+    self.0 ^= self.0 >> 7;
+    self.0 ^= self.0 << 17;
+    self.0
+  }
+}
+
+fn corpus() -> Vec<Vec<u8>> {
+  ["eh.so", "fortify.so", "relr.so", "start", "struct.o", "notes.o", "syscalls-aarch64.o", "syscalls-arm.o", "syscalls-riscv.o"].iter()
+    .map(|name| std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap())
+    .collect()
+}
+
+#[test]
+fn valid_files_pass_both_parsers() {
+  for data in corpus() {
+    assert_eq!(parse_oracle(&data), OracleOutcome { strict: true, lenient: true });
+  }
+}
+
+#[test]
+fn strict_rejects_what_lenient_keeps() {
+  let data = corpus().remove(0);
+  //a reserved e_machine
+  let mut unknown = data.clone();
+  unknown[18] = 11;
+  unknown[19] = 0;
+  assert_eq!(parse_oracle(&unknown), OracleOutcome { strict: false, lenient: true });
+  //the section header table cut off
+  let truncated = &data[..data.len() - 1];
+  assert_eq!(parse_oracle(truncated), OracleOutcome { strict: false, lenient: true });
+  assert_eq!(parse_oracle(&data[..3]), OracleOutcome { strict: false, lenient: false });
+}
+
+#[test]
+fn mutated_corpus() {
+  let mut mutations = Mutations(0x9e37_79b9_7f4a_7c15);
+  let inputs = corpus();
+  for round in 0..2000 {
+    let mut data = inputs[round % inputs.len()].clone();
+    for _ in 0..1 + mutations.next() % 4 {
+      let position = (mutations.next() % data.len() as u64) as usize;
+      data[position] = mutations.next() as u8;
+    }
+    parse_oracle(&data);
+    let length = (mutations.next() % data.len() as u64) as usize;
+    parse_oracle(&data[..length]);
+  }
+}
@@ -0,0 +1,119 @@
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn set_class_updates_header_sizes() {
+  let mut elf = library();
+  elf.set_class(ELFCLASS32).unwrap();
+  let description = &elf.header.description;
+  assert_eq!((description.elf_hdr_size, description.program_hdr_entry_size, description.section_hdr_entry_size), (52, 32, 40));
+}
+
+#[test]
+fn repair_restores_counts() {
+  let mut elf = library();
+  let (program_hdr_num, section_hdr_num) = (elf.header.description.program_hdr_num, elf.header.description.section_hdr_num);
+  elf.header.description.program_hdr_num = 0;
+  elf.header.description.section_hdr_num = 1;
+  elf.write_header();
+  let fixes = elf.repair();
+  assert!(fixes.iter().any(|fix| fix.field == "e_phnum"));
+  assert_eq!((elf.header.description.program_hdr_num, elf.header.description.section_hdr_num), (program_hdr_num, section_hdr_num));
+}
+
+#[test]
+fn repair_survives_offsets_past_the_address_space() {
+  let mut elf = library();
+  elf.header.description.section_hdr_offset = u64::MAX - 8;
+  elf.header.description.program_hdr_offset = u64::MAX - 8;
+  elf.repair();
+  assert!(!elf.section_headers.is_empty());
+  assert_eq!(elf.header.description.program_hdr_offset, 64);
+}
+
+#[test]
+fn repair_keeps_a_table_with_extended_numbering() {
+  let mut elf = library();
+  let (offset, count) = (elf.header.description.section_hdr_offset as usize, elf.section_headers.len());
+  //sh_size of entry 0
+  elf.data[offset + 32..offset + 40].copy_from_slice(&(count as u64).to_le_bytes());
+  elf.header.description.section_hdr_num = 0;
+  elf.write_header();
+  let mut elf = Elf::new(elf.data);
+  elf.repair();
+  assert_eq!(elf.header.description.section_hdr_offset as usize, offset);
+  assert_eq!(elf.section_headers.len(), count);
+}
+
+//eh.so with its section header table copied to the end of the file and padded with copies of
+//section 1, so that .shstrtab ends up at index SHN_LORESERVE
+#[test]
+fn repair_moves_a_large_name_table_index_to_section_zero() {
+  let elf = library();
+  let (offset, names) = (elf.header.description.section_hdr_offset as usize, elf.header.description.section_hdr_str_index as usize);
+  let entry = |index: usize| &elf.data[offset + index * 64..offset + (index + 1) * 64];
+  let mut table = Vec::new();
+  for index in (0..elf.section_headers.len()).filter(|&index| index != names) {
+    table.extend_from_slice(entry(index));
+  }
+  while table.len() < SHN_LORESERVE as usize * 64 {
+    table.extend_from_slice(entry(1));
+  }
+  table.extend_from_slice(entry(names));
+  let table_offset = elf.data.len();
+  let mut data = elf.data.to_vec();
+  data.extend_from_slice(&table);
+  let mut elf = Elf::new(data.into_boxed_slice());
+  elf.header.description.section_hdr_offset = table_offset as u64;
+  elf.header.description.section_hdr_num = 0;
+  elf.write_header();
+
+  let fixes = elf.repair();
+  assert!(fixes.iter().any(|fix| fix.field == "e_shstrndx" && fix.new == SHN_LORESERVE as u64));
+  assert_eq!((elf.header.description.section_hdr_num, elf.header.description.section_hdr_str_index), (0, SHN_XINDEX));
+  assert_eq!(elf.data[62..64], SHN_XINDEX.to_le_bytes());
+  let first = &elf.data[table_offset..table_offset + 64];
+  assert_eq!(first[32..40], (SHN_LORESERVE as u64 + 1).to_le_bytes());
+  assert_eq!(first[40..44], (SHN_LORESERVE as u32).to_le_bytes());
+  //the index in sh_link is read back
+  assert!(elf.repair().iter().all(|fix| fix.field != "e_shstrndx"));
+}
+
+#[test]
+fn no_section_headers_without_a_table_offset() {
+  let mut data = include_bytes!("data/eh.so").to_vec();
+  //e_shoff
+  data[0x28..0x30].copy_from_slice(&0u64.to_le_bytes());
+  let elf = Elf::new(data.into_boxed_slice());
+  assert!(elf.section_headers.is_empty());
+  assert!(!elf.program_headers.is_empty());
+}
+
+#[test]
+fn extended_section_count() {
+  let elf = library();
+  let (offset, count) = (elf.header.description.section_hdr_offset as usize, elf.section_headers.len());
+  let mut data = elf.data.to_vec();
+  data[offset + 32..offset + 40].copy_from_slice(&(count as u64).to_le_bytes());
+  //e_shnum
+  data[0x3c..0x3e].copy_from_slice(&0u16.to_le_bytes());
+  let extended = Elf::new(data.into_boxed_slice());
+  assert_eq!(extended.section_headers.len(), count);
+  assert_eq!(extended.section_name(&extended.section_headers[count - 1]), elf.section_name(&elf.section_headers[count - 1]));
+}
+
+#[test]
+fn object_types() {
+  let mut elf = library();
+  assert_eq!(elf.object_type(), ObjectType::SharedObject);
+  for (raw, object_type) in [(ET_REL, ObjectType::Relocatable), (0xfe10, ObjectType::OsSpecific(0xfe10)), (ET_HIPROC, ObjectType::ProcessorSpecific(ET_HIPROC))] {
+    elf.set_obj_type(raw).unwrap();
+    assert_eq!((elf.object_type(), elf.object_type().raw()), (object_type, raw));
+  }
+  assert!(elf.set_obj_type(5).is_err());
+  assert_eq!(ObjectType::from_raw(0xfdff), ObjectType::Unknown(0xfdff));
+  assert_eq!(elf.header.description.obj_type, ET_HIPROC);
+}
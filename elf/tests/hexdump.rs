@@ -0,0 +1,29 @@
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn rows_carry_the_labels_of_spans_starting_in_them() {
+  let elf = library();
+  let dump = elf.annotated_hexdump(0..0x40);
+  let rows: Vec<&str> = dump.lines().collect();
+  assert_eq!(rows.len(), 4);
+  assert!(rows[0].starts_with("00000000  7f 45 4c 46 02 01 01 00"));
+  assert!(rows[1].starts_with("00000010  03 00 3e 00"));
+  assert!(rows[1].contains("e_type"));
+  assert!(!rows[0].contains("e_type"));
+  //labels follow offsets within a row
+  let labels = rows[1].rsplit("|  ").next().unwrap();
+  assert!(labels.find("e_type").unwrap() < labels.find("e_machine").unwrap());
+}
+
+#[test]
+fn partial_rows_only_label_spans_inside_the_range() {
+  let elf = library();
+  let dump = elf.annotated_hexdump(0x12..0x14);
+  assert_eq!(dump.lines().count(), 1);
+  assert!(dump.contains("e_machine"));
+  assert!(!dump.contains("e_type"));
+}
@@ -0,0 +1,41 @@
+use byteorder::{ByteOrder, LittleEndian};
+use elf::*;
+
+//tests/data/ifunc.so: IRELATIVE slots at 0x1f10 and 0x1f18 for the ifuncs compute (resolver
+//at 0x2b0) and measure (0x2c0), implemented by slow (0x290) and fast (0x2a0)
+const BIAS: u64 = 0x7f00_0000_0000;
+
+fn library() -> (Elf, LoadedImage) {
+  let elf = Elf::new(include_bytes!("data/ifunc.so").to_vec().into_boxed_slice());
+  let image = elf.load(Some(BIAS)).unwrap();
+  (elf, image)
+}
+
+fn slot(image: &LoadedImage, address: u64) -> u64 {
+  LittleEndian::read_u64(image.read(BIAS + address, 8).unwrap())
+}
+
+#[test]
+fn slots_are_named_after_the_ifunc_symbols() {
+  let (elf, mut image) = library();
+  let mut resolvers = IfuncResolvers::new();
+  resolvers.add_result(0x2b0, 0x290);
+  let slots = elf.apply_ifunc_relocations(&mut image, &mut resolvers).unwrap();
+  let slots: Vec<_> = slots.iter().map(|slot| (slot.slot - BIAS, slot.resolver - BIAS, slot.resolver_name.as_deref(), slot.value)).collect();
+  assert_eq!(slots, [(0x1f10, 0x2b0, Some("compute"), Some(BIAS + 0x290)), (0x1f18, 0x2c0, Some("measure"), None)]);
+  assert_eq!(slot(&image, 0x1f10), BIAS + 0x290);
+}
+
+#[test]
+fn named_results_and_the_evaluator() {
+  let (elf, mut image) = library();
+  let mut resolvers = IfuncResolvers::new();
+  resolvers.add_named_result("measure", "fast");
+  resolvers.set_evaluator(|request| match request.resolver_name {
+    Some("compute") => Some(request.resolver + 0x10),
+    _ => None,
+  });
+  let slots = elf.apply_ifunc_relocations(&mut image, &mut resolvers).unwrap();
+  assert_eq!(slots.iter().map(|slot| slot.value).collect::<Vec<_>>(), [Some(BIAS + 0x2c0), Some(BIAS + 0x2a0)]);
+  assert_eq!((slot(&image, 0x1f10), slot(&image, 0x1f18)), (BIAS + 0x2c0, BIAS + 0x2a0));
+}
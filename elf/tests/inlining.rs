@@ -0,0 +1,38 @@
+use elf::*;
+
+//tests/data/inline.so: norm inlines sum_of_squares, which inlines square twice, and twice
+//inlines square at 0x1010, 0x1019 and 0x1021
+fn report() -> InlineReport {
+  Elf::new(include_bytes!("data/inline.so").to_vec().into_boxed_slice()).inline_attribution()
+}
+
+#[test]
+fn inlines_of_inlines_are_attributed_to_the_out_of_line_function() {
+  let report = report();
+  let nested: Vec<_> = report.at(0x1004).iter().map(|range| (range.callee.as_str(), range.inlined_into.as_str(), range.function.as_str(), range.depth)).collect();
+  assert_eq!(nested, [("sum_of_squares", "norm", "norm", 1), ("square", "sum_of_squares", "norm", 2)]);
+  let outer = report.at(0x1006);
+  assert_eq!((outer.len(), outer[0].start, outer[0].end), (1, 0x1000, 0x1009));
+  assert!(report.at(0x1009).is_empty());
+  assert!(outer[0].call_file.as_deref().unwrap().ends_with("inline.c"));
+  assert_eq!(outer[0].call_line, Some(5));
+}
+
+#[test]
+fn bytes_per_callee() {
+  let report = report();
+  let square = report.stats("square").unwrap();
+  assert_eq!((square.instances, square.callers, square.bytes, square.exclusive_bytes), (4, 2, 14, 14));
+  //6 of its 9 bytes are the copies of square
+  let sum_of_squares = report.stats("sum_of_squares").unwrap();
+  assert_eq!((sum_of_squares.instances, sum_of_squares.callers, sum_of_squares.bytes, sum_of_squares.exclusive_bytes), (1, 1, 9, 3));
+  assert_eq!(report.callees.iter().map(|stats| stats.callee.as_str()).collect::<Vec<_>>(), ["square", "sum_of_squares"]);
+  assert!(report.stats("norm").is_none());
+  assert!(report.to_string().lines().nth(1).unwrap().ends_with("  square"));
+}
+
+#[test]
+fn no_debug_info_no_ranges() {
+  let report = Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice()).inline_attribution();
+  assert!(report.ranges.is_empty() && report.callees.is_empty());
+}
@@ -0,0 +1,36 @@
+use std::sync::Arc;
+use elf::*;
+
+#[test]
+fn interned_strings_are_shared() {
+  let interner = Interner::new();
+  let a = interner.intern("printf");
+  let b = interner.intern("printf");
+  assert!(Arc::ptr_eq(&a, &b));
+  assert_eq!(interner.len(), 1);
+}
+
+#[test]
+fn symbols_of_two_elfs_share_their_names() {
+  let interner = Arc::new(Interner::new());
+  let data = include_bytes!("data/eh.so");
+  let (a, b) = (Elf::with_interner(data.to_vec().into_boxed_slice(), interner.clone()), Elf::with_interner(data.to_vec().into_boxed_slice(), interner.clone()));
+  let (a, b) = (a.dynamic_symbols(), b.dynamic_symbols());
+  assert!(a.iter().zip(&b).all(|(a, b)| Arc::ptr_eq(&a.name, &b.name)));
+}
+
+#[test]
+fn purge_drops_strings_of_dropped_elfs() {
+  let interner = Arc::new(Interner::new());
+  let kept = interner.intern("kept");
+  let elf = Elf::with_interner(include_bytes!("data/eh.so").to_vec().into_boxed_slice(), interner.clone());
+  let symbols = elf.dynamic_symbols();
+  assert!(elf.memory_usage().interned_strings > 0);
+  let with_elf = interner.len();
+  assert_eq!(interner.purge(), 0);
+  drop(symbols);
+  drop(elf);
+  assert_eq!(interner.purge(), with_elf - 1);
+  assert_eq!(interner.len(), 1);
+  assert_eq!(&*kept, "kept");
+}
@@ -0,0 +1,53 @@
+use std::io::ErrorKind;
+use elf::*;
+
+fn dump() -> JitDump {
+  JitDump {
+    header: JitDumpHeader { version: JITDUMP_VERSION, elf_mach: EM_X86_64 as u32, pid: 42, timestamp: 1, flags: 0 },
+    records: vec![
+      JitRecord::CodeLoad { timestamp: 2, pid: 42, tid: 43, vma: 0x1000, code_address: 0x1000, code_index: 0, name: "jitted".to_string(), code: vec![0xc3; 5] },
+      JitRecord::CodeMove { timestamp: 3, pid: 42, tid: 43, vma: 0x2000, old_code_address: 0x1000, new_code_address: 0x2000, code_size: 5, code_index: 0 },
+    ],
+  }
+}
+
+fn written(dump: &JitDump) -> Vec<u8> {
+  let mut data = Vec::new();
+  dump.write(&mut data).unwrap();
+  data
+}
+
+#[test]
+fn jitdump_round_trip() {
+  let dump = JitDump::read(&written(&dump())[..]).unwrap();
+  assert_eq!((dump.header.pid, dump.records.len()), (42, 2));
+  let symbols = dump.symbols();
+  assert_eq!(symbols.len(), 1);
+  assert_eq!((symbols[0].address, symbols[0].size, &symbols[0].name[..]), (0x2000, 5, "jitted"));
+}
+
+#[test]
+fn sizes_past_the_input_are_invalid_data() {
+  let data = written(&dump());
+  //header size
+  let mut header = data.clone();
+  header[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+  assert_eq!(JitDump::read(&header[..]).err().map(|error| error.kind()), Some(ErrorKind::InvalidData));
+  //total size of the first record
+  let mut record = data.clone();
+  record[44..48].copy_from_slice(&u32::MAX.to_le_bytes());
+  assert_eq!(JitDump::read(&record[..]).err().map(|error| error.kind()), Some(ErrorKind::InvalidData));
+  //a record cut short
+  assert_eq!(JitDump::read(&data[..data.len() - 4]).err().map(|error| error.kind()), Some(ErrorKind::InvalidData));
+}
+
+#[test]
+fn perf_map_round_trip() {
+  let symbols = [SyntheticSymbol { address: 0x7f00_1000, size: 0x20, name: "Interpreter::run()".to_string() }];
+  let mut map = Vec::new();
+  write_perf_map(&mut map, &symbols).unwrap();
+  assert_eq!(map, b"7f001000 20 Interpreter::run()\n");
+  let read = read_perf_map(&map[..]).unwrap();
+  assert_eq!((read[0].address, read[0].size, &read[0].name[..]), (0x7f00_1000, 0x20, "Interpreter::run()"));
+  assert!(read_perf_map(&b"xyz 20 name\n"[..]).is_err());
+}
@@ -0,0 +1,78 @@
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use elf::*;
+
+fn file(name: &str) -> Elf {
+  Elf::new(std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().into_boxed_slice())
+}
+
+//every section header and symbol of the file read through the raw structures of its class,
+//widened to what the parser has, then narrowed and written back to the same bytes
+fn round_trip<L: ElfLayout, E: ByteOrder>(elf: &Elf) {
+  let offset = elf.header.description.section_hdr_offset as usize;
+  for (index, section) in elf.section_headers.iter().enumerate() {
+    let bytes = &elf.data[offset + index * L::SectionHeader::SIZE..];
+    let raw = L::SectionHeader::read::<E>(bytes).unwrap();
+    let wide = raw.widen();
+    assert_eq!((wide.name_index, wide.section_type, wide.offset, wide.size, wide.link), (section.name_index, section.section_type, section.offset, section.size, section.link));
+    let mut written = vec![0u8; L::SectionHeader::SIZE];
+    L::SectionHeader::narrow(&wide).write::<E>(&mut written).unwrap();
+    assert_eq!(written, bytes[..L::SectionHeader::SIZE]);
+  }
+
+  //objects have .symtab, the library only .dynsym
+  let (table, symbols) = match elf.section_headers.iter().find(|section| section.section_type == SHT_SYMTAB) {
+    Some(table) => (table, elf.symbols()),
+    None => (elf.section_headers.iter().find(|section| section.section_type == SHT_DYNSYM).unwrap(), elf.dynamic_symbols()),
+  };
+  assert_eq!(symbols.len() as u64, table.size / L::Symbol::SIZE as u64);
+  for (index, symbol) in symbols.iter().enumerate() {
+    let bytes = &elf.data[table.offset as usize + index * L::Symbol::SIZE..];
+    let raw = L::Symbol::read::<E>(bytes).unwrap();
+    let wide = raw.widen();
+    assert_eq!((wide.value, wide.size, wide.symbol_type, wide.binding, wide.section_index), (symbol.value, symbol.size, symbol.symbol_type, symbol.binding, symbol.section_index));
+    //narrowing leaves st_name to the writer
+    let mut narrow = L::Symbol::narrow(&wide);
+    assert_eq!(narrow.name_index(), 0);
+    narrow.set_name_index(raw.name_index());
+    let mut written = vec![0u8; L::Symbol::SIZE];
+    narrow.write::<E>(&mut written).unwrap();
+    assert_eq!(written, bytes[..L::Symbol::SIZE]);
+  }
+}
+
+#[test]
+fn raw_structures_of_a_32_bit_big_endian_object() {
+  let elf = file("syscalls-arm.o");
+  assert_eq!((elf.header.identification.class, elf.header.identification.endianness), (ELFCLASS32, 2));
+  round_trip::<Elf32, BigEndian>(&elf);
+}
+
+#[test]
+fn raw_structures_of_a_64_bit_little_endian_library() {
+  let elf = file("eh.so");
+  assert_eq!((elf.header.identification.class, elf.header.identification.endianness), (ELFCLASS64, 1));
+  round_trip::<Elf64, LittleEndian>(&elf);
+}
+
+#[test]
+fn header_sizes_come_from_the_layouts() {
+  let elf = file("syscalls-arm.o");
+  assert_eq!((elf.expected_elf_hdr_size(), elf.expected_section_hdr_entry_size()), (ElfHeader32::SIZE as u16, SectionHeader32::SIZE as u16));
+  let elf = file("eh.so");
+  assert_eq!((elf.expected_elf_hdr_size(), elf.expected_program_hdr_entry_size()), (ElfHeader64::SIZE as u16, ProgramHeader64::SIZE as u16));
+}
+
+#[test]
+fn write_header_keeps_the_padding() {
+  for name in ["syscalls-arm.o", "eh.so"] {
+    let mut data = file(name).data.to_vec();
+    data[9..16].copy_from_slice(b"padding");
+    let mut elf = Elf::new(data.into_boxed_slice());
+    elf.set_flags(0x1234).unwrap();
+    elf.write_header();
+    assert_eq!(&elf.data[9..16], b"padding");
+    let reread = Elf::new(elf.data.clone());
+    assert_eq!(reread.header.description.flags, 0x1234);
+    assert_eq!(reread.section_headers.len(), elf.section_headers.len());
+  }
+}
@@ -0,0 +1,42 @@
+use elf::*;
+
+fn object(name: &str) -> Elf {
+  Elf::new(std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().into_boxed_slice())
+}
+
+#[test]
+fn glibc_versions() {
+  assert_eq!(GlibcVersion::parse("GLIBC_2.2.5"), Some(GlibcVersion::new(2, 2, 5)));
+  assert_eq!(GlibcVersion::parse("2.17"), Some(GlibcVersion::new(2, 17, 0)));
+  assert_eq!(GlibcVersion::parse("GLIBC_PRIVATE"), None);
+  assert_eq!(GlibcVersion::parse("1.2.3.4"), None);
+  assert!(GlibcVersion::new(2, 3, 4) < GlibcVersion::new(2, 26, 0));
+  assert_eq!(GlibcVersion::new(2, 2, 5).to_string(), "2.2.5");
+}
+
+//tests/data/glibc.so imports reallocarray@GLIBC_2.26 and __strcpy_chk@GLIBC_2.3.4 and
+//needs GLIBC_ABI_DT_RELR for its packed relocations
+#[test]
+fn requirements_of_a_glibc_library() {
+  let requirements = object("glibc.so").libc_requirements();
+  assert_eq!(requirements.libc, LibcKind::Glibc);
+  assert_eq!(requirements.minimum_glibc, Some(GlibcVersion::new(2, 36, 0)));
+  let symbols: Vec<_> = requirements.glibc_symbols.iter().map(|(name, version)| (name.as_str(), version.to_string())).collect();
+  assert_eq!(symbols, [("reallocarray", "2.26".into()), ("__strcpy_chk", "2.3.4".into()), ("__cxa_finalize", "2.2.5".into())]);
+  assert!(requirements.private_symbols.is_empty());
+  assert_eq!(requirements.musl_blockers, ["needs libc.so.6", "__strcpy_chk is a glibc fortify function"]);
+  assert!(requirements.runs_on_glibc(GlibcVersion::new(2, 36, 0)));
+  assert!(!requirements.runs_on_glibc(GlibcVersion::new(2, 35, 0)));
+  assert!(!requirements.runs_on_musl());
+}
+
+#[test]
+fn files_without_a_libc() {
+  //-nostdlib: no DT_NEEDED, the RELR tag alone does not ask for a glibc
+  let requirements = object("relr.so").libc_requirements();
+  assert_eq!(requirements.libc, LibcKind::Static);
+  assert_eq!(requirements.minimum_glibc, None);
+  assert!(requirements.runs_on_musl());
+  //start's interpreter is fortify.so, which is neither loader
+  assert_eq!(object("start").libc_kind(), LibcKind::Unknown);
+}
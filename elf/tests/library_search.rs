@@ -0,0 +1,33 @@
+use elf::*;
+
+fn data() -> String {
+  format!("{}/tests/data", env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn expand_path_tokens() {
+  let mut workspace = Workspace::new();
+  let user = workspace.open_object(format!("{}/abi-user.so", data()), 0).unwrap();
+  assert_eq!(workspace.expand_path_tokens("$ORIGIN/../$LIB:${PLATFORM}", Some(user)).unwrap(), format!("{}/../lib64:x86_64", data()));
+  //unknown tokens and an unterminated brace are kept
+  assert_eq!(workspace.expand_path_tokens("/opt/$HOME/${LIB", Some(user)).unwrap(), "/opt/$HOME/${LIB");
+  //no requester, no $ORIGIN
+  assert_eq!(workspace.expand_path_tokens("$ORIGIN/lib", None), None);
+  workspace.path_tokens = PathTokens { lib: Some(String::from("lib/x86_64-linux-gnu")), platform: Some(String::from("haswell")) };
+  assert_eq!(workspace.expand_path_tokens("/usr/$LIB/$PLATFORM", Some(user)).unwrap(), "/usr/lib/x86_64-linux-gnu/haswell");
+}
+
+#[test]
+fn platform_without_a_guess() {
+  let mut workspace = Workspace::new();
+  let object = workspace.open_object(format!("{}/syscalls-riscv.o", data()), 0).unwrap();
+  assert_eq!(workspace.expand_path_tokens("/usr/$PLATFORM", Some(object)), None);
+  assert_eq!(workspace.search_library("$PLATFORM/libc.so.6", Some(object)), None);
+}
+
+#[test]
+fn needed_names_with_origin() {
+  let mut workspace = Workspace::new();
+  let user = workspace.open_object(format!("{}/abi-user.so", data()), 0).unwrap();
+  assert_eq!(workspace.search_library("$ORIGIN/abi-v1.so", Some(user)).unwrap(), std::path::Path::new(&data()).join("abi-v1.so"));
+}
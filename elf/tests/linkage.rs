@@ -0,0 +1,32 @@
+use elf::*;
+
+fn object(name: &str) -> Elf {
+  Elf::new(std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().into_boxed_slice())
+}
+
+#[test]
+fn linkage_of_the_test_files() {
+  for (name, linkage) in [
+    ("static", Linkage::Static), ("static-pie", Linkage::StaticPie), ("static-dlopen", Linkage::StaticWithDlopen),
+    ("start", Linkage::Dynamic), ("eh.so", Linkage::Dynamic), ("relr.so", Linkage::Dynamic),
+  ] {
+    assert_eq!(object(name).linkage().linkage, linkage, "{}", name);
+  }
+}
+
+#[test]
+fn evidence() {
+  assert_eq!(object("start").linkage().evidence, ["PT_INTERP /fortify.so"]);
+  assert_eq!(object("eh.so").linkage().evidence, ["DT_NEEDED libstdc++.so.6", "DT_NEEDED libgcc_s.so.1"]);
+  assert_eq!(object("static").linkage().evidence, ["no PT_INTERP and no PT_DYNAMIC"]);
+  assert_eq!(object("static-pie").linkage().evidence, ["DF_1_PIE without PT_INTERP"]);
+  assert_eq!(object("static-dlopen").linkage().evidence, ["no PT_INTERP and no PT_DYNAMIC", "direct calls to dlopen: 1"]);
+}
+
+//a bl and a b to dlopen in a big-endian object
+#[test]
+fn aarch64_calls_to_dlopen() {
+  let report = object("dlopen-aarch64.o").linkage();
+  assert_eq!(report.linkage, Linkage::StaticWithDlopen);
+  assert_eq!(report.evidence.last().unwrap(), "direct calls to dlopen: 2");
+}
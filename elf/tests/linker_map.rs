@@ -0,0 +1,28 @@
+use elf::*;
+
+//a GNU ld map of the ET_EXEC fixture's .text, with an input section before it, and of a
+//.comment added to the fixture
+fn mapped() -> (Elf, LinkerMap) {
+  let mut elf = Elf::new(fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_EXEC, machine: EM_X86_64 }).into_boxed_slice());
+  elf.add_section(".comment", SHT_PROGBITS, 0, 1, &[0; 0x40]).unwrap();
+  let text = elf.section_by_name(".text").unwrap().address;
+  let map = format!(
+    "Linker script and memory map\n\n.text {:#x} 0x10\n .text {:#x} 0x8 a.o\n .text {:#x} 0x8 b.o\n .text {:#x} 0x8 c.o\n.comment 0x0 0x40\n .comment 0x0 0x40 a.o\n",
+    text, text, text - 0x10, text + 8);
+  (elf, LinkerMap::parse(&map).unwrap())
+}
+
+#[test]
+fn input_sections_outside_their_output_section_are_dropped() {
+  let (elf, map) = mapped();
+  let text = elf.section_by_name(".text").unwrap();
+  let ranges: Vec<_> = elf.map_ranges(&map).unwrap().iter().map(|range| (range.input_index, range.offset)).collect();
+  assert_eq!(ranges, [(0, Some(text.offset)), (2, Some(text.offset + 8))]);
+}
+
+#[test]
+fn size_by_file_counts_loaded_sections() {
+  let (elf, map) = mapped();
+  assert_eq!(map.input_sections.len(), 4);
+  assert_eq!(elf.size_by_file(&map).unwrap(), [("a.o", 8), ("c.o", 8)]);
+}
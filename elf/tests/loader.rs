@@ -0,0 +1,76 @@
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/fortify.so").to_vec().into_boxed_slice())
+}
+
+//fortify.so with its first PT_LOAD rewritten through `update`
+fn with_load(update: impl Fn(&mut ProgramHeader)) -> Elf {
+  let mut elf = library();
+  update(elf.program_headers.iter_mut().find(|ph| ph.entry_type == PT_LOAD).unwrap());
+  elf
+}
+
+#[test]
+fn loads_segments() {
+  let elf = library();
+  let image = elf.load(Some(0x40_0000)).unwrap();
+  assert_eq!(image.segments.len(), elf.program_headers.iter().filter(|ph| ph.entry_type == PT_LOAD).count());
+  assert_eq!(image.segments[0].address, 0x40_0000);
+  assert_eq!(image.segments[0].data[..4], b"\x7fELF"[..]);
+  assert!(image.segments.iter().all(|segment| (segment.data.len() as u64).is_multiple_of(PAGE_SIZE)));
+  let stack = image.initial_stack(&StackOptions { arguments: vec!["a.out".into()], ..Default::default() }).unwrap();
+  assert_eq!(stack.stack_pointer & 15, 0);
+}
+
+#[test]
+fn oversized_segments_are_rejected() {
+  let elf = with_load(|ph| ph.memory_size = 1 << 60);
+  assert_eq!(elf.load(None).err().unwrap().kind(), std::io::ErrorKind::InvalidData);
+  let elf = with_load(|ph| ph.memory_size = u64::MAX - ph.virtual_address);
+  assert!(elf.load(None).is_err());
+  let elf = with_load(|ph| ph.file_size = 1 << 40);
+  assert!(elf.load(None).is_err());
+  assert!(library().load_with_limit(None, PAGE_SIZE - 1).is_err());
+}
+
+#[test]
+fn stack_must_fit() {
+  let image = library().load(None).unwrap();
+  let options = StackOptions { arguments: vec!["a.out".into()], stack_top: Some(0x20), ..Default::default() };
+  assert!(image.initial_stack(&options).is_err());
+  let options = StackOptions { environment: vec!["X".repeat(1 << 16)], stack_limit: 1 << 12, ..Default::default() };
+  assert!(image.initial_stack(&options).is_err());
+}
+
+//tests/data/start asks for /fortify.so
+fn process(options: LoadOptions) -> std::io::Result<ProcessImage> {
+  Elf::new(include_bytes!("data/start").to_vec().into_boxed_slice()).load_process(&options)
+}
+
+fn in_sysroot() -> LoadOptions {
+  LoadOptions { sysroot: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data").into()), ..Default::default() }
+}
+
+#[test]
+fn loads_the_interpreter_below_the_mmap_base() {
+  let image = process(in_sysroot()).unwrap();
+  assert_eq!(image.executable.segments[0].address, 0x5555_5555_4000);
+  let interpreter = image.interpreter.as_ref().unwrap();
+  assert!(image.interpreter_path.as_ref().unwrap().ends_with("tests/data/fortify.so"));
+  let end = interpreter.segments.iter().map(|segment| segment.address + segment.data.len() as u64).max().unwrap();
+  assert_eq!(end, 0x7fff_f7ff_f000);
+  assert_eq!(image.entry(), interpreter.entry);
+  let stack = image.initial_stack(&Default::default()).unwrap();
+  assert!(stack.auxiliary_vector.contains(&(AT_BASE, interpreter.bias)));
+}
+
+#[test]
+fn the_interpreter_is_optional() {
+  let image = process(LoadOptions { load_interpreter: false, ..in_sysroot() }).unwrap();
+  assert!(image.interpreter.is_none());
+  assert_eq!(image.entry(), image.executable.entry);
+  let missing = process(LoadOptions { sysroot: Some("/nonexistent".into()), ..Default::default() });
+  assert!(missing.is_err());
+  assert!(process(LoadOptions { memory_limit: PAGE_SIZE, ..in_sysroot() }).is_err());
+}
@@ -0,0 +1,94 @@
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/fortify.so").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn sections_by_name() {
+  let elf = library();
+  let dynsym = elf.section_by_name(".dynsym").unwrap();
+  assert_eq!(dynsym.section_type, SHT_DYNSYM);
+  assert_eq!(elf.section_index_by_name(".dynsym").map(|index| elf.section_headers[index].offset), Some(dynsym.offset));
+  assert!(elf.section_by_name(".missing").is_none());
+}
+
+#[test]
+fn symbols_by_name_skip_imports() {
+  let elf = library();
+  let copy = elf.symbol_by_name("copy").unwrap();
+  assert!(!copy.is_undefined());
+  assert!(elf.symbol_table().len() > elf.dynamic_symbol_table().len());
+  //strcpy is only an import in .dynsym
+  assert!(elf.symbol_by_name("strcpy").is_none());
+  let imports = elf.dynamic_symbol_indices_by_name("strcpy");
+  assert_eq!(imports.len(), 1);
+  assert!(elf.dynamic_symbol_table()[imports[0]].is_undefined());
+}
+
+#[test]
+fn name_maps_are_counted_once_built() {
+  let elf = library();
+  let before = elf.memory_usage().name_maps;
+  assert!(elf.symbol_by_name("watch").is_some());
+  assert!(elf.memory_usage().name_maps > before);
+}
+
+#[test]
+fn a_new_name_table_rebuilds_the_section_map() {
+  let mut elf = library();
+  assert!(elf.section_by_name(".text").is_some());
+  let dynstr = elf.section_index_by_name(".dynstr").unwrap();
+  elf.set_section_hdr_str_index(dynstr as u16).unwrap();
+  assert!(elf.section_by_name(".text").is_none());
+}
+
+#[test]
+fn section_headers_edited_after_a_lookup() {
+  let mut elf = library();
+  let text = elf.section_index_by_name(".text").unwrap();
+  elf.section_headers.swap(text, 1);
+  assert_eq!(elf.section_index_by_name(".text"), Some(1));
+  assert_eq!(elf.section_name(elf.section_by_name(".text").unwrap()), Some(".text"));
+  elf.section_headers.truncate(1);
+  assert!(elf.section_by_name(".text").is_none());
+  //once told, the maps are built from the edited headers
+  elf.reload_tables();
+  assert_eq!(elf.section_index_by_name(".text"), Some(text));
+  elf.section_headers.swap(text, 1);
+  elf.invalidate_caches();
+  assert_eq!(elf.section_index_by_name(".text"), Some(1));
+}
+
+#[test]
+fn debug_sections_of_edited_headers() {
+  let mut elf = Elf::new(include_bytes!("data/inline.so").to_vec().into_boxed_slice());
+  assert!(!elf.dwarf().debug_info.is_empty());
+  elf.section_headers.truncate(1);
+  assert!(elf.dwarf().debug_info.is_empty());
+}
+
+#[test]
+fn cross_references() {
+  let elf = library();
+  let copy = elf.symbol_by_name("copy").unwrap().value;
+  let text = elf.section_at_address(copy).unwrap();
+  assert_eq!(elf.section_name(&elf.section_headers[text]), Some(".text"));
+  let section = &elf.section_headers[text];
+  assert_eq!(elf.section_offset_of(copy), Some(section.offset + copy - section.address));
+  //the first PT_LOAD only, PT_GNU_EH_FRAME starts at .eh_frame_hdr
+  assert_eq!(elf.segments_of_section(text), [0]);
+  assert!(elf.sections_of_segment(0).contains(&text));
+  let memcpy = &elf.dynamic_symbol_table()[elf.dynamic_symbol_indices_by_name("__memcpy_chk")[0]];
+  let references: Vec<u64> = elf.symbol_references(memcpy).iter().map(|relocation| relocation.offset).collect();
+  assert_eq!(references, [0x1ff8]);
+}
+
+#[test]
+fn addresses_past_every_section() {
+  let elf = library();
+  assert_eq!(elf.section_at_address(u64::MAX), None);
+  assert_eq!(elf.section_offset_of(u64::MAX), None);
+  assert!(elf.segments_of_section(usize::MAX).is_empty());
+  assert!(elf.sections_of_segment(usize::MAX).is_empty());
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "sha2")]
+
+use std::io::ErrorKind;
+use elf::*;
+
+//data/signed.ko is the ET_REL x86-64 fixture signed as sign-file does, a detached PKCS#7
+//without certificates or attributes by the key in data/signing_cert.pem:
+//openssl cms -sign -binary -noattr -nocerts -nosmimecap -outform DER -md sha256
+fn signed() -> Vec<u8> {
+  std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/signed.ko")).unwrap()
+}
+
+fn certificates() -> Vec<Certificate> {
+  Certificate::from_pem(&std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/signing_cert.pem")).unwrap()).unwrap()
+}
+
+#[test]
+fn appended_signature_is_parsed() {
+  let elf = Elf::new(signed().into_boxed_slice());
+  let signature = elf.module_signature().unwrap();
+  assert_eq!(signature.id_type, PKEY_ID_PKCS7);
+  assert_eq!(signature.signers.len(), 1);
+  assert!(signature.certificates.is_empty());
+  let unsigned = fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_REL, machine: EM_X86_64 });
+  assert_eq!(signature.signed_size, unsigned.len() as u64);
+  assert!(signature.signers[0].issued_by(&certificates()[0]));
+}
+
+#[test]
+fn signature_verifies_against_its_certificate() {
+  let certificates = certificates();
+  assert_eq!(certificates[0].subject.common_name(), Some("walker test module key"));
+  assert_eq!(Elf::new(signed().into_boxed_slice()).verify_module_signature(&certificates).unwrap(), 0);
+}
+
+#[test]
+fn changed_modules_do_not_verify() {
+  let mut data = signed();
+  data[0x40] ^= 1;
+  let error = Elf::new(data.into_boxed_slice()).verify_module_signature(&certificates()).unwrap_err();
+  assert_eq!(error.kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn unsigned_and_truncated_blocks() {
+  let unsigned = Elf::new(fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_REL, machine: EM_X86_64 }).into_boxed_slice());
+  assert_eq!(unsigned.module_signature().err().unwrap().kind(), ErrorKind::NotFound);
+  //sig_len larger than the file
+  let mut data = signed();
+  let info = data.len() - MODULE_SIGNATURE_MAGIC.len() - 4;
+  data[info..info + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+  assert_eq!(Elf::new(data.into_boxed_slice()).module_signature().err().unwrap().kind(), ErrorKind::InvalidData);
+}
@@ -0,0 +1,60 @@
+use elf::fuzz::parse_oracle;
+use elf::*;
+
+fn object(name: &str) -> Elf {
+  Elf::new(std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().into_boxed_slice())
+}
+
+fn mutation<'a>(mutations: &'a [Mutation], label: &str) -> &'a Mutation {
+  mutations.iter().find(|mutation| mutation.label == label).unwrap_or_else(|| panic!("no mutation {}", label))
+}
+
+//the bytes that differ between the file and a mutation of it
+fn changed(elf: &Elf, mutation: &Mutation) -> Vec<usize> {
+  (0..elf.data.len()).filter(|&position| elf.data[position] != mutation.data[position]).collect()
+}
+
+#[test]
+fn header_mutations_change_one_field() {
+  let elf = object("eh.so");
+  let mutations = Mutator::new(&elf).mutations();
+  let mutated = Elf::new(mutation(&mutations, "e_shoff at the end of the file").data.clone().into_boxed_slice());
+  assert_eq!(mutated.header.description.section_hdr_offset, elf.data.len() as u64);
+  assert!(changed(&elf, mutation(&mutations, "e_shoff at the end of the file")).iter().all(|position| (0x28..0x30).contains(position)));
+  assert_eq!(changed(&elf, mutation(&mutations, "e_phentsize 1")), [0x36]);
+}
+
+//a 32-bit big-endian object
+#[test]
+fn section_mutations_use_the_class_layout() {
+  let elf = object("syscalls-arm.o");
+  assert_eq!((elf.header.identification.class, elf.header.identification.endianness), (ELFCLASS32, ELFDATA2MSB));
+  let mutations = Mutator::new(&elf).mutations();
+  let index = elf.section_index_by_name(".text").unwrap();
+  let mutated = Elf::new(mutation(&mutations, &format!("section {} (.text) sh_size at its maximum", index)).data.clone().into_boxed_slice());
+  assert_eq!(mutated.section_headers[index].size, u32::MAX as u64);
+  //sh_size, the fifth word of the entry
+  let entry = elf.header.description.section_hdr_offset as usize + index * 40;
+  assert_eq!(changed(&elf, mutation(&mutations, &format!("section {} (.text) sh_size at its maximum", index))), (entry + 20..entry + 24).filter(|&position| elf.data[position] != 0xff).collect::<Vec<_>>());
+  assert_eq!(mutated.header.description.section_hdr_offset, elf.header.description.section_hdr_offset);
+}
+
+#[test]
+fn every_mutation_differs_and_parses_without_panicking() {
+  for name in ["eh.so", "fortify.so", "struct.o", "syscalls-arm.o"] {
+    let elf = object(name);
+    let mutations = Mutator::new(&elf).mutations();
+    for mutation in &mutations {
+      assert_ne!(mutation.data, *elf.data, "{}", mutation.label);
+      if mutation.kind != MutationKind::Truncation {
+        assert_eq!(mutation.data.len(), elf.data.len(), "{}", mutation.label);
+      }
+      let outcome = parse_oracle(&mutation.data);
+      //an unaligned table and p_memsz below p_filesz are left for the loader to reject
+      let loader_only = mutation.label.ends_with("unaligned") || mutation.label.ends_with("p_memsz below p_filesz");
+      if matches!(mutation.kind, MutationKind::Truncation | MutationKind::CountOverflow | MutationKind::OffsetBomb) && !loader_only {
+        assert!(!outcome.strict, "{} {}", name, mutation.label);
+      }
+    }
+  }
+}
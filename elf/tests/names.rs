@@ -0,0 +1,46 @@
+use std::io::ErrorKind;
+use elf::*;
+
+//the ET_DYN fixture with the first byte of "text", "start" and "libfixture.so" made 0xff
+fn invalid_names() -> Elf {
+  let mut data = fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_DYN, machine: EM_X86_64 });
+  for name in [&b".text\0"[..], b"_start\0", b"\0libfixture.so\0"] {
+    let position = data.windows(name.len()).position(|window| window == name).unwrap();
+    data[position + 1] = 0xff;
+  }
+  Elf::new(data.into_boxed_slice())
+}
+
+#[test]
+fn section_names_that_are_not_utf8() {
+  let elf = invalid_names();
+  let text = &elf.section_headers[1];
+  assert_eq!(elf.section_name_bytes(text), Some(&b".\xffext"[..]));
+  assert_eq!(elf.section_name(text), None);
+  assert_eq!(elf.section_name_lossy(text).as_deref(), Some(".\u{fffd}ext"));
+  assert_eq!(elf.section_name_str(text).unwrap_err().kind(), ErrorKind::InvalidData);
+  let missing = SectionHeader { name_index: u32::MAX, ..Default::default() };
+  assert_eq!(elf.section_name_str(&missing).unwrap_err().kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn symbol_names_that_are_not_utf8() {
+  let elf = invalid_names();
+  let symbol = &elf.symbol_table()[1];
+  assert_eq!(symbol.raw_name(), Some(&b"_\xfftart"[..]));
+  assert_eq!(symbol.name_bytes(), b"_\xfftart");
+  assert_eq!(symbol.name_lossy(), "_\u{fffd}tart");
+  assert_eq!(symbol.name_str().unwrap_err().kind(), ErrorKind::InvalidData);
+  let dynamic = &elf.dynamic_symbol_table()[1];
+  assert_eq!((dynamic.raw_name(), dynamic.name_str().unwrap()), (None, "fixture_function"));
+}
+
+#[test]
+fn dynamic_strings_that_are_not_utf8() {
+  let elf = invalid_names();
+  let soname = elf.dynamic_value(DT_SONAME).unwrap();
+  assert_eq!(elf.dynamic_string(soname), None);
+  assert_eq!(elf.dynamic_string_lossy(soname).as_deref(), Some("\u{fffd}ibfixture.so"));
+  assert_eq!(elf.dynamic_string_str(soname).unwrap_err().kind(), ErrorKind::InvalidData);
+  assert_eq!(elf.dynamic_string_str(u32::MAX as u64).unwrap_err().kind(), ErrorKind::NotFound);
+}
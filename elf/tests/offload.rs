@@ -0,0 +1,43 @@
+use elf::*;
+
+const HEADER_SIZE: usize = 24 + 8 + 24;
+const ID: &[u8] = b"hip-amdgcn-amd-amdhsa--gfx90a";
+
+//a clang offload bundle of one entry with `payload`, or covering the whole bundle without one
+fn bundle(payload: Option<&[u8]>) -> Vec<u8> {
+  let size = payload.map_or(HEADER_SIZE + ID.len(), <[u8]>::len);
+  let offset = if payload.is_some() { HEADER_SIZE + ID.len() } else { 0 };
+  let mut data = CLANG_OFFLOAD_BUNDLE_MAGIC.to_vec();
+  for field in &[1, offset, size, ID.len()] {
+    data.extend_from_slice(&(*field as u64).to_le_bytes());
+  }
+  data.extend_from_slice(ID);
+  data.extend_from_slice(payload.unwrap_or(&[]));
+  data
+}
+
+fn with_offloading(contents: &[u8]) -> Elf {
+  let mut elf = Elf::new(fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_EXEC, machine: EM_X86_64 }).into_boxed_slice());
+  elf.add_section(".hip_fatbin", SHT_PROGBITS, 0, 8, contents).unwrap();
+  elf
+}
+
+#[test]
+fn images_of_nested_bundles_are_found() {
+  //EM_AMDGPU
+  let code_object = fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_DYN, machine: 224 });
+  let elf = with_offloading(&bundle(Some(&bundle(Some(&code_object)))));
+  let images = elf.offload_elf_images();
+  assert_eq!(images.len(), 1);
+  assert_eq!((images[0].0.arch.as_str(), images[0].0.data), ("gfx90a", &code_object[..]));
+  assert_eq!(images[0].1.as_ref().unwrap().header.description.machine, 224);
+}
+
+#[test]
+fn bundle_entry_covering_the_bundle_is_not_nested() {
+  let elf = with_offloading(&bundle(None));
+  let images = elf.offload_images();
+  assert_eq!((images.len(), images[0].kind), (1, DeviceImageKind::Fatbin));
+  assert!(images[0].nested().is_empty());
+  assert!(elf.offload_elf_images().is_empty());
+}
@@ -0,0 +1,43 @@
+use elf::*;
+
+//eh.so with the entries of a table repeated past the size the rayon feature splits into chunks
+fn repeated(section_type: u32, copies: usize) -> (Elf, Elf, usize) {
+  let original = Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice());
+  let index = original.section_headers.iter().position(|section| section.section_type == section_type).unwrap();
+  let (offset, size) = (original.section_headers[index].offset as usize, original.section_headers[index].size as usize);
+  let mut data = original.data.to_vec();
+  let table = data[offset..offset + size].to_vec();
+  let table_offset = data.len();
+  for _ in 0..copies {
+    data.extend_from_slice(&table);
+  }
+  let mut elf = Elf::new(data.into_boxed_slice());
+  elf.section_headers[index].offset = table_offset as u64;
+  elf.section_headers[index].size = (size * copies) as u64;
+  (original, elf, index)
+}
+
+#[test]
+fn large_symbol_tables_keep_their_order() {
+  let (original, elf, _) = repeated(SHT_DYNSYM, 1000);
+  let symbols = original.dynamic_symbols();
+  let large = elf.dynamic_symbols();
+  assert!(large.len() > 4096);
+  assert_eq!(large.len(), symbols.len() * 1000);
+  for (index, symbol) in large.iter().enumerate() {
+    let expected = &symbols[index % symbols.len()];
+    assert_eq!((&symbol.name, symbol.value, symbol.size, symbol.binding), (&expected.name, expected.value, expected.size, expected.binding));
+  }
+}
+
+#[test]
+fn large_relocation_tables_keep_their_order() {
+  let (original, elf, section) = repeated(SHT_RELA, 1000);
+  let relocations: Vec<_> = original.relocations().into_iter().filter(|relocation| relocation.section_index == section).collect();
+  let large: Vec<_> = elf.relocations().into_iter().filter(|relocation| relocation.section_index == section).collect();
+  assert_eq!(large.len(), relocations.len() * 1000);
+  for (index, relocation) in large.iter().enumerate() {
+    let expected = &relocations[index % relocations.len()];
+    assert_eq!((relocation.offset, relocation.relocation_type, relocation.symbol_index, relocation.addend), (expected.offset, expected.relocation_type, expected.symbol_index, expected.addend));
+  }
+}
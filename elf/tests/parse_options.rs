@@ -0,0 +1,36 @@
+use std::fs;
+use elf::*;
+
+fn library() -> Vec<u8> {
+  include_bytes!("data/eh.so").to_vec()
+}
+
+#[test]
+fn open_rejects_unreadable_identification() {
+  let path = std::env::temp_dir().join(format!("walker-open-{}", std::process::id()));
+  let mut bad_class = library();
+  bad_class[4] = 3;
+  let mut bad_endianness = library();
+  bad_endianness[5] = 0;
+  for data in [b"\x7fEL".to_vec(), bad_class, bad_endianness, library()[..40].to_vec()] {
+    fs::write(&path, &data).unwrap();
+    assert_eq!(Elf::open(&path).err().map(|error| error.kind() == std::io::ErrorKind::InvalidData || error.kind() == std::io::ErrorKind::UnexpectedEof), Some(true));
+  }
+  fs::write(&path, library()).unwrap();
+  assert!(Elf::open(&path).is_ok());
+  fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unknown_value_policies() {
+  let mut data = library();
+  //e_machine 11 is reserved
+  data[18] = 11;
+  let parse = |policy| Elf::parse(data.clone().into_boxed_slice(), &ParseOptions { unknown_values: policy });
+  assert!(parse(UnknownValuePolicy::Error).is_err());
+  let (_, unknown) = parse(UnknownValuePolicy::Warn).unwrap();
+  assert_eq!(unknown, [UnknownValue { field: "e_machine", value: 11, section_index: None }]);
+  let (elf, unknown) = parse(UnknownValuePolicy::Preserve).unwrap();
+  assert!(unknown.is_empty());
+  assert_eq!(elf.header.description.machine, 11);
+}
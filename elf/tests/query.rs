@@ -0,0 +1,28 @@
+use elf::*;
+use elf::query::Query;
+
+fn executable() -> Elf {
+  Elf::new(fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_EXEC, machine: EM_X86_64 }).into_boxed_slice())
+}
+
+//the files of tests/data a query matches
+fn matching(text: &str) -> Vec<String> {
+  let data = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data");
+  Query::parse(text).unwrap().scan(data).unwrap().iter().map(|path| path.file_name().unwrap().to_string_lossy().into_owned()).collect()
+}
+
+#[test]
+fn scan_a_directory() {
+  assert_eq!(matching("needs(\"libabi.so.1\")"), ["abi-user.so"]);
+  assert_eq!(matching("soname == \"libabi.so.1\" && !exports(\"shift\")"), ["abi-v1.so"]);
+  assert_eq!(matching("class < 64 || exports(\"sca?e\") && defines(\"legacy\")"), ["abi-v1.so", "syscalls-arm.o"]);
+}
+
+#[test]
+fn deeply_nested_expressions_are_rejected() {
+  for text in &["(".repeat(100_000) + "pie", "!".repeat(100_000) + "pie", vec!["pie"; 100_000].join(" && ")] {
+    assert_eq!(Query::parse(text).unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+  }
+  let nested = format!("{}pie{}", "!(".repeat(100), ")".repeat(100));
+  assert!(!Query::parse(&nested).unwrap().matches(&executable()));
+}
@@ -0,0 +1,63 @@
+use byteorder::{ByteOrder, LittleEndian};
+use elf::*;
+
+fn library(name: &str) -> Elf {
+  Elf::new(std::fs::read(format!("{}/tests/data/{}", env!("CARGO_MANIFEST_DIR"), name)).unwrap().into_boxed_slice())
+}
+
+fn word_at(elf: &Elf, address: u64) -> u64 {
+  let offset = elf.address_to_offset(address).unwrap() as usize;
+  LittleEndian::read_u64(&elf.data[offset..])
+}
+
+//tests/data/eh.so has three R_X86_64_RELATIVE relocations next to its GLOB_DAT and 64 ones
+#[test]
+fn relative_rela_relocations() {
+  let mut elf = library("eh.so");
+  let relative: Vec<_> = elf.relocations().into_iter().filter(|relocation| relocation.relocation_type == 8).collect();
+  assert_eq!(relative.len(), 3);
+  assert_eq!(elf.rebase(0x7f00_0000_0000).unwrap(), 3);
+  assert_eq!(elf.bias, 0x7f00_0000_0000);
+  for relocation in &relative {
+    assert_eq!(word_at(&elf, relocation.offset), 0x7f00_0000_0000 + relocation.addend.unwrap() as u64);
+  }
+  //rebasing again starts from the addends, not from the words written the first time
+  assert_eq!(elf.rebase(0x1000).unwrap(), 3);
+  assert_eq!(word_at(&elf, relative[0].offset), 0x1000 + relative[0].addend.unwrap() as u64);
+}
+
+//tests/data/relr.so keeps the 16 pointers of relr.c in one SHT_RELR address and bitmap
+#[test]
+fn relr_relocations() {
+  let mut elf = library("relr.so");
+  let pointers = 0x1e40;
+  let before: Vec<_> = (0..16).map(|index| word_at(&elf, pointers + index * 8)).collect();
+  assert_eq!(elf.rebase(0x40_0000).unwrap(), 16);
+  for (index, value) in before.iter().enumerate() {
+    assert_eq!(word_at(&elf, pointers + index as u64 * 8), value + 0x40_0000);
+  }
+  //the implicit addends are rebased from the previous bias
+  elf.rebase(0x80_0000).unwrap();
+  assert_eq!(word_at(&elf, pointers), before[0] + 0x80_0000);
+}
+
+#[test]
+fn targets_past_the_end_of_the_file_are_rejected() {
+  let mut elf = library("eh.so");
+  let last = elf.relocations().into_iter().filter(|relocation| relocation.relocation_type == 8).map(|relocation| relocation.offset).max().unwrap();
+  //the writable PT_LOAD moved so that only half of the last target is in the file
+  let length = elf.data.len() as u64;
+  let load = elf.program_headers.iter_mut().rfind(|ph| ph.entry_type == PT_LOAD).unwrap();
+  load.offset = length - 4 - (last - load.virtual_address);
+  let data = elf.data.clone();
+  assert_eq!(elf.rebase(0x1000).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+  assert_eq!(elf.data, data);
+  assert_eq!(elf.bias, 0);
+}
+
+#[test]
+fn unknown_class_is_an_error() {
+  let mut elf = library("eh.so");
+  elf.header.identification.class = 3;
+  assert_eq!(elf.rebase(0x1000).unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+}
@@ -0,0 +1,62 @@
+use elf::*;
+
+#[test]
+fn data_sections_are_read_as_far_as_the_file_goes() {
+  let mut data = fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_EXEC, machine: EM_X86_64 });
+  let elf = Elf::new(data.clone().into_boxed_slice());
+  let index = elf.section_index_by_name(".text").unwrap();
+  let entry = (elf.header.description.section_hdr_offset + index as u64 * 64) as usize;
+  //sh_flags and sh_size
+  data[entry + 8..entry + 16].copy_from_slice(&SHF_ALLOC.to_le_bytes());
+  data[entry + 32..entry + 40].copy_from_slice(&(u64::MAX - elf.section_headers[index].address).to_le_bytes());
+  let elf = Elf::new(data.into_boxed_slice());
+  assert!(elf.cpp_classes().is_empty());
+}
+
+#[test]
+fn demangling_gives_up_on_deep_or_huge_types() {
+  assert_eq!(demangle_type("PKi").as_deref(), Some("int const*"));
+  assert_eq!(demangle_type(&format!("{}i", "P".repeat(100_000))), None);
+  assert_eq!(demangle_type("S999999999999999999999999_"), None);
+  assert_eq!(demangle_type("99999999999999999999a"), None);
+  //each template argument repeats the one before
+  let doubling = format!("1aI{}E", (0..40).map(|index| if index == 0 { "i".to_string() } else { format!("S{}_", index - 1) }).collect::<String>());
+  assert!(demangle_type(&doubling).is_none_or(|name| name.len() <= 4096));
+}
+
+//tests/data/classes.so: Square derives from the abstract Shape and from Named, Node
+//virtually from Named
+fn class(classes: &[CppClass], name: &str) -> CppClass {
+  classes.iter().find(|class| class.typeinfo.name == name).unwrap().clone()
+}
+
+fn slots(table: &VirtualTable) -> Vec<Option<&str>> {
+  table.functions.iter().map(|function| function.symbol.as_deref()).collect()
+}
+
+#[test]
+fn hierarchies_and_vtables_of_a_built_library() {
+  let classes = Elf::new(include_bytes!("data/classes.so").to_vec().into_boxed_slice()).cpp_classes();
+  assert_eq!(classes.len(), 4);
+  let (shape, named, square, node) = (class(&classes, "Shape"), class(&classes, "Named"), class(&classes, "Square"), class(&classes, "Node"));
+  assert_eq!((shape.typeinfo.kind, named.typeinfo.mangled_name.as_str()), (ClassKind::Class, "5Named"));
+  //the destructors of an abstract class are left null
+  assert_eq!(slots(&shape.vtables[0]), [None, None, Some("__cxa_pure_virtual")]);
+
+  let bases: Vec<_> = square.typeinfo.bases.iter().map(|base| (base.name.as_str(), base.typeinfo, base.offset, base.is_virtual)).collect();
+  assert_eq!(bases, [("Shape", Some(shape.typeinfo.address), 0, false), ("Named", Some(named.typeinfo.address), 8, false)]);
+  assert_eq!(square.vtables.len(), 2);
+  assert_eq!(slots(&square.vtables[0]), [Some("_ZN6SquareD1Ev"), Some("_ZN6SquareD0Ev"), Some("_ZNK6Square4areaEv"), Some("_ZNK6Square4nameEv")]);
+  //Named's vtable in Square, reached through a thunk
+  let secondary = &square.vtables[1];
+  assert_eq!((secondary.offset_to_top, secondary.typeinfo, secondary.symbol.as_deref()), (-8, square.typeinfo.address, Some("_ZTV6Square")));
+  assert_eq!(slots(secondary), [Some("_ZThn8_NK6Square4nameEv")]);
+  assert_eq!(secondary.address_point, secondary.address + 16);
+
+  //a virtual base is placed through the vbase offset 24 bytes before the vtable's address point
+  let base = &node.typeinfo.bases[0];
+  assert_eq!((base.name.as_str(), base.offset, base.is_virtual), ("Named", -24, true));
+  assert!(node.vtables[0].functions.is_empty());
+  assert_eq!(slots(&node.vtables[1]), [Some("_ZNK5Named4nameEv")]);
+  assert_eq!(node.vtables[1].functions[0].address, named.vtables[0].functions[0].address);
+}
@@ -0,0 +1,13 @@
+use elf::*;
+
+//kSystemZ_ShadowOffset64 and the kX86_64/kAArch64 offsets of asan_mapping.h
+#[test]
+fn shadow_offsets() {
+  let offset = |machine, class| ShadowLayout::for_machine(machine, class).unwrap().offset;
+  assert_eq!(offset(EM_X86_64, 2), 0x7fff_8000);
+  assert_eq!(offset(EM_AARCH64, 2), 1 << 36);
+  assert_eq!(offset(EM_S390, 2), 1 << 52);
+  let layout = ShadowLayout::for_machine(EM_S390, 2).unwrap();
+  assert_eq!(layout.shadow_address(0), 1 << 52);
+  assert!(!layout.is_shadow(0x1000));
+}
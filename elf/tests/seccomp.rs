@@ -0,0 +1,38 @@
+use elf::*;
+
+fn inventory(machine: u16, indirect: bool) -> SyscallInventory {
+  SyscallInventory {
+    machine,
+    class: ELFCLASS64,
+    uses: vec![
+      SyscallUse { site: SyscallSite::Instruction(0x1000), number: Some(1), name: syscall_name(machine, ELFCLASS64, 1) },
+      SyscallUse { site: SyscallSite::Instruction(0x1010), number: None, name: None },
+    ],
+    indirect,
+  }
+}
+
+fn policy(inventory: &SyscallInventory, format: SeccompFormat) -> std::io::Result<String> {
+  let mut output = Vec::new();
+  inventory.write_seccomp_policy(format, &mut output)?;
+  Ok(String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn json_profile_notes_what_it_misses() {
+  let json = policy(&inventory(EM_X86_64, true), SeccompFormat::Json).unwrap();
+  assert!(json.contains("\"architectures\": [\"SCMP_ARCH_X86_64\"]"));
+  assert!(json.contains("\"write\""));
+  assert!(json.contains("\"comment\": \"draft from static analysis, the binary calls syscall(2) with numbers only known at runtime; syscall sites with unknown numbers: 0x1010\""));
+  let libseccomp = policy(&inventory(EM_X86_64, false), SeccompFormat::Libseccomp).unwrap();
+  assert!(libseccomp.contains("/* syscall sites with unknown numbers: 0x1010 */"));
+  assert!(libseccomp.contains("SCMP_SYS(write)"));
+}
+
+#[test]
+fn unsupported_machines_are_an_error() {
+  //ARM has a seccomp architecture but no syscall table, the profile would deny everything
+  let error = policy(&inventory(EM_ARM, false), SeccompFormat::Json).unwrap_err();
+  assert_eq!(error.kind(), std::io::ErrorKind::Unsupported);
+  assert!(policy(&inventory(EM_SPARCV9, false), SeccompFormat::Libseccomp).is_err());
+}
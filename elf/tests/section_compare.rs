@@ -0,0 +1,15 @@
+use elf::*;
+
+fn eh() -> Elf {
+  Elf::open(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/eh.so")).unwrap()
+}
+
+//the dynamic relocations of a linked file go by the address they patch
+#[test]
+fn dynamic_relocations_are_masked_in_their_sections() {
+  let elf = eh();
+  let comparison = elf.compare_sections_all(&eh());
+  assert!(comparison.is_equivalent());
+  let masked: Vec<&str> = comparison.sections.iter().filter(|section| section.masked_bytes > 0).map(|section| section.name.as_str()).collect();
+  assert!(masked.contains(&".got") || masked.contains(&".got.plt"), "{:?}", masked);
+}
@@ -0,0 +1,45 @@
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice())
+}
+
+fn content<'a>(elf: &'a Elf, name: &str) -> SectionContent<'a> {
+  elf.section_content(elf.section_index_by_name(name).unwrap()).unwrap()
+}
+
+//.init_array of tests/data/eh.so is zero in the file, an R_X86_64_RELATIVE fills it with 0x790
+#[test]
+fn function_pointers_take_relative_addends() {
+  let mut elf = library();
+  assert!(matches!(content(&elf, ".init_array"), SectionContent::FunctionPointers(pointers) if pointers == [0x790]));
+  elf.rebase(0x10_0000).unwrap();
+  assert!(matches!(content(&elf, ".init_array"), SectionContent::FunctionPointers(pointers) if pointers == [0x10_0790]));
+  assert!(matches!(content(&elf, ".got"), SectionContent::Pointers(pointers) if pointers == [0; 4]));
+}
+
+#[test]
+fn eh_frame_header_table() {
+  let elf = library();
+  let header = match content(&elf, ".eh_frame_hdr") {
+    SectionContent::EhFrameHeader(header) => header,
+    _ => panic!("not decoded"),
+  };
+  assert_eq!((header.version, header.eh_frame, header.fde_count), (1, Some(0x860), 5));
+  assert_eq!(header.table, [(0x670, 0x878), (0x6d0, 0x8a0), (0x799, 0x8d8), (0x7c4, 0x8f8), (0x7eb, 0x918)]);
+  assert_eq!(header.lookup(0x7a0), Some(0x8d8));
+  assert_eq!(header.lookup(0x66f), None);
+}
+
+#[test]
+fn other_sections() {
+  let elf = library();
+  assert!(matches!(elf.section_content(0), Some(SectionContent::Empty)));
+  assert!(elf.section_content(elf.section_headers.len()).is_none());
+  match content(&elf, ".dynstr") {
+    SectionContent::Strings(strings) => assert!(strings.contains(&(0, &b""[..])) && strings.iter().any(|&(_, string)| string == b"__cxa_finalize")),
+    _ => panic!("not strings"),
+  }
+  assert!(matches!(content(&elf, ".rela.plt"), SectionContent::Relocations(relocations) if relocations.len() == 5));
+  assert!(matches!(content(&elf, ".dynamic"), SectionContent::Dynamic(entries) if !entries.is_empty()));
+}
@@ -0,0 +1,62 @@
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice())
+}
+
+//the first byte of the section, None for empty ones
+struct FirstByte;
+
+impl SectionDecoder for FirstByte {
+  fn decode(&self, _: &Elf, _: &SectionHeader, data: &[u8]) -> Option<DecodedValue> {
+    Some(DecodedValue::Fields(vec![("first".to_string(), DecodedValue::Unsigned(*data.first()? as u64))]))
+  }
+}
+
+struct Label(&'static str);
+
+impl SectionDecoder for Label {
+  fn decode(&self, _: &Elf, _: &SectionHeader, _: &[u8]) -> Option<DecodedValue> {
+    Some(DecodedValue::String(self.0.to_string()))
+  }
+}
+
+fn decoders() -> SectionDecoders {
+  let mut decoders = SectionDecoders::new();
+  decoders.register(SectionKey::Type(SHT_PROGBITS), FirstByte);
+  decoders.register(SectionKey::Name(".eh_frame_hdr".to_string()), Label("header \"v1\""));
+  decoders
+}
+
+#[test]
+fn name_keys_win_over_type_keys() {
+  let elf = library();
+  let decoders = decoders();
+  let content = |name: &str| elf.section_content_with(elf.section_index_by_name(name).unwrap(), &decoders).unwrap();
+  assert!(matches!(content(".eh_frame_hdr"), SectionContent::Custom(DecodedValue::String(label)) if label == "header \"v1\""));
+  //.eh_frame_hdr starts with version 1
+  let eh_frame_hdr = elf.section_index_by_name(".eh_frame_hdr").unwrap();
+  assert_eq!(FirstByte.decode(&elf, &elf.section_headers[eh_frame_hdr], elf.section_data(&elf.section_headers[eh_frame_hdr])), Some(DecodedValue::Fields(vec![("first".to_string(), DecodedValue::Unsigned(1))])));
+  assert!(matches!(content(".got"), SectionContent::Custom(DecodedValue::Fields(_))));
+  //other types keep the built in decoding
+  assert!(matches!(content(".dynamic"), SectionContent::Dynamic(_)));
+}
+
+#[test]
+fn dumps() {
+  let elf = library();
+  let mut text = Vec::new();
+  elf.write_section_dump(&decoders(), DumpFormat::Text, &mut text).unwrap();
+  let text = String::from_utf8(text).unwrap();
+  assert!(text.lines().any(|line| line == "[14] .eh_frame_hdr type 0x1 address 0x828 size 0x34: \"header \\\"v1\\\"\""));
+  assert!(text.lines().any(|line| line == "  first: 0x0"));
+  assert!(text.contains("dynamic entries"));
+
+  let mut json = Vec::new();
+  elf.write_section_dump(&decoders(), DumpFormat::Json, &mut json).unwrap();
+  let json = String::from_utf8(json).unwrap();
+  assert!(json.starts_with("[{\"index\":0,\"name\":\"\",\"type\":0,"));
+  assert!(json.contains("\"name\":\".eh_frame_hdr\",\"type\":1,\"address\":2088,\"size\":52,\"decoded\":\"header \\\"v1\\\"\"}"));
+  assert!(json.contains("\"decoded\":{\"first\":"));
+  assert!(json.trim_end().ends_with("}]"));
+}
@@ -0,0 +1,43 @@
+#![cfg(any(feature = "sha2", feature = "blake3"))]
+
+use elf::*;
+
+fn executable() -> Elf {
+  Elf::new(fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_EXEC, machine: EM_X86_64 }).into_boxed_slice())
+}
+
+fn hex(bytes: &[u8]) -> String {
+  bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn sha256_digests_sections_and_loads() {
+  let elf = executable();
+  let digests = elf.section_digests(DigestAlgorithm::Sha256);
+  assert_eq!(digests.len(), elf.section_headers.len() + elf.program_headers.iter().filter(|ph| ph.entry_type == PT_LOAD).count());
+  //the null section hashes no bytes
+  assert_eq!(hex(&digests[0].digest), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+  assert!(digest_changes(&digests, &elf.section_digests(DigestAlgorithm::Sha256)).is_empty());
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn changed_sections_are_reported_by_name() {
+  let elf = executable();
+  let before = elf.section_digests(DigestAlgorithm::Sha256);
+  let text = elf.section_by_name(".symtab").unwrap().offset as usize;
+  let mut data = elf.data.to_vec();
+  data[text + 1] ^= 1;
+  let after = Elf::new(data.into_boxed_slice()).section_digests(DigestAlgorithm::Sha256);
+  let changes = digest_changes(&before, &after);
+  assert_eq!(changes.len(), 1);
+  assert_eq!((changes[0].name.as_str(), changes[0].kind), (".symtab", DigestChangeKind::Changed));
+}
+
+#[cfg(feature = "blake3")]
+#[test]
+fn blake3_digests() {
+  let digests = executable().section_digests(DigestAlgorithm::Blake3);
+  assert_eq!(hex(&digests[0].digest), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+}
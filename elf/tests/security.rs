@@ -0,0 +1,33 @@
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/fortify.so").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn fortified_and_unprotected_imports() {
+  let report = library().fortify_report();
+  assert_eq!(report.fortified, ["FD_SET", "memcpy"]);
+  assert_eq!(report.unprotected, ["strcpy"]);
+}
+
+#[test]
+fn fdelt_is_not_a_function() {
+  assert!(!FORTIFIABLE_FUNCTIONS.contains(&"fdelt"));
+  let mut elf = library();
+  //a dynamic symbol renamed to the unprefixed helper is not an unprotected import
+  let offset = elf.data.windows(12).position(|window| window == b"__fdelt_chk\0").unwrap();
+  elf.data[offset..offset + 11].copy_from_slice(b"fdelt\0_chk\0");
+  assert!(elf.imports().iter().any(|symbol| &*symbol.name == "fdelt"));
+  let report = elf.fortify_report();
+  assert_eq!((report.fortified, report.unprotected), (vec!["memcpy".to_string()], vec!["strcpy".to_string()]));
+}
+
+#[test]
+fn security_report_of_a_library() {
+  let report = library().security_report();
+  assert!(!report.pie);
+  assert!(report.nx);
+  assert!(report.relro == Relro::Full);
+  assert!(report.stack_canary);
+}
@@ -0,0 +1,35 @@
+use elf::*;
+
+//an x86-64 SFrame v2 section with one PCMASK function of `size` bytes at 0x1000 repeating a
+//single `sp+8` row every `repetition` bytes, the return address at the fixed CFA-8
+fn pcmask(size: u32, repetition: u8) -> Vec<u8> {
+  let mut data = Vec::new();
+  data.extend_from_slice(&0xdee2u16.to_le_bytes());
+  data.extend_from_slice(&[2, 0, SFRAME_ABI_AMD64_ENDIAN_LITTLE, 0, -8i8 as u8, 0]);
+  for field in &[1u32, 1, 3, 0, 20] {
+    data.extend_from_slice(&field.to_le_bytes());
+  }
+  data.extend_from_slice(&0x1000i32.to_le_bytes());
+  data.extend_from_slice(&size.to_le_bytes());
+  data.extend_from_slice(&0u32.to_le_bytes());
+  data.extend_from_slice(&1u32.to_le_bytes());
+  data.extend_from_slice(&[0x10, repetition, 0, 0]);
+  data.extend_from_slice(&[0, 0x03, 8]);
+  data
+}
+
+#[test]
+fn pcmask_rows_repeat_across_the_function() {
+  let data = pcmask(64, 16);
+  let frames = SFrame { data: &data, address: 0, big_endian: false }.frame_descriptions();
+  let addresses: Vec<u64> = frames[0].rows.iter().map(|row| row.address).collect();
+  assert_eq!(addresses, [0x1000, 0x1010, 0x1020, 0x1030]);
+}
+
+#[test]
+fn pcmask_expansion_is_capped() {
+  let data = pcmask(u32::MAX, 1);
+  let frames = SFrame { data: &data, address: 0, big_endian: false }.frame_descriptions();
+  assert_eq!(frames[0].size, u32::MAX as u64);
+  assert_eq!(frames[0].rows.len(), 1 << 16);
+}
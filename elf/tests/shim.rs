@@ -0,0 +1,105 @@
+#![cfg(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64")))]
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use elf::*;
+
+const CALLER: &str = "int answer(void);\nint main(void) { return answer() == 42 ? 0 : 1; }\n";
+
+struct Directory(PathBuf);
+
+impl Drop for Directory {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_dir_all(&self.0);
+  }
+}
+
+fn directory(name: &str) -> Directory {
+  let path = std::env::temp_dir().join(format!("walker-shim-{}-{}", name, std::process::id()));
+  std::fs::create_dir_all(&path).unwrap();
+  Directory(path)
+}
+
+//gcc in `directory`, None when it is not installed
+fn gcc(directory: &Path, args: &[&str]) -> Option<bool> {
+  match Command::new("gcc").current_dir(directory).args(args).output() {
+    Ok(output) => Some(output.status.success()),
+    Err(error) if error.kind() == ErrorKind::NotFound => None,
+    Err(error) => panic!("gcc: {}", error),
+  }
+}
+
+//lib<name>.so defining answer() as `value`, versioned by `script` when given
+fn library(directory: &Path, name: &str, value: i32, script: Option<&str>) -> Option<Elf> {
+  let source = format!("{}.c", name);
+  std::fs::write(directory.join(&source), format!("int answer(void) {{ return {}; }}\nint other(void) {{ return 7; }}\n", value)).unwrap();
+  let soname = format!("-Wl,-soname,lib{}.so", name);
+  let output = format!("lib{}.so", name);
+  let mut args = vec!["-shared", "-fPIC", "-o", &output, &soname, &source];
+  let version_script = script.map(|script| {
+    std::fs::write(directory.join("version.map"), script).unwrap();
+    "-Wl,--version-script=version.map"
+  });
+  args.extend(version_script);
+  assert!(gcc(directory, &args)?);
+  Some(Elf::open(directory.join(output)).unwrap())
+}
+
+fn strict(data: Vec<u8>) -> Elf {
+  let (elf, unknown) = Elf::parse(data.into_boxed_slice(), &ParseOptions { unknown_values: UnknownValuePolicy::Error }).unwrap();
+  assert!(unknown.is_empty());
+  elf
+}
+
+//the runpath of a program does not apply to what the shim needs
+fn runs(program: &Path) -> bool {
+  Command::new(program).env("LD_LIBRARY_PATH", program.parent().unwrap()).status().unwrap().success()
+}
+
+#[test]
+fn programs_link_against_a_shim_of_a_versioned_library() {
+  let directory = directory("versioned");
+  let target = match library(&directory.0, "target", 42, Some("TARGET_1 { global: answer; other; local: *; };")) {
+    Some(target) => target,
+    None => return,
+  };
+  let shim = target.build_shim("libshim.so", "SHIM_1", &["answer"]).unwrap();
+  let elf = strict(shim.clone());
+  assert_eq!(elf.soname().as_deref(), Some("libshim.so"));
+  let plt = elf.plt_entries();
+  assert_eq!((plt.len(), &*plt[0].name), (1, "answer"));
+  assert_eq!(elf.dynamic_value(DT_FLAGS), Some(DF_BIND_NOW));
+  std::fs::write(directory.0.join("libshim.so"), shim).unwrap();
+
+  std::fs::write(directory.0.join("main.c"), CALLER).unwrap();
+  let rpath = format!("-Wl,-rpath,{}", directory.0.display());
+  assert_eq!(gcc(&directory.0, &["-o", "main", "main.c", "-L.", "-lshim", &rpath]), Some(true));
+  let main = Elf::open(directory.0.join("main")).unwrap();
+  let needed = main.needed_libraries();
+  assert!(needed.iter().any(|library| library == "libshim.so") && !needed.iter().any(|library| library == "libtarget.so"));
+  assert!(runs(&directory.0.join("main")));
+}
+
+//answer() is not versioned, so the shim only replaces a library programs were linked against
+#[test]
+fn shim_of_an_unversioned_library_is_a_drop_in() {
+  let directory = directory("unversioned");
+  let new = match (library(&directory.0, "new", 42, None), library(&directory.0, "old", 1, None)) {
+    (Some(new), Some(_)) => new,
+    _ => return,
+  };
+  std::fs::write(directory.0.join("main.c"), CALLER).unwrap();
+  let rpath = format!("-Wl,-rpath,{}", directory.0.display());
+  assert_eq!(gcc(&directory.0, &["-o", "main", "main.c", "-L.", "-lold", &rpath]), Some(true));
+  assert!(!runs(&directory.0.join("main")));
+
+  let shim = new.build_shim("libold.so", "SHIM_1", &["answer"]).unwrap();
+  let elf = strict(shim.clone());
+  let versions = elf.symbol_versions();
+  let answer = elf.dynamic_symbols().iter().position(|symbol| *symbol.name == *"answer" && !symbol.is_undefined()).unwrap();
+  assert!(versions[answer].as_ref().unwrap().hidden);
+  std::fs::write(directory.0.join("libold.so"), shim).unwrap();
+  assert!(runs(&directory.0.join("main")));
+  //the definition is hidden from the linker too
+  assert_eq!(gcc(&directory.0, &["-o", "relinked", "main.c", "-L.", "-lold", &rpath]), Some(false));
+}
@@ -0,0 +1,124 @@
+#![cfg(feature = "sha2")]
+
+use std::convert::TryInto;
+use elf::*;
+
+fn executable() -> Elf {
+  Elf::new(fixture(FixtureSpec { class: ELFCLASS64, endianness: ELFDATA2LSB, obj_type: ET_EXEC, machine: EM_X86_64 }).into_boxed_slice())
+}
+
+#[test]
+fn signed_files_verify_with_the_key() {
+  let key = SigningKey::from_seed([7; 32]);
+  let mut elf = executable();
+  assert!(elf.signature().is_none());
+  elf.sign(&key).unwrap();
+  let signature = elf.signature().unwrap();
+  assert_eq!(signature.public_key, key.public_key());
+  elf.verify_signature(&key.public_key()).unwrap();
+  let reparsed = Elf::new(elf.data.to_vec().into_boxed_slice());
+  reparsed.verify_signature(&key.public_key()).unwrap();
+}
+
+#[test]
+fn other_keys_and_changes_are_rejected() {
+  let key = SigningKey::from_seed([7; 32]);
+  let mut elf = executable();
+  elf.sign(&key).unwrap();
+  assert!(elf.verify_signature(&SigningKey::from_seed([8; 32]).public_key()).is_err());
+  let text = elf.section_by_name(".text").unwrap().offset as usize;
+  let mut data = elf.data.to_vec();
+  data[text] ^= 1;
+  assert!(Elf::new(data.into_boxed_slice()).verify_signature(&key.public_key()).is_err());
+}
+
+//tests/data/snippet.o has no segments, its relocations and symbols are signed with its code
+#[test]
+fn relocations_and_symbols_of_objects_are_signed() {
+  let key = SigningKey::from_seed([7; 32]);
+  let mut elf = Elf::new(include_bytes!("data/snippet.o").to_vec().into_boxed_slice());
+  elf.sign(&key).unwrap();
+  elf.verify_signature(&key.public_key()).unwrap();
+  for name in [".rela.text", ".symtab", ".strtab"] {
+    let section = elf.section_by_name(name).unwrap();
+    //r_addend of the first relocation, st_value of the first defined symbol, a name
+    let offset = section.offset as usize + if name == ".strtab" { 1 } else { section.entry_size as usize + 8 };
+    let mut data = elf.data.to_vec();
+    data[offset] ^= 1;
+    assert!(Elf::new(data.into_boxed_slice()).verify_signature(&key.public_key()).is_err(), "{}", name);
+  }
+  //re-signing keeps one signature section and verifies again
+  let sections = elf.section_headers.len();
+  elf.sign(&key).unwrap();
+  assert_eq!(elf.section_headers.len(), sections);
+  Elf::new(elf.data.clone()).verify_signature(&key.public_key()).unwrap();
+}
+
+fn decode(hex: &str) -> Vec<u8> {
+  (0..hex.len()).step_by(2).map(|index| u8::from_str_radix(&hex[index..index + 2], 16).unwrap()).collect()
+}
+
+//RFC 8032 section 7.1: TEST 1, 2, 3 and SHA(abc), as secret key, public key, message, signature
+const RFC_8032: [(&str, &str, &str, &str); 4] = [
+  (
+    "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+    "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+    "",
+    "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b",
+  ),
+  (
+    "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+    "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+    "72",
+    "92a009a9f0d4cab8720e820b5f642540a2b27b5416503f8fb3762223ebdb69da085ac1e43e15996e458f3613d0f11d8c387b2eaeb4302aeeb00d291612bb0c00",
+  ),
+  (
+    "c5aa8df43f9f837bedb7442f31dcb7b166d38535076f094b85ce3a2e0b4458f7",
+    "fc51cd8e6218a1a38da47ed00230f0580816ed13ba3303ac5deb911548908025",
+    "af82",
+    "6291d657deec24024827e69c3abe01a30ce548a284743a445e3680d7db5ac3ac18ff9b538d16f290ae67f760984dc6594a7c15e9716ed28dc027beceea1ec40a",
+  ),
+  (
+    "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+    "ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf",
+    "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+    "dc2a4459e7369633a52b1bf277839a00201009a3efbf3ecb69bea2186c26b58909351fc9ac90b3ecfdfbc7c66431e0303dca179c138ac17ad9bef1177331a704",
+  ),
+];
+
+#[test]
+fn rfc_8032_vectors() {
+  for (secret, public, message, signature) in RFC_8032 {
+    let key = SigningKey::from_seed(decode(secret).try_into().unwrap());
+    let (message, signature): (Vec<u8>, [u8; 64]) = (decode(message), decode(signature).try_into().unwrap());
+    assert_eq!(key.public_key()[..], decode(public));
+    assert_eq!(key.sign_message(&message), signature);
+    assert!(verify_message(&key.public_key(), &message, &signature));
+  }
+}
+
+#[test]
+fn tampered_messages_and_signatures_are_rejected() {
+  let (secret, _, message, signature) = RFC_8032[2];
+  let key = SigningKey::from_seed(decode(secret).try_into().unwrap());
+  let signature: [u8; 64] = decode(signature).try_into().unwrap();
+  let mut message = decode(message);
+  message[1] ^= 1;
+  assert!(!verify_message(&key.public_key(), &message, &signature));
+  assert!(!verify_message(&key.public_key(), &message[..1], &signature));
+  message[1] ^= 1;
+  let mut tampered = signature;
+  tampered[40] ^= 1;
+  assert!(!verify_message(&key.public_key(), &message, &tampered));
+  //s + L verifies the same equation, it must still be rejected
+  let mut malleable = signature;
+  let l = decode("edd3f55c1a631258d69cf7a2def9de1400000000000000000000000000000010");
+  let mut carry = 0u16;
+  for (byte, l) in malleable[32..].iter_mut().zip(l) {
+    let sum = *byte as u16 + l as u16 + carry;
+    *byte = sum as u8;
+    carry = sum >> 8;
+  }
+  assert!(!verify_message(&key.public_key(), &message, &malleable));
+  assert!(verify_message(&key.public_key(), &message, &signature));
+}
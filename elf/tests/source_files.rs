@@ -0,0 +1,35 @@
+use elf::*;
+
+//tests/data/paths.o, compiled from build/ as ./../src/paths.c with -I../src/../include and
+//its comp_dir mapped to /work/build
+fn object() -> Elf {
+  Elf::new(include_bytes!("data/paths.o").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn paths_are_resolved_against_the_include_directories() {
+  let units = object().source_files();
+  assert_eq!(units.len(), 1);
+  let unit = &units[0];
+  assert_eq!((unit.name.as_deref(), unit.comp_dir.as_deref()), (Some("/work/src/paths.c"), Some("/work/build")));
+  assert!(unit.producer.as_deref().unwrap().starts_with("GNU C"));
+  let files: Vec<_> = unit.files.iter().map(|file| (file.index, file.name.as_str(), file.path.as_str())).collect();
+  //DWARF 5 lists the primary file as 0 and again as 2
+  assert_eq!(files, [(0, "paths.c", "/work/src/paths.c"), (1, "paths.h", "/work/include/paths.h"), (2, "paths.c", "/work/src/paths.c")]);
+  assert_eq!(object().source_paths(), ["/work/include/paths.h", "/work/src/paths.c"]);
+}
+
+#[test]
+fn no_debug_info_no_units() {
+  let elf = Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice());
+  assert!(elf.source_files().is_empty());
+}
+
+#[test]
+fn normalized_paths() {
+  assert_eq!(normalize_path("/a/./b/../c/"), "/a/c");
+  assert_eq!(normalize_path("/../a"), "/a");
+  assert_eq!(normalize_path("../a/../../b"), "../../b");
+  assert_eq!(normalize_path("a/.."), ".");
+  assert_eq!(normalize_path("a//b"), "a/b");
+}
@@ -0,0 +1,24 @@
+use elf::*;
+
+const EH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/eh.so");
+
+#[test]
+fn maps_are_read_back_from_the_cache() {
+  let directory = std::env::temp_dir().join(format!("walker-symbol-cache-{}", std::process::id()));
+  let cache = SymbolCache::new(&directory);
+  let map = cache.function_map(EH).unwrap();
+  assert_eq!(cache.function_map(EH).unwrap().to_bytes(), map.to_bytes());
+  assert_eq!(cache.clear().unwrap(), 1);
+  std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn map_is_returned_when_the_cache_cannot_be_written() {
+  let file = std::env::temp_dir().join(format!("walker-symbol-cache-file-{}", std::process::id()));
+  std::fs::write(&file, b"").unwrap();
+  //a directory below a regular file cannot be created
+  let cache = SymbolCache { directory: file.join("cache"), max_bytes: Some(0) };
+  let map = cache.function_map(EH);
+  std::fs::remove_file(&file).unwrap();
+  assert_eq!(map.unwrap().to_bytes(), Elf::open(EH).unwrap().function_map().to_bytes());
+}
@@ -0,0 +1,44 @@
+use elf::*;
+
+fn object(data: &[u8]) -> Elf {
+  Elf::new(data.to_vec().into_boxed_slice())
+}
+
+fn sites(elf: &Elf) -> Vec<(SyscallSite, Option<u64>, Option<&'static str>)> {
+  elf.syscall_inventory().uses.into_iter().map(|entry| (entry.site, entry.number, entry.name)).collect()
+}
+
+#[test]
+fn big_endian_aarch64_code_is_little_endian() {
+  let elf = object(include_bytes!("data/syscalls-aarch64.o"));
+  assert_eq!(elf.header.identification.endianness, ELFDATA2MSB);
+  assert_eq!(sites(&elf), [
+    (SyscallSite::Instruction(4), Some(64), Some("write")),
+    (SyscallSite::Instruction(12), Some(93), Some("exit")),
+  ]);
+}
+
+#[test]
+fn arm_be32_and_be8_code() {
+  let mut elf = object(include_bytes!("data/syscalls-arm.o"));
+  let expected = [(SyscallSite::Instruction(4), Some(4), None), (SyscallSite::Instruction(12), Some(248), None)];
+  assert_eq!(sites(&elf), expected);
+  //what a BE8 link makes of it: EF_ARM_BE8 and little-endian instructions
+  let flags = elf.header.description.flags | EF_ARM_BE8;
+  elf.data[0x24..0x28].copy_from_slice(&flags.to_be_bytes());
+  let text = elf.section_by_name(".text").unwrap().offset as usize;
+  for word in elf.data[text..text + 16].chunks_exact_mut(4) {
+    word.reverse();
+  }
+  let elf = object(&elf.data);
+  assert_eq!(sites(&elf), expected);
+}
+
+#[test]
+fn riscv_addi_and_compressed_li() {
+  let elf = object(include_bytes!("data/syscalls-riscv.o"));
+  assert_eq!(sites(&elf), [
+    (SyscallSite::Instruction(4), Some(64), Some("write")),
+    (SyscallSite::Instruction(10), Some(29), syscall_name(EM_RISCV, ELFCLASS64, 29)),
+  ]);
+}
@@ -0,0 +1,85 @@
+use elf::*;
+
+fn workspace(file_size: u64, memory_size: u64, align: u64) -> Workspace {
+  let mut elf = Elf::new(include_bytes!("data/fortify.so").to_vec().into_boxed_slice());
+  elf.program_headers.push(ProgramHeader {
+    entry_type: PT_TLS,
+    flags: 4,
+    offset: 0,
+    virtual_address: 0x40_1000,
+    physical_address: 0x40_1000,
+    file_size,
+    memory_size,
+    align,
+  });
+  let mut workspace = Workspace::new();
+  workspace.add_object("a.out", elf, 0);
+  workspace
+}
+
+#[test]
+fn variant_two_static_image() {
+  let workspace = workspace(4, 0x20, 16);
+  let layout = workspace.tls_layout().unwrap();
+  assert_eq!(layout.modules[0].thread_pointer_offset, Some(-0x20));
+  assert_eq!(layout.static_size, 0x20);
+  let (start, image) = layout.static_image(&workspace).unwrap();
+  assert_eq!((start, image.len()), (-0x20, 0x20));
+  assert_eq!(&image[..4], b"\x7fELF");
+}
+
+#[test]
+fn invalid_tls_segments_are_rejected() {
+  assert!(workspace(0x40, 0x20, 16).tls_layout().is_err());
+  assert!(workspace(0, 0x20, 24).tls_layout().is_err());
+  assert!(workspace(0, u64::MAX, 16).tls_layout().is_err());
+  assert!(workspace(0, i64::MAX as u64, 16).tls_layout().is_err());
+  assert!(Workspace::new().tls_layout().is_err());
+}
+
+fn models(machine: u16, relocations: &[u32]) -> Vec<Option<TlsAccessModel>> {
+  relocations.iter().map(|&relocation| tls_access_model(machine, relocation)).collect()
+}
+
+#[test]
+fn x86_64_access_models() {
+  use TlsAccessModel::*;
+  //TLSGD, TLSLD, DTPOFF32, GOTTPOFF, TPOFF32, GOTPC32_TLSDESC, PC32
+  assert_eq!(models(EM_X86_64, &[19, 20, 21, 22, 23, 34, 2]), [Some(GlobalDynamic), Some(LocalDynamic), Some(LocalDynamic), Some(InitialExec), Some(LocalExec), Some(Descriptor), None]);
+}
+
+#[test]
+fn i386_access_models() {
+  use TlsAccessModel::*;
+  //TLS_GD, TLS_LDM, TLS_IE, TLS_LE, TLS_GOTDESC, PC32
+  assert_eq!(models(EM_386, &[18, 19, 15, 17, 39, 2]), [Some(GlobalDynamic), Some(LocalDynamic), Some(InitialExec), Some(LocalExec), Some(Descriptor), None]);
+}
+
+#[test]
+fn arm_access_models() {
+  use TlsAccessModel::*;
+  //TLS_GD32, TLS_LDM32, TLS_IE32, TLS_LE32, TLS_GOTDESC, ABS32
+  assert_eq!(models(EM_ARM, &[104, 105, 107, 108, 90, 2]), [Some(GlobalDynamic), Some(LocalDynamic), Some(InitialExec), Some(LocalExec), Some(Descriptor), None]);
+}
+
+#[test]
+fn aarch64_access_models() {
+  use TlsAccessModel::*;
+  //TLSGD_ADR_PAGE21, TLSLD_ADR_PAGE21, TLSLD_LDST64_DTPREL_LO12_NC, TLSIE_MOVW_GOTTPREL_G1,
+  //TLSIE_LD_GOTTPREL_PREL19, TLSLE_MOVW_TPREL_G2, TLSLE_LDST64_TPREL_LO12_NC,
+  //TLSDESC_ADR_PAGE21, TLSLE_LDST128_TPREL_LO12, TLSLD_LDST128_DTPREL_LO12_NC, ABS64
+  assert_eq!(
+    models(EM_AARCH64, &[513, 518, 538, 539, 543, 544, 559, 562, 570, 573, 257]),
+    [
+      Some(GlobalDynamic), Some(LocalDynamic), Some(LocalDynamic), Some(InitialExec), Some(InitialExec), Some(LocalExec),
+      Some(LocalExec), Some(Descriptor), Some(LocalExec), Some(LocalDynamic), None,
+    ],
+  );
+}
+
+#[test]
+fn riscv_access_models() {
+  use TlsAccessModel::*;
+  //TLS_GD_HI20, TLS_GOT_HI20, TPREL_HI20, TLSDESC_HI20, TLSDESC_CALL, GOT_HI20, PLT32
+  assert_eq!(models(EM_RISCV, &[22, 21, 29, 62, 65, 20, 59]), [Some(GlobalDynamic), Some(InitialExec), Some(LocalExec), Some(Descriptor), Some(Descriptor), None, None]);
+}
@@ -0,0 +1,84 @@
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice())
+}
+
+fn names(elf: &Elf) -> Vec<&'static str> {
+  elf.detect_tricks().iter().map(AntiAnalysisTrick::name).collect()
+}
+
+#[test]
+fn a_linker_output_uses_no_tricks() {
+  assert!(names(&library()).is_empty());
+}
+
+#[test]
+fn header_tricks() {
+  let mut elf = library();
+  elf.header.description.section_hdr_entry_size = 0x48;
+  elf.header.description.section_hdr_str_index = 1;
+  let tricks = elf.detect_tricks();
+  assert!(tricks.iter().any(|trick| matches!(trick, AntiAnalysisTrick::HeaderSizeMismatch { field: "e_shentsize", value: 0x48, expected: 64 })));
+  assert!(tricks.iter().any(|trick| matches!(trick, AntiAnalysisTrick::BogusSectionNameTable { index: 1 })));
+  //the first PT_LOAD maps the start of the file, the section table at its end is outside of it
+  let mut elf = library();
+  elf.header.description.section_hdr_offset = 0x100;
+  assert!(names(&elf).contains(&"section-table-in-segment"));
+  elf.header.description.section_hdr_num = HUGE_SECTION_NUM;
+  assert!(names(&elf).contains(&"huge-section-num"));
+  assert!(names(&elf).contains(&"section-table-outside-file"));
+}
+
+//e_shnum 0 and the count in sh_size of entry 0, the table is as large as with e_shnum
+#[test]
+fn extended_section_count_keeps_the_table_size() {
+  let elf = library();
+  let (offset, count) = (elf.header.description.section_hdr_offset as usize, elf.section_headers.len());
+  let mut data = elf.data.to_vec();
+  data[offset + 32..offset + 40].copy_from_slice(&(count as u64).to_le_bytes());
+  //e_shnum
+  data[0x3c..0x3e].copy_from_slice(&0u16.to_le_bytes());
+  let mut elf = Elf::new(data.into_boxed_slice());
+  assert_eq!(elf.section_headers.len(), count);
+  assert!(names(&elf).is_empty());
+  elf.header.description.section_hdr_offset = 0x100;
+  assert!(names(&elf).contains(&"section-table-in-segment"));
+  elf.header.description.section_hdr_offset = elf.data.len() as u64 - 64;
+  assert!(names(&elf).contains(&"section-table-outside-file"));
+}
+
+#[test]
+fn stripped_section_table_with_dynamic_symbols() {
+  let mut elf = library();
+  elf.section_headers.clear();
+  elf.header.description.section_hdr_num = 0;
+  elf.header.description.section_hdr_str_index = 0;
+  assert_eq!(names(&elf), ["symbols-without-section-table"]);
+}
+
+#[test]
+fn lying_and_overlapping_sections() {
+  let mut elf = library();
+  let text = elf.section_headers.iter().position(|section| elf.section_name(section) == Some(".text")).unwrap();
+  let fini = elf.section_headers.iter().position(|section| elf.section_name(section) == Some(".fini")).unwrap();
+  elf.section_headers[text].offset += 0x10;
+  let tricks = elf.detect_tricks();
+  assert!(tricks.iter().any(|trick| matches!(trick, AntiAnalysisTrick::LyingSectionHeader { section_index, .. } if *section_index == text)));
+  let mut elf = library();
+  elf.section_headers[fini].offset = elf.section_headers[text].offset;
+  elf.section_headers[fini].flags = 0;
+  let tricks = elf.detect_tricks();
+  assert!(tricks.iter().any(|trick| matches!(trick, AntiAnalysisTrick::OverlappingSections { first_index, second_index } if (*first_index, *second_index) == (text, fini))));
+}
+
+#[test]
+fn segment_tricks() {
+  let mut elf = library();
+  elf.program_headers[1].virtual_address = 0x100;
+  assert_eq!(names(&elf), ["overlapping-segments"]);
+  //the writable PT_LOAD
+  let mut elf = library();
+  elf.header.description.entry = elf.program_headers[1].virtual_address;
+  assert_eq!(names(&elf), ["entry-outside-executable-segment"]);
+}
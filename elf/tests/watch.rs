@@ -0,0 +1,53 @@
+use std::fs;
+use std::thread;
+use std::time::Duration;
+use elf::*;
+
+fn library() -> Elf {
+  Elf::new(include_bytes!("data/eh.so").to_vec().into_boxed_slice())
+}
+
+#[test]
+fn duplicate_section_names_are_not_merged() {
+  let old = library();
+  let mut new = library();
+  let text = new.section_index_by_name(".text").unwrap();
+  let renamed = (1..new.section_headers.len()).find(|&index| index != text).unwrap();
+  let original = new.section_name(&new.section_headers[renamed]).unwrap().to_string();
+  new.section_headers[renamed].name_index = new.section_headers[text].name_index;
+  let diff = old.diff(&new);
+  assert_eq!(diff.sections_added, [".text"]);
+  assert_eq!(diff.sections_removed, [original]);
+  assert!(diff.symbols_added.is_empty() && diff.symbols_removed.is_empty());
+}
+
+#[test]
+fn watch_skips_files_that_do_not_parse() {
+  let path = std::env::temp_dir().join(format!("walker-watch-{}", std::process::id()));
+  let data = include_bytes!("data/eh.so").to_vec();
+  fs::write(&path, &data).unwrap();
+  let mut changed = data.clone();
+  let entry = library().header.description.entry;
+  changed[24..32].copy_from_slice(&(entry + 4).to_le_bytes());
+  let writer = {
+    let path = path.clone();
+    thread::spawn(move || {
+      thread::sleep(Duration::from_millis(100));
+      //a linker half way through writing the file
+      fs::write(&path, b"\x7fEL").unwrap();
+      thread::sleep(Duration::from_millis(200));
+      fs::write(&path, &changed).unwrap();
+    })
+  };
+  let mut diffs = Vec::new();
+  Elf::watch_with_interval(&path, Duration::from_millis(20), |elf, diff| {
+    diffs.push((elf.header.description.entry, diff.clone()));
+    false
+  }).unwrap();
+  writer.join().unwrap();
+  fs::remove_file(&path).unwrap();
+  assert_eq!(diffs.len(), 1);
+  assert_eq!(diffs[0].0, entry + 4);
+  assert_eq!(diffs[0].1.entry, Some((entry, entry + 4)));
+  assert!(diffs[0].1.sections_changed.is_empty());
+}
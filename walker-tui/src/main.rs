@@ -0,0 +1,239 @@
+use std::env;
+use std::io;
+use std::process;
+use elf::*;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+
+//bytes shown in the hex view of a single item
+const HEX_LIMIT: u64 = 0x4000;
+
+#[derive(Clone, Copy, PartialEq)]
+enum View {
+  Sections,
+  Segments,
+  Symbols,
+}
+
+const VIEWS: [View; 3] = [View::Sections, View::Segments, View::Symbols];
+
+struct App {
+  elf: Elf,
+  symbols: Vec<Symbol>,
+  view: usize,
+  lists: [ListState; 3],
+  scroll: u16,
+  //(view, selection) the detail text was rendered for
+  detail_key: Option<(usize, usize)>,
+  detail: String,
+}
+
+impl App {
+  fn new(elf: Elf) -> App {
+    let mut symbols: Vec<Symbol> = elf.symbols().into_iter().chain(elf.dynamic_symbols())
+      .filter(|symbol| !symbol.name.is_empty())
+      .collect();
+    symbols.sort_by(|a, b| (a.value, &a.name).cmp(&(b.value, &b.name)));
+    let mut lists: [ListState; 3] = Default::default();
+    for list in lists.iter_mut() {
+      list.select(Some(0));
+    }
+    App { elf, symbols, view: 0, lists, scroll: 0, detail_key: None, detail: String::new() }
+  }
+
+  fn len(&self) -> usize {
+    match VIEWS[self.view] {
+      View::Sections => self.elf.section_headers.len(),
+      View::Segments => self.elf.program_headers.len(),
+      View::Symbols => self.symbols.len(),
+    }
+  }
+
+  fn selected(&self) -> usize {
+    self.lists[self.view].selected().unwrap_or(0)
+  }
+
+  fn select(&mut self, position: usize) {
+    let last = self.len().saturating_sub(1);
+    self.lists[self.view].select(Some(position.min(last)));
+    self.scroll = 0;
+  }
+
+  fn items(&self) -> Vec<ListItem<'static>> {
+    match VIEWS[self.view] {
+      View::Sections => self.elf.section_headers.iter().enumerate()
+        .map(|(index, section)| ListItem::new(format!("{:3} {}", index, self.elf.section_name(section).unwrap_or(""))))
+        .collect(),
+      View::Segments => self.elf.program_headers.iter().enumerate()
+        .map(|(index, segment)| ListItem::new(format!("{:3} {:#010x} {:#x}", index, segment.entry_type, segment.virtual_address)))
+        .collect(),
+      View::Symbols => self.symbols.iter()
+        .map(|symbol| ListItem::new(format!("{:#x} {}", symbol.value, symbol.name)))
+        .collect(),
+    }
+  }
+
+  fn section_of(&self, address: u64) -> Option<&str> {
+    self.elf.section_headers.get(self.elf.section_at_address(address)?).and_then(|section| self.elf.section_name(section))
+  }
+
+  fn hexdump(&self, offset: u64, size: u64) -> String {
+    if size == 0 {
+      return String::new();
+    }
+    let mut text = self.elf.annotated_hexdump(offset..offset.saturating_add(size.min(HEX_LIMIT)));
+    if size > HEX_LIMIT {
+      text.push_str(&format!("... {:#x} more bytes\n", size - HEX_LIMIT));
+    }
+    text
+  }
+
+  fn render_detail(&self, index: usize) -> String {
+    let mut text = String::new();
+    match VIEWS[self.view] {
+      View::Sections => {
+        let section = match self.elf.section_headers.get(index) {
+          Some(section) => section,
+          None => return text,
+        };
+        text.push_str(&format!("name    {}\ntype    {:#x}\nflags   {:#x}\naddress {:#x}\noffset  {:#x}\nsize    {:#x}\nlink    {}\ninfo    {}\nalign   {:#x}\nentsize {:#x}\n",
+          self.elf.section_name(section).unwrap_or(""), section.section_type, section.flags, section.address,
+          section.offset, section.size, section.link, section.info, section.align, section.entry_size));
+        //cross references: the segments mapping it and the symbols it defines
+        let segments: Vec<String> = self.elf.segments_of_section(index).iter().map(|position| position.to_string()).collect();
+        text.push_str(&format!("segments {}\n", segments.join(" ")));
+        let defined = self.symbols.iter().filter(|symbol| symbol.section_index as usize == index).count();
+        text.push_str(&format!("symbols  {}\n\n", defined));
+        if section.section_type != SHT_NOBITS {
+          text.push_str(&self.hexdump(section.offset, section.size));
+        }
+      },
+      View::Segments => {
+        let segment = match self.elf.program_headers.get(index) {
+          Some(segment) => segment,
+          None => return text,
+        };
+        text.push_str(&format!("type     {:#x}\nflags    {:#x}\noffset   {:#x}\nvaddr    {:#x}\npaddr    {:#x}\nfilesz   {:#x}\nmemsz    {:#x}\nalign    {:#x}\n",
+          segment.entry_type, segment.flags, segment.offset, segment.virtual_address, segment.physical_address,
+          segment.file_size, segment.memory_size, segment.align));
+        let sections: Vec<&str> = self.elf.sections_of_segment(index).into_iter()
+          .filter_map(|position| self.elf.section_name(&self.elf.section_headers[position]))
+          .collect();
+        text.push_str(&format!("sections {}\n\n", sections.join(" ")));
+        text.push_str(&self.hexdump(segment.offset, segment.file_size));
+      },
+      View::Symbols => {
+        let symbol = match self.symbols.get(index) {
+          Some(symbol) => symbol,
+          None => return text,
+        };
+        text.push_str(&format!("name       {}\nvalue      {:#x}\nsize       {:#x}\ntype       {}\nbinding    {}\nvisibility {}\nsection    {} {}\n",
+          symbol.name, symbol.value, symbol.size, symbol.symbol_type, symbol.binding, symbol.visibility,
+          symbol.section_index, self.section_of(symbol.value).unwrap_or("")));
+        let aliases: Vec<&str> = self.symbols.iter()
+          .filter(|other| other.value == symbol.value && other.name != symbol.name && !symbol.is_undefined())
+          .map(|other| &*other.name)
+          .collect();
+        if !aliases.is_empty() {
+          text.push_str(&format!("aliases    {}\n", aliases.join(" ")));
+        }
+        text.push_str("\nreferences\n");
+        for relocation in self.elf.symbol_references(symbol) {
+          let section = self.elf.section_headers.get(relocation.section_index).and_then(|section| self.elf.section_name(section)).unwrap_or("");
+          text.push_str(&format!("  {:#x} type {} in {} ({})\n", relocation.offset, relocation.relocation_type, self.section_of(relocation.offset).unwrap_or("?"), section));
+        }
+        text.push('\n');
+        if !symbol.is_undefined() {
+          if let Some(offset) = self.elf.section_offset_of(symbol.value) {
+            text.push_str(&self.hexdump(offset, symbol.size));
+          }
+        }
+      },
+    }
+    text
+  }
+
+  fn refresh_detail(&mut self) {
+    let key = (self.view, self.selected());
+    if self.detail_key != Some(key) {
+      self.detail = self.render_detail(key.1);
+      self.detail_key = Some(key);
+    }
+  }
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+  let [tabs, body, help] = Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+  let [left, right] = Layout::horizontal([Constraint::Percentage(30), Constraint::Min(0)]).areas(body);
+
+  let titles = ["Sections", "Segments", "Symbols"];
+  frame.render_widget(Tabs::new(titles).select(app.view).highlight_style(Style::new().add_modifier(Modifier::REVERSED)), tabs);
+
+  let list = List::new(app.items())
+    .block(Block::bordered().title(titles[app.view]))
+    .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+  frame.render_stateful_widget(list, left, &mut app.lists[app.view]);
+
+  let detail = Paragraph::new(app.detail.as_str())
+    .block(Block::bordered().title("Detail"))
+    .scroll((app.scroll, 0));
+  frame.render_widget(detail, right);
+
+  frame.render_widget(Paragraph::new("q quit  tab view  up/down select  pgup/pgdn home/end  j/k scroll detail"), help);
+}
+
+fn run(terminal: &mut DefaultTerminal, app: &mut App) -> io::Result<()> {
+  loop {
+    app.refresh_detail();
+    terminal.draw(|frame| draw(frame, app))?;
+    let key = match event::read()? {
+      Event::Key(key) if key.kind == KeyEventKind::Press => key,
+      _ => continue,
+    };
+    let selected = app.selected();
+    match key.code {
+      KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+      KeyCode::Tab => app.view = (app.view + 1) % VIEWS.len(),
+      KeyCode::BackTab => app.view = (app.view + VIEWS.len() - 1) % VIEWS.len(),
+      KeyCode::Down => app.select(selected + 1),
+      KeyCode::Up => app.select(selected.saturating_sub(1)),
+      KeyCode::PageDown => app.select(selected + 20),
+      KeyCode::PageUp => app.select(selected.saturating_sub(20)),
+      KeyCode::Home => app.select(0),
+      KeyCode::End => app.select(usize::MAX),
+      KeyCode::Char('j') => app.scroll = app.scroll.saturating_add(1),
+      KeyCode::Char('k') => app.scroll = app.scroll.saturating_sub(1),
+      KeyCode::Char('J') => app.scroll = app.scroll.saturating_add(20),
+      KeyCode::Char('K') => app.scroll = app.scroll.saturating_sub(20),
+      _ => {},
+    }
+  }
+}
+
+fn main() {
+  let path = match env::args().nth(1) {
+    Some(path) => path,
+    None => {
+      eprintln!("usage: walker-tui <file>");
+      process::exit(2);
+    },
+  };
+  let elf = match Elf::open(&path) {
+    Ok(elf) => elf,
+    Err(error) => {
+      eprintln!("{}: {}", path, error);
+      process::exit(1);
+    },
+  };
+  let mut app = App::new(elf);
+  let mut terminal = ratatui::init();
+  let result = run(&mut terminal, &mut app);
+  ratatui::restore();
+  if let Err(error) = result {
+    eprintln!("{}", error);
+    process::exit(1);
+  }
+}